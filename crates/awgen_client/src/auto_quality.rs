@@ -0,0 +1,312 @@
+//! Adapts the render quality to the performance of the machine, by lowering
+//! the render distance, meshing budget, and particle count while the frame
+//! rate stays below a target, and raising them again once there is headroom.
+
+
+use crate::prelude::{ParticleBudget, DEFAULT_MAX_PARTICLES};
+use awgen_world_mesh::prelude::ChunkRenderSettings;
+use bevy::prelude::*;
+
+
+/// The default frame rate below which the render quality is lowered.
+pub const DEFAULT_TARGET_FPS: f32 = 45.0;
+
+
+/// The default frame rate above which the render quality is raised again.
+pub const DEFAULT_RESTORE_FPS: f32 = 55.0;
+
+
+/// The number of seconds that the frame rate is averaged over by default.
+const DEFAULT_SAMPLE_SECONDS: f32 = 2.0;
+
+
+/// The number of times that the render quality may be lowered, each time by
+/// an equal fraction of the full quality.
+pub const MAX_QUALITY_REDUCTION: u32 = 3;
+
+
+/// The smallest render distance, in chunks, that the render quality is ever
+/// lowered to.
+const MIN_RENDER_DISTANCE: u32 = 2;
+
+
+/// A resource that monitors the frame rate, and lowers or raises the render
+/// quality to keep it above a target.
+///
+/// The average frame rate is measured over a short window. If it is below the
+/// target frame rate, the render quality is lowered by one step. If it is
+/// above the restore frame rate, the render quality is raised by one step,
+/// until the full quality is reached again.
+#[derive(Debug, Clone, Resource)]
+pub struct AutoQuality {
+    /// Whether or not the render quality is adapted at all. If disabled, the
+    /// render settings are left untouched.
+    pub enabled: bool,
+
+    /// The frame rate below which the render quality is lowered.
+    pub target_fps: f32,
+
+    /// The frame rate above which the render quality is raised. This should be
+    /// somewhat higher than the target frame rate, to avoid flip-flopping
+    /// between two quality levels, but not higher than the refresh rate of
+    /// the display if vsync is enabled.
+    pub restore_fps: f32,
+
+    /// The number of seconds that the frame rate is averaged over before the
+    /// render quality is changed.
+    pub sample_seconds: f32,
+
+    /// The render distance and meshing budget at full quality.
+    pub full_quality: ChunkRenderSettings,
+
+    /// The maximum number of live particles at full quality.
+    pub max_particles: usize,
+
+    /// The number of steps that the render quality is currently lowered by.
+    reduction: u32,
+
+    /// The number of frames within the current sample window.
+    frames: u32,
+
+    /// The number of seconds within the current sample window.
+    elapsed: f32,
+}
+
+impl AutoQuality {
+    /// Creates a new, enabled render quality monitor that starts at the given
+    /// full quality chunk render settings and particle budget.
+    pub fn new(full_quality: ChunkRenderSettings, max_particles: usize) -> Self {
+        Self {
+            enabled: true,
+            target_fps: DEFAULT_TARGET_FPS,
+            restore_fps: DEFAULT_RESTORE_FPS,
+            sample_seconds: DEFAULT_SAMPLE_SECONDS,
+            full_quality,
+            max_particles,
+            reduction: 0,
+            frames: 0,
+            elapsed: 0.0,
+        }
+    }
+
+
+    /// Sets the frame rate below which the render quality is lowered, and the
+    /// frame rate above which it is raised again.
+    pub fn with_target_fps(mut self, target_fps: f32, restore_fps: f32) -> Self {
+        self.target_fps = target_fps;
+        self.restore_fps = restore_fps;
+        self
+    }
+
+
+    /// Gets the number of steps that the render quality is currently lowered
+    /// by, between 0 and [`MAX_QUALITY_REDUCTION`].
+    pub fn reduction(&self) -> u32 {
+        self.reduction
+    }
+
+
+    /// Gets the current render quality, as a fraction of the full quality.
+    pub fn quality(&self) -> f32 {
+        1.0 - self.reduction as f32 / (MAX_QUALITY_REDUCTION + 1) as f32
+    }
+
+
+    /// Records a frame that took the given number of seconds, and lowers or
+    /// raises the render quality by one step if the sample window is complete.
+    ///
+    /// Returns true if the render quality was changed.
+    pub fn record_frame(&mut self, delta: f32) -> bool {
+        self.frames += 1;
+        self.elapsed += delta;
+        if self.elapsed < self.sample_seconds {
+            return false;
+        }
+
+        let fps = self.frames as f32 / self.elapsed;
+        self.frames = 0;
+        self.elapsed = 0.0;
+
+        if fps < self.target_fps && self.reduction < MAX_QUALITY_REDUCTION {
+            self.reduction += 1;
+            return true;
+        }
+
+        if fps > self.restore_fps && self.reduction > 0 {
+            self.reduction -= 1;
+            return true;
+        }
+
+        false
+    }
+
+
+    /// Applies the current render quality to the given chunk render settings
+    /// and particle budget.
+    pub fn apply(&self, chunks: &mut ChunkRenderSettings, particles: &mut ParticleBudget) {
+        let quality = self.quality();
+        let full = self.full_quality;
+        let render_distance = (full.render_distance as f32 * quality).round() as u32;
+
+        chunks.render_distance = render_distance.max(MIN_RENDER_DISTANCE.min(full.render_distance));
+        chunks.meshes_per_frame = ((full.meshes_per_frame as f32 * quality) as usize).max(1);
+        particles.max_particles = (self.max_particles as f32 * quality) as usize;
+    }
+}
+
+impl Default for AutoQuality {
+    fn default() -> Self {
+        Self::new(ChunkRenderSettings::default(), DEFAULT_MAX_PARTICLES)
+    }
+}
+
+
+/// Measures the frame rate, and lowers or raises the render distance, meshing
+/// budget, and particle budget when the render quality changes.
+pub fn tune_render_quality(
+    time: Res<Time>,
+    mut quality: ResMut<AutoQuality>,
+    mut chunks: ResMut<ChunkRenderSettings>,
+    mut particles: ResMut<ParticleBudget>,
+) {
+    if !quality.enabled || !quality.record_frame(time.delta_seconds()) {
+        return;
+    }
+
+    quality.apply(&mut chunks, &mut particles);
+    info!(
+        "Changed render quality to {:.0}% (render distance: {}, meshes per frame: {}, particles: {})",
+        quality.quality() * 100.0,
+        chunks.render_distance,
+        chunks.meshes_per_frame,
+        particles.max_particles
+    );
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates a render quality monitor with a full quality render distance of
+    /// 16 chunks, 8 meshes per frame, and 1000 particles.
+    fn auto_quality() -> AutoQuality {
+        let full_quality = ChunkRenderSettings {
+            render_distance:  16,
+            meshes_per_frame: 8,
+        };
+
+        AutoQuality::new(full_quality, 1000)
+    }
+
+
+    /// Records frames at the given frame rate until a full sample window has
+    /// passed, returning whether or not the render quality was changed.
+    fn sample(quality: &mut AutoQuality, fps: f32) -> bool {
+        let mut changed = quality.record_frame(1.0 / fps);
+        while quality.frames > 0 {
+            changed |= quality.record_frame(1.0 / fps);
+        }
+        changed
+    }
+
+
+    /// Applies the render quality to the default settings, returning the
+    /// render distance, meshes per frame, and particle count.
+    fn applied(quality: &AutoQuality) -> (u32, usize, usize) {
+        let mut chunks = ChunkRenderSettings::default();
+        let mut particles = ParticleBudget::default();
+        quality.apply(&mut chunks, &mut particles);
+        (
+            chunks.render_distance,
+            chunks.meshes_per_frame,
+            particles.max_particles,
+        )
+    }
+
+
+    #[test]
+    fn changes_once_per_window() {
+        let mut quality = auto_quality();
+        for _ in 0..63 {
+            assert!(!quality.record_frame(1.0 / 32.0));
+        }
+
+        assert!(quality.record_frame(1.0 / 32.0));
+        assert_eq!(quality.reduction(), 1);
+    }
+
+
+    #[test]
+    fn lower_quality_until_limit() {
+        let mut quality = auto_quality();
+        for step in 1..=MAX_QUALITY_REDUCTION {
+            assert!(sample(&mut quality, 32.0));
+            assert_eq!(quality.reduction(), step);
+        }
+
+        assert!(!sample(&mut quality, 32.0));
+        assert_eq!(quality.reduction(), MAX_QUALITY_REDUCTION);
+    }
+
+
+    #[test]
+    fn raise_quality_until_full() {
+        let mut quality = auto_quality();
+        quality.reduction = 2;
+
+        assert!(sample(&mut quality, 64.0));
+        assert_eq!(quality.reduction(), 1);
+        assert!(sample(&mut quality, 64.0));
+        assert_eq!(quality.reduction(), 0);
+
+        assert!(!sample(&mut quality, 64.0));
+        assert_eq!(quality.reduction(), 0);
+    }
+
+
+    #[test]
+    fn hold_quality_between_targets() {
+        let mut quality = auto_quality();
+        quality.reduction = 1;
+
+        for fps in [46.0, 50.0, 54.0] {
+            assert!(!sample(&mut quality, fps));
+            assert_eq!(quality.reduction(), 1);
+        }
+    }
+
+
+    #[test]
+    fn apply_quality() {
+        let mut quality = auto_quality();
+        assert_eq!(applied(&quality), (16, 8, 1000));
+
+        quality.reduction = 2;
+        assert_eq!(quality.quality(), 0.5);
+        assert_eq!(applied(&quality), (8, 4, 500));
+
+        quality.reduction = MAX_QUALITY_REDUCTION;
+        assert_eq!(quality.quality(), 0.25);
+        assert_eq!(applied(&quality), (4, 2, 250));
+    }
+
+
+    #[test]
+    fn apply_minimum_limits() {
+        let mut quality = AutoQuality::new(
+            ChunkRenderSettings {
+                render_distance:  4,
+                meshes_per_frame: 1,
+            },
+            10,
+        );
+        quality.reduction = MAX_QUALITY_REDUCTION;
+        assert_eq!(applied(&quality), (MIN_RENDER_DISTANCE, 1, 2));
+
+        quality.full_quality.render_distance = 1;
+        assert_eq!(applied(&quality).0, 1);
+    }
+}
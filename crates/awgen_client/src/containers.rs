@@ -0,0 +1,74 @@
+//! The container window, which displays the contents of the container that the
+//! local player has opened and allows items to be moved between its slots.
+
+
+use awgen_network::prelude::{send_container_action, ContainerAction, ContainerView, MessageBatch};
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align2};
+use bevy_egui::EguiContext;
+
+
+/// The number of slots shown within each row of the container window.
+const SLOTS_PER_ROW: usize = 9;
+
+
+/// Draws the container window while the local player has a container open.
+///
+/// Clicking a slot selects it, and clicking a second slot moves the selected
+/// item stack into that slot. The window is closed with its close button or
+/// by pressing the E key.
+pub fn show_container(
+    keyboard: Res<Input<KeyCode>>,
+    view: Res<ContainerView>,
+    mut selected: Local<Option<usize>>,
+    mut batch: ResMut<MessageBatch>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let Some(container) = view.get() else {
+        *selected = None;
+        return;
+    };
+
+    let mut open = !keyboard.just_pressed(KeyCode::E);
+    let mut moved = None;
+
+    egui::Window::new("Container")
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::Grid::new("container_slots").show(ui, |ui| {
+                for (slot, stack) in container.slots.iter().enumerate() {
+                    let text = match stack.is_empty() {
+                        true => String::new(),
+                        false => format!("{} x{}", stack.item, stack.count),
+                    };
+
+                    if ui.selectable_label(*selected == Some(slot), text).clicked() {
+                        match selected.take() {
+                            Some(from) => moved = Some((from, slot)),
+                            None => *selected = Some(slot),
+                        }
+                    }
+
+                    if (slot + 1) % SLOTS_PER_ROW == 0 {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+
+    if let Some((from, to)) = moved {
+        send_container_action(&mut batch, ContainerAction::Move {
+            from:     from as u16,
+            to:       to as u16,
+            revision: container.revision,
+        });
+    }
+
+    if !open {
+        *selected = None;
+        send_container_action(&mut batch, ContainerAction::Close);
+    }
+}
@@ -1,11 +1,15 @@
 //! The controller and user input handling components and systems.
 
 
-use awgen_physics::prelude::VelocitySource;
+use awgen_network::prelude::{
+    LookRotation, PendingInputActivity, PlayerRoster, PredictedMovement, SpectateView
+};
+use awgen_physics::prelude::{AppState, CameraRig, GameMode, Look, VelocitySource};
 use awgen_physics::time::PhysicsTickrate;
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::window::CursorGrabMode;
+use bevy_renet::renet::RenetClient;
 use std::f32::consts::PI;
 
 
@@ -50,7 +54,8 @@ impl Default for MouseController {
 
 
 /// A marker that indicates that the output of a mouse controller rotation
-/// should be applied to a camera's transform.
+/// should be applied to a camera's transform, through the camera rig of the
+/// entity.
 #[derive(Debug, Clone, Reflect, Component, Default)]
 #[reflect(Component)]
 pub struct CameraController {
@@ -61,15 +66,27 @@ pub struct CameraController {
 
 /// A system that is triggered every physics frame in order to update the
 /// velocity source of a WASD-controlled entity.
+///
+/// Vertical movement is only applied if the entity's game mode allows for
+/// flying. Entities without a game mode are always allowed to fly. No movement
+/// is applied while the local player is spectating another player.
 pub fn wasd_velocity_input(
     keyboard: Res<Input<KeyCode>>,
     tickrate: Res<PhysicsTickrate>,
-    mut query: Query<(&mut VelocitySource, &MouseController), With<WasdController>>,
+    spectate: Res<SpectateView>,
+    mut query: Query<
+        (&mut VelocitySource, &MouseController, Option<&GameMode>),
+        With<WasdController>,
+    >,
 ) {
-    for (mut source, controller) in query.iter_mut() {
+    for (mut source, controller, game_mode) in query.iter_mut() {
         let movement_speed = 2.5 * tickrate.delta();
 
         source.force = Vec3::ZERO;
+        if spectate.is_spectating() {
+            continue;
+        }
+
         let mut vert_speed = Vec3::ZERO;
 
         if keyboard.pressed(KeyCode::W) {
@@ -101,7 +118,8 @@ pub fn wasd_velocity_input(
             source.force = source.force.normalize() * movement_speed;
         }
 
-        if vert_speed.length_squared() > 0.0 {
+        let can_fly = game_mode.map_or(true, |mode| mode.can_fly());
+        if can_fly && vert_speed.length_squared() > 0.0 {
             source.force += vert_speed * movement_speed;
         }
     }
@@ -158,16 +176,159 @@ pub fn toggle_cursor(
 }
 
 
-/// Applies a rotation transformation to a camera based on the rotational value
-/// provided from a mouse controller.
-pub fn apply_camera_transform(
-    query: Query<(&MouseController, &CameraController)>,
-    mut camera_list: Query<&mut Transform>,
+/// Toggles the pause menu each time the escape key is pressed while in game.
+///
+/// The cursor is released when the game is paused, so that the player may
+/// interact with menus.
+pub fn toggle_pause(
+    input: Res<Input<KeyCode>>,
+    state: Option<ResMut<State<AppState>>>,
+    mut windows: ResMut<Windows>,
+    mut query: Query<&mut MouseController>,
+) {
+    let Some(mut state) = state else {
+        return;
+    };
+
+    if !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    match state.current() {
+        AppState::InGame => {
+            let _ = state.set(AppState::Paused);
+
+            let window = windows.get_primary_mut().unwrap();
+            window.set_cursor_grab_mode(CursorGrabMode::None);
+            window.set_cursor_visibility(true);
+
+            for mut controller in query.iter_mut() {
+                controller.locked = false;
+            }
+        },
+        AppState::Paused => {
+            let _ = state.set(AppState::InGame);
+        },
+        _ => {},
+    }
+}
+
+
+/// Applies the rotation of each mouse controller as the look rotation of its
+/// entity, and points the camera rig of the entity at the camera of its camera
+/// controller.
+///
+/// The look rotation is applied every render frame rather than interpolated,
+/// so that the camera follows the mouse without waiting for a physics frame.
+#[allow(clippy::type_complexity)]
+pub fn update_camera_look(
+    mut query: Query<(
+        Entity,
+        &MouseController,
+        &CameraController,
+        Option<&mut Look>,
+        Option<&CameraRig>,
+    )>,
+    mut commands: Commands,
 ) {
-    for (mouse, cam_target) in query.iter() {
-        if let Some(cam_entity) = cam_target.camera {
-            let mut transform = camera_list.get_mut(cam_entity).unwrap();
-            transform.rotation = mouse.quat();
+    for (entity, mouse, controller, look, rig) in query.iter_mut() {
+        let rotation = Look::new(mouse.angle.y, mouse.angle.x);
+        match look {
+            Some(mut look) if *look != rotation => *look = rotation,
+            Some(_) => {},
+            None => {
+                commands.entity(entity).insert(rotation);
+            },
+        }
+
+        if rig.map(|rig| rig.camera) != Some(controller.camera) {
+            commands.entity(entity).insert(CameraRig {
+                camera: controller.camera,
+            });
         }
     }
 }
+
+
+/// Applies the game mode of the local player, as reported by the server within
+/// the player roster, to all WASD-controlled entities.
+///
+/// While a recorded session is being played back, there is no local player
+/// within the roster, so the game mode is left unchanged.
+pub fn apply_local_game_mode(
+    client: Option<Res<RenetClient>>,
+    roster: Res<PlayerRoster>,
+    mut query: Query<(Entity, Option<&mut GameMode>), With<WasdController>>,
+    mut commands: Commands,
+) {
+    let Some(client) = client else {
+        return;
+    };
+
+    if !roster.is_changed() {
+        return;
+    }
+
+    let Some(entry) = roster.get(client.client_id()) else {
+        return;
+    };
+
+    for (entity, game_mode) in query.iter_mut() {
+        match game_mode {
+            Some(mut game_mode) if *game_mode != entry.game_mode => *game_mode = entry.game_mode,
+            Some(_) => {},
+            None => {
+                commands.entity(entity).insert(entry.game_mode);
+            },
+        }
+    }
+}
+
+
+/// Adds movement prediction to each WASD-controlled entity, so that its
+/// movement is applied immediately and reconciled with the server. The look
+/// rotation of the entity is also added, which the local player writes itself.
+pub fn insert_predicted_movement(
+    query: Query<Entity, (With<WasdController>, Without<PredictedMovement>)>,
+    mut commands: Commands,
+) {
+    for entity in query.iter() {
+        commands
+            .entity(entity)
+            .insert((PredictedMovement::default(), LookRotation::default()));
+    }
+}
+
+
+/// Copies the mouse rotation of the local player into its look rotation, which
+/// is then sent to the server and replicated to other players.
+pub fn update_look_rotation(mut query: Query<(&MouseController, &mut LookRotation)>) {
+    for (mouse, mut look) in query.iter_mut() {
+        let rotation = LookRotation {
+            yaw:   mouse.angle.y,
+            pitch: mouse.angle.x,
+        };
+
+        if *look != rotation {
+            *look = rotation;
+        }
+    }
+}
+
+
+/// Records whether or not the local player has provided any keyboard or mouse
+/// input, so that the server may tell when the player is idle.
+pub fn track_input_activity(
+    keyboard: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse: EventReader<MouseMotion>,
+    mut activity: ResMut<PendingInputActivity>,
+) {
+    let moved = mouse.iter().count() > 0;
+    let pressed = keyboard.get_pressed().next().is_some();
+    let clicked = mouse_buttons.get_pressed().next().is_some();
+
+    if moved || pressed || clicked {
+        activity.mark_active();
+    }
+}
@@ -0,0 +1,115 @@
+//! Shows the crack stage of each block that is being broken, whether by the
+//! local player or by other players within the same world, as a darkened
+//! overlay around the block.
+
+
+use crate::prelude::LocalBlockBreaking;
+use awgen_network::prelude::BlockCracks;
+use awgen_world::prelude::CRACK_STAGES;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The size of the crack overlay cube, which is slightly larger than a block
+/// so that it is drawn over the faces of the block.
+const CRACK_OVERLAY_SIZE: f32 = 1.004;
+
+
+/// The opacity of the crack overlay at the last crack stage.
+const MAX_CRACK_OPACITY: f32 = 0.6;
+
+
+/// A client-side resource that stores the overlay entity of each block that is
+/// being broken.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct CrackOverlays {
+    /// The cube mesh that is shared by all overlays, once it has been created.
+    mesh: Option<Handle<Mesh>>,
+
+    /// The overlay entity and material of each cracked block, by block
+    /// position.
+    overlays: HashMap<IVec3, (Entity, Handle<StandardMaterial>)>,
+}
+
+
+/// Gets the color of the crack overlay at the given crack stage.
+fn crack_color(stage: u8) -> Color {
+    let opacity = (stage + 1) as f32 / CRACK_STAGES as f32 * MAX_CRACK_OPACITY;
+    Color::rgba(0.0, 0.0, 0.0, opacity)
+}
+
+
+/// Spawns, updates, and despawns the crack overlay of each block as the crack
+/// stage of the block changes.
+///
+/// If multiple players are breaking the same block, the furthest crack stage
+/// is shown.
+pub fn show_block_cracks(
+    time: Res<Time>,
+    cracks: Res<BlockCracks>,
+    local: Option<Res<LocalBlockBreaking>>,
+    mut overlays: ResMut<CrackOverlays>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+    let local = local
+        .and_then(|local| local.get().copied())
+        .map(|breaking| (breaking.block_pos, breaking.crack_stage(now)));
+
+    let mut stages: HashMap<IVec3, u8> = HashMap::default();
+    for (block_pos, stage) in cracks.iter().chain(local) {
+        let current = stages.entry(block_pos).or_default();
+        *current = (*current).max(stage);
+    }
+
+    overlays.overlays.retain(|block_pos, (entity, _)| {
+        let cracked = stages.contains_key(block_pos);
+        if !cracked {
+            commands.entity(*entity).despawn();
+        }
+        cracked
+    });
+
+    let mesh = overlays
+        .mesh
+        .get_or_insert_with(|| {
+            meshes.add(Mesh::from(shape::Cube {
+                size: CRACK_OVERLAY_SIZE,
+            }))
+        })
+        .clone();
+
+    for (block_pos, stage) in stages {
+        let color = crack_color(stage);
+
+        if let Some((_, material)) = overlays.overlays.get(&block_pos) {
+            let changed = materials.get(material).is_some_and(|m| m.base_color != color);
+            if changed {
+                if let Some(material) = materials.get_mut(material) {
+                    material.base_color = color;
+                }
+            }
+            continue;
+        }
+
+        let material = materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+
+        let entity = commands
+            .spawn(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(block_pos.as_vec3() + Vec3::splat(0.5)),
+                ..default()
+            })
+            .id();
+
+        overlays.overlays.insert(block_pos, (entity, material));
+    }
+}
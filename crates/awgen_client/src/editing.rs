@@ -0,0 +1,236 @@
+//! Allows the local player to break the block that they are looking at, or to
+//! place a new block against it.
+//!
+//! Blocks are broken by holding the left mouse button on them for their break
+//! time, while the server is told when breaking starts and stops. Once broken,
+//! blocks are edited like placed blocks.
+//!
+//! Edits are applied to the local world immediately, so that they do not wait
+//! for a round trip to the server. Each edit is recorded within the
+//! [`BlockEditJournal`] until the server responds, and is rolled back if the
+//! server rejects it.
+
+
+use crate::prelude::MouseController;
+use awgen_network::prelude::{
+    send_block_break, send_block_edit, BlockBreakAction, BlockEditAck, BlockEditAction, LocalHeldItem, MessageBatch, ServerMessage, MAX_INTERACTION_REACH, PLAYER_EYE_HEIGHT
+};
+use awgen_physics::prelude::{run_in_game, run_in_world, GameMode, Position};
+use awgen_world::prelude::{
+    break_time, BlockBreaking, BlockEditJournal, BlockHardness, BlockItem, VoxelWorld
+};
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+
+/// A client-side resource that stores the progress of the local player
+/// breaking a block.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct LocalBlockBreaking {
+    /// The block that is being broken, if any.
+    breaking: Option<BlockBreaking>,
+}
+
+impl LocalBlockBreaking {
+    /// Gets the block that the local player is breaking, if any.
+    pub fn get(&self) -> Option<&BlockBreaking> {
+        self.breaking.as_ref()
+    }
+}
+
+
+/// Breaks the targeted block once the left mouse button has been held on it
+/// for its break time, while the mouse is locked.
+///
+/// The server is told when the local player starts breaking a block, and when
+/// they stop before it is broken. The broken block is predicted within the
+/// local world and sent to the server as a block edit.
+#[allow(clippy::too_many_arguments)]
+pub fn break_targeted_block<BlockData>(
+    time: Res<Time>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    held: Res<LocalHeldItem>,
+    mut local: ResMut<LocalBlockBreaking>,
+    mut journal: ResMut<BlockEditJournal<BlockData>>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&Position, &MouseController, Option<&GameMode>)>,
+    mut worlds: Query<(&mut VoxelWorld<BlockData>, &CollisionLayer)>,
+) where
+    BlockData: BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let Some((mut world, layer)) = worlds.iter_mut().next() else {
+        return;
+    };
+
+    let now = time.elapsed_seconds_f64();
+    let tool = (!held.item().is_empty()).then_some(held.item().item.as_str());
+
+    let target = players.iter().find_map(|(position, controller, game_mode)| {
+        let game_mode = game_mode.copied().unwrap_or_default();
+        if !controller.locked || !game_mode.can_interact() {
+            return None;
+        }
+
+        let eye = position.translation + Vec3::Y * PLAYER_EYE_HEIGHT;
+        let look = controller.quat() * Vec3::NEG_Z;
+        let hit = layer.raycast(eye, look, MAX_INTERACTION_REACH)?;
+        let face = hit.face?;
+
+        let block = world.get_block_data(hit.cell);
+        if block == BlockData::default() {
+            return None;
+        }
+
+        let duration = break_time(&block, game_mode, tool)?;
+        Some((hit.cell, face, duration))
+    });
+
+    let target = target.filter(|_| mouse_buttons.pressed(MouseButton::Left));
+    let Some((block_pos, face, duration)) = target else {
+        if local.breaking.take().is_some() {
+            send_block_break(&mut batch, BlockBreakAction::Cancel);
+        }
+        return;
+    };
+
+    if local.breaking.map(|breaking| breaking.block_pos) != Some(block_pos) {
+        local.breaking = Some(BlockBreaking::new(block_pos, now, duration));
+        send_block_break(&mut batch, BlockBreakAction::Start {
+            block_pos,
+        });
+    }
+
+    if !local.breaking.is_some_and(|breaking| breaking.is_finished(now, 0.0)) {
+        return;
+    }
+
+    local.breaking = None;
+    if let Some(sequence) = journal.predict(&mut world, block_pos, BlockData::default()) {
+        send_block_edit(
+            &mut batch,
+            sequence,
+            block_pos,
+            face,
+            BlockEditAction::Break,
+        );
+    }
+}
+
+
+/// Places the held item against the targeted block when the middle mouse
+/// button is pressed, while the mouse is locked.
+///
+/// The edit is predicted within the local world and sent to the server. Edits
+/// that are not allowed within the local world are not sent.
+pub fn edit_targeted_block<BlockData>(
+    mouse_buttons: Res<Input<MouseButton>>,
+    held: Res<LocalHeldItem>,
+    mut journal: ResMut<BlockEditJournal<BlockData>>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&Position, &MouseController, Option<&GameMode>)>,
+    mut worlds: Query<(&mut VoxelWorld<BlockData>, &CollisionLayer)>,
+) where
+    BlockData: BlockItem + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    if !mouse_buttons.just_pressed(MouseButton::Middle) || held.item().is_empty() {
+        return;
+    }
+
+    let item = held.item().item.clone();
+    let Some(block) = BlockData::from_item(&item) else {
+        return;
+    };
+
+    let Some((mut world, layer)) = worlds.iter_mut().next() else {
+        return;
+    };
+
+    for (position, controller, game_mode) in players.iter() {
+        if !controller.locked || !game_mode.map_or(true, |mode| mode.can_interact()) {
+            continue;
+        }
+
+        let eye = position.translation + Vec3::Y * PLAYER_EYE_HEIGHT;
+        let look = controller.quat() * Vec3::NEG_Z;
+        let Some(hit) = layer.raycast(eye, look, MAX_INTERACTION_REACH) else {
+            continue;
+        };
+
+        let Some(face) = hit.face else {
+            continue;
+        };
+
+        let Some(sequence) = journal.predict(&mut world, hit.cell + face.offset(), block) else {
+            continue;
+        };
+
+        send_block_edit(
+            &mut batch,
+            sequence,
+            hit.cell,
+            face,
+            BlockEditAction::Place {
+                item: item.clone(),
+            },
+        );
+    }
+}
+
+
+/// Confirms or rolls back each predicted block edit as the server responds to
+/// it.
+pub fn reconcile_block_edits<BlockData>(
+    mut ack_ev: EventReader<ServerMessage<BlockEditAck>>,
+    mut journal: ResMut<BlockEditJournal<BlockData>>,
+    mut worlds: Query<&mut VoxelWorld<BlockData>>,
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    for ev in ack_ev.iter() {
+        let ack = ev.message;
+        if ack.accepted {
+            journal.confirm(ack.sequence);
+            continue;
+        }
+
+        let Some(mut world) = worlds.iter_mut().next() else {
+            continue;
+        };
+
+        if let Some(block_pos) = journal.reject(&mut world, ack.sequence) {
+            debug!("Rolled back rejected block edit at {block_pos}");
+        }
+    }
+}
+
+
+/// A mini extension plugin that allows the local player to edit blocks of the
+/// given type, predicting each edit until the server responds to it.
+#[derive(Debug, Clone, Default)]
+pub struct BlockEditPredictionPlugin<BlockData>
+where BlockData: BlockItem + BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for BlockEditPredictionPlugin<BlockData>
+where BlockData: BlockItem + BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockEditJournal<BlockData>>()
+            .init_resource::<LocalBlockBreaking>()
+            .add_system(break_targeted_block::<BlockData>.with_run_criteria(run_in_game))
+            .add_system(
+                edit_targeted_block::<BlockData>
+                    .with_run_criteria(run_in_game)
+                    .after(break_targeted_block::<BlockData>),
+            )
+            .add_system(
+                reconcile_block_edits::<BlockData>
+                    .with_run_criteria(run_in_world)
+                    .after(break_targeted_block::<BlockData>)
+                    .after(edit_targeted_block::<BlockData>),
+            );
+    }
+}
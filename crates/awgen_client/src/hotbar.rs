@@ -0,0 +1,63 @@
+//! Allows the local player to select a hotbar slot with the number keys, and
+//! displays the item that they are holding.
+
+
+use awgen_network::prelude::{send_hotbar_selection, LocalHeldItem, MessageBatch, HOTBAR_SLOTS};
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align2};
+use bevy_egui::EguiContext;
+
+
+/// The key that selects each hotbar slot, in slot order.
+const SLOT_KEYS: [KeyCode; HOTBAR_SLOTS] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+
+/// Requests the server to select a hotbar slot each time that its number key
+/// is pressed.
+pub fn select_hotbar_slot(
+    keyboard: Res<Input<KeyCode>>,
+    held: Res<LocalHeldItem>,
+    mut batch: ResMut<MessageBatch>,
+) {
+    let Some(slot) = SLOT_KEYS.iter().position(|key| keyboard.just_pressed(*key)) else {
+        return;
+    };
+
+    if slot as u8 != held.slot() {
+        send_hotbar_selection(&mut batch, slot as u8);
+    }
+}
+
+
+/// Draws the selected hotbar slot and the held item at the bottom of the
+/// screen.
+pub fn show_held_item(held: Res<LocalHeldItem>, mut egui_context: ResMut<EguiContext>) {
+    let text = match held.item().is_empty() {
+        true => format!("[{}] Empty", held.slot() + 1),
+        false => {
+            format!(
+                "[{}] {} x{}",
+                held.slot() + 1,
+                held.item().item,
+                held.item().count
+            )
+        },
+    };
+
+    egui::Area::new("held_item").anchor(Align2::CENTER_BOTTOM, [0.0, -16.0]).show(
+        egui_context.ctx_mut(),
+        |ui| {
+            ui.label(text);
+        },
+    );
+}
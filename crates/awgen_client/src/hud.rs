@@ -0,0 +1,80 @@
+//! Draws the on-screen messages that the server shows to the local player,
+//! such as boss bars, titles, and action bar messages.
+
+
+use awgen_network::prelude::ClientHud;
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align2, Color32, RichText};
+use bevy_egui::EguiContext;
+
+
+/// The width, in points, of each boss bar.
+const BOSS_BAR_WIDTH: f32 = 320.0;
+
+
+/// The font size of the text of a title.
+const TITLE_SIZE: f32 = 48.0;
+
+
+/// The font size of the text of a subtitle.
+const SUBTITLE_SIZE: f32 = 24.0;
+
+
+/// Draws each boss bar along the top of the screen, sorted by id.
+pub fn show_boss_bars(hud: Res<ClientHud>, mut egui_context: ResMut<EguiContext>) {
+    let bars = hud.boss_bars();
+    if bars.is_empty() {
+        return;
+    }
+
+    egui::Area::new("boss_bars")
+        .anchor(Align2::CENTER_TOP, [0.0, 8.0])
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            for (_, bar) in bars {
+                let [r, g, b] = bar.color.rgb();
+                let color = Color32::from_rgb(r, g, b);
+
+                ui.vertical_centered(|ui| ui.colored_label(color, &bar.title));
+                ui.visuals_mut().selection.bg_fill = color;
+                ui.add(egui::ProgressBar::new(bar.progress).desired_width(BOSS_BAR_WIDTH));
+            }
+        });
+}
+
+
+/// Draws the title and subtitle in the middle of the screen while one is
+/// shown, fading it in and out.
+pub fn show_title(hud: Res<ClientHud>, mut egui_context: ResMut<EguiContext>) {
+    let Some(title) = hud.title() else {
+        return;
+    };
+
+    let color = Color32::WHITE.linear_multiply(title.opacity());
+    egui::Area::new("title")
+        .anchor(Align2::CENTER_CENTER, [0.0, -64.0])
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new(&title.title).size(TITLE_SIZE).strong().color(color));
+                if !title.subtitle.is_empty() {
+                    ui.label(RichText::new(&title.subtitle).size(SUBTITLE_SIZE).color(color));
+                }
+            });
+        });
+}
+
+
+/// Draws the action bar message just above the held item while one is shown.
+pub fn show_action_bar(hud: Res<ClientHud>, mut egui_context: ResMut<EguiContext>) {
+    let Some(text) = hud.action_bar() else {
+        return;
+    };
+
+    egui::Area::new("action_bar")
+        .anchor(Align2::CENTER_BOTTOM, [0.0, -48.0])
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(text);
+        });
+}
@@ -0,0 +1,51 @@
+//! Allows the local player to use the block that they are looking at, such as
+//! opening a door or toggling a lever.
+
+
+use crate::prelude::MouseController;
+use awgen_network::prelude::{
+    send_block_use, MessageBatch, MAX_INTERACTION_REACH, PLAYER_EYE_HEIGHT
+};
+use awgen_physics::prelude::{GameMode, Position};
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+
+
+/// Sends a request to use the targeted block to the server each time the
+/// right mouse button is pressed while the mouse is locked.
+///
+/// The targeted block is the first solid block along the look ray of the
+/// player, within interaction reach. The server validates and executes the
+/// request, and replicates the result back to all players within the world.
+pub fn use_targeted_block(
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&Position, &MouseController, Option<&GameMode>)>,
+    layers: Query<&CollisionLayer>,
+) {
+    if !mouse_buttons.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    let Some(layer) = layers.iter().next() else {
+        return;
+    };
+
+    for (position, controller, game_mode) in players.iter() {
+        if !controller.locked || !game_mode.map_or(true, |mode| mode.can_interact()) {
+            continue;
+        }
+
+        let eye = position.translation + Vec3::Y * PLAYER_EYE_HEIGHT;
+        let look = controller.quat() * Vec3::NEG_Z;
+        let Some(hit) = layer.raycast(eye, look, MAX_INTERACTION_REACH) else {
+            continue;
+        };
+
+        let Some(face) = hit.face else {
+            continue;
+        };
+
+        send_block_use(&mut batch, hit.cell, face);
+    }
+}
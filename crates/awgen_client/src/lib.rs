@@ -7,18 +7,50 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+pub mod auto_quality;
+pub mod containers;
 pub mod controller;
+pub mod cracks;
+pub mod editing;
+pub mod hotbar;
+pub mod hud;
+pub mod interaction;
+pub mod particles;
+pub mod physics_debug;
+pub mod player_list;
+pub mod scoreboard;
+pub mod sounds;
+pub mod spectate;
+pub mod text_displays;
+pub mod weather;
 
 
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
+    pub use super::auto_quality::*;
+    pub use super::containers::*;
     pub use super::controller::*;
+    pub use super::cracks::*;
+    pub use super::editing::*;
+    pub use super::hotbar::*;
+    pub use super::hud::*;
+    pub use super::interaction::*;
+    pub use super::particles::*;
+    pub use super::physics_debug::*;
+    pub use super::player_list::*;
+    pub use super::scoreboard::*;
+    pub use super::sounds::*;
+    pub use super::spectate::*;
+    pub use super::text_displays::*;
+    pub use super::weather::*;
     pub use super::*;
 }
 
 
+use awgen_physics::prelude::{run_in_game, run_in_world, AwgenSystemExt, AwgenSystemOrdering};
 use bevy::ecs::schedule::ReportExecutionOrderAmbiguities;
 use bevy::prelude::*;
+use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::WorldInspectorPlugin;
 use prelude::*;
 
@@ -50,17 +82,81 @@ impl ClientPlugin {
 
 impl Plugin for ClientPlugin {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugin(EguiPlugin);
+        }
+
         if self.is_debug() {
             app.insert_resource(ReportExecutionOrderAmbiguities)
-                .add_plugin(WorldInspectorPlugin::new());
+                .add_plugin(WorldInspectorPlugin::new())
+                .init_resource::<PhysicsDebug>()
+                .init_resource::<DebugLines>()
+                .add_system(toggle_physics_debug)
+                .add_system(draw_collider_bounds.after(toggle_physics_debug))
+                .add_system(draw_velocity_vectors.after(draw_collider_bounds))
+                .add_system(draw_look_raycast.after(draw_velocity_vectors))
+                .add_system(draw_ground_contacts.after(draw_look_raycast))
+                .add_system_to_stage(CoreStage::PostUpdate, render_debug_lines);
         }
 
         app.register_type::<WasdController>()
             .register_type::<MouseController>()
             .register_type::<CameraController>()
-            .add_system(wasd_velocity_input)
-            .add_system(mouse_rotation_input.ambiguous_with(wasd_velocity_input))
-            .add_system(toggle_cursor.ambiguous_with(mouse_rotation_input))
-            .add_system(apply_camera_transform.after(mouse_rotation_input));
+            .init_resource::<ParticleBudget>()
+            .init_resource::<AutoQuality>()
+            .init_resource::<ParticlePool>()
+            .init_resource::<SkyColor>()
+            .init_resource::<RainEmitter>()
+            .init_resource::<CrackOverlays>()
+            .add_system(apply_local_game_mode)
+            .add_system(tune_render_quality)
+            .add_system(insert_predicted_movement)
+            .add_system(
+                wasd_velocity_input
+                    .with_run_criteria(run_in_game)
+                    .in_awgen_set(AwgenSystemOrdering::PlayerInput)
+                    .after(apply_local_game_mode),
+            )
+            .add_system(
+                mouse_rotation_input
+                    .with_run_criteria(run_in_game)
+                    .in_awgen_set(AwgenSystemOrdering::PlayerInput),
+            )
+            .add_system(
+                toggle_cursor
+                    .with_run_criteria(run_in_game)
+                    .in_awgen_set(AwgenSystemOrdering::PlayerInput),
+            )
+            .add_system(toggle_pause.in_awgen_set(AwgenSystemOrdering::PlayerInput))
+            .add_system(
+                update_camera_look
+                    .with_run_criteria(run_in_world)
+                    .in_awgen_set(AwgenSystemOrdering::PlayerInput)
+                    .after(mouse_rotation_input),
+            )
+            .add_system(
+                update_look_rotation.with_run_criteria(run_in_world).after(mouse_rotation_input),
+            )
+            .add_system(show_player_list.with_run_criteria(run_in_world))
+            .add_system(show_scoreboard_sidebar.with_run_criteria(run_in_world))
+            .add_system(show_container.with_run_criteria(run_in_world))
+            .add_system(show_held_item.with_run_criteria(run_in_world))
+            .add_system(show_boss_bars.with_run_criteria(run_in_world))
+            .add_system(show_title.with_run_criteria(run_in_world))
+            .add_system(show_action_bar.with_run_criteria(run_in_world))
+            .add_system(show_text_displays.with_run_criteria(run_in_world))
+            .add_system(select_hotbar_slot.with_run_criteria(run_in_game))
+            .add_system(track_input_activity.with_run_criteria(run_in_game))
+            .add_system(use_targeted_block.with_run_criteria(run_in_game))
+            .add_system(cycle_spectate_target.with_run_criteria(run_in_game))
+            .add_system(attach_spectate_camera.with_run_criteria(run_in_world))
+            .add_system(spawn_particles.with_run_criteria(run_in_world))
+            .add_system(update_particles.with_run_criteria(run_in_world).after(spawn_particles))
+            .add_system(play_world_sounds.with_run_criteria(run_in_world))
+            .add_system(show_block_cracks.with_run_criteria(run_in_world))
+            .add_system(darken_sky)
+            .add_system(
+                emit_rain_particles.with_run_criteria(run_in_world).before(spawn_particles),
+            );
     }
 }
@@ -0,0 +1,273 @@
+//! A lightweight particle system, which plays the bursts of particles that are
+//! replicated from the server.
+//!
+//! Particles are small, unlit cubes that only exist on the client. Particle
+//! entities are pooled and reused once they expire, and the total number of
+//! live particles is limited by the [`ParticleBudget`].
+
+
+use awgen_math::prelude::{Seed, SeededRng};
+use awgen_network::prelude::{ParticleEvent, ParticleKind};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The default maximum number of particles that may be alive at once.
+pub const DEFAULT_MAX_PARTICLES: usize = 2048;
+
+
+/// The color of footstep dust particles.
+const DUST_COLOR: [u8; 3] = [156, 138, 110];
+
+
+/// The color of explosion smoke particles.
+const SMOKE_COLOR: [u8; 3] = [72, 72, 72];
+
+
+/// The color of raindrop particles.
+const RAIN_COLOR: [u8; 3] = [150, 170, 210];
+
+
+/// The maximum number of particles that may be alive at once. Particles that
+/// would exceed this budget are not emitted.
+#[derive(Debug, Clone, Resource)]
+pub struct ParticleBudget {
+    /// The maximum number of live particles.
+    pub max_particles: usize,
+}
+
+impl Default for ParticleBudget {
+    fn default() -> Self {
+        Self {
+            max_particles: DEFAULT_MAX_PARTICLES,
+        }
+    }
+}
+
+
+/// The motion and appearance of a single particle.
+#[derive(Debug, Clone, Component)]
+pub struct Particle {
+    /// The current velocity of this particle, in meters per second.
+    velocity: Vec3,
+
+    /// The downwards acceleration of this particle, in meters per second
+    /// squared. Negative values cause the particle to rise.
+    gravity: f32,
+
+    /// The number of seconds that this particle has been alive for.
+    age: f32,
+
+    /// The number of seconds that this particle lives for.
+    lifetime: f32,
+
+    /// The initial size of this particle, in meters.
+    size: f32,
+
+    /// The size of this particle at the end of its lifetime, relative to its
+    /// initial size.
+    end_scale: f32,
+}
+
+
+/// The pool of particle entities, alongside the render assets that are shared
+/// between all particles.
+#[derive(Resource)]
+pub struct ParticlePool {
+    /// The hidden particle entities that may be reused.
+    free: Vec<Entity>,
+
+    /// The number of particles that are currently alive.
+    live: usize,
+
+    /// The cube mesh that is used by all particles.
+    mesh: Option<Handle<Mesh>>,
+
+    /// The material that is used for each particle color.
+    materials: HashMap<[u8; 3], Handle<StandardMaterial>>,
+
+    /// The random number generator used to scatter particles.
+    rng: SeededRng,
+}
+
+impl Default for ParticlePool {
+    fn default() -> Self {
+        Self {
+            free:      vec![],
+            live:      0,
+            mesh:      None,
+            materials: default(),
+            rng:       SeededRng::new(Seed::from_text("particles")),
+        }
+    }
+}
+
+impl ParticlePool {
+    /// Gets the number of particles that are currently alive.
+    pub fn live(&self) -> usize {
+        self.live
+    }
+
+
+    /// Gets the shared particle mesh, creating it if needed.
+    fn mesh(&mut self, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        self.mesh
+            .get_or_insert_with(|| {
+                meshes.add(Mesh::from(shape::Cube {
+                    size: 1.0,
+                }))
+            })
+            .clone()
+    }
+
+
+    /// Gets the particle material for the given color, creating it if needed.
+    fn material(
+        &mut self,
+        color: [u8; 3],
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        self.materials
+            .entry(color)
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::rgb_u8(color[0], color[1], color[2]),
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone()
+    }
+
+
+    /// Creates a new particle of the given kind with a random velocity and
+    /// lifetime.
+    fn create(&mut self, kind: ParticleKind) -> Particle {
+        let (speed, lift, gravity, lifetime, size, end_scale) = match kind {
+            ParticleKind::BlockDebris {
+                ..
+            } => (2.5, 0.5, 12.0, (0.6, 1.2), 0.12, 0.5),
+            ParticleKind::FootstepDust => (0.6, 0.0, 0.5, (0.3, 0.6), 0.08, 0.0),
+            ParticleKind::ExplosionSmoke => (1.5, 0.2, -0.6, (1.5, 3.0), 0.4, 2.5),
+            ParticleKind::Raindrop => (1.0, -8.0, 10.0, (0.8, 1.2), 0.04, 1.0),
+        };
+
+        let direction = Vec3::new(
+            self.rng.range_f32(-1.0, 1.0),
+            self.rng.range_f32(0.0, 1.0) + lift,
+            self.rng.range_f32(-1.0, 1.0),
+        );
+
+        Particle {
+            velocity: direction * speed,
+            gravity,
+            age: 0.0,
+            lifetime: self.rng.range_f32(lifetime.0, lifetime.1),
+            size,
+            end_scale,
+        }
+    }
+}
+
+
+/// Gets the color of particles of the given kind.
+fn particle_color(kind: ParticleKind) -> [u8; 3] {
+    match kind {
+        ParticleKind::BlockDebris {
+            color,
+        } => color,
+        ParticleKind::FootstepDust => DUST_COLOR,
+        ParticleKind::ExplosionSmoke => SMOKE_COLOR,
+        ParticleKind::Raindrop => RAIN_COLOR,
+    }
+}
+
+
+/// Emits the particles of each received particle event, reusing pooled
+/// particle entities where possible, up to the particle budget.
+#[allow(clippy::type_complexity)]
+pub fn spawn_particles(
+    mut particle_ev: EventReader<ParticleEvent>,
+    budget: Res<ParticleBudget>,
+    mut pool: ResMut<ParticlePool>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut pooled: Query<(
+        &mut Particle,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
+    mut commands: Commands,
+) {
+    for ev in particle_ev.iter() {
+        let available = budget.max_particles.saturating_sub(pool.live);
+        let count = (ev.count as usize).min(available);
+        if count == 0 {
+            continue;
+        }
+
+        let mesh = pool.mesh(&mut meshes);
+        let material = pool.material(particle_color(ev.kind), &mut materials);
+
+        for _ in 0..count {
+            let particle = pool.create(ev.kind);
+            let transform =
+                Transform::from_translation(ev.position).with_scale(Vec3::splat(particle.size));
+            pool.live += 1;
+
+            if let Some(entity) = pool.free.pop() {
+                if let Ok((mut p, mut t, mut visibility, mut m)) = pooled.get_mut(entity) {
+                    *p = particle;
+                    *t = transform;
+                    *m = material.clone();
+                    visibility.is_visible = true;
+                    continue;
+                }
+            }
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform,
+                    ..default()
+                },
+                particle,
+            ));
+        }
+    }
+}
+
+
+/// Moves, grows, and shrinks all live particles, and returns expired particles
+/// to the pool.
+pub fn update_particles(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Visibility)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut particle, mut transform, mut visibility) in particles.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            visibility.is_visible = false;
+            pool.free.push(entity);
+            pool.live = pool.live.saturating_sub(1);
+            continue;
+        }
+
+        let gravity = particle.gravity;
+        particle.velocity.y -= gravity * delta;
+        transform.translation += particle.velocity * delta;
+
+        let progress = particle.age / particle.lifetime;
+        let scale = 1.0 + (particle.end_scale - 1.0) * progress;
+        transform.scale = Vec3::splat(particle.size * scale);
+    }
+}
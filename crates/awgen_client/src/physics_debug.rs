@@ -0,0 +1,302 @@
+//! A debug layer that draws the physics state of the world as lines, including
+//! collider bounds, velocity vectors, the look ray of the local player, and
+//! the points where colliders rest on the ground.
+//!
+//! This layer is only available when the client is loaded in debug mode, and
+//! is toggled at runtime with the F3 key.
+
+
+use crate::prelude::MouseController;
+use awgen_math::prelude::Aabb;
+use awgen_network::prelude::{MAX_INTERACTION_REACH, PLAYER_EYE_HEIGHT};
+use awgen_physics::prelude::{Collider, Knockback, Position, VelocitySource};
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::view::NoFrustumCulling;
+
+
+/// The key that toggles the physics debug layer.
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+
+/// The length, in meters, that velocity vectors are drawn at for each meter per
+/// physics frame of velocity.
+const VELOCITY_LINE_SCALE: f32 = 10.0;
+
+
+/// The distance, in meters, beneath the bottom of a collider that solid blocks
+/// are considered to be in contact with it.
+const GROUND_CONTACT_DISTANCE: f32 = 0.05;
+
+
+/// The half size, in meters, of the cross that marks each point.
+const MARKER_SIZE: f32 = 0.1;
+
+
+/// The color of collider bounds.
+const COLLIDER_COLOR: Color = Color::rgb(0.2, 1.0, 0.2);
+
+
+/// The color of velocity vectors generated by a velocity source.
+const VELOCITY_COLOR: Color = Color::rgb(0.2, 0.5, 1.0);
+
+
+/// The color of knockback velocity vectors.
+const KNOCKBACK_COLOR: Color = Color::rgb(1.0, 0.3, 0.2);
+
+
+/// The color of raycast lines.
+const RAYCAST_COLOR: Color = Color::rgb(1.0, 1.0, 0.2);
+
+
+/// The color of ground contact points.
+const CONTACT_COLOR: Color = Color::rgb(1.0, 0.2, 1.0);
+
+
+/// The settings of the physics debug layer.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PhysicsDebug {
+    /// Whether or not the physics debug layer is drawn.
+    pub enabled: bool,
+}
+
+
+/// The lines that are drawn by the physics debug layer this frame.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct DebugLines {
+    /// The start point, end point, and color of each line.
+    lines: Vec<(Vec3, Vec3, Color)>,
+}
+
+impl DebugLines {
+    /// Draws a line between the given points.
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        self.lines.push((start, end, color));
+    }
+
+
+    /// Draws the twelve edges of the given bounding box.
+    pub fn aabb(&mut self, aabb: &Aabb, color: Color) {
+        let min = aabb.min();
+        let max = aabb.max();
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+
+        for a in [false, true] {
+            for b in [false, true] {
+                self.line(corner(false, a, b), corner(true, a, b), color);
+                self.line(corner(a, false, b), corner(a, true, b), color);
+                self.line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+    }
+
+
+    /// Draws a small cross centered on the given point.
+    pub fn point(&mut self, point: Vec3, color: Color) {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.line(
+                point - axis * MARKER_SIZE,
+                point + axis * MARKER_SIZE,
+                color,
+            );
+        }
+    }
+
+
+    /// Gets the number of lines that are drawn this frame.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+
+    /// Gets whether or not no lines are drawn this frame.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+
+/// A marker component for the entity that renders the debug lines.
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct DebugLineMesh;
+
+
+/// Toggles the physics debug layer when the toggle key is pressed.
+pub fn toggle_physics_debug(keys: Res<Input<KeyCode>>, mut debug: ResMut<PhysicsDebug>) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        debug.enabled = !debug.enabled;
+        info!(
+            "Physics debug rendering: {}",
+            if debug.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+
+/// Draws the world bounds of every collider.
+pub fn draw_collider_bounds(
+    debug: Res<PhysicsDebug>,
+    mut lines: ResMut<DebugLines>,
+    colliders: Query<(&Position, &Collider)>,
+) {
+    if !debug.enabled {
+        return;
+    }
+
+    for (position, collider) in colliders.iter() {
+        lines.aabb(&collider.world_bounds(position), COLLIDER_COLOR);
+    }
+}
+
+
+/// Draws the velocity and knockback vectors of every entity, starting from its
+/// position.
+pub fn draw_velocity_vectors(
+    debug: Res<PhysicsDebug>,
+    mut lines: ResMut<DebugLines>,
+    entities: Query<(&Position, Option<&VelocitySource>, Option<&Knockback>)>,
+) {
+    if !debug.enabled {
+        return;
+    }
+
+    for (position, source, knockback) in entities.iter() {
+        let origin = position.translation;
+
+        if let Some(source) = source.filter(|s| s.force != Vec3::ZERO) {
+            lines.line(
+                origin,
+                origin + source.force * VELOCITY_LINE_SCALE,
+                VELOCITY_COLOR,
+            );
+        }
+
+        if let Some(knockback) = knockback.filter(|k| k.velocity != Vec3::ZERO) {
+            let end = origin + knockback.velocity * VELOCITY_LINE_SCALE;
+            lines.line(origin, end, KNOCKBACK_COLOR);
+        }
+    }
+}
+
+
+/// Draws the look ray of the local player up to interaction reach, marking the
+/// block face that it hits.
+pub fn draw_look_raycast(
+    debug: Res<PhysicsDebug>,
+    mut lines: ResMut<DebugLines>,
+    players: Query<(&Position, &MouseController)>,
+    layers: Query<&CollisionLayer>,
+) {
+    if !debug.enabled {
+        return;
+    }
+
+    let layer = layers.iter().next();
+    for (position, controller) in players.iter() {
+        let eye = position.translation + Vec3::Y * PLAYER_EYE_HEIGHT;
+        let look = controller.quat() * Vec3::NEG_Z;
+
+        let hit = layer.and_then(|layer| layer.raycast(eye, look, MAX_INTERACTION_REACH));
+        match hit {
+            Some(hit) => {
+                let point = eye + look * hit.distance;
+                lines.line(eye, point, RAYCAST_COLOR);
+                lines.point(point, RAYCAST_COLOR);
+            },
+            None => lines.line(eye, eye + look * MAX_INTERACTION_REACH, RAYCAST_COLOR),
+        }
+    }
+}
+
+
+/// Marks each bottom corner of every collider that is resting on a solid block.
+pub fn draw_ground_contacts(
+    debug: Res<PhysicsDebug>,
+    mut lines: ResMut<DebugLines>,
+    colliders: Query<(&Position, &Collider)>,
+    layers: Query<&CollisionLayer>,
+) {
+    if !debug.enabled {
+        return;
+    }
+
+    let Some(layer) = layers.iter().next() else {
+        return;
+    };
+
+    for (position, collider) in colliders.iter() {
+        let bounds = collider.world_bounds(position);
+        let (min, max) = (bounds.min(), bounds.max());
+
+        for x in [min.x, max.x] {
+            for z in [min.z, max.z] {
+                let corner = Vec3::new(x, min.y, z);
+                let below = corner - Vec3::Y * GROUND_CONTACT_DISTANCE;
+                if layer.is_solid(below.floor().as_ivec3()) {
+                    lines.point(corner, CONTACT_COLOR);
+                }
+            }
+        }
+    }
+}
+
+
+/// Uploads the debug lines that were drawn this frame to the debug line mesh,
+/// spawning the mesh if needed, and clears them for the next frame.
+///
+/// The line mesh is never frustum culled, as its bounds change every frame.
+pub fn render_debug_lines(
+    debug: Res<PhysicsDebug>,
+    mut lines: ResMut<DebugLines>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_meshes: Query<(&Handle<Mesh>, &mut Visibility), With<DebugLineMesh>>,
+    mut commands: Commands,
+) {
+    let visible = debug.enabled && !lines.is_empty();
+    let Ok((handle, mut visibility)) = line_meshes.get_single_mut() else {
+        if visible {
+            commands.spawn((
+                PbrBundle {
+                    mesh: meshes.add(Mesh::new(PrimitiveTopology::LineList)),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::WHITE,
+                        unlit: true,
+                        ..default()
+                    }),
+                    ..default()
+                },
+                NoFrustumCulling,
+                DebugLineMesh,
+            ));
+        }
+        lines.lines.clear();
+        return;
+    };
+
+    visibility.is_visible = visible;
+    if visible {
+        if let Some(mesh) = meshes.get_mut(handle) {
+            let mut positions = Vec::with_capacity(lines.len() * 2);
+            let mut colors = Vec::with_capacity(lines.len() * 2);
+            let normals = vec![[0.0, 1.0, 0.0]; lines.len() * 2];
+            for (start, end, color) in lines.lines.iter() {
+                positions.extend([start.to_array(), end.to_array()]);
+                colors.extend([color.as_linear_rgba_f32(); 2]);
+            }
+
+            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        }
+    }
+
+    lines.lines.clear();
+}
@@ -0,0 +1,49 @@
+//! The player list overlay, which displays all players on the server while the
+//! tab key is held.
+//!
+//! If the server shows an objective within the list display slot, the score of
+//! each player within that objective is displayed alongside them. The names of
+//! players that are on a team are shown in the color of their team.
+
+
+use awgen_network::prelude::{ClientScoreboard, ClientTeams, DisplaySlot, PlayerRoster};
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align2};
+use bevy_egui::EguiContext;
+
+
+/// Draws the player list overlay while the tab key is held down.
+pub fn show_player_list(
+    keyboard: Res<Input<KeyCode>>,
+    roster: Res<PlayerRoster>,
+    scoreboard: Res<ClientScoreboard>,
+    teams: Res<ClientTeams>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if !keyboard.pressed(KeyCode::Tab) {
+        return;
+    }
+
+    let objective = scoreboard.get(DisplaySlot::List);
+
+    egui::Window::new("Players")
+        .anchor(Align2::CENTER_TOP, [0.0, 32.0])
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::Grid::new("player_list").striped(true).show(ui, |ui| {
+                for entry in roster.entries() {
+                    let [r, g, b] = teams.color_of(&entry.name).rgb();
+                    ui.colored_label(egui::Color32::from_rgb(r, g, b), &entry.name);
+                    ui.label(entry.game_mode.name());
+                    ui.label(format!("{} ms", entry.ping));
+                    if let Some(objective) = objective {
+                        let score = objective.score(&entry.name);
+                        ui.label(score.map(|score| score.to_string()).unwrap_or_default());
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,42 @@
+//! The scoreboard sidebar, which displays the scores of the objective that the
+//! server shows within the sidebar display slot. Score holders that are on a
+//! team are shown in the color of their team.
+
+
+use awgen_network::prelude::{ClientScoreboard, ClientTeams, DisplaySlot};
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align2};
+use bevy_egui::EguiContext;
+
+
+/// The maximum number of scores that are shown within the sidebar.
+const MAX_SIDEBAR_SCORES: usize = 15;
+
+
+/// Draws the scoreboard sidebar while the server shows an objective within it.
+pub fn show_scoreboard_sidebar(
+    scoreboard: Res<ClientScoreboard>,
+    teams: Res<ClientTeams>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let Some(objective) = scoreboard.get(DisplaySlot::Sidebar) else {
+        return;
+    };
+
+    egui::Window::new("Scoreboard")
+        .anchor(Align2::RIGHT_CENTER, [-16.0, 0.0])
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| ui.strong(&objective.display_name));
+            egui::Grid::new("scoreboard_sidebar").striped(true).show(ui, |ui| {
+                for entry in objective.scores.iter().take(MAX_SIDEBAR_SCORES) {
+                    let [r, g, b] = teams.color_of(&entry.holder).rgb();
+                    ui.colored_label(egui::Color32::from_rgb(r, g, b), &entry.holder);
+                    ui.label(entry.score.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
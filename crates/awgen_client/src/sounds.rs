@@ -0,0 +1,50 @@
+//! Plays the sounds that the server reports within the world of the local
+//! player, quietening each sound with its distance from the camera.
+
+
+use awgen_network::prelude::SoundEvent;
+use bevy::prelude::*;
+
+
+/// Gets the asset path of the sound with the given id.
+///
+/// Each dot separated segment of the sound id is a folder within the `sounds`
+/// asset folder, such that `mob.zombie.groan` is loaded from
+/// `sounds/mob/zombie/groan.ogg`.
+fn sound_path(sound_id: &str) -> String {
+    format!("sounds/{}.ogg", sound_id.replace('.', "/"))
+}
+
+
+/// Plays each sound that was received from the server, with its volume fading
+/// out linearly towards the edge of its audible range.
+pub fn play_world_sounds(
+    mut sound_ev: EventReader<SoundEvent>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+) {
+    let listener = cameras.iter().next().map(|t| t.translation());
+
+    for ev in sound_ev.iter() {
+        if !ev.has_valid_id() {
+            warn!("Ignoring sound with invalid id: '{}'", ev.sound_id);
+            continue;
+        }
+
+        let range = ev.audible_distance();
+        let falloff = match (listener, range > 0.0) {
+            (Some(listener), true) => 1.0 - listener.distance(ev.position) / range,
+            (None, true) => 1.0,
+            (_, false) => 0.0,
+        };
+
+        let volume = ev.volume.min(1.0) * falloff;
+        if volume <= 0.0 {
+            continue;
+        }
+
+        let sound = asset_server.load(sound_path(&ev.sound_id));
+        audio.play_with_settings(sound, PlaybackSettings::ONCE.with_volume(volume));
+    }
+}
@@ -0,0 +1,61 @@
+//! Allows the local player to spectate other players, attaching the camera to
+//! the replicated entity of the spectated player.
+
+
+use crate::prelude::CameraController;
+use awgen_network::prelude::{
+    send_spectate_request, MessageBatch, RemoteEntities, SpectateRequest, SpectateView
+};
+use bevy::prelude::*;
+
+
+/// Sends a spectate request to the server when the spectate keys are pressed.
+///
+/// The right bracket key spectates the next player, the left bracket key
+/// spectates the previous player, and the backslash key stops spectating. The
+/// server ignores these requests if the local player does not have permission
+/// to spectate.
+pub fn cycle_spectate_target(keyboard: Res<Input<KeyCode>>, mut batch: ResMut<MessageBatch>) {
+    let request = if keyboard.just_pressed(KeyCode::RBracket) {
+        SpectateRequest::Next
+    } else if keyboard.just_pressed(KeyCode::LBracket) {
+        SpectateRequest::Previous
+    } else if keyboard.just_pressed(KeyCode::Backslash) {
+        SpectateRequest::Stop
+    } else {
+        return;
+    };
+
+    send_spectate_request(&mut batch, request);
+}
+
+
+/// Attaches the camera of the local player to the proxy of the spectated
+/// player, and returns it to the local player once spectating stops.
+///
+/// If the proxy of the spectated player has not yet been replicated, the
+/// camera remains attached to the local player until it is.
+pub fn attach_spectate_camera(
+    view: Res<SpectateView>,
+    remote: Res<RemoteEntities>,
+    players: Query<(Entity, &CameraController)>,
+    cameras: Query<Option<&Parent>>,
+    mut commands: Commands,
+) {
+    let target = view.target().and_then(|id| remote.get(id));
+
+    for (player, controller) in players.iter() {
+        let Some(camera) = controller.camera else {
+            continue;
+        };
+
+        let Ok(parent) = cameras.get(camera) else {
+            continue;
+        };
+
+        let desired = target.unwrap_or(player);
+        if parent.map(|p| p.get()) != Some(desired) {
+            commands.entity(desired).add_child(camera);
+        }
+    }
+}
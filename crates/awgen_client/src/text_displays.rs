@@ -0,0 +1,96 @@
+//! Draws the text displays within the world, such as signs and leaderboards,
+//! always facing the camera.
+
+
+use awgen_network::prelude::TextDisplay;
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align2, Color32, FontId};
+use bevy_egui::EguiContext;
+
+
+/// The height, in blocks, of each line of text of a text display with a scale
+/// of 1.0.
+const LINE_HEIGHT: f32 = 0.25;
+
+
+/// The largest distance, in blocks, that text displays are drawn at.
+const MAX_TEXT_DISTANCE: f32 = 64.0;
+
+
+/// The smallest font size, in points, that text is drawn with. Text that would
+/// be smaller than this is not drawn at all.
+const MIN_FONT_SIZE: f32 = 4.0;
+
+
+/// Draws each text display that is within view of the camera behind all other
+/// interface elements, from the farthest to the nearest.
+///
+/// The size of the text shrinks with its distance to the camera, as if it were
+/// part of the world. Text displays that are hidden behind blocks are not
+/// drawn, unless they are see-through.
+pub fn show_text_displays(
+    mut egui_context: ResMut<EguiContext>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    displays: Query<(&TextDisplay, &GlobalTransform)>,
+    layers: Query<&CollisionLayer>,
+) {
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+
+    let Some(viewport) = camera.logical_viewport_size() else {
+        return;
+    };
+
+    let eye = camera_transform.translation();
+    let up = camera_transform.up();
+    let layer = layers.iter().next();
+
+    let mut visible = Vec::new();
+    for (display, transform) in displays.iter() {
+        let center = transform.translation();
+        let distance = center.distance(eye);
+        if display.text.is_empty() || distance <= f32::EPSILON || distance > MAX_TEXT_DISTANCE {
+            continue;
+        }
+
+        if !display.see_through {
+            let direction = (center - eye) / distance;
+            if layer.map_or(false, |layer| {
+                layer.raycast(eye, direction, distance).is_some()
+            }) {
+                continue;
+            }
+        }
+
+        let line_height = LINE_HEIGHT * display.scale;
+        let screen = camera.world_to_viewport(camera_transform, center);
+        let top = camera.world_to_viewport(camera_transform, center + up * line_height);
+        let (Some(screen), Some(top)) = (screen, top) else {
+            continue;
+        };
+
+        let font_size = screen.distance(top);
+        if font_size < MIN_FONT_SIZE {
+            continue;
+        }
+
+        let pos = egui::pos2(screen.x, viewport.y - screen.y);
+        visible.push((distance, display, pos, font_size));
+    }
+
+    visible.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let painter = egui_context.ctx_mut().layer_painter(egui::LayerId::background());
+    for (_, display, pos, font_size) in visible {
+        let [r, g, b] = display.color;
+        painter.text(
+            pos,
+            Align2::CENTER_CENTER,
+            &display.text,
+            FontId::proportional(font_size),
+            Color32::from_rgb(r, g, b),
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Renders the weather within the world of the local player, darkening the sky
+//! and emitting rain particles around the camera while precipitation falls.
+
+
+use awgen_math::prelude::{Seed, SeededRng};
+use awgen_network::prelude::{ClientWeather, ParticleEvent, ParticleKind};
+use bevy::prelude::*;
+
+
+/// The maximum number of raindrops that are emitted each second, at full
+/// precipitation strength.
+const MAX_RAINDROPS_PER_SECOND: f32 = 400.0;
+
+
+/// The horizontal distance, in meters, around the camera that raindrops are
+/// emitted within.
+const RAIN_RADIUS: f32 = 12.0;
+
+
+/// The height, in meters, above the camera that raindrops are emitted at.
+const RAIN_HEIGHT: f32 = 8.0;
+
+
+/// The color that the sky fades towards as it darkens.
+const STORM_SKY_COLOR: [f32; 3] = [0.18, 0.19, 0.22];
+
+
+/// The color of the sky under clear weather, which is darkened according to
+/// the current weather.
+///
+/// By default, this is the clear color that was set when the client plugin was
+/// built.
+#[derive(Debug, Clone, Resource)]
+pub struct SkyColor(pub Color);
+
+impl FromWorld for SkyColor {
+    fn from_world(world: &mut World) -> Self {
+        let color = world.get_resource::<ClearColor>().map(|c| c.0).unwrap_or_default();
+        Self(color)
+    }
+}
+
+
+/// The state used to scatter raindrops around the camera.
+#[derive(Debug, Clone, Resource)]
+pub struct RainEmitter {
+    /// The fractional number of raindrops that are waiting to be emitted.
+    pending: f32,
+
+    /// The random number generator used to scatter raindrops.
+    rng: SeededRng,
+}
+
+impl Default for RainEmitter {
+    fn default() -> Self {
+        Self {
+            pending: 0.0,
+            rng:     SeededRng::new(Seed::from_text("rain")),
+        }
+    }
+}
+
+
+/// Darkens the clear color of the sky according to the current weather.
+pub fn darken_sky(weather: Res<ClientWeather>, sky: Res<SkyColor>, mut clear: ResMut<ClearColor>) {
+    let darkness = weather.sky_darkness();
+    let [r, g, b, a] = sky.0.as_rgba_f32();
+    let [dr, dg, db] = STORM_SKY_COLOR;
+    let color = Color::rgba(
+        r + (dr - r) * darkness,
+        g + (dg - g) * darkness,
+        b + (db - b) * darkness,
+        a,
+    );
+
+    if clear.0 != color {
+        clear.0 = color;
+    }
+}
+
+
+/// Emits raindrop particles above the camera, at a rate that matches the
+/// current precipitation strength.
+pub fn emit_rain_particles(
+    time: Res<Time>,
+    weather: Res<ClientWeather>,
+    mut emitter: ResMut<RainEmitter>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    mut particle_ev: EventWriter<ParticleEvent>,
+) {
+    let precipitation = weather.precipitation();
+    if precipitation <= 0.0 {
+        emitter.pending = 0.0;
+        return;
+    }
+
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+
+    emitter.pending += MAX_RAINDROPS_PER_SECOND * precipitation * time.delta_seconds();
+    let center = camera.translation() + Vec3::Y * RAIN_HEIGHT;
+
+    while emitter.pending >= 1.0 {
+        emitter.pending -= 1.0;
+
+        let offset = Vec3::new(
+            emitter.rng.range_f32(-RAIN_RADIUS, RAIN_RADIUS),
+            0.0,
+            emitter.rng.range_f32(-RAIN_RADIUS, RAIN_RADIUS),
+        );
+
+        particle_ev.send(ParticleEvent {
+            kind:     ParticleKind::Raindrop,
+            position: center + offset,
+            count:    1,
+        });
+    }
+}
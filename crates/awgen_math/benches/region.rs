@@ -0,0 +1,52 @@
+//! Benchmarks for converting block positions into region array indices.
+
+
+use awgen_math::prelude::*;
+use bevy::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+
+/// Compares the different methods of converting a point within a chunk into an
+/// array index.
+fn chunk_index(c: &mut Criterion) {
+    let points: Vec<IVec3> = Region::CHUNK.iter().collect();
+    let mut group = c.benchmark_group("chunk_index");
+
+    group.bench_function("point_to_index", |b| {
+        b.iter(|| {
+            for &pos in &points {
+                black_box(Region::CHUNK.point_to_index(black_box(pos)).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("get_index", |b| {
+        b.iter(|| {
+            for &pos in &points {
+                black_box(Region::CHUNK.get_index(black_box(pos)));
+            }
+        })
+    });
+
+    group.bench_function("point_to_index_unchecked", |b| {
+        b.iter(|| {
+            for &pos in &points {
+                black_box(Region::CHUNK.point_to_index_unchecked(black_box(pos)));
+            }
+        })
+    });
+
+    group.bench_function("chunk_index", |b| {
+        b.iter(|| {
+            for &pos in &points {
+                black_box(Region::chunk_index(black_box(pos)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+
+criterion_group!(benches, chunk_index);
+criterion_main!(benches);
@@ -0,0 +1,359 @@
+//! An axis-aligned bounding box, defined by a minimum and maximum corner in
+//! continuous space.
+
+
+use crate::prelude::{Direction, Region};
+use bevy::prelude::*;
+
+
+/// An axis-aligned bounding box within continuous 3D space.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect, FromReflect)]
+pub struct Aabb {
+    /// The minimum corner of the bounding box.
+    min: Vec3,
+
+    /// The maximum corner of the bounding box.
+    max: Vec3,
+}
+
+impl Aabb {
+    /// Creates a new bounding box from two points.
+    ///
+    /// Each point is an opposite corner of the bounding box.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+
+    /// Creates a new bounding box from a center point and the half size of the
+    /// box along each axis.
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        let half_extents = half_extents.abs();
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+
+    /// Gets the minimum corner of this bounding box.
+    pub fn min(&self) -> Vec3 {
+        self.min
+    }
+
+
+    /// Gets the maximum corner of this bounding box.
+    pub fn max(&self) -> Vec3 {
+        self.max
+    }
+
+
+    /// Gets the center point of this bounding box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+
+    /// Gets the size of this bounding box along each axis.
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+
+    /// Gets half of the size of this bounding box along each axis.
+    pub fn half_extents(&self) -> Vec3 {
+        self.size() * 0.5
+    }
+
+
+    /// Checks if the given point is within this bounding box, inclusive of the
+    /// box edges.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+
+    /// Checks if the given bounding box is fully within this bounding box.
+    pub fn contains_aabb(&self, other: &Aabb) -> bool {
+        other.min.cmpge(self.min).all() && other.max.cmple(self.max).all()
+    }
+
+
+    /// Checks if this bounding box overlaps the given bounding box. Boxes that
+    /// are only touching along an edge or face are not considered to overlap.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
+
+
+    /// Gets the bounding box that is shared between this bounding box and the
+    /// given bounding box, or `None` if the two boxes do not overlap.
+    pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        Some(Aabb {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        })
+    }
+
+
+    /// Gets the smallest bounding box that contains both this bounding box and
+    /// the given bounding box.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+
+    /// Gets a copy of this bounding box that is moved by the given offset.
+    pub fn translate(&self, offset: Vec3) -> Aabb {
+        Aabb {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+
+    /// Gets a copy of this bounding box that is grown by the given amount in
+    /// both directions along each axis.
+    ///
+    /// Negative amounts shrink the box, down to a minimum size of zero.
+    pub fn expand(&self, amount: Vec3) -> Aabb {
+        let min = self.min - amount;
+        let max = self.max + amount;
+        let center = self.center();
+
+        Aabb {
+            min: min.min(center),
+            max: max.max(center),
+        }
+    }
+
+
+    /// Finds the distance along a ray at which it first enters this bounding
+    /// box, or `None` if the ray misses the box or the box is further away
+    /// than the given maximum distance.
+    ///
+    /// The direction does not need to be normalized. If the ray origin is
+    /// within the bounding box, a distance of zero is returned.
+    pub fn ray_intersection(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let direction = direction.normalize_or_zero();
+        let mut t_min = 0.0f32;
+        let mut t_max = max_distance;
+
+        for axis in 0..3 {
+            let (o, d) = (origin[axis], direction[axis]);
+            let (lo, hi) = (self.min[axis], self.max[axis]);
+
+            if d == 0.0 {
+                if o < lo || o > hi {
+                    return None;
+                }
+                continue;
+            }
+
+            let t1 = (lo - o) / d;
+            let t2 = (hi - o) / d;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+
+            if t_min > t_max {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+
+
+    /// Sweeps this bounding box along the given velocity and finds the first
+    /// point in time, between 0 and 1, that it collides with the given
+    /// stationary bounding box.
+    ///
+    /// Returns `None` if the boxes do not collide during the movement. If the
+    /// boxes are already overlapping, a hit at time zero, with no normal, is
+    /// returned.
+    pub fn sweep(&self, velocity: Vec3, other: &Aabb) -> Option<SweepHit> {
+        let mut entry = Vec3::splat(f32::NEG_INFINITY);
+        let mut exit = Vec3::splat(f32::INFINITY);
+
+        for axis in 0..3 {
+            let v = velocity[axis];
+            let (near, far) = if v >= 0.0 {
+                (
+                    other.min[axis] - self.max[axis],
+                    other.max[axis] - self.min[axis],
+                )
+            } else {
+                (
+                    other.max[axis] - self.min[axis],
+                    other.min[axis] - self.max[axis],
+                )
+            };
+
+            if v == 0.0 {
+                if near >= 0.0 || far <= 0.0 {
+                    return None;
+                }
+                continue;
+            }
+
+            entry[axis] = near / v;
+            exit[axis] = far / v;
+        }
+
+        let t_entry = entry.max_element();
+        let t_exit = exit.min_element();
+
+        if t_entry >= t_exit || t_entry > 1.0 || t_exit <= 0.0 {
+            return None;
+        }
+
+        if t_entry < 0.0 {
+            return Some(SweepHit {
+                time:   0.0,
+                normal: None,
+            });
+        }
+
+        let normal = if entry.x == t_entry {
+            if velocity.x > 0.0 {
+                Direction::NegX
+            } else {
+                Direction::PosX
+            }
+        } else if entry.y == t_entry {
+            if velocity.y > 0.0 {
+                Direction::NegY
+            } else {
+                Direction::PosY
+            }
+        } else if velocity.z > 0.0 {
+            Direction::NegZ
+        } else {
+            Direction::PosZ
+        };
+
+        Some(SweepHit {
+            time:   t_entry,
+            normal: Some(normal),
+        })
+    }
+}
+
+impl From<Region> for Aabb {
+    fn from(region: Region) -> Self {
+        Self {
+            min: region.min().as_vec3(),
+            max: (region.max() + 1).as_vec3(),
+        }
+    }
+}
+
+
+/// The result of a swept bounding box collision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepHit {
+    /// The fraction of the velocity, between 0 and 1, that can be applied
+    /// before the collision occurs.
+    pub time: f32,
+
+    /// The face of the stationary bounding box that was hit. This is `None` if
+    /// the boxes were already overlapping.
+    pub normal: Option<Direction>,
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn overlap_and_containment() {
+        let a = Aabb::new(Vec3::ZERO, Vec3::splat(2.0));
+        let b = Aabb::new(Vec3::splat(3.0), Vec3::ONE);
+        let c = Aabb::new(Vec3::splat(2.0), Vec3::splat(4.0));
+
+        assert!(a.intersects(&b));
+        assert!(!a.intersects(&c));
+        assert_eq!(
+            a.intersection(&b),
+            Some(Aabb::new(Vec3::ONE, Vec3::splat(2.0)))
+        );
+        assert_eq!(a.intersection(&c), None);
+        assert_eq!(a.union(&c), Aabb::new(Vec3::ZERO, Vec3::splat(4.0)));
+
+        assert!(a.contains_point(Vec3::splat(2.0)));
+        assert!(!a.contains_point(Vec3::new(1.0, 2.1, 1.0)));
+        assert!(a.contains_aabb(&Aabb::from_center(Vec3::ONE, Vec3::splat(0.5))));
+        assert!(!a.contains_aabb(&b));
+    }
+
+
+    #[test]
+    fn from_region() {
+        let region = Region::from_points(IVec3::new(-1, 0, 2), IVec3::new(1, 0, 3));
+        let aabb = Aabb::from(region);
+
+        assert_eq!(aabb.min(), Vec3::new(-1.0, 0.0, 2.0));
+        assert_eq!(aabb.max(), Vec3::new(2.0, 1.0, 4.0));
+        assert_eq!(aabb.size(), region.size().as_vec3());
+    }
+
+
+    #[test]
+    fn ray_intersection() {
+        let aabb = Aabb::new(Vec3::new(2.0, -1.0, -1.0), Vec3::new(4.0, 1.0, 1.0));
+
+        assert_eq!(aabb.ray_intersection(Vec3::ZERO, Vec3::X, 10.0), Some(2.0));
+        assert_eq!(aabb.ray_intersection(Vec3::ZERO, Vec3::NEG_X, 10.0), None);
+        assert_eq!(aabb.ray_intersection(Vec3::ZERO, Vec3::X, 1.0), None);
+        assert_eq!(
+            aabb.ray_intersection(Vec3::new(0.0, 2.0, 0.0), Vec3::X, 10.0),
+            None
+        );
+        assert_eq!(
+            aabb.ray_intersection(Vec3::new(3.0, 0.0, 0.0), Vec3::Y, 10.0),
+            Some(0.0)
+        );
+    }
+
+
+    #[test]
+    fn sweep() {
+        let mover = Aabb::from_center(Vec3::ZERO, Vec3::splat(0.5));
+        let wall = Aabb::new(Vec3::new(2.0, -5.0, -5.0), Vec3::new(3.0, 5.0, 5.0));
+
+        let hit = mover.sweep(Vec3::new(3.0, 0.0, 0.0), &wall).unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Some(Direction::NegX));
+
+        assert_eq!(mover.sweep(Vec3::new(1.0, 0.0, 0.0), &wall), None);
+        assert_eq!(mover.sweep(Vec3::new(-3.0, 0.0, 0.0), &wall), None);
+        assert_eq!(mover.sweep(Vec3::new(0.0, 3.0, 0.0), &wall), None);
+
+        let inside = mover.translate(Vec3::new(2.5, 0.0, 0.0));
+        assert_eq!(
+            inside.sweep(Vec3::Y, &wall),
+            Some(SweepHit {
+                time:   0.0,
+                normal: None,
+            })
+        );
+    }
+}
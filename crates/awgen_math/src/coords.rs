@@ -0,0 +1,239 @@
+//! Conversions between the different coordinate spaces of a voxel world.
+//!
+//! A voxel world is made up of three nested grids. Each block is a single unit
+//! cube, each chunk is a 16x16x16 grid of blocks, and each region is a
+//! 16x16x16 grid of chunks. Coordinates within each grid are signed, and
+//! conversions between grids always round towards negative infinity, so that
+//! the block at `-1` lies within the chunk at `-1`, rather than the chunk at
+//! `0`.
+//!
+//! Local coordinates refer to the position of a block within its chunk, or of
+//! a chunk within its region, and are always within the range `0..16`.
+
+
+use crate::prelude::Region;
+use bevy::prelude::*;
+
+
+/// The number of bits used to store a local coordinate along a single axis.
+pub const CHUNK_BITS: i32 = 4;
+
+
+/// The number of blocks along each axis of a chunk, and the number of chunks
+/// along each axis of a region.
+pub const CHUNK_SIZE: i32 = 1 << CHUNK_BITS;
+
+
+/// The bit mask that extracts a local coordinate from a global coordinate.
+const LOCAL_MASK: i32 = CHUNK_SIZE - 1;
+
+
+/// Gets the coordinates of the chunk that contains the given block.
+#[inline]
+pub fn block_to_chunk(block_pos: IVec3) -> IVec3 {
+    block_pos >> CHUNK_BITS
+}
+
+
+/// Gets the coordinates of the region that contains the given block.
+#[inline]
+pub fn block_to_region(block_pos: IVec3) -> IVec3 {
+    block_pos >> (CHUNK_BITS * 2)
+}
+
+
+/// Gets the coordinates of the region that contains the given chunk.
+#[inline]
+pub fn chunk_to_region(chunk_coords: IVec3) -> IVec3 {
+    chunk_coords >> CHUNK_BITS
+}
+
+
+/// Gets the coordinates of the minimum block within the given chunk.
+#[inline]
+pub fn chunk_to_block(chunk_coords: IVec3) -> IVec3 {
+    chunk_coords << CHUNK_BITS
+}
+
+
+/// Gets the coordinates of the minimum chunk within the given region.
+#[inline]
+pub fn region_to_chunk(region_coords: IVec3) -> IVec3 {
+    region_coords << CHUNK_BITS
+}
+
+
+/// Gets the region of blocks that make up the given chunk.
+pub fn chunk_blocks(chunk_coords: IVec3) -> Region {
+    Region::from_size(chunk_to_block(chunk_coords), IVec3::splat(CHUNK_SIZE))
+}
+
+
+/// Gets the position of the given block within its chunk, or of the given
+/// chunk within its region.
+#[inline]
+pub fn local_coords(pos: IVec3) -> IVec3 {
+    pos & LOCAL_MASK
+}
+
+
+/// Gets the array index of the given block within its chunk, or of the given
+/// chunk within its region.
+///
+/// This is the same index that is returned by [`Region::chunk_index`].
+#[inline]
+pub fn local_index(pos: IVec3) -> usize {
+    Region::chunk_index(pos)
+}
+
+
+/// Gets the local coordinates that correspond to the given array index within
+/// a chunk. This is the inverse of [`local_index`].
+#[inline]
+pub fn index_to_local(index: usize) -> IVec3 {
+    let index = index as i32;
+    IVec3::new(
+        (index >> (CHUNK_BITS * 2)) & LOCAL_MASK,
+        (index >> CHUNK_BITS) & LOCAL_MASK,
+        index & LOCAL_MASK,
+    )
+}
+
+
+/// Divides the given position by the given divisor along each axis, rounding
+/// towards negative infinity.
+///
+/// This function panics if the divisor is <= 0.
+pub fn floor_div(pos: IVec3, divisor: i32) -> IVec3 {
+    if divisor <= 0 {
+        panic!("Cannot divide by a value <= 0. Found: {divisor}");
+    }
+
+    IVec3::new(
+        pos.x.div_euclid(divisor),
+        pos.y.div_euclid(divisor),
+        pos.z.div_euclid(divisor),
+    )
+}
+
+
+/// Gets the remainder of dividing the given position by the given divisor
+/// along each axis. The result is always within the range `0..divisor`.
+///
+/// This function panics if the divisor is <= 0.
+pub fn floor_mod(pos: IVec3, divisor: i32) -> IVec3 {
+    if divisor <= 0 {
+        panic!("Cannot divide by a value <= 0. Found: {divisor}");
+    }
+
+    IVec3::new(
+        pos.x.rem_euclid(divisor),
+        pos.y.rem_euclid(divisor),
+        pos.z.rem_euclid(divisor),
+    )
+}
+
+
+/// Gets the coordinates of the block that contains the given world-space
+/// position.
+#[inline]
+pub fn world_to_block(pos: Vec3) -> IVec3 {
+    pos.floor().as_ivec3()
+}
+
+
+/// Gets the coordinates of the chunk that contains the given world-space
+/// position.
+#[inline]
+pub fn world_to_chunk(pos: Vec3) -> IVec3 {
+    block_to_chunk(world_to_block(pos))
+}
+
+
+/// Gets the world-space position of the minimum corner of the given block.
+#[inline]
+pub fn block_to_world(block_pos: IVec3) -> Vec3 {
+    block_pos.as_vec3()
+}
+
+
+/// Gets the world-space position of the center of the given block.
+#[inline]
+pub fn block_center(block_pos: IVec3) -> Vec3 {
+    block_pos.as_vec3() + 0.5
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn negative_coordinates() {
+        assert_eq!(
+            block_to_chunk(IVec3::new(-1, -16, -17)),
+            IVec3::new(-1, -1, -2)
+        );
+        assert_eq!(block_to_chunk(IVec3::new(0, 15, 16)), IVec3::new(0, 0, 1));
+        assert_eq!(
+            block_to_region(IVec3::new(-1, 255, 256)),
+            IVec3::new(-1, 0, 1)
+        );
+        assert_eq!(
+            chunk_to_region(IVec3::new(-1, 15, 16)),
+            IVec3::new(-1, 0, 1)
+        );
+        assert_eq!(local_coords(IVec3::new(-1, -16, 17)), IVec3::new(15, 0, 1));
+    }
+
+
+    #[test]
+    fn conversions_agree() {
+        for block in Region::from_points(IVec3::splat(-40), IVec3::splat(40)).iter() {
+            let chunk = block_to_chunk(block);
+
+            assert_eq!(chunk, floor_div(block, CHUNK_SIZE));
+            assert_eq!(local_coords(block), floor_mod(block, CHUNK_SIZE));
+            assert_eq!(chunk_to_block(chunk) + local_coords(block), block);
+            assert_eq!(block_to_region(block), chunk_to_region(chunk));
+            assert!(chunk_blocks(chunk).contains_point(block));
+        }
+    }
+
+
+    #[test]
+    fn local_indices() {
+        for pos in Region::CHUNK.iter() {
+            let index = local_index(pos);
+            assert_eq!(index, Region::CHUNK.point_to_index(pos).unwrap());
+            assert_eq!(index_to_local(index), pos);
+        }
+    }
+
+
+    #[test]
+    fn world_space() {
+        assert_eq!(
+            world_to_block(Vec3::new(-0.5, 0.5, 16.0)),
+            IVec3::new(-1, 0, 16)
+        );
+        assert_eq!(
+            world_to_chunk(Vec3::new(-0.5, 0.5, 16.0)),
+            IVec3::new(-1, 0, 1)
+        );
+        assert_eq!(
+            block_center(IVec3::new(-1, 0, 2)),
+            Vec3::new(-0.5, 0.5, 2.5)
+        );
+        assert_eq!(
+            world_to_block(block_center(IVec3::new(-3, 7, -9))),
+            IVec3::new(-3, 7, -9)
+        );
+        assert_eq!(
+            block_to_world(IVec3::new(-3, 7, -9)),
+            Vec3::new(-3.0, 7.0, -9.0)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Defines the six axis-aligned directions of a grid, which also act as the six
+//! faces of a cuboid.
+
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// One of the six axis-aligned directions along a grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    /// The direction along the positive X axis.
+    PosX,
+
+    /// The direction along the negative X axis.
+    NegX,
+
+    /// The direction along the positive Y axis.
+    PosY,
+
+    /// The direction along the negative Y axis.
+    NegY,
+
+    /// The direction along the positive Z axis.
+    PosZ,
+
+    /// The direction along the negative Z axis.
+    NegZ,
+}
+
+impl Direction {
+    /// A list of all six directions.
+    pub const ALL: [Direction; 6] = [
+        Direction::PosX,
+        Direction::NegX,
+        Direction::PosY,
+        Direction::NegY,
+        Direction::PosZ,
+        Direction::NegZ,
+    ];
+
+
+    /// Gets the unit grid offset that points along this direction.
+    pub fn offset(&self) -> IVec3 {
+        match self {
+            Direction::PosX => IVec3::X,
+            Direction::NegX => IVec3::NEG_X,
+            Direction::PosY => IVec3::Y,
+            Direction::NegY => IVec3::NEG_Y,
+            Direction::PosZ => IVec3::Z,
+            Direction::NegZ => IVec3::NEG_Z,
+        }
+    }
+
+
+    /// Gets the direction pointing the opposite way along the same axis.
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::PosX => Direction::NegX,
+            Direction::NegX => Direction::PosX,
+            Direction::PosY => Direction::NegY,
+            Direction::NegY => Direction::PosY,
+            Direction::PosZ => Direction::NegZ,
+            Direction::NegZ => Direction::PosZ,
+        }
+    }
+
+
+    /// Gets the direction that most closely matches the given vector, based on
+    /// the axis with the largest magnitude.
+    ///
+    /// If the vector is zero or not finite, `None` is returned.
+    pub fn from_vec3(vec: Vec3) -> Option<Direction> {
+        if vec == Vec3::ZERO || !vec.is_finite() {
+            return None;
+        }
+
+        let abs = vec.abs();
+        let dir = if abs.x >= abs.y && abs.x >= abs.z {
+            if vec.x > 0.0 {
+                Direction::PosX
+            } else {
+                Direction::NegX
+            }
+        } else if abs.y >= abs.z {
+            if vec.y > 0.0 {
+                Direction::PosY
+            } else {
+                Direction::NegY
+            }
+        } else if vec.z > 0.0 {
+            Direction::PosZ
+        } else {
+            Direction::NegZ
+        };
+
+        Some(dir)
+    }
+
+
+    /// Gets whether or not this direction points along a positive axis.
+    pub fn is_positive(&self) -> bool {
+        matches!(self, Direction::PosX | Direction::PosY | Direction::PosZ)
+    }
+
+
+    /// Rotates this direction by the given number of quarter turns around the
+    /// Y axis. Positive turns are counter-clockwise when viewed from above.
+    ///
+    /// Directions along the Y axis are unchanged.
+    pub fn rotate_y(&self, quarter_turns: i32) -> Direction {
+        (0..quarter_turns.rem_euclid(4)).fold(*self, |dir, _| {
+            match dir {
+                Direction::PosX => Direction::NegZ,
+                Direction::NegZ => Direction::NegX,
+                Direction::NegX => Direction::PosZ,
+                Direction::PosZ => Direction::PosX,
+                other => other,
+            }
+        })
+    }
+
+
+    /// Rotates this direction by the given rotation, snapping the result to
+    /// the nearest direction.
+    pub fn rotate(&self, rotation: Quat) -> Direction {
+        Direction::from_vec3(rotation * self.offset().as_vec3()).unwrap_or(*self)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::f32::consts::FRAC_PI_2;
+
+
+    #[test]
+    fn opposite_offsets() {
+        for dir in Direction::ALL {
+            assert_eq!(dir.opposite().offset(), -dir.offset());
+            assert_eq!(dir.opposite().opposite(), dir);
+            assert_eq!(Direction::from_vec3(dir.offset().as_vec3()), Some(dir));
+        }
+    }
+
+
+    #[test]
+    fn from_vec3() {
+        assert_eq!(
+            Direction::from_vec3(Vec3::new(0.2, -3.0, 1.0)),
+            Some(Direction::NegY)
+        );
+        assert_eq!(
+            Direction::from_vec3(Vec3::new(0.2, 0.1, 1.0)),
+            Some(Direction::PosZ)
+        );
+        assert_eq!(Direction::from_vec3(Vec3::ZERO), None);
+    }
+
+
+    #[test]
+    fn rotation() {
+        let quat = Quat::from_rotation_y(FRAC_PI_2);
+        for dir in Direction::ALL {
+            assert_eq!(dir.rotate_y(1), dir.rotate(quat));
+            assert_eq!(dir.rotate_y(4), dir);
+            assert_eq!(dir.rotate_y(-1).rotate_y(1), dir);
+        }
+
+        assert_eq!(Direction::PosX.rotate_y(2), Direction::NegX);
+        assert_eq!(Direction::PosY.rotate_y(1), Direction::PosY);
+    }
+}
@@ -0,0 +1,155 @@
+//! A camera view frustum, used for checking whether or not objects are within
+//! view of a camera.
+
+
+use crate::prelude::{Aabb, Region};
+use bevy::prelude::*;
+
+
+/// A view frustum, defined by six planes that each face inwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// The planes of the frustum, in the order left, right, bottom, top, near,
+    /// and far. Each plane is stored with the normal within the XYZ components
+    /// and the distance within the W component.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Creates a new frustum from a camera view-projection matrix.
+    ///
+    /// The projection is expected to map depth into the range 0 to 1, as used
+    /// by Bevy. Reversed and infinite depth projections are also supported.
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        let row_x = view_projection.row(0);
+        let row_y = view_projection.row(1);
+        let row_z = view_projection.row(2);
+        let row_w = view_projection.row(3);
+
+        let normalize = |plane: Vec4| {
+            let length = plane.truncate().length();
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        };
+
+        let planes = [
+            normalize(row_w + row_x),
+            normalize(row_w - row_x),
+            normalize(row_w + row_y),
+            normalize(row_w - row_y),
+            normalize(row_z),
+            normalize(row_w - row_z),
+        ];
+
+        Self {
+            planes,
+        }
+    }
+
+
+    /// Gets the six planes of this frustum.
+    pub fn planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+
+    /// Checks if the given point is within this frustum.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+    }
+
+
+    /// Checks if the given bounding box is at least partially within this
+    /// frustum.
+    ///
+    /// This is a conservative test; boxes near the corners of the frustum may
+    /// be reported as visible even when they are just outside of it.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max(), aabb.min());
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+
+
+    /// Checks if the given block region is at least partially within this
+    /// frustum.
+    pub fn intersects_region(&self, region: &Region) -> bool {
+        self.intersects_aabb(&Aabb::from(*region))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+
+    fn camera() -> Frustum {
+        let projection = Mat4::perspective_rh(FRAC_PI_2, 1.0, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        Frustum::from_view_projection(projection * view)
+    }
+
+
+    #[test]
+    fn points() {
+        let frustum = camera();
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+        assert!(frustum.contains_point(Vec3::new(9.0, -9.0, -10.0)));
+        assert!(!frustum.contains_point(Vec3::new(11.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.05)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -150.0)));
+    }
+
+
+    #[test]
+    fn aabb_culling() {
+        let frustum = camera();
+
+        let ahead = Aabb::from_center(Vec3::new(0.0, 0.0, -20.0), Vec3::ONE);
+        let behind = Aabb::from_center(Vec3::new(0.0, 0.0, 20.0), Vec3::ONE);
+        let edge = Aabb::new(Vec3::new(9.0, 0.0, -9.0), Vec3::new(12.0, 1.0, -11.0));
+        let around = Aabb::from_center(Vec3::ZERO, Vec3::splat(500.0));
+
+        assert!(frustum.intersects_aabb(&ahead));
+        assert!(!frustum.intersects_aabb(&behind));
+        assert!(frustum.intersects_aabb(&edge));
+        assert!(frustum.intersects_aabb(&around));
+    }
+
+
+    #[test]
+    fn region_culling() {
+        let frustum = camera();
+
+        let visible = Region::from_size(IVec3::new(-8, -8, -32), IVec3::splat(16));
+        let hidden = Region::from_size(IVec3::new(-8, -8, 16), IVec3::splat(16));
+        let far = Region::from_size(IVec3::new(-8, -8, -256), IVec3::splat(16));
+        let left = Region::from_size(IVec3::new(-64, -8, -32), IVec3::splat(16));
+
+        assert!(frustum.intersects_region(&visible));
+        assert!(!frustum.intersects_region(&hidden));
+        assert!(!frustum.intersects_region(&far));
+        assert!(!frustum.intersects_region(&left));
+    }
+
+
+    #[test]
+    fn reversed_infinite_projection() {
+        let projection = Mat4::perspective_infinite_reverse_rh(FRAC_PI_2, 1.0, 0.1);
+        let frustum = Frustum::from_view_projection(projection);
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10000.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.05)));
+        assert!(frustum.intersects_region(&Region::from_size(IVec3::new(0, 0, -32), IVec3::ONE)));
+    }
+}
@@ -1,8 +1,10 @@
 //! A collection of useful coordinate iterators.
 
 
-use crate::prelude::Region;
+use crate::prelude::{block_to_chunk, Region, Region2};
 use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 
 /// An iterator for a cuboid grid of coordinates.
@@ -66,9 +68,385 @@ impl Iterator for CuboidIterator {
 }
 
 
+/// An iterator for a rectangular grid of column coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RectIterator {
+    /// The minimum corner point.
+    min: IVec2,
+
+    /// The maximum corner point.
+    max: IVec2,
+
+    /// The next coordinate value within the iterator.
+    next: Option<IVec2>,
+}
+
+impl RectIterator {
+    /// Creates a new rectangle iterator over all columns within a 2D region.
+    pub fn from(region: &Region2) -> Self {
+        Self {
+            min:  region.min(),
+            max:  region.max(),
+            next: Some(region.min()),
+        }
+    }
+}
+
+impl Iterator for RectIterator {
+    type Item = IVec2;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next?;
+
+        let mut value = next;
+        value.y += 1;
+        if value.y > self.max.y {
+            value.y = self.min.y;
+            value.x += 1;
+        }
+
+        self.next = match value.x > self.max.x {
+            true => None,
+            false => Some(value),
+        };
+
+        Some(next)
+    }
+}
+
+
+/// An iterator over only the boundary coordinates of a cuboid grid, skipping
+/// over all interior coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellIterator {
+    /// The cuboid iterator that is being filtered.
+    inner: CuboidIterator,
+}
+
+impl ShellIterator {
+    /// Creates a new shell iterator over the boundary of the given region.
+    pub fn from(region: &Region) -> Self {
+        Self {
+            inner: CuboidIterator::from(region),
+        }
+    }
+}
+
+impl Iterator for ShellIterator {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (min, max) = (self.inner.min, self.inner.max);
+        let next = self.inner.next()?;
+
+        let on_x = next.x == min.x || next.x == max.x;
+        let on_y = next.y == min.y || next.y == max.y;
+        if on_x || on_y || next.z == min.z || next.z == max.z {
+            return Some(next);
+        }
+
+        // This is the first interior coordinate of the row, so skip the rest of
+        // the interior and continue from the far side of the row.
+        self.inner.next = Some(IVec3::new(next.x, next.y, max.z));
+        self.inner.next()
+    }
+}
+
+
+/// An iterator over all coordinates of a grid that lie within a given radius
+/// of a center coordinate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SphereIterator {
+    /// The cuboid iterator over the bounding box of the sphere.
+    inner: CuboidIterator,
+
+    /// The center coordinate of the sphere.
+    center: IVec3,
+
+    /// The squared radius of the sphere.
+    radius_squared: f32,
+}
+
+impl SphereIterator {
+    /// Creates a new sphere iterator over all coordinates with a distance to
+    /// the center that is less than or equal to the given radius.
+    ///
+    /// This function panics if the radius is < 0.
+    pub fn new(center: IVec3, radius: f32) -> Self {
+        if radius < 0.0 {
+            panic!("Cannot create a sphere with a radius < 0. Found: {radius}");
+        }
+
+        let extent = IVec3::splat(radius.floor() as i32);
+        let bounds = Region::from_points(center - extent, center + extent);
+
+        Self {
+            inner: CuboidIterator::from(&bounds),
+            center,
+            radius_squared: radius * radius,
+        }
+    }
+
+
+    /// Creates a new sphere iterator over the coordinates of all chunks within
+    /// the given radius, in chunks, of the chunk containing the given block
+    /// position.
+    ///
+    /// Half a chunk is added to the radius so that a radius of 0 contains a
+    /// single chunk and the chunks along each axis are always included.
+    pub fn chunks(block_pos: IVec3, radius: u16) -> Self {
+        Self::new(block_to_chunk(block_pos), radius as f32 + 0.5)
+    }
+}
+
+impl Iterator for SphereIterator {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find(|pos| {
+            let offset = (*pos - self.center).as_vec3();
+            offset.length_squared() <= self.radius_squared
+        })
+    }
+}
+
+
+/// An iterator over all coordinates of a region, ordered from nearest to
+/// farthest from a focal point.
+///
+/// Coordinates are gathered one cube shell at a time, expanding outwards from
+/// the focal point, and are only yielded once no unvisited shell could contain
+/// a nearer coordinate. This avoids collecting and sorting the entire region.
+#[derive(Debug, Clone)]
+pub struct NearestIterator {
+    /// The region being iterated over.
+    region: Region,
+
+    /// The focal point to measure distances from.
+    center: IVec3,
+
+    /// The radius of the next cube shell to gather.
+    ring: i32,
+
+    /// The radius of the largest cube shell that overlaps the region.
+    max_ring: i32,
+
+    /// The gathered coordinates that have not yet been yielded, ordered by
+    /// their squared distance to the focal point.
+    queue: BinaryHeap<Reverse<(i64, [i32; 3])>>,
+}
+
+impl NearestIterator {
+    /// Creates a new nearest-first iterator over the given region, measuring
+    /// distances from the given focal point.
+    ///
+    /// The focal point does not need to be within the region.
+    pub fn new(region: &Region, center: IVec3) -> Self {
+        let far = (region.min() - center).abs().max((region.max() - center).abs());
+
+        Self {
+            region: *region,
+            center,
+            ring: 0,
+            max_ring: far.max_element(),
+            queue: BinaryHeap::new(),
+        }
+    }
+
+
+    /// Gathers all coordinates within the next cube shell that lie within the
+    /// region.
+    fn gather_ring(&mut self) {
+        let shell = Region::from_points(self.center - self.ring, self.center + self.ring);
+
+        if self.region.overlaps(&shell) {
+            for pos in ShellIterator::from(&shell).filter(|p| self.region.contains_point(*p)) {
+                let offset = (pos - self.center).to_array().map(|v| v as i64);
+                let dist = offset.iter().map(|v| v * v).sum();
+                self.queue.push(Reverse((dist, pos.to_array())));
+            }
+        }
+
+        self.ring += 1;
+    }
+}
+
+impl Iterator for NearestIterator {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Ungathered coordinates are at least `ring` units away.
+            let limit = self.ring as i64 * self.ring as i64;
+            let exhausted = self.ring > self.max_ring;
+
+            if let Some(Reverse((dist, pos))) = self.queue.peek() {
+                if exhausted || *dist <= limit {
+                    let pos = IVec3::from_array(*pos);
+                    self.queue.pop();
+                    return Some(pos);
+                }
+            } else if exhausted {
+                return None;
+            }
+
+            self.gather_ring();
+        }
+    }
+}
+
+
+/// An iterator over the integer coordinates of a straight line between two
+/// points, including both end points.
+///
+/// By default, the line is traced using Bresenham's algorithm, which visits a
+/// single coordinate along the longest axis per step and may move diagonally
+/// between coordinates. In supercover mode, every step moves along a single
+/// axis, so no diagonal gaps are left within the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIterator {
+    /// The next coordinate to be yielded.
+    current: [i32; 3],
+
+    /// The direction to step along each axis, either -1, 0, or 1.
+    step: [i32; 3],
+
+    /// The absolute distance to travel along each axis.
+    delta: [i32; 3],
+
+    /// The accumulated error along each axis for Bresenham traversal, or the
+    /// number of steps taken along each axis for supercover traversal.
+    progress: [i32; 3],
+
+    /// The axis with the largest distance to travel.
+    major: usize,
+
+    /// The number of coordinates remaining after the next coordinate.
+    remaining: i32,
+
+    /// Whether or not this line uses supercover traversal.
+    supercover: bool,
+
+    /// Whether or not the final coordinate has been yielded.
+    done: bool,
+}
+
+impl LineIterator {
+    /// Creates a new line iterator from the start point to the end point,
+    /// using Bresenham's algorithm.
+    pub fn new(start: IVec3, end: IVec3) -> Self {
+        let delta = (end - start).abs().to_array();
+        let major = (0..3).fold(0, |m, i| if delta[i] > delta[m] { i } else { m });
+        let progress = delta.map(|d| 2 * d - delta[major]);
+
+        Self {
+            current: start.to_array(),
+            step: (end - start).signum().to_array(),
+            delta,
+            progress,
+            major,
+            remaining: delta[major],
+            supercover: false,
+            done: false,
+        }
+    }
+
+
+    /// Creates a new line iterator from the start point to the end point,
+    /// using supercover traversal.
+    pub fn supercover(start: IVec3, end: IVec3) -> Self {
+        let delta = (end - start).abs().to_array();
+
+        Self {
+            current: start.to_array(),
+            step: (end - start).signum().to_array(),
+            delta,
+            progress: [0; 3],
+            major: 0,
+            remaining: delta.iter().sum(),
+            supercover: true,
+            done: false,
+        }
+    }
+
+
+    /// Advances the current coordinate by a single Bresenham step.
+    fn bresenham_step(&mut self) {
+        let major = self.major;
+
+        for axis in (0..3).filter(|a| *a != major) {
+            if self.progress[axis] >= 0 {
+                self.current[axis] += self.step[axis];
+                self.progress[axis] -= 2 * self.delta[major];
+            }
+
+            self.progress[axis] += 2 * self.delta[axis];
+        }
+
+        self.current[major] += self.step[major];
+    }
+
+
+    /// Advances the current coordinate by a single supercover step, moving
+    /// along the axis whose next cell boundary is nearest along the line.
+    fn supercover_step(&mut self) {
+        // The boundary along an axis is crossed at the fraction
+        // (2k + 1) / (2d) of the line, where k is the number of steps already
+        // taken along the axis.
+        let crossing = |axis: usize| {
+            (
+                2 * self.progress[axis] as i64 + 1,
+                2 * self.delta[axis] as i64,
+            )
+        };
+
+        let axis = (0..3)
+            .filter(|a| self.progress[*a] < self.delta[*a])
+            .reduce(|a, b| {
+                let (na, da) = crossing(a);
+                let (nb, db) = crossing(b);
+                if nb * da < na * db {
+                    b
+                } else {
+                    a
+                }
+            })
+            .unwrap();
+
+        self.current[axis] += self.step[axis];
+        self.progress[axis] += 1;
+    }
+}
+
+impl Iterator for LineIterator {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let next = IVec3::from_array(self.current);
+        if self.remaining == 0 {
+            self.done = true;
+            return Some(next);
+        }
+
+        self.remaining -= 1;
+        match self.supercover {
+            true => self.supercover_step(),
+            false => self.bresenham_step(),
+        }
+
+        Some(next)
+    }
+}
+
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use bevy::utils::HashSet;
 
 
     #[test]
@@ -83,4 +461,125 @@ mod test {
         assert_eq!(iter.next(), Some(IVec3::new(0, 0, 3)));
         assert_eq!(iter.next(), None);
     }
+
+
+    #[test]
+    fn shell_skips_interior() {
+        let region = Region::from_points(IVec3::new(-2, 0, 1), IVec3::new(2, 3, 5));
+        let shell: Vec<IVec3> = ShellIterator::from(&region).collect();
+
+        let inner = region.shrink(IVec3::ONE).unwrap();
+        assert_eq!(shell.len(), region.count() - inner.count());
+        assert!(shell.iter().all(|pos| region.contains_point(*pos)));
+        assert!(shell.iter().all(|pos| !inner.contains_point(*pos)));
+
+        let unique: HashSet<IVec3> = shell.iter().copied().collect();
+        assert_eq!(unique.len(), shell.len());
+    }
+
+
+    #[test]
+    fn thin_shell() {
+        let region = Region::from_points(IVec3::new(0, 0, 0), IVec3::new(3, 1, 3));
+        assert_eq!(ShellIterator::from(&region).count(), region.count());
+    }
+
+
+    #[test]
+    fn sphere_within_radius() {
+        let center = IVec3::new(4, -2, 7);
+        let cells: Vec<IVec3> = SphereIterator::new(center, 3.0).collect();
+
+        assert!(cells.contains(&center));
+        assert!(cells.contains(&(center + IVec3::new(3, 0, 0))));
+        assert!(!cells.contains(&(center + IVec3::new(3, 1, 0))));
+        assert!(cells.iter().all(|pos| (*pos - center).as_vec3().length() <= 3.0));
+
+        let cube = Region::from_points(center - 3, center + 3);
+        let expected = cube.iter().filter(|pos| (*pos - center).as_vec3().length() <= 3.0);
+        assert_eq!(cells.len(), expected.count());
+    }
+
+
+    #[test]
+    fn chunk_sphere() {
+        let chunks: Vec<IVec3> = SphereIterator::chunks(IVec3::new(40, 3, -5), 0).collect();
+        assert_eq!(chunks, vec![IVec3::new(2, 0, -1)]);
+
+        let chunks = SphereIterator::chunks(IVec3::ZERO, 4).count();
+        let cube = Region::from_points(IVec3::splat(-4), IVec3::splat(4)).count();
+        assert!(chunks < cube * 2 / 3);
+    }
+
+
+    #[test]
+    fn nearest_first() {
+        let region = Region::from_points(IVec3::new(-3, 0, -2), IVec3::new(4, 2, 5));
+        let center = IVec3::new(1, 1, 1);
+
+        let cells: Vec<IVec3> = NearestIterator::new(&region, center).collect();
+        assert_eq!(cells.len(), region.count());
+        assert_eq!(cells[0], center);
+
+        let dist = |pos: &IVec3| (*pos - center).as_vec3().length_squared();
+        assert!(cells.windows(2).all(|w| dist(&w[0]) <= dist(&w[1])));
+
+        let unique: HashSet<IVec3> = cells.iter().copied().collect();
+        assert_eq!(unique.len(), cells.len());
+    }
+
+
+    #[test]
+    fn nearest_outside_focus() {
+        let region = Region::from_points(IVec3::new(10, 0, 0), IVec3::new(12, 1, 1));
+        let cells: Vec<IVec3> = NearestIterator::new(&region, IVec3::ZERO).collect();
+
+        assert_eq!(cells.len(), region.count());
+        assert_eq!(cells[0], IVec3::new(10, 0, 0));
+        assert_eq!(cells.last(), Some(&IVec3::new(12, 1, 1)));
+    }
+
+
+    #[test]
+    fn bresenham_line() {
+        let start = IVec3::new(-2, 3, 1);
+        let end = IVec3::new(7, -1, 4);
+        let line: Vec<IVec3> = LineIterator::new(start, end).collect();
+
+        assert_eq!(line.len(), 10);
+        assert_eq!(line.first(), Some(&start));
+        assert_eq!(line.last(), Some(&end));
+        assert!(line.windows(2).all(|w| (w[1] - w[0]).abs().max_element() == 1));
+        assert!(line.windows(2).all(|w| w[1].x - w[0].x == 1));
+    }
+
+
+    #[test]
+    fn supercover_line() {
+        let start = IVec3::new(0, 0, 0);
+        let end = IVec3::new(3, -5, 2);
+        let line: Vec<IVec3> = LineIterator::supercover(start, end).collect();
+
+        assert_eq!(line.len(), 11);
+        assert_eq!(line.first(), Some(&start));
+        assert_eq!(line.last(), Some(&end));
+
+        for w in line.windows(2) {
+            let offset = (w[1] - w[0]).abs();
+            assert_eq!(offset.x + offset.y + offset.z, 1);
+        }
+    }
+
+
+    #[test]
+    fn single_point_line() {
+        let point = IVec3::new(4, 4, 4);
+        assert_eq!(LineIterator::new(point, point).collect::<Vec<_>>(), vec![
+            point
+        ]);
+        assert_eq!(
+            LineIterator::supercover(point, point).collect::<Vec<_>>(),
+            vec![point]
+        );
+    }
 }
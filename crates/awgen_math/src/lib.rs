@@ -6,12 +6,32 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+pub mod aabb;
+pub mod coords;
+pub mod direction;
+pub mod frustum;
 pub mod iterators;
+pub mod noise;
+pub mod random;
+pub mod raycast;
 pub mod region;
+pub mod region2;
+pub mod rotation;
+pub mod transform;
 
 
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
+    pub use super::aabb::*;
+    pub use super::coords::*;
+    pub use super::direction::*;
+    pub use super::frustum::*;
     pub use super::iterators::*;
+    pub use super::noise::*;
+    pub use super::random::*;
+    pub use super::raycast::*;
     pub use super::region::*;
+    pub use super::region2::*;
+    pub use super::rotation::*;
+    pub use super::transform::*;
 }
@@ -0,0 +1,364 @@
+//! Deterministic, seeded coherent noise functions for procedural generation.
+//!
+//! All noise functions implement the [`NoiseFn`] trait, allowing them to be
+//! freely combined. For example, fractal noise may be layered on top of Perlin
+//! noise, and then warped by another noise function.
+
+
+use crate::prelude::Seed;
+use bevy::prelude::*;
+
+
+/// The set of gradient vectors used by [`PerlinNoise`], pointing from the
+/// center of a cube to each of its 12 edges.
+const GRADIENTS: [Vec3; 12] = [
+    Vec3::new(1.0, 1.0, 0.0),
+    Vec3::new(-1.0, 1.0, 0.0),
+    Vec3::new(1.0, -1.0, 0.0),
+    Vec3::new(-1.0, -1.0, 0.0),
+    Vec3::new(1.0, 0.0, 1.0),
+    Vec3::new(-1.0, 0.0, 1.0),
+    Vec3::new(1.0, 0.0, -1.0),
+    Vec3::new(-1.0, 0.0, -1.0),
+    Vec3::new(0.0, 1.0, 1.0),
+    Vec3::new(0.0, -1.0, 1.0),
+    Vec3::new(0.0, 1.0, -1.0),
+    Vec3::new(0.0, -1.0, -1.0),
+];
+
+
+/// A coherent noise function that can be sampled at any point in space.
+pub trait NoiseFn: Send + Sync {
+    /// Samples this noise function at the given position. The returned value
+    /// is roughly within the range `-1.0..=1.0`.
+    fn sample(&self, pos: Vec3) -> f32;
+
+
+    /// Samples this noise function at the given 2D position, such as a
+    /// horizontal column position within the world.
+    fn sample_2d(&self, pos: Vec2) -> f32 {
+        self.sample(Vec3::new(pos.x, 0.0, pos.y))
+    }
+}
+
+impl<N: NoiseFn + ?Sized> NoiseFn for &N {
+    fn sample(&self, pos: Vec3) -> f32 {
+        (**self).sample(pos)
+    }
+}
+
+impl<N: NoiseFn + ?Sized> NoiseFn for Box<N> {
+    fn sample(&self, pos: Vec3) -> f32 {
+        (**self).sample(pos)
+    }
+}
+
+
+/// The quintic fade curve used to smooth interpolation between lattice points.
+#[inline]
+fn fade(t: Vec3) -> Vec3 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+
+/// Trilinearly interpolates between the eight corner values of a unit cube.
+///
+/// The corners are ordered by their X, Y, then Z offsets, where bit 2 of the
+/// index is the X offset, bit 1 is the Y offset, and bit 0 is the Z offset.
+#[inline]
+fn trilerp(corners: [f32; 8], t: Vec3) -> f32 {
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(corners[0b000], corners[0b100], t.x);
+    let x01 = lerp(corners[0b001], corners[0b101], t.x);
+    let x10 = lerp(corners[0b010], corners[0b110], t.x);
+    let x11 = lerp(corners[0b011], corners[0b111], t.x);
+    let y0 = lerp(x00, x10, t.y);
+    let y1 = lerp(x01, x11, t.y);
+    lerp(y0, y1, t.z)
+}
+
+
+/// Gets the lattice cell containing the given position, alongside the local
+/// position within that cell.
+#[inline]
+fn lattice(pos: Vec3) -> (IVec3, Vec3) {
+    let cell = pos.floor();
+    (cell.as_ivec3(), pos - cell)
+}
+
+
+/// Gets the offset of the cube corner with the given index. See [`trilerp`]
+/// for the corner ordering.
+#[inline]
+fn corner(index: usize) -> IVec3 {
+    IVec3::new(
+        (index >> 2) as i32 & 1,
+        (index >> 1) as i32 & 1,
+        index as i32 & 1,
+    )
+}
+
+
+/// Classic gradient noise, as described by Ken Perlin, using a seeded hash in
+/// place of a permutation table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerlinNoise {
+    /// The seed of this noise function.
+    seed: Seed,
+}
+
+impl PerlinNoise {
+    /// Creates a new Perlin noise function with the given seed.
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            seed,
+        }
+    }
+}
+
+impl NoiseFn for PerlinNoise {
+    fn sample(&self, pos: Vec3) -> f32 {
+        let (cell, local) = lattice(pos);
+
+        let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|index| {
+            let offset = corner(index);
+            let hash = self.seed.derive_pos(cell + offset).0;
+            let gradient = GRADIENTS[(hash % GRADIENTS.len() as u64) as usize];
+            gradient.dot(local - offset.as_vec3())
+        });
+
+        trilerp(corners, fade(local))
+    }
+}
+
+
+/// Value noise, which smoothly interpolates between random values assigned to
+/// each lattice point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueNoise {
+    /// The seed of this noise function.
+    seed: Seed,
+}
+
+impl ValueNoise {
+    /// Creates a new value noise function with the given seed.
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            seed,
+        }
+    }
+}
+
+impl NoiseFn for ValueNoise {
+    fn sample(&self, pos: Vec3) -> f32 {
+        let (cell, local) = lattice(pos);
+
+        let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|index| {
+            let value = self.seed.derive_pos(cell + corner(index)).rng().next_f32();
+            value * 2.0 - 1.0
+        });
+
+        trilerp(corners, fade(local))
+    }
+}
+
+
+/// Fractal Brownian motion, which layers multiple octaves of a noise function
+/// at increasing frequencies and decreasing amplitudes.
+#[derive(Debug, Clone)]
+pub struct Fbm<N: NoiseFn> {
+    /// The noise function that is sampled for each octave.
+    source: N,
+
+    /// The number of octaves to layer.
+    octaves: u32,
+
+    /// The frequency of the first octave.
+    frequency: f32,
+
+    /// The frequency multiplier applied to each successive octave.
+    lacunarity: f32,
+
+    /// The amplitude multiplier applied to each successive octave.
+    persistence: f32,
+}
+
+impl<N: NoiseFn> Fbm<N> {
+    /// Creates a new fractal noise function from the given source noise, with
+    /// four octaves, a frequency of 1, a lacunarity of 2, and a persistence of
+    /// 0.5.
+    pub fn new(source: N) -> Self {
+        Self {
+            source,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+
+
+    /// Sets the number of octaves to layer.
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+
+    /// Sets the frequency of the first octave.
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+
+    /// Sets the frequency multiplier applied to each successive octave.
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+
+    /// Sets the amplitude multiplier applied to each successive octave.
+    pub fn with_persistence(mut self, persistence: f32) -> Self {
+        self.persistence = persistence;
+        self
+    }
+}
+
+impl<N: NoiseFn> NoiseFn for Fbm<N> {
+    fn sample(&self, pos: Vec3) -> f32 {
+        let mut total = 0.0;
+        let mut max_amplitude = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = self.frequency;
+
+        for octave in 0..self.octaves {
+            // Offset each octave so that lattice points do not line up.
+            let offset = Vec3::splat(octave as f32 * 31.7);
+            total += self.source.sample(pos * frequency + offset) * amplitude;
+            max_amplitude += amplitude;
+            amplitude *= self.persistence;
+            frequency *= self.lacunarity;
+        }
+
+        if max_amplitude > 0.0 {
+            total / max_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+
+/// Distorts the input position of a noise function using another noise
+/// function, creating more organic, swirling patterns.
+#[derive(Debug, Clone)]
+pub struct DomainWarp<N: NoiseFn, W: NoiseFn> {
+    /// The noise function that is sampled at the warped position.
+    source: N,
+
+    /// The noise function used to offset the input position.
+    warp: W,
+
+    /// The maximum distance that the input position may be offset by.
+    strength: f32,
+}
+
+impl<N: NoiseFn, W: NoiseFn> DomainWarp<N, W> {
+    /// Creates a new domain warp, where the given source noise is sampled at
+    /// a position offset by the warp noise, scaled by the given strength.
+    pub fn new(source: N, warp: W, strength: f32) -> Self {
+        Self {
+            source,
+            warp,
+            strength,
+        }
+    }
+}
+
+impl<N: NoiseFn, W: NoiseFn> NoiseFn for DomainWarp<N, W> {
+    fn sample(&self, pos: Vec3) -> f32 {
+        // Sample the warp at distant offsets to get independent values per axis.
+        let offset = Vec3::new(
+            self.warp.sample(pos),
+            self.warp.sample(pos + Vec3::new(5.2, 1.3, 7.1)),
+            self.warp.sample(pos + Vec3::new(9.7, 4.6, 2.8)),
+        );
+
+        self.source.sample(pos + offset * self.strength)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::Region;
+    use pretty_assertions::assert_eq;
+
+
+    fn sample_points() -> impl Iterator<Item = Vec3> {
+        (0..500).map(|i| {
+            let i = i as f32;
+            Vec3::new(i * 0.173 - 40.0, i * 0.051 - 10.0, i * -0.097 + 3.0)
+        })
+    }
+
+
+    #[test]
+    fn deterministic() {
+        let a = Fbm::new(PerlinNoise::new(Seed(42)));
+        let b = Fbm::new(PerlinNoise::new(Seed(42)));
+        let c = Fbm::new(PerlinNoise::new(Seed(43)));
+
+        for pos in sample_points() {
+            assert_eq!(a.sample(pos), b.sample(pos));
+        }
+
+        assert!(sample_points().any(|pos| a.sample(pos) != c.sample(pos)));
+    }
+
+
+    #[test]
+    fn perlin_is_zero_on_lattice() {
+        let noise = PerlinNoise::new(Seed(1));
+        for pos in Region::from_points(IVec3::splat(-3), IVec3::splat(3)).iter() {
+            assert_eq!(noise.sample(pos.as_vec3()), 0.0);
+        }
+    }
+
+
+    #[test]
+    fn within_range() {
+        let noises: [Box<dyn NoiseFn>; 4] = [
+            Box::new(PerlinNoise::new(Seed(5))),
+            Box::new(ValueNoise::new(Seed(5))),
+            Box::new(Fbm::new(ValueNoise::new(Seed(5))).with_octaves(6)),
+            Box::new(DomainWarp::new(
+                PerlinNoise::new(Seed(5)),
+                ValueNoise::new(Seed(6)),
+                4.0,
+            )),
+        ];
+
+        for noise in noises {
+            for pos in sample_points() {
+                let value = noise.sample(pos);
+                assert!((-1.5..=1.5).contains(&value), "Out of range: {value}");
+            }
+
+            assert!(sample_points().any(|pos| noise.sample(pos) != 0.0));
+        }
+    }
+
+
+    #[test]
+    fn continuous() {
+        let noise = Fbm::new(PerlinNoise::new(Seed(9))).with_frequency(0.1);
+        for pos in sample_points() {
+            let delta = (noise.sample(pos) - noise.sample(pos + 0.001)).abs();
+            assert!(delta < 0.01, "Discontinuity at {pos}: {delta}");
+        }
+    }
+}
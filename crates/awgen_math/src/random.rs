@@ -0,0 +1,245 @@
+//! Deterministic, seeded random number generation and hashing utilities.
+//!
+//! All values produced within this module depend only on the inputs given, and
+//! are identical across runs, platforms, and compiler versions. A single world
+//! seed may be split into sub-seeds for individual chunks, block positions, or
+//! purposes, so that each consumer receives an independent and reproducible
+//! random stream.
+
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The golden ratio constant used to advance the SplitMix64 state.
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+
+/// Mixes the bits of the given value into a well distributed 64-bit hash,
+/// using the SplitMix64 finalizer.
+#[inline]
+pub fn mix_u64(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+
+/// Hashes the given string into a 64-bit value, using FNV-1a.
+///
+/// Unlike the standard library hasher, this hash is stable across platforms
+/// and compiler versions.
+pub fn hash_str(value: &str) -> u64 {
+    value.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+
+/// A seed value for deterministic random number generation.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect, FromReflect, Serialize, Deserialize,
+)]
+pub struct Seed(pub u64);
+
+impl Seed {
+    /// Creates a new seed from the hash of the given text. This is useful for
+    /// allowing users to enter world seeds as words.
+    ///
+    /// If the text is a valid integer, it is used as the seed value directly.
+    pub fn from_text(text: &str) -> Self {
+        match text.trim().parse::<i64>() {
+            Ok(value) => Seed(value as u64),
+            Err(_) => Seed(hash_str(text)),
+        }
+    }
+
+
+    /// Creates a new sub-seed that is derived from this seed and the given
+    /// value.
+    pub fn derive(&self, value: u64) -> Seed {
+        Seed(mix_u64(self.0 ^ mix_u64(value.wrapping_add(GOLDEN_GAMMA))))
+    }
+
+
+    /// Creates a new sub-seed that is derived from this seed and the given
+    /// purpose name, such as "terrain" or "ores".
+    ///
+    /// This allows for multiple independent random streams to be created
+    /// from a single world seed.
+    pub fn derive_str(&self, purpose: &str) -> Seed {
+        self.derive(hash_str(purpose))
+    }
+
+
+    /// Creates a new sub-seed that is derived from this seed and the given
+    /// grid position.
+    pub fn derive_pos(&self, pos: IVec3) -> Seed {
+        self.derive(pos.x as u32 as u64)
+            .derive(pos.y as u32 as u64)
+            .derive(pos.z as u32 as u64)
+    }
+
+
+    /// Creates a new sub-seed for the chunk at the given chunk coordinates.
+    ///
+    /// This is equivalent to [`Seed::derive_pos`], but is kept separate to
+    /// make the intent clear at the call site.
+    pub fn for_chunk(&self, chunk_coords: IVec3) -> Seed {
+        self.derive_pos(chunk_coords)
+    }
+
+
+    /// Creates a new random number generator from this seed.
+    pub fn rng(&self) -> SeededRng {
+        SeededRng::new(*self)
+    }
+}
+
+
+/// A small, fast, deterministic random number generator, based on SplitMix64.
+///
+/// This generator is not cryptographically secure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRng {
+    /// The current state of the generator.
+    state: u64,
+}
+
+impl SeededRng {
+    /// Creates a new random number generator from the given seed.
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            state: seed.0,
+        }
+    }
+
+
+    /// Generates a random 64-bit value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix_u64(self.state)
+    }
+
+
+    /// Generates a random 32-bit value.
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+
+    /// Generates a random float within the range `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+
+    /// Generates a random double within the range `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+
+    /// Generates a random integer within the range `min..max`.
+    ///
+    /// This function panics if `min >= max`.
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        if min >= max {
+            panic!("Cannot generate a value within an empty range. Found: {min}..{max}");
+        }
+
+        let span = (max as i64 - min as i64) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+
+    /// Generates a random float within the range `min..max`.
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+
+    /// Returns true with the given probability, between 0 and 1.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+
+    /// Picks a random element from the given slice, or `None` if the slice is
+    /// empty.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+
+        items.get((self.next_u64() % items.len() as u64) as usize)
+    }
+
+
+    /// Shuffles the given slice in place.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn deterministic() {
+        let seed = Seed(12345);
+        let a: Vec<u64> = (0..8).scan(seed.rng(), |rng, _| Some(rng.next_u64())).collect();
+        let b: Vec<u64> = (0..8).scan(seed.rng(), |rng, _| Some(rng.next_u64())).collect();
+
+        assert_eq!(a, b);
+        assert_eq!(seed.rng().next_u64(), 0x2211_8258_A9D1_11A0);
+        assert_eq!(hash_str("awgen"), 0x361B_D49D_A24F_7961);
+    }
+
+
+    #[test]
+    fn derived_seeds_differ() {
+        let seed = Seed::from_text("awgen");
+
+        assert_eq!(seed, Seed(hash_str("awgen")));
+        assert_eq!(Seed::from_text("-1"), Seed(u64::MAX));
+        assert_ne!(seed.derive_str("terrain"), seed.derive_str("ores"));
+        assert_ne!(
+            seed.for_chunk(IVec3::new(1, 0, 0)),
+            seed.for_chunk(IVec3::new(0, 1, 0))
+        );
+        assert_ne!(seed.for_chunk(IVec3::ZERO), seed.for_chunk(IVec3::NEG_ONE));
+        assert_eq!(
+            seed.derive_pos(IVec3::new(5, -2, 9)),
+            seed.derive_pos(IVec3::new(5, -2, 9))
+        );
+    }
+
+
+    #[test]
+    fn ranges() {
+        let mut rng = Seed(7).rng();
+
+        for _ in 0..1000 {
+            let value = rng.range_i32(-3, 4);
+            assert!((-3..4).contains(&value));
+
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+        }
+
+        let mut items = [1, 2, 3, 4, 5, 6];
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(rng.pick::<u8>(&[]), None);
+    }
+}
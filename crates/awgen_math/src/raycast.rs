@@ -0,0 +1,186 @@
+//! A voxel grid traversal iterator, based on the algorithm described by
+//! Amanatides and Woo, for walking the cells along a ray.
+
+
+use crate::prelude::Direction;
+use bevy::prelude::*;
+
+
+/// A single cell that was visited by a grid raycast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastStep {
+    /// The coordinates of the visited cell.
+    pub cell: IVec3,
+
+    /// The face of the cell that the ray entered through. This is `None` for
+    /// the cell containing the ray origin.
+    pub face: Option<Direction>,
+
+    /// The distance along the ray at which the cell was entered.
+    pub distance: f32,
+}
+
+
+/// An iterator that walks every grid cell that is intersected by a ray, in
+/// order, up to a maximum distance.
+///
+/// Each grid cell is a unit cube, with the cell `(x, y, z)` covering the area
+/// from `(x, y, z)` to `(x + 1, y + 1, z + 1)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridRaycast {
+    /// The next cell to be visited.
+    cell: IVec3,
+
+    /// The direction to step along each axis, either -1, 0, or 1.
+    step: IVec3,
+
+    /// The distance along the ray at which the next cell boundary along each
+    /// axis is crossed.
+    t_max: Vec3,
+
+    /// The distance along the ray that is required to cross a full cell along
+    /// each axis.
+    t_delta: Vec3,
+
+    /// The face that the next cell is entered through.
+    face: Option<Direction>,
+
+    /// The distance along the ray at which the next cell is entered.
+    distance: f32,
+
+    /// The maximum distance along the ray to travel.
+    max_distance: f32,
+}
+
+impl GridRaycast {
+    /// Creates a new grid raycast starting at the given origin and traveling
+    /// along the given direction for up to the given distance.
+    ///
+    /// The direction does not need to be normalized. If the direction is zero,
+    /// only the cell containing the origin is visited.
+    pub fn new(origin: Vec3, direction: Vec3, max_distance: f32) -> Self {
+        let direction = direction.normalize_or_zero();
+        let cell = origin.floor().as_ivec3();
+        let sign = |v: f32| (v > 0.0) as i32 - (v < 0.0) as i32;
+        let step = IVec3::new(sign(direction.x), sign(direction.y), sign(direction.z));
+
+        let axis = |o: f32, d: f32, c: i32, s: i32| -> (f32, f32) {
+            if s == 0 {
+                return (f32::INFINITY, f32::INFINITY);
+            }
+
+            let boundary = if s > 0 { c as f32 + 1.0 } else { c as f32 };
+            ((boundary - o) / d, 1.0 / d.abs())
+        };
+
+        let (tx, dx) = axis(origin.x, direction.x, cell.x, step.x);
+        let (ty, dy) = axis(origin.y, direction.y, cell.y, step.y);
+        let (tz, dz) = axis(origin.z, direction.z, cell.z, step.z);
+
+        Self {
+            cell,
+            step,
+            t_max: Vec3::new(tx, ty, tz),
+            t_delta: Vec3::new(dx, dy, dz),
+            face: None,
+            distance: 0.0,
+            max_distance,
+        }
+    }
+}
+
+impl Iterator for GridRaycast {
+    type Item = RaycastStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.distance > self.max_distance {
+            return None;
+        }
+
+        let current = RaycastStep {
+            cell:     self.cell,
+            face:     self.face,
+            distance: self.distance,
+        };
+
+        if self.t_max.x < self.t_max.y && self.t_max.x < self.t_max.z {
+            self.cell.x += self.step.x;
+            self.distance = self.t_max.x;
+            self.t_max.x += self.t_delta.x;
+            self.face = Some(if self.step.x > 0 { Direction::NegX } else { Direction::PosX });
+        } else if self.t_max.y < self.t_max.z {
+            self.cell.y += self.step.y;
+            self.distance = self.t_max.y;
+            self.t_max.y += self.t_delta.y;
+            self.face = Some(if self.step.y > 0 { Direction::NegY } else { Direction::PosY });
+        } else {
+            self.cell.z += self.step.z;
+            self.distance = self.t_max.z;
+            self.t_max.z += self.t_delta.z;
+            self.face = Some(if self.step.z > 0 { Direction::NegZ } else { Direction::PosZ });
+        }
+
+        Some(current)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn straight_line() {
+        let cells: Vec<RaycastStep> =
+            GridRaycast::new(Vec3::new(0.5, 0.5, 0.5), Vec3::X, 3.0).collect();
+
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[0].cell, IVec3::ZERO);
+        assert_eq!(cells[0].face, None);
+        assert_eq!(cells[1].cell, IVec3::X);
+        assert_eq!(cells[1].face, Some(Direction::NegX));
+        assert_eq!(cells[1].distance, 0.5);
+        assert_eq!(cells[3].cell, IVec3::new(3, 0, 0));
+    }
+
+
+    #[test]
+    fn negative_direction() {
+        let cells: Vec<IVec3> = GridRaycast::new(Vec3::new(0.5, 0.5, 0.5), Vec3::NEG_Y, 2.0)
+            .map(|step| step.cell)
+            .collect();
+
+        assert_eq!(cells, vec![IVec3::ZERO, IVec3::NEG_Y, IVec3::new(0, -2, 0)]);
+    }
+
+
+    #[test]
+    fn diagonal_is_connected() {
+        let origin = Vec3::new(0.2, 0.7, 0.4);
+        let steps: Vec<RaycastStep> =
+            GridRaycast::new(origin, Vec3::new(1.0, -0.6, 0.3), 10.0).collect();
+
+        for pair in steps.windows(2) {
+            let offset = pair[1].cell - pair[0].cell;
+            let face = pair[1].face.unwrap();
+            assert_eq!(offset.abs().max_element(), 1);
+            assert_eq!(offset.abs().x + offset.abs().y + offset.abs().z, 1);
+            assert_eq!(offset, -face.offset());
+            assert!(pair[0].distance <= pair[1].distance);
+        }
+
+        assert!(steps.last().unwrap().distance <= 10.0);
+    }
+
+
+    #[test]
+    fn zero_direction() {
+        let steps: Vec<RaycastStep> =
+            GridRaycast::new(Vec3::new(-0.5, 2.0, 3.9), Vec3::ZERO, 5.0).collect();
+
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].cell, IVec3::new(-1, 2, 3));
+    }
+}
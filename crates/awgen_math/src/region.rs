@@ -1,7 +1,9 @@
 //! A region defines a cuboid boundary of blocks along a uniform, 3D grid.
 
 
-use crate::prelude::CuboidIterator;
+use crate::prelude::{
+    block_to_chunk, chunk_blocks, CuboidIterator, Direction, NearestIterator, ShellIterator
+};
 use anyhow::{bail, Result};
 use bevy::prelude::*;
 use std::fmt::Display;
@@ -76,7 +78,7 @@ impl Region {
 
 
     /// Gets whether or not the given point is within this region.
-    pub fn contains(&self, point: IVec3) -> bool {
+    pub fn contains_point(&self, point: IVec3) -> bool {
         let p = point - self.pos;
 
         p.x >= 0
@@ -88,17 +90,172 @@ impl Region {
     }
 
 
+    /// Gets whether or not the given region is entirely within this region.
+    pub fn contains_region(&self, other: &Region) -> bool {
+        self.contains_point(other.min()) && self.contains_point(other.max())
+    }
+
+
+    /// Gets whether or not this region shares at least one point with the
+    /// given region.
+    pub fn overlaps(&self, other: &Region) -> bool {
+        let min = self.min().max(other.min());
+        let max = self.max().min(other.max());
+        min.cmple(max).all()
+    }
+
+
+    /// Gets the region of points that are shared between this region and the
+    /// given region.
+    ///
+    /// If the two regions do not overlap, `None` is returned.
+    pub fn intersect(&self, other: &Region) -> Option<Region> {
+        let min = self.min().max(other.min());
+        let max = self.max().min(other.max());
+
+        match min.cmple(max).all() {
+            true => Some(Region::from_points(min, max)),
+            false => None,
+        }
+    }
+
+
+    /// Gets the smallest region that contains both this region and the given
+    /// region.
+    pub fn union_bounds(&self, other: &Region) -> Region {
+        Region::from_points(self.min().min(other.min()), self.max().max(other.max()))
+    }
+
+
+    /// Creates a new region that is grown outwards by the given amount along
+    /// each side of each axis.
+    ///
+    /// This function panics if the amount is < 0 along any axis.
+    pub fn expand(&self, amount: IVec3) -> Region {
+        if amount.cmplt(IVec3::ZERO).any() {
+            panic!("Cannot expand a region by a negative amount. Found: {amount}");
+        }
+
+        Region::from_points(self.min() - amount, self.max() + amount)
+    }
+
+
+    /// Creates a new region that is shrunk inwards by the given amount along
+    /// each side of each axis.
+    ///
+    /// If the region would be shrunk to a size <= 0 along any axis, `None` is
+    /// returned. This function panics if the amount is < 0 along any axis.
+    pub fn shrink(&self, amount: IVec3) -> Option<Region> {
+        if amount.cmplt(IVec3::ZERO).any() {
+            panic!("Cannot shrink a region by a negative amount. Found: {amount}");
+        }
+
+        let size = self.size - amount * 2;
+        match size.cmpgt(IVec3::ZERO).all() {
+            true => Some(Region::from_size(self.pos + amount, size)),
+            false => None,
+        }
+    }
+
+
+    /// Creates a new region that is grown outwards until each corner lies on a
+    /// multiple of the given granularity along each axis.
+    ///
+    /// The returned region is the smallest region that contains this region
+    /// and that starts and ends on a granularity boundary. For example,
+    /// aligning to a granularity of 16 returns the region of all chunks that
+    /// this region overlaps. This function panics if the granularity is <= 0
+    /// along any axis.
+    pub fn align_to(&self, granularity: IVec3) -> Region {
+        if granularity.cmple(IVec3::ZERO).any() {
+            panic!("Cannot align a region to a granularity <= 0. Found: {granularity}");
+        }
+
+        let floor = |v: IVec3| {
+            IVec3::new(
+                v.x.div_euclid(granularity.x),
+                v.y.div_euclid(granularity.y),
+                v.z.div_euclid(granularity.z),
+            ) * granularity
+        };
+
+        Region::from_points(floor(self.min()), floor(self.max()) + granularity - 1)
+    }
+
+
+    /// Gets the region of chunk coordinates for all chunks that this region
+    /// overlaps.
+    pub fn chunks(&self) -> Region {
+        Region::from_points(block_to_chunk(self.min()), block_to_chunk(self.max()))
+    }
+
+
+    /// Splits this region along chunk boundaries, creating an iterator over
+    /// the coordinates of each chunk that this region overlaps, alongside the
+    /// part of this region that lies within that chunk.
+    pub fn split_by_chunks(&self) -> impl Iterator<Item = (IVec3, Region)> {
+        let region = *self;
+        self.chunks().into_iter().map(move |chunk_coords| {
+            (
+                chunk_coords,
+                region.intersect(&chunk_blocks(chunk_coords)).unwrap(),
+            )
+        })
+    }
+
+
     /// Contains a position within this region into a unique array index.
     ///
     /// If the given point is not within this region, an error is returned.
     pub fn point_to_index(&self, point: IVec3) -> Result<usize> {
-        if !self.contains(point) {
+        if !self.contains_point(point) {
             bail!("Point is outside of region: {point}, Region: {self}");
         }
 
+        Ok(self.point_to_index_unchecked(point))
+    }
+
+
+    /// Converts a position within this region into a unique array index, or
+    /// returns `None` if the point is not within this region.
+    #[inline]
+    pub fn get_index(&self, point: IVec3) -> Option<usize> {
+        if !self.contains_point(point) {
+            return None;
+        }
+
+        Some(self.point_to_index_unchecked(point))
+    }
+
+
+    /// Converts a position within this region into a unique array index,
+    /// without checking if the point is within this region.
+    ///
+    /// If the point is outside of this region, the returned index is not
+    /// meaningful, and may be out of bounds of an array of this region's size.
+    #[inline]
+    pub fn point_to_index_unchecked(&self, point: IVec3) -> usize {
+        debug_assert!(
+            self.contains_point(point),
+            "Point is outside of region: {point}"
+        );
+
         let p = point - self.pos;
         let index = p.x * self.size.y * self.size.z + p.y * self.size.z + p.z;
-        Ok(index as usize)
+        index as usize
+    }
+
+
+    /// Converts a local block position within a chunk into an array index for
+    /// the [`Region::CHUNK`] region.
+    ///
+    /// Only the lowest four bits of each axis are used, so any block position
+    /// may be given and will wrap into the chunk. This is equivalent to calling
+    /// `Region::CHUNK.point_to_index(point & 15)`, but cannot fail.
+    #[inline]
+    pub fn chunk_index(point: IVec3) -> usize {
+        let p = point & 15;
+        ((p.x << 8) | (p.y << 4) | p.z) as usize
     }
 
 
@@ -108,6 +265,41 @@ impl Region {
     }
 
 
+    /// Creates a new iterator over only the boundary points of this region.
+    pub fn iter_shell(&self) -> ShellIterator {
+        ShellIterator::from(self)
+    }
+
+
+    /// Creates a new iterator over all points of this region, ordered from
+    /// nearest to farthest from the given focal point.
+    pub fn iter_sorted_by_distance(&self, center: IVec3) -> NearestIterator {
+        NearestIterator::new(self, center)
+    }
+
+
+    /// Gets the one element thick slice of this region that lies along the
+    /// face in the given direction.
+    pub fn face(&self, direction: Direction) -> Region {
+        let (min, max) = (self.min(), self.max());
+        match direction {
+            Direction::PosX => Region::from_points(IVec3::new(max.x, min.y, min.z), max),
+            Direction::NegX => Region::from_points(min, IVec3::new(min.x, max.y, max.z)),
+            Direction::PosY => Region::from_points(IVec3::new(min.x, max.y, min.z), max),
+            Direction::NegY => Region::from_points(min, IVec3::new(max.x, min.y, max.z)),
+            Direction::PosZ => Region::from_points(IVec3::new(min.x, min.y, max.z), max),
+            Direction::NegZ => Region::from_points(min, IVec3::new(max.x, max.y, min.z)),
+        }
+    }
+
+
+    /// Creates a new iterator over all points of this region that lie along
+    /// the face in the given direction.
+    pub fn iter_faces(&self, direction: Direction) -> CuboidIterator {
+        CuboidIterator::from(&self.face(direction))
+    }
+
+
     /// Gets the number of elements within this region.
     pub fn count(&self) -> usize {
         (self.size.x * self.size.y * self.size.z) as usize
@@ -151,4 +343,158 @@ mod test {
         assert_eq!(indices.iter().min(), Some(0).as_ref());
         assert_eq!(indices.iter().max(), Some(region.count() - 1).as_ref());
     }
+
+
+    #[test]
+    fn index_variants_agree() {
+        let region = Region::from_points(IVec3::new(-3, 5, 0), IVec3::new(2, 9, 4));
+
+        for pos in region.iter() {
+            let index = region.point_to_index(pos).unwrap();
+            assert_eq!(region.get_index(pos), Some(index));
+            assert_eq!(region.point_to_index_unchecked(pos), index);
+        }
+
+        assert_eq!(region.get_index(IVec3::new(3, 5, 0)), None);
+        assert!(region.point_to_index(IVec3::new(3, 5, 0)).is_err());
+    }
+
+
+    #[test]
+    fn chunk_index() {
+        for pos in Region::CHUNK.iter() {
+            let index = Region::CHUNK.point_to_index(pos).unwrap();
+            assert_eq!(Region::chunk_index(pos), index);
+            assert_eq!(Region::chunk_index(pos + IVec3::new(-32, 16, 48)), index);
+        }
+    }
+
+
+    #[test]
+    fn align_to() {
+        let region = Region::from_points(IVec3::new(-3, 0, 17), IVec3::new(5, 15, 40));
+
+        assert_eq!(
+            region.align_to(IVec3::splat(16)),
+            Region::from_points(IVec3::new(-16, 0, 16), IVec3::new(15, 15, 47))
+        );
+        assert_eq!(
+            region.align_to(IVec3::new(1, 4, 8)),
+            Region::from_points(IVec3::new(-3, 0, 16), IVec3::new(5, 15, 47))
+        );
+        assert_eq!(region.align_to(IVec3::ONE), region);
+    }
+
+
+    #[test]
+    fn split_by_chunks() {
+        let region = Region::from_points(IVec3::new(-3, 4, 10), IVec3::new(20, 5, 17));
+        let parts: Vec<(IVec3, Region)> = region.split_by_chunks().collect();
+
+        assert_eq!(parts.len(), region.chunks().count());
+        assert_eq!(parts.len(), 6);
+        assert_eq!(
+            parts.iter().map(|(_, part)| part.count()).sum::<usize>(),
+            region.count()
+        );
+        assert_eq!(
+            parts[0],
+            (
+                IVec3::new(-1, 0, 0),
+                Region::from_points(IVec3::new(-3, 4, 10), IVec3::new(-1, 5, 15))
+            )
+        );
+
+        for (chunk_coords, part) in parts {
+            assert!(region.contains_region(&part));
+            assert_eq!(part.min() >> 4, chunk_coords);
+            assert_eq!(part.max() >> 4, chunk_coords);
+        }
+    }
+
+
+    #[test]
+    fn contains() {
+        let region = Region::from_points(IVec3::new(-2, 0, 1), IVec3::new(3, 4, 5));
+
+        assert!(region.contains_point(IVec3::new(-2, 0, 1)));
+        assert!(region.contains_point(IVec3::new(3, 4, 5)));
+        assert!(!region.contains_point(IVec3::new(4, 4, 5)));
+
+        let inner = Region::from_points(IVec3::new(-1, 1, 2), IVec3::new(3, 3, 3));
+        let outer = Region::from_points(IVec3::new(-1, 1, 2), IVec3::new(4, 3, 3));
+        assert!(region.contains_region(&region));
+        assert!(region.contains_region(&inner));
+        assert!(!region.contains_region(&outer));
+        assert!(!inner.contains_region(&region));
+    }
+
+
+    #[test]
+    fn intersect_and_overlap() {
+        let a = Region::from_points(IVec3::new(0, 0, 0), IVec3::new(4, 4, 4));
+        let b = Region::from_points(IVec3::new(3, -2, 4), IVec3::new(8, 1, 9));
+        let c = Region::from_points(IVec3::new(5, 0, 0), IVec3::new(6, 4, 4));
+
+        assert!(a.overlaps(&b));
+        assert!(b.overlaps(&a));
+        assert!(!a.overlaps(&c));
+
+        let expected = Region::from_points(IVec3::new(3, 0, 4), IVec3::new(4, 1, 4));
+        assert_eq!(a.intersect(&b), Some(expected));
+        assert_eq!(b.intersect(&a), Some(expected));
+        assert_eq!(a.intersect(&c), None);
+    }
+
+
+    #[test]
+    fn union_bounds() {
+        let a = Region::from_points(IVec3::new(0, 0, 0), IVec3::new(1, 1, 1));
+        let b = Region::from_points(IVec3::new(5, -3, 2), IVec3::new(6, -2, 8));
+
+        let union = a.union_bounds(&b);
+        assert_eq!(
+            union,
+            Region::from_points(IVec3::new(0, -3, 0), IVec3::new(6, 1, 8))
+        );
+        assert!(union.contains_region(&a));
+        assert!(union.contains_region(&b));
+    }
+
+
+    #[test]
+    fn expand_and_shrink() {
+        let region = Region::from_points(IVec3::new(0, 0, 0), IVec3::new(4, 4, 4));
+
+        let expanded = region.expand(IVec3::new(1, 0, 2));
+        assert_eq!(expanded.min(), IVec3::new(-1, 0, -2));
+        assert_eq!(expanded.max(), IVec3::new(5, 4, 6));
+
+        assert_eq!(expanded.shrink(IVec3::new(1, 0, 2)), Some(region));
+        assert_eq!(
+            region.shrink(IVec3::splat(2)),
+            Some(Region::from_points(IVec3::splat(2), IVec3::splat(2)))
+        );
+        assert_eq!(region.shrink(IVec3::new(0, 3, 0)), None);
+    }
+
+
+    #[test]
+    fn faces() {
+        let region = Region::from_points(IVec3::new(-1, 2, 0), IVec3::new(3, 4, 1));
+
+        for face in Direction::ALL {
+            let slice = region.face(face);
+            assert!(region.contains_region(&slice));
+            assert_eq!(region.iter_faces(face).count(), slice.count());
+
+            let mut outside = slice.iter().map(|pos| pos + face.offset());
+            assert!(outside.all(|pos| !region.contains_point(pos)));
+        }
+
+        assert_eq!(
+            region.face(Direction::PosY),
+            Region::from_points(IVec3::new(-1, 4, 0), IVec3::new(3, 4, 1))
+        );
+    }
 }
@@ -0,0 +1,223 @@
+//! A 2D region defines a rectangular boundary of block columns along the
+//! horizontal plane of a uniform, 3D grid.
+
+
+use crate::prelude::{RectIterator, Region};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use std::fmt::Display;
+
+
+/// A rectangular region defining a collection of columns within a 3D grid, such
+/// as for heightmaps or biome maps.
+///
+/// Columns are addressed by their X and Z coordinates, which are stored within
+/// the X and Y components of an [`IVec2`] respectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region2 {
+    /// The position of the region.
+    pos: IVec2,
+
+    /// The size of the region.
+    size: IVec2,
+}
+
+impl Region2 {
+    /// A region that contains the columns of a single chunk located at the
+    /// position (0, 0).
+    pub const CHUNK: Region2 = Region2 {
+        pos:  IVec2::ZERO,
+        size: IVec2::new(16, 16),
+    };
+
+
+    /// Creates a new region from two column positions.
+    ///
+    /// Each position is an opposite corner of the region.
+    pub fn from_points(a: IVec2, b: IVec2) -> Self {
+        let min = a.min(b);
+        let max = a.max(b);
+        let size = max - min + 1;
+
+        Self {
+            pos: min,
+            size,
+        }
+    }
+
+
+    /// Creates a new region from a column position and a size.
+    ///
+    /// The position is the lowest point along the X and Z axis'.
+    ///
+    /// This function panics if the size is <= 0 along either axis.
+    pub fn from_size(pos: IVec2, size: IVec2) -> Self {
+        if size.x <= 0 || size.y <= 0 {
+            panic!("Cannot a region with a size <= 0. Found: {size}");
+        }
+
+        Self {
+            pos,
+            size,
+        }
+    }
+
+
+    /// Gets the minimum corner of this region.
+    pub fn min(&self) -> IVec2 {
+        self.pos
+    }
+
+
+    /// Gets the maximum corner of this region.
+    pub fn max(&self) -> IVec2 {
+        self.pos + self.size - 1
+    }
+
+
+    /// Gets the size of this region.
+    pub fn size(&self) -> IVec2 {
+        self.size
+    }
+
+
+    /// Checks if the given column is within this region.
+    pub fn contains_point(&self, point: IVec2) -> bool {
+        point.cmpge(self.min()).all() && point.cmple(self.max()).all()
+    }
+
+
+    /// Converts a column position within this region into a unique array
+    /// index.
+    ///
+    /// If the given column is not within this region, an error is returned.
+    pub fn point_to_index(&self, point: IVec2) -> Result<usize> {
+        if !self.contains_point(point) {
+            bail!("Point is outside of region: {point}, Region: {self}");
+        }
+
+        Ok(self.point_to_index_unchecked(point))
+    }
+
+
+    /// Converts a column position within this region into a unique array
+    /// index, or returns `None` if the column is not within this region.
+    #[inline]
+    pub fn get_index(&self, point: IVec2) -> Option<usize> {
+        if !self.contains_point(point) {
+            return None;
+        }
+
+        Some(self.point_to_index_unchecked(point))
+    }
+
+
+    /// Converts a column position within this region into a unique array
+    /// index, without checking if the column is within this region.
+    #[inline]
+    pub fn point_to_index_unchecked(&self, point: IVec2) -> usize {
+        debug_assert!(
+            self.contains_point(point),
+            "Point is outside of region: {point}"
+        );
+
+        let p = point - self.pos;
+        (p.x * self.size.y + p.y) as usize
+    }
+
+
+    /// Converts an array index back into the column position within this
+    /// region that it refers to, or `None` if the index is out of bounds.
+    pub fn index_to_point(&self, index: usize) -> Option<IVec2> {
+        if index >= self.count() {
+            return None;
+        }
+
+        let index = index as i32;
+        Some(self.pos + IVec2::new(index / self.size.y, index % self.size.y))
+    }
+
+
+    /// Creates a 3D region that spans all blocks of the columns within this
+    /// region, between the given minimum and maximum heights, inclusive.
+    pub fn with_height(&self, min_y: i32, max_y: i32) -> Region {
+        let min = self.min();
+        let max = self.max();
+        Region::from_points(
+            IVec3::new(min.x, min_y, min.y),
+            IVec3::new(max.x, max_y, max.y),
+        )
+    }
+
+
+    /// Creates a new iterator over all columns within this region.
+    pub fn iter(&self) -> RectIterator {
+        RectIterator::from(self)
+    }
+
+
+    /// Gets the number of columns within this region.
+    pub fn count(&self) -> usize {
+        (self.size.x * self.size.y) as usize
+    }
+}
+
+impl From<Region> for Region2 {
+    fn from(region: Region) -> Self {
+        let min = region.min();
+        let max = region.max();
+        Region2::from_points(IVec2::new(min.x, min.z), IVec2::new(max.x, max.z))
+    }
+}
+
+impl IntoIterator for Region2 {
+    type Item = IVec2;
+    type IntoIter = RectIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        RectIterator::from(&self)
+    }
+}
+
+impl Display for Region2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Pos: {}, Size: {})", self.pos, self.size)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn index_round_trip() {
+        let region = Region2::from_points(IVec2::new(-5, 3), IVec2::new(2, -1));
+        let columns: Vec<IVec2> = region.iter().collect();
+
+        assert_eq!(columns.len(), region.count());
+        assert_eq!(region.count(), 8 * 5);
+
+        for (index, column) in columns.into_iter().enumerate() {
+            assert_eq!(region.point_to_index(column).unwrap(), index);
+            assert_eq!(region.index_to_point(index), Some(column));
+        }
+
+        assert_eq!(region.get_index(IVec2::new(3, 0)), None);
+        assert_eq!(region.index_to_point(region.count()), None);
+    }
+
+
+    #[test]
+    fn matches_3d_region() {
+        let region = Region::from_points(IVec3::new(-2, 0, 4), IVec3::new(3, 10, 9));
+        let columns = Region2::from(region);
+
+        assert_eq!(columns.min(), IVec2::new(-2, 4));
+        assert_eq!(columns.max(), IVec2::new(3, 9));
+        assert_eq!(columns.with_height(0, 10), region);
+        assert_eq!(Region2::from(Region::CHUNK), Region2::CHUNK);
+    }
+}
@@ -0,0 +1,266 @@
+//! Defines the 24 axis-aligned orientations that a block may be rotated into.
+
+
+use crate::prelude::Direction;
+use bevy::prelude::*;
+
+
+/// One of the 24 rotations that map the grid axes onto themselves.
+///
+/// A rotation is defined by the directions that the positive X and positive Y
+/// axes are rotated to. The positive Z axis is always perpendicular to both,
+/// following the right-hand rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockRotation {
+    /// The direction that the positive X axis is rotated to.
+    x: Direction,
+
+    /// The direction that the positive Y axis is rotated to.
+    y: Direction,
+}
+
+impl BlockRotation {
+    /// The identity rotation, which leaves all directions unchanged.
+    pub const IDENTITY: BlockRotation = BlockRotation {
+        x: Direction::PosX,
+        y: Direction::PosY,
+    };
+
+
+    /// Creates a new rotation from the directions that the positive X and
+    /// positive Y axes are rotated to.
+    ///
+    /// If the two directions are not perpendicular, `None` is returned.
+    pub fn from_axes(x: Direction, y: Direction) -> Option<BlockRotation> {
+        if x.offset().dot(y.offset()) != 0 {
+            return None;
+        }
+
+        Some(BlockRotation {
+            x,
+            y,
+        })
+    }
+
+
+    /// Creates a new rotation by the given number of quarter turns around the Y
+    /// axis. Positive turns are counter-clockwise when viewed from above,
+    /// matching [`Direction::rotate_y`].
+    pub fn from_y_turns(quarter_turns: i32) -> BlockRotation {
+        BlockRotation {
+            x: Direction::PosX.rotate_y(quarter_turns),
+            y: Direction::PosY,
+        }
+    }
+
+
+    /// Creates a new rotation from the given quaternion, snapping it to the
+    /// nearest axis-aligned rotation.
+    ///
+    /// If the quaternion is not close enough to an axis-aligned rotation to
+    /// resolve unambiguously, `None` is returned.
+    pub fn from_quat(rotation: Quat) -> Option<BlockRotation> {
+        let x = Direction::from_vec3(rotation * Vec3::X)?;
+        let y = Direction::from_vec3(rotation * Vec3::Y)?;
+        BlockRotation::from_axes(x, y)
+    }
+
+
+    /// Creates a new rotation from its unique index, within the range `0..24`,
+    /// as returned by [`BlockRotation::index`].
+    pub fn from_index(index: u8) -> Option<BlockRotation> {
+        BlockRotation::all().nth(index as usize)
+    }
+
+
+    /// Creates an iterator over all 24 rotations, ordered by their index.
+    pub fn all() -> impl Iterator<Item = BlockRotation> {
+        Direction::ALL.into_iter().flat_map(|x| {
+            Direction::ALL.into_iter().filter_map(move |y| BlockRotation::from_axes(x, y))
+        })
+    }
+
+
+    /// Gets the unique index of this rotation, within the range `0..24`. This
+    /// is useful for storing a rotation within block metadata.
+    pub fn index(&self) -> u8 {
+        let x_index = Direction::ALL.iter().position(|d| *d == self.x).unwrap();
+        let y_index = Direction::ALL
+            .iter()
+            .filter(|d| d.offset().dot(self.x.offset()) == 0)
+            .position(|d| *d == self.y)
+            .unwrap();
+
+        (x_index * 4 + y_index) as u8
+    }
+
+
+    /// Gets the direction that the positive X axis is rotated to.
+    pub fn x_axis(&self) -> Direction {
+        self.x
+    }
+
+
+    /// Gets the direction that the positive Y axis is rotated to.
+    pub fn y_axis(&self) -> Direction {
+        self.y
+    }
+
+
+    /// Gets the direction that the positive Z axis is rotated to.
+    pub fn z_axis(&self) -> Direction {
+        let z = self.x.offset().as_vec3().cross(self.y.offset().as_vec3());
+        Direction::from_vec3(z).unwrap()
+    }
+
+
+    /// Rotates the given direction.
+    pub fn apply_direction(&self, direction: Direction) -> Direction {
+        match direction {
+            Direction::PosX => self.x,
+            Direction::NegX => self.x.opposite(),
+            Direction::PosY => self.y,
+            Direction::NegY => self.y.opposite(),
+            Direction::PosZ => self.z_axis(),
+            Direction::NegZ => self.z_axis().opposite(),
+        }
+    }
+
+
+    /// Rotates the given grid coordinates around the origin.
+    pub fn apply_ivec3(&self, vec: IVec3) -> IVec3 {
+        self.x.offset() * vec.x + self.y.offset() * vec.y + self.z_axis().offset() * vec.z
+    }
+
+
+    /// Rotates the given vector around the origin.
+    pub fn apply_vec3(&self, vec: Vec3) -> Vec3 {
+        self.to_mat3() * vec
+    }
+
+
+    /// Gets the rotation that applies this rotation, followed by the given
+    /// rotation.
+    pub fn then(&self, other: BlockRotation) -> BlockRotation {
+        BlockRotation {
+            x: other.apply_direction(self.x),
+            y: other.apply_direction(self.y),
+        }
+    }
+
+
+    /// Gets the rotation that undoes this rotation.
+    pub fn inverse(&self) -> BlockRotation {
+        // The inverse of a rotation matrix is its transpose.
+        let (x, y, z) = (self.x.offset(), self.y.offset(), self.z_axis().offset());
+        let row = |i: usize| Direction::from_vec3(IVec3::new(x[i], y[i], z[i]).as_vec3()).unwrap();
+
+        BlockRotation {
+            x: row(0),
+            y: row(1),
+        }
+    }
+
+
+    /// Gets the rotation matrix of this rotation.
+    pub fn to_mat3(&self) -> Mat3 {
+        Mat3::from_cols(
+            self.x.offset().as_vec3(),
+            self.y.offset().as_vec3(),
+            self.z_axis().offset().as_vec3(),
+        )
+    }
+
+
+    /// Gets the quaternion of this rotation.
+    pub fn to_quat(&self) -> Quat {
+        Quat::from_mat3(&self.to_mat3())
+    }
+}
+
+impl Default for BlockRotation {
+    fn default() -> Self {
+        BlockRotation::IDENTITY
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::f32::consts::FRAC_PI_2;
+
+
+    #[test]
+    fn indices() {
+        let all: Vec<BlockRotation> = BlockRotation::all().collect();
+        assert_eq!(all.len(), 24);
+
+        for (index, rotation) in all.iter().enumerate() {
+            assert_eq!(rotation.index(), index as u8);
+            assert_eq!(BlockRotation::from_index(index as u8), Some(*rotation));
+        }
+
+        assert_eq!(BlockRotation::IDENTITY.index(), 0);
+        assert_eq!(BlockRotation::from_index(24), None);
+    }
+
+
+    #[test]
+    fn application() {
+        let point = IVec3::new(3, -1, 2);
+        for rotation in BlockRotation::all() {
+            let rotated = rotation.apply_ivec3(point);
+            assert_eq!(rotation.apply_vec3(point.as_vec3()), rotated.as_vec3());
+            assert_eq!(
+                rotation.to_quat().mul_vec3(point.as_vec3()).round(),
+                rotated.as_vec3()
+            );
+
+            for dir in Direction::ALL {
+                assert_eq!(
+                    rotation.apply_direction(dir).offset(),
+                    rotation.apply_ivec3(dir.offset())
+                );
+            }
+
+            assert_eq!(BlockRotation::from_quat(rotation.to_quat()), Some(rotation));
+        }
+    }
+
+
+    #[test]
+    fn composition_and_inverse() {
+        for a in BlockRotation::all() {
+            assert_eq!(a.then(a.inverse()), BlockRotation::IDENTITY);
+            assert_eq!(a.inverse().then(a), BlockRotation::IDENTITY);
+
+            for b in BlockRotation::all() {
+                let point = IVec3::new(1, 2, 3);
+                assert_eq!(
+                    a.then(b).apply_ivec3(point),
+                    b.apply_ivec3(a.apply_ivec3(point))
+                );
+            }
+        }
+    }
+
+
+    #[test]
+    fn y_turns() {
+        let quat = Quat::from_rotation_y(FRAC_PI_2);
+        assert_eq!(
+            BlockRotation::from_quat(quat),
+            Some(BlockRotation::from_y_turns(1))
+        );
+        assert_eq!(BlockRotation::from_y_turns(4), BlockRotation::IDENTITY);
+
+        for dir in Direction::ALL {
+            assert_eq!(
+                BlockRotation::from_y_turns(3).apply_direction(dir),
+                dir.rotate_y(3)
+            );
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Rotation and mirroring transforms for orienting blocks and regions of
+//! blocks, such as when placing structures or pasting copied areas.
+
+
+use crate::prelude::{Direction, Region};
+use bevy::prelude::*;
+
+
+/// An axis that a block transform may be mirrored across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Mirror {
+    /// No mirroring is applied.
+    #[default]
+    None,
+
+    /// The X coordinate is negated.
+    X,
+
+    /// The Y coordinate is negated.
+    Y,
+
+    /// The Z coordinate is negated.
+    Z,
+}
+
+
+/// A transformation of block coordinates, made up of a mirror followed by a
+/// rotation in 90 degree steps around the Y axis.
+///
+/// Block coordinates are transformed around the block at the origin, so that
+/// the block at `(0, 0, 0)` always remains in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BlockTransform {
+    /// The number of counter-clockwise quarter turns around the Y axis, when
+    /// viewed from above, within the range `0..4`.
+    quarter_turns: i32,
+
+    /// The axis that coordinates are mirrored across before rotating.
+    mirror: Mirror,
+}
+
+impl BlockTransform {
+    /// The identity transform, which leaves all coordinates unchanged.
+    pub const IDENTITY: BlockTransform = BlockTransform {
+        quarter_turns: 0,
+        mirror:        Mirror::None,
+    };
+
+
+    /// Creates a new block transform that mirrors coordinates across the
+    /// given axis and then rotates them by the given number of quarter turns.
+    ///
+    /// Positive turns are counter-clockwise when viewed from above, matching
+    /// [`Direction::rotate_y`].
+    pub fn new(quarter_turns: i32, mirror: Mirror) -> Self {
+        Self {
+            quarter_turns: quarter_turns.rem_euclid(4),
+            mirror,
+        }
+    }
+
+
+    /// Creates a new block transform that only rotates coordinates by the
+    /// given number of quarter turns.
+    pub fn rotation(quarter_turns: i32) -> Self {
+        Self::new(quarter_turns, Mirror::None)
+    }
+
+
+    /// Creates a new block transform that only mirrors coordinates across the
+    /// given axis.
+    pub fn mirror(mirror: Mirror) -> Self {
+        Self::new(0, mirror)
+    }
+
+
+    /// Gets the number of counter-clockwise quarter turns of this transform,
+    /// within the range `0..4`.
+    pub fn quarter_turns(&self) -> i32 {
+        self.quarter_turns
+    }
+
+
+    /// Gets the mirror axis of this transform.
+    pub fn mirror_axis(&self) -> Mirror {
+        self.mirror
+    }
+
+
+    /// Gets the transform that undoes this transform.
+    pub fn inverse(&self) -> BlockTransform {
+        match self.mirror {
+            // Mirroring across a horizontal axis reverses the direction of
+            // rotation, so the transform is its own inverse.
+            Mirror::X | Mirror::Z => *self,
+            Mirror::None | Mirror::Y => Self::new(-self.quarter_turns, self.mirror),
+        }
+    }
+
+
+    /// Transforms the given block coordinates.
+    pub fn apply_point(&self, point: IVec3) -> IVec3 {
+        let point = match self.mirror {
+            Mirror::None => point,
+            Mirror::X => IVec3::new(-point.x, point.y, point.z),
+            Mirror::Y => IVec3::new(point.x, -point.y, point.z),
+            Mirror::Z => IVec3::new(point.x, point.y, -point.z),
+        };
+
+        match self.quarter_turns {
+            1 => IVec3::new(point.z, point.y, -point.x),
+            2 => IVec3::new(-point.x, point.y, -point.z),
+            3 => IVec3::new(-point.z, point.y, point.x),
+            _ => point,
+        }
+    }
+
+
+    /// Transforms the given block coordinates around the given pivot block,
+    /// rather than around the origin.
+    pub fn apply_point_around(&self, point: IVec3, pivot: IVec3) -> IVec3 {
+        self.apply_point(point - pivot) + pivot
+    }
+
+
+    /// Transforms the given direction, such as the facing of a block.
+    pub fn apply_direction(&self, direction: Direction) -> Direction {
+        let direction = match (self.mirror, direction) {
+            (Mirror::X, Direction::PosX | Direction::NegX)
+            | (Mirror::Y, Direction::PosY | Direction::NegY)
+            | (Mirror::Z, Direction::PosZ | Direction::NegZ) => direction.opposite(),
+            _ => direction,
+        };
+
+        direction.rotate_y(self.quarter_turns)
+    }
+
+
+    /// Transforms the given region, returning the region that contains all of
+    /// the transformed block coordinates.
+    pub fn apply_region(&self, region: &Region) -> Region {
+        Region::from_points(
+            self.apply_point(region.min()),
+            self.apply_point(region.max()),
+        )
+    }
+
+
+    /// Transforms the given region around the given pivot block, rather than
+    /// around the origin.
+    pub fn apply_region_around(&self, region: &Region, pivot: IVec3) -> Region {
+        Region::from_points(
+            self.apply_point_around(region.min(), pivot),
+            self.apply_point_around(region.max(), pivot),
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    fn all_transforms() -> impl Iterator<Item = BlockTransform> {
+        [Mirror::None, Mirror::X, Mirror::Y, Mirror::Z]
+            .into_iter()
+            .flat_map(|mirror| (0..4).map(move |turns| BlockTransform::new(turns, mirror)))
+    }
+
+
+    #[test]
+    fn rotation() {
+        let quarter = BlockTransform::rotation(1);
+
+        assert_eq!(
+            quarter.apply_point(IVec3::new(1, 2, 0)),
+            IVec3::new(0, 2, -1)
+        );
+        assert_eq!(
+            quarter.apply_point(IVec3::new(0, 2, 1)),
+            IVec3::new(1, 2, 0)
+        );
+        assert_eq!(BlockTransform::rotation(-1), BlockTransform::rotation(3));
+        assert_eq!(BlockTransform::rotation(4), BlockTransform::IDENTITY);
+        assert_eq!(
+            BlockTransform::mirror(Mirror::Z).apply_point(IVec3::new(1, 2, 3)),
+            IVec3::new(1, 2, -3)
+        );
+    }
+
+
+    #[test]
+    fn directions_match_points() {
+        for transform in all_transforms() {
+            for dir in Direction::ALL {
+                let rotated = transform.apply_direction(dir);
+                assert_eq!(rotated.offset(), transform.apply_point(dir.offset()));
+            }
+        }
+    }
+
+
+    #[test]
+    fn inverse() {
+        let point = IVec3::new(3, -7, 12);
+        for transform in all_transforms() {
+            let inverse = transform.inverse();
+            assert_eq!(inverse.apply_point(transform.apply_point(point)), point);
+
+            for dir in Direction::ALL {
+                assert_eq!(inverse.apply_direction(transform.apply_direction(dir)), dir);
+            }
+        }
+    }
+
+
+    #[test]
+    fn regions() {
+        let region = Region::from_points(IVec3::new(1, 0, 2), IVec3::new(4, 3, 3));
+        let pivot = IVec3::new(1, 0, 2);
+
+        for transform in all_transforms() {
+            let rotated = transform.apply_region_around(&region, pivot);
+            assert_eq!(rotated.count(), region.count());
+
+            for point in region.iter() {
+                assert!(rotated.contains_point(transform.apply_point_around(point, pivot)));
+            }
+        }
+
+        assert_eq!(
+            BlockTransform::rotation(1).apply_region(&region),
+            Region::from_points(IVec3::new(2, 0, -1), IVec3::new(3, 3, -4))
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Tracks when each client last provided user input, allowing for the server to
+//! detect idle players.
+
+
+use crate::prelude::{send_to_server, ClientMessage, MessageBatch};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The minimum number of seconds between each input activity message that is
+/// sent by a client.
+const ACTIVITY_SEND_INTERVAL: f32 = 1.0;
+
+
+/// A network message that is sent from a client to the server to indicate that
+/// the player has recently provided user input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputActivityMessage;
+
+
+/// Tracks the last time that a client sent an input activity message to the
+/// server.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct InputActivity {
+    /// The elapsed server time, in seconds, that input was last received from
+    /// this client.
+    last_input: f64,
+}
+
+impl InputActivity {
+    /// Creates a new input activity tracker that last received input at the
+    /// given elapsed server time.
+    pub fn new(last_input: f64) -> Self {
+        Self {
+            last_input,
+        }
+    }
+
+
+    /// Gets the elapsed server time, in seconds, that input was last received
+    /// from this client.
+    pub fn last_input(&self) -> f64 {
+        self.last_input
+    }
+
+
+    /// Gets the number of seconds since input was last received from this
+    /// client, based on the given elapsed server time.
+    pub fn idle_seconds(&self, now: f64) -> f64 {
+        (now - self.last_input).max(0.0)
+    }
+}
+
+
+/// A client-side resource that records whether or not the local player has
+/// provided user input since the last activity message was sent.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PendingInputActivity {
+    /// Whether or not user input has been received.
+    active: bool,
+
+    /// The number of seconds since the last activity message was sent.
+    timer: f32,
+}
+
+impl PendingInputActivity {
+    /// Marks that the local player has provided user input.
+    pub fn mark_active(&mut self) {
+        self.active = true;
+    }
+}
+
+
+/// Sends an input activity message to the server if the local player has
+/// provided user input recently.
+pub fn send_input_activity(
+    time: Res<Time>,
+    mut pending: ResMut<PendingInputActivity>,
+    mut batch: ResMut<MessageBatch>,
+) {
+    pending.timer += time.delta_seconds();
+    if !pending.active || pending.timer < ACTIVITY_SEND_INTERVAL {
+        return;
+    }
+
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "activity").entered();
+
+    send_to_server(&mut batch, &InputActivityMessage);
+
+    pending.active = false;
+    pending.timer = 0.0;
+}
+
+
+/// Updates the input activity tracker of each player that has sent an input
+/// activity message.
+pub fn receive_input_activity(
+    time: Res<Time>,
+    mut activity_ev: EventReader<ClientMessage<InputActivityMessage>>,
+    mut players: Query<&mut InputActivity>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for ev in activity_ev.iter() {
+        if let Ok(mut activity) = players.get_mut(ev.player) {
+            activity.last_input = now;
+        }
+    }
+}
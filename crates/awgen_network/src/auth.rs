@@ -0,0 +1,223 @@
+//! Secure authentication of clients using connect tokens.
+//!
+//! A server that is given a private key only accepts clients that present a
+//! connect token that was signed with that key. Tokens are obtained by clients
+//! from a [`TokenIssuer`], which may sign tokens locally when the private key
+//! is shared with the client, such as in single player, or may load a token
+//! that was issued ahead of time by an external service, such as a login
+//! server.
+
+
+use crate::prelude::UserData;
+use anyhow::{anyhow, bail, Context, Result};
+use bevy_renet::renet::{ConnectToken, NETCODE_KEY_BYTES};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// The default number of seconds that issued connect tokens remain valid for.
+pub const DEFAULT_TOKEN_EXPIRE_SECONDS: u64 = 300;
+
+
+/// The default number of seconds without a response before a connection made
+/// with an issued connect token times out.
+pub const DEFAULT_TOKEN_TIMEOUT_SECONDS: i32 = 15;
+
+
+/// The private key that is shared between a server and its token issuer, and
+/// used to sign and verify connect tokens.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey([u8; NETCODE_KEY_BYTES]);
+
+impl PrivateKey {
+    /// Creates a new private key from the given bytes.
+    pub fn new(bytes: [u8; NETCODE_KEY_BYTES]) -> Self {
+        Self(bytes)
+    }
+
+
+    /// Parses a private key from a string of hexadecimal digits. Whitespace
+    /// around the key is ignored.
+    pub fn from_hex(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if !text.is_ascii() || text.len() != NETCODE_KEY_BYTES * 2 {
+            bail!(
+                "Private key must be {} hexadecimal digits, found {}",
+                NETCODE_KEY_BYTES * 2,
+                text.len()
+            );
+        }
+
+        let mut bytes = [0; NETCODE_KEY_BYTES];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let digits = &text[i * 2..i * 2 + 2];
+            *byte = u8::from_str_radix(digits, 16)
+                .map_err(|_| anyhow!("Invalid hexadecimal digits in private key: '{digits}'"))?;
+        }
+
+        Ok(Self(bytes))
+    }
+
+
+    /// Loads a hex encoded private key from the file at the given path.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read private key file: {}", path.display()))?;
+        Self::from_hex(&text)
+    }
+
+
+    /// Gets the bytes of this private key.
+    pub fn bytes(&self) -> &[u8; NETCODE_KEY_BYTES] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PrivateKey(..)")
+    }
+}
+
+
+/// A source of connect tokens for a client.
+pub trait TokenIssuer: Send + Sync {
+    /// Issues a connect token that allows the client with the given client id
+    /// to join the server at the given address, using the given protocol id.
+    ///
+    /// The given user data is carried within the token, unless the issuer
+    /// decides on the user data itself.
+    fn issue(
+        &self,
+        client_id: u64,
+        protocol_id: u64,
+        server_addr: SocketAddr,
+        user_data: &UserData,
+    ) -> Result<ConnectToken>;
+}
+
+
+/// A token issuer that signs connect tokens locally using the private key of
+/// the server.
+///
+/// This should only be used where the private key may be trusted to the
+/// client, such as in single player, or by a token issuing service.
+#[derive(Debug, Clone)]
+pub struct KeyTokenIssuer {
+    /// The private key to sign tokens with.
+    key: PrivateKey,
+
+    /// The number of seconds that each token remains valid for.
+    expire_seconds: u64,
+
+    /// The number of seconds without a response before a connection times
+    /// out.
+    timeout_seconds: i32,
+}
+
+impl KeyTokenIssuer {
+    /// Creates a new token issuer that signs tokens with the given private
+    /// key.
+    pub fn new(key: PrivateKey) -> Self {
+        Self {
+            key,
+            expire_seconds: DEFAULT_TOKEN_EXPIRE_SECONDS,
+            timeout_seconds: DEFAULT_TOKEN_TIMEOUT_SECONDS,
+        }
+    }
+
+
+    /// Sets the number of seconds that each issued token remains valid for.
+    pub fn with_expiry(mut self, expire_seconds: u64) -> Self {
+        self.expire_seconds = expire_seconds;
+        self
+    }
+
+
+    /// Sets the number of seconds without a response before a connection made
+    /// with an issued token times out.
+    pub fn with_timeout(mut self, timeout_seconds: i32) -> Self {
+        self.timeout_seconds = timeout_seconds;
+        self
+    }
+}
+
+impl TokenIssuer for KeyTokenIssuer {
+    fn issue(
+        &self,
+        client_id: u64,
+        protocol_id: u64,
+        server_addr: SocketAddr,
+        user_data: &UserData,
+    ) -> Result<ConnectToken> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let user_data = user_data.encode()?;
+        ConnectToken::generate(
+            time,
+            protocol_id,
+            self.expire_seconds,
+            client_id,
+            self.timeout_seconds,
+            vec![server_addr],
+            Some(&user_data),
+            self.key.bytes(),
+        )
+        .map_err(|err| anyhow!("Failed to generate connect token: {err}"))
+    }
+}
+
+
+/// A token issuer that loads a connect token that was issued ahead of time,
+/// such as by a login service, from a file.
+///
+/// The client id, server address, and user data of the loaded token are
+/// decided by whoever issued it.
+#[derive(Debug, Clone)]
+pub struct FileTokenIssuer {
+    /// The path of the connect token file.
+    path: PathBuf,
+}
+
+impl FileTokenIssuer {
+    /// Creates a new token issuer that loads the connect token file at the
+    /// given path.
+    pub fn new<P>(path: P) -> Self
+    where P: Into<PathBuf> {
+        Self {
+            path: path.into(),
+        }
+    }
+}
+
+impl TokenIssuer for FileTokenIssuer {
+    fn issue(&self, _: u64, _: u64, _: SocketAddr, _: &UserData) -> Result<ConnectToken> {
+        let file = File::open(&self.path).with_context(|| {
+            format!("Failed to open connect token file: {}", self.path.display())
+        })?;
+
+        ConnectToken::read(&mut BufReader::new(file))
+            .with_context(|| format!("Failed to read connect token file: {}", self.path.display()))
+    }
+}
+
+
+/// Issues a connect token with the given token issuer and user data, and
+/// writes it to the file at the given path, so that it may be handed to a
+/// client.
+pub fn write_connect_token(
+    issuer: &dyn TokenIssuer,
+    client_id: u64,
+    protocol_id: u64,
+    server_addr: SocketAddr,
+    user_data: &UserData,
+    path: &Path,
+) -> Result<()> {
+    let token = issuer.issue(client_id, protocol_id, server_addr, user_data)?;
+    let mut file = File::create(path)
+        .with_context(|| format!("Failed to create connect token file: {}", path.display()))?;
+    token.write(&mut file)?;
+    Ok(())
+}
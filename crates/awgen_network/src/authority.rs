@@ -0,0 +1,210 @@
+//! Ownership and authority of replicated entities, which decide which side of
+//! the network writes the state of each entity.
+//!
+//! Replicated entities are server-authoritative by default. The server
+//! simulates them, and clients only apply the state that the server
+//! replicates. An entity that is [`OwnedBy`] a client and has
+//! [`Authority::Client`] instead lets its owner write each component type that
+//! is marked with [`ReplicatedComponent::CLIENT_AUTHORITY`], such as cosmetic
+//! state. The owner sends these components to the server with a
+//! [`ComponentUpdateMessage`], and replication never overwrites them on the
+//! owner. All other components, such as the [`Position`] that movement
+//! prediction is reconciled against, are still written by the server.
+//!
+//! Each player owns their own player entity. When the owner of an entity
+//! disconnects, the server takes over the authority of the entity.
+//!
+//! [`Position`]: awgen_physics::prelude::Position
+
+
+use crate::prelude::{
+    send_to_server, ClientMessage, ClientSocket, ComponentData, MessageBatch, PredictedMovement, RemoteEntity, ReplicatedComponent, ReplicationRegistry
+};
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use serde::{Deserialize, Serialize};
+
+
+/// The side of the network that writes the client-authoritative components of
+/// a replicated entity.
+///
+/// On the server, this applies to the owner of the entity. On a client, this
+/// is inserted into the proxy of each replicated entity, describing the
+/// authority of the local client over it.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Reflect,
+    FromReflect,
+    Component,
+    Serialize,
+    Deserialize,
+)]
+#[reflect(Component)]
+pub enum Authority {
+    /// The server writes all components of the entity.
+    #[default]
+    Server,
+
+    /// The owner of the entity writes its client-authoritative components.
+    Client,
+}
+
+impl Authority {
+    /// Gets the authority that the client with the given client id has over a
+    /// server entity with the given authority and owner.
+    pub fn of_client(
+        authority: Option<&Authority>,
+        owner: Option<&OwnedBy>,
+        client_id: u64,
+    ) -> Self {
+        match (authority, owner) {
+            (Some(Authority::Client), Some(owner)) if owner.client_id() == client_id => {
+                Authority::Client
+            },
+            _ => Authority::Server,
+        }
+    }
+}
+
+
+/// A server-side component for a replicated entity that is owned by a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct OwnedBy(pub ClientSocket);
+
+impl OwnedBy {
+    /// Gets the client id of the owner.
+    pub fn client_id(&self) -> u64 {
+        self.0.id()
+    }
+}
+
+
+/// A network message that is sent from a client to the server with the
+/// client-authoritative components of an entity that it owns, that have
+/// changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentUpdateMessage {
+    /// The network ID of the entity, or `None` for the player entity of the
+    /// client.
+    pub entity: Option<u64>,
+
+    /// The value of each changed component.
+    pub components: Vec<ComponentData>,
+}
+
+
+/// The direction that a player is looking in, which is cosmetic state that is
+/// written by the player themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Component, Serialize, Deserialize)]
+pub struct LookRotation {
+    /// The rotation around the vertical axis, in radians.
+    pub yaw: f32,
+
+    /// The rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+}
+
+impl ReplicatedComponent for LookRotation {
+    const ID: u16 = 3;
+    const CLIENT_AUTHORITY: bool = true;
+}
+
+
+/// Returns the authority of each owned entity to the server once its owner has
+/// disconnected.
+pub fn release_ownership(
+    server: Res<RenetServer>,
+    owned: Query<(Entity, &OwnedBy)>,
+    mut commands: Commands,
+) {
+    for (entity, owner) in owned.iter() {
+        if server.is_connected(owner.client_id()) {
+            continue;
+        }
+
+        debug!(
+            "Client {} disconnected, server takes over {entity:?}",
+            owner.client_id()
+        );
+        commands.entity(entity).remove::<OwnedBy>().insert(Authority::Server);
+    }
+}
+
+
+/// Applies the client-authoritative components that clients send for the
+/// entities that they own.
+///
+/// Updates for entities that the client does not have authority over, and
+/// components that are not client-authoritative, are discarded.
+pub fn apply_component_updates(
+    mut update_ev: EventReader<ClientMessage<ComponentUpdateMessage>>,
+    registry: Res<ReplicationRegistry>,
+    entities: Query<(Option<&Authority>, Option<&OwnedBy>)>,
+    mut commands: Commands,
+) {
+    for ev in update_ev.iter() {
+        let entity = ev.message.entity.map_or(ev.player, Entity::from_bits);
+        let Ok((authority, owner)) = entities.get(entity) else {
+            continue;
+        };
+
+        if Authority::of_client(authority, owner, ev.client_id) != Authority::Client {
+            warn!(
+                "Client {} updated {entity:?} without authority",
+                ev.client_id
+            );
+            continue;
+        }
+
+        let components: Vec<ComponentData> = ev
+            .message
+            .components
+            .iter()
+            .filter(|c| registry.is_client_authoritative(c.id))
+            .cloned()
+            .collect();
+
+        registry.insert_all(&mut commands.entity(entity), &components);
+    }
+}
+
+
+/// Sends the given client-authoritative component to the server whenever it
+/// changes on the local player, or on the proxy of an entity that the local
+/// client has authority over.
+pub fn send_owned_components<C: ReplicatedComponent>(
+    mut batch: ResMut<MessageBatch>,
+    local_player: Query<&C, (With<PredictedMovement>, Changed<C>)>,
+    proxies: Query<(&RemoteEntity, &Authority, &C), Changed<C>>,
+) {
+    let serialize = |component: &C| {
+        vec![ComponentData {
+            id:   C::ID,
+            data: bincode::serialize(component).unwrap(),
+        }]
+    };
+
+    for component in local_player.iter() {
+        send_to_server(&mut batch, &ComponentUpdateMessage {
+            entity:     None,
+            components: serialize(component),
+        });
+    }
+
+    for (proxy, authority, component) in proxies.iter() {
+        if *authority != Authority::Client {
+            continue;
+        }
+
+        send_to_server(&mut batch, &ComponentUpdateMessage {
+            entity:     Some(proxy.id()),
+            components: serialize(component),
+        });
+    }
+}
@@ -0,0 +1,264 @@
+//! Limits the rate that the server sends data to each client, so that a single
+//! client downloading a large amount of world data cannot crowd out the
+//! time-sensitive updates of every other client.
+//!
+//! Messages that are subject to the budget are pushed to the
+//! [`OutgoingQueue`] with a [`SendPriority`] instead of being sent right away.
+//! Each client has its own send budget, which refills at a fixed number of
+//! bytes per physics tick. Queued messages are sent from the highest priority
+//! to the lowest, in the order that they were queued, for as long as the
+//! budget of the client allows.
+
+
+use crate::prelude::{
+    encode_message, CompressedClients, CompressionSettings, MessageBatch, MessageChannel, NetworkMessage
+};
+use awgen_physics::prelude::PhysicsTickrate;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_renet::renet::RenetServer;
+use std::collections::VecDeque;
+
+
+/// The default number of bytes that may be sent to each client per physics
+/// tick.
+pub const DEFAULT_SEND_BUDGET: usize = 8192;
+
+
+/// The priority of a queued outgoing message. Messages with a higher priority
+/// are always sent before messages with a lower priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendPriority {
+    /// Time-sensitive messages, such as the acknowledgement of the movement of
+    /// a player.
+    High,
+
+    /// Regular gameplay messages, such as the changed components of visible
+    /// entities.
+    Normal,
+
+    /// Bulk world data that may take a while to arrive, such as the full state
+    /// of entities that have become visible.
+    Low,
+}
+
+impl SendPriority {
+    /// Gets the index of the message queue of this priority.
+    fn index(self) -> usize {
+        match self {
+            SendPriority::High => 0,
+            SendPriority::Normal => 1,
+            SendPriority::Low => 2,
+        }
+    }
+}
+
+
+/// The number of bytes that may be sent to each client per physics tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct SendBudget {
+    /// The number of bytes per client per physics tick.
+    pub bytes_per_tick: usize,
+}
+
+impl Default for SendBudget {
+    fn default() -> Self {
+        Self {
+            bytes_per_tick: DEFAULT_SEND_BUDGET,
+        }
+    }
+}
+
+
+/// A serialized message that is waiting to be sent.
+#[derive(Debug, Clone)]
+struct QueuedMessage {
+    /// The message ID and key of this message, if it may be replaced by a
+    /// newer message.
+    key: Option<(u16, u64)>,
+
+    /// The channel to send the message over.
+    channel: MessageChannel,
+
+    /// The serialized message.
+    bytes: Vec<u8>,
+
+    /// Whether or not the message has already been compressed, if it was
+    /// large enough.
+    compressed: bool,
+}
+
+
+/// The messages that are waiting to be sent to a single client.
+#[derive(Debug, Clone, Default)]
+struct ClientQueue {
+    /// The queued messages of each send priority, from highest to lowest.
+    messages: [VecDeque<QueuedMessage>; 3],
+
+    /// The number of bytes that may currently be sent to the client. This may
+    /// become negative after sending a message that is larger than the
+    /// remaining budget.
+    credit: f32,
+}
+
+impl ClientQueue {
+    /// Gets a mutable reference to the queued message with the given key, if
+    /// any.
+    fn find_mut(&mut self, key: (u16, u64)) -> Option<&mut QueuedMessage> {
+        self.messages.iter_mut().flatten().find(|message| message.key == Some(key))
+    }
+
+
+    /// Gets the queued message that should be sent next, if any.
+    fn front_mut(&mut self) -> Option<&mut QueuedMessage> {
+        self.messages.iter_mut().find_map(|queue| queue.front_mut())
+    }
+
+
+    /// Removes the queued message that should be sent next, if any.
+    fn pop_front(&mut self) -> Option<QueuedMessage> {
+        self.messages.iter_mut().find_map(|queue| queue.pop_front())
+    }
+}
+
+
+/// A server-side resource that stores the messages that are waiting to be sent
+/// to each client within its send budget.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct OutgoingQueue {
+    /// The message queue of each client.
+    clients: HashMap<u64, ClientQueue>,
+}
+
+impl OutgoingQueue {
+    /// Queues a message to be sent to the given client with the given priority.
+    pub fn push<M: NetworkMessage>(&mut self, client_id: u64, priority: SendPriority, message: &M) {
+        self.push_message(client_id, priority, QueuedMessage {
+            key:        None,
+            channel:    M::CHANNEL,
+            bytes:      encode_message(message),
+            compressed: false,
+        });
+    }
+
+
+    /// Queues a message to be sent to the given client with the given priority,
+    /// under the given key.
+    ///
+    /// If a message of the same type and key is still waiting to be sent to
+    /// the client, it is replaced by this message instead, keeping its place
+    /// within the queue. This allows messages that carry the latest state of
+    /// something to be updated while they wait.
+    pub fn push_keyed<M: NetworkMessage>(
+        &mut self,
+        client_id: u64,
+        priority: SendPriority,
+        key: u64,
+        message: &M,
+    ) {
+        let key = (M::ID, key);
+        let bytes = encode_message(message);
+
+        if let Some(existing) = self.clients.get_mut(&client_id).and_then(|q| q.find_mut(key)) {
+            existing.bytes = bytes;
+            existing.compressed = false;
+            return;
+        }
+
+        self.push_message(client_id, priority, QueuedMessage {
+            key: Some(key),
+            channel: M::CHANNEL,
+            bytes,
+            compressed: false,
+        });
+    }
+
+
+    /// Gets whether or not a message of the given type and key is still
+    /// waiting to be sent to the given client.
+    pub fn contains_key<M: NetworkMessage>(&self, client_id: u64, key: u64) -> bool {
+        self.clients.get(&client_id).is_some_and(|queue| {
+            queue.messages.iter().flatten().any(|message| message.key == Some((M::ID, key)))
+        })
+    }
+
+
+    /// Gets the number of messages that are waiting to be sent to the given
+    /// client.
+    pub fn len(&self, client_id: u64) -> usize {
+        self.clients
+            .get(&client_id)
+            .map_or(0, |queue| queue.messages.iter().map(VecDeque::len).sum())
+    }
+
+
+    /// Gets the total size, in bytes, of the messages that are waiting to be
+    /// sent to the given client.
+    pub fn queued_bytes(&self, client_id: u64) -> usize {
+        self.clients.get(&client_id).map_or(0, |queue| {
+            queue.messages.iter().flatten().map(|message| message.bytes.len()).sum()
+        })
+    }
+
+
+    /// Adds a message to the end of the queue of its priority.
+    fn push_message(&mut self, client_id: u64, priority: SendPriority, message: QueuedMessage) {
+        self.clients.entry(client_id).or_default().messages[priority.index()].push_back(message);
+    }
+}
+
+
+/// Moves the queued messages of each client to the message batch, from the
+/// highest priority to the lowest, until the send budget of the client runs
+/// out.
+///
+/// The budget of each client refills continuously at the configured number of
+/// bytes per physics tick, up to a maximum of one tick's worth. A message that
+/// is larger than the whole budget is sent once the budget is full, so that it
+/// cannot block the queue forever. The queues of disconnected clients are
+/// discarded.
+///
+/// Messages to clients that have negotiated compression are compressed before
+/// being sent if they are large enough, and only their compressed size counts
+/// against the budget.
+#[allow(clippy::too_many_arguments)]
+pub fn flush_outgoing_queue(
+    time: Res<Time>,
+    tickrate: Res<PhysicsTickrate>,
+    budget: Res<SendBudget>,
+    compression: Res<CompressionSettings>,
+    compressed: Res<CompressedClients>,
+    server: Res<RenetServer>,
+    mut queue: ResMut<OutgoingQueue>,
+    mut batch: ResMut<MessageBatch>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "queue").entered();
+
+    let connected = server.clients_id();
+    queue.clients.retain(|id, _| connected.contains(id));
+
+    let max_credit = budget.bytes_per_tick as f32;
+    let refill = max_credit * tickrate.tickrate() * time.delta_seconds();
+
+    for (client_id, client_queue) in queue.clients.iter_mut() {
+        client_queue.credit = (client_queue.credit + refill).min(max_credit);
+
+        while let Some(message) = client_queue.front_mut() {
+            if !message.compressed {
+                let bytes = std::mem::take(&mut message.bytes);
+                message.bytes = compressed.compress(&compression, *client_id, bytes);
+                message.compressed = true;
+            }
+
+            let size = message.bytes.len() as f32;
+            if size > client_queue.credit && client_queue.credit < max_credit {
+                break;
+            }
+
+            let message = client_queue.pop_front().unwrap();
+            client_queue.credit -= size;
+            batch.push_to_client(*client_id, message.channel, message.bytes);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! The list of clients and addresses that are banned from joining the server.
+
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+
+
+/// A server-side resource that stores the client ids and IP addresses that are
+/// banned from the server, alongside the reason for each ban.
+///
+/// Clients are checked against this list as they connect, and banned clients
+/// are kicked before they are given a player entity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Resource, Serialize, Deserialize)]
+pub struct BanList {
+    /// The ban reason of each banned client id.
+    clients: BTreeMap<u64, String>,
+
+    /// The ban reason of each banned IP address.
+    addresses: BTreeMap<IpAddr, String>,
+}
+
+impl BanList {
+    /// Bans the client with the given id, for the given reason.
+    ///
+    /// Client ids are chosen by each client, so this ban only holds until the
+    /// client reconnects with a different id.
+    ///
+    /// Returns false if the client was already banned, in which case the
+    /// reason is replaced.
+    pub fn ban_client<R>(&mut self, client_id: u64, reason: R) -> bool
+    where R: Into<String> {
+        self.clients.insert(client_id, reason.into()).is_none()
+    }
+
+
+    /// Lifts the ban on the client with the given id.
+    ///
+    /// Returns false if the client was not banned.
+    pub fn unban_client(&mut self, client_id: u64) -> bool {
+        self.clients.remove(&client_id).is_some()
+    }
+
+
+    /// Bans all clients that connect from the given IP address, for the given
+    /// reason.
+    ///
+    /// Returns false if the address was already banned, in which case the
+    /// reason is replaced.
+    pub fn ban_address<R>(&mut self, address: IpAddr, reason: R) -> bool
+    where R: Into<String> {
+        self.addresses.insert(address, reason.into()).is_none()
+    }
+
+
+    /// Lifts the ban on the given IP address.
+    ///
+    /// Returns false if the address was not banned.
+    pub fn unban_address(&mut self, address: IpAddr) -> bool {
+        self.addresses.remove(&address).is_some()
+    }
+
+
+    /// Gets the reason that a client with the given id, connecting from the
+    /// given address, is banned, or `None` if it is not banned.
+    pub fn check(&self, client_id: u64, address: Option<SocketAddr>) -> Option<&str> {
+        self.clients
+            .get(&client_id)
+            .or_else(|| address.and_then(|addr| self.addresses.get(&addr.ip())))
+            .map(|reason| reason.as_str())
+    }
+
+
+    /// Gets an iterator over all banned client ids and their ban reasons.
+    pub fn clients(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.clients.iter().map(|(id, reason)| (*id, reason.as_str()))
+    }
+
+
+    /// Gets an iterator over all banned IP addresses and their ban reasons.
+    pub fn addresses(&self) -> impl Iterator<Item = (IpAddr, &str)> {
+        self.addresses.iter().map(|(addr, reason)| (*addr, reason.as_str()))
+    }
+
+
+    /// Gets whether or not no clients or addresses are banned.
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty() && self.addresses.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Parses a socket address.
+    fn addr(addr: &str) -> Option<SocketAddr> {
+        Some(addr.parse().unwrap())
+    }
+
+
+    #[test]
+    fn check_client() {
+        let mut bans = BanList::default();
+        assert!(bans.ban_client(7, "Griefing"));
+
+        assert_eq!(bans.check(7, None), Some("Griefing"));
+        assert_eq!(bans.check(7, addr("10.0.0.1:5000")), Some("Griefing"));
+        assert_eq!(bans.check(8, addr("10.0.0.1:5000")), None);
+    }
+
+
+    #[test]
+    fn check_address() {
+        let mut bans = BanList::default();
+        assert!(bans.ban_address("10.0.0.1".parse().unwrap(), "Spam"));
+
+        assert_eq!(bans.check(1, addr("10.0.0.1:5000")), Some("Spam"));
+        assert_eq!(bans.check(2, addr("10.0.0.1:6000")), Some("Spam"));
+        assert_eq!(bans.check(1, addr("10.0.0.2:5000")), None);
+        assert_eq!(bans.check(1, None), None);
+    }
+
+
+    #[test]
+    fn client_ban_takes_priority() {
+        let mut bans = BanList::default();
+        bans.ban_client(1, "Griefing");
+        bans.ban_address("10.0.0.1".parse().unwrap(), "Spam");
+
+        assert_eq!(bans.check(1, addr("10.0.0.1:5000")), Some("Griefing"));
+    }
+
+
+    #[test]
+    fn ban_again_replaces_reason() {
+        let mut bans = BanList::default();
+        assert!(bans.ban_client(1, "Griefing"));
+        assert!(!bans.ban_client(1, "Spam"));
+
+        assert_eq!(bans.check(1, None), Some("Spam"));
+    }
+
+
+    #[test]
+    fn unban() {
+        let mut bans = BanList::default();
+        let address = "10.0.0.1".parse().unwrap();
+        bans.ban_client(1, "Griefing");
+        bans.ban_address(address, "Spam");
+
+        assert!(bans.unban_client(1));
+        assert!(!bans.unban_client(1));
+        assert!(bans.unban_address(address));
+        assert!(!bans.unban_address(address));
+
+        assert!(bans.is_empty());
+        assert_eq!(bans.check(1, addr("10.0.0.1:5000")), None);
+    }
+}
@@ -0,0 +1,348 @@
+//! Collects the network messages that are produced during a frame, so that
+//! they are all sent at once at the end of the frame.
+//!
+//! Game systems never send messages directly over the transport. Instead,
+//! [`send_to_client`], [`broadcast`], and [`send_to_server`] push them to the
+//! [`MessageBatch`], which is flushed within the [`NetworkFlush`] stage, after
+//! [`CoreStage::PostUpdate`]. The packets of the whole batch are then sent
+//! right away, rather than once per message.
+//!
+//! Messages to clients that have negotiated compression are compressed as the
+//! batch is flushed, if they are large enough.
+//!
+//! The messages of a batch are sent to each client in the order of its client
+//! id, and over each channel in the order of its channel ID. Within a single
+//! channel, messages keep the order that they were queued in, so the order in
+//! which they are sent no longer depends on which clients they were addressed
+//! to.
+//!
+//! [`send_to_client`]: crate::prelude::send_to_client
+//! [`broadcast`]: crate::prelude::broadcast
+//! [`send_to_server`]: crate::prelude::send_to_server
+
+
+#[cfg(feature = "network_simulator")]
+use crate::prelude::NetworkSimulator;
+use crate::prelude::{
+    capture_message, CaptureDirection, CaptureSide, ClientEncryption, CompressedClients, CompressionSettings, MessageChannel, ServerEncryption
+};
+use bevy::prelude::*;
+use bevy_renet::renet::{RenetClient, RenetServer};
+use std::collections::BTreeMap;
+
+
+/// The stage that flushes the message batch, which runs after
+/// [`CoreStage::PostUpdate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, StageLabel)]
+pub struct NetworkFlush;
+
+
+/// The receiver of a batched message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recipient {
+    /// The server, when sent from a client.
+    Server,
+
+    /// A single client, by its client id.
+    Client(u64),
+
+    /// All clients that are connected when the batch is flushed.
+    AllClients,
+}
+
+
+/// A serialized message that is waiting for the batch to be flushed.
+#[derive(Debug, Clone)]
+struct BatchedMessage {
+    /// The receiver of the message.
+    recipient: Recipient,
+
+    /// The channel to send the message over.
+    channel: MessageChannel,
+
+    /// The serialized message.
+    bytes: Vec<u8>,
+}
+
+
+/// A resource that stores all messages that have been sent during the current
+/// frame, until they are flushed.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct MessageBatch {
+    /// The messages of this batch, in the order that they were queued.
+    messages: Vec<BatchedMessage>,
+}
+
+impl MessageBatch {
+    /// Queues a serialized message to be sent to the given client.
+    pub fn push_to_client(&mut self, client_id: u64, channel: MessageChannel, bytes: Vec<u8>) {
+        self.push(Recipient::Client(client_id), channel, bytes);
+    }
+
+
+    /// Queues a serialized message to be sent to all connected clients.
+    pub fn push_to_all(&mut self, channel: MessageChannel, bytes: Vec<u8>) {
+        self.push(Recipient::AllClients, channel, bytes);
+    }
+
+
+    /// Queues a serialized message to be sent to the server.
+    pub fn push_to_server(&mut self, channel: MessageChannel, bytes: Vec<u8>) {
+        self.push(Recipient::Server, channel, bytes);
+    }
+
+
+    /// Gets the number of messages that are waiting to be flushed.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+
+    /// Gets whether or not no messages are waiting to be flushed.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+
+    /// Removes all messages that are waiting to be flushed.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+
+    /// Adds a message to the end of this batch.
+    fn push(&mut self, recipient: Recipient, channel: MessageChannel, bytes: Vec<u8>) {
+        self.messages.push(BatchedMessage {
+            recipient,
+            channel,
+            bytes,
+        });
+    }
+
+
+    /// Removes all messages from this batch, grouped by client id and channel
+    /// ID. Messages for all clients are copied for each of the given clients.
+    /// Messages for other clients, or for the server, are discarded.
+    ///
+    /// Each message is compressed for the client that it is sent to, if that
+    /// client receives compressed messages.
+    fn drain_by_client(
+        &mut self,
+        clients: &[u64],
+        settings: &CompressionSettings,
+        compressed: &CompressedClients,
+    ) -> BTreeMap<(u64, u8), Vec<Vec<u8>>> {
+        let mut grouped: BTreeMap<(u64, u8), Vec<Vec<u8>>> = BTreeMap::new();
+
+        for message in self.messages.drain(..) {
+            match message.recipient {
+                Recipient::Client(client_id) if clients.contains(&client_id) => {
+                    let bytes = compressed.compress(settings, client_id, message.bytes);
+                    grouped.entry((client_id, message.channel.id)).or_default().push(bytes);
+                },
+                Recipient::AllClients => {
+                    let mut compressed_bytes = None;
+                    for client_id in clients {
+                        let bytes = match compressed.contains(*client_id) {
+                            true => {
+                                compressed_bytes
+                                    .get_or_insert_with(|| {
+                                        compressed.compress(
+                                            settings,
+                                            *client_id,
+                                            message.bytes.clone(),
+                                        )
+                                    })
+                                    .clone()
+                            },
+                            false => message.bytes.clone(),
+                        };
+
+                        let key = (*client_id, message.channel.id);
+                        grouped.entry(key).or_default().push(bytes);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        grouped
+    }
+}
+
+
+/// Sends all batched messages from the server to their clients, and sends the
+/// resulting packets right away.
+///
+/// Messages to clients that have disconnected are discarded. Large messages
+/// are compressed for each client that receives compressed messages. If the
+/// server requires encrypted connections, messages are encrypted after they
+/// have been captured.
+pub fn flush_server_messages(
+    mut batch: ResMut<MessageBatch>,
+    mut server: ResMut<RenetServer>,
+    mut encryption: Option<ResMut<ServerEncryption>>,
+    settings: Res<CompressionSettings>,
+    compressed: Res<CompressedClients>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "flush").entered();
+
+    if batch.is_empty() {
+        return;
+    }
+
+    let mut clients = server.clients_id();
+    clients.sort_unstable();
+
+    for ((client_id, channel), messages) in batch.drain_by_client(&clients, &settings, &compressed)
+    {
+        for bytes in messages {
+            capture_message(
+                CaptureSide::Server,
+                CaptureDirection::Sent,
+                Some(client_id),
+                &bytes,
+            );
+
+            let bytes = match &mut encryption {
+                Some(encryption) => encryption.seal(client_id, bytes),
+                None => Some(bytes),
+            };
+
+            if let Some(bytes) = bytes {
+                server.send_message(client_id, channel, bytes);
+            }
+        }
+    }
+
+    if let Err(err) = server.send_packets() {
+        error!("Failed to send packets: {err}");
+    }
+}
+
+
+/// Sends all batched messages from the client to the server, and sends the
+/// resulting packets right away.
+///
+/// Messages that were queued while the client was not connected are
+/// discarded, so that they are never sent over a new connection before its
+/// handshake. Once the server has sent its public key, messages are encrypted
+/// after they have been captured.
+///
+/// With the `network_simulator` feature, sent messages pass through the network
+/// simulator first, and are only sent once they are delivered.
+pub fn flush_client_messages(
+    mut batch: ResMut<MessageBatch>,
+    mut client: ResMut<RenetClient>,
+    mut encryption: ResMut<ClientEncryption>,
+    #[cfg(feature = "network_simulator")] time: Res<Time>,
+    #[cfg(feature = "network_simulator")] mut simulator: ResMut<NetworkSimulator>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "flush").entered();
+
+    #[cfg(feature = "network_simulator")]
+    let idle = batch.is_empty() && !simulator.has_outbound();
+
+    #[cfg(not(feature = "network_simulator"))]
+    let idle = batch.is_empty();
+
+    if idle {
+        return;
+    }
+
+    if !client.is_connected() {
+        batch.clear();
+        return;
+    }
+
+    let mut messages: Vec<BatchedMessage> = batch.messages.drain(..).collect();
+    messages.retain(|message| message.recipient == Recipient::Server);
+    messages.sort_by_key(|message| message.channel.id);
+
+    let messages: Vec<(MessageChannel, Vec<u8>)> =
+        messages.into_iter().map(|message| (message.channel, message.bytes)).collect();
+
+    #[cfg(feature = "network_simulator")]
+    let messages = simulator.simulate_outbound(time.elapsed_seconds_f64(), messages);
+
+    for (channel, bytes) in messages {
+        capture_message(CaptureSide::Client, CaptureDirection::Sent, None, &bytes);
+        if let Some(bytes) = encryption.seal(bytes) {
+            client.send_message(channel, bytes);
+        }
+    }
+
+    if let Err(err) = client.send_packets() {
+        error!("Failed to send packets: {err}");
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{broadcast, send_to_client, NetworkMessage, COMPRESSED_FLAG};
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+
+
+    /// A large message that compresses well.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct LargeMessage {
+        /// The payload of the message.
+        data: Vec<u8>,
+    }
+
+    impl NetworkMessage for LargeMessage {
+        const CHANNEL: MessageChannel = MessageChannel::RELIABLE;
+        const ID: u16 = 0x7FFE;
+    }
+
+
+    /// Gets whether or not each message sent to the given client is
+    /// compressed.
+    fn compressed_flags(grouped: &BTreeMap<(u64, u8), Vec<Vec<u8>>>, client_id: u64) -> Vec<bool> {
+        grouped
+            .iter()
+            .filter(|((id, _), _)| *id == client_id)
+            .flat_map(|(_, messages)| messages.iter())
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) & COMPRESSED_FLAG != 0)
+            .collect()
+    }
+
+
+    #[test]
+    fn compress_broadcasts_and_direct_messages() {
+        let large = LargeMessage {
+            data: vec![7; 2048],
+        };
+        let small = LargeMessage {
+            data: vec![7; 8],
+        };
+
+        let mut batch = MessageBatch::default();
+        broadcast(&mut batch, &large);
+        broadcast(&mut batch, &small);
+        send_to_client(&mut batch, 1, &large);
+        send_to_client(&mut batch, 2, &large);
+
+        let settings = CompressionSettings::default();
+        let mut compressed = CompressedClients::default();
+        compressed.set(1, true);
+
+        let grouped = batch.drain_by_client(&[1, 2], &settings, &compressed);
+        assert_eq!(compressed_flags(&grouped, 1), vec![true, false, true]);
+        assert_eq!(compressed_flags(&grouped, 2), vec![false, false, false]);
+        assert!(batch.is_empty());
+
+        let disabled = CompressionSettings {
+            enabled: false,
+            ..settings
+        };
+        broadcast(&mut batch, &large);
+        let grouped = batch.drain_by_client(&[1, 2], &disabled, &compressed);
+        assert_eq!(compressed_flags(&grouped, 1), vec![false]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Requests from clients to start and stop breaking the block that they are
+//! looking at, and the crack stages of the blocks that other players are
+//! breaking.
+//!
+//! A client tells the server when it starts breaking a block, and when it
+//! stops before the block is broken. Once the block has been broken for long
+//! enough, the client finishes breaking it with a block edit request, which
+//! the server only accepts if the block has been broken for its full break
+//! time. While a block is being broken, the server sends its crack stage to
+//! the other players within the same world, so that they may see it crack.
+
+
+use crate::prelude::{send_to_server, MessageBatch, ServerMessage};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+
+/// The action of a block breaking request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockBreakAction {
+    /// Starts breaking the block at the given position, replacing any block
+    /// that was already being broken.
+    Start {
+        /// The position of the block.
+        block_pos: IVec3,
+    },
+
+    /// Stops breaking the block that is being broken, without breaking it.
+    Cancel,
+}
+
+
+/// A network message that is sent from a client to the server when the local
+/// player starts or stops breaking a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockBreakMessage {
+    /// The action to take.
+    pub action: BlockBreakAction,
+}
+
+
+/// A network message that is sent from the server to a client when the crack
+/// stage of a block that another player is breaking changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCrackMessage {
+    /// The client id of the player that is breaking the block.
+    pub client_id: u64,
+
+    /// The position of the block.
+    pub block_pos: IVec3,
+
+    /// The crack stage of the block, or `None` if the player stopped breaking
+    /// it.
+    pub stage: Option<u8>,
+}
+
+
+/// A client-side resource that stores the crack stage of each block that
+/// another player is breaking, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct BlockCracks {
+    /// The position and crack stage of the block that each player is
+    /// breaking, by client id.
+    cracks: HashMap<u64, (IVec3, u8)>,
+}
+
+impl BlockCracks {
+    /// Gets the position and crack stage of the block that the player with the
+    /// given client id is breaking, if any.
+    pub fn get(&self, client_id: u64) -> Option<(IVec3, u8)> {
+        self.cracks.get(&client_id).copied()
+    }
+
+
+    /// Gets an iterator over the position and crack stage of each block that is
+    /// being broken.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, u8)> + '_ {
+        self.cracks.values().copied()
+    }
+}
+
+
+/// Sends a request to the server to start or stop breaking a block.
+pub fn send_block_break(batch: &mut MessageBatch, action: BlockBreakAction) {
+    send_to_server(batch, &BlockBreakMessage {
+        action,
+    });
+}
+
+
+/// Applies the block crack messages that were received from the server to the
+/// block cracks resource.
+pub fn apply_block_crack_messages(
+    mut crack_ev: EventReader<ServerMessage<BlockCrackMessage>>,
+    mut cracks: ResMut<BlockCracks>,
+) {
+    for ev in crack_ev.iter() {
+        let message = ev.message;
+        match message.stage {
+            Some(stage) => {
+                cracks.cracks.insert(message.client_id, (message.block_pos, stage));
+            },
+            None => {
+                cracks.cracks.remove(&message.client_id);
+            },
+        }
+    }
+}
+
+
+/// Clears the block cracks once the local player has disconnected from the
+/// server.
+pub fn reset_block_cracks(mut cracks: ResMut<BlockCracks>) {
+    cracks.cracks.clear();
+}
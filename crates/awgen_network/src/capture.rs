@@ -0,0 +1,409 @@
+//! An optional capture mode that records every network message sent and
+//! received by this process to a file, so that protocol issues reported by
+//! users can be inspected offline.
+//!
+//! Capturing is started for the whole process with [`start_capture`]. When
+//! both a server and a client run within the same process, the messages of
+//! both sides are written to the same file, marked by their [`CaptureSide`].
+//! Each record carries the physics tick of its side, the time since capturing
+//! started, and the type name of the message according to the protocol schema
+//! of the capturing process. The messages that a client received may be
+//! played back into a client with a [`SessionPlayback`].
+//!
+//! A capture file starts with [`CAPTURE_MAGIC`], followed by a bincode encoded
+//! [`CaptureHeader`] and any number of bincode encoded [`CaptureRecord`]s.
+//!
+//! [`SessionPlayback`]: crate::prelude::SessionPlayback
+
+
+use crate::prelude::{
+    decompress_payload, AWGEN_VERSION, COMPRESSED_FLAG, MESSAGE_ID_BYTES, PROTOCOL, PROTOCOL_ID
+};
+use anyhow::{bail, Error, Result};
+use awgen_physics::prelude::PhysicsFrame;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+
+/// The bytes that every capture file starts with.
+pub const CAPTURE_MAGIC: [u8; 8] = *b"AWGNCAP1";
+
+
+/// Whether or not a capture is currently running, which allows messages to be
+/// skipped without locking the capture when capturing is disabled.
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+
+/// The capture that is currently running within this process, if any.
+static CAPTURE: Mutex<Option<PacketCapture>> = Mutex::new(None);
+
+
+/// The side of the network that captured a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureSide {
+    /// The message was captured by the server.
+    Server,
+
+    /// The message was captured by a client.
+    Client,
+}
+
+impl CaptureSide {
+    /// Gets the index of the physics tick of this side within a capture.
+    fn index(self) -> usize {
+        match self {
+            CaptureSide::Server => 0,
+            CaptureSide::Client => 1,
+        }
+    }
+}
+
+impl Display for CaptureSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CaptureSide::Server => "server",
+            CaptureSide::Client => "client",
+        })
+    }
+}
+
+impl FromStr for CaptureSide {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "server" => Ok(CaptureSide::Server),
+            "client" => Ok(CaptureSide::Client),
+            _ => bail!("Unknown capture side: {s}"),
+        }
+    }
+}
+
+
+/// Whether a captured message was sent or received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureDirection {
+    /// The message was sent by the capturing side.
+    Sent,
+
+    /// The message was received by the capturing side.
+    Received,
+}
+
+impl Display for CaptureDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CaptureDirection::Sent => "sent",
+            CaptureDirection::Received => "received",
+        })
+    }
+}
+
+impl FromStr for CaptureDirection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sent" => Ok(CaptureDirection::Sent),
+            "received" => Ok(CaptureDirection::Received),
+            _ => bail!("Unknown capture direction: {s}"),
+        }
+    }
+}
+
+
+/// The header of a capture file, describing the process that wrote it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureHeader {
+    /// The version of Awgen that wrote the capture.
+    pub version: String,
+
+    /// The protocol schema fingerprint of the process that wrote the capture.
+    pub protocol: u64,
+}
+
+
+/// A single captured network message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    /// The physics tick of the capturing side when the message was captured.
+    pub tick: u64,
+
+    /// The number of seconds since capturing started.
+    pub time: f64,
+
+    /// The side of the network that captured the message.
+    pub side: CaptureSide,
+
+    /// Whether the message was sent or received.
+    pub direction: CaptureDirection,
+
+    /// The client id of the client the message was sent to or received from,
+    /// if captured by the server. This is not set for broadcasts.
+    pub client_id: Option<u64>,
+
+    /// The message ID, without the compression flag.
+    pub message_id: u16,
+
+    /// The type name of the message, or `Unknown` if the message ID is not
+    /// within the protocol schema.
+    pub message_type: String,
+
+    /// Whether or not the payload was compressed on the wire.
+    pub compressed: bool,
+
+    /// The payload of the message as it was sent over the wire, without its
+    /// message ID.
+    pub payload: Vec<u8>,
+}
+
+impl CaptureRecord {
+    /// Gets the uncompressed payload of this message.
+    pub fn decoded_payload(&self) -> Result<Vec<u8>> {
+        match self.compressed {
+            true => decompress_payload(&self.payload),
+            false => Ok(self.payload.clone()),
+        }
+    }
+
+
+    /// Gets this message as it was sent over the wire, including its message
+    /// ID and compression flag.
+    pub fn wire_bytes(&self) -> Vec<u8> {
+        let id = match self.compressed {
+            true => self.message_id | COMPRESSED_FLAG,
+            false => self.message_id,
+        };
+
+        let mut bytes = id.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+}
+
+impl Display for CaptureRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[tick {} | {:.3}s] {} {}",
+            self.tick, self.time, self.side, self.direction
+        )?;
+
+        match (self.client_id, self.side, self.direction) {
+            (Some(client_id), _, CaptureDirection::Sent) => write!(f, " to client {client_id}")?,
+            (Some(client_id), _, _) => write!(f, " from client {client_id}")?,
+            (None, CaptureSide::Server, _) => write!(f, " to all clients")?,
+            (None, ..) => {},
+        }
+
+        write!(
+            f,
+            ": {} (#{}, {} bytes{})",
+            self.message_type,
+            self.message_id,
+            self.payload.len(),
+            if self.compressed { ", compressed" } else { "" }
+        )
+    }
+}
+
+
+/// The conditions that a captured message must match to be inspected.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureFilter {
+    /// Only match messages with this message ID, or whose type name contains
+    /// this text, ignoring case.
+    pub message: Option<String>,
+
+    /// Only match messages sent to or received from this client.
+    pub client_id: Option<u64>,
+
+    /// Only match messages captured by this side.
+    pub side: Option<CaptureSide>,
+
+    /// Only match messages that were sent or received.
+    pub direction: Option<CaptureDirection>,
+
+    /// Only match messages captured at or after this tick.
+    pub from_tick: Option<u64>,
+
+    /// Only match messages captured at or before this tick.
+    pub to_tick: Option<u64>,
+}
+
+impl CaptureFilter {
+    /// Gets whether or not the given record matches this filter.
+    pub fn matches(&self, record: &CaptureRecord) -> bool {
+        let message = self.message.as_ref().is_none_or(|message| {
+            match message.parse::<u16>() {
+                Ok(id) => record.message_id == id,
+                Err(_) => record.message_type.to_lowercase().contains(&message.to_lowercase()),
+            }
+        });
+
+        message
+            && self.client_id.is_none_or(|id| record.client_id == Some(id))
+            && self.side.is_none_or(|side| record.side == side)
+            && self.direction.is_none_or(|direction| record.direction == direction)
+            && self.from_tick.is_none_or(|tick| record.tick >= tick)
+            && self.to_tick.is_none_or(|tick| record.tick <= tick)
+    }
+}
+
+
+/// A running capture, which writes captured messages to a file.
+struct PacketCapture {
+    /// The capture file.
+    writer: BufWriter<File>,
+
+    /// The time at which capturing started.
+    started: Instant,
+
+    /// The latest physics tick of each side.
+    ticks: [u64; 2],
+}
+
+
+/// Starts capturing all network messages sent and received by this process to
+/// the file at the given path, replacing the file if it exists.
+pub fn start_capture(path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&CAPTURE_MAGIC)?;
+    bincode::serialize_into(&mut writer, &CaptureHeader {
+        version:  AWGEN_VERSION.to_string(),
+        protocol: PROTOCOL_ID,
+    })?;
+
+    *CAPTURE.lock().unwrap() = Some(PacketCapture {
+        writer,
+        started: Instant::now(),
+        ticks: [0; 2],
+    });
+    CAPTURING.store(true, Ordering::Release);
+    Ok(())
+}
+
+
+/// Gets whether or not network messages are currently being captured.
+pub fn is_capturing() -> bool {
+    CAPTURING.load(Ordering::Acquire)
+}
+
+
+/// Records a single encoded message, as it is sent or received over the wire,
+/// within the running capture. Does nothing if no capture is running.
+///
+/// If the capture file could not be written to, capturing is stopped.
+pub fn capture_message(
+    side: CaptureSide,
+    direction: CaptureDirection,
+    client_id: Option<u64>,
+    bytes: &[u8],
+) {
+    if !is_capturing() || bytes.len() < MESSAGE_ID_BYTES {
+        return;
+    }
+
+    let mut capture = CAPTURE.lock().unwrap();
+    let Some(running) = capture.as_mut() else {
+        return;
+    };
+
+    let id = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let message_id = id & !COMPRESSED_FLAG;
+    let message_type = PROTOCOL
+        .iter()
+        .find(|schema| schema.id == message_id)
+        .map_or("Unknown", |schema| schema.name);
+
+    let record = CaptureRecord {
+        tick: running.ticks[side.index()],
+        time: running.started.elapsed().as_secs_f64(),
+        side,
+        direction,
+        client_id,
+        message_id,
+        message_type: message_type.to_string(),
+        compressed: id & COMPRESSED_FLAG != 0,
+        payload: bytes[MESSAGE_ID_BYTES..].to_vec(),
+    };
+
+    if let Err(err) = bincode::serialize_into(&mut running.writer, &record) {
+        error!("Failed to write network capture, capturing stopped: {err}");
+        *capture = None;
+        CAPTURING.store(false, Ordering::Release);
+    }
+}
+
+
+/// Stores the current physics tick of the given side, and flushes the captured
+/// messages to the capture file.
+fn update_capture(side: CaptureSide, frame: &PhysicsFrame) {
+    if !is_capturing() {
+        return;
+    }
+
+    let mut capture = CAPTURE.lock().unwrap();
+    let Some(running) = capture.as_mut() else {
+        return;
+    };
+
+    running.ticks[side.index()] = frame.frame_number();
+    if let Err(err) = running.writer.flush() {
+        error!("Failed to write network capture, capturing stopped: {err}");
+        *capture = None;
+        CAPTURING.store(false, Ordering::Release);
+    }
+}
+
+
+/// Updates the server physics tick of the running capture, if any.
+pub fn update_server_capture(frame: Res<PhysicsFrame>) {
+    update_capture(CaptureSide::Server, &frame);
+}
+
+
+/// Updates the client physics tick of the running capture, if any.
+pub fn update_client_capture(frame: Res<PhysicsFrame>) {
+    update_capture(CaptureSide::Client, &frame);
+}
+
+
+/// Reads the header and all records of the capture file at the given path.
+///
+/// A record that was only partially written, such as when the capturing
+/// process crashed, is ignored.
+pub fn read_capture(path: &Path) -> Result<(CaptureHeader, Vec<CaptureRecord>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; CAPTURE_MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if magic != CAPTURE_MAGIC {
+        bail!("'{}' is not a network capture file", path.display());
+    }
+
+    let header: CaptureHeader = bincode::deserialize_from(&mut reader)?;
+
+    let mut records = vec![];
+    loop {
+        match bincode::deserialize_from(&mut reader) {
+            Ok(record) => records.push(record),
+            Err(err) => {
+                match *err {
+                    bincode::ErrorKind::Io(io) if io.kind() == ErrorKind::UnexpectedEof => break,
+                    err => return Err(err.into()),
+                }
+            },
+        }
+    }
+
+    Ok((header, records))
+}
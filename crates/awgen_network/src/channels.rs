@@ -0,0 +1,152 @@
+//! The configuration of the named network channels that messages are sent
+//! over.
+//!
+//! Each message type declares the [`MessageChannel`] that it is sent over. The
+//! [`NetworkChannels`] of the network plugin decide how the messages of each
+//! channel are delivered, and are used to configure the underlying transport.
+//! The server and its clients must use the same channel configuration.
+
+
+use crate::prelude::{MessageChannel, MessageSchema};
+use bevy::prelude::*;
+use bevy_renet::renet::{
+    ChannelConfig, ChunkChannelConfig, ReliableChannelConfig, RenetConnectionConfig, UnreliableChannelConfig
+};
+
+
+/// How the messages of a channel are delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelDelivery {
+    /// Messages are guaranteed to arrive, in the order that they were sent.
+    ReliableOrdered,
+
+    /// Messages are guaranteed to arrive, but may arrive in any order.
+    ReliableUnordered,
+
+    /// Messages may be dropped, and may arrive in any order.
+    Unreliable,
+
+    /// Large messages are split into slices, which are guaranteed to arrive
+    /// and are reassembled in the order that the messages were sent. Only a
+    /// single message is in flight at a time, so this is best suited for bulk
+    /// data, such as chunks.
+    Chunked,
+}
+
+impl ChannelDelivery {
+    /// Creates the transport configuration of a channel with the given ID and
+    /// this delivery.
+    fn config(self, channel_id: u8) -> ChannelConfig {
+        match self {
+            ChannelDelivery::ReliableOrdered | ChannelDelivery::ReliableUnordered => {
+                ChannelConfig::Reliable(ReliableChannelConfig {
+                    channel_id,
+                    ordered: self == ChannelDelivery::ReliableOrdered,
+                    ..default()
+                })
+            },
+            ChannelDelivery::Unreliable => {
+                ChannelConfig::Unreliable(UnreliableChannelConfig {
+                    channel_id,
+                    ..default()
+                })
+            },
+            ChannelDelivery::Chunked => {
+                ChannelConfig::Chunk(ChunkChannelConfig {
+                    channel_id,
+                    ..default()
+                })
+            },
+        }
+    }
+}
+
+
+/// The channels that are available to network messages, alongside how the
+/// messages of each channel are delivered.
+#[derive(Debug, Clone, Resource)]
+pub struct NetworkChannels {
+    /// Each configured channel and its delivery, ordered by channel ID.
+    channels: Vec<(MessageChannel, ChannelDelivery)>,
+}
+
+impl Default for NetworkChannels {
+    fn default() -> Self {
+        let mut channels = Self {
+            channels: vec![],
+        };
+
+        channels.set(MessageChannel::RELIABLE, ChannelDelivery::ReliableOrdered);
+        channels.set(MessageChannel::UNRELIABLE, ChannelDelivery::Unreliable);
+        channels.set(MessageChannel::CHAT, ChannelDelivery::ReliableOrdered);
+        channels.set(MessageChannel::ENTITY_MOVES, ChannelDelivery::Unreliable);
+        channels
+    }
+}
+
+impl NetworkChannels {
+    /// Configures the delivery of the given channel, replacing its previous
+    /// delivery if it was already configured.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another channel has already been configured with the same
+    /// channel ID.
+    pub fn set(&mut self, channel: MessageChannel, delivery: ChannelDelivery) {
+        match self.channels.binary_search_by_key(&channel.id, |(c, _)| c.id) {
+            Ok(index) => {
+                let existing = self.channels[index].0;
+                if existing != channel {
+                    panic!(
+                        "Channel ID {} of {} is already used by {}",
+                        channel.id, channel.name, existing.name
+                    );
+                }
+                self.channels[index].1 = delivery;
+            },
+            Err(index) => self.channels.insert(index, (channel, delivery)),
+        }
+    }
+
+
+    /// Gets the delivery of the given channel, if it has been configured.
+    pub fn get(&self, channel: MessageChannel) -> Option<ChannelDelivery> {
+        self.channels.iter().find(|(c, _)| *c == channel).map(|(_, delivery)| *delivery)
+    }
+
+
+    /// Gets an iterator over all configured channels.
+    pub fn iter(&self) -> impl Iterator<Item = MessageChannel> + '_ {
+        self.channels.iter().map(|(channel, _)| *channel)
+    }
+
+
+    /// Panics if any message type within the given schema is sent over a
+    /// channel that has not been configured.
+    pub fn assert_schema(&self, schema: &[MessageSchema]) {
+        for message in schema {
+            if self.get(message.channel).is_none() {
+                panic!(
+                    "Message {} is sent over the {} channel, which is not configured",
+                    message.name, message.channel.name
+                );
+            }
+        }
+    }
+
+
+    /// Creates the transport connection configuration for these channels.
+    pub fn connection_config(&self) -> RenetConnectionConfig {
+        let channels: Vec<ChannelConfig> = self
+            .channels
+            .iter()
+            .map(|(channel, delivery)| delivery.config(channel.id))
+            .collect();
+
+        RenetConnectionConfig {
+            send_channels_config: channels.clone(),
+            receive_channels_config: channels,
+            ..default()
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! The chat protocol, which allows players to send text messages that the
+//! server validates and broadcasts to all connected clients.
+
+
+use crate::prelude::{send_to_server, MessageBatch, PlayerRoster, ServerMessage};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The maximum number of characters within a single chat message. Longer
+/// messages are truncated.
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+
+
+/// A network message that is sent from a client to the server to post a
+/// message to the chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendChatMessage {
+    /// The text of the message.
+    pub text: String,
+}
+
+
+/// A network message that is sent from the server to clients when a message
+/// has been posted to the chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// The client id of the player that posted the message, or `None` if the
+    /// message was posted by the server.
+    pub sender: Option<u64>,
+
+    /// The text of the message.
+    pub text: String,
+}
+
+
+/// An event that is triggered on the client for each chat message that is
+/// received from the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessageReceivedEvent {
+    /// The client id of the player that posted the message, or `None` if the
+    /// message was posted by the server.
+    pub sender: Option<u64>,
+
+    /// The display name of the sender, as listed within the player roster. If
+    /// the sender is not within the roster, this is `None`.
+    pub sender_name: Option<String>,
+
+    /// The text of the message.
+    pub text: String,
+}
+
+
+/// Cleans up the given chat message text, removing control characters and
+/// surrounding whitespace, and truncating it to the maximum chat message
+/// length.
+///
+/// Returns `None` if nothing remains of the message.
+pub fn sanitize_chat_message(text: &str) -> Option<String> {
+    let text: String = text
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_MESSAGE_LENGTH)
+        .collect();
+
+    match text.trim_end().is_empty() {
+        true => None,
+        false => Some(text.trim_end().to_string()),
+    }
+}
+
+
+/// Sends a chat message to the server. Messages that are empty once sanitized
+/// are not sent.
+pub fn send_chat_message(batch: &mut MessageBatch, text: &str) {
+    if let Some(text) = sanitize_chat_message(text) {
+        send_to_server(batch, &SendChatMessage {
+            text,
+        });
+    }
+}
+
+
+/// Triggers a chat message received event for each chat message that was
+/// received from the server, resolving the name of the sender from the player
+/// roster.
+pub fn receive_chat_messages(
+    mut message_ev: EventReader<ServerMessage<ChatMessage>>,
+    mut chat_ev: EventWriter<ChatMessageReceivedEvent>,
+    roster: Res<PlayerRoster>,
+) {
+    for ev in message_ev.iter() {
+        let message = &ev.message;
+        let sender_name = message
+            .sender
+            .and_then(|client_id| roster.get(client_id))
+            .map(|entry| entry.name.clone());
+
+        chat_ev.send(ChatMessageReceivedEvent {
+            sender: message.sender,
+            sender_name,
+            text: message.text.clone(),
+        });
+    }
+}
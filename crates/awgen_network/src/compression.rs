@@ -0,0 +1,301 @@
+//! Transparent compression of large network messages.
+//!
+//! Once connected, each client reports whether it would like to receive
+//! compressed messages with a [`CompressionRequest`]. If compression is also
+//! enabled on the server, every message to that client with a payload above
+//! the compression threshold is compressed with LZ4 before being sent. This
+//! happens when the message batch is flushed, so messages that are sent
+//! directly, broadcast, or queued within the send budget are all covered.
+//!
+//! A compressed message has the highest bit of its message ID set, and its
+//! payload is prefixed with its uncompressed size. Received messages are
+//! decompressed before they are stored within the message inbox, so message
+//! handlers never see the difference.
+
+
+use crate::prelude::{send_to_server, ClientMessage, MessageBatch, MESSAGE_ID_BYTES};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_renet::renet::RenetServer;
+use serde::{Deserialize, Serialize};
+
+
+/// The default payload size, in bytes, above which messages are compressed.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 512;
+
+
+/// The largest uncompressed payload size, in bytes, that will be accepted when
+/// decompressing a message. This prevents a small message from claiming an
+/// enormous size and exhausting memory.
+pub const MAX_DECOMPRESSED_SIZE: usize = 4 * 1024 * 1024;
+
+
+/// The bit of a message ID that marks the payload of the message as being
+/// compressed.
+pub const COMPRESSED_FLAG: u16 = 0x8000;
+
+
+/// The number of bytes used to store the uncompressed size of a compressed
+/// payload.
+const SIZE_PREFIX_BYTES: usize = 4;
+
+
+/// The compression settings of this side of the network.
+///
+/// On a client, this decides whether compression is requested from the
+/// server. On the server, this decides whether those requests are accepted,
+/// and which messages are large enough to be compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct CompressionSettings {
+    /// Whether or not compression is enabled.
+    pub enabled: bool,
+
+    /// The payload size, in bytes, above which messages are compressed.
+    pub threshold: usize,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            enabled:   true,
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+}
+
+
+/// A network message that is sent from a client to the server once it has
+/// connected, to report whether it would like to receive compressed messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionRequest {
+    /// Whether or not the client would like to receive compressed messages.
+    pub enabled: bool,
+}
+
+
+/// A server-side resource that stores the clients that have negotiated to
+/// receive compressed messages.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct CompressedClients {
+    /// The client id of each client that receives compressed messages.
+    clients: HashSet<u64>,
+}
+
+impl CompressedClients {
+    /// Gets whether or not the client with the given id receives compressed
+    /// messages.
+    pub fn contains(&self, client_id: u64) -> bool {
+        self.clients.contains(&client_id)
+    }
+
+
+    /// Sets whether or not the client with the given id receives compressed
+    /// messages.
+    pub fn set(&mut self, client_id: u64, compressed: bool) {
+        match compressed {
+            true => {
+                self.clients.insert(client_id);
+            },
+            false => {
+                self.clients.remove(&client_id);
+            },
+        }
+    }
+
+
+    /// Compresses an encoded message that is about to be sent to the client
+    /// with the given id, if compression is enabled, the client receives
+    /// compressed messages, and the message is large enough to be compressed.
+    /// Otherwise, the message is returned unchanged.
+    pub fn compress(
+        &self,
+        settings: &CompressionSettings,
+        client_id: u64,
+        bytes: Vec<u8>,
+    ) -> Vec<u8> {
+        if !settings.enabled || !self.contains(client_id) {
+            return bytes;
+        }
+
+        compress_message(&bytes, settings.threshold).unwrap_or(bytes)
+    }
+}
+
+
+/// Compresses an encoded message, as returned by
+/// [`encode_message`](crate::prelude::encode_message), if its payload is
+/// larger than the given threshold.
+///
+/// Returns `None` if the message is not large enough to be compressed, if it
+/// is already compressed, or if compressing it would not make it any smaller.
+pub fn compress_message(bytes: &[u8], threshold: usize) -> Option<Vec<u8>> {
+    if bytes.len() < MESSAGE_ID_BYTES || bytes.len() - MESSAGE_ID_BYTES <= threshold {
+        return None;
+    }
+
+    let id = u16::from_le_bytes([bytes[0], bytes[1]]);
+    if id & COMPRESSED_FLAG != 0 {
+        return None;
+    }
+
+    let id = id | COMPRESSED_FLAG;
+    let mut compressed = id.to_le_bytes().to_vec();
+    compressed.extend(lz4_flex::compress_prepend_size(&bytes[MESSAGE_ID_BYTES..]));
+
+    match compressed.len() < bytes.len() {
+        true => Some(compressed),
+        false => None,
+    }
+}
+
+
+/// Decompresses the payload of a compressed message, without its message ID.
+pub fn decompress_payload(payload: &[u8]) -> Result<Vec<u8>> {
+    if payload.len() < SIZE_PREFIX_BYTES {
+        bail!("Compressed payload is too short to contain its size");
+    }
+
+    let mut size = [0; SIZE_PREFIX_BYTES];
+    size.copy_from_slice(&payload[..SIZE_PREFIX_BYTES]);
+    let size = u32::from_le_bytes(size) as usize;
+
+    if size > MAX_DECOMPRESSED_SIZE {
+        bail!("Compressed payload is too large: {size} bytes");
+    }
+
+    Ok(lz4_flex::decompress(&payload[SIZE_PREFIX_BYTES..], size)?)
+}
+
+
+/// Reports to the server whether the local client would like to receive
+/// compressed messages.
+pub fn request_compression(settings: Res<CompressionSettings>, mut batch: ResMut<MessageBatch>) {
+    send_to_server(&mut batch, &CompressionRequest {
+        enabled: settings.enabled,
+    });
+}
+
+
+/// Records which clients have negotiated to receive compressed messages, and
+/// forgets clients that have disconnected.
+///
+/// Requests are only accepted while compression is enabled on the server.
+pub fn apply_compression_requests(
+    mut request_ev: EventReader<ClientMessage<CompressionRequest>>,
+    settings: Res<CompressionSettings>,
+    server: Res<RenetServer>,
+    mut compressed: ResMut<CompressedClients>,
+) {
+    let connected = server.clients_id();
+    compressed.clients.retain(|id| connected.contains(id));
+
+    for ev in request_ev.iter() {
+        compressed.set(ev.client_id, settings.enabled && ev.message.enabled);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{encode_message, MessageChannel, NetworkMessage};
+    use pretty_assertions::assert_eq;
+
+
+    /// A message carrying the block data of a single chunk.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct ChunkData {
+        /// The coordinates of the chunk.
+        chunk_coords: (i32, i32, i32),
+
+        /// The block data of the chunk.
+        blocks: Vec<u8>,
+    }
+
+    impl NetworkMessage for ChunkData {
+        const CHANNEL: MessageChannel = MessageChannel::RELIABLE;
+        const ID: u16 = 0x7FFE;
+    }
+
+
+    /// Creates a chunk with a layer of ground and a few scattered blocks.
+    fn chunk() -> ChunkData {
+        let blocks = (0..4096)
+            .map(|i| {
+                match i {
+                    i if i % 256 < 16 * 4 => 1,
+                    i if i % 97 == 0 => 2,
+                    _ => 0,
+                }
+            })
+            .collect();
+
+        ChunkData {
+            chunk_coords: (3, -1, 7),
+            blocks,
+        }
+    }
+
+
+    /// Decodes the given encoded message, decompressing it if needed.
+    fn decode(bytes: &[u8]) -> ChunkData {
+        let id = u16::from_le_bytes([bytes[0], bytes[1]]);
+        assert_eq!(id & !COMPRESSED_FLAG, ChunkData::ID);
+
+        let payload = match id & COMPRESSED_FLAG != 0 {
+            true => decompress_payload(&bytes[MESSAGE_ID_BYTES..]).unwrap(),
+            false => bytes[MESSAGE_ID_BYTES..].to_vec(),
+        };
+
+        bincode::deserialize(&payload).unwrap()
+    }
+
+
+    #[test]
+    fn chunk_round_trip() {
+        let chunk = chunk();
+        let encoded = encode_message(&chunk);
+        let compressed = compress_message(&encoded, DEFAULT_COMPRESSION_THRESHOLD).unwrap();
+
+        assert!(compressed.len() < encoded.len());
+        assert_eq!(
+            u16::from_le_bytes([compressed[0], compressed[1]]),
+            ChunkData::ID | COMPRESSED_FLAG
+        );
+        assert_eq!(
+            decompress_payload(&compressed[MESSAGE_ID_BYTES..]).unwrap(),
+            encoded[MESSAGE_ID_BYTES..]
+        );
+        assert_eq!(decode(&compressed), chunk);
+        assert_eq!(
+            compress_message(&compressed, DEFAULT_COMPRESSION_THRESHOLD),
+            None
+        );
+    }
+
+
+    #[test]
+    fn small_messages_are_not_compressed() {
+        let chunk = ChunkData {
+            chunk_coords: (0, 0, 0),
+            blocks:       vec![1; 16],
+        };
+
+        let encoded = encode_message(&chunk);
+        assert_eq!(
+            compress_message(&encoded, DEFAULT_COMPRESSION_THRESHOLD),
+            None
+        );
+        assert_eq!(decode(&encoded), chunk);
+    }
+
+
+    #[test]
+    fn reject_oversized_payload() {
+        let mut payload = ((MAX_DECOMPRESSED_SIZE + 1) as u32).to_le_bytes().to_vec();
+        payload.extend([0; 8]);
+        assert!(decompress_payload(&payload).is_err());
+        assert!(decompress_payload(&[1, 0]).is_err());
+    }
+}
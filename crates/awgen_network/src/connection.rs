@@ -0,0 +1,124 @@
+//! Drives the client application state based on the state of the connection to
+//! the server, and reports why the client was disconnected.
+
+
+use crate::prelude::{
+    finish_reconnect, schedule_reconnect, DisconnectCause, DisconnectMessage, ReconnectSettings, ReconnectState, ReconnectedEvent, ReconnectingEvent, Reconnection, ServerMessage
+};
+use awgen_physics::prelude::AppState;
+use bevy::prelude::*;
+use bevy_renet::renet::{DisconnectionReason, RenetClient};
+
+
+/// An event that is triggered on the client when it has been disconnected from
+/// the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisconnectedEvent {
+    /// The cause of the disconnect.
+    pub cause: DisconnectCause,
+
+    /// A human readable reason for the disconnect.
+    pub reason: String,
+}
+
+
+/// The reason for an upcoming disconnect, as reported by the server, which is
+/// used in place of the reason reported by the connection itself.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PendingDisconnect(pub Option<DisconnectMessage>);
+
+
+/// Stores the reason for a disconnect that was received from the server, and
+/// closes the connection.
+pub fn apply_disconnect_messages(
+    mut disconnect_ev: EventReader<ServerMessage<DisconnectMessage>>,
+    mut pending: ResMut<PendingDisconnect>,
+    mut client: ResMut<RenetClient>,
+) {
+    let Some(ev) = disconnect_ev.iter().last() else {
+        return;
+    };
+
+    pending.0 = Some(ev.message.clone());
+    client.disconnect();
+}
+
+
+/// Gets the cause of a disconnect that was not reported by the server, from
+/// the reason reported by the connection.
+fn disconnect_cause(reason: &DisconnectionReason) -> DisconnectCause {
+    match reason {
+        DisconnectionReason::Timeout => DisconnectCause::Timeout,
+        DisconnectionReason::DisconnectedByServer => DisconnectCause::Kicked,
+        _ => DisconnectCause::ConnectionLost,
+    }
+}
+
+
+/// Moves the client into the loading state once it has connected to the
+/// server, and back to the main menu if the connection is lost.
+///
+/// If an established connection was lost unexpectedly, the client instead
+/// returns to the connecting state while it attempts to reconnect, and only
+/// returns to the main menu once all attempts have failed.
+#[allow(clippy::too_many_arguments)]
+pub fn update_connection_state(
+    time: Res<Time>,
+    client: Res<RenetClient>,
+    settings: Res<ReconnectSettings>,
+    mut state: ResMut<State<AppState>>,
+    mut pending: ResMut<PendingDisconnect>,
+    mut reconnection: ResMut<Reconnection>,
+    mut disconnected_ev: EventWriter<DisconnectedEvent>,
+    mut reconnecting_ev: EventWriter<ReconnectingEvent>,
+    mut reconnected_ev: EventWriter<ReconnectedEvent>,
+) {
+    let current = *state.current();
+
+    if let Some(reason) = client.disconnected() {
+        let waiting = matches!(reconnection.state(), ReconnectState::Waiting { .. });
+        if current != AppState::MainMenu && !waiting {
+            let ev = match pending.0.take() {
+                Some(message) => {
+                    DisconnectedEvent {
+                        cause:  message.cause,
+                        reason: message.reason,
+                    }
+                },
+                None => {
+                    DisconnectedEvent {
+                        cause:  disconnect_cause(&reason),
+                        reason: reason.to_string(),
+                    }
+                },
+            };
+
+            let Some(ev) = schedule_reconnect(
+                ev,
+                current.is_connected(),
+                time.elapsed_seconds_f64(),
+                &settings,
+                &mut reconnection,
+                &mut reconnecting_ev,
+            ) else {
+                if current != AppState::Connecting {
+                    let _ = state.set(AppState::Connecting);
+                }
+                return;
+            };
+
+            warn!("Disconnected from server: {} ({})", ev.cause, ev.reason);
+            disconnected_ev.send(ev);
+            let _ = state.set(AppState::MainMenu);
+        }
+        return;
+    }
+
+    if current == AppState::Connecting && client.is_connected() {
+        match finish_reconnect(&mut reconnection, &mut reconnected_ev) {
+            Some(attempts) => info!("Reconnected to server after {attempts} attempt(s)"),
+            None => info!("Connected to server"),
+        }
+        let _ = state.set(AppState::LoadingWorld);
+    }
+}
@@ -0,0 +1,144 @@
+//! Synchronizes the contents of the container that a player is viewing, and
+//! sends the actions that the player takes within it back to the server.
+
+
+use crate::prelude::{send_to_server, MessageBatch, ServerMessage};
+use awgen_physics::prelude::ItemStack;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// A network message that is sent from the server to a client to update the
+/// container that the player is viewing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerMessage {
+    /// The player has opened the container at the given block position.
+    Open {
+        /// The position of the container block.
+        block_pos: IVec3,
+
+        /// The item stack within each slot of the container.
+        slots: Vec<ItemStack>,
+
+        /// The revision of the container inventory.
+        revision: u32,
+    },
+
+    /// The contents of the container at the given block position have
+    /// changed.
+    Update {
+        /// The position of the container block.
+        block_pos: IVec3,
+
+        /// The item stack within each slot of the container.
+        slots: Vec<ItemStack>,
+
+        /// The revision of the container inventory.
+        revision: u32,
+    },
+
+    /// The container at the given block position has been closed.
+    Close {
+        /// The position of the container block.
+        block_pos: IVec3,
+    },
+}
+
+
+/// An action that is sent from a client to the server to change the container
+/// that the player is viewing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerAction {
+    /// Moves the item stack within one slot to another slot.
+    Move {
+        /// The slot to move the item stack from.
+        from: u16,
+
+        /// The slot to move the item stack to.
+        to: u16,
+
+        /// The revision of the container inventory that the move was made
+        /// against.
+        revision: u32,
+    },
+
+    /// Closes the container.
+    Close,
+}
+
+
+/// The container that is currently opened by the local player.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenContainer {
+    /// The position of the container block.
+    pub block_pos: IVec3,
+
+    /// The item stack within each slot of the container.
+    pub slots: Vec<ItemStack>,
+
+    /// The revision of the container inventory.
+    pub revision: u32,
+}
+
+
+/// A client-side resource that stores the container that the local player is
+/// currently viewing, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ContainerView {
+    /// The open container, if any.
+    open: Option<OpenContainer>,
+}
+
+impl ContainerView {
+    /// Gets the container that the local player is viewing, if any.
+    pub fn get(&self) -> Option<&OpenContainer> {
+        self.open.as_ref()
+    }
+}
+
+
+/// Sends a container action to the server.
+pub fn send_container_action(batch: &mut MessageBatch, action: ContainerAction) {
+    send_to_server(batch, &action);
+}
+
+
+/// Applies the container messages that were received from the server to the
+/// container view of the local player.
+pub fn apply_container_messages(
+    mut container_ev: EventReader<ServerMessage<ContainerMessage>>,
+    mut view: ResMut<ContainerView>,
+) {
+    for ev in container_ev.iter() {
+        match ev.message.clone() {
+            ContainerMessage::Open {
+                block_pos,
+                slots,
+                revision,
+            } => {
+                view.open = Some(OpenContainer {
+                    block_pos,
+                    slots,
+                    revision,
+                })
+            },
+            ContainerMessage::Update {
+                block_pos,
+                slots,
+                revision,
+            } => {
+                if let Some(open) = view.open.as_mut().filter(|open| open.block_pos == block_pos) {
+                    open.slots = slots;
+                    open.revision = revision;
+                }
+            },
+            ContainerMessage::Close {
+                block_pos,
+            } => {
+                if view.open.as_ref().is_some_and(|open| open.block_pos == block_pos) {
+                    view.open = None;
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Requests from clients to break the block that they are looking at, or to
+//! place a new block against it, and the responses of the server to each
+//! request.
+//!
+//! Clients apply their own edits immediately, and tag each request with a
+//! sequence number. The server validates and applies each request in order,
+//! then reports whether or not it was accepted, so that clients may roll back
+//! the edits that were rejected.
+
+
+use crate::prelude::{send_to_server, MessageBatch};
+use awgen_math::prelude::Direction;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The type of change that a block edit request makes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockEditAction {
+    /// Breaks the targeted block.
+    Break,
+
+    /// Places a block against the targeted face of the targeted block.
+    Place {
+        /// The name of the item that is placed as a block.
+        item: String,
+    },
+}
+
+
+/// A network message that is sent from a client to the server to edit a block.
+///
+/// Block edits are discrete actions that must be applied in order, so they
+/// are sent over the reliable channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockEditMessage {
+    /// The sequence number of the edit, which is returned within the response
+    /// of the server.
+    pub sequence: u32,
+
+    /// The position of the block that the player is looking at.
+    pub block_pos: IVec3,
+
+    /// The face of the block that the player is looking at.
+    pub face: Direction,
+
+    /// The change to make.
+    pub action: BlockEditAction,
+}
+
+impl BlockEditMessage {
+    /// Gets the position of the block that is changed by this edit.
+    ///
+    /// Broken blocks are the targeted block itself, while placed blocks are
+    /// placed within the neighboring cell of the targeted face.
+    pub fn edited_pos(&self) -> IVec3 {
+        match self.action {
+            BlockEditAction::Break => self.block_pos,
+            BlockEditAction::Place {
+                ..
+            } => self.block_pos + self.face.offset(),
+        }
+    }
+}
+
+
+/// A network message that is sent from the server to a client in response to
+/// each block edit request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockEditAck {
+    /// The sequence number of the edit request.
+    pub sequence: u32,
+
+    /// Whether or not the edit was applied by the server.
+    pub accepted: bool,
+}
+
+
+/// Sends a request to the server to edit the block at the given position.
+pub fn send_block_edit(
+    batch: &mut MessageBatch,
+    sequence: u32,
+    block_pos: IVec3,
+    face: Direction,
+    action: BlockEditAction,
+) {
+    let message = BlockEditMessage {
+        sequence,
+        block_pos,
+        face,
+        action,
+    };
+
+    send_to_server(batch, &message);
+}
@@ -0,0 +1,191 @@
+//! Visual and audio effects that are triggered on the server and replicated to
+//! clients, such as explosions and particles.
+
+
+use crate::prelude::ServerMessage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The distance, in meters, that a sound may be heard from for each unit of
+/// volume.
+pub const AUDIBLE_DISTANCE_PER_VOLUME: f32 = 16.0;
+
+
+/// A network message that is sent from the server to clients to play an effect
+/// that occurred within the world of the player.
+///
+/// Effects are purely cosmetic, so they are sent over the unreliable channel.
+/// Any changes to blocks or entities that are caused by an effect are
+/// replicated separately.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EffectMessage {
+    /// An explosion occurred.
+    Explosion {
+        /// The center of the explosion, in world space.
+        center: Vec3,
+
+        /// The power of the explosion.
+        power: f32,
+    },
+
+    /// A burst of particles was emitted.
+    Particles(ParticleEvent),
+
+    /// A block was used by a player.
+    BlockUsed {
+        /// The client ID of the player that used the block.
+        client_id: u64,
+
+        /// The position of the block that was used.
+        block_pos: IVec3,
+
+        /// Whether or not using the block opened its container interface.
+        opened_container: bool,
+    },
+
+    /// A sound was played.
+    Sound(SoundEvent),
+}
+
+
+/// The kinds of particles that may be emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ParticleKind {
+    /// Small cubes of debris from a block that was broken.
+    BlockDebris {
+        /// The color of the broken block, as 8-bit RGB values.
+        color: [u8; 3],
+    },
+
+    /// A small puff of dust kicked up by a footstep.
+    FootstepDust,
+
+    /// Large, slowly rising clouds of smoke left behind by an explosion.
+    ExplosionSmoke,
+
+    /// Fast falling drops of rain.
+    Raindrop,
+}
+
+
+/// A burst of particles that is emitted at a position within the world.
+///
+/// On the server, this is sent to players within a world as part of an effect
+/// message. On the client, it is triggered as an event when such a message is
+/// received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParticleEvent {
+    /// The kind of particles to emit.
+    pub kind: ParticleKind,
+
+    /// The center of the burst, in world space.
+    pub position: Vec3,
+
+    /// The number of particles to emit.
+    pub count: u16,
+}
+
+
+/// A sound that is played at a position within the world.
+///
+/// On the server, this is sent to players within audible range as part of an
+/// effect message. On the client, it is triggered as an event when such a
+/// message is received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoundEvent {
+    /// The id of the sound to play, as a dot separated path, such as
+    /// `mob.zombie.groan`.
+    pub sound_id: String,
+
+    /// The position that the sound is played at, in world space.
+    pub position: Vec3,
+
+    /// The volume of the sound, where 1 is the natural volume of the sound.
+    pub volume: f32,
+}
+
+impl SoundEvent {
+    /// Gets the distance, in meters, that this sound may be heard from.
+    pub fn audible_distance(&self) -> f32 {
+        self.volume.max(0.0) * AUDIBLE_DISTANCE_PER_VOLUME
+    }
+
+
+    /// Gets whether or not the sound id of this event is valid. Valid sound
+    /// ids are made of lowercase letters, digits, and underscores, with each
+    /// path segment separated by a dot.
+    pub fn has_valid_id(&self) -> bool {
+        self.sound_id.split('.').all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        })
+    }
+}
+
+
+/// An event that is triggered on the client when the server reports that an
+/// explosion occurred within the world of the local player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplosionEffectEvent {
+    /// The center of the explosion, in world space.
+    pub center: Vec3,
+
+    /// The power of the explosion.
+    pub power: f32,
+}
+
+
+/// An event that is triggered on the client when the server reports that a
+/// block was used within the world of the local player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockUsedEffectEvent {
+    /// The client ID of the player that used the block.
+    pub client_id: u64,
+
+    /// The position of the block that was used.
+    pub block_pos: IVec3,
+
+    /// Whether or not using the block opened its container interface.
+    pub opened_container: bool,
+}
+
+
+/// Triggers the matching effect event for each effect message that was
+/// received from the server.
+pub fn dispatch_effect_messages(
+    mut effect_ev: EventReader<ServerMessage<EffectMessage>>,
+    mut explosion_ev: EventWriter<ExplosionEffectEvent>,
+    mut particle_ev: EventWriter<ParticleEvent>,
+    mut block_used_ev: EventWriter<BlockUsedEffectEvent>,
+    mut sound_ev: EventWriter<SoundEvent>,
+) {
+    for ev in effect_ev.iter() {
+        match ev.message.clone() {
+            EffectMessage::Explosion {
+                center,
+                power,
+            } => {
+                explosion_ev.send(ExplosionEffectEvent {
+                    center,
+                    power,
+                })
+            },
+            EffectMessage::Particles(particles) => particle_ev.send(particles),
+            EffectMessage::BlockUsed {
+                client_id,
+                block_pos,
+                opened_container,
+            } => {
+                block_used_ev.send(BlockUsedEffectEvent {
+                    client_id,
+                    block_pos,
+                    opened_container,
+                })
+            },
+            EffectMessage::Sound(sound) => sound_ev.send(sound),
+        }
+    }
+}
@@ -0,0 +1,746 @@
+//! An optional encryption layer for the game messages that are sent between
+//! the server and its clients, so that chat and authentication data cannot be
+//! read by anyone listening in on the connection.
+//!
+//! Servers that require connect tokens are already encrypted by the transport.
+//! Servers that do not may opt into this layer instead, using
+//! [`NetworkPlugin::with_encryption`](crate::NetworkPlugin::with_encryption).
+//!
+//! Each client generates a new X25519 key pair for every connection, and sends
+//! its public key within its user data. Once connected, the server replies
+//! with the public key of its own key pair within an [`EncryptionKey`]
+//! message. Both sides then derive a pair of XChaCha20-Poly1305 keys from the
+//! shared secret, one for each direction.
+//!
+//! Encrypted messages are sent with the reserved [`ENCRYPTED_MESSAGE_ID`],
+//! followed by a little-endian `u64` counter that is used as the nonce, and
+//! the encrypted message, including its own message ID. The handshake,
+//! disconnect, and encryption key messages are never encrypted, as they may be
+//! exchanged before the keys are known.
+//!
+//! Game messages that a client sends before it has received the public key of
+//! the server are sent in plain text. The server holds these back, and only
+//! accepts them once the client has sent its first encrypted message, so the
+//! game messages of a client that never encrypts its connection are never
+//! accepted. From then on, game messages in plain text are dropped. If the
+//! server sends an invalid public key, the client disconnects instead.
+//!
+//! This layer protects against passive eavesdroppers only. The server has no
+//! long-term identity that clients could verify its public key against, so an
+//! attacker that is able to intercept and rewrite packets may still read the
+//! connection. Replayed messages are not detected either, and the user data
+//! within the connection request is always sent in plain text.
+
+
+use crate::prelude::{
+    encode_message, DisconnectMessage, HandshakeMessage, KickClient, NetworkMessage, UserData, MESSAGE_ID_BYTES
+};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_renet::renet::{RenetServer, ServerEvent};
+use blake2::{Blake2s256, Digest};
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use serde::{Deserialize, Serialize};
+
+
+/// The message ID that is reserved for encrypted messages. No message type may
+/// ever be assigned this ID.
+pub const ENCRYPTED_MESSAGE_ID: u16 = 0x7FFF;
+
+
+/// The number of bytes within a public key.
+pub const PUBLIC_KEY_BYTES: usize = 32;
+
+
+/// The number of bytes used to store the counter of each encrypted message.
+const COUNTER_BYTES: usize = 8;
+
+
+/// The maximum number of messages that are held back until the session has
+/// started, on either side of the connection. Further messages are dropped.
+const MAX_EARLY_MESSAGES: usize = 1024;
+
+
+/// The label that the key for messages sent from the client to the server is
+/// derived with.
+const CLIENT_TO_SERVER: &[u8] = b"awgen client to server";
+
+
+/// The label that the key for messages sent from the server to the client is
+/// derived with.
+const SERVER_TO_CLIENT: &[u8] = b"awgen server to client";
+
+
+/// A network message that is sent from the server to a client as soon as it
+/// has connected, carrying the public key of the server for this connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionKey {
+    /// The public key of the server.
+    pub public_key: [u8; PUBLIC_KEY_BYTES],
+}
+
+
+/// An ephemeral X25519 secret key, which is only ever used for a single
+/// connection.
+#[derive(Clone)]
+pub struct SecretKey([u8; 32]);
+
+impl SecretKey {
+    /// Generates a new random secret key.
+    ///
+    /// Returns an error if the operating system could not provide any
+    /// randomness.
+    pub fn generate() -> Result<Self> {
+        let mut bytes = [0; 32];
+        if let Err(err) = getrandom::getrandom(&mut bytes) {
+            bail!("Failed to generate a secret key: {err}");
+        }
+        Ok(Self(bytes))
+    }
+
+
+    /// Gets the public key of this secret key.
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        MontgomeryPoint::mul_base_clamped(self.0).to_bytes()
+    }
+
+
+    /// Computes the secret that is shared with the owner of the given public
+    /// key.
+    ///
+    /// Returns an error if the public key is of low order, in which case the
+    /// shared secret would be known to anyone.
+    fn agree(&self, public_key: &[u8; PUBLIC_KEY_BYTES]) -> Result<[u8; 32]> {
+        let shared = MontgomeryPoint(*public_key).mul_clamped(self.0).to_bytes();
+        if shared == [0; 32] {
+            bail!("Invalid public key");
+        }
+        Ok(shared)
+    }
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+
+/// The keys of a single encrypted connection.
+#[derive(Clone)]
+pub struct SessionCipher {
+    /// The cipher that sent messages are encrypted with.
+    send: XChaCha20Poly1305,
+
+    /// The cipher that received messages are decrypted with.
+    receive: XChaCha20Poly1305,
+
+    /// The counter of the next sent message.
+    counter: u64,
+}
+
+impl SessionCipher {
+    /// Creates the client side of a session from the secret key of the client
+    /// and the public key of the server.
+    pub fn client(secret: &SecretKey, server_key: &[u8; PUBLIC_KEY_BYTES]) -> Result<Self> {
+        let shared = secret.agree(server_key)?;
+        let client_key = secret.public_key();
+        Ok(Self {
+            send:    derive_cipher(&shared, &client_key, server_key, CLIENT_TO_SERVER),
+            receive: derive_cipher(&shared, &client_key, server_key, SERVER_TO_CLIENT),
+            counter: 0,
+        })
+    }
+
+
+    /// Creates the server side of a session from the secret key of the server
+    /// and the public key of the client.
+    pub fn server(secret: &SecretKey, client_key: &[u8; PUBLIC_KEY_BYTES]) -> Result<Self> {
+        let shared = secret.agree(client_key)?;
+        let server_key = secret.public_key();
+        Ok(Self {
+            send:    derive_cipher(&shared, client_key, &server_key, SERVER_TO_CLIENT),
+            receive: derive_cipher(&shared, client_key, &server_key, CLIENT_TO_SERVER),
+            counter: 0,
+        })
+    }
+
+
+    /// Encrypts the given serialized message, returning the encrypted message.
+    ///
+    /// Returns an error if the message is too large to be encrypted.
+    pub fn encrypt(&mut self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let Ok(ciphertext) = self.send.encrypt(&nonce(self.counter), bytes) else {
+            bail!("Failed to encrypt message");
+        };
+
+        let mut message = ENCRYPTED_MESSAGE_ID.to_le_bytes().to_vec();
+        message.extend_from_slice(&self.counter.to_le_bytes());
+        message.extend(ciphertext);
+        self.counter += 1;
+        Ok(message)
+    }
+
+
+    /// Decrypts the given encrypted message, returning the serialized message.
+    ///
+    /// Returns an error if the message is not an encrypted message, or if it
+    /// was not encrypted with the key of the other side.
+    pub fn decrypt(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        if message_id(bytes) != Some(ENCRYPTED_MESSAGE_ID)
+            || bytes.len() < MESSAGE_ID_BYTES + COUNTER_BYTES
+        {
+            bail!("Not an encrypted message");
+        }
+
+        let mut counter = [0; COUNTER_BYTES];
+        counter.copy_from_slice(&bytes[MESSAGE_ID_BYTES..MESSAGE_ID_BYTES + COUNTER_BYTES]);
+        let counter = u64::from_le_bytes(counter);
+
+        match self
+            .receive
+            .decrypt(&nonce(counter), &bytes[MESSAGE_ID_BYTES + COUNTER_BYTES..])
+        {
+            Ok(message) => Ok(message),
+            Err(_) => bail!("Failed to decrypt message"),
+        }
+    }
+}
+
+impl std::fmt::Debug for SessionCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionCipher")
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Derives the cipher for a single direction of a session from the shared
+/// secret and both public keys.
+fn derive_cipher(
+    shared: &[u8; 32],
+    client_key: &[u8; PUBLIC_KEY_BYTES],
+    server_key: &[u8; PUBLIC_KEY_BYTES],
+    label: &[u8],
+) -> XChaCha20Poly1305 {
+    let key = Blake2s256::new()
+        .chain_update(label)
+        .chain_update(shared)
+        .chain_update(client_key)
+        .chain_update(server_key)
+        .finalize();
+    XChaCha20Poly1305::new(&key)
+}
+
+
+/// Creates the nonce of the message with the given counter.
+fn nonce(counter: u64) -> XNonce {
+    let mut nonce = XNonce::default();
+    nonce[..COUNTER_BYTES].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+
+/// Gets the message ID of the given serialized message, if it is long enough
+/// to contain one.
+fn message_id(bytes: &[u8]) -> Option<u16> {
+    match bytes {
+        [a, b, ..] => Some(u16::from_le_bytes([*a, *b])),
+        _ => None,
+    }
+}
+
+
+/// Gets whether or not the given serialized message is always sent in plain
+/// text, as it may be sent before the keys are known.
+fn is_plain_text(bytes: &[u8]) -> bool {
+    matches!(
+        message_id(bytes),
+        Some(HandshakeMessage::ID | DisconnectMessage::ID | EncryptionKey::ID)
+    )
+}
+
+
+/// A client-side resource that stores the secret key of the current
+/// connection, and the session with the server once the server has sent its
+/// public key.
+#[derive(Debug, Clone, Resource)]
+pub struct ClientEncryption {
+    /// The secret key of the current connection.
+    secret: SecretKey,
+
+    /// The session with the server, if the server has sent its public key.
+    session: Option<SessionCipher>,
+
+    /// The encrypted messages that were received before the server sent its
+    /// public key, as the public key is sent over a different channel than
+    /// many encrypted messages.
+    early: Vec<Vec<u8>>,
+}
+
+impl ClientEncryption {
+    /// Creates a new client encryption resource with a new random secret key.
+    ///
+    /// Returns an error if the operating system could not provide any
+    /// randomness.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            secret:  SecretKey::generate()?,
+            session: None,
+            early:   vec![],
+        })
+    }
+
+
+    /// Gets the public key of the current connection, which is sent to the
+    /// server within the user data.
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        self.secret.public_key()
+    }
+
+
+    /// Replaces the secret key with a new random secret key for a new
+    /// connection, and ends the current session.
+    ///
+    /// Returns an error if the operating system could not provide any
+    /// randomness.
+    pub fn regenerate(&mut self) -> Result<()> {
+        *self = Self::new()?;
+        Ok(())
+    }
+
+
+    /// Gets whether or not the messages of the current connection are
+    /// encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.session.is_some()
+    }
+
+
+    /// Encrypts the given serialized message before it is sent to the server,
+    /// unless the connection is not encrypted or the message is always sent
+    /// in plain text.
+    ///
+    /// Returns None if the message could not be encrypted, in which case it
+    /// must be dropped.
+    pub fn seal(&mut self, bytes: Vec<u8>) -> Option<Vec<u8>> {
+        let Some(session) = &mut self.session else {
+            return Some(bytes);
+        };
+
+        if is_plain_text(&bytes) {
+            return Some(bytes);
+        }
+
+        match session.encrypt(&bytes) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                warn!("Dropped message to server: {err}");
+                None
+            },
+        }
+    }
+
+
+    /// Decrypts the given message that was received from the server, returning
+    /// each serialized message that is received by now.
+    ///
+    /// Encrypted messages that arrive before the public key of the server are
+    /// held back, and are returned right after the message that carries the
+    /// public key. Once the session has started, further public keys and
+    /// messages that should have been encrypted are dropped.
+    ///
+    /// Returns an error if the server sent an invalid public key, in which
+    /// case the connection must be closed.
+    pub fn open(&mut self, bytes: Vec<u8>) -> Result<Vec<Vec<u8>>> {
+        let id = message_id(&bytes);
+        let Some(session) = &self.session else {
+            return match id {
+                Some(EncryptionKey::ID) => self.start_session(bytes),
+                Some(ENCRYPTED_MESSAGE_ID) if self.early.len() < MAX_EARLY_MESSAGES => {
+                    self.early.push(bytes);
+                    Ok(vec![])
+                },
+                Some(ENCRYPTED_MESSAGE_ID) => {
+                    warn!("Dropped encrypted message received before the server sent its key");
+                    Ok(vec![])
+                },
+                _ => Ok(vec![bytes]),
+            };
+        };
+
+        if id == Some(EncryptionKey::ID) {
+            warn!("Ignored another encryption key from the server");
+            return Ok(vec![]);
+        }
+
+        if is_plain_text(&bytes) {
+            return Ok(vec![bytes]);
+        }
+
+        match session.decrypt(&bytes) {
+            Ok(message) => Ok(vec![message]),
+            Err(err) => {
+                warn!("Dropped message from server: {err}");
+                Ok(vec![])
+            },
+        }
+    }
+
+
+    /// Starts the session with the server from the given serialized encryption
+    /// key message, returning the message followed by each held back message.
+    ///
+    /// Returns an error if the encryption key is invalid.
+    fn start_session(&mut self, bytes: Vec<u8>) -> Result<Vec<Vec<u8>>> {
+        let early = std::mem::take(&mut self.early);
+        let session = bincode::deserialize::<EncryptionKey>(&bytes[MESSAGE_ID_BYTES..])
+            .map_err(anyhow::Error::from)
+            .and_then(|key| SessionCipher::client(&self.secret, &key.public_key));
+
+        let session = match session {
+            Ok(session) => session,
+            Err(err) => bail!("Received an invalid encryption key from the server: {err:#}"),
+        };
+
+        info!("Encrypting the connection to the server");
+        let mut messages = vec![bytes];
+        for message in early {
+            match session.decrypt(&message) {
+                Ok(message) => messages.push(message),
+                Err(err) => warn!("Dropped message from server: {err}"),
+            }
+        }
+
+        self.session = Some(session);
+        Ok(messages)
+    }
+}
+
+
+/// The session of a single client on the server.
+#[derive(Debug, Clone)]
+struct ClientSession {
+    /// The keys of the session.
+    cipher: SessionCipher,
+
+    /// Whether or not an encrypted message has been received from the client.
+    /// Until then, the client may not know the public key of the server yet,
+    /// so messages in plain text are held back instead of being dropped.
+    confirmed: bool,
+
+    /// The messages in plain text that were received before the first
+    /// encrypted message of the client.
+    early: Vec<Vec<u8>>,
+}
+
+impl ClientSession {
+    /// Creates a new unconfirmed session with the given keys.
+    fn new(cipher: SessionCipher) -> Self {
+        Self {
+            cipher,
+            confirmed: false,
+            early: vec![],
+        }
+    }
+}
+
+
+/// A server-side resource that stores the session of each connected client,
+/// which only exists if the server requires encrypted connections.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ServerEncryption {
+    /// The session of each client, by client id.
+    sessions: HashMap<u64, ClientSession>,
+}
+
+impl ServerEncryption {
+    /// Gets whether or not the connection of the client with the given id is
+    /// encrypted.
+    pub fn is_encrypted(&self, client_id: u64) -> bool {
+        self.sessions.contains_key(&client_id)
+    }
+
+
+    /// Encrypts the given serialized message before it is sent to the given
+    /// client, unless the connection is not encrypted or the message is always
+    /// sent in plain text.
+    ///
+    /// Returns None if the message could not be encrypted, in which case it
+    /// must be dropped.
+    pub fn seal(&mut self, client_id: u64, bytes: Vec<u8>) -> Option<Vec<u8>> {
+        let Some(session) = self.sessions.get_mut(&client_id) else {
+            return Some(bytes);
+        };
+
+        if is_plain_text(&bytes) {
+            return Some(bytes);
+        }
+
+        match session.cipher.encrypt(&bytes) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                warn!("Dropped message to client {client_id}: {err}");
+                None
+            },
+        }
+    }
+
+
+    /// Decrypts the given message that was received from the given client,
+    /// returning each serialized message that is accepted by now.
+    ///
+    /// Messages that should have been encrypted are held back until the first
+    /// encrypted message of the client, and are returned right before it. Once
+    /// an encrypted message has been received from a client, messages from
+    /// that client that should have been encrypted are dropped.
+    pub fn open(&mut self, client_id: u64, bytes: Vec<u8>) -> Vec<Vec<u8>> {
+        let Some(session) = self.sessions.get_mut(&client_id) else {
+            return vec![bytes];
+        };
+
+        if message_id(&bytes) != Some(ENCRYPTED_MESSAGE_ID) {
+            if is_plain_text(&bytes) {
+                return vec![bytes];
+            }
+
+            match session.confirmed || session.early.len() >= MAX_EARLY_MESSAGES {
+                true => warn!("Dropped unencrypted message from client {client_id}"),
+                false => session.early.push(bytes),
+            }
+            return vec![];
+        }
+
+        match session.cipher.decrypt(&bytes) {
+            Ok(message) => {
+                session.confirmed = true;
+                let mut messages = std::mem::take(&mut session.early);
+                messages.push(message);
+                messages
+            },
+            Err(err) => {
+                warn!("Dropped message from client {client_id}: {err}");
+                vec![]
+            },
+        }
+    }
+}
+
+
+/// Starts an encrypted session with each newly connected client, sending it
+/// the public key of the server, and ends the session of each disconnected
+/// client.
+///
+/// Clients that did not send a public key within their user data are kicked.
+/// The encryption key message is sent directly over the transport, rather
+/// than through the message batch, so that it arrives before any encrypted
+/// message on the same channel. Encrypted messages on other channels may still
+/// arrive first, which the client holds back until the key arrives.
+pub fn exchange_encryption_keys(
+    mut events: EventReader<ServerEvent>,
+    mut encryption: ResMut<ServerEncryption>,
+    mut server: ResMut<RenetServer>,
+    mut ev_kick: EventWriter<KickClient>,
+) {
+    for event in events.iter() {
+        match event {
+            ServerEvent::ClientConnected(id, user_data) => {
+                let client_key =
+                    UserData::decode(user_data).ok().and_then(|user_data| user_data.public_key);
+
+                let Some(client_key) = client_key else {
+                    let reason = "This server requires an encrypted connection";
+                    ev_kick.send(KickClient::from_id(*id, reason));
+                    continue;
+                };
+
+                let session = SecretKey::generate().and_then(|secret| {
+                    let cipher = SessionCipher::server(&secret, &client_key)?;
+                    Ok((cipher, secret.public_key()))
+                });
+
+                let (cipher, public_key) = match session {
+                    Ok(session) => session,
+                    Err(err) => {
+                        warn!("Failed to encrypt the connection of client {id}: {err:#}");
+                        ev_kick.send(KickClient::from_id(*id, "Invalid encryption key"));
+                        continue;
+                    },
+                };
+
+                let message = encode_message(&EncryptionKey {
+                    public_key,
+                });
+                server.send_message(*id, EncryptionKey::CHANNEL, message);
+
+                encryption.sessions.insert(*id, ClientSession::new(cipher));
+            },
+            ServerEvent::ClientDisconnected(id) => {
+                encryption.sessions.remove(id);
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::ChatMessage;
+    use pretty_assertions::assert_eq;
+
+
+    /// A serialized chat message, which is always encrypted.
+    const CHAT: [u8; 5] = [ChatMessage::ID as u8, 0, 1, 2, 3];
+
+
+    /// Creates a client and the server side of its session, before the public
+    /// key of the server has been sent, alongside the public key message.
+    fn connect() -> (ClientEncryption, ServerEncryption, Vec<u8>) {
+        let client = ClientEncryption::new().unwrap();
+        let secret = SecretKey::generate().unwrap();
+
+        let mut server = ServerEncryption::default();
+        let cipher = SessionCipher::server(&secret, &client.public_key()).unwrap();
+        server.sessions.insert(1, ClientSession::new(cipher));
+
+        let key = encode_message(&EncryptionKey {
+            public_key: secret.public_key(),
+        });
+        (client, server, key)
+    }
+
+
+    #[test]
+    fn round_trip() {
+        let (mut client, mut server, key) = connect();
+        assert_eq!(client.open(key.clone()).unwrap(), vec![key]);
+        assert!(client.is_encrypted());
+
+        let sent = server.seal(1, CHAT.to_vec()).unwrap();
+        assert_eq!(message_id(&sent), Some(ENCRYPTED_MESSAGE_ID));
+        assert_ne!(&sent[sent.len() - CHAT.len()..], &CHAT[..]);
+        assert_eq!(client.open(sent).unwrap(), vec![CHAT.to_vec()]);
+
+        let sent = client.seal(CHAT.to_vec()).unwrap();
+        assert_eq!(server.open(1, sent), vec![CHAT.to_vec()]);
+    }
+
+
+    #[test]
+    fn reject_tampered_messages() {
+        let (mut client, mut server, key) = connect();
+        client.open(key).unwrap();
+
+        let mut sent = server.seal(1, CHAT.to_vec()).unwrap();
+        let last = sent.len() - 1;
+        sent[last] ^= 1;
+        assert_eq!(client.open(sent).unwrap(), Vec::<Vec<u8>>::new());
+
+        let mut sent = server.seal(1, CHAT.to_vec()).unwrap();
+        sent[MESSAGE_ID_BYTES] ^= 1;
+        assert_eq!(client.open(sent).unwrap(), Vec::<Vec<u8>>::new());
+
+        let sent = server.seal(1, CHAT.to_vec()).unwrap();
+        assert_eq!(
+            client.open(sent[..MESSAGE_ID_BYTES + 4].to_vec()).unwrap(),
+            Vec::<Vec<u8>>::new()
+        );
+    }
+
+
+    #[test]
+    fn reject_wrong_direction() {
+        let client_secret = SecretKey::generate().unwrap();
+        let server_secret = SecretKey::generate().unwrap();
+        let mut client =
+            SessionCipher::client(&client_secret, &server_secret.public_key()).unwrap();
+        let mut server =
+            SessionCipher::server(&server_secret, &client_secret.public_key()).unwrap();
+
+        let from_client = client.encrypt(&CHAT).unwrap();
+        assert!(client.decrypt(&from_client).is_err());
+        assert_eq!(server.decrypt(&from_client).unwrap(), CHAT.to_vec());
+
+        let from_server = server.encrypt(&CHAT).unwrap();
+        assert!(server.decrypt(&from_server).is_err());
+        assert_eq!(client.decrypt(&from_server).unwrap(), CHAT.to_vec());
+    }
+
+
+    #[test]
+    fn reject_low_order_keys() {
+        let secret = SecretKey::generate().unwrap();
+        assert!(SessionCipher::client(&secret, &[0; PUBLIC_KEY_BYTES]).is_err());
+        assert!(SessionCipher::server(&secret, &[0; PUBLIC_KEY_BYTES]).is_err());
+    }
+
+
+    #[test]
+    fn hold_back_early_messages() {
+        let (mut client, mut server, key) = connect();
+        let sent = server.seal(1, CHAT.to_vec()).unwrap();
+        assert_eq!(client.open(sent).unwrap(), Vec::<Vec<u8>>::new());
+        assert_eq!(client.open(key.clone()).unwrap(), vec![key, CHAT.to_vec()]);
+    }
+
+
+    #[test]
+    fn ignore_second_key() {
+        let (mut client, mut server, key) = connect();
+        client.open(key).unwrap();
+
+        let (_, _, other_key) = connect();
+        assert_eq!(client.open(other_key).unwrap(), Vec::<Vec<u8>>::new());
+
+        let sent = server.seal(1, CHAT.to_vec()).unwrap();
+        assert_eq!(client.open(sent).unwrap(), vec![CHAT.to_vec()]);
+    }
+
+
+    #[test]
+    fn plain_text_messages() {
+        let (mut client, mut server, key) = connect();
+        client.open(key).unwrap();
+
+        let handshake = encode_message(&HandshakeMessage::local());
+        assert_eq!(client.seal(handshake.clone()), Some(handshake.clone()));
+        assert_eq!(server.seal(1, handshake.clone()), Some(handshake.clone()));
+        assert_eq!(client.open(CHAT.to_vec()).unwrap(), Vec::<Vec<u8>>::new());
+
+        assert_eq!(server.open(1, handshake.clone()), vec![handshake.clone()]);
+        assert_eq!(server.open(1, CHAT.to_vec()), Vec::<Vec<u8>>::new());
+        let sent = client.seal(CHAT.to_vec()).unwrap();
+        assert_eq!(server.open(1, sent), vec![CHAT.to_vec(), CHAT.to_vec()]);
+        assert_eq!(server.open(1, CHAT.to_vec()), Vec::<Vec<u8>>::new());
+        assert_eq!(server.open(1, handshake.clone()), vec![handshake]);
+    }
+
+
+    #[test]
+    fn reject_invalid_server_key() {
+        let (mut client, mut server, _) = connect();
+        let sent = server.seal(1, CHAT.to_vec()).unwrap();
+        client.open(sent).unwrap();
+
+        let key = encode_message(&EncryptionKey {
+            public_key: [0; PUBLIC_KEY_BYTES],
+        });
+        assert!(client.open(key).is_err());
+        assert!(!client.is_encrypted());
+    }
+
+
+    #[test]
+    fn require_encrypted_client() {
+        let (_, mut server, _) = connect();
+        for _ in 0..MAX_EARLY_MESSAGES + 1 {
+            assert_eq!(server.open(1, CHAT.to_vec()), Vec::<Vec<u8>>::new());
+        }
+        assert_eq!(server.sessions[&1].early.len(), MAX_EARLY_MESSAGES);
+    }
+}
@@ -0,0 +1,291 @@
+//! Verifies that a newly connected client is compatible with the server before
+//! it is allowed to join.
+//!
+//! All versions of Awgen share the same [`TRANSPORT_PROTOCOL_ID`], so that any
+//! client is able to open a connection to any server. Once connected, the
+//! client sends a [`HandshakeMessage`] carrying its version and the
+//! fingerprint of its protocol schema. The server only creates the client
+//! socket of the client once the handshake has been accepted. Incompatible
+//! clients are kicked with a [`DisconnectCause::ProtocolMismatch`] and a
+//! reason that describes the mismatch, instead of silently failing to connect.
+//!
+//! For this to work across versions, the message IDs and formats of both the
+//! [`HandshakeMessage`] and the
+//! [`DisconnectMessage`](crate::prelude::DisconnectMessage) must never change.
+
+
+use crate::prelude::{
+    capture_message, send_to_server, Authority, CaptureDirection, CaptureSide, ClientConnectedEvent, ClientSocket, DisconnectCause, InputActivity, KickClient, MessageBatch, MessageChannel, NetworkMessage, OwnedBy, PlayerName, UserData, MESSAGE_ID_BYTES, PROTOCOL_ID
+};
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use serde::{Deserialize, Serialize};
+
+
+/// The protocol ID of the underlying transport, which is shared by every
+/// version of Awgen. Compatibility is checked by the handshake instead.
+pub const TRANSPORT_PROTOCOL_ID: u64 = u64::from_le_bytes(*b"AWGEN\0\0\x01");
+
+
+/// The version of Awgen that is reported within the handshake.
+pub const AWGEN_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// The number of seconds that a newly connected client is given to complete
+/// the handshake before it is kicked.
+pub const HANDSHAKE_TIMEOUT_SECONDS: f64 = 5.0;
+
+
+/// A network message that is sent from a client to the server as soon as it
+/// has connected, describing the version of the client.
+///
+/// The format of this message must never change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeMessage {
+    /// The semantic version of Awgen that the client is running.
+    pub version: String,
+
+    /// The fingerprint of the protocol schema of the client.
+    pub protocol: u64,
+}
+
+impl HandshakeMessage {
+    /// Creates the handshake message that describes the local version.
+    pub fn local() -> Self {
+        Self {
+            version:  AWGEN_VERSION.to_string(),
+            protocol: PROTOCOL_ID,
+        }
+    }
+
+
+    /// Checks whether a client that sent this handshake is compatible with the
+    /// local version, returning the reason that it is not otherwise.
+    ///
+    /// Versions are compatible if they share the same major version, or the
+    /// same minor version while the major version is still zero. Compatible
+    /// versions must also share the same protocol schema.
+    pub fn check(&self) -> Result<()> {
+        if !versions_compatible(&self.version, AWGEN_VERSION) {
+            bail!(
+                "The server is running version {AWGEN_VERSION}, but the client is running version {}",
+                self.version
+            );
+        }
+
+        if self.protocol != PROTOCOL_ID {
+            bail!(
+                "The protocol schema of the client ({:016x}) does not match the server ({PROTOCOL_ID:016x})",
+                self.protocol
+            );
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Parses the major, minor, and patch numbers of a semantic version, ignoring
+/// any pre-release or build suffix.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+
+    match parts.next() {
+        Some(_) => None,
+        None => Some(version),
+    }
+}
+
+
+/// Gets whether or not the two given semantic versions are compatible with
+/// each other.
+pub fn versions_compatible(a: &str, b: &str) -> bool {
+    match (parse_version(a), parse_version(b)) {
+        (Some((0, a_minor, _)), Some((0, b_minor, _))) => a_minor == b_minor,
+        (Some((a_major, ..)), Some((b_major, ..))) => a_major == b_major,
+        _ => false,
+    }
+}
+
+
+/// A server-side resource that stores the clients that have connected but not
+/// yet completed the handshake, alongside the time at which they connected and
+/// the user data that they connected with.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PendingHandshakes {
+    /// The client id, connection time, and user data of each pending client.
+    clients: Vec<(u64, f64, UserData)>,
+}
+
+impl PendingHandshakes {
+    /// Adds a newly connected client that must complete the handshake.
+    pub fn push(&mut self, client_id: u64, connected_at: f64, user_data: UserData) {
+        self.clients.push((client_id, connected_at, user_data));
+    }
+
+
+    /// Gets whether or not the client with the given id is still waiting to
+    /// complete the handshake.
+    pub fn contains(&self, client_id: u64) -> bool {
+        self.clients.iter().any(|(id, ..)| *id == client_id)
+    }
+}
+
+
+/// Decodes the handshake from the given raw message, if it is one.
+fn decode_handshake(bytes: &[u8]) -> Option<HandshakeMessage> {
+    if bytes.len() < MESSAGE_ID_BYTES {
+        return None;
+    }
+
+    match u16::from_le_bytes([bytes[0], bytes[1]]) == HandshakeMessage::ID {
+        true => bincode::deserialize(&bytes[MESSAGE_ID_BYTES..]).ok(),
+        false => None,
+    }
+}
+
+
+/// Sends the handshake of the local client to the server.
+pub fn send_handshake(mut batch: ResMut<MessageBatch>) {
+    send_to_server(&mut batch, &HandshakeMessage::local());
+}
+
+
+/// Reads the handshake of each pending client, creating the [`ClientSocket`]
+/// entity of each client that is compatible and kicking all others. Each
+/// client socket entity is named after the username of its client.
+///
+/// The handshake must be the first reliable message that a client sends.
+/// Clients that disconnect before completing the handshake are forgotten, and
+/// clients that do not complete it in time are kicked.
+pub fn verify_handshakes(
+    time: Res<Time>,
+    mut server: ResMut<RenetServer>,
+    mut pending: ResMut<PendingHandshakes>,
+    mut ev_connected: EventWriter<ClientConnectedEvent>,
+    mut ev_kick: EventWriter<KickClient>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+    pending.clients.retain(|(client_id, connected_at, user_data)| {
+        if !server.is_connected(*client_id) {
+            return false;
+        }
+
+        let Some(bytes) = server.receive_message(*client_id, MessageChannel::RELIABLE) else {
+            if now - *connected_at < HANDSHAKE_TIMEOUT_SECONDS {
+                return true;
+            }
+
+            info!("Client {client_id} did not complete the handshake");
+            let reason = "The client did not complete the handshake in time";
+            let kick = KickClient::from_id(*client_id, reason);
+            ev_kick.send(kick.with_cause(DisconnectCause::ProtocolMismatch));
+            return false;
+        };
+
+        capture_message(
+            CaptureSide::Server,
+            CaptureDirection::Received,
+            Some(*client_id),
+            &bytes,
+        );
+
+        let result = match decode_handshake(&bytes) {
+            Some(handshake) => handshake.check(),
+            None => Err(anyhow!("The client sent an unrecognized handshake")),
+        };
+
+        match result {
+            Ok(()) => {
+                let socket = ClientSocket::new(*client_id);
+                let activity = InputActivity::new(now);
+                let ownership = (OwnedBy(socket), Authority::Client);
+                let name = PlayerName::of_client(*client_id, user_data);
+                let entity = commands.spawn((socket, activity, ownership, name)).id();
+                ev_connected.send(ClientConnectedEvent {
+                    entity,
+                    user_data: user_data.clone(),
+                });
+            },
+            Err(err) => {
+                info!("Rejecting incompatible client {client_id}: {err}");
+                let kick = KickClient::from_id(*client_id, err.to_string());
+                ev_kick.send(kick.with_cause(DisconnectCause::ProtocolMismatch));
+            },
+        }
+
+        false
+    });
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("0.1.0-alpha.2"), Some((0, 1, 0)));
+        assert_eq!(parse_version("2.0.1+build.5"), Some((2, 0, 1)));
+    }
+
+
+    #[test]
+    fn reject_malformed_versions() {
+        for version in ["", "1", "1.2", "1.2.3.4", "1.x.3", "-1.2.3", "1..3", "v1.2.3"] {
+            assert_eq!(parse_version(version), None, "{version} was accepted");
+        }
+    }
+
+
+    #[test]
+    fn equal_versions_are_compatible() {
+        assert!(versions_compatible("1.2.3", "1.2.3"));
+        assert!(versions_compatible("0.1.0", "0.1.0"));
+        assert!(versions_compatible(AWGEN_VERSION, AWGEN_VERSION));
+    }
+
+
+    #[test]
+    fn minor_mismatch() {
+        assert!(versions_compatible("1.2.3", "1.5.0"));
+        assert!(versions_compatible("0.1.0", "0.1.7"));
+        assert!(!versions_compatible("0.1.0", "0.2.0"));
+    }
+
+
+    #[test]
+    fn major_mismatch() {
+        assert!(!versions_compatible("1.2.3", "2.2.3"));
+        assert!(!versions_compatible("0.1.0", "1.1.0"));
+    }
+
+
+    #[test]
+    fn malformed_versions_are_incompatible() {
+        assert!(!versions_compatible("1.2", "1.2"));
+        assert!(!versions_compatible("1.2.3", "banana"));
+        assert!(!versions_compatible("", ""));
+    }
+
+
+    #[test]
+    fn check_handshake() {
+        assert!(HandshakeMessage::local().check().is_ok());
+
+        let mut handshake = HandshakeMessage::local();
+        handshake.protocol ^= 1;
+        assert!(handshake.check().is_err());
+
+        let mut handshake = HandshakeMessage::local();
+        handshake.version = "999.0.0".to_string();
+        assert!(handshake.check().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Synchronizes the hotbar slot that each player has selected, and the item
+//! that they are holding as a result.
+//!
+//! The held item of each player is replicated to all other clients as the
+//! [`HeldItem`] component, so that it may be rendered on their player models.
+//! The local player is instead told about its own held item directly, as
+//! players are not replicated to themselves.
+
+
+use crate::prelude::{send_to_server, MessageBatch, ReplicatedComponent, ServerMessage};
+use awgen_physics::prelude::ItemStack;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The number of slots within the hotbar of a player.
+pub const HOTBAR_SLOTS: usize = 9;
+
+
+/// The item stack that a player is holding, which is the stack within their
+/// selected hotbar slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Component, Serialize, Deserialize)]
+pub struct HeldItem(pub ItemStack);
+
+impl HeldItem {
+    /// Gets whether or not at least one of the given item is being held.
+    pub fn is_holding(&self, item: &str) -> bool {
+        !self.0.is_empty() && self.0.item == item
+    }
+}
+
+impl ReplicatedComponent for HeldItem {
+    const ID: u16 = 2;
+}
+
+
+/// A network message that is sent from a client to the server to select a
+/// hotbar slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectHotbarSlot {
+    /// The index of the selected slot.
+    pub slot: u8,
+}
+
+
+/// A network message that is sent from the server to a client when the held
+/// item of the local player changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeldItemMessage {
+    /// The index of the selected hotbar slot.
+    pub slot: u8,
+
+    /// The item stack within the selected hotbar slot.
+    pub item: ItemStack,
+}
+
+
+/// A client-side resource that stores the hotbar slot that the local player
+/// has selected, and the item within it, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct LocalHeldItem {
+    /// The index of the selected hotbar slot.
+    slot: u8,
+
+    /// The item stack within the selected hotbar slot.
+    item: ItemStack,
+}
+
+impl LocalHeldItem {
+    /// Gets the index of the selected hotbar slot.
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+
+    /// Gets the item stack within the selected hotbar slot.
+    pub fn item(&self) -> &ItemStack {
+        &self.item
+    }
+}
+
+
+/// Sends a request to the server to select the given hotbar slot.
+pub fn send_hotbar_selection(batch: &mut MessageBatch, slot: u8) {
+    send_to_server(batch, &SelectHotbarSlot {
+        slot,
+    });
+}
+
+
+/// Applies the held item messages that were received from the server to the
+/// held item of the local player.
+pub fn apply_held_item_messages(
+    mut held_ev: EventReader<ServerMessage<HeldItemMessage>>,
+    mut held: ResMut<LocalHeldItem>,
+) {
+    for ev in held_ev.iter() {
+        held.slot = ev.message.slot;
+        held.item = ev.message.item.clone();
+    }
+}
+
+
+/// Clears the held item of the local player once it has disconnected from the
+/// server.
+pub fn reset_local_held_item(mut held: ResMut<LocalHeldItem>) {
+    *held = LocalHeldItem::default();
+}
@@ -0,0 +1,268 @@
+//! The on-screen messages that the server may show to clients, such as the
+//! remaining time of a mini-game round within a boss bar, or an announcement
+//! within a title.
+//!
+//! Boss bars stay on screen until the server removes them, while titles and
+//! action bar messages fade out on their own once their time is up. The
+//! client keeps track of this timing, so the server only needs to send each
+//! message once.
+
+
+use crate::prelude::{ServerMessage, TeamColor};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+
+/// The number of seconds that an action bar message is shown for by default.
+pub const DEFAULT_ACTION_BAR_SECONDS: f32 = 3.0;
+
+
+/// A titled progress bar along the top of the screen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BossBar {
+    /// The text that is shown above the bar.
+    pub title: String,
+
+    /// The filled fraction of the bar, between 0.0 and 1.0.
+    pub progress: f32,
+
+    /// The color of the bar.
+    pub color: TeamColor,
+}
+
+
+/// The number of seconds that a title fades in, stays on screen, and fades
+/// out for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TitleTiming {
+    /// The number of seconds that the title fades in for.
+    pub fade_in: f32,
+
+    /// The number of seconds that the title is fully shown for.
+    pub stay: f32,
+
+    /// The number of seconds that the title fades out for.
+    pub fade_out: f32,
+}
+
+impl TitleTiming {
+    /// Gets the total number of seconds that a title with this timing is shown
+    /// for.
+    pub fn total(&self) -> f32 {
+        self.fade_in + self.stay + self.fade_out
+    }
+
+
+    /// Gets the opacity of a title with this timing, the given number of
+    /// seconds after it was shown.
+    pub fn opacity(&self, elapsed: f32) -> f32 {
+        if elapsed < self.fade_in {
+            return elapsed / self.fade_in;
+        }
+
+        let fading = elapsed - self.fade_in - self.stay;
+        if fading <= 0.0 {
+            return 1.0;
+        }
+
+        (1.0 - fading / self.fade_out).max(0.0)
+    }
+}
+
+impl Default for TitleTiming {
+    fn default() -> Self {
+        Self {
+            fade_in:  0.5,
+            stay:     3.0,
+            fade_out: 1.0,
+        }
+    }
+}
+
+
+/// A network message that is sent from the server to a client in order to
+/// change the on-screen messages that are shown to them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HudMessage {
+    /// Shows or updates the boss bar with the given id.
+    SetBossBar {
+        /// The id of the boss bar.
+        id: String,
+
+        /// The boss bar to show.
+        bar: BossBar,
+    },
+
+    /// Removes the boss bar with the given id.
+    RemoveBossBar(String),
+
+    /// Shows a title and subtitle in the middle of the screen, replacing any
+    /// title that is currently shown.
+    Title {
+        /// The large text of the title.
+        title: String,
+
+        /// The smaller text below the title, which may be empty.
+        subtitle: String,
+
+        /// How long the title is shown for.
+        timing: TitleTiming,
+    },
+
+    /// Removes the title that is currently shown.
+    ClearTitle,
+
+    /// Shows a short message just above the hotbar, replacing any message that
+    /// is currently shown.
+    ActionBar {
+        /// The text of the message.
+        text: String,
+
+        /// The number of seconds that the message is shown for.
+        seconds: f32,
+    },
+}
+
+
+/// A title that is currently shown on a client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShownTitle {
+    /// The large text of the title.
+    pub title: String,
+
+    /// The smaller text below the title, which may be empty.
+    pub subtitle: String,
+
+    /// How long the title is shown for.
+    pub timing: TitleTiming,
+
+    /// The number of seconds since the title was shown.
+    pub elapsed: f32,
+}
+
+impl ShownTitle {
+    /// Gets the current opacity of this title.
+    pub fn opacity(&self) -> f32 {
+        self.timing.opacity(self.elapsed)
+    }
+}
+
+
+/// A client-side resource that stores the on-screen messages that are
+/// currently shown, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ClientHud {
+    /// Each boss bar that is shown, by id.
+    boss_bars: HashMap<String, BossBar>,
+
+    /// The title that is shown, if any.
+    title: Option<ShownTitle>,
+
+    /// The action bar message that is shown, and the number of seconds that
+    /// it remains on screen for, if any.
+    action_bar: Option<(String, f32)>,
+}
+
+impl ClientHud {
+    /// Gets each boss bar that is shown, sorted by id.
+    pub fn boss_bars(&self) -> Vec<(&str, &BossBar)> {
+        let mut bars: Vec<(&str, &BossBar)> =
+            self.boss_bars.iter().map(|(id, bar)| (id.as_str(), bar)).collect();
+        bars.sort_by(|a, b| a.0.cmp(b.0));
+        bars
+    }
+
+
+    /// Gets the title that is shown, if any.
+    pub fn title(&self) -> Option<&ShownTitle> {
+        self.title.as_ref()
+    }
+
+
+    /// Gets the action bar message that is shown, if any.
+    pub fn action_bar(&self) -> Option<&str> {
+        self.action_bar.as_ref().map(|(text, _)| text.as_str())
+    }
+
+
+    /// Applies the given message from the server.
+    pub fn apply(&mut self, message: &HudMessage) {
+        match message {
+            HudMessage::SetBossBar {
+                id,
+                bar,
+            } => {
+                let mut bar = bar.clone();
+                bar.progress = bar.progress.clamp(0.0, 1.0);
+                self.boss_bars.insert(id.clone(), bar);
+            },
+            HudMessage::RemoveBossBar(id) => {
+                self.boss_bars.remove(id);
+            },
+            HudMessage::Title {
+                title,
+                subtitle,
+                timing,
+            } => {
+                self.title = Some(ShownTitle {
+                    title:    title.clone(),
+                    subtitle: subtitle.clone(),
+                    timing:   *timing,
+                    elapsed:  0.0,
+                });
+            },
+            HudMessage::ClearTitle => self.title = None,
+            HudMessage::ActionBar {
+                text,
+                seconds,
+            } => {
+                self.action_bar = Some((text.clone(), *seconds));
+            },
+        }
+    }
+
+
+    /// Advances the timers of the title and action bar message by the given
+    /// number of seconds, removing them once their time is up.
+    pub fn advance(&mut self, delta: f32) {
+        if let Some(title) = &mut self.title {
+            title.elapsed += delta;
+            if title.elapsed >= title.timing.total() {
+                self.title = None;
+            }
+        }
+
+        if let Some((_, remaining)) = &mut self.action_bar {
+            *remaining -= delta;
+            if *remaining <= 0.0 {
+                self.action_bar = None;
+            }
+        }
+    }
+}
+
+
+/// Applies the on-screen message changes that were received from the server
+/// to the client HUD.
+pub fn apply_hud_messages(
+    mut hud_ev: EventReader<ServerMessage<HudMessage>>,
+    mut hud: ResMut<ClientHud>,
+) {
+    for ev in hud_ev.iter() {
+        hud.apply(&ev.message);
+    }
+}
+
+
+/// Advances the timers of the on-screen messages of the client HUD.
+pub fn update_client_hud(time: Res<Time>, mut hud: ResMut<ClientHud>) {
+    hud.advance(time.delta_seconds());
+}
+
+
+/// Clears the client HUD once the local player has disconnected from the
+/// server.
+pub fn reset_client_hud(mut hud: ResMut<ClientHud>) {
+    *hud = ClientHud::default();
+}
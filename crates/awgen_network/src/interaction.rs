@@ -0,0 +1,43 @@
+//! Requests from clients to use the block that they are looking at, such as
+//! opening a door or toggling a lever.
+
+
+use crate::prelude::{send_to_server, MessageBatch};
+use awgen_math::prelude::Direction;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The height, in meters, of the eyes of a player above its position. Look
+/// rays used for interactions are cast from this point.
+pub const PLAYER_EYE_HEIGHT: f32 = 1.6;
+
+
+/// The maximum distance, in meters, from the eyes of a player to the block
+/// that it may interact with.
+pub const MAX_INTERACTION_REACH: f32 = 5.0;
+
+
+/// A network message that is sent from a client to the server to use a block.
+///
+/// Using a block is a discrete action, so it is sent over the reliable
+/// channel. The server validates the request before executing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockUseMessage {
+    /// The position of the block to use.
+    pub block_pos: IVec3,
+
+    /// The face of the block that the player is looking at.
+    pub face: Direction,
+}
+
+
+/// Sends a request to the server to use the block at the given position.
+pub fn send_block_use(batch: &mut MessageBatch, block_pos: IVec3, face: Direction) {
+    let message = BlockUseMessage {
+        block_pos,
+        face,
+    };
+
+    send_to_server(batch, &message);
+}
@@ -0,0 +1,222 @@
+//! Smooths the movement of replicated entities by interpolating between the
+//! positions that the server sent for them.
+//!
+//! Position updates arrive over the unreliable channel, and so are not spread
+//! evenly over time. Rather than applying each update as it arrives, the
+//! client stores the last few positions of each proxy within a
+//! [`SnapshotBuffer`], and moves the proxy to the position that it had a short
+//! delay in the past, interpolated between the two surrounding snapshots. The
+//! delay gives late updates time to arrive before they are needed.
+//!
+//! The interpolated position is applied once per physics tick, after the
+//! previous position has been pushed, so the render transform of each proxy
+//! is further interpolated between physics ticks as usual.
+
+
+use crate::prelude::{Authority, ComponentData, ReplicatedComponent};
+use awgen_physics::prelude::{PhysicsTickrate, Position};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+
+/// The maximum number of position snapshots that are stored for each proxy.
+pub const SNAPSHOT_BUFFER_SIZE: usize = 32;
+
+
+/// The number of seconds in the past that proxies are rendered at.
+pub const INTERPOLATION_DELAY: f32 = 0.1;
+
+
+/// The largest gap, in replication ticks, between two snapshots that is
+/// interpolated across. Positions are only sent when they change, so a larger
+/// gap means that the proxy did not move in between.
+const MAX_INTERPOLATED_GAP: f64 = 3.0;
+
+
+/// The number of replication ticks that the snapshot clock may drift from the
+/// latest received tick before it is reset.
+const MAX_CLOCK_DRIFT: f64 = 10.0;
+
+
+/// The fraction of the drift of the snapshot clock that is corrected each time
+/// an update is received.
+const CLOCK_CORRECTION: f64 = 0.1;
+
+
+/// A client-side resource that estimates the replication tick of the server,
+/// for sampling the snapshot buffers of proxies.
+///
+/// The clock advances by one tick each physics tick, and is nudged towards the
+/// tick of each received update, so that it follows the server without
+/// jumping each time an update arrives late.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct SnapshotClock {
+    /// The estimated replication tick of the server, unwrapped into a
+    /// continuous value, or `None` if no updates have been received yet.
+    tick: Option<f64>,
+}
+
+impl SnapshotClock {
+    /// Gets the estimated replication tick of the server, if any updates have
+    /// been received.
+    pub fn tick(&self) -> Option<f64> {
+        self.tick
+    }
+
+
+    /// Converts the given replication tick into the continuous tick space of
+    /// this clock, accounting for ticks that have wrapped around.
+    pub fn unwrap_tick(&self, tick: u32) -> f64 {
+        match self.tick {
+            Some(current) => {
+                let wrapped = (current as u64) as u32;
+                (current as u64) as f64 + tick.wrapping_sub(wrapped) as i32 as f64
+            },
+            None => tick as f64,
+        }
+    }
+
+
+    /// Corrects this clock towards the given replication tick, which was just
+    /// received from the server.
+    pub fn observe(&mut self, tick: u32) {
+        let tick = self.unwrap_tick(tick);
+        self.tick = match self.tick {
+            Some(current) if (tick - current).abs() <= MAX_CLOCK_DRIFT => {
+                Some(current + (tick - current) * CLOCK_CORRECTION)
+            },
+            _ => Some(tick),
+        };
+    }
+
+
+    /// Advances this clock by the given number of replication ticks.
+    pub fn advance(&mut self, ticks: f64) {
+        if let Some(tick) = &mut self.tick {
+            *tick += ticks;
+        }
+    }
+}
+
+
+/// A client-side component for a proxy that stores the last few positions that
+/// were received for it, and the replication ticks that they were collected
+/// on.
+#[derive(Debug, Clone, Default, Component)]
+pub struct SnapshotBuffer {
+    /// Each snapshot, ordered from oldest to newest.
+    snapshots: VecDeque<(f64, Position)>,
+}
+
+impl SnapshotBuffer {
+    /// Stores the position of the proxy on the given replication tick.
+    ///
+    /// If the proxy did not move for a while before the given tick, its
+    /// previous position is held until the tick before, rather than being
+    /// interpolated across the whole gap.
+    pub fn push(&mut self, tick: f64, position: Position) {
+        let index = self.snapshots.partition_point(|(t, _)| *t < tick);
+        if self.snapshots.get(index).is_some_and(|(t, _)| *t == tick) {
+            self.snapshots[index].1 = position;
+            return;
+        }
+
+        if index == self.snapshots.len() {
+            if let Some((last_tick, last)) = self.snapshots.back().cloned() {
+                if tick - last_tick > MAX_INTERPOLATED_GAP {
+                    self.snapshots.push_back((tick - 1.0, last));
+                }
+            }
+        }
+
+        let index = self.snapshots.partition_point(|(t, _)| *t < tick);
+        self.snapshots.insert(index, (tick, position));
+
+        while self.snapshots.len() > SNAPSHOT_BUFFER_SIZE {
+            self.snapshots.pop_front();
+        }
+    }
+
+
+    /// Gets the number of snapshots within this buffer.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+
+    /// Gets whether or not this buffer has no snapshots.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+
+    /// Gets the position of the proxy on the given replication tick,
+    /// interpolated between the two surrounding snapshots.
+    ///
+    /// Ticks before the oldest snapshot or after the newest snapshot use the
+    /// oldest or newest snapshot, respectively, as positions are not
+    /// extrapolated.
+    pub fn sample(&self, tick: f64) -> Option<Position> {
+        let index = self.snapshots.partition_point(|(t, _)| *t <= tick);
+
+        let (next_tick, next) = match self.snapshots.get(index) {
+            Some(next) => next,
+            None => return self.snapshots.back().map(|(_, last)| last.clone()),
+        };
+
+        let Some((last_tick, last)) = index.checked_sub(1).and_then(|i| self.snapshots.get(i))
+        else {
+            return Some(next.clone());
+        };
+
+        let delta = ((tick - last_tick) / (next_tick - last_tick)) as f32;
+        Some(Position {
+            translation: last.translation.lerp(next.translation, delta),
+            rotation:    last.rotation.slerp(next.rotation, delta),
+            scale:       last.scale.lerp(next.scale, delta),
+        })
+    }
+}
+
+
+/// Gets the position within the given replicated components, if it is one of
+/// them and can be decoded.
+pub fn decode_position(components: &[ComponentData]) -> Option<Position> {
+    let component = components.iter().find(|c| c.id == Position::ID)?;
+    bincode::deserialize(&component.data).ok()
+}
+
+
+/// Advances the snapshot clock, and moves each proxy that the server has
+/// authority over to its position at the interpolation delay in the past.
+///
+/// This is called once per physics tick.
+pub fn interpolate_remote_positions(
+    tickrate: Res<PhysicsTickrate>,
+    mut clock: ResMut<SnapshotClock>,
+    mut proxies: Query<(&mut Position, &SnapshotBuffer, &Authority)>,
+) {
+    clock.advance(1.0);
+
+    let Some(tick) = clock.tick() else {
+        return;
+    };
+
+    let render_tick = tick - (INTERPOLATION_DELAY * tickrate.tickrate()) as f64;
+    for (mut position, buffer, authority) in proxies.iter_mut() {
+        if *authority != Authority::Server {
+            continue;
+        }
+
+        if let Some(sampled) = buffer.sample(render_tick) {
+            *position = sampled;
+        }
+    }
+}
+
+
+/// Resets the snapshot clock once the local player has disconnected from the
+/// server.
+pub fn reset_snapshot_clock(mut clock: ResMut<SnapshotClock>) {
+    clock.tick = None;
+}
@@ -6,31 +6,107 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+pub mod activity;
+pub mod auth;
+pub mod authority;
+pub mod bandwidth;
+pub mod bans;
+pub mod batch;
+pub mod breaking;
+pub mod capture;
+pub mod channels;
+pub mod chat;
+pub mod compression;
+pub mod connection;
+pub mod containers;
+pub mod editing;
+pub mod effects;
+pub mod encryption;
+pub mod handshake;
+pub mod held_item;
+pub mod hud;
+pub mod interaction;
+pub mod interpolation;
+pub mod message;
+pub mod playback;
+pub mod prediction;
+pub mod protocol;
+pub mod rcon;
+pub mod reconnect;
+pub mod replication;
+pub mod roster;
+pub mod scoreboard;
 pub mod server_events;
+#[cfg(feature = "network_simulator")]
+pub mod simulator;
+pub mod spectate;
+pub mod stats;
+pub mod status;
+pub mod teams;
+pub mod text_display;
+pub mod user_data;
+pub mod weather;
 
 
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
+    pub use super::activity::*;
+    pub use super::auth::*;
+    pub use super::authority::*;
+    pub use super::bandwidth::*;
+    pub use super::bans::*;
+    pub use super::batch::*;
+    pub use super::breaking::*;
+    pub use super::capture::*;
+    pub use super::channels::*;
+    pub use super::chat::*;
+    pub use super::compression::*;
+    pub use super::connection::*;
+    pub use super::containers::*;
+    pub use super::editing::*;
+    pub use super::effects::*;
+    pub use super::encryption::*;
+    pub use super::handshake::*;
+    pub use super::held_item::*;
+    pub use super::hud::*;
+    pub use super::interaction::*;
+    pub use super::interpolation::*;
+    pub use super::message::*;
+    pub use super::playback::*;
+    pub use super::prediction::*;
+    pub use super::protocol::*;
+    pub use super::rcon::*;
+    pub use super::reconnect::*;
+    pub use super::replication::*;
+    pub use super::roster::*;
+    pub use super::scoreboard::*;
     pub use super::server_events::*;
+    #[cfg(feature = "network_simulator")]
+    pub use super::simulator::*;
+    pub use super::spectate::*;
+    pub use super::stats::*;
+    pub use super::status::*;
+    pub use super::teams::*;
+    pub use super::text_display::*;
+    pub use super::user_data::*;
+    pub use super::weather::*;
     pub use super::*;
 }
 
 
+use anyhow::{Context, Result};
+use awgen_physics::prelude::{run_while_connected, AppState, Position};
 use bevy::prelude::*;
 use bevy_renet::renet::{
-    ClientAuthentication, RenetClient, RenetConnectionConfig, RenetServer, ServerAuthentication, ServerConfig
+    ClientAuthentication, RenetClient, RenetServer, ServerAuthentication, ServerConfig
 };
 use bevy_renet::{RenetClientPlugin, RenetServerPlugin};
 use prelude::*;
 use std::net::UdpSocket;
+use std::sync::Arc;
 use std::time::SystemTime;
 
 
-/// The current networking protocol index for this version of the Awgen
-/// networking plugin.
-const PROTOCOL_ID: u64 = 1;
-
-
 /// An indicator for the side of the network to be handled within the runtime.
 pub enum NetworkSide {
     /// The client-side of the network.
@@ -40,6 +116,13 @@ pub enum NetworkSide {
 
         /// The port of the server to connect to.
         port: u16,
+
+        /// The issuer of the connect token to join the server with. If not
+        /// set, the client connects without authentication.
+        issuer: Option<Arc<dyn TokenIssuer>>,
+
+        /// The user data to join the server with.
+        user_data: UserData,
     },
 
     /// The server-side of the network.
@@ -50,6 +133,26 @@ pub enum NetworkSide {
         /// The maximum number of clients that are allowed on the server at
         /// once.
         max_clients: usize,
+
+        /// The private key that connect tokens must be signed with. If not
+        /// set, clients may connect without authentication.
+        private_key: Option<PrivateKey>,
+
+        /// The settings of the remote administration channel, if enabled.
+        rcon: Option<RconSettings>,
+
+        /// The settings of the status query, if enabled.
+        status: Option<StatusSettings>,
+
+        /// Whether or not clients must encrypt their connection.
+        encryption: bool,
+    },
+
+    /// The client-side of the network, playing back a recorded session
+    /// instead of connecting to a server.
+    Playback {
+        /// The session to play back.
+        session: SessionPlayback,
     },
 }
 
@@ -58,16 +161,24 @@ pub enum NetworkSide {
 pub struct NetworkPlugin {
     /// The side of the network begin handled.
     side: NetworkSide,
+
+    /// The channels that network messages are sent over.
+    channels: NetworkChannels,
 }
 
 impl NetworkPlugin {
     /// Creates a new server instance of the network plugin.
     pub fn new_server(port: u16, max_clients: usize) -> Self {
         Self {
-            side: NetworkSide::Server {
+            side:     NetworkSide::Server {
                 port,
                 max_clients,
+                private_key: None,
+                rcon: None,
+                status: None,
+                encryption: false,
             },
+            channels: NetworkChannels::default(),
         }
     }
 
@@ -76,11 +187,138 @@ impl NetworkPlugin {
     pub fn new_client<S>(ip: S, port: u16) -> Self
     where S: Into<String> {
         Self {
-            side: NetworkSide::Client {
+            side:     NetworkSide::Client {
                 ip: ip.into(),
                 port,
+                issuer: None,
+                user_data: UserData::default(),
             },
+            channels: NetworkChannels::default(),
+        }
+    }
+
+
+    /// Creates a new client instance of the network plugin that plays back the
+    /// given recorded session, rather than connecting to a server.
+    pub fn new_playback(session: SessionPlayback) -> Self {
+        Self {
+            side:     NetworkSide::Playback {
+                session,
+            },
+            channels: NetworkChannels::default(),
+        }
+    }
+
+
+    /// Requires clients to connect with a connect token that was signed with
+    /// the given private key.
+    ///
+    /// This has no effect on the client instance of the network plugin.
+    pub fn with_private_key(mut self, key: PrivateKey) -> Self {
+        if let NetworkSide::Server {
+            private_key,
+            ..
+        } = &mut self.side
+        {
+            *private_key = Some(key);
+        }
+        self
+    }
+
+
+    /// Requires clients to encrypt the messages that are sent over their
+    /// connection, so that chat and authentication data cannot be read by
+    /// anyone listening in. Clients that do not support encryption are kicked.
+    ///
+    /// Servers that require connect tokens are already encrypted by the
+    /// transport, so this is only needed for servers without a private key.
+    /// See the `encryption` module for the guarantees that this provides.
+    ///
+    /// This has no effect on the client instance of the network plugin.
+    pub fn with_encryption(mut self) -> Self {
+        if let NetworkSide::Server {
+            encryption,
+            ..
+        } = &mut self.side
+        {
+            *encryption = true;
+        }
+        self
+    }
+
+
+    /// Joins the server with the given user data, such as the username to play
+    /// as.
+    ///
+    /// This has no effect on the server instance of the network plugin.
+    pub fn with_user_data(mut self, data: UserData) -> Self {
+        if let NetworkSide::Client {
+            user_data,
+            ..
+        } = &mut self.side
+        {
+            *user_data = data;
         }
+        self
+    }
+
+
+    /// Opens a remote administration channel with the given settings, which
+    /// accepts text commands without joining the server.
+    ///
+    /// This has no effect on the client instance of the network plugin.
+    pub fn with_rcon(mut self, settings: RconSettings) -> Self {
+        if let NetworkSide::Server {
+            rcon,
+            ..
+        } = &mut self.side
+        {
+            *rcon = Some(settings);
+        }
+        self
+    }
+
+
+    /// Answers status queries with the given settings, which report the state
+    /// of the server without joining it.
+    ///
+    /// This has no effect on the client instance of the network plugin.
+    pub fn with_status(mut self, settings: StatusSettings) -> Self {
+        if let NetworkSide::Server {
+            status,
+            ..
+        } = &mut self.side
+        {
+            *status = Some(settings);
+        }
+        self
+    }
+
+
+    /// Connects to the server using a connect token from the given token
+    /// issuer.
+    ///
+    /// This has no effect on the server instance of the network plugin.
+    pub fn with_token_issuer<I>(mut self, token_issuer: I) -> Self
+    where I: TokenIssuer + 'static {
+        if let NetworkSide::Client {
+            issuer,
+            ..
+        } = &mut self.side
+        {
+            *issuer = Some(Arc::new(token_issuer));
+        }
+        self
+    }
+
+
+    /// Configures how the messages of the given channel are delivered,
+    /// replacing the delivery of the channel if it was already configured.
+    ///
+    /// The server and its clients must use the same channel configuration.
+    pub fn with_channel(mut self, channel: MessageChannel, delivery: ChannelDelivery) -> Self {
+        self.channels.set(channel, delivery);
+        self
     }
 
 
@@ -92,54 +330,339 @@ impl NetworkPlugin {
 
 impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
+        self.channels.assert_schema(PROTOCOL);
+        app.insert_resource(self.channels.clone())
+            .init_resource::<MessageBatch>()
+            .add_stage_after(CoreStage::PostUpdate, NetworkFlush, SystemStage::parallel());
+
         match &self.side {
             NetworkSide::Server {
                 port,
                 max_clients,
+                private_key,
+                rcon,
+                status,
+                encryption,
             } => {
+                if let Some(settings) = rcon {
+                    match RconServer::start(settings) {
+                        Ok(rcon) => {
+                            info!("Accepting RCON connections on {}", rcon.address());
+                            app.insert_resource(rcon).add_system(receive_rcon_commands);
+                        },
+                        Err(err) => error!("Failed to open the RCON port: {err:#}"),
+                    }
+                }
+
+                if let Some(settings) = status {
+                    match StatusServer::start(settings, *max_clients) {
+                        Ok(status) => {
+                            info!("Answering status queries on {}", status.address());
+                            app.insert_resource(status).add_system(update_server_status);
+                        },
+                        Err(err) => error!("Failed to open the status port: {err:#}"),
+                    }
+                }
+
+                if *encryption {
+                    info!("Requiring encrypted connections");
+                    app.init_resource::<ServerEncryption>().add_system(exchange_encryption_keys);
+                }
+
                 app.add_plugin(RenetServerPlugin::default())
-                    .insert_resource(build_server(*port, *max_clients))
+                    .insert_resource(build_server(
+                        *port,
+                        *max_clients,
+                        private_key.as_ref(),
+                        &self.channels,
+                    ))
                     .register_type::<ClientSocket>()
+                    .register_type::<InputActivity>()
+                    .register_type::<Replicated>()
+                    .register_type::<Authority>()
+                    .register_type::<PlayerName>()
                     .add_event::<ClientConnectedEvent>()
                     .add_event::<ClientDisconnectedEvent>()
+                    .add_event::<KickClient>()
+                    .add_event::<RconCommandEvent>()
+                    .init_resource::<PlayerRoster>()
+                    .init_resource::<PendingKicks>()
+                    .init_resource::<PendingHandshakes>()
+                    .init_resource::<BanList>()
+                    .init_resource::<MessageInbox>()
+                    .init_resource::<ReplicationOutbox>()
+                    .init_resource::<NetworkStats>()
+                    .init_resource::<SendBudget>()
+                    .init_resource::<OutgoingQueue>()
+                    .init_resource::<CompressionSettings>()
+                    .init_resource::<CompressedClients>()
                     .add_system(server_socket_event)
+                    .add_system(verify_handshakes.after(server_socket_event))
+                    .add_system(update_server_network_stats)
+                    .add_system(receive_client_messages)
+                    .add_system(receive_input_activity)
+                    .add_system(apply_compression_requests)
+                    .add_system(apply_component_updates)
+                    .add_system(release_ownership)
+                    .add_system(update_roster_connections)
+                    .add_system(broadcast_roster.after(update_roster_connections))
+                    .add_system(kick_clients.after(verify_handshakes))
+                    .add_system(disconnect_kicked_clients.after(kick_clients))
+                    .add_system(announce_server_shutdown)
+                    .add_system_to_stage(CoreStage::PostUpdate, flush_outgoing_queue)
+                    .add_system_to_stage(NetworkFlush, flush_server_messages)
+                    .add_system_to_stage(CoreStage::Last, update_server_capture)
             },
             NetworkSide::Client {
                 ip,
                 port,
+                issuer,
+                user_data,
             } => {
+                if !app.world.contains_resource::<State<AppState>>() {
+                    app.add_state(AppState::Connecting);
+                }
+
+                let encryption = ClientEncryption::new()
+                    .unwrap_or_else(|err| panic!("Failed to create the network client: {err:#}"));
+                let keyed_user_data = user_data.clone().with_public_key(encryption.public_key());
+                let client = build_client(
+                    ip,
+                    *port,
+                    issuer.as_deref(),
+                    &keyed_user_data,
+                    &self.channels,
+                )
+                .unwrap_or_else(|err| panic!("Failed to create the network client: {err:#}"));
+
+                #[cfg(feature = "network_simulator")]
+                app.insert_resource(NetworkSimulator::new(&self.channels)).add_system_set(
+                    SystemSet::on_enter(AppState::Connecting).with_system(reset_network_simulator),
+                );
+
                 app.add_plugin(RenetClientPlugin::default())
-                    .insert_resource(build_client(ip, *port))
+                    .insert_resource(client)
+                    .insert_resource(encryption)
+                    .insert_resource(ServerConnection {
+                        ip:        ip.clone(),
+                        port:      *port,
+                        issuer:    issuer.clone(),
+                        user_data: user_data.clone(),
+                    })
+                    .init_resource::<PlayerRoster>()
+                    .init_resource::<PendingInputActivity>()
+                    .init_resource::<ContainerView>()
+                    .init_resource::<MessageInbox>()
+                    .init_resource::<RemoteEntities>()
+                    .init_resource::<SpectateView>()
+                    .init_resource::<LocalHeldItem>()
+                    .init_resource::<NetworkStats>()
+                    .init_resource::<ClientWeather>()
+                    .init_resource::<BlockCracks>()
+                    .init_resource::<SnapshotClock>()
+                    .init_resource::<ClientScoreboard>()
+                    .init_resource::<ClientTeams>()
+                    .init_resource::<ClientHud>()
+                    .init_resource::<PendingDisconnect>()
+                    .init_resource::<CompressionSettings>()
+                    .init_resource::<ReconnectSettings>()
+                    .init_resource::<Reconnection>()
+                    .add_event::<DisconnectedEvent>()
+                    .add_event::<ReconnectingEvent>()
+                    .add_event::<ReconnectedEvent>()
+                    .add_event::<PlayerJoinedEvent>()
+                    .add_event::<PlayerLeftEvent>()
+                    .add_event::<ExplosionEffectEvent>()
+                    .add_event::<ParticleEvent>()
+                    .add_event::<BlockUsedEffectEvent>()
+                    .add_event::<SoundEvent>()
+                    .add_event::<ChatMessageReceivedEvent>()
+                    .add_system(update_connection_state.after(apply_disconnect_messages))
+                    .add_system(retry_connection.after(update_connection_state))
+                    .add_system(receive_server_messages.with_run_criteria(run_while_connected))
+                    .add_system(update_client_network_stats.with_run_criteria(run_while_connected))
+                    .add_system(apply_disconnect_messages.after(receive_server_messages))
+                    .add_system(apply_roster_messages.after(receive_server_messages))
+                    .add_system(receive_chat_messages.after(apply_roster_messages))
+                    .add_system(apply_container_messages.after(receive_server_messages))
+                    .add_system(dispatch_effect_messages.after(receive_server_messages))
+                    .add_system(apply_replication_messages.after(receive_server_messages))
+                    .add_system(apply_entity_updates.after(apply_replication_messages))
+                    .add_system(apply_spectate_messages.after(receive_server_messages))
+                    .add_system(apply_held_item_messages.after(receive_server_messages))
+                    .add_system(reconcile_movement.after(receive_server_messages))
+                    .add_system(apply_weather_messages.after(receive_server_messages))
+                    .add_system(update_weather_transition.after(apply_weather_messages))
+                    .add_system(apply_block_crack_messages.after(receive_server_messages))
+                    .add_system(apply_scoreboard_messages.after(receive_server_messages))
+                    .add_system(apply_teams_messages.after(receive_server_messages))
+                    .add_system(apply_hud_messages.after(receive_server_messages))
+                    .add_system(update_client_hud.after(apply_hud_messages))
+                    .add_system_to_stage(
+                        "tick",
+                        record_movement_inputs.with_run_criteria(run_while_connected),
+                    )
+                    .add_system_to_stage(
+                        "tick",
+                        interpolate_remote_positions.with_run_criteria(run_while_connected),
+                    )
+                    .add_system_set(
+                        SystemSet::on_enter(AppState::LoadingWorld)
+                            .with_system(send_handshake)
+                            .with_system(request_compression.after(send_handshake)),
+                    )
+                    .add_system_set(
+                        SystemSet::on_enter(AppState::MainMenu)
+                            .with_system(clear_remote_entities)
+                            .with_system(reset_spectate_view)
+                            .with_system(reset_local_held_item)
+                            .with_system(reset_network_stats)
+                            .with_system(reset_client_weather)
+                            .with_system(reset_block_cracks)
+                            .with_system(reset_snapshot_clock)
+                            .with_system(reset_client_scoreboard)
+                            .with_system(reset_client_teams)
+                            .with_system(reset_client_hud),
+                    )
+                    .add_system_set(
+                        SystemSet::on_enter(AppState::Connecting)
+                            .with_system(clear_remote_entities)
+                            .with_system(reset_spectate_view)
+                            .with_system(reset_local_held_item)
+                            .with_system(reset_client_weather)
+                            .with_system(reset_block_cracks)
+                            .with_system(reset_snapshot_clock)
+                            .with_system(reset_client_scoreboard)
+                            .with_system(reset_client_teams)
+                            .with_system(reset_client_hud),
+                    )
+                    .add_system(send_input_activity.with_run_criteria(run_while_connected))
+                    .add_system_to_stage(NetworkFlush, flush_client_messages)
+                    .add_system_to_stage(CoreStage::Last, update_client_capture)
+            },
+            NetworkSide::Playback {
+                session,
+            } => {
+                if !app.world.contains_resource::<State<AppState>>() {
+                    app.add_state(AppState::LoadingWorld);
+                }
+
+                app.insert_resource(session.clone())
+                    .init_resource::<PlayerRoster>()
+                    .init_resource::<PendingInputActivity>()
+                    .init_resource::<ContainerView>()
+                    .init_resource::<MessageInbox>()
+                    .init_resource::<RemoteEntities>()
+                    .init_resource::<SpectateView>()
+                    .init_resource::<LocalHeldItem>()
+                    .init_resource::<NetworkStats>()
+                    .init_resource::<ClientWeather>()
+                    .init_resource::<BlockCracks>()
+                    .init_resource::<SnapshotClock>()
+                    .init_resource::<ClientScoreboard>()
+                    .init_resource::<ClientTeams>()
+                    .init_resource::<ClientHud>()
+                    .add_event::<PlayerJoinedEvent>()
+                    .add_event::<PlayerLeftEvent>()
+                    .add_event::<ExplosionEffectEvent>()
+                    .add_event::<ParticleEvent>()
+                    .add_event::<BlockUsedEffectEvent>()
+                    .add_event::<SoundEvent>()
+                    .add_event::<ChatMessageReceivedEvent>()
+                    .add_system(play_back_session)
+                    .add_system(apply_roster_messages.after(play_back_session))
+                    .add_system(receive_chat_messages.after(apply_roster_messages))
+                    .add_system(apply_container_messages.after(play_back_session))
+                    .add_system(dispatch_effect_messages.after(play_back_session))
+                    .add_system(apply_replication_messages.after(play_back_session))
+                    .add_system(apply_entity_updates.after(apply_replication_messages))
+                    .add_system(apply_spectate_messages.after(play_back_session))
+                    .add_system(apply_held_item_messages.after(play_back_session))
+                    .add_system(reconcile_movement.after(play_back_session))
+                    .add_system(apply_weather_messages.after(play_back_session))
+                    .add_system(update_weather_transition.after(apply_weather_messages))
+                    .add_system(apply_block_crack_messages.after(play_back_session))
+                    .add_system(apply_scoreboard_messages.after(play_back_session))
+                    .add_system(apply_teams_messages.after(play_back_session))
+                    .add_system(apply_hud_messages.after(play_back_session))
+                    .add_system(update_client_hud.after(apply_hud_messages))
+                    .add_system_to_stage("tick", record_movement_inputs)
+                    .add_system_to_stage("tick", interpolate_remote_positions)
+                    .add_system_to_stage(NetworkFlush, discard_playback_messages)
             },
         };
+
+        add_protocol_messages(app);
+        app.replicate_component::<Position>();
+        app.replicate_component::<HeldItem>();
+        app.replicate_component::<LookRotation>();
+        app.replicate_component::<TextDisplay>();
     }
 }
 
 
-/// Builds a new Renet Server instance on the given port.
-fn build_server(port: u16, max_clients: usize) -> RenetServer {
+/// Builds a new Renet Server instance on the given port, with the given
+/// channels.
+///
+/// If a private key is given, clients must connect with a connect token that
+/// was signed with that key.
+fn build_server(
+    port: u16,
+    max_clients: usize,
+    private_key: Option<&PrivateKey>,
+    channels: &NetworkChannels,
+) -> RenetServer {
     let server_addr = format!("127.0.0.1:{port}").parse().unwrap();
     let socket = UdpSocket::bind(server_addr).unwrap();
-    let connection_config = RenetConnectionConfig::default();
-    let auth = ServerAuthentication::Unsecure;
-    let server_config = ServerConfig::new(max_clients, PROTOCOL_ID, server_addr, auth);
+    let connection_config = channels.connection_config();
+    let auth = match private_key {
+        Some(key) => {
+            ServerAuthentication::Secure {
+                private_key: *key.bytes(),
+            }
+        },
+        None => ServerAuthentication::Unsecure,
+    };
+    let server_config = ServerConfig::new(max_clients, TRANSPORT_PROTOCOL_ID, server_addr, auth);
     let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
     RenetServer::new(time, server_config, connection_config, socket).unwrap()
 }
 
 
-/// Builds a new Renet Client instance on the given port.
-fn build_client(ip: &str, port: u16) -> RenetClient {
-    let server_addr = format!("{ip}:{port}").parse().unwrap();
-    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
-    let connection_config = RenetConnectionConfig::default();
-    let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
-    let client_id = time.as_millis() as u64;
-    let auth = ClientAuthentication::Unsecure {
-        client_id,
-        protocol_id: PROTOCOL_ID,
-        server_addr,
-        user_data: None,
+/// Builds a new Renet Client instance that connects to the server at the
+/// given address with the given user data, over the given channels.
+///
+/// If a token issuer is given, the client connects using a connect token from
+/// that issuer.
+fn build_client(
+    ip: &str,
+    port: u16,
+    issuer: Option<&dyn TokenIssuer>,
+    user_data: &UserData,
+    channels: &NetworkChannels,
+) -> Result<RenetClient> {
+    let server_addr = format!("{ip}:{port}").parse()?;
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    let connection_config = channels.connection_config();
+    let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    let client_id = time.as_nanos() as u64;
+    let auth = match issuer {
+        Some(issuer) => {
+            let connect_token = issuer
+                .issue(client_id, TRANSPORT_PROTOCOL_ID, server_addr, user_data)
+                .context("Failed to obtain a connect token")?;
+            ClientAuthentication::Secure {
+                connect_token,
+            }
+        },
+        None => {
+            ClientAuthentication::Unsecure {
+                client_id,
+                protocol_id: TRANSPORT_PROTOCOL_ID,
+                server_addr,
+                user_data: Some(user_data.encode()?),
+            }
+        },
     };
-    RenetClient::new(time, socket, connection_config, auth).unwrap()
+    Ok(RenetClient::new(time, socket, connection_config, auth)?)
 }
@@ -0,0 +1,461 @@
+//! A typed API for the game messages that are sent between the server and its
+//! clients.
+//!
+//! Each message type implements [`NetworkMessage`], which assigns it a unique
+//! ID and the channel that it is sent over. Messages are serialized with
+//! bincode and prefixed with their ID, so that many message types may share a
+//! single channel. Once received, each message is triggered as an event on the
+//! receiving side, so that game systems never need to read from the raw
+//! network channels.
+
+
+#[cfg(feature = "network_simulator")]
+use crate::prelude::NetworkSimulator;
+use crate::prelude::{
+    capture_message, decompress_payload, play_back_session, CaptureDirection, CaptureSide, ClientEncryption, ClientSocket, DisconnectCause, DisconnectMessage, MessageBatch, NetworkChannels, PendingDisconnect, ServerEncryption, SessionPlayback, COMPRESSED_FLAG
+};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_renet::renet::{RenetClient, RenetServer};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+
+/// The number of bytes used to store the ID of each message.
+pub const MESSAGE_ID_BYTES: usize = 2;
+
+
+/// The tag of a named network channel that messages are sent over.
+///
+/// Each channel tag carries the ID of its underlying transport channel, so
+/// that messages are routed by their tag without any lookup. How the messages
+/// of each channel are delivered is configured by the
+/// [`NetworkChannels`](crate::prelude::NetworkChannels) of the network plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct MessageChannel {
+    /// The ID of the underlying transport channel.
+    pub id: u8,
+
+    /// The name of the channel.
+    pub name: &'static str,
+}
+
+impl MessageChannel {
+    /// General gameplay messages that must arrive in the order that they were
+    /// sent. The handshake is always sent over this channel.
+    pub const RELIABLE: MessageChannel = MessageChannel::new(0, "Reliable");
+
+    /// Frequent or purely cosmetic messages that may be dropped.
+    pub const UNRELIABLE: MessageChannel = MessageChannel::new(1, "Unreliable");
+
+    /// Chat messages, which are kept apart from bulk gameplay messages so that
+    /// they are never held up behind them.
+    pub const CHAT: MessageChannel = MessageChannel::new(2, "Chat");
+
+    /// Movement inputs and entity position updates, which are superseded by
+    /// the next update and may be dropped.
+    pub const ENTITY_MOVES: MessageChannel = MessageChannel::new(3, "EntityMoves");
+
+
+    /// Creates a new channel tag with the given transport channel ID and name.
+    pub const fn new(id: u8, name: &'static str) -> Self {
+        Self {
+            id,
+            name,
+        }
+    }
+}
+
+impl From<MessageChannel> for u8 {
+    fn from(channel: MessageChannel) -> Self {
+        channel.id
+    }
+}
+
+
+/// A game message that may be sent between the server and its clients.
+///
+/// The message types of the Awgen protocol are implemented by the protocol
+/// schema, within the `protocol` module. Message types must be registered with
+/// [`NetworkMessageExt::add_network_message`] on the receiving side in order
+/// to be received.
+pub trait NetworkMessage: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The unique ID of this message type. No two message types may share the
+    /// same ID.
+    const ID: u16;
+
+    /// The channel that this message type is sent over.
+    const CHANNEL: MessageChannel;
+}
+
+
+/// An event that is triggered on the server for each message of the given
+/// type that is received from a client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientMessage<M> {
+    /// The client id of the client that sent the message.
+    pub client_id: u64,
+
+    /// The player entity of the client that sent the message.
+    pub player: Entity,
+
+    /// The message.
+    pub message: M,
+}
+
+
+/// An event that is triggered on a client for each message of the given type
+/// that is received from the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerMessage<M> {
+    /// The message.
+    pub message: M,
+}
+
+
+/// A single message that has been received, but not yet deserialized.
+#[derive(Debug, Clone)]
+struct InboundMessage {
+    /// The client id and player entity of the sender, or None if the message
+    /// was sent by the server.
+    sender: Option<(u64, Entity)>,
+
+    /// The serialized message, without its ID.
+    payload: Vec<u8>,
+}
+
+
+/// A resource that stores the messages received during the current frame,
+/// grouped by message ID, until they are forwarded as events.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct MessageInbox {
+    /// The received messages of each message ID.
+    messages: HashMap<u16, Vec<InboundMessage>>,
+}
+
+impl MessageInbox {
+    /// Splits the ID from the given raw message and stores it within this
+    /// inbox, decompressing the message if it was compressed.
+    ///
+    /// Returns false if the message is too short to contain an ID, or if it
+    /// could not be decompressed.
+    pub fn push(&mut self, sender: Option<(u64, Entity)>, mut bytes: Vec<u8>) -> bool {
+        if bytes.len() < MESSAGE_ID_BYTES {
+            return false;
+        }
+
+        let id = u16::from_le_bytes([bytes[0], bytes[1]]);
+        bytes.drain(..MESSAGE_ID_BYTES);
+
+        if id & COMPRESSED_FLAG != 0 {
+            match decompress_payload(&bytes) {
+                Ok(payload) => bytes = payload,
+                Err(_) => return false,
+            }
+        }
+
+        let id = id & !COMPRESSED_FLAG;
+
+        self.messages.entry(id).or_default().push(InboundMessage {
+            sender,
+            payload: bytes,
+        });
+        true
+    }
+
+
+    /// Removes and returns all stored messages with the given ID.
+    fn take(&mut self, id: u16) -> Vec<InboundMessage> {
+        self.messages.remove(&id).unwrap_or_default()
+    }
+
+
+    /// Removes all stored messages, returning the number of messages that
+    /// were never forwarded.
+    pub fn clear(&mut self) -> usize {
+        let unhandled = self.messages.values().map(Vec::len).sum();
+        self.messages.clear();
+        unhandled
+    }
+}
+
+
+/// A resource that stores the name of the message type that has been
+/// registered for each message ID.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct MessageRegistry {
+    /// The type name of each registered message ID.
+    types: HashMap<u16, &'static str>,
+}
+
+impl MessageRegistry {
+    /// Registers the given message type.
+    ///
+    /// Returns false if the message type has already been registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another message type has already been registered with the
+    /// same ID.
+    pub fn register<M: NetworkMessage>(&mut self) -> bool {
+        let name = std::any::type_name::<M>();
+        match self.types.get(&M::ID) {
+            Some(existing) if *existing == name => false,
+            Some(existing) => {
+                panic!(
+                    "Message ID {} of {name} is already used by {existing}",
+                    M::ID
+                )
+            },
+            None => {
+                self.types.insert(M::ID, name);
+                true
+            },
+        }
+    }
+
+
+    /// Gets the type name of the message type with the given ID, if it has
+    /// been registered.
+    pub fn get(&self, id: u16) -> Option<&'static str> {
+        self.types.get(&id).copied()
+    }
+}
+
+
+/// Serializes the given message, prefixed with its message ID.
+pub fn encode_message<M: NetworkMessage>(message: &M) -> Vec<u8> {
+    let mut bytes = M::ID.to_le_bytes().to_vec();
+    bincode::serialize_into(&mut bytes, message).unwrap();
+    bytes
+}
+
+
+/// Serializes a message and queues it to be sent to the given client once the
+/// message batch is flushed.
+pub fn send_to_client<M: NetworkMessage>(batch: &mut MessageBatch, client_id: u64, message: &M) {
+    batch.push_to_client(client_id, M::CHANNEL, encode_message(message));
+}
+
+
+/// Serializes a message and queues it to be sent to all connected clients
+/// once the message batch is flushed.
+pub fn broadcast<M: NetworkMessage>(batch: &mut MessageBatch, message: &M) {
+    batch.push_to_all(M::CHANNEL, encode_message(message));
+}
+
+
+/// Serializes a message and queues it to be sent to the server once the
+/// message batch is flushed.
+pub fn send_to_server<M: NetworkMessage>(batch: &mut MessageBatch, message: &M) {
+    batch.push_to_server(M::CHANNEL, encode_message(message));
+}
+
+
+/// Receives all messages from the server and stores them within the message
+/// inbox, to be forwarded as events.
+///
+/// Encrypted messages are decrypted before they are captured. The session with
+/// the server starts as soon as its public key is received, so that the
+/// messages that follow it are decrypted right away. Encrypted messages that
+/// arrived before the public key are received alongside it. If the server sent
+/// an invalid public key, the client disconnects.
+///
+/// With the `network_simulator` feature, received messages pass through the
+/// network simulator first, and are only stored once they are delivered.
+pub fn receive_server_messages(
+    channels: Res<NetworkChannels>,
+    mut client: ResMut<RenetClient>,
+    mut inbox: ResMut<MessageInbox>,
+    mut encryption: ResMut<ClientEncryption>,
+    mut pending: ResMut<PendingDisconnect>,
+    #[cfg(feature = "network_simulator")] time: Res<Time>,
+    #[cfg(feature = "network_simulator")] mut simulator: ResMut<NetworkSimulator>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "receive", message = "server").entered();
+
+    let unhandled = inbox.clear();
+    if unhandled > 0 {
+        warn!("Dropped {unhandled} messages of unregistered types");
+    }
+
+    let mut received = vec![];
+    for channel in channels.iter() {
+        while let Some(bytes) = client.receive_message(channel) {
+            let messages = match encryption.open(bytes) {
+                Ok(messages) => messages,
+                Err(err) => {
+                    error!("Disconnecting from the server: {err:#}");
+                    pending.0 = Some(DisconnectMessage {
+                        cause:  DisconnectCause::ProtocolMismatch,
+                        reason: "The server sent an invalid encryption key".to_string(),
+                    });
+                    client.disconnect();
+                    return;
+                },
+            };
+
+            for bytes in messages {
+                capture_message(
+                    CaptureSide::Client,
+                    CaptureDirection::Received,
+                    None,
+                    &bytes,
+                );
+                received.push((channel, bytes));
+            }
+        }
+    }
+
+    #[cfg(feature = "network_simulator")]
+    let received = simulator.simulate_inbound(time.elapsed_seconds_f64(), received);
+
+    for (_, bytes) in received {
+        if !inbox.push(None, bytes) {
+            warn!("Received malformed message from server");
+        }
+    }
+}
+
+
+/// Receives all messages from each client and stores them within the message
+/// inbox, to be forwarded as events.
+///
+/// If the server requires encrypted connections, messages are decrypted before
+/// they are captured, and game messages from a client are held back until its
+/// first encrypted message.
+pub fn receive_client_messages(
+    channels: Res<NetworkChannels>,
+    mut server: ResMut<RenetServer>,
+    mut inbox: ResMut<MessageInbox>,
+    mut encryption: Option<ResMut<ServerEncryption>>,
+    clients: Query<(Entity, &ClientSocket)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "receive", message = "client").entered();
+
+    let unhandled = inbox.clear();
+    if unhandled > 0 {
+        warn!("Dropped {unhandled} messages of unregistered types");
+    }
+
+    for (player, socket) in clients.iter() {
+        for channel in channels.iter() {
+            while let Some(bytes) = server.receive_message(socket.id(), channel) {
+                let messages = match &mut encryption {
+                    Some(encryption) => encryption.open(socket.id(), bytes),
+                    None => vec![bytes],
+                };
+
+                for bytes in messages {
+                    capture_message(
+                        CaptureSide::Server,
+                        CaptureDirection::Received,
+                        Some(socket.id()),
+                        &bytes,
+                    );
+                    if !inbox.push(Some((socket.id(), player)), bytes) {
+                        warn!("Received malformed message from client {}", socket.id());
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+/// Deserializes all received messages of the given type from the server and
+/// triggers an event for each of them.
+pub fn forward_server_messages<M: NetworkMessage>(
+    mut inbox: ResMut<MessageInbox>,
+    mut message_ev: EventWriter<ServerMessage<M>>,
+) {
+    for inbound in inbox.take(M::ID) {
+        match bincode::deserialize::<M>(&inbound.payload) {
+            Ok(message) => {
+                message_ev.send(ServerMessage {
+                    message,
+                })
+            },
+            Err(err) => {
+                warn!(
+                    "Received malformed {} message: {err}",
+                    std::any::type_name::<M>()
+                )
+            },
+        }
+    }
+}
+
+
+/// Deserializes all received messages of the given type from each client and
+/// triggers an event for each of them.
+pub fn forward_client_messages<M: NetworkMessage>(
+    mut inbox: ResMut<MessageInbox>,
+    mut message_ev: EventWriter<ClientMessage<M>>,
+) {
+    for inbound in inbox.take(M::ID) {
+        let Some((client_id, player)) = inbound.sender else {
+            continue;
+        };
+
+        match bincode::deserialize::<M>(&inbound.payload) {
+            Ok(message) => {
+                message_ev.send(ClientMessage {
+                    client_id,
+                    player,
+                    message,
+                })
+            },
+            Err(err) => {
+                warn!(
+                    "Received malformed {} message from client {client_id}: {err}",
+                    std::any::type_name::<M>()
+                )
+            },
+        }
+    }
+}
+
+
+/// An extension trait for apps that allows for network message types to be
+/// registered.
+pub trait NetworkMessageExt {
+    /// Registers the given message type, so that it is triggered as an event
+    /// when it is received.
+    ///
+    /// On the server, received messages are triggered as [`ClientMessage`]
+    /// events. On a client, they are triggered as [`ServerMessage`] events.
+    /// The network plugin must be added before any message types are
+    /// registered.
+    fn add_network_message<M: NetworkMessage>(&mut self) -> &mut Self;
+}
+
+impl NetworkMessageExt for App {
+    fn add_network_message<M: NetworkMessage>(&mut self) -> &mut Self {
+        let registered =
+            self.world.get_resource_or_insert_with(MessageRegistry::default).register::<M>();
+
+        if !registered {
+            return self;
+        }
+
+        if self.world.contains_resource::<RenetServer>() {
+            self.add_event::<ClientMessage<M>>()
+                .add_system(forward_client_messages::<M>.after(receive_client_messages));
+        }
+
+        if self.world.contains_resource::<RenetClient>() {
+            self.add_event::<ServerMessage<M>>()
+                .add_system(forward_server_messages::<M>.after(receive_server_messages));
+        }
+
+        if self.world.contains_resource::<SessionPlayback>() {
+            self.add_event::<ServerMessage<M>>()
+                .add_system(forward_server_messages::<M>.after(play_back_session));
+        }
+
+        self
+    }
+}
@@ -0,0 +1,171 @@
+//! Plays a recorded network session back into the client, so that desync bugs
+//! may be reproduced offline without a server.
+//!
+//! Sessions are recorded with the capture mode of the [`capture`] module.
+//! When a capture is played back, each message that the recording client
+//! received from the server is fed into the [`MessageInbox`] at the same time,
+//! relative to the first message, as it was originally received. From there,
+//! it is forwarded as an event just like a message received over the network.
+//! Messages that the client sends during playback are discarded.
+//!
+//! [`capture`]: crate::capture
+
+
+use crate::prelude::{
+    read_capture, CaptureDirection, CaptureRecord, CaptureSide, MessageBatch, MessageInbox, PROTOCOL_ID
+};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::path::Path;
+
+
+/// A client-side resource that stores a recorded session that is being played
+/// back.
+#[derive(Debug, Clone, Resource)]
+pub struct SessionPlayback {
+    /// The messages that have not been played back yet, in the order that
+    /// they were received.
+    records: VecDeque<CaptureRecord>,
+
+    /// The capture time of the first message of the session.
+    first_time: f64,
+
+    /// The rate at which the session is played back, where 1.0 is the speed
+    /// at which it was recorded.
+    speed: f64,
+
+    /// The app time at which playback started, once it has started.
+    started: Option<f64>,
+}
+
+impl SessionPlayback {
+    /// Creates a new playback of the messages that were received by a client
+    /// within the given capture records.
+    ///
+    /// Returns an error if no client received any messages within the
+    /// records.
+    pub fn new(records: Vec<CaptureRecord>) -> Result<Self> {
+        let records: VecDeque<CaptureRecord> = records
+            .into_iter()
+            .filter(|record| {
+                record.side == CaptureSide::Client && record.direction == CaptureDirection::Received
+            })
+            .collect();
+
+        let Some(first) = records.front() else {
+            bail!("The session does not contain any messages received by a client");
+        };
+
+        Ok(Self {
+            first_time: first.time,
+            records,
+            speed: 1.0,
+            started: None,
+        })
+    }
+
+
+    /// Loads the session that was recorded within the capture file at the
+    /// given path.
+    ///
+    /// Returns an error if the capture was recorded with a different protocol
+    /// schema, as its messages could not be decoded.
+    pub fn load(path: &Path) -> Result<Self> {
+        let (header, records) = read_capture(path)?;
+        if header.protocol != PROTOCOL_ID {
+            bail!(
+                "The session was recorded by Awgen {} with protocol {:016x}, which does not match {PROTOCOL_ID:016x}",
+                header.version,
+                header.protocol
+            );
+        }
+
+        Self::new(records)
+    }
+
+
+    /// Plays this session back at the given rate, where 1.0 is the speed at
+    /// which it was recorded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the given speed is not positive.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "Playback speed must be positive");
+        self.speed = speed;
+        self
+    }
+
+
+    /// Gets the number of messages that have not been played back yet.
+    pub fn remaining(&self) -> usize {
+        self.records.len()
+    }
+
+
+    /// Gets whether or not all messages of this session have been played
+    /// back.
+    pub fn is_finished(&self) -> bool {
+        self.records.is_empty()
+    }
+
+
+    /// Removes and returns all messages that are due at the given app time,
+    /// starting playback if it has not started yet.
+    fn take_due(&mut self, now: f64) -> Vec<CaptureRecord> {
+        let started = *self.started.get_or_insert(now);
+        let elapsed = (now - started) * self.speed;
+
+        let mut due = vec![];
+        while let Some(record) = self.records.front() {
+            if record.time - self.first_time > elapsed {
+                break;
+            }
+
+            due.extend(self.records.pop_front());
+        }
+
+        due
+    }
+}
+
+
+/// Feeds all recorded messages that are due into the message inbox, to be
+/// forwarded as events. This runs in place of
+/// [`receive_server_messages`](crate::prelude::receive_server_messages) while
+/// a session is being played back.
+pub fn play_back_session(
+    time: Res<Time>,
+    mut playback: ResMut<SessionPlayback>,
+    mut inbox: ResMut<MessageInbox>,
+) {
+    let unhandled = inbox.clear();
+    if unhandled > 0 {
+        warn!("Dropped {unhandled} messages of unregistered types");
+    }
+
+    if playback.is_finished() {
+        return;
+    }
+
+    for record in playback.take_due(time.elapsed_seconds_f64()) {
+        if !inbox.push(None, record.wire_bytes()) {
+            warn!(
+                "Recorded {} message at {:.3}s is malformed",
+                record.message_type, record.time
+            );
+        }
+    }
+
+    if playback.is_finished() {
+        info!("Session playback finished");
+    }
+}
+
+
+/// Discards all messages that the client has sent during playback, as there is
+/// no server to send them to.
+pub fn discard_playback_messages(mut batch: ResMut<MessageBatch>) {
+    batch.clear();
+}
@@ -0,0 +1,175 @@
+//! Client-side prediction of the movement of the local player, with
+//! reconciliation against the authoritative position reported by the server.
+//!
+//! Each physics tick, the client applies the force of its own velocity source
+//! immediately, and records it as a movement input tagged with a sequence
+//! number. All inputs that have not yet been acknowledged are sent to the
+//! server, which applies them in order and acknowledges the last input that it
+//! applied alongside the resulting position. When an acknowledgement arrives,
+//! the client resets its position to the acknowledged position and replays
+//! all of the inputs that the server has not yet applied.
+
+
+use crate::prelude::{send_to_server, MessageBatch, ServerMessage};
+use awgen_physics::prelude::{Position, VelocitySource};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+
+/// The maximum horizontal and vertical movement speed of a player, in meters
+/// per second. The server rejects movement inputs that exceed this speed.
+pub const MAX_MOVEMENT_SPEED: f32 = 4.0;
+
+
+/// The maximum number of unacknowledged movement inputs that are kept by the
+/// client. Older inputs are discarded if the server stops responding. The
+/// server rejects movement input messages with more inputs than this.
+pub const MAX_PENDING_INPUTS: usize = 64;
+
+
+/// The distance, in meters, that the predicted position of the local player
+/// may differ from the reconciled position before it is corrected.
+const RECONCILE_TOLERANCE: f32 = 0.001;
+
+
+/// A network message that is sent from a client to the server each physics
+/// tick, containing all movement inputs that have not yet been acknowledged.
+///
+/// Unacknowledged inputs are resent with each message, so that inputs within
+/// lost messages are still applied by the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MovementInput {
+    /// The sequence number of the first input.
+    pub first_sequence: u32,
+
+    /// The force of each input, in meters per physics tick, in sequence
+    /// order.
+    pub forces: Vec<Vec3>,
+}
+
+
+/// A network message that is sent from the server to a client after applying
+/// its movement inputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MovementAck {
+    /// The sequence number of the last movement input that was applied.
+    pub sequence: u32,
+
+    /// The authoritative position of the player after applying the input.
+    pub translation: Vec3,
+}
+
+
+/// A client-side component for the local player, which records the movement
+/// inputs that have been predicted but not yet acknowledged by the server.
+#[derive(Debug, Clone, Default, Component)]
+pub struct PredictedMovement {
+    /// The sequence number of the next movement input.
+    next_sequence: u32,
+
+    /// The sequence number and force of each unacknowledged movement input,
+    /// from oldest to newest.
+    pending: VecDeque<(u32, Vec3)>,
+
+    /// The sequence number of the last acknowledged movement input, if any.
+    last_ack: Option<u32>,
+}
+
+impl PredictedMovement {
+    /// Gets the number of movement inputs that have not yet been acknowledged.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+
+    /// Records a new movement input with the given force, returning its
+    /// sequence number.
+    pub fn record(&mut self, force: Vec3) -> u32 {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+
+        self.pending.push_back((sequence, force));
+        if self.pending.len() > MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+
+        sequence
+    }
+
+
+    /// Discards all inputs up to and including the given sequence number, and
+    /// returns the position of the player after replaying all remaining
+    /// inputs on top of the given acknowledged position.
+    ///
+    /// Returns None if a newer acknowledgement has already been reconciled.
+    pub fn reconcile(&mut self, sequence: u32, translation: Vec3) -> Option<Vec3> {
+        if self.last_ack.is_some_and(|last| sequence.wrapping_sub(last) as i32 <= 0) {
+            return None;
+        }
+        self.last_ack = Some(sequence);
+
+        while let Some((pending, _)) = self.pending.front() {
+            if pending.wrapping_sub(sequence) as i32 > 0 {
+                break;
+            }
+            self.pending.pop_front();
+        }
+
+        Some(self.pending.iter().fold(translation, |pos, (_, force)| pos + *force))
+    }
+
+
+    /// Builds the movement input message that contains all unacknowledged
+    /// inputs.
+    fn message(&self) -> Option<MovementInput> {
+        let (first_sequence, _) = self.pending.front()?;
+        Some(MovementInput {
+            first_sequence: *first_sequence,
+            forces:         self.pending.iter().map(|(_, force)| *force).collect(),
+        })
+    }
+}
+
+
+/// Records the current force of the velocity source of the local player as a
+/// movement input, and sends all unacknowledged inputs to the server.
+///
+/// This is called once each physics tick, before velocity is applied, so that
+/// each recorded input is predicted on the same tick.
+pub fn record_movement_inputs(
+    mut batch: ResMut<MessageBatch>,
+    mut players: Query<(&VelocitySource, &mut PredictedMovement)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "movement").entered();
+
+    for (source, mut prediction) in players.iter_mut() {
+        prediction.record(source.force);
+
+        if let Some(message) = prediction.message() {
+            send_to_server(&mut batch, &message);
+        }
+    }
+}
+
+
+/// Reconciles the predicted position of the local player with each movement
+/// acknowledgement that was received from the server.
+pub fn reconcile_movement(
+    mut ack_ev: EventReader<ServerMessage<MovementAck>>,
+    mut players: Query<(&mut Position, &mut PredictedMovement)>,
+) {
+    for ev in ack_ev.iter() {
+        for (mut position, mut prediction) in players.iter_mut() {
+            let ack = &ev.message;
+            let Some(reconciled) = prediction.reconcile(ack.sequence, ack.translation) else {
+                continue;
+            };
+
+            if position.translation.distance(reconciled) > RECONCILE_TOLERANCE {
+                position.translation = reconciled;
+            }
+        }
+    }
+}
@@ -0,0 +1,440 @@
+//! The declarative schema of the Awgen wire protocol.
+//!
+//! Every network message type is listed within the schema below alongside its
+//! message ID, channel, revision, and layout. The schema generates the
+//! [`NetworkMessage`] implementation of each message type, and is also
+//! available at runtime as [`PROTOCOL`], so that tools and alternative clients
+//! can be built against it.
+//!
+//! The layout of a struct message lists each of its fields and their types,
+//! while the layout of an enum message lists each of its variants. Layouts are
+//! checked against the message types at compile time, so a message type cannot
+//! be changed without also updating the schema, and therefore the protocol
+//! fingerprint. Changes within the variants of an enum message, or within the
+//! types that a message contains, are not covered by its layout, and must be
+//! marked by increasing the revision of the message type instead.
+//!
+//! Message IDs are stable. Once a message ID has been assigned, it must never
+//! be reused for another message type, even if the original message type is
+//! removed. Duplicate message IDs are rejected at compile time.
+//!
+//! On the wire, each message is encoded as its message ID, as a little-endian
+//! `u16`, followed by the message itself, serialized with the default bincode
+//! configuration. If the highest bit of the message ID is set, the message is
+//! compressed, as described within the `compression` module. Message IDs must
+//! therefore stay below `0x8000`. The message ID `0x7FFF` is reserved for
+//! encrypted messages, as described within the `encryption` module.
+//!
+//! The handshake and disconnect messages are read by every version of Awgen,
+//! so their message IDs and formats are frozen, as described within the
+//! `handshake` module.
+
+
+use crate::prelude::*;
+use awgen_math::prelude::Direction;
+use awgen_physics::prelude::ItemStack;
+use bevy::prelude::*;
+use serde::Serialize;
+
+
+/// The 64-bit FNV-1a offset basis.
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+
+/// The 64-bit FNV-1a prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+
+/// The schema entry of a single network message type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MessageSchema {
+    /// The stable ID of the message type.
+    pub id: u16,
+
+    /// The name of the message type.
+    pub name: &'static str,
+
+    /// The channel that the message type is sent over.
+    pub channel: MessageChannel,
+
+    /// The revision of the message format, which is increased each time the
+    /// message type changes in a way that is not covered by its layout.
+    pub revision: u16,
+
+    /// The fields and their types of a struct message, or the variants of an
+    /// enum message, as written within the schema.
+    pub layout: &'static str,
+}
+
+
+/// Panics if any two message types within the given schema share the same ID,
+/// or if any message type uses a reserved ID of [`ENCRYPTED_MESSAGE_ID`] or
+/// above.
+///
+/// This is evaluated at compile time for the protocol schema.
+pub const fn assert_unique_ids(schema: &[MessageSchema]) {
+    let mut i = 0;
+    while i < schema.len() {
+        if schema[i].id >= ENCRYPTED_MESSAGE_ID {
+            panic!("Reserved message ID within the protocol schema");
+        }
+
+        let mut j = i + 1;
+        while j < schema.len() {
+            if schema[i].id == schema[j].id {
+                panic!("Duplicate message ID within the protocol schema");
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+
+/// Computes a fingerprint of the given protocol schema.
+///
+/// Any change to the ID, channel, revision, or layout of a message type results
+/// in a different fingerprint. The order and names of message types are
+/// ignored, as they do not affect the wire format. Whitespace within layouts is
+/// ignored, so that the fingerprint does not depend on how the compiler formats
+/// the types within them.
+pub const fn protocol_fingerprint(schema: &[MessageSchema]) -> u64 {
+    let mut fingerprint = 0;
+
+    let mut i = 0;
+    while i < schema.len() {
+        let entry = &schema[i];
+        let id = entry.id.to_le_bytes();
+        let revision = entry.revision.to_le_bytes();
+        let bytes = [id[0], id[1], entry.channel.id, revision[0], revision[1]];
+
+        let mut hash = FNV_OFFSET;
+        let mut j = 0;
+        while j < bytes.len() {
+            hash = (hash ^ bytes[j] as u64).wrapping_mul(FNV_PRIME);
+            j += 1;
+        }
+
+        let layout = entry.layout.as_bytes();
+        let mut j = 0;
+        while j < layout.len() {
+            if !layout[j].is_ascii_whitespace() {
+                hash = (hash ^ layout[j] as u64).wrapping_mul(FNV_PRIME);
+            }
+            j += 1;
+        }
+
+        fingerprint ^= hash;
+        i += 1;
+    }
+
+    fingerprint
+}
+
+
+/// Checks the layout of a message type within the protocol schema against the
+/// message type at compile time, or describes the layout as a string.
+macro_rules! message_layout {
+    (check $message:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        const _: () = {
+            #[allow(dead_code)]
+            fn check(message: &$message) {
+                let $message { $($field),* } = message;
+                $(let _: &$ty = $field;)*
+            }
+        };
+    };
+    (check $message:ident [ $($variant:ident),* $(,)? ]) => {
+        const _: () = {
+            #[allow(dead_code)]
+            fn check(message: &$message) {
+                match message {
+                    $($message::$variant { .. } => {},)*
+                }
+            }
+        };
+    };
+    (describe { $($field:ident: $ty:ty),* $(,)? }) => {
+        concat!("{", $(stringify!($field), ":", stringify!($ty), ";",)* "}")
+    };
+    (describe [ $($variant:ident),* $(,)? ]) => {
+        concat!("[", $(stringify!($variant), ";",)* "]")
+    };
+}
+
+
+/// Declares the protocol schema, generating the network message
+/// implementation and layout check of each listed message type, the
+/// [`PROTOCOL`] constant, and [`add_protocol_messages`].
+macro_rules! network_protocol {
+    ($(
+        $id:literal => $message:ident {
+            channel: $channel:ident,
+            revision: $revision:literal,
+            layout: $layout:tt $(,)?
+        },
+    )*) => {
+        $(
+            impl NetworkMessage for $message {
+                const ID: u16 = $id;
+                const CHANNEL: MessageChannel = MessageChannel::$channel;
+            }
+
+            message_layout!(check $message $layout);
+        )*
+
+
+        /// The schema of every message type within the Awgen wire protocol.
+        pub const PROTOCOL: &[MessageSchema] = &[
+            $(
+                MessageSchema {
+                    id:       $id,
+                    name:     stringify!($message),
+                    channel:  MessageChannel::$channel,
+                    revision: $revision,
+                    layout:   message_layout!(describe $layout),
+                },
+            )*
+        ];
+
+
+        /// Registers every message type within the protocol schema with the
+        /// given app.
+        pub fn add_protocol_messages(app: &mut App) {
+            $(app.add_network_message::<$message>();)*
+        }
+    };
+}
+
+
+network_protocol! {
+    1 => RosterMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: [List, Joined, Left],
+    },
+    2 => ContainerMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: [Open, Update, Close],
+    },
+    3 => ContainerAction {
+        channel: RELIABLE,
+        revision: 1,
+        layout: [Move, Close],
+    },
+    4 => BlockUseMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { block_pos: IVec3, face: Direction },
+    },
+    5 => EffectMessage {
+        channel: UNRELIABLE,
+        revision: 3,
+        layout: [Explosion, Particles, BlockUsed, Sound],
+    },
+    6 => InputActivityMessage {
+        channel: UNRELIABLE,
+        revision: 1,
+        layout: {},
+    },
+    7 => ReplicationMessage {
+        channel: RELIABLE,
+        revision: 2,
+        layout: [Spawn, Authority, Despawn],
+    },
+    8 => EntityUpdateMessage {
+        channel: ENTITY_MOVES,
+        revision: 1,
+        layout: { tick: u32, entities: Vec<(u64, Vec<ComponentData>)> },
+    },
+    9 => SpectateRequest {
+        channel: RELIABLE,
+        revision: 1,
+        layout: [Next, Previous, Stop],
+    },
+    10 => SpectateStatus {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { target: Option<u64> },
+    },
+    11 => MovementInput {
+        channel: ENTITY_MOVES,
+        revision: 1,
+        layout: { first_sequence: u32, forces: Vec<Vec3> },
+    },
+    12 => MovementAck {
+        channel: ENTITY_MOVES,
+        revision: 1,
+        layout: { sequence: u32, translation: Vec3 },
+    },
+    13 => WeatherMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { weather: Weather, transition: f32 },
+    },
+    14 => DisconnectMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { cause: DisconnectCause, reason: String },
+    },
+    15 => SendChatMessage {
+        channel: CHAT,
+        revision: 1,
+        layout: { text: String },
+    },
+    16 => ChatMessage {
+        channel: CHAT,
+        revision: 1,
+        layout: { sender: Option<u64>, text: String },
+    },
+    17 => BlockEditMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { sequence: u32, block_pos: IVec3, face: Direction, action: BlockEditAction },
+    },
+    18 => BlockEditAck {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { sequence: u32, accepted: bool },
+    },
+    19 => SelectHotbarSlot {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { slot: u8 },
+    },
+    20 => HeldItemMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { slot: u8, item: ItemStack },
+    },
+    21 => CompressionRequest {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { enabled: bool },
+    },
+    22 => HandshakeMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { version: String, protocol: u64 },
+    },
+    23 => ComponentUpdateMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { entity: Option<u64>, components: Vec<ComponentData> },
+    },
+    24 => BlockBreakMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { action: BlockBreakAction },
+    },
+    25 => BlockCrackMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { client_id: u64, block_pos: IVec3, stage: Option<u8> },
+    },
+    26 => ScoreboardMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { slot: DisplaySlot, objective: Option<DisplayedObjective> },
+    },
+    27 => TeamsMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { teams: Vec<TeamInfo> },
+    },
+    28 => HudMessage {
+        channel: RELIABLE,
+        revision: 1,
+        layout: [SetBossBar, RemoveBossBar, Title, ClearTitle, ActionBar],
+    },
+    29 => EncryptionKey {
+        channel: RELIABLE,
+        revision: 1,
+        layout: { public_key: [u8; PUBLIC_KEY_BYTES] },
+    },
+}
+
+
+/// Ensures that no message IDs are shared at compile time.
+const _: () = assert_unique_ids(PROTOCOL);
+
+
+/// The fingerprint of the protocol schema, which is exchanged within the
+/// handshake to reject clients with an incompatible protocol schema.
+pub const PROTOCOL_ID: u64 = protocol_fingerprint(PROTOCOL);
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates a schema entry for a message type with the given ID.
+    fn entry(id: u16) -> MessageSchema {
+        MessageSchema {
+            id,
+            name: "Message",
+            channel: MessageChannel::RELIABLE,
+            revision: 1,
+            layout: "{}",
+        }
+    }
+
+
+    #[test]
+    fn unique_ids() {
+        assert_unique_ids(&[entry(1), entry(2), entry(ENCRYPTED_MESSAGE_ID - 1)]);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn reject_duplicate_ids() {
+        assert_unique_ids(&[entry(1), entry(2), entry(1)]);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn reject_reserved_ids() {
+        assert_unique_ids(&[entry(1), entry(ENCRYPTED_MESSAGE_ID)]);
+    }
+
+
+    #[test]
+    fn describe_layouts() {
+        let layout = |name| PROTOCOL.iter().find(|entry| entry.name == name).unwrap().layout;
+        assert_eq!(layout("BlockEditAck"), "{sequence:u32;accepted:bool;}");
+        assert_eq!(layout("SpectateRequest"), "[Next;Previous;Stop;]");
+        assert_eq!(layout("InputActivityMessage"), "{}");
+    }
+
+
+    #[test]
+    fn fingerprint_includes_layout() {
+        let mut changed = entry(1);
+        changed.layout = "{value:u32;}";
+        assert_ne!(
+            protocol_fingerprint(&[entry(1)]),
+            protocol_fingerprint(&[changed])
+        );
+
+        let mut spaced = changed;
+        spaced.layout = "{ value : u32 ; }";
+        assert_eq!(
+            protocol_fingerprint(&[changed]),
+            protocol_fingerprint(&[spaced])
+        );
+    }
+
+
+    /// Pins the protocol fingerprint, so that changes to the protocol schema
+    /// are always deliberate. If this test fails after changing the schema,
+    /// update the expected fingerprint below.
+    #[test]
+    fn pinned_fingerprint() {
+        assert_eq!(format!("{PROTOCOL_ID:016x}"), "0122788b69970ff5");
+    }
+}
@@ -0,0 +1,450 @@
+//! A remote administration channel, which allows a headless server to be
+//! administered without joining it with a game client.
+//!
+//! The channel is a TCP sidecar that runs next to the game server on its own
+//! port, and is authenticated with a password of its own rather than with
+//! connect tokens. Every message in either direction is a single frame, made
+//! of its length in bytes as a little endian `u32`, followed by that many
+//! bytes of UTF-8 text.
+//!
+//! The first frame that a connection sends must be the password, which the
+//! server answers with [`RCON_AUTH_ACCEPTED`], or with a reason and a closed
+//! connection if it was wrong. Connections that do not send the password
+//! within [`RCON_AUTH_TIMEOUT_SECONDS`] are closed, so that they cannot hold
+//! on to the limited connection slots. Every following frame is a command line,
+//! which is answered with a single frame containing the response of the
+//! command. Commands are forwarded to the game as [`RconCommandEvent`]s, and
+//! answered with [`RconServer::respond`].
+
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+
+/// The largest frame, in bytes, that is accepted over the remote
+/// administration channel.
+pub const RCON_MAX_FRAME_BYTES: usize = 64 * 1024;
+
+
+/// The maximum number of remote administration connections that may be open
+/// at once.
+pub const RCON_MAX_CONNECTIONS: usize = 8;
+
+
+/// The number of seconds that an authenticated remote administration
+/// connection may stay silent before it is closed.
+pub const RCON_IDLE_TIMEOUT_SECONDS: u64 = 300;
+
+
+/// The number of seconds that a new remote administration connection is given
+/// to send its password before it is closed.
+pub const RCON_AUTH_TIMEOUT_SECONDS: u64 = 5;
+
+
+/// The number of seconds to wait for the game to answer a command before the
+/// connection is told that the command did not respond.
+pub const RCON_RESPONSE_TIMEOUT_SECONDS: u64 = 30;
+
+
+/// The number of seconds to wait before answering a wrong password, which
+/// slows down attempts to guess it.
+pub const RCON_AUTH_FAILURE_DELAY_SECONDS: u64 = 1;
+
+
+/// The response that is sent to a connection once its password was accepted.
+pub const RCON_AUTH_ACCEPTED: &str = "Authenticated";
+
+
+/// The settings of the remote administration channel of a server.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RconSettings {
+    /// The port to listen for remote administration connections on.
+    pub port: u16,
+
+    /// The password that connections must authenticate with.
+    pub password: String,
+}
+
+impl std::fmt::Debug for RconSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RconSettings")
+            .field("port", &self.port)
+            .field("password", &"..")
+            .finish()
+    }
+}
+
+
+/// A command line that was received from an authenticated connection, and is
+/// waiting for its response.
+#[derive(Debug)]
+struct RconRequest {
+    /// The id of the connection that sent the command.
+    connection: u64,
+
+    /// The command line.
+    command: String,
+
+    /// The channel to send the response of the command over.
+    reply: Sender<String>,
+}
+
+
+/// An event that is triggered on the server for each command line received
+/// over the remote administration channel.
+///
+/// Each command must be answered with [`RconServer::respond`], as its
+/// connection waits for the response before it reads its next command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RconCommandEvent {
+    /// The id of the connection that sent the command.
+    pub connection: u64,
+
+    /// The command line.
+    pub command: String,
+}
+
+
+/// A server-side resource that accepts remote administration connections on
+/// a background thread.
+#[derive(Resource)]
+pub struct RconServer {
+    /// The receiving end of the command channel.
+    requests: Mutex<Receiver<RconRequest>>,
+
+    /// The reply channel of each connection that is waiting for a response.
+    replies: HashMap<u64, Sender<String>>,
+
+    /// The address that connections are accepted on.
+    address: SocketAddr,
+}
+
+impl RconServer {
+    /// Starts accepting remote administration connections on the port of the
+    /// given settings.
+    ///
+    /// Returns an error if no password was set, or if the port could not be
+    /// opened.
+    pub fn start(settings: &RconSettings) -> Result<Self> {
+        if settings.password.is_empty() {
+            bail!("A password must be set for remote administration");
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", settings.port))?;
+        let address = listener.local_addr()?;
+        let password: Arc<str> = settings.password.as_str().into();
+        let (sender, receiver) = channel();
+
+        std::thread::Builder::new()
+            .name("RCON".to_string())
+            .spawn(move || accept_connections(listener, password, sender))?;
+
+        Ok(Self {
+            requests: Mutex::new(receiver),
+            replies: HashMap::default(),
+            address,
+        })
+    }
+
+
+    /// Gets the address that connections are accepted on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+
+    /// Sends the response of the last command of the given connection.
+    ///
+    /// Does nothing if the connection is not waiting for a response, such as
+    /// when it has already been closed.
+    pub fn respond(&mut self, connection: u64, response: &str) {
+        if let Some(reply) = self.replies.remove(&connection) {
+            let _ = reply.send(response.to_string());
+        }
+    }
+}
+
+
+/// A connection to the remote administration channel of a server.
+#[derive(Debug)]
+pub struct RconClient {
+    /// The authenticated connection to the server.
+    stream: TcpStream,
+}
+
+impl RconClient {
+    /// Connects to the remote administration channel at the given address and
+    /// authenticates with the given password.
+    pub fn connect<A>(address: A, password: &str) -> Result<Self>
+    where A: ToSocketAddrs {
+        let mut stream = TcpStream::connect(address)?;
+        write_frame(&mut stream, password)?;
+
+        match read_frame(&mut stream)? {
+            Some(response) if response == RCON_AUTH_ACCEPTED => {
+                Ok(Self {
+                    stream,
+                })
+            },
+            Some(response) => bail!("{response}"),
+            None => bail!("The server closed the connection"),
+        }
+    }
+
+
+    /// Executes a command line on the server, returning its response.
+    pub fn execute(&mut self, command: &str) -> Result<String> {
+        write_frame(&mut self.stream, command)?;
+        read_frame(&mut self.stream)?.ok_or_else(|| anyhow!("The server closed the connection"))
+    }
+}
+
+
+/// Writes a single frame containing the given text.
+pub fn write_frame<W: Write>(writer: &mut W, text: &str) -> Result<()> {
+    if text.len() > RCON_MAX_FRAME_BYTES {
+        bail!("Frame of {} bytes is too large", text.len());
+    }
+
+    writer.write_all(&(text.len() as u32).to_le_bytes())?;
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+
+/// Reads a single frame, or `None` if the stream was closed before the frame
+/// started.
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<Option<String>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {},
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+
+    let length = u32::from_le_bytes(length) as usize;
+    if length > RCON_MAX_FRAME_BYTES {
+        bail!("Frame of {length} bytes is too large");
+    }
+
+    let mut text = vec![0; length];
+    reader.read_exact(&mut text)?;
+    Ok(Some(String::from_utf8(text)?))
+}
+
+
+/// A reader over a TCP stream that fails once a deadline has passed, no matter
+/// how often the stream receives data, so that a connection cannot stretch a
+/// read out by sending one byte at a time.
+struct DeadlineReader<'a> {
+    /// The stream to read from.
+    stream: &'a TcpStream,
+
+    /// The instant after which all reads fail.
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(ErrorKind::TimedOut, "Deadline has passed"));
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+
+/// Compares two byte strings in an amount of time that only depends on their
+/// lengths, so that the password cannot be guessed from response times.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+
+/// Accepts remote administration connections for as long as the server runs,
+/// handling each on its own thread.
+fn accept_connections(listener: TcpListener, password: Arc<str>, requests: Sender<RconRequest>) {
+    let open = Arc::new(AtomicUsize::new(0));
+
+    for (connection, stream) in (0..).zip(listener.incoming()) {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to accept RCON connection: {err}");
+                continue;
+            },
+        };
+
+        // The slot is reserved before the limit is checked, so that two
+        // connections accepted together cannot both take the last slot.
+        if open.fetch_add(1, Ordering::AcqRel) >= RCON_MAX_CONNECTIONS {
+            open.fetch_sub(1, Ordering::AcqRel);
+            let mut stream = stream;
+            let _ = write_frame(&mut stream, "Too many RCON connections are open");
+            continue;
+        }
+
+        let password = password.clone();
+        let requests = requests.clone();
+        let slot = open.clone();
+
+        let spawned =
+            std::thread::Builder::new().name(format!("RCON {connection}")).spawn(move || {
+                let peer = stream.peer_addr().ok();
+                if let Err(err) = handle_connection(stream, connection, &password, &requests) {
+                    warn!("RCON connection {connection} from {peer:?} closed: {err}");
+                }
+                slot.fetch_sub(1, Ordering::AcqRel);
+            });
+
+        if let Err(err) = spawned {
+            open.fetch_sub(1, Ordering::AcqRel);
+            error!("Failed to start RCON connection thread: {err}");
+        }
+    }
+}
+
+
+/// Authenticates a single connection, and then forwards its commands to the
+/// game until it is closed.
+fn handle_connection(
+    mut stream: TcpStream,
+    connection: u64,
+    password: &str,
+    requests: &Sender<RconRequest>,
+) -> Result<()> {
+    let mut auth_reader = DeadlineReader {
+        stream:   &stream,
+        deadline: Instant::now() + Duration::from_secs(RCON_AUTH_TIMEOUT_SECONDS),
+    };
+
+    let Some(attempt) = read_frame(&mut auth_reader)? else {
+        return Ok(());
+    };
+
+    if !constant_time_eq(attempt.as_bytes(), password.as_bytes()) {
+        std::thread::sleep(Duration::from_secs(RCON_AUTH_FAILURE_DELAY_SECONDS));
+        write_frame(&mut stream, "Wrong RCON password")?;
+        bail!("Wrong password");
+    }
+
+    stream.set_read_timeout(Some(Duration::from_secs(RCON_IDLE_TIMEOUT_SECONDS)))?;
+    write_frame(&mut stream, RCON_AUTH_ACCEPTED)?;
+    info!(
+        "RCON connection {connection} from {:?} authenticated",
+        stream.peer_addr().ok()
+    );
+
+    while let Some(command) = read_frame(&mut stream)? {
+        let (reply, response) = channel();
+        requests
+            .send(RconRequest {
+                connection,
+                command,
+                reply,
+            })
+            .map_err(|_| anyhow!("The server has stopped"))?;
+
+        let timeout = Duration::from_secs(RCON_RESPONSE_TIMEOUT_SECONDS);
+        let response = response
+            .recv_timeout(timeout)
+            .unwrap_or_else(|_| "The command did not respond in time".to_string());
+        write_frame(&mut stream, &response)?;
+    }
+
+    Ok(())
+}
+
+
+/// Triggers an [`RconCommandEvent`] for each command that has been received
+/// over the remote administration channel since the last frame.
+pub fn receive_rcon_commands(
+    mut rcon: ResMut<RconServer>,
+    mut command_ev: EventWriter<RconCommandEvent>,
+) {
+    let requests: Vec<RconRequest> = rcon.requests.lock().unwrap().try_iter().collect();
+
+    for request in requests {
+        rcon.replies.insert(request.connection, request.reply);
+        command_ev.send(RconCommandEvent {
+            connection: request.connection,
+            command:    request.command,
+        });
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Starts a remote administration server on a free port.
+    fn start() -> RconServer {
+        RconServer::start(&RconSettings {
+            port:     0,
+            password: "hunter2".to_string(),
+        })
+        .unwrap()
+    }
+
+
+    #[test]
+    fn authenticate() {
+        let server = start();
+        assert!(RconClient::connect(server.address(), "hunter2").is_ok());
+
+        let err = RconClient::connect(server.address(), "hunter3").unwrap_err();
+        assert_eq!(err.to_string(), "Wrong RCON password");
+    }
+
+
+    #[test]
+    fn close_silent_connections() {
+        let server = start();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(RCON_AUTH_TIMEOUT_SECONDS * 3)))
+            .unwrap();
+
+        let started = Instant::now();
+        stream.write_all(&8u32.to_le_bytes()).unwrap();
+        stream.write_all(b"hunt").unwrap();
+
+        assert!(!matches!(read_frame(&mut stream), Ok(Some(_))));
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(RCON_AUTH_TIMEOUT_SECONDS - 1));
+        assert!(elapsed < Duration::from_secs(RCON_AUTH_TIMEOUT_SECONDS * 2));
+    }
+
+
+    #[test]
+    fn limit_open_connections() {
+        let server = start();
+        let _clients: Vec<RconClient> = (0..RCON_MAX_CONNECTIONS)
+            .map(|_| RconClient::connect(server.address(), "hunter2").unwrap())
+            .collect();
+
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        assert_eq!(
+            read_frame(&mut stream).unwrap(),
+            Some("Too many RCON connections are open".to_string())
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Automatic reconnection of the client after its connection to the server is
+//! lost unexpectedly.
+//!
+//! When an established connection times out or is lost, the client returns to
+//! the connecting state rather than to the main menu, and retries the
+//! connection with exponential backoff. Each attempt rebuilds the
+//! [`RenetClient`] resource from the [`ServerConnection`] that the client was
+//! created with, after which the handshake is repeated as normal. Disconnects
+//! that were reported by the server, such as kicks and shutdowns, are never
+//! retried.
+
+
+use crate::build_client;
+use crate::prelude::{
+    ClientEncryption, DisconnectCause, DisconnectedEvent, NetworkChannels, TokenIssuer, UserData
+};
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+use std::sync::Arc;
+
+
+/// The settings for how the client reconnects to the server after the
+/// connection has been lost.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct ReconnectSettings {
+    /// The maximum number of reconnection attempts before giving up. If zero,
+    /// the client never reconnects.
+    pub max_attempts: u32,
+
+    /// The number of seconds to wait before the first attempt. This delay is
+    /// doubled for each following attempt.
+    pub initial_delay: f64,
+
+    /// The maximum number of seconds to wait before any attempt.
+    pub max_delay: f64,
+}
+
+impl Default for ReconnectSettings {
+    fn default() -> Self {
+        Self {
+            max_attempts:  5,
+            initial_delay: 1.0,
+            max_delay:     30.0,
+        }
+    }
+}
+
+impl ReconnectSettings {
+    /// Gets the number of seconds to wait before the given attempt, starting
+    /// from attempt 1.
+    pub fn delay(&self, attempt: u32) -> f64 {
+        let factor = 2f64.powi(attempt.saturating_sub(1).min(31) as i32);
+        (self.initial_delay * factor).min(self.max_delay)
+    }
+}
+
+
+/// The server that the client connects to, which is used to rebuild the
+/// client when reconnecting.
+#[derive(Clone, Resource)]
+pub struct ServerConnection {
+    /// The IP of the server.
+    pub ip: String,
+
+    /// The port of the server.
+    pub port: u16,
+
+    /// The issuer of the connect tokens used to join the server, if the server
+    /// requires authentication.
+    pub issuer: Option<Arc<dyn TokenIssuer>>,
+
+    /// The user data to join the server with.
+    pub user_data: UserData,
+}
+
+
+/// The current step of reconnecting to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReconnectState {
+    /// The client is not reconnecting.
+    #[default]
+    Idle,
+
+    /// The client is waiting to start the next attempt.
+    Waiting {
+        /// The number of the next attempt, starting from 1.
+        attempt: u32,
+
+        /// The elapsed time, in seconds, at which the next attempt starts.
+        retry_at: f64,
+    },
+
+    /// The client is attempting to connect to the server.
+    Connecting {
+        /// The number of the current attempt, starting from 1.
+        attempt: u32,
+    },
+}
+
+
+/// A client-side resource that tracks the progress of reconnecting to the
+/// server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Reconnection {
+    /// The current step of reconnecting.
+    state: ReconnectState,
+
+    /// The disconnect that caused the client to start reconnecting, which is
+    /// reported if all attempts fail.
+    lost: Option<DisconnectedEvent>,
+}
+
+impl Reconnection {
+    /// Gets the current step of reconnecting.
+    pub fn state(&self) -> ReconnectState {
+        self.state
+    }
+
+
+    /// Gets whether or not the client is currently reconnecting.
+    pub fn is_reconnecting(&self) -> bool {
+        self.state != ReconnectState::Idle
+    }
+}
+
+
+/// An event that is triggered on the client when the connection has been lost
+/// and a reconnection attempt has been scheduled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectingEvent {
+    /// The number of the scheduled attempt, starting from 1.
+    pub attempt: u32,
+
+    /// The maximum number of attempts before giving up.
+    pub max_attempts: u32,
+
+    /// The number of seconds until the attempt starts.
+    pub delay: f64,
+
+    /// The cause of the disconnect that is being recovered from.
+    pub cause: DisconnectCause,
+}
+
+
+/// An event that is triggered on the client when it has successfully
+/// reconnected to the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectedEvent {
+    /// The number of attempts that were needed to reconnect.
+    pub attempts: u32,
+}
+
+
+/// Handles a disconnect on the client, scheduling the next reconnection
+/// attempt if the disconnect may be recovered from.
+///
+/// Returns the disconnect to report if the client should give up instead.
+pub fn schedule_reconnect(
+    ev: DisconnectedEvent,
+    was_connected: bool,
+    now: f64,
+    settings: &ReconnectSettings,
+    reconnection: &mut Reconnection,
+    reconnecting_ev: &mut EventWriter<ReconnectingEvent>,
+) -> Option<DisconnectedEvent> {
+    let attempt = match reconnection.state {
+        ReconnectState::Connecting {
+            attempt,
+        } => attempt + 1,
+        _ if was_connected && ev.cause.is_recoverable() => 1,
+        _ => 0,
+    };
+
+    if attempt == 0 || attempt > settings.max_attempts {
+        reconnection.state = ReconnectState::Idle;
+        return Some(reconnection.lost.take().unwrap_or(ev));
+    }
+
+    let delay = settings.delay(attempt);
+    warn!(
+        "Lost connection to server: {} ({}), reconnecting in {delay:.1}s ({attempt}/{})",
+        ev.cause, ev.reason, settings.max_attempts
+    );
+
+    reconnecting_ev.send(ReconnectingEvent {
+        attempt,
+        max_attempts: settings.max_attempts,
+        delay,
+        cause: ev.cause,
+    });
+
+    reconnection.lost.get_or_insert(ev);
+    reconnection.state = ReconnectState::Waiting {
+        attempt,
+        retry_at: now + delay,
+    };
+    None
+}
+
+
+/// Completes reconnecting once the client has connected to the server again.
+///
+/// Returns the number of attempts that were needed, or `None` if the client
+/// was not reconnecting.
+pub fn finish_reconnect(
+    reconnection: &mut Reconnection,
+    reconnected_ev: &mut EventWriter<ReconnectedEvent>,
+) -> Option<u32> {
+    let ReconnectState::Connecting {
+        attempt,
+    } = reconnection.state
+    else {
+        return None;
+    };
+
+    reconnection.state = ReconnectState::Idle;
+    reconnection.lost = None;
+    reconnected_ev.send(ReconnectedEvent {
+        attempts: attempt,
+    });
+    Some(attempt)
+}
+
+
+/// Starts the scheduled reconnection attempt once its delay has passed, by
+/// replacing the client with a new connection to the server, which is
+/// encrypted with a new key pair.
+///
+/// If the client could not be created, the attempt fails immediately and the
+/// next attempt is scheduled.
+pub fn retry_connection(
+    time: Res<Time>,
+    server: Res<ServerConnection>,
+    channels: Res<NetworkChannels>,
+    mut reconnection: ResMut<Reconnection>,
+    mut client: ResMut<RenetClient>,
+    mut encryption: ResMut<ClientEncryption>,
+) {
+    let ReconnectState::Waiting {
+        attempt,
+        retry_at,
+    } = reconnection.state
+    else {
+        return;
+    };
+
+    if time.elapsed_seconds_f64() < retry_at {
+        return;
+    }
+
+    info!("Reconnecting to server (attempt {attempt})");
+    let issuer = server.issuer.as_deref();
+    let new_client = encryption.regenerate().and_then(|_| {
+        let user_data = server.user_data.clone().with_public_key(encryption.public_key());
+        build_client(&server.ip, server.port, issuer, &user_data, &channels)
+    });
+
+    match new_client {
+        Ok(new_client) => *client = new_client,
+        Err(err) => warn!("Failed to reconnect to server: {err:#}"),
+    }
+
+    reconnection.state = ReconnectState::Connecting {
+        attempt,
+    };
+}
@@ -0,0 +1,539 @@
+//! Replicates the state of server entities to clients.
+//!
+//! Entities on the server that are marked with the [`Replicated`] component
+//! have each of their registered replicated components collected every
+//! physics tick. The server then spawns, updates, and despawns a proxy of each
+//! entity on the clients that are able to see it. Proxies on the client are
+//! marked with the [`RemoteEntity`] component.
+//!
+//! The positions of proxies that the server has authority over are not applied
+//! as they arrive, but are instead stored within their [`SnapshotBuffer`] to
+//! be interpolated.
+
+
+use crate::prelude::{
+    decode_position, send_owned_components, Authority, ServerMessage, SnapshotBuffer, SnapshotClock
+};
+use awgen_physics::prelude::{apply_velocity, run_while_connected, Position, PreviousPosition};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_renet::renet::{RenetClient, RenetServer};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+
+/// A component type whose value is replicated from server entities to their
+/// proxies on each client.
+///
+/// Component types must be registered with
+/// [`ReplicationExt::replicate_component`] on both the server and the client.
+/// Removing a replicated component from a server entity is not replicated.
+pub trait ReplicatedComponent: Component + Clone + Serialize + DeserializeOwned {
+    /// The stable ID of this component type. No two replicated component types
+    /// may share the same ID.
+    const ID: u16;
+
+    /// Whether or not this component type is written by the owner of an
+    /// entity with [`Authority::Client`], rather than by the server. This
+    /// should only be enabled for cosmetic state that the server does not need
+    /// to validate.
+    const CLIENT_AUTHORITY: bool = false;
+}
+
+impl ReplicatedComponent for Position {
+    const ID: u16 = 1;
+}
+
+
+/// A marker component for a server entity that is replicated to clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Replicated;
+
+
+/// The serialized value of a single replicated component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentData {
+    /// The ID of the replicated component type.
+    pub id: u16,
+
+    /// The serialized component value.
+    pub data: Vec<u8>,
+}
+
+
+/// A network message that is sent from the server to a client to spawn or
+/// despawn the proxy of a replicated entity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplicationMessage {
+    /// A replicated entity has become visible to the client.
+    Spawn {
+        /// The network ID of the entity.
+        entity: u64,
+
+        /// The replication tick that the component values were collected on.
+        tick: u32,
+
+        /// The authority of the client over the entity.
+        authority: Authority,
+
+        /// The value of each replicated component of the entity.
+        components: Vec<ComponentData>,
+    },
+
+    /// The authority of the client over a visible replicated entity has
+    /// changed.
+    Authority {
+        /// The network ID of the entity.
+        entity: u64,
+
+        /// The new authority of the client over the entity.
+        authority: Authority,
+    },
+
+    /// A replicated entity is no longer visible to the client.
+    Despawn {
+        /// The network ID of the entity.
+        entity: u64,
+    },
+}
+
+
+/// A network message that is sent from the server to a client each tick with
+/// the replicated components that have changed on each visible entity.
+///
+/// Updates are sent over the unreliable channel, as each update supersedes
+/// the previous one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityUpdateMessage {
+    /// The replication tick that the component values were collected on.
+    pub tick: u32,
+
+    /// The network ID of each changed entity, alongside the value of each of
+    /// its changed components.
+    pub entities: Vec<(u64, Vec<ComponentData>)>,
+}
+
+
+/// The value of a replicated component that was collected on the current
+/// tick.
+#[derive(Debug, Clone)]
+struct CollectedComponent {
+    /// The serialized component value.
+    data: ComponentData,
+
+    /// Whether or not the component has changed since the previous tick.
+    changed: bool,
+}
+
+
+/// A server-side resource that stores the replicated components that were
+/// collected on the current tick, until they are sent to clients.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ReplicationOutbox {
+    /// The current replication tick.
+    tick: u32,
+
+    /// Whether or not components have been collected since they were last
+    /// sent.
+    ready: bool,
+
+    /// The collected components of each replicated entity.
+    entities: HashMap<Entity, Vec<CollectedComponent>>,
+}
+
+impl ReplicationOutbox {
+    /// Gets the current replication tick.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+
+    /// Gets whether or not components have been collected since they were
+    /// last sent.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+
+    /// Gets the value of every replicated component of the given entity.
+    pub fn full_state(&self, entity: Entity) -> Vec<ComponentData> {
+        self.entities
+            .get(&entity)
+            .map(|components| components.iter().map(|c| c.data.clone()).collect())
+            .unwrap_or_default()
+    }
+
+
+    /// Gets the value of each replicated component of the given entity that
+    /// has changed since the previous tick.
+    pub fn changes(&self, entity: Entity) -> Vec<ComponentData> {
+        self.entities
+            .get(&entity)
+            .map(|components| {
+                components.iter().filter(|c| c.changed).map(|c| c.data.clone()).collect()
+            })
+            .unwrap_or_default()
+    }
+
+
+    /// Clears all collected components and advances to the next replication
+    /// tick.
+    pub fn finish_tick(&mut self) {
+        self.entities.clear();
+        self.ready = false;
+        self.tick = self.tick.wrapping_add(1);
+    }
+
+
+    /// Stores the value of a replicated component of the given entity.
+    fn push(&mut self, entity: Entity, data: ComponentData, changed: bool) {
+        let components = self.entities.entry(entity).or_default();
+        match components.iter_mut().find(|c| c.data.id == data.id) {
+            Some(existing) => {
+                existing.changed |= changed;
+                existing.data = data;
+            },
+            None => {
+                components.push(CollectedComponent {
+                    data,
+                    changed,
+                })
+            },
+        }
+    }
+}
+
+
+/// Gets the network ID of the given server entity.
+pub fn network_id(entity: Entity) -> u64 {
+    entity.to_bits()
+}
+
+
+/// A client-side component for the proxy of a replicated server entity.
+#[derive(Debug, Clone, Component)]
+pub struct RemoteEntity {
+    /// The network ID of the server entity.
+    id: u64,
+
+    /// The replication tick that the proxy was last updated on.
+    last_tick: u32,
+}
+
+impl RemoteEntity {
+    /// Gets the network ID of the server entity.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+
+/// A client-side resource that maps the network ID of each replicated entity
+/// to its local proxy.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct RemoteEntities {
+    /// The local proxy of each network ID.
+    entities: HashMap<u64, Entity>,
+}
+
+impl RemoteEntities {
+    /// Gets the local proxy of the replicated entity with the given network ID.
+    pub fn get(&self, id: u64) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+
+    /// Gets the number of replicated entities that are currently visible.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+
+    /// Gets whether or not no replicated entities are currently visible.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+
+/// A function that deserializes a replicated component and inserts it into an
+/// entity.
+type ComponentInserter = fn(&mut EntityCommands, &[u8]) -> bincode::Result<()>;
+
+
+/// A registered replicated component type.
+#[derive(Debug, Clone)]
+struct RegisteredComponent {
+    /// The type name of the component.
+    name: &'static str,
+
+    /// Deserializes the component and inserts it into an entity.
+    inserter: ComponentInserter,
+
+    /// Whether or not the component is client-authoritative.
+    client_authority: bool,
+}
+
+
+/// A resource that stores each registered replicated component type.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ReplicationRegistry {
+    /// Each registered component type, by replicated component ID.
+    components: HashMap<u16, RegisteredComponent>,
+}
+
+impl ReplicationRegistry {
+    /// Registers the given replicated component type.
+    ///
+    /// Returns false if the component type has already been registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another component type has already been registered with the
+    /// same ID.
+    pub fn register<C: ReplicatedComponent>(&mut self) -> bool {
+        let name = std::any::type_name::<C>();
+        match self.components.get(&C::ID) {
+            Some(existing) if existing.name == name => false,
+            Some(existing) => {
+                panic!(
+                    "Replicated component ID {} of {name} is already used by {}",
+                    C::ID,
+                    existing.name
+                )
+            },
+            None => {
+                self.components.insert(C::ID, RegisteredComponent {
+                    name,
+                    inserter: insert_component::<C>,
+                    client_authority: C::CLIENT_AUTHORITY,
+                });
+                true
+            },
+        }
+    }
+
+
+    /// Gets whether or not the replicated component type with the given ID is
+    /// client-authoritative.
+    pub fn is_client_authoritative(&self, id: u16) -> bool {
+        self.components.get(&id).is_some_and(|c| c.client_authority)
+    }
+
+
+    /// Deserializes each of the given components and inserts them into the
+    /// given entity.
+    pub fn insert_all(&self, entity: &mut EntityCommands, components: &[ComponentData]) {
+        for component in components {
+            let Some(registered) = self.components.get(&component.id) else {
+                warn!("Received unknown replicated component {}", component.id);
+                continue;
+            };
+
+            if let Err(err) = (registered.inserter)(entity, &component.data) {
+                warn!("Received malformed replicated {}: {err}", registered.name);
+            }
+        }
+    }
+}
+
+
+/// Deserializes a replicated component and inserts it into the given entity.
+fn insert_component<C: ReplicatedComponent>(
+    entity: &mut EntityCommands,
+    bytes: &[u8],
+) -> bincode::Result<()> {
+    entity.insert(bincode::deserialize::<C>(bytes)?);
+    Ok(())
+}
+
+
+/// Collects the value of the given replicated component from each replicated
+/// entity on the server.
+pub fn collect_replicated_components<C: ReplicatedComponent>(
+    mut outbox: ResMut<ReplicationOutbox>,
+    query: Query<(Entity, &C, ChangeTrackers<C>), With<Replicated>>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "replication").entered();
+
+    outbox.ready = true;
+
+    for (entity, component, tracker) in query.iter() {
+        let data = ComponentData {
+            id:   C::ID,
+            data: bincode::serialize(component).unwrap(),
+        };
+
+        outbox.push(entity, data, tracker.is_changed());
+    }
+}
+
+
+/// Spawns and despawns the local proxies of replicated entities as the server
+/// reports them.
+pub fn apply_replication_messages(
+    mut replication_ev: EventReader<ServerMessage<ReplicationMessage>>,
+    mut remote: ResMut<RemoteEntities>,
+    clock: Res<SnapshotClock>,
+    registry: Res<ReplicationRegistry>,
+    mut commands: Commands,
+) {
+    for ev in replication_ev.iter() {
+        match &ev.message {
+            ReplicationMessage::Spawn {
+                entity,
+                tick,
+                authority,
+                components,
+            } => {
+                let proxy = RemoteEntity {
+                    id:        *entity,
+                    last_tick: *tick,
+                };
+
+                let mut buffer = SnapshotBuffer::default();
+                if let Some(position) = decode_position(components) {
+                    buffer.push(clock.unwrap_tick(*tick), position);
+                }
+
+                let mut proxy_commands = match remote.get(*entity) {
+                    Some(existing) => commands.entity(existing),
+                    None => {
+                        commands.spawn((
+                            proxy.clone(),
+                            PreviousPosition::default(),
+                            TransformBundle::default(),
+                        ))
+                    },
+                };
+
+                proxy_commands.insert((proxy, *authority, buffer));
+                registry.insert_all(&mut proxy_commands, components);
+                remote.entities.insert(*entity, proxy_commands.id());
+            },
+            ReplicationMessage::Authority {
+                entity,
+                authority,
+            } => {
+                if let Some(proxy) = remote.get(*entity) {
+                    commands.entity(proxy).insert(*authority);
+                }
+            },
+            ReplicationMessage::Despawn {
+                entity,
+            } => {
+                if let Some(proxy) = remote.entities.remove(entity) {
+                    commands.entity(proxy).despawn_recursive();
+                }
+            },
+        }
+    }
+}
+
+
+/// Applies the component updates of replicated entities to their local
+/// proxies. Updates that arrive out of order are discarded, as are updates to
+/// the client-authoritative components of proxies that the local client has
+/// authority over.
+///
+/// The positions of proxies that the server has authority over are pushed to
+/// their snapshot buffers, rather than being applied immediately.
+pub fn apply_entity_updates(
+    mut update_ev: EventReader<ServerMessage<EntityUpdateMessage>>,
+    remote: Res<RemoteEntities>,
+    registry: Res<ReplicationRegistry>,
+    mut clock: ResMut<SnapshotClock>,
+    mut proxies: Query<(&mut RemoteEntity, &Authority, Option<&mut SnapshotBuffer>)>,
+    mut commands: Commands,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "receive", message = "replication").entered();
+
+    for ev in update_ev.iter() {
+        let tick = ev.message.tick;
+        clock.observe(tick);
+
+        for (id, components) in &ev.message.entities {
+            let Some(entity) = remote.get(*id) else {
+                continue;
+            };
+
+            let Ok((mut proxy, authority, buffer)) = proxies.get_mut(entity) else {
+                continue;
+            };
+
+            if tick.wrapping_sub(proxy.last_tick) as i32 <= 0 {
+                continue;
+            }
+
+            proxy.last_tick = tick;
+            match (authority, buffer) {
+                (Authority::Server, Some(mut buffer)) => {
+                    if let Some(position) = decode_position(components) {
+                        buffer.push(clock.unwrap_tick(tick), position);
+                    }
+
+                    let components: Vec<ComponentData> =
+                        components.iter().filter(|c| c.id != Position::ID).cloned().collect();
+                    registry.insert_all(&mut commands.entity(entity), &components);
+                },
+                (Authority::Server, None) => {
+                    registry.insert_all(&mut commands.entity(entity), components)
+                },
+                (Authority::Client, _) => {
+                    let components: Vec<ComponentData> = components
+                        .iter()
+                        .filter(|c| !registry.is_client_authoritative(c.id))
+                        .cloned()
+                        .collect();
+                    registry.insert_all(&mut commands.entity(entity), &components);
+                },
+            }
+        }
+    }
+}
+
+
+/// Despawns the local proxies of all replicated entities.
+pub fn clear_remote_entities(mut remote: ResMut<RemoteEntities>, mut commands: Commands) {
+    for (_, proxy) in remote.entities.drain() {
+        commands.entity(proxy).despawn_recursive();
+    }
+}
+
+
+/// An extension trait for apps that allows for component types to be
+/// replicated.
+pub trait ReplicationExt {
+    /// Registers the given component type to be replicated from server
+    /// entities to their proxies on each client.
+    ///
+    /// The network plugin must be added before any component types are
+    /// registered.
+    fn replicate_component<C: ReplicatedComponent>(&mut self) -> &mut Self;
+}
+
+impl ReplicationExt for App {
+    fn replicate_component<C: ReplicatedComponent>(&mut self) -> &mut Self {
+        let registered = self
+            .world
+            .get_resource_or_insert_with(ReplicationRegistry::default)
+            .register::<C>();
+
+        if registered && self.world.contains_resource::<RenetServer>() {
+            self.add_system_to_stage(
+                "post_tick",
+                collect_replicated_components::<C>.after(apply_velocity),
+            );
+        }
+
+        if registered && C::CLIENT_AUTHORITY && self.world.contains_resource::<RenetClient>() {
+            self.add_system(send_owned_components::<C>.with_run_criteria(run_while_connected));
+        }
+
+        self
+    }
+}
@@ -0,0 +1,200 @@
+//! Contains the player roster, a server-maintained list of all connected
+//! players that is shared with each client for display within the player list.
+
+
+use crate::prelude::{
+    broadcast, send_to_client, BanList, ClientSocket, MessageBatch, PlayerName, ServerMessage, UserData
+};
+use awgen_physics::prelude::GameMode;
+use bevy::prelude::*;
+use bevy_renet::renet::{RenetServer, ServerEvent};
+use serde::{Deserialize, Serialize};
+
+
+/// The number of seconds between each full roster update sent to clients.
+const ROSTER_UPDATE_INTERVAL: f32 = 1.0;
+
+
+/// A single player entry within the player roster.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RosterEntry {
+    /// The client id of the player.
+    pub client_id: u64,
+
+    /// The display name of the player.
+    pub name: String,
+
+    /// The round trip time between the server and the player, measured in
+    /// milliseconds.
+    pub ping: u32,
+
+    /// The current game mode of the player.
+    pub game_mode: GameMode,
+}
+
+
+/// A network message that is sent from the server to clients in order to keep
+/// their player roster up to date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RosterMessage {
+    /// The full list of all players currently on the server.
+    List(Vec<RosterEntry>),
+
+    /// A player has joined the server.
+    Joined(RosterEntry),
+
+    /// The player with the given client id has left the server.
+    Left(u64),
+}
+
+
+/// A list of all players that are currently connected to the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PlayerRoster {
+    /// The list of player entries, in the order they joined.
+    entries: Vec<RosterEntry>,
+}
+
+impl PlayerRoster {
+    /// Gets the list of all player entries, in the order they joined.
+    pub fn entries(&self) -> &[RosterEntry] {
+        &self.entries
+    }
+
+
+    /// Gets the player entry for the given client id, if it exists.
+    pub fn get(&self, client_id: u64) -> Option<&RosterEntry> {
+        self.entries.iter().find(|e| e.client_id == client_id)
+    }
+
+
+    /// Adds a player entry to this roster, replacing any existing entry with
+    /// the same client id.
+    pub fn insert(&mut self, entry: RosterEntry) {
+        match self.entries.iter_mut().find(|e| e.client_id == entry.client_id) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+
+    /// Removes the player entry with the given client id, returning it if it
+    /// existed.
+    pub fn remove(&mut self, client_id: u64) -> Option<RosterEntry> {
+        let index = self.entries.iter().position(|e| e.client_id == client_id)?;
+        Some(self.entries.remove(index))
+    }
+}
+
+
+/// An event that is triggered on the client when a player joins the server.
+#[derive(Debug, Clone)]
+pub struct PlayerJoinedEvent(pub RosterEntry);
+
+
+/// An event that is triggered on the client when a player leaves the server.
+#[derive(Debug, Clone)]
+pub struct PlayerLeftEvent(pub RosterEntry);
+
+
+/// Adds and removes players from the server roster as clients connect and
+/// disconnect, announcing each change to all clients. Banned clients are never
+/// added to the roster.
+pub fn update_roster_connections(
+    bans: Res<BanList>,
+    mut server_events: EventReader<ServerEvent>,
+    mut roster: ResMut<PlayerRoster>,
+    mut batch: ResMut<MessageBatch>,
+    server: Res<RenetServer>,
+) {
+    for event in server_events.iter() {
+        match event {
+            ServerEvent::ClientConnected(client_id, user_data) => {
+                if bans.check(*client_id, server.client_addr(*client_id)).is_some() {
+                    continue;
+                }
+
+                let user_data = UserData::decode(user_data).unwrap_or_default();
+                let entry = RosterEntry {
+                    client_id: *client_id,
+                    name:      PlayerName::of_client(*client_id, &user_data).0,
+                    ping:      0,
+                    game_mode: GameMode::default(),
+                };
+
+                roster.insert(entry.clone());
+                broadcast(&mut batch, &RosterMessage::Joined(entry));
+
+                let list = RosterMessage::List(roster.entries.clone());
+                send_to_client(&mut batch, *client_id, &list);
+            },
+            ServerEvent::ClientDisconnected(client_id) => {
+                if roster.remove(*client_id).is_some() {
+                    broadcast(&mut batch, &RosterMessage::Left(*client_id));
+                }
+            },
+        }
+    }
+}
+
+
+/// Periodically refreshes the ping and game mode of each player within the
+/// server roster and sends the full roster to all clients.
+pub fn broadcast_roster(
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    mut roster: ResMut<PlayerRoster>,
+    mut batch: ResMut<MessageBatch>,
+    server: Res<RenetServer>,
+    players: Query<(&ClientSocket, &GameMode)>,
+) {
+    *timer += time.delta_seconds();
+    if *timer < ROSTER_UPDATE_INTERVAL {
+        return;
+    }
+    *timer = 0.0;
+
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "roster").entered();
+
+    for entry in &mut roster.entries {
+        if let Some(info) = server.network_info(entry.client_id) {
+            entry.ping = info.rtt as u32;
+        }
+    }
+
+    for (socket, game_mode) in players.iter() {
+        if let Some(entry) = roster.entries.iter_mut().find(|e| e.client_id == socket.id()) {
+            entry.game_mode = *game_mode;
+        }
+    }
+
+    broadcast(&mut batch, &RosterMessage::List(roster.entries.clone()));
+}
+
+
+/// Applies the roster messages that were received from the server to the
+/// client roster, triggering join and leave events.
+pub fn apply_roster_messages(
+    mut roster_ev: EventReader<ServerMessage<RosterMessage>>,
+    mut roster: ResMut<PlayerRoster>,
+    mut joined_ev: EventWriter<PlayerJoinedEvent>,
+    mut left_ev: EventWriter<PlayerLeftEvent>,
+) {
+    for ev in roster_ev.iter() {
+        match ev.message.clone() {
+            RosterMessage::List(entries) => roster.entries = entries,
+            RosterMessage::Joined(entry) => {
+                info!("{} joined the game", entry.name);
+                roster.insert(entry.clone());
+                joined_ev.send(PlayerJoinedEvent(entry));
+            },
+            RosterMessage::Left(client_id) => {
+                if let Some(entry) = roster.remove(client_id) {
+                    info!("{} left the game", entry.name);
+                    left_ev.send(PlayerLeftEvent(entry));
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! The scoreboard objectives that are shown to clients, such as the scores of
+//! a mini-game within a sidebar, or alongside each player within the player
+//! list.
+//!
+//! The server decides which objective is shown within each display slot, and
+//! sends the sorted scores of that objective to every client whenever they
+//! change.
+
+
+use crate::prelude::ServerMessage;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+
+/// The places where a scoreboard objective may be displayed on clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DisplaySlot {
+    /// A sidebar along the right side of the screen.
+    Sidebar,
+
+    /// Alongside each player within the player list.
+    List,
+}
+
+impl DisplaySlot {
+    /// Every display slot.
+    pub const ALL: [DisplaySlot; 2] = [DisplaySlot::Sidebar, DisplaySlot::List];
+
+
+    /// Gets the name of this display slot, as used within commands.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplaySlot::Sidebar => "sidebar",
+            DisplaySlot::List => "list",
+        }
+    }
+
+
+    /// Gets the display slot with the given name, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sidebar" => Some(DisplaySlot::Sidebar),
+            "list" => Some(DisplaySlot::List),
+            _ => None,
+        }
+    }
+}
+
+
+/// The order that the scores of an objective are sorted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// The highest score is shown first.
+    #[default]
+    Descending,
+
+    /// The lowest score is shown first, such as for the time taken to finish a
+    /// race.
+    Ascending,
+}
+
+impl SortOrder {
+    /// Gets the name of this sort order, as used within commands.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortOrder::Descending => "descending",
+            SortOrder::Ascending => "ascending",
+        }
+    }
+
+
+    /// Gets the sort order with the given name, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "descending" => Some(SortOrder::Descending),
+            "ascending" => Some(SortOrder::Ascending),
+            _ => None,
+        }
+    }
+}
+
+
+/// The score of a single score holder within an objective.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    /// The name of the score holder, which is usually a player name.
+    pub holder: String,
+
+    /// The score of the holder.
+    pub score: i32,
+}
+
+
+/// An objective that is shown within a display slot, alongside its sorted
+/// scores.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplayedObjective {
+    /// The name of the objective.
+    pub name: String,
+
+    /// The title that the objective is shown with.
+    pub display_name: String,
+
+    /// The score of each score holder, in display order.
+    pub scores: Vec<ScoreEntry>,
+}
+
+impl DisplayedObjective {
+    /// Gets the score of the given score holder, if they have one.
+    pub fn score(&self, holder: &str) -> Option<i32> {
+        self.scores.iter().find(|entry| entry.holder == holder).map(|entry| entry.score)
+    }
+}
+
+
+/// A network message that is sent from the server to clients when the
+/// objective that is shown within a display slot, or its scores, change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreboardMessage {
+    /// The display slot that changed.
+    pub slot: DisplaySlot,
+
+    /// The objective to show within the display slot, or `None` to clear it.
+    pub objective: Option<DisplayedObjective>,
+}
+
+
+/// A client-side resource that stores the objective that is shown within each
+/// display slot, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ClientScoreboard {
+    /// The objective of each display slot that is not empty.
+    slots: HashMap<DisplaySlot, DisplayedObjective>,
+}
+
+impl ClientScoreboard {
+    /// Gets the objective that is shown within the given display slot, if any.
+    pub fn get(&self, slot: DisplaySlot) -> Option<&DisplayedObjective> {
+        self.slots.get(&slot)
+    }
+}
+
+
+/// Applies the scoreboard messages that were received from the server to the
+/// client scoreboard.
+pub fn apply_scoreboard_messages(
+    mut scoreboard_ev: EventReader<ServerMessage<ScoreboardMessage>>,
+    mut scoreboard: ResMut<ClientScoreboard>,
+) {
+    for ev in scoreboard_ev.iter() {
+        let message = &ev.message;
+        match &message.objective {
+            Some(objective) => {
+                scoreboard.slots.insert(message.slot, objective.clone());
+            },
+            None => {
+                scoreboard.slots.remove(&message.slot);
+            },
+        }
+    }
+}
+
+
+/// Clears the client scoreboard once the local player has disconnected from
+/// the server.
+pub fn reset_client_scoreboard(mut scoreboard: ResMut<ClientScoreboard>) {
+    scoreboard.slots.clear();
+}
@@ -1,13 +1,24 @@
 //! Contains systems, components, and handlers in charge of distributing player
-//! connection events.
+//! connection events, and of disconnecting clients from the server.
 
 
+use crate::prelude::{
+    broadcast, send_to_client, BanList, MessageBatch, PendingHandshakes, UserData
+};
+use bevy::app::AppExit;
 use bevy::prelude::*;
-use bevy_renet::renet::ServerEvent;
+use bevy_renet::renet::{RenetServer, ServerEvent};
+use serde::{Deserialize, Serialize};
+
+
+/// The number of seconds that a kicked client is given to receive the reason
+/// it was kicked and disconnect on its own, before the server closes the
+/// connection.
+pub const KICK_GRACE_SECONDS: f64 = 1.0;
 
 
 /// A ID pointer that represents a client connection socket.
-#[derive(Reflect, Component, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Component, Default)]
 #[reflect(Component)]
 pub struct ClientSocket {
     /// The Renet client socket ID.
@@ -30,37 +41,255 @@ impl ClientSocket {
 }
 
 
-/// An event that is triggered when a new client connects to the server.
-pub struct ClientConnectedEvent(Entity);
+/// An event that is triggered when a new client connects to the server and
+/// completes the handshake.
+pub struct ClientConnectedEvent {
+    /// The client socket entity of the client.
+    pub entity: Entity,
+
+    /// The user data that the client connected with.
+    pub user_data: UserData,
+}
 
 
 /// An event that is triggered when a client disconnects from the server.
-pub struct ClientDisconnectedEvent(Entity);
+pub struct ClientDisconnectedEvent(pub Entity);
 
 
 /// An event listener that handles when a new client socket is opened or closed.
 ///
-/// This will create new entities with client sockets as needed or dispose them.
-/// This will also trigger ClientConnected and ClientDisconnected events for the
-/// corresponding entities.
+/// New clients must complete the handshake before they are given a client
+/// socket entity, which is handled by
+/// [`verify_handshakes`](crate::prelude::verify_handshakes). Client socket
+/// entities are disposed of when their client disconnects, triggering a
+/// ClientDisconnected event for the corresponding entity.
+///
+/// Clients that are within the ban list are kicked instead, and never begin
+/// the handshake.
+#[allow(clippy::too_many_arguments)]
 pub fn server_socket_event(
+    time: Res<Time>,
+    bans: Res<BanList>,
+    server: Res<RenetServer>,
     mut events: EventReader<ServerEvent>,
-    mut ev_connected: EventWriter<ClientConnectedEvent>,
+    mut pending: ResMut<PendingHandshakes>,
     mut ev_disconnected: EventWriter<ClientDisconnectedEvent>,
+    mut ev_kick: EventWriter<KickClient>,
     mut commands: Commands,
     client_list: Query<(Entity, &ClientSocket)>,
 ) {
     for event in events.iter() {
         match event {
-            ServerEvent::ClientConnected(id, _) => {
-                let entity = commands.spawn(ClientSocket::new(*id)).id();
-                ev_connected.send(ClientConnectedEvent(entity));
+            ServerEvent::ClientConnected(id, user_data) => {
+                if let Some(reason) = bans.check(*id, server.client_addr(*id)) {
+                    info!("Rejecting banned client {id}: {reason}");
+                    let reason = format!("You are banned from this server: {reason}");
+                    ev_kick.send(KickClient::from_id(*id, reason));
+                    continue;
+                }
+
+                let user_data = UserData::decode(user_data).unwrap_or_else(|err| {
+                    warn!("Client {id} sent malformed user data: {err}");
+                    UserData::default()
+                });
+
+                pending.push(*id, time.elapsed_seconds_f64(), user_data);
             },
             ServerEvent::ClientDisconnected(id) => {
-                let (entity, _) = client_list.iter().find(|(_, c)| c.id == *id).unwrap();
+                let Some((entity, _)) = client_list.iter().find(|(_, c)| c.id == *id) else {
+                    continue;
+                };
                 ev_disconnected.send(ClientDisconnectedEvent(entity));
                 commands.entity(entity).despawn();
             },
         }
     }
 }
+
+
+/// The cause of a client being disconnected from the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DisconnectCause {
+    /// The client was kicked by the server.
+    Kicked,
+
+    /// The connection to the server timed out.
+    Timeout,
+
+    /// The server was shut down.
+    ServerShutdown,
+
+    /// The client and server use incompatible versions of the protocol.
+    ProtocolMismatch,
+
+    /// The connection was closed for any other reason.
+    ConnectionLost,
+}
+
+impl DisconnectCause {
+    /// Gets whether or not the client may reconnect after being disconnected
+    /// for this cause.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            DisconnectCause::Timeout | DisconnectCause::ConnectionLost
+        )
+    }
+}
+
+impl std::fmt::Display for DisconnectCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DisconnectCause::Kicked => "Kicked",
+            DisconnectCause::Timeout => "Timed out",
+            DisconnectCause::ServerShutdown => "Server shut down",
+            DisconnectCause::ProtocolMismatch => "Protocol mismatch",
+            DisconnectCause::ConnectionLost => "Connection lost",
+        })
+    }
+}
+
+
+/// A message that is sent from the server to a client right before the client
+/// is disconnected, reporting why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisconnectMessage {
+    /// The cause of the disconnect.
+    pub cause: DisconnectCause,
+
+    /// A human readable reason for the disconnect.
+    pub reason: String,
+}
+
+
+/// An event that may be sent on the server in order to disconnect a client,
+/// telling the client why it was disconnected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KickClient {
+    /// The id of the client socket to disconnect.
+    pub client_id: u64,
+
+    /// The cause that is reported to the client.
+    pub cause: DisconnectCause,
+
+    /// A human readable reason that is reported to the client.
+    pub reason: String,
+}
+
+impl KickClient {
+    /// Creates a new kick event for the given client socket, with the given
+    /// reason.
+    pub fn new<R>(socket: &ClientSocket, reason: R) -> Self
+    where R: Into<String> {
+        Self::from_id(socket.id(), reason)
+    }
+
+
+    /// Creates a new kick event for the client socket with the given id, with
+    /// the given reason.
+    pub fn from_id<R>(client_id: u64, reason: R) -> Self
+    where R: Into<String> {
+        Self {
+            client_id,
+            cause: DisconnectCause::Kicked,
+            reason: reason.into(),
+        }
+    }
+
+
+    /// Replaces the cause that is reported to the client.
+    pub fn with_cause(mut self, cause: DisconnectCause) -> Self {
+        self.cause = cause;
+        self
+    }
+}
+
+
+/// The clients that have been kicked but not yet disconnected, alongside the
+/// time at which their connection is closed by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PendingKicks {
+    /// The client id and disconnect time of each kicked client.
+    clients: Vec<(u64, f64)>,
+}
+
+impl PendingKicks {
+    /// Gets whether or not the client with the given id has been kicked and is
+    /// waiting to be disconnected.
+    pub fn contains(&self, client_id: u64) -> bool {
+        self.clients.iter().any(|(id, _)| *id == client_id)
+    }
+}
+
+
+/// Sends the reason for each kick to the kicked client, and schedules its
+/// connection to be closed once the kick grace period has passed.
+pub fn kick_clients(
+    time: Res<Time>,
+    mut kick_ev: EventReader<KickClient>,
+    mut batch: ResMut<MessageBatch>,
+    server: Res<RenetServer>,
+    mut pending: ResMut<PendingKicks>,
+) {
+    for ev in kick_ev.iter() {
+        if pending.contains(ev.client_id) || !server.is_connected(ev.client_id) {
+            continue;
+        }
+
+        info!(
+            "Kicking client {}: {} ({})",
+            ev.client_id, ev.cause, ev.reason
+        );
+        send_to_client(&mut batch, ev.client_id, &DisconnectMessage {
+            cause:  ev.cause,
+            reason: ev.reason.clone(),
+        });
+
+        let deadline = time.elapsed_seconds_f64() + KICK_GRACE_SECONDS;
+        pending.clients.push((ev.client_id, deadline));
+    }
+}
+
+
+/// Closes the connection of each kicked client that has not disconnected on
+/// its own within the kick grace period.
+pub fn disconnect_kicked_clients(
+    time: Res<Time>,
+    mut server: ResMut<RenetServer>,
+    mut pending: ResMut<PendingKicks>,
+) {
+    let now = time.elapsed_seconds_f64();
+    pending.clients.retain(|(client_id, deadline)| {
+        if !server.is_connected(*client_id) {
+            return false;
+        }
+
+        if now < *deadline {
+            return true;
+        }
+
+        server.disconnect(*client_id);
+        false
+    });
+}
+
+
+/// Tells all connected clients that the server is shutting down when the app
+/// is about to exit.
+///
+/// The announcement is only delivered if the exit event is sent before this
+/// system runs, so that the message is sent out before the app exits at the
+/// end of the frame.
+pub fn announce_server_shutdown(
+    mut exit_ev: EventReader<AppExit>,
+    mut batch: ResMut<MessageBatch>,
+) {
+    if exit_ev.iter().last().is_none() {
+        return;
+    }
+
+    broadcast(&mut batch, &DisconnectMessage {
+        cause:  DisconnectCause::ServerShutdown,
+        reason: "The server is shutting down".to_string(),
+    });
+}
@@ -0,0 +1,259 @@
+//! A network condition simulator, which adds artificial latency, jitter,
+//! packet loss, and duplication to the messages that a client sends and
+//! receives, so that prediction and interpolation may be tested on a local
+//! connection.
+//!
+//! The simulator is only compiled with the `network_simulator` feature, and is
+//! meant for debugging only. It is configured at runtime through the
+//! [`NetworkSimulator`] resource of the client, and has no effect until its
+//! conditions are changed.
+//!
+//! Conditions are simulated per message, rather than per packet. Messages on
+//! unreliable channels may be dropped, duplicated, or reordered. Messages on
+//! reliable channels are never dropped; a lost reliable message is delayed by
+//! an extra round trip instead, as it would be by a resend. Messages on
+//! ordered channels keep their order.
+
+
+use crate::prelude::{ChannelDelivery, MessageChannel, NetworkChannels};
+use awgen_math::prelude::{Seed, SeededRng};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The delay, in milliseconds, that is added on top of a round trip each time
+/// that a reliable message is lost, before it is resent.
+const RESEND_INTERVAL: f32 = 100.0;
+
+
+/// The largest delay, in milliseconds, after which a lost reliable message is
+/// no longer lost again, so that a packet loss of 1 does not stall a channel
+/// forever.
+const MAX_RESEND_DELAY: f32 = 5000.0;
+
+
+/// The artificial network conditions that are applied to each message, in
+/// each direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetworkConditions {
+    /// The delay, in milliseconds, that is added to each message. As it is
+    /// added in both directions, the round trip time increases by twice this
+    /// amount.
+    pub latency: f32,
+
+    /// The largest amount, in milliseconds, that the delay of a message may
+    /// randomly differ from the latency by.
+    pub jitter: f32,
+
+    /// The chance, between 0 and 1, that a message is lost.
+    pub packet_loss: f32,
+
+    /// The chance, between 0 and 1, that a message on an unreliable channel
+    /// arrives twice.
+    pub duplication: f32,
+}
+
+impl NetworkConditions {
+    /// Gets whether or not these conditions leave messages untouched.
+    pub fn is_ideal(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+
+/// A message that is held back by the simulator until it is delivered.
+#[derive(Debug, Clone)]
+struct DelayedMessage {
+    /// The time that the message is delivered at.
+    deliver_at: f64,
+
+    /// The channel that the message is sent over.
+    channel: MessageChannel,
+
+    /// The serialized message.
+    bytes: Vec<u8>,
+}
+
+
+/// The messages that are held back in a single direction.
+#[derive(Debug, Clone, Default)]
+struct DelayQueue {
+    /// Each held back message, ordered by delivery time.
+    messages: Vec<DelayedMessage>,
+}
+
+impl DelayQueue {
+    /// Holds back the given message until the given time.
+    ///
+    /// If the channel is ordered, the message is never delivered before the
+    /// messages that were held back before it on the same channel.
+    fn push(&mut self, mut message: DelayedMessage, ordered: bool) {
+        if ordered {
+            let last = self.messages.iter().rev().find(|m| m.channel == message.channel);
+            if let Some(last) = last {
+                message.deliver_at = message.deliver_at.max(last.deliver_at);
+            }
+        }
+
+        let index = self.messages.partition_point(|m| m.deliver_at <= message.deliver_at);
+        self.messages.insert(index, message);
+    }
+
+
+    /// Removes and returns each message that is delivered by the given time,
+    /// in delivery order.
+    fn take(&mut self, now: f64) -> Vec<DelayedMessage> {
+        let due = self.messages.partition_point(|m| m.deliver_at <= now);
+        self.messages.drain(..due).collect()
+    }
+}
+
+
+/// A client-side resource that applies artificial network conditions to the
+/// messages that are sent to and received from the server.
+#[derive(Debug, Clone, Resource)]
+pub struct NetworkSimulator {
+    /// The conditions that are applied to each message. These may be changed
+    /// at any time, and apply to messages that are sent or received from then
+    /// on.
+    pub conditions: NetworkConditions,
+
+    /// The delivery of each channel, by channel ID.
+    deliveries: HashMap<u8, ChannelDelivery>,
+
+    /// The random number generator that decides the fate of each message.
+    rng: SeededRng,
+
+    /// The messages that were received from the server, but not yet delivered.
+    inbound: DelayQueue,
+
+    /// The messages that were sent to the server, but not yet delivered.
+    outbound: DelayQueue,
+}
+
+impl NetworkSimulator {
+    /// Creates a new network simulator for the given channels, which leaves
+    /// messages untouched until its conditions are changed.
+    pub fn new(channels: &NetworkChannels) -> Self {
+        let deliveries = channels
+            .iter()
+            .filter_map(|channel| channels.get(channel).map(|delivery| (channel.id, delivery)))
+            .collect();
+
+        Self {
+            conditions: NetworkConditions::default(),
+            deliveries,
+            rng: Seed::from_text("network_simulator").rng(),
+            inbound: DelayQueue::default(),
+            outbound: DelayQueue::default(),
+        }
+    }
+
+
+    /// Gets whether or not any messages that were sent to the server are
+    /// currently held back.
+    pub fn has_outbound(&self) -> bool {
+        !self.outbound.messages.is_empty()
+    }
+
+
+    /// Applies the network conditions to the given messages that were just
+    /// received from the server at the given time, and returns each received
+    /// message that is delivered by now, in delivery order.
+    pub fn simulate_inbound(
+        &mut self,
+        now: f64,
+        received: Vec<(MessageChannel, Vec<u8>)>,
+    ) -> Vec<(MessageChannel, Vec<u8>)> {
+        let mut queue = std::mem::take(&mut self.inbound);
+        for (channel, bytes) in received {
+            self.hold_back(&mut queue, now, channel, bytes);
+        }
+
+        let delivered = queue.take(now);
+        self.inbound = queue;
+        delivered.into_iter().map(|m| (m.channel, m.bytes)).collect()
+    }
+
+
+    /// Applies the network conditions to the given messages that are being
+    /// sent to the server at the given time, and returns each sent message
+    /// that is delivered by now, in delivery order.
+    pub fn simulate_outbound(
+        &mut self,
+        now: f64,
+        sent: Vec<(MessageChannel, Vec<u8>)>,
+    ) -> Vec<(MessageChannel, Vec<u8>)> {
+        let mut queue = std::mem::take(&mut self.outbound);
+        for (channel, bytes) in sent {
+            self.hold_back(&mut queue, now, channel, bytes);
+        }
+
+        let delivered = queue.take(now);
+        self.outbound = queue;
+        delivered.into_iter().map(|m| (m.channel, m.bytes)).collect()
+    }
+
+
+    /// Discards every message that is currently held back.
+    pub fn clear(&mut self) {
+        self.inbound.messages.clear();
+        self.outbound.messages.clear();
+    }
+
+
+    /// Holds back a copy of the given message within the given queue for each
+    /// time that it arrives under the current conditions.
+    fn hold_back(
+        &mut self,
+        queue: &mut DelayQueue,
+        now: f64,
+        channel: MessageChannel,
+        bytes: Vec<u8>,
+    ) {
+        let conditions = self.conditions;
+        let delivery = self.deliveries.get(&channel.id).copied();
+        let reliable = delivery != Some(ChannelDelivery::Unreliable);
+        let ordered = matches!(
+            delivery,
+            Some(ChannelDelivery::ReliableOrdered | ChannelDelivery::Chunked)
+        );
+
+        let copies = match reliable {
+            true => 1,
+            false if self.rng.chance(conditions.packet_loss) => 0,
+            false if self.rng.chance(conditions.duplication) => 2,
+            false => 1,
+        };
+
+        for _ in 0..copies {
+            let jitter = match conditions.jitter > 0.0 {
+                true => self.rng.range_f32(-conditions.jitter, conditions.jitter),
+                false => 0.0,
+            };
+
+            let mut delay = (conditions.latency + jitter).max(0.0);
+            if reliable {
+                while delay < MAX_RESEND_DELAY && self.rng.chance(conditions.packet_loss) {
+                    delay += conditions.latency * 2.0 + RESEND_INTERVAL;
+                }
+            }
+
+            queue.push(
+                DelayedMessage {
+                    deliver_at: now + delay as f64 / 1000.0,
+                    channel,
+                    bytes: bytes.clone(),
+                },
+                ordered,
+            );
+        }
+    }
+}
+
+
+/// Discards every message that the network simulator is holding back once the
+/// local player has disconnected from the server.
+pub fn reset_network_simulator(mut simulator: ResMut<NetworkSimulator>) {
+    simulator.clear();
+}
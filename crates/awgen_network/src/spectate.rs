@@ -0,0 +1,79 @@
+//! Allows permitted players to spectate other players, attaching their camera
+//! to the replicated entity of the spectated player.
+
+
+use crate::prelude::{send_to_server, MessageBatch, ServerMessage};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// A network message that is sent from a client to the server to change which
+/// player is being spectated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpectateRequest {
+    /// Spectates the next player, or the first player if not spectating.
+    Next,
+
+    /// Spectates the previous player, or the last player if not spectating.
+    Previous,
+
+    /// Stops spectating.
+    Stop,
+}
+
+
+/// A network message that is sent from the server to a client when the player
+/// that it is spectating changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectateStatus {
+    /// The network ID of the replicated entity of the spectated player, or
+    /// None if the player has stopped spectating.
+    pub target: Option<u64>,
+}
+
+
+/// A client-side resource that stores the player that the local player is
+/// currently spectating, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct SpectateView {
+    /// The network ID of the spectated entity, if any.
+    target: Option<u64>,
+}
+
+impl SpectateView {
+    /// Gets the network ID of the replicated entity that the local player is
+    /// spectating, if any.
+    pub fn target(&self) -> Option<u64> {
+        self.target
+    }
+
+
+    /// Gets whether or not the local player is spectating another player.
+    pub fn is_spectating(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+
+/// Sends a spectate request to the server.
+pub fn send_spectate_request(batch: &mut MessageBatch, request: SpectateRequest) {
+    send_to_server(batch, &request);
+}
+
+
+/// Applies the spectate status messages that were received from the server to
+/// the spectate view of the local player.
+pub fn apply_spectate_messages(
+    mut status_ev: EventReader<ServerMessage<SpectateStatus>>,
+    mut view: ResMut<SpectateView>,
+) {
+    for ev in status_ev.iter() {
+        view.target = ev.message.target;
+    }
+}
+
+
+/// Stops spectating once the local player has disconnected from the server.
+pub fn reset_spectate_view(mut view: ResMut<SpectateView>) {
+    view.target = None;
+}
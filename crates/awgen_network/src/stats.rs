@@ -0,0 +1,127 @@
+//! Exposes the quality of each network connection, such as its round trip time
+//! and packet loss, so that gameplay and interface code may react to it.
+
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_renet::renet::{NetworkInfo, RenetClient, RenetServer};
+
+
+/// The number of bytes within a kilobit.
+const BYTES_PER_KILOBIT: f32 = 125.0;
+
+
+/// The statistics of a single network connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConnectionStats {
+    /// The round trip time of the connection, in milliseconds.
+    pub rtt: f32,
+
+    /// The fraction of packets that have been lost recently, between 0 and 1.
+    pub packet_loss: f32,
+
+    /// The rate that data is being sent at, in kilobits per second.
+    pub sent_kbps: f32,
+
+    /// The rate that data is being received at, in kilobits per second.
+    pub received_kbps: f32,
+
+    /// The estimated number of bytes that have been sent over the connection
+    /// since it was opened.
+    pub bytes_sent: u64,
+
+    /// The estimated number of bytes that have been received over the
+    /// connection since it was opened.
+    pub bytes_received: u64,
+}
+
+impl ConnectionStats {
+    /// Updates these statistics from the latest network info of the
+    /// connection, accumulating the data transferred over the given number of
+    /// seconds.
+    ///
+    /// Renet only reports transfer rates, so the byte totals are integrated
+    /// from the rates each frame and are an estimate.
+    fn update(&mut self, info: &NetworkInfo, delta: f32) {
+        self.rtt = info.rtt;
+        self.packet_loss = info.packet_loss;
+        self.sent_kbps = info.sent_kbps;
+        self.received_kbps = info.received_kbps;
+        self.bytes_sent += (info.sent_kbps * BYTES_PER_KILOBIT * delta) as u64;
+        self.bytes_received += (info.received_kbps * BYTES_PER_KILOBIT * delta) as u64;
+    }
+}
+
+
+/// A resource that stores the statistics of the network connections of this
+/// app, updated every frame.
+///
+/// On a client, this contains the connection to the server. On a server, this
+/// contains the connection to each connected client.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct NetworkStats {
+    /// The statistics of the connection to the server, on a client.
+    server: ConnectionStats,
+
+    /// The statistics of the connection to each client, on a server.
+    clients: HashMap<u64, ConnectionStats>,
+}
+
+impl NetworkStats {
+    /// Gets the statistics of the connection to the server. On a server, this
+    /// is always empty.
+    pub fn server(&self) -> &ConnectionStats {
+        &self.server
+    }
+
+
+    /// Gets the statistics of the connection to the client with the given id,
+    /// or `None` if the client is not connected. On a client, this is always
+    /// `None`.
+    pub fn client(&self, client_id: u64) -> Option<&ConnectionStats> {
+        self.clients.get(&client_id)
+    }
+
+
+    /// Gets an iterator over the statistics of the connection to each client,
+    /// alongside its client id.
+    pub fn clients(&self) -> impl Iterator<Item = (u64, &ConnectionStats)> {
+        self.clients.iter().map(|(id, stats)| (*id, stats))
+    }
+}
+
+
+/// Updates the statistics of the connection to the server.
+pub fn update_client_network_stats(
+    time: Res<Time>,
+    client: Res<RenetClient>,
+    mut stats: ResMut<NetworkStats>,
+) {
+    stats.server.update(&client.network_info(), time.delta_seconds());
+}
+
+
+/// Updates the statistics of the connection to each client, and removes the
+/// statistics of clients that have disconnected.
+pub fn update_server_network_stats(
+    time: Res<Time>,
+    server: Res<RenetServer>,
+    mut stats: ResMut<NetworkStats>,
+) {
+    let delta = time.delta_seconds();
+    let connected = server.clients_id();
+    stats.clients.retain(|id, _| connected.contains(id));
+
+    for client_id in connected {
+        if let Some(info) = server.network_info(client_id) {
+            stats.clients.entry(client_id).or_default().update(&info, delta);
+        }
+    }
+}
+
+
+/// Clears the statistics of the connection to the server once the local
+/// player has disconnected.
+pub fn reset_network_stats(mut stats: ResMut<NetworkStats>) {
+    *stats = NetworkStats::default();
+}
@@ -0,0 +1,255 @@
+//! A lightweight status query, which allows launchers and server lists to
+//! check whether a server is online, and how full it is, without joining it.
+//!
+//! The query is a UDP sidecar that runs next to the game server on its own
+//! port, and is answered on a background thread without waiting for the game.
+//! A request is a single datagram of exactly [`STATUS_REQUEST_BYTES`] bytes,
+//! starting with [`STATUS_REQUEST_MAGIC`] and a `u64` nonce, and padded with
+//! zeros. The response is a single datagram containing the bincode encoded
+//! [`StatusResponse`], which echoes the nonce of the request.
+//!
+//! Requests are never smaller than their responses, so that the status query
+//! cannot be used to amplify traffic towards a spoofed address.
+
+
+use crate::prelude::{PlayerRoster, AWGEN_VERSION, PROTOCOL_ID};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+
+/// The bytes that every status request starts with.
+pub const STATUS_REQUEST_MAGIC: &[u8; 8] = b"AWGNPING";
+
+
+/// The size, in bytes, of every status request.
+pub const STATUS_REQUEST_BYTES: usize = 512;
+
+
+/// The largest message of the day, in bytes, that is sent within a status
+/// response. Longer messages are truncated.
+pub const STATUS_MAX_MOTD_BYTES: usize = 256;
+
+
+/// The settings of the status query of a server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusSettings {
+    /// The port to answer status requests on.
+    pub port: u16,
+
+    /// The message of the day that is shown within server lists.
+    pub motd: String,
+}
+
+
+/// The public status of a server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerStatus {
+    /// The message of the day of the server.
+    pub motd: String,
+
+    /// The number of players that are currently on the server.
+    pub players: usize,
+
+    /// The maximum number of players that may be on the server at once.
+    pub max_players: usize,
+
+    /// The version of Awgen that the server is running.
+    pub version: String,
+
+    /// The fingerprint of the network protocol of the server. Clients may only
+    /// join servers with the same protocol.
+    pub protocol: u64,
+}
+
+impl ServerStatus {
+    /// Gets whether or not a client of this version of Awgen is able to join a
+    /// server with this status.
+    pub fn is_compatible(&self) -> bool {
+        self.protocol == PROTOCOL_ID
+    }
+}
+
+
+/// The response to a status request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    /// The nonce of the request that is being answered.
+    pub nonce: u64,
+
+    /// The status of the server.
+    pub status: ServerStatus,
+}
+
+
+/// A server-side resource that answers status requests on a background
+/// thread.
+#[derive(Debug, Resource)]
+pub struct StatusServer {
+    /// The status that is sent within each response.
+    status: Arc<RwLock<ServerStatus>>,
+
+    /// The address that status requests are answered on.
+    address: SocketAddr,
+}
+
+impl StatusServer {
+    /// Starts answering status requests on the port of the given settings, for
+    /// a server with the given maximum number of players.
+    ///
+    /// Returns an error if the port could not be opened.
+    pub fn start(settings: &StatusSettings, max_players: usize) -> Result<Self> {
+        let socket = UdpSocket::bind(("127.0.0.1", settings.port))?;
+        let address = socket.local_addr()?;
+
+        let status = Arc::new(RwLock::new(ServerStatus {
+            motd: truncate_motd(&settings.motd).to_string(),
+            players: 0,
+            max_players,
+            version: AWGEN_VERSION.to_string(),
+            protocol: PROTOCOL_ID,
+        }));
+
+        let shared = status.clone();
+        std::thread::Builder::new()
+            .name("Status".to_string())
+            .spawn(move || answer_requests(socket, shared))?;
+
+        Ok(Self {
+            status,
+            address,
+        })
+    }
+
+
+    /// Gets the address that status requests are answered on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+
+    /// Gets the status that is currently sent within each response.
+    pub fn status(&self) -> ServerStatus {
+        self.status.read().unwrap().clone()
+    }
+
+
+    /// Sets the number of players that are reported to be on the server.
+    pub fn set_players(&self, players: usize) {
+        self.status.write().unwrap().players = players;
+    }
+}
+
+
+/// Truncates the given message of the day to at most
+/// [`STATUS_MAX_MOTD_BYTES`] bytes, without splitting a character.
+fn truncate_motd(motd: &str) -> &str {
+    let mut end = motd.len().min(STATUS_MAX_MOTD_BYTES);
+    while !motd.is_char_boundary(end) {
+        end -= 1;
+    }
+    &motd[..end]
+}
+
+
+/// Answers status requests for as long as the server runs.
+fn answer_requests(socket: UdpSocket, status: Arc<RwLock<ServerStatus>>) {
+    let mut buffer = [0; STATUS_REQUEST_BYTES + 1];
+
+    loop {
+        let (length, peer) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) => {
+                warn!("Failed to receive status request: {err}");
+                continue;
+            },
+        };
+
+        let Some(nonce) = parse_request(&buffer[..length]) else {
+            continue;
+        };
+
+        let response = StatusResponse {
+            nonce,
+            status: status.read().unwrap().clone(),
+        };
+
+        let bytes = bincode::serialize(&response).unwrap();
+        if bytes.len() > STATUS_REQUEST_BYTES {
+            warn!("Status response of {} bytes is too large", bytes.len());
+            continue;
+        }
+
+        if let Err(err) = socket.send_to(&bytes, peer) {
+            debug!("Failed to answer status request from {peer}: {err}");
+        }
+    }
+}
+
+
+/// Builds a status request with the given nonce.
+fn build_request(nonce: u64) -> [u8; STATUS_REQUEST_BYTES] {
+    let mut request = [0; STATUS_REQUEST_BYTES];
+    request[..8].copy_from_slice(STATUS_REQUEST_MAGIC);
+    request[8..16].copy_from_slice(&nonce.to_le_bytes());
+    request
+}
+
+
+/// Gets the nonce of the given status request, or `None` if it is not a valid
+/// status request.
+fn parse_request(request: &[u8]) -> Option<u64> {
+    if request.len() != STATUS_REQUEST_BYTES || !request.starts_with(STATUS_REQUEST_MAGIC) {
+        return None;
+    }
+
+    Some(u64::from_le_bytes(request[8..16].try_into().ok()?))
+}
+
+
+/// Queries the status of the server that answers status requests at the given
+/// address, waiting up to the given timeout for a response.
+///
+/// Returns the status of the server, alongside the round trip time of the
+/// query.
+pub fn query_status<A>(address: A, timeout: Duration) -> Result<(ServerStatus, Duration)>
+where A: ToSocketAddrs {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(address)?;
+    socket.set_read_timeout(Some(timeout))?;
+
+    let started = SystemTime::now();
+    let nonce = started.duration_since(SystemTime::UNIX_EPOCH)?.as_nanos() as u64;
+    socket.send(&build_request(nonce))?;
+
+    let mut buffer = [0; STATUS_REQUEST_BYTES];
+    loop {
+        let length = socket.recv(&mut buffer)?;
+        let Ok(response) = bincode::deserialize::<StatusResponse>(&buffer[..length]) else {
+            continue;
+        };
+
+        if response.nonce != nonce {
+            continue;
+        }
+
+        let elapsed = started.elapsed()?;
+        if elapsed > timeout {
+            bail!("The server did not respond in time");
+        }
+
+        return Ok((response.status, elapsed));
+    }
+}
+
+
+/// Updates the number of players that are reported within status responses
+/// whenever the player roster changes.
+pub fn update_server_status(status: Res<StatusServer>, roster: Res<PlayerRoster>) {
+    if roster.is_changed() {
+        status.set_players(roster.entries().len());
+    }
+}
@@ -0,0 +1,180 @@
+//! The teams that players are grouped into, as shown to clients.
+//!
+//! The server owns the teams and their rules, and sends the color and members
+//! of every team to each client whenever they change, so that clients may
+//! color the names of players by their team. Chat messages that start with
+//! [`TEAM_CHAT_PREFIX`] are only sent to the team of the sender.
+
+
+use crate::prelude::ServerMessage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The prefix of a chat message that is only sent to the members of the team
+/// of the sender, rather than to every player.
+pub const TEAM_CHAT_PREFIX: char = '#';
+
+
+/// The colors that a team may be shown with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TeamColor {
+    /// The color of players that are not highlighted.
+    #[default]
+    White,
+
+    /// A red team color.
+    Red,
+
+    /// An orange team color.
+    Orange,
+
+    /// A yellow team color.
+    Yellow,
+
+    /// A green team color.
+    Green,
+
+    /// An aqua team color.
+    Aqua,
+
+    /// A blue team color.
+    Blue,
+
+    /// A purple team color.
+    Purple,
+
+    /// A gray team color.
+    Gray,
+}
+
+impl TeamColor {
+    /// Every team color.
+    pub const ALL: [TeamColor; 9] = [
+        TeamColor::White,
+        TeamColor::Red,
+        TeamColor::Orange,
+        TeamColor::Yellow,
+        TeamColor::Green,
+        TeamColor::Aqua,
+        TeamColor::Blue,
+        TeamColor::Purple,
+        TeamColor::Gray,
+    ];
+
+
+    /// Gets the name of this team color, as used within commands.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TeamColor::White => "white",
+            TeamColor::Red => "red",
+            TeamColor::Orange => "orange",
+            TeamColor::Yellow => "yellow",
+            TeamColor::Green => "green",
+            TeamColor::Aqua => "aqua",
+            TeamColor::Blue => "blue",
+            TeamColor::Purple => "purple",
+            TeamColor::Gray => "gray",
+        }
+    }
+
+
+    /// Gets the team color with the given name, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        TeamColor::ALL.into_iter().find(|color| color.name() == name)
+    }
+
+
+    /// Gets the red, green, and blue components of this team color.
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            TeamColor::White => [255, 255, 255],
+            TeamColor::Red => [255, 85, 85],
+            TeamColor::Orange => [255, 170, 0],
+            TeamColor::Yellow => [255, 255, 85],
+            TeamColor::Green => [85, 255, 85],
+            TeamColor::Aqua => [85, 255, 255],
+            TeamColor::Blue => [85, 85, 255],
+            TeamColor::Purple => [170, 0, 170],
+            TeamColor::Gray => [170, 170, 170],
+        }
+    }
+}
+
+
+/// A team, as it is shown to clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamInfo {
+    /// The name of the team.
+    pub name: String,
+
+    /// The title that the team is shown with.
+    pub display_name: String,
+
+    /// The color that the names of the members of the team are shown with.
+    pub color: TeamColor,
+
+    /// The names of the members of the team, sorted alphabetically.
+    pub members: Vec<String>,
+}
+
+
+/// A network message that is sent from the server to clients containing every
+/// team, whenever any of them change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamsMessage {
+    /// Every team on the server, sorted by name.
+    pub teams: Vec<TeamInfo>,
+}
+
+
+/// A client-side resource that stores every team, as last reported by the
+/// server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ClientTeams {
+    /// Every team on the server, sorted by name.
+    teams: Vec<TeamInfo>,
+}
+
+impl ClientTeams {
+    /// Gets every team on the server, sorted by name.
+    pub fn teams(&self) -> &[TeamInfo] {
+        &self.teams
+    }
+
+
+    /// Gets the team that the player with the given name is a member of, if
+    /// any.
+    pub fn team_of(&self, player: &str) -> Option<&TeamInfo> {
+        self.teams
+            .iter()
+            .find(|team| team.members.iter().any(|member| member == player))
+    }
+
+
+    /// Gets the color that the name of the player with the given name is shown
+    /// with, which is the color of their team, or white if they are not on a
+    /// team.
+    pub fn color_of(&self, player: &str) -> TeamColor {
+        self.team_of(player).map(|team| team.color).unwrap_or_default()
+    }
+}
+
+
+/// Applies the team messages that were received from the server to the client
+/// teams.
+pub fn apply_teams_messages(
+    mut teams_ev: EventReader<ServerMessage<TeamsMessage>>,
+    mut teams: ResMut<ClientTeams>,
+) {
+    for ev in teams_ev.iter() {
+        teams.teams = ev.message.teams.clone();
+    }
+}
+
+
+/// Clears the client teams once the local player has disconnected from the
+/// server.
+pub fn reset_client_teams(mut teams: ResMut<ClientTeams>) {
+    teams.teams.clear();
+}
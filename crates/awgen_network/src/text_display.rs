@@ -0,0 +1,46 @@
+//! Text that floats in the world, such as signs, leaderboards, and tutorial
+//! hints within mini-games.
+//!
+//! A text display is a server entity with a [`TextDisplay`] component and a
+//! position, which is replicated to clients like any other entity. Clients
+//! draw the text at the position of the entity, always facing the camera.
+
+
+use crate::prelude::ReplicatedComponent;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// Text that is drawn in the world, centered on the position of its entity,
+/// and always facing the camera.
+#[derive(Debug, Clone, PartialEq, Reflect, Component, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct TextDisplay {
+    /// The text that is shown. Each line is separated by a newline.
+    pub text: String,
+
+    /// The red, green, and blue components of the color of the text.
+    pub color: [u8; 3],
+
+    /// The size of the text, where 1.0 is roughly a quarter of a block tall
+    /// per line.
+    pub scale: f32,
+
+    /// Whether or not the text is visible through blocks.
+    pub see_through: bool,
+}
+
+impl Default for TextDisplay {
+    fn default() -> Self {
+        Self {
+            text:        String::new(),
+            color:       [255, 255, 255],
+            scale:       1.0,
+            see_through: false,
+        }
+    }
+}
+
+impl ReplicatedComponent for TextDisplay {
+    const ID: u16 = 4;
+}
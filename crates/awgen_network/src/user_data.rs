@@ -0,0 +1,125 @@
+//! The user data that a client sends along with its connection request, such
+//! as the username that it wishes to play as.
+//!
+//! User data is carried within the connection request of the transport, or
+//! within the connect token when joining a server that requires
+//! authentication, so it is known to the server before the handshake begins.
+//! It is limited to [`NETCODE_USER_DATA_BYTES`] once encoded.
+//!
+//! New fields are only ever appended to the user data, so that older servers
+//! ignore them, and newer servers decode them as zeros from older clients.
+
+
+use crate::prelude::PUBLIC_KEY_BYTES;
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use bevy_renet::renet::NETCODE_USER_DATA_BYTES;
+use serde::{Deserialize, Serialize};
+
+
+/// The maximum number of characters within a username.
+pub const MAX_USERNAME_LENGTH: usize = 32;
+
+
+/// The data that a client supplies when connecting to a server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserData {
+    /// The username that the client wishes to play as, or an empty string if
+    /// the client did not choose one.
+    pub username: String,
+
+    /// An opaque authentication blob, which is reserved for verifying the
+    /// identity of the client in the future.
+    pub auth: Vec<u8>,
+
+    /// The public key that the client encrypts its connection with, if the
+    /// server requires encrypted connections.
+    pub public_key: Option<[u8; PUBLIC_KEY_BYTES]>,
+}
+
+impl UserData {
+    /// Creates new user data with the given username.
+    pub fn new<S>(username: S) -> Self
+    where S: Into<String> {
+        Self {
+            username:   username.into(),
+            auth:       vec![],
+            public_key: None,
+        }
+    }
+
+
+    /// Attaches the given authentication blob to this user data.
+    pub fn with_auth(mut self, auth: Vec<u8>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+
+    /// Attaches the given public key to this user data, which the connection
+    /// is encrypted with if the server requires it.
+    pub fn with_public_key(mut self, public_key: [u8; PUBLIC_KEY_BYTES]) -> Self {
+        self.public_key = Some(public_key);
+        self
+    }
+
+
+    /// Encodes this user data into the fixed size buffer of the transport.
+    ///
+    /// Returns an error if the encoded user data does not fit.
+    pub fn encode(&self) -> Result<[u8; NETCODE_USER_DATA_BYTES]> {
+        let bytes = bincode::serialize(self)?;
+        if bytes.len() > NETCODE_USER_DATA_BYTES {
+            bail!(
+                "User data is {} bytes, but only {NETCODE_USER_DATA_BYTES} bytes are allowed",
+                bytes.len()
+            );
+        }
+
+        let mut buffer = [0; NETCODE_USER_DATA_BYTES];
+        buffer[..bytes.len()].copy_from_slice(&bytes);
+        Ok(buffer)
+    }
+
+
+    /// Decodes user data from the fixed size buffer of the transport. A buffer
+    /// of zeros, as sent by clients without user data, decodes to the default
+    /// user data.
+    pub fn decode(buffer: &[u8; NETCODE_USER_DATA_BYTES]) -> Result<Self> {
+        Ok(bincode::deserialize(buffer)?)
+    }
+
+
+    /// Gets the username of this user data, if it is a valid username.
+    ///
+    /// Valid usernames are not empty, are at most [`MAX_USERNAME_LENGTH`]
+    /// characters long, have no surrounding whitespace, and contain no control
+    /// characters.
+    pub fn valid_username(&self) -> Option<&str> {
+        let name = self.username.as_str();
+        let valid = !name.is_empty()
+            && name.chars().count() <= MAX_USERNAME_LENGTH
+            && name.trim() == name
+            && !name.chars().any(char::is_control);
+
+        valid.then_some(name)
+    }
+}
+
+
+/// A server-side component for a client socket entity that stores the display
+/// name of the player.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Reflect, Component)]
+#[reflect(Component)]
+pub struct PlayerName(pub String);
+
+impl PlayerName {
+    /// Gets the display name of the client with the given client id and user
+    /// data. Clients without a valid username are named after their client id.
+    pub fn of_client(client_id: u64, user_data: &UserData) -> Self {
+        match user_data.valid_username() {
+            Some(name) => Self(name.to_string()),
+            None => Self(format!("Player {client_id}")),
+        }
+    }
+}
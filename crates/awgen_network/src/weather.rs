@@ -0,0 +1,182 @@
+//! The weather of each world, which is decided by the server and synced to the
+//! clients of the players within that world.
+//!
+//! Clients blend between the previous and current weather over a short
+//! transition, so that rendering effects, such as rain and sky darkness, fade
+//! in and out rather than changing instantly.
+
+
+use crate::prelude::ServerMessage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The weather states that a world may be in.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect, FromReflect, Serialize, Deserialize,
+)]
+pub enum Weather {
+    /// No precipitation.
+    #[default]
+    Clear,
+
+    /// Steady rainfall.
+    Rain,
+
+    /// Heavy rainfall under a dark sky.
+    Storm,
+}
+
+impl Weather {
+    /// Gets the name of this weather state, as used within commands.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+            Weather::Storm => "storm",
+        }
+    }
+
+
+    /// Gets the weather state with the given name, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clear" => Some(Weather::Clear),
+            "rain" => Some(Weather::Rain),
+            "storm" => Some(Weather::Storm),
+            _ => None,
+        }
+    }
+
+
+    /// Gets whether or not precipitation falls during this weather state.
+    pub fn is_precipitating(&self) -> bool {
+        *self != Weather::Clear
+    }
+
+
+    /// Gets the strength of the precipitation of this weather state, between
+    /// 0 and 1.
+    pub fn precipitation(&self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain => 0.6,
+            Weather::Storm => 1.0,
+        }
+    }
+
+
+    /// Gets how much the sky is darkened during this weather state, between 0
+    /// and 1.
+    pub fn sky_darkness(&self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain => 0.35,
+            Weather::Storm => 0.65,
+        }
+    }
+}
+
+
+/// A network message that is sent from the server to a client when the weather
+/// within the world of the local player changes, or when the player enters a
+/// new world.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WeatherMessage {
+    /// The new weather state.
+    pub weather: Weather,
+
+    /// The number of seconds to blend from the previous weather state into the
+    /// new one. If zero, the weather changes instantly.
+    pub transition: f32,
+}
+
+
+/// A client-side resource that stores the weather within the world of the
+/// local player, as last reported by the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ClientWeather {
+    /// The weather state that is being blended from.
+    previous: Weather,
+
+    /// The current weather state.
+    current: Weather,
+
+    /// The number of seconds that the current transition lasts for.
+    duration: f32,
+
+    /// The number of seconds since the current transition started.
+    elapsed: f32,
+}
+
+impl ClientWeather {
+    /// Gets the current weather state.
+    pub fn current(&self) -> Weather {
+        self.current
+    }
+
+
+    /// Gets how far the transition into the current weather state has
+    /// progressed, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        match self.duration > 0.0 {
+            true => (self.elapsed / self.duration).clamp(0.0, 1.0),
+            false => 1.0,
+        }
+    }
+
+
+    /// Gets the blended precipitation strength, between 0 and 1.
+    pub fn precipitation(&self) -> f32 {
+        self.blend(Weather::precipitation)
+    }
+
+
+    /// Gets the blended sky darkness, between 0 and 1.
+    pub fn sky_darkness(&self) -> f32 {
+        self.blend(Weather::sky_darkness)
+    }
+
+
+    /// Blends the given property of the previous and current weather states
+    /// by the progress of the current transition.
+    fn blend(&self, property: fn(&Weather) -> f32) -> f32 {
+        let from = property(&self.previous);
+        let to = property(&self.current);
+        from + (to - from) * self.progress()
+    }
+}
+
+
+/// Applies the weather messages that were received from the server to the
+/// client weather.
+pub fn apply_weather_messages(
+    mut weather_ev: EventReader<ServerMessage<WeatherMessage>>,
+    mut weather: ResMut<ClientWeather>,
+) {
+    for ev in weather_ev.iter() {
+        let message = ev.message;
+        weather.previous = match message.transition > 0.0 {
+            true => weather.current,
+            false => message.weather,
+        };
+        weather.current = message.weather;
+        weather.duration = message.transition.max(0.0);
+        weather.elapsed = 0.0;
+    }
+}
+
+
+/// Advances the transition of the client weather.
+pub fn update_weather_transition(time: Res<Time>, mut weather: ResMut<ClientWeather>) {
+    if weather.elapsed < weather.duration {
+        weather.elapsed += time.delta_seconds();
+    }
+}
+
+
+/// Clears the client weather once the local player has disconnected from the
+/// server.
+pub fn reset_client_weather(mut weather: ResMut<ClientWeather>) {
+    *weather = ClientWeather::default();
+}
@@ -0,0 +1,37 @@
+//! Components for defining the collision bounds of an entity.
+
+
+use crate::prelude::Position;
+use awgen_math::prelude::Aabb;
+use bevy::prelude::*;
+
+
+/// The axis-aligned collision bounds of an entity, relative to its position.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Collider {
+    /// The collision bounds of the entity, in local space.
+    pub bounds: Aabb,
+}
+
+impl Collider {
+    /// Creates a new collider with the given local bounds.
+    pub fn new(bounds: Aabb) -> Self {
+        Self {
+            bounds,
+        }
+    }
+
+
+    /// Gets the collision bounds of this collider within the world, based on
+    /// the given entity position.
+    ///
+    /// Rotation is ignored, as the bounds always remain axis-aligned.
+    pub fn world_bounds(&self, position: &Position) -> Aabb {
+        Aabb::new(
+            self.bounds.min() * position.scale,
+            self.bounds.max() * position.scale,
+        )
+        .translate(position.translation)
+    }
+}
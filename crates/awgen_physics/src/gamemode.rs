@@ -0,0 +1,105 @@
+//! Defines the game modes that a player may be in, which determine how that
+//! player is able to interact with the world.
+
+
+use anyhow::{bail, Error};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+
+/// The game mode of a player.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Reflect,
+    FromReflect,
+    Component,
+    Serialize,
+    Deserialize,
+)]
+#[reflect(Component)]
+pub enum GameMode {
+    /// The player is affected by collision and gravity, must break blocks over
+    /// time, and uses a limited inventory.
+    #[default]
+    Survival,
+
+    /// The player may fly, breaks blocks instantly, and has an unlimited
+    /// inventory.
+    Creative,
+
+    /// The player may fly through blocks freely but cannot interact with the
+    /// world.
+    Spectator,
+}
+
+impl GameMode {
+    /// Gets whether or not players in this game mode are allowed to fly.
+    pub fn can_fly(&self) -> bool {
+        matches!(self, GameMode::Creative | GameMode::Spectator)
+    }
+
+
+    /// Gets whether or not players in this game mode break blocks instantly.
+    pub fn instant_break(&self) -> bool {
+        matches!(self, GameMode::Creative)
+    }
+
+
+    /// Gets whether or not players in this game mode consume items from their
+    /// inventory when using them.
+    pub fn consumes_items(&self) -> bool {
+        matches!(self, GameMode::Survival)
+    }
+
+
+    /// Gets whether or not players in this game mode are able to interact with
+    /// the world, such as placing and breaking blocks or using their
+    /// inventory.
+    pub fn can_interact(&self) -> bool {
+        !matches!(self, GameMode::Spectator)
+    }
+
+
+    /// Gets whether or not players in this game mode collide with blocks and
+    /// other entities.
+    pub fn has_collision(&self) -> bool {
+        !matches!(self, GameMode::Spectator)
+    }
+
+
+    /// Gets the lowercase name of this game mode.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+            GameMode::Spectator => "spectator",
+        }
+    }
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "survival" | "s" | "0" => Ok(GameMode::Survival),
+            "creative" | "c" | "1" => Ok(GameMode::Creative),
+            "spectator" | "sp" | "2" => Ok(GameMode::Spectator),
+            _ => bail!("Unknown game mode: {s}"),
+        }
+    }
+}
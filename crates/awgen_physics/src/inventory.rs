@@ -0,0 +1,133 @@
+//! Defines item stacks and inventories, which hold items for players and
+//! container blocks.
+
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// A number of items of the same type, held within a single inventory slot.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, Reflect, FromReflect, Serialize, Deserialize,
+)]
+pub struct ItemStack {
+    /// The name of the item type. This is empty for an empty stack.
+    pub item: String,
+
+    /// The number of items within this stack.
+    pub count: u16,
+}
+
+impl ItemStack {
+    /// Creates a new item stack with the given item type and count.
+    pub fn new<S>(item: S, count: u16) -> Self
+    where S: Into<String> {
+        Self {
+            item: item.into(),
+            count,
+        }
+    }
+
+
+    /// Gets whether or not this stack contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.item.is_empty() || self.count == 0
+    }
+}
+
+
+/// A fixed number of slots that each hold a stack of items.
+///
+/// Each change to the inventory increases its revision, which allows changes
+/// that were requested against an older view of the inventory to be detected
+/// and rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect, Component)]
+#[reflect(Component)]
+pub struct Inventory {
+    /// The item stack within each slot.
+    slots: Vec<ItemStack>,
+
+    /// The number of changes that have been made to this inventory.
+    revision: u32,
+}
+
+impl Inventory {
+    /// Creates a new, empty inventory with the given number of slots.
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots:    vec![ItemStack::default(); size],
+            revision: 0,
+        }
+    }
+
+
+    /// Gets the number of slots within this inventory.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+
+    /// Gets whether or not this inventory has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+
+    /// Gets the item stacks within all slots of this inventory.
+    pub fn slots(&self) -> &[ItemStack] {
+        &self.slots
+    }
+
+
+    /// Gets the item stack within the given slot, or `None` if the slot does
+    /// not exist.
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot)
+    }
+
+
+    /// Gets the current revision of this inventory.
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+
+    /// Replaces the item stack within the given slot, returning the previous
+    /// stack, or `None` if the slot does not exist.
+    pub fn set(&mut self, slot: usize, stack: ItemStack) -> Option<ItemStack> {
+        let previous = std::mem::replace(self.slots.get_mut(slot)?, stack);
+        self.revision = self.revision.wrapping_add(1);
+        Some(previous)
+    }
+
+
+    /// Moves the item stack within one slot to another slot. If the target
+    /// slot contains the same item type, the stacks are merged, up to the
+    /// given maximum stack size. Otherwise, the two stacks are swapped.
+    ///
+    /// Returns false if either slot does not exist.
+    pub fn move_stack(&mut self, from: usize, to: usize, max_stack: u16) -> bool {
+        if from >= self.slots.len() || to >= self.slots.len() {
+            return false;
+        }
+
+        if from == to {
+            return true;
+        }
+
+        let (source, target) = (&self.slots[from], &self.slots[to]);
+        if !source.is_empty() && source.item == target.item {
+            let moved = source.count.min(max_stack.saturating_sub(target.count));
+            self.slots[to].count += moved;
+            self.slots[from].count -= moved;
+            if self.slots[from].count == 0 {
+                self.slots[from] = ItemStack::default();
+            }
+        } else {
+            self.slots.swap(from, to);
+        }
+
+        self.revision = self.revision.wrapping_add(1);
+        true
+    }
+}
@@ -6,13 +6,25 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+pub mod collider;
+pub mod gamemode;
+pub mod inventory;
+pub mod look;
+pub mod ordering;
 pub mod position;
+pub mod state;
 pub mod time;
 pub mod velocity;
 
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
+    pub use super::collider::*;
+    pub use super::gamemode::*;
+    pub use super::inventory::*;
+    pub use super::look::*;
+    pub use super::ordering::*;
     pub use super::position::*;
+    pub use super::state::*;
     pub use super::time::*;
     pub use super::velocity::*;
     pub use super::*;
@@ -44,9 +56,18 @@ impl Plugin for PhysicsPlugin {
         let timestep = 1.0 / self.tickrate as f64;
 
         app.register_type::<Position>()
+            .register_type::<Collider>()
             .register_type::<PreviousPosition>()
             .register_type::<VelocitySource>()
             .register_type::<Movable>()
+            .register_type::<Knockback>()
+            .register_type::<GameMode>()
+            .register_type::<ItemStack>()
+            .register_type::<Vec<ItemStack>>()
+            .register_type::<Inventory>()
+            .register_type::<Look>()
+            .register_type::<PreviousLook>()
+            .register_type::<CameraRig>()
             .insert_resource(PhysicsTickrate::new(self.tickrate))
             .insert_resource(PhysicsFrame::default())
             .add_stage_before(
@@ -55,6 +76,7 @@ impl Plugin for PhysicsPlugin {
                 SystemStage::parallel()
                     .with_run_criteria(FixedTimestep::step(timestep))
                     .with_system(push_position_stack)
+                    .with_system(push_look_stack)
                     .with_system(prepare_physics_render_frame),
             )
             .add_stage_after(
@@ -69,8 +91,19 @@ impl Plugin for PhysicsPlugin {
                     .with_run_criteria(FixedTimestep::step(timestep))
                     .with_system(apply_velocity),
             )
-            .add_system(update_physics_render_frame)
-            .add_system(update_render_position.after(update_physics_render_frame));
+            .add_system(
+                update_physics_render_frame.in_awgen_set(AwgenSystemOrdering::RenderInterpolation),
+            )
+            .add_system(
+                update_render_position
+                    .in_awgen_set(AwgenSystemOrdering::RenderInterpolation)
+                    .after(update_physics_render_frame),
+            )
+            .add_system(
+                update_camera_rigs
+                    .in_awgen_set(AwgenSystemOrdering::Camera)
+                    .after(update_render_position),
+            );
     }
 }
 
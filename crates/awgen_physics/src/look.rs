@@ -0,0 +1,133 @@
+//! Components and systems for the look rotation of an entity, and for camera
+//! rigs that follow it.
+//!
+//! A camera is usually a child of the entity that it looks out of, whose
+//! render transform is interpolated between physics frames. If the rotation
+//! of the camera was set directly, the interpolated rotation of its parent
+//! would be applied on top of it, which jitters between physics frames.
+//! Instead, the camera of a [`CameraRig`] is rotated each render frame so that
+//! it faces the look rotation of the rig, regardless of how its parent is
+//! rotated.
+
+
+use crate::prelude::PhysicsFrame;
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+
+/// The rotation that an entity is looking towards on a physics frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect, Component)]
+#[reflect(Component)]
+pub struct Look {
+    /// The rotation around the vertical axis, in radians.
+    pub yaw: f32,
+
+    /// The rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+}
+
+impl Look {
+    /// Creates a new look rotation from the given yaw and pitch, in radians.
+    pub fn new(yaw: f32, pitch: f32) -> Self {
+        Self {
+            yaw,
+            pitch,
+        }
+    }
+
+
+    /// Gets the quaternion value of this look rotation.
+    pub fn quat(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0)
+    }
+
+
+    /// Interpolates between this look rotation and the given look rotation.
+    ///
+    /// The yaw is interpolated along the shortest path, so that a look
+    /// rotation that wraps around a full turn does not spin the other way.
+    pub fn lerp(&self, next: &Look, delta: f32) -> Look {
+        let yaw_offset = (next.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+
+        Look {
+            yaw:   self.yaw + yaw_offset * delta,
+            pitch: self.pitch + (next.pitch - self.pitch) * delta,
+        }
+    }
+}
+
+
+/// The value of an entity's look rotation on the previous physics frame.
+///
+/// Entities with this component have their look rotation interpolated between
+/// physics frames, in the same way as their position. Entities whose look
+/// rotation is updated every render frame, such as the local player, should
+/// not have this component.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect, Component)]
+#[reflect(Component)]
+pub struct PreviousLook {
+    /// The rotation around the vertical axis, in radians.
+    pub yaw: f32,
+
+    /// The rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+}
+
+
+/// A component for an entity with a [`Look`] rotation that rotates a camera to
+/// face its look rotation each render frame.
+///
+/// The camera is usually a child of the rig entity, but may be attached to
+/// any other entity, such as while spectating. Only the rotation of the camera
+/// is changed, so its translation remains free to offset it from its parent.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct CameraRig {
+    /// The camera entity that is rotated by this rig.
+    pub camera: Option<Entity>,
+}
+
+
+/// This is called once at the beginning of each physics frame to assign the
+/// current look rotation as the look rotation of the last physics frame.
+pub fn push_look_stack(mut query: Query<(&mut PreviousLook, &Look)>) {
+    for (mut previous, look) in query.iter_mut() {
+        previous.yaw = look.yaw;
+        previous.pitch = look.pitch;
+    }
+}
+
+
+/// Rotates the camera of each camera rig to face the look rotation of the rig,
+/// interpolated between physics frames.
+///
+/// The rotation of the camera is applied relative to the render transform of
+/// its parent, so this must run after render interpolation.
+pub fn update_camera_rigs(
+    frame: Res<PhysicsFrame>,
+    rigs: Query<(&CameraRig, &Look, Option<&PreviousLook>)>,
+    cameras: Query<Option<&Parent>>,
+    mut transforms: ParamSet<(Query<&Transform>, Query<&mut Transform>)>,
+) {
+    for (rig, look, previous) in rigs.iter() {
+        let Some(camera) = rig.camera else {
+            continue;
+        };
+
+        let look = match previous {
+            Some(previous) => Look::new(previous.yaw, previous.pitch).lerp(look, frame.delta()),
+            None => *look,
+        };
+
+        let parent_rotation = cameras
+            .get(camera)
+            .ok()
+            .flatten()
+            .and_then(|parent| transforms.p0().get(parent.get()).ok().map(|t| t.rotation))
+            .unwrap_or(Quat::IDENTITY);
+
+        if let Ok(mut transform) = transforms.p1().get_mut(camera) {
+            transform.rotation = parent_rotation.inverse() * look.quat();
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! The labeled system sets that are shared between the Awgen crates, and an
+//! audit of the execution order ambiguities within a schedule.
+//!
+//! Systems join a set with [`AwgenSystemExt::in_awgen_set`], which labels the
+//! system and applies the ordering constraints of the set. The systems of an
+//! ambiguity set, such as [`AwgenSystemOrdering::PlayerInput`], may run in any
+//! order relative to each other.
+//!
+//! Bevy only logs execution order ambiguities as warnings. An
+//! [`AmbiguityAudit`] instead returns them, so that tests are able to fail as
+//! soon as a new ambiguity is introduced.
+
+
+use bevy::ecs::schedule::{
+    GraphNode, IntoSystemDescriptor, Schedule, SystemContainer, SystemDescriptor, SystemLabelId
+};
+use bevy::prelude::*;
+
+
+/// The labeled sets of systems that are shared between the Awgen crates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub enum AwgenSystemOrdering {
+    /// Systems that interpolate the render transforms of entities between
+    /// physics frames.
+    RenderInterpolation,
+
+    /// Systems that read the input of the local player. This is an ambiguity
+    /// set, as each system handles a separate part of the input.
+    PlayerInput,
+
+    /// Systems that move the camera of the local player. Runs after player
+    /// input and render interpolation, so that the camera always reflects the
+    /// latest state of the player.
+    Camera,
+
+    /// Systems that load and unload the chunks around chunk anchors.
+    ChunkLoading,
+
+    /// Systems that generate and prune the block data of chunks. Runs after
+    /// chunk loading, so that newly loaded chunks are generated within the
+    /// same frame.
+    ChunkGeneration,
+}
+
+impl AwgenSystemOrdering {
+    /// All shared system sets.
+    pub const ALL: [AwgenSystemOrdering; 5] = [
+        AwgenSystemOrdering::RenderInterpolation,
+        AwgenSystemOrdering::PlayerInput,
+        AwgenSystemOrdering::Camera,
+        AwgenSystemOrdering::ChunkLoading,
+        AwgenSystemOrdering::ChunkGeneration,
+    ];
+
+
+    /// Gets the sets that the systems of this set must run after.
+    pub fn dependencies(self) -> &'static [AwgenSystemOrdering] {
+        match self {
+            AwgenSystemOrdering::Camera => {
+                &[AwgenSystemOrdering::PlayerInput, AwgenSystemOrdering::RenderInterpolation]
+            },
+            AwgenSystemOrdering::ChunkGeneration => &[AwgenSystemOrdering::ChunkLoading],
+            _ => &[],
+        }
+    }
+
+
+    /// Gets whether or not the systems of this set may run in any order
+    /// relative to each other.
+    pub fn is_ambiguity_set(self) -> bool {
+        self == AwgenSystemOrdering::PlayerInput
+    }
+}
+
+
+/// An extension for systems that allows them to join a shared system set.
+pub trait AwgenSystemExt<Params>: IntoSystemDescriptor<Params> {
+    /// Labels this system with the given set, and orders it after all
+    /// dependencies of the set. Within an ambiguity set, this system is also
+    /// marked as ambiguous with the other systems of the set.
+    fn in_awgen_set(self, set: AwgenSystemOrdering) -> SystemDescriptor;
+}
+
+impl<Params, S> AwgenSystemExt<Params> for S
+where S: IntoSystemDescriptor<Params>
+{
+    fn in_awgen_set(self, set: AwgenSystemOrdering) -> SystemDescriptor {
+        let mut descriptor = self.label(set);
+
+        for dependency in set.dependencies() {
+            descriptor = descriptor.after(*dependency);
+        }
+
+        if set.is_ambiguity_set() {
+            descriptor = descriptor.ambiguous_with(set);
+        }
+
+        descriptor
+    }
+}
+
+
+/// A pair of systems within the same stage that access the same data, without
+/// an explicit order between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemAmbiguity {
+    /// The name of the stage that contains both systems.
+    pub stage: String,
+
+    /// The names of both systems.
+    pub systems: [String; 2],
+
+    /// The names of the components and resources that both systems access,
+    /// where at least one of them writes to it. This is empty if either
+    /// system is exclusive.
+    pub conflicts: Vec<String>,
+}
+
+
+/// An opt-in audit of the execution order ambiguities within the schedule of
+/// an app.
+///
+/// By default, the systems of each shared ambiguity set may be ambiguous with
+/// each other. Ambiguities that are allowed by calling `ambiguous_with` on an
+/// individual system are not visible to the audit, and must be allowed with
+/// [`AmbiguityAudit::allow_pair`] instead.
+#[derive(Debug, Clone)]
+pub struct AmbiguityAudit {
+    /// The labels whose systems may be ambiguous with each other.
+    allowed_sets: Vec<SystemLabelId>,
+
+    /// The names of system pairs whose ambiguity is accepted.
+    allowed_pairs: Vec<(String, String)>,
+}
+
+impl Default for AmbiguityAudit {
+    fn default() -> Self {
+        Self {
+            allowed_sets:  AwgenSystemOrdering::ALL
+                .into_iter()
+                .filter(|set| set.is_ambiguity_set())
+                .map(|set| set.as_label())
+                .collect(),
+            allowed_pairs: vec![],
+        }
+    }
+}
+
+impl AmbiguityAudit {
+    /// Allows the systems with the given label to be ambiguous with each other.
+    pub fn allow_set(mut self, label: impl SystemLabel) -> Self {
+        self.allowed_sets.push(label.as_label());
+        self
+    }
+
+
+    /// Allows the two given systems to be ambiguous with each other. Systems
+    /// are matched by their full name, or by their name without its module
+    /// path.
+    pub fn allow_pair(mut self, a: &str, b: &str) -> Self {
+        self.allowed_pairs.push((a.to_string(), b.to_string()));
+        self
+    }
+
+
+    /// Finds all ambiguities within the schedule of the given app that are not
+    /// allowed by this audit.
+    ///
+    /// Systems are only ordered once their stage has run, so the app must be
+    /// updated at least once before it is audited.
+    pub fn run(&self, app: &App) -> Vec<SystemAmbiguity> {
+        let mut ambiguities = vec![];
+        self.audit_schedule(&app.schedule, &app.world, &mut ambiguities);
+        ambiguities
+    }
+
+
+    /// Panics if the schedule of the given app contains any ambiguity that is
+    /// not allowed by this audit, listing each ambiguity.
+    pub fn assert_none(&self, app: &App) {
+        let ambiguities = self.run(app);
+        if ambiguities.is_empty() {
+            return;
+        }
+
+        let list: Vec<String> = ambiguities
+            .iter()
+            .map(|a| {
+                format!(
+                    "  [{}] {} and {} conflict on {:?}",
+                    a.stage, a.systems[0], a.systems[1], a.conflicts
+                )
+            })
+            .collect();
+
+        panic!(
+            "Found {} unexpected system order ambiguities:\n{}",
+            ambiguities.len(),
+            list.join("\n")
+        );
+    }
+
+
+    /// Audits all stages of the given schedule, including nested schedules.
+    fn audit_schedule(
+        &self,
+        schedule: &Schedule,
+        world: &World,
+        ambiguities: &mut Vec<SystemAmbiguity>,
+    ) {
+        for (label, stage) in schedule.iter_stages() {
+            if let Some(stage) = stage.downcast_ref::<SystemStage>() {
+                let lists = [
+                    stage.parallel_systems(),
+                    stage.exclusive_at_start_systems(),
+                    stage.exclusive_before_commands_systems(),
+                    stage.exclusive_at_end_systems(),
+                ];
+
+                for systems in lists {
+                    self.audit_systems(label.as_str(), systems, world, ambiguities);
+                }
+            } else if let Some(schedule) = stage.downcast_ref::<Schedule>() {
+                self.audit_schedule(schedule, world, ambiguities);
+            }
+        }
+    }
+
+
+    /// Audits a single list of systems within the given stage.
+    fn audit_systems(
+        &self,
+        stage: &str,
+        systems: &[SystemContainer],
+        world: &World,
+        ambiguities: &mut Vec<SystemAmbiguity>,
+    ) {
+        let ancestors: Vec<Vec<bool>> = (0..systems.len())
+            .map(|index| {
+                let mut visited = vec![false; systems.len()];
+                let mut stack = systems[index].dependencies().to_vec();
+                while let Some(dependency) = stack.pop() {
+                    if !visited[dependency] {
+                        visited[dependency] = true;
+                        stack.extend_from_slice(systems[dependency].dependencies());
+                    }
+                }
+                visited
+            })
+            .collect();
+
+        for a in 0..systems.len() {
+            for b in a + 1..systems.len() {
+                if ancestors[a][b] || ancestors[b][a] || self.is_allowed(&systems[a], &systems[b]) {
+                    continue;
+                }
+
+                let conflicts = match systems[a].is_exclusive() || systems[b].is_exclusive() {
+                    true => vec![],
+                    false => {
+                        let access = systems[a].component_access();
+                        let conflicts = access.get_conflicts(systems[b].component_access());
+                        if conflicts.is_empty() {
+                            continue;
+                        }
+
+                        conflicts
+                            .into_iter()
+                            .map(|id| {
+                                world.components().get_info(id).map_or_else(
+                                    || format!("{id:?}"),
+                                    |info| info.name().to_string(),
+                                )
+                            })
+                            .collect()
+                    },
+                };
+
+                ambiguities.push(SystemAmbiguity {
+                    stage: stage.to_string(),
+                    systems: [systems[a].name().to_string(), systems[b].name().to_string()],
+                    conflicts,
+                });
+            }
+        }
+    }
+
+
+    /// Gets whether or not the two given systems are allowed to be ambiguous
+    /// with each other.
+    fn is_allowed(&self, a: &SystemContainer, b: &SystemContainer) -> bool {
+        let shares_set = self
+            .allowed_sets
+            .iter()
+            .any(|label| a.labels().contains(label) && b.labels().contains(label));
+
+        let (a, b) = (a.name(), b.name());
+        shares_set
+            || self.allowed_pairs.iter().any(|(x, y)| {
+                (name_matches(&a, x) && name_matches(&b, y))
+                    || (name_matches(&a, y) && name_matches(&b, x))
+            })
+    }
+}
+
+
+/// Gets whether or not the given system name matches the given pattern, either
+/// fully or without its module path.
+fn name_matches(name: &str, pattern: &str) -> bool {
+    name == pattern || name.strip_suffix(pattern).is_some_and(|path| path.ends_with("::"))
+}
@@ -4,10 +4,11 @@
 
 use crate::PhysicsFrame;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 
 /// The absolute position of an entity on a physics frame.
-#[derive(Debug, Clone, Reflect, Component)]
+#[derive(Debug, Clone, Reflect, Component, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Position {
     /// The translation value of the entity within the world, measured in
@@ -63,6 +64,9 @@ pub fn update_render_position(
     frame: Res<PhysicsFrame>,
     mut query: Query<(&mut Transform, &Position, &PreviousPosition)>,
 ) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("physics", stage = "update_render_position").entered();
+
     let delta = frame.delta();
     query.par_for_each_mut(128, move |(mut transform, next, last)| {
         transform.translation = last.translation.lerp(next.translation, delta);
@@ -77,6 +81,9 @@ pub fn update_render_position(
 ///
 /// This allows for the calculation of render position calculations.
 pub fn push_position_stack(mut query: Query<(&mut PreviousPosition, &Position)>) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("physics", stage = "push_position_stack").entered();
+
     query.par_for_each_mut(512, |(mut previous, pos)| {
         previous.translation = pos.translation;
         previous.rotation = pos.rotation;
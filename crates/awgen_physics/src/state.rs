@@ -0,0 +1,81 @@
+//! The top-level application states that drive the flow of the client, from
+//! the main menu, through connecting and loading, and into gameplay.
+//!
+//! Headless servers do not use application states. All run criteria within
+//! this module treat an app without an [`AppState`] resource as being in game.
+
+
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+
+
+/// The top-level state of the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AppState {
+    /// The player is within the main menu, and is not connected to a server.
+    #[default]
+    MainMenu,
+
+    /// The client is attempting to connect to a server.
+    Connecting,
+
+    /// The client is connected, and is waiting for the world around the
+    /// player to finish loading.
+    LoadingWorld,
+
+    /// The player is in game.
+    InGame,
+
+    /// The player is in game, but has the pause menu open. The world continues
+    /// to update, but player input is ignored.
+    Paused,
+}
+
+impl AppState {
+    /// Gets whether or not the world is active within this state, meaning the
+    /// client is connected and the world is loaded.
+    pub fn is_in_world(&self) -> bool {
+        matches!(self, AppState::InGame | AppState::Paused)
+    }
+
+
+    /// Gets whether or not the client is connected to a server within this
+    /// state.
+    pub fn is_connected(&self) -> bool {
+        matches!(
+            self,
+            AppState::LoadingWorld | AppState::InGame | AppState::Paused
+        )
+    }
+}
+
+
+/// Runs if application states are not used within this app, or if the current
+/// state matches the given predicate.
+fn run_if(state: Option<Res<State<AppState>>>, predicate: fn(&AppState) -> bool) -> ShouldRun {
+    match state {
+        Some(state) if !predicate(state.current()) => ShouldRun::No,
+        _ => ShouldRun::Yes,
+    }
+}
+
+
+/// A run criteria that runs while the world is active, including while the
+/// game is paused.
+pub fn run_in_world(state: Option<Res<State<AppState>>>) -> ShouldRun {
+    run_if(state, AppState::is_in_world)
+}
+
+
+/// A run criteria that runs only while the player is in game and the game is
+/// not paused. This should be used for systems that handle player input.
+pub fn run_in_game(state: Option<Res<State<AppState>>>) -> ShouldRun {
+    run_if(state, |state| *state == AppState::InGame)
+}
+
+
+/// A run criteria that runs while the client is connected to a server,
+/// including while the world is loading.
+pub fn run_while_connected(state: Option<Res<State<AppState>>>) -> ShouldRun {
+    run_if(state, AppState::is_connected)
+}
@@ -7,6 +7,15 @@ use crate::prelude::Position;
 use bevy::prelude::*;
 
 
+/// The fraction of knockback velocity that remains after each physics frame.
+const KNOCKBACK_DAMPING: f32 = 0.8;
+
+
+/// The speed, in meters per physics frame, below which knockback velocity is
+/// discarded.
+const KNOCKBACK_THRESHOLD: f32 = 0.001;
+
+
 /// Indicates that the current entity is capable of generating force to apply
 /// to another entity or itself.
 #[derive(Reflect, Component, Default)]
@@ -33,18 +42,54 @@ pub struct Movable {
 }
 
 
+/// An external velocity that has been applied to a movable entity, such as
+/// from an explosion, which fades out over a number of physics frames.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Knockback {
+    /// The current knockback velocity, in meters per physics frame.
+    pub velocity: Vec3,
+}
+
+impl Knockback {
+    /// Adds the given impulse, in meters per physics frame, to the knockback
+    /// velocity.
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.velocity += impulse;
+    }
+}
+
+
 /// Called each physics frame in order to apply velocity to all movable entities
 /// and thus update their position.
 pub fn apply_velocity(
-    mut query: Query<(&mut Position, &Movable, Option<&VelocitySource>)>,
+    mut query: Query<(
+        &mut Position,
+        &Movable,
+        Option<&VelocitySource>,
+        Option<&mut Knockback>,
+    )>,
     vel_sources: Query<&VelocitySource>,
 ) {
-    query.par_for_each_mut(32, |(mut position, movable, self_force)| {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("physics", stage = "apply_velocity").entered();
+
+    query.par_for_each_mut(32, |(mut position, movable, self_force, knockback)| {
         let mut force = self_force.map_or(Vec3::ZERO, |f| f.force);
         for velocity_source in &movable.forces {
             force += vel_sources.get(*velocity_source).unwrap().force;
         }
 
+        if let Some(mut knockback) = knockback {
+            if knockback.velocity != Vec3::ZERO {
+                force += knockback.velocity;
+                knockback.velocity *= KNOCKBACK_DAMPING;
+                if knockback.velocity.length() < KNOCKBACK_THRESHOLD {
+                    knockback.velocity = Vec3::ZERO;
+                }
+            }
+        }
+
         // TODO: Check for collisions!
         position.translation += force;
     });
@@ -0,0 +1,247 @@
+//! Persists the ban list of the server, and allows clients and addresses to be
+//! banned and unbanned through commands.
+
+
+use crate::prelude::{read_save, write_save, CommandSender, SaveKind};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{BanList, ClientSocket, KickClient};
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+
+/// The file that the ban list of the server is saved to.
+#[derive(Debug, Clone, Resource)]
+pub struct BanListFile(pub PathBuf);
+
+impl BanListFile {
+    /// Loads the ban list from this file.
+    ///
+    /// If the file does not exist, `None` is returned.
+    pub fn load(&self) -> Result<Option<BanList>> {
+        read_save(&self.0, SaveKind::Server)
+    }
+
+
+    /// Saves the given ban list to this file.
+    pub fn save(&self, bans: &BanList) -> Result<()> {
+        write_save(&self.0, bans)
+    }
+}
+
+impl Default for BanListFile {
+    fn default() -> Self {
+        Self(PathBuf::from("world/bans.ron"))
+    }
+}
+
+
+/// Loads the saved ban list when the server starts.
+///
+/// If the ban list could not be loaded, an error is logged and the saved file
+/// is left untouched.
+pub fn load_ban_list(file: Res<BanListFile>, mut bans: ResMut<BanList>) {
+    match file.load() {
+        Ok(Some(loaded)) => *bans = loaded,
+        Ok(None) => {},
+        Err(err) => error!("Failed to load the ban list: {err:?}"),
+    }
+}
+
+
+/// Saves the ban list each time that it changes.
+pub fn save_ban_list(file: Res<BanListFile>, bans: Res<BanList>) {
+    if !bans.is_changed() || bans.is_added() {
+        return;
+    }
+
+    if let Err(err) = file.save(&bans) {
+        error!("Failed to save the ban list: {err:?}");
+    }
+}
+
+
+/// A target of a ban, which is either a client id or an IP address.
+enum BanTarget {
+    /// A single client id.
+    Client(u64),
+
+    /// All clients that connect from an IP address.
+    Address(IpAddr),
+}
+
+impl BanTarget {
+    /// Parses a ban target from a command argument.
+    fn parse(arg: &str) -> Result<Self> {
+        if let Ok(client_id) = arg.parse() {
+            return Ok(BanTarget::Client(client_id));
+        }
+
+        match arg.parse() {
+            Ok(address) => Ok(BanTarget::Address(address)),
+            Err(_) => bail!("'{arg}' is not a client id or IP address"),
+        }
+    }
+}
+
+
+/// Bans a client id or IP address from the server, kicking all matching
+/// clients that are online.
+///
+/// Client ids are chosen by each client when it connects, and are not tied to
+/// an account, so a banned client may rejoin by connecting with a new client
+/// id. Client id bans only remove a player for the current session; use an
+/// address ban to keep a player from rejoining.
+///
+/// Usage: `ban <client id|address> [reason]`
+pub fn ban_command(world: &mut World, _: &CommandSender, args: &[&str]) -> Result<String> {
+    let Some((target, reason)) = args.split_first() else {
+        bail!("Usage: ban <client id|address> [reason]");
+    };
+
+    let reason = match reason.is_empty() {
+        true => "Banned by an operator".to_string(),
+        false => reason.join(" "),
+    };
+
+    let target = BanTarget::parse(target)?;
+    let mut bans = world.resource_mut::<BanList>();
+    let (newly_banned, description) = match target {
+        BanTarget::Client(client_id) => {
+            (
+                bans.ban_client(client_id, &reason),
+                format!("client {client_id}"),
+            )
+        },
+        BanTarget::Address(address) => {
+            (
+                bans.ban_address(address, &reason),
+                format!("address {address}"),
+            )
+        },
+    };
+
+    let online: Vec<u64> =
+        world.query::<&ClientSocket>().iter(world).map(|socket| socket.id()).collect();
+
+    let server = world.resource::<RenetServer>();
+    let bans = world.resource::<BanList>();
+    let kicked: Vec<u64> = online
+        .into_iter()
+        .filter(|id| bans.check(*id, server.client_addr(*id)).is_some())
+        .collect();
+
+    let mut kick_ev = world.resource_mut::<Events<KickClient>>();
+    for client_id in kicked.iter() {
+        let reason = format!("You have been banned from this server: {reason}");
+        kick_ev.send(KickClient::from_id(*client_id, reason));
+    }
+
+    match newly_banned {
+        true => {
+            Ok(format!(
+                "Banned {description} and kicked {} players: {reason}",
+                kicked.len()
+            ))
+        },
+        false => Ok(format!("Updated the ban reason of {description}: {reason}")),
+    }
+}
+
+
+/// Lifts the ban on a client id or IP address.
+///
+/// Usage: `unban <client id|address>`
+pub fn unban_command(world: &mut World, _: &CommandSender, args: &[&str]) -> Result<String> {
+    let [target] = args else {
+        bail!("Usage: unban <client id|address>");
+    };
+
+    let mut bans = world.resource_mut::<BanList>();
+    let (unbanned, description) = match BanTarget::parse(target)? {
+        BanTarget::Client(client_id) => {
+            (bans.unban_client(client_id), format!("client {client_id}"))
+        },
+        BanTarget::Address(address) => (bans.unban_address(address), format!("address {address}")),
+    };
+
+    match unbanned {
+        true => Ok(format!("Unbanned {description}")),
+        false => bail!("The {description} is not banned"),
+    }
+}
+
+
+/// Lists all banned client ids and IP addresses.
+///
+/// Usage: `banlist`
+pub fn banlist_command(world: &mut World, _: &CommandSender, _: &[&str]) -> Result<String> {
+    let bans = world.resource::<BanList>();
+    if bans.is_empty() {
+        return Ok("No clients or addresses are banned".to_string());
+    }
+
+    let clients = bans.clients().map(|(id, reason)| format!("client {id}: {reason}"));
+    let addresses = bans.addresses().map(|(addr, reason)| format!("address {addr}: {reason}"));
+    let lines: Vec<String> = clients.chain(addresses).collect();
+    Ok(format!("Bans:\n{}", lines.join("\n")))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{TestServer, WorldConfig};
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("awgen-bans-{}", std::process::id()));
+        let file = BanListFile(dir.join("bans.ron"));
+        assert_eq!(file.load().unwrap(), None);
+
+        let mut bans = BanList::default();
+        bans.ban_client(12, "Griefing");
+        bans.ban_address("10.0.0.1".parse().unwrap(), "Spam");
+        file.save(&bans).unwrap();
+
+        let loaded = file.load();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(loaded.unwrap(), Some(bans));
+    }
+
+
+    #[test]
+    fn ban_command_is_saved() {
+        let mut test = TestServer::builder()
+            .with_world(WorldConfig::new("lobby", "default"))
+            .build()
+            .unwrap();
+        test.tick(1);
+
+        let response = test.run_command("ban 10.0.0.1 Spam").unwrap();
+        assert_eq!(
+            response,
+            "Banned address 10.0.0.1 and kicked 0 players: Spam"
+        );
+        test.tick(1);
+
+        let file = test.world().resource::<BanListFile>().clone();
+        assert_ne!(file.0, BanListFile::default().0);
+
+        let bans = file.load().unwrap().unwrap();
+        assert_eq!(
+            bans.check(5, Some("10.0.0.1:4000".parse().unwrap())),
+            Some("Spam")
+        );
+
+        test.run_command("unban 10.0.0.1").unwrap();
+        test.tick(1);
+
+        let bans = file.load().unwrap().unwrap();
+        assert!(bans.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+//! Tracks the progress of each player that is breaking a block, and replicates
+//! the crack stage of each block that is being broken to the other players
+//! within the same world.
+
+
+use crate::prelude::{check_block_use, BlockEditHistory, GameRules, PositionHistory, Rewind};
+use awgen_network::prelude::{
+    send_to_client, BlockBreakAction, BlockBreakMessage, BlockCrackMessage, ClientMessage, ClientSocket, HeldItem, MessageBatch, NetworkStats
+};
+use awgen_physics::prelude::{GameMode, Position};
+use awgen_world::prelude::{break_time, BlockBreaking, BlockHardness, InWorld, VoxelWorld};
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The number of seconds that a player may finish breaking a block early by,
+/// to account for the latency between the start and finish requests.
+pub const BREAK_TIME_TOLERANCE: f32 = 0.25;
+
+
+/// Starts or stops tracking the breaking progress of each player that sent a
+/// block breaking request.
+///
+/// A player may only start breaking a block that they are able to use, as
+/// checked by [`check_block_use`] after rewinding by the round trip time of the
+/// player, within a world that allows building. The
+/// block must not be empty, and must be breakable by the player. Requests that
+/// are not valid stop the breaking progress of the player.
+#[allow(clippy::type_complexity)]
+pub fn handle_block_break_requests<BlockData>(
+    time: Res<Time>,
+    stats: Res<NetworkStats>,
+    mut request_ev: EventReader<ClientMessage<BlockBreakMessage>>,
+    players: Query<(
+        &ClientSocket,
+        &Position,
+        &InWorld,
+        &GameMode,
+        Option<&HeldItem>,
+        Option<&PositionHistory>,
+    )>,
+    worlds: Query<(
+        &VoxelWorld<BlockData>,
+        Option<&CollisionLayer>,
+        Option<&GameRules>,
+        Option<&BlockEditHistory>,
+    )>,
+    mut commands: Commands,
+) where
+    BlockData: BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let now = time.elapsed_seconds_f64();
+
+    for ev in request_ev.iter() {
+        let Ok((socket, position, in_world, game_mode, held, positions)) = players.get(ev.player)
+        else {
+            continue;
+        };
+
+        if socket.id() != ev.client_id {
+            continue;
+        }
+
+        let BlockBreakAction::Start {
+            block_pos,
+        } = ev.message.action
+        else {
+            commands.entity(ev.player).remove::<BlockBreaking>();
+            continue;
+        };
+
+        let result = match worlds.get(in_world.0) {
+            Ok((_, _, Some(rules), _)) if !rules.allow_building => Err("building is not allowed"),
+            Ok((world, layer, _, edits)) => {
+                let rewind = Rewind::of_client(now, &stats, socket.id(), positions, edits);
+                check_block_use(position, game_mode, layer, block_pos, &rewind).and_then(|_| {
+                    let block = world.get_block_data(block_pos);
+                    if block == BlockData::default() {
+                        return Err("block is empty");
+                    }
+
+                    let tool =
+                        held.filter(|held| !held.0.is_empty()).map(|held| held.0.item.as_str());
+                    break_time(&block, *game_mode, tool).ok_or("block cannot be broken")
+                })
+            },
+            Err(_) => Err("world is not loaded"),
+        };
+
+        match result {
+            Ok(duration) => {
+                commands.entity(ev.player).insert(BlockBreaking::new(block_pos, now, duration));
+            },
+            Err(reason) => {
+                debug!(
+                    "Rejected block breaking at {block_pos} from client {}: {reason}",
+                    socket.id()
+                );
+                commands.entity(ev.player).remove::<BlockBreaking>();
+            },
+        }
+    }
+}
+
+
+/// The crack stage that was last sent for a player that is breaking a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentCrack {
+    /// The client id of the player.
+    pub client_id: u64,
+
+    /// The world that the block is within.
+    pub world: Entity,
+
+    /// The position of the block.
+    pub block_pos: IVec3,
+
+    /// The crack stage of the block.
+    pub stage: u8,
+}
+
+
+/// Sends the crack stage of each block that is being broken to the other
+/// players within the same world whenever it changes, and clears it once the
+/// player stops breaking the block.
+pub fn replicate_block_cracks(
+    time: Res<Time>,
+    mut sent: Local<HashMap<Entity, SentCrack>>,
+    mut batch: ResMut<MessageBatch>,
+    breakers: Query<(Entity, &ClientSocket, &InWorld, &BlockBreaking)>,
+    players: Query<(&ClientSocket, &InWorld)>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    let mut send = |crack: &SentCrack, stage: Option<u8>| {
+        for (socket, in_world) in players.iter() {
+            if in_world.0 != crack.world || socket.id() == crack.client_id {
+                continue;
+            }
+
+            send_to_client(&mut batch, socket.id(), &BlockCrackMessage {
+                client_id: crack.client_id,
+                block_pos: crack.block_pos,
+                stage,
+            });
+        }
+    };
+
+    let mut current = HashMap::default();
+    for (player, socket, in_world, breaking) in breakers.iter() {
+        current.insert(player, SentCrack {
+            client_id: socket.id(),
+            world:     in_world.0,
+            block_pos: breaking.block_pos,
+            stage:     breaking.crack_stage(now),
+        });
+    }
+
+    for (player, crack) in sent.iter() {
+        let moved = !current
+            .get(player)
+            .is_some_and(|next| next.world == crack.world && next.block_pos == crack.block_pos);
+
+        if moved {
+            send(crack, None);
+        }
+    }
+
+    for (player, crack) in current.iter() {
+        if sent.get(player) != Some(crack) {
+            send(crack, Some(crack.stage));
+        }
+    }
+
+    *sent = current;
+}
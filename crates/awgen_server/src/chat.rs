@@ -0,0 +1,228 @@
+//! Validates the chat messages that are sent by players, and broadcasts them
+//! to all connected clients, or only to the team of the sender for team chat
+//! messages.
+
+
+use crate::prelude::{CommandSender, Teams};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{
+    broadcast, sanitize_chat_message, send_to_client, ChatMessage, ClientMessage, ClientSocket, MessageBatch, PlayerName, SendChatMessage, TEAM_CHAT_PREFIX
+};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The minimum number of seconds between each chat message of a player.
+/// Messages that are sent faster than this are dropped.
+pub const CHAT_COOLDOWN_SECONDS: f64 = 0.5;
+
+
+/// An event that is triggered on the server for each chat message that a
+/// player posts, after it has been validated and broadcast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatEvent {
+    /// The player entity that posted the message.
+    pub player: Entity,
+
+    /// The client id of the player that posted the message.
+    pub client_id: u64,
+
+    /// The sanitized text of the message.
+    pub text: String,
+
+    /// The name of the team that the message was sent to, if it was a team
+    /// chat message.
+    pub team: Option<String>,
+}
+
+
+/// The time at which a player last posted a chat message.
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct LastChatMessage(pub f64);
+
+
+/// Validates each chat message that was received from a player, and broadcasts
+/// it to all connected clients.
+///
+/// Messages that start with the team chat prefix are only sent to the online
+/// members of the team of the sender. If the sender is not on a team, they are
+/// told so instead.
+///
+/// Messages are dropped if the sending entity is no longer the client socket of
+/// the sender, if nothing remains of the message once sanitized, or if the
+/// player is sending messages too quickly.
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
+pub fn handle_chat_messages(
+    time: Res<Time>,
+    teams: Res<Teams>,
+    mut message_ev: EventReader<ClientMessage<SendChatMessage>>,
+    mut chat_ev: EventWriter<ChatEvent>,
+    mut batch: ResMut<MessageBatch>,
+    mut players: Query<(
+        &ClientSocket,
+        Option<&PlayerName>,
+        Option<&mut LastChatMessage>,
+    )>,
+    members: Query<(&ClientSocket, &PlayerName)>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    // The times are tracked here while handling the messages, and only written
+    // back afterwards, so that a player without a last message time cannot send
+    // several messages within the same frame.
+    let mut last_sent: HashMap<Entity, f64> = HashMap::new();
+
+    for ev in message_ev.iter() {
+        let Ok((socket, name, last_message)) = players.get(ev.player) else {
+            continue;
+        };
+
+        if socket.id() != ev.client_id {
+            continue;
+        }
+
+        let Some(text) = sanitize_chat_message(&ev.message.text) else {
+            continue;
+        };
+
+        let sender = name.map_or_else(|| ev.client_id.to_string(), |name| name.0.clone());
+        let last = last_sent.get(&ev.player).copied().or(last_message.map(|last| last.0));
+        if last.is_some_and(|last| now - last < CHAT_COOLDOWN_SECONDS) {
+            debug!("Dropped chat message from {sender}: too fast");
+            continue;
+        }
+        last_sent.insert(ev.player, now);
+
+        let Some(team_text) = text.strip_prefix(TEAM_CHAT_PREFIX) else {
+            info!("<{sender}> {text}");
+            broadcast(&mut batch, &ChatMessage {
+                sender: Some(ev.client_id),
+                text:   text.clone(),
+            });
+
+            chat_ev.send(ChatEvent {
+                player: ev.player,
+                client_id: ev.client_id,
+                text,
+                team: None,
+            });
+            continue;
+        };
+
+        let Some(text) = sanitize_chat_message(team_text) else {
+            continue;
+        };
+
+        let team_name = name.and_then(|name| teams.team_of(&name.0));
+        let Some((team_name, team)) = team_name.and_then(|n| Some((n, teams.team(n)?))) else {
+            send_to_client(&mut batch, ev.client_id, &ChatMessage {
+                sender: None,
+                text:   "You are not on a team".to_string(),
+            });
+            continue;
+        };
+
+        info!("[{team_name}] <{sender}> {text}");
+        let message = ChatMessage {
+            sender: Some(ev.client_id),
+            text:   format!("[{}] {text}", team.display_name()),
+        };
+
+        for (member, _) in members.iter().filter(|(_, name)| team.members().any(|m| m == name.0)) {
+            send_to_client(&mut batch, member.id(), &message);
+        }
+
+        chat_ev.send(ChatEvent {
+            player: ev.player,
+            client_id: ev.client_id,
+            text,
+            team: Some(team_name.to_string()),
+        });
+    }
+
+    for (player, time) in last_sent {
+        match players.get_mut(player) {
+            Ok((_, _, Some(mut last))) => last.0 = time,
+            _ => {
+                commands.entity(player).insert(LastChatMessage(time));
+            },
+        }
+    }
+}
+
+
+/// Posts a message to the chat on behalf of the server.
+///
+/// Usage: `say <message>`
+pub fn say_command(world: &mut World, _: &CommandSender, args: &[&str]) -> Result<String> {
+    let Some(text) = sanitize_chat_message(&args.join(" ")) else {
+        bail!("Usage: say <message>");
+    };
+
+    broadcast(&mut world.resource_mut::<MessageBatch>(), &ChatMessage {
+        sender: None,
+        text:   text.clone(),
+    });
+    Ok(format!("[Server] {text}"))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates a new app that handles chat messages, with a single player.
+    fn setup() -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Teams>()
+            .init_resource::<MessageBatch>()
+            .add_event::<ClientMessage<SendChatMessage>>()
+            .add_event::<ChatEvent>()
+            .add_system(handle_chat_messages);
+
+        let player = app.world.spawn((ClientSocket::new(1), PlayerName("alice".to_string()))).id();
+        (app, player)
+    }
+
+
+    /// Sends the given chat messages from the given player.
+    fn send(app: &mut App, player: Entity, messages: &[&str]) {
+        let mut message_ev = app.world.resource_mut::<Events<ClientMessage<SendChatMessage>>>();
+        for text in messages {
+            message_ev.send(ClientMessage {
+                client_id: 1,
+                player,
+                message: SendChatMessage {
+                    text: text.to_string(),
+                },
+            });
+        }
+    }
+
+
+    /// Gets the text of each chat event that has been triggered.
+    fn posted(app: &App) -> Vec<String> {
+        let chat_ev = app.world.resource::<Events<ChatEvent>>();
+        chat_ev.get_reader().iter(chat_ev).map(|ev| ev.text.clone()).collect()
+    }
+
+
+    #[test]
+    fn limit_first_frame() {
+        let (mut app, player) = setup();
+        send(&mut app, player, &["one", "two", "three"]);
+        app.update();
+
+        assert_eq!(posted(&app), vec!["one".to_string()]);
+        assert!(app.world.get::<LastChatMessage>(player).is_some());
+
+        send(&mut app, player, &["four"]);
+        app.update();
+        assert_eq!(posted(&app), vec!["one".to_string()]);
+    }
+}
@@ -0,0 +1,280 @@
+//! The server command framework. Commands are registered by name within the
+//! [`CommandRegistry`] and executed from the server console, over the remote
+//! administration channel, or by players.
+//!
+//! Each executed command is recorded within the command audit log.
+
+
+use crate::prelude::AUDIT_LOG_TARGET;
+use anyhow::{bail, Result};
+use awgen_network::prelude::{RconCommandEvent, RconServer};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::io::BufRead;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+
+
+/// A function that executes a command with the given arguments on behalf of a
+/// command sender, returning a response message on success.
+pub type CommandHandler = fn(&mut World, &CommandSender, &[&str]) -> Result<String>;
+
+
+/// The source that a command was executed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandSender {
+    /// The command was entered into the server console.
+    Console,
+
+    /// The command was sent over the remote administration channel by the
+    /// connection with the given id.
+    Remote(u64),
+
+    /// The command was sent by the given player entity.
+    Player(Entity),
+}
+
+
+/// A single command that has been registered with the server.
+#[derive(Debug, Clone)]
+pub struct RegisteredCommand {
+    /// The usage string of this command, describing its arguments.
+    pub usage: &'static str,
+
+    /// A short description of what this command does.
+    pub description: &'static str,
+
+    /// The function that executes this command.
+    pub handler: CommandHandler,
+}
+
+
+/// A registry of all commands that are available on the server.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct CommandRegistry {
+    /// A map of command names to their registered commands.
+    commands: HashMap<String, RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    /// Registers a new command with the given name.
+    ///
+    /// If a command with the same name was already registered, it is replaced.
+    pub fn register(
+        &mut self,
+        name: &str,
+        usage: &'static str,
+        description: &'static str,
+        handler: CommandHandler,
+    ) {
+        self.commands.insert(name.to_lowercase(), RegisteredCommand {
+            usage,
+            description,
+            handler,
+        });
+    }
+
+
+    /// Gets the registered command with the given name, if it exists.
+    pub fn get(&self, name: &str) -> Option<&RegisteredCommand> {
+        self.commands.get(&name.to_lowercase())
+    }
+
+
+    /// Gets an iterator over all registered command names and their commands.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &RegisteredCommand)> {
+        self.commands.iter()
+    }
+}
+
+
+/// An event that requests a command line to be executed on the server.
+#[derive(Debug, Clone)]
+pub struct ServerCommandEvent {
+    /// The source that the command was sent from.
+    pub sender: CommandSender,
+
+    /// The full command line, including the command name and all arguments.
+    /// A leading slash is optional.
+    pub line: String,
+}
+
+
+/// An event that is triggered after a command has been executed, containing
+/// the response to be shown to the command sender.
+#[derive(Debug, Clone)]
+pub struct CommandResponseEvent {
+    /// The source that the command was sent from.
+    pub sender: CommandSender,
+
+    /// The response message of the command. If the command failed, this
+    /// contains the error message instead.
+    pub response: Result<String, String>,
+}
+
+
+/// A resource that receives lines entered into the server console from a
+/// background thread.
+#[derive(Resource)]
+pub struct ConsoleInput {
+    /// The receiving end of the console line channel.
+    lines: Mutex<Receiver<String>>,
+}
+
+impl ConsoleInput {
+    /// Starts a new background thread that reads lines from the standard input
+    /// stream.
+    pub fn from_stdin() -> Self {
+        let (sender, receiver) = channel();
+
+        std::thread::Builder::new()
+            .name("Console".to_string())
+            .spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            lines: Mutex::new(receiver),
+        }
+    }
+}
+
+
+/// Converts lines entered into the server console into command events.
+pub fn read_console_input(
+    console: Res<ConsoleInput>,
+    mut command_ev: EventWriter<ServerCommandEvent>,
+) {
+    let lines = console.lines.lock().unwrap();
+    for line in lines.try_iter() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        command_ev.send(ServerCommandEvent {
+            sender: CommandSender::Console,
+            line,
+        });
+    }
+}
+
+
+/// Converts commands received over the remote administration channel into
+/// command events.
+pub fn read_rcon_commands(
+    mut rcon_ev: EventReader<RconCommandEvent>,
+    mut command_ev: EventWriter<ServerCommandEvent>,
+) {
+    for ev in rcon_ev.iter() {
+        command_ev.send(ServerCommandEvent {
+            sender: CommandSender::Remote(ev.connection),
+            line:   ev.command.clone(),
+        });
+    }
+}
+
+
+/// Parses and executes a single command line on behalf of the given sender.
+pub fn execute_command(world: &mut World, sender: &CommandSender, line: &str) -> Result<String> {
+    let line = line.trim().trim_start_matches('/');
+    let mut args = line.split_whitespace();
+
+    let Some(name) = args.next() else {
+        bail!("No command was given");
+    };
+
+    let Some(command) = world.resource::<CommandRegistry>().get(name).cloned() else {
+        bail!("Unknown command: {name}");
+    };
+
+    let args: Vec<&str> = args.collect();
+    (command.handler)(world, sender, &args)
+}
+
+
+/// Executes all pending command events, writing each to the audit log and
+/// triggering a response event.
+pub fn execute_commands(world: &mut World) {
+    let events: Vec<ServerCommandEvent> =
+        world.resource_mut::<Events<ServerCommandEvent>>().drain().collect();
+
+    for event in events {
+        let response = execute_command(world, &event.sender, &event.line);
+
+        match &response {
+            Ok(msg) => {
+                info!(target: AUDIT_LOG_TARGET, sender = ?event.sender, command = %event.line, "{msg}");
+            },
+            Err(err) => {
+                warn!(target: AUDIT_LOG_TARGET, sender = ?event.sender, command = %event.line, "{err}");
+            },
+        }
+
+        world.resource_mut::<Events<CommandResponseEvent>>().send(CommandResponseEvent {
+            sender:   event.sender,
+            response: response.map_err(|err| err.to_string()),
+        });
+    }
+}
+
+
+/// Prints the responses of commands that were sent from the server console.
+pub fn print_console_responses(mut response_ev: EventReader<CommandResponseEvent>) {
+    for event in response_ev.iter() {
+        if event.sender != CommandSender::Console {
+            continue;
+        }
+
+        match &event.response {
+            Ok(msg) => info!("{msg}"),
+            Err(err) => warn!("{err}"),
+        }
+    }
+}
+
+
+/// Sends the responses of commands that were received over the remote
+/// administration channel back to their connections.
+pub fn send_rcon_responses(
+    mut response_ev: EventReader<CommandResponseEvent>,
+    rcon: Option<ResMut<RconServer>>,
+) {
+    let Some(mut rcon) = rcon else {
+        return;
+    };
+
+    for event in response_ev.iter() {
+        let CommandSender::Remote(connection) = event.sender else {
+            continue;
+        };
+
+        match &event.response {
+            Ok(msg) => rcon.respond(connection, msg),
+            Err(err) => rcon.respond(connection, &format!("Error: {err}")),
+        }
+    }
+}
+
+
+/// Lists all registered commands.
+pub fn help_command(world: &mut World, _: &CommandSender, _: &[&str]) -> Result<String> {
+    let registry = world.resource::<CommandRegistry>();
+    let mut commands: Vec<_> = registry.iter().collect();
+    commands.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut help = "Available commands:".to_string();
+    for (_, command) in commands {
+        help.push_str(&format!("\n  {} - {}", command.usage, command.description));
+    }
+
+    Ok(help)
+}
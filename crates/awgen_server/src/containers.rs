@@ -0,0 +1,164 @@
+//! Forwards the container actions of players to the world, and keeps the
+//! contents of each open container synchronized with all of its viewers.
+
+
+use awgen_network::prelude::{
+    send_to_client, ClientMessage, ClientSocket, ContainerAction, ContainerMessage, MessageBatch, MAX_INTERACTION_REACH
+};
+use awgen_physics::prelude::{Inventory, Position};
+use awgen_world::prelude::{
+    CloseContainerEvent, ContainerBlock, ContainerClosedEvent, ContainerMoveEvent, ContainerOpenedEvent, ContainerViewers
+};
+use bevy::prelude::*;
+
+
+/// The additional distance, in meters, beyond the interaction reach that a
+/// player may move away from an open container before it is closed.
+const CONTAINER_CLOSE_MARGIN: f32 = 2.0;
+
+
+/// Forwards each container action that was received from a player to the
+/// container that the player is viewing.
+pub fn handle_container_actions(
+    mut request_ev: EventReader<ClientMessage<ContainerAction>>,
+    mut close_ev: EventWriter<CloseContainerEvent>,
+    mut move_ev: EventWriter<ContainerMoveEvent>,
+) {
+    for ev in request_ev.iter() {
+        match ev.message {
+            ContainerAction::Move {
+                from,
+                to,
+                revision,
+            } => {
+                move_ev.send(ContainerMoveEvent {
+                    viewer: ev.player,
+                    from: from as usize,
+                    to: to as usize,
+                    revision,
+                })
+            },
+            ContainerAction::Close => {
+                close_ev.send(CloseContainerEvent {
+                    viewer: ev.player,
+                })
+            },
+        }
+    }
+}
+
+
+/// Closes the container of each player that has moved too far away from it.
+pub fn close_distant_containers(
+    mut close_ev: EventWriter<CloseContainerEvent>,
+    containers: Query<(&Position, &ContainerViewers), With<ContainerBlock>>,
+    players: Query<&Position, With<ClientSocket>>,
+) {
+    let max_distance = MAX_INTERACTION_REACH + CONTAINER_CLOSE_MARGIN;
+
+    for (container_pos, viewers) in containers.iter() {
+        for viewer in viewers.iter() {
+            let Ok(player_pos) = players.get(viewer) else {
+                continue;
+            };
+
+            if player_pos.translation.distance(container_pos.translation) > max_distance {
+                close_ev.send(CloseContainerEvent {
+                    viewer,
+                });
+            }
+        }
+    }
+}
+
+
+/// Sends the contents of each container that a player opens to that player.
+pub fn sync_opened_containers(
+    mut opened_ev: EventReader<ContainerOpenedEvent>,
+    mut batch: ResMut<MessageBatch>,
+    containers: Query<(&ContainerBlock, &Inventory)>,
+    players: Query<&ClientSocket>,
+) {
+    for ev in opened_ev.iter() {
+        let Ok(socket) = players.get(ev.viewer) else {
+            continue;
+        };
+
+        // Newly created containers are not spawned until the end of the frame,
+        // so their contents are sent with the first inventory update instead.
+        let message = match containers.get(ev.container) {
+            Ok((block, inventory)) => {
+                ContainerMessage::Open {
+                    block_pos: block.block_pos,
+                    slots:     inventory.slots().to_vec(),
+                    revision:  inventory.revision(),
+                }
+            },
+            Err(_) => continue,
+        };
+
+        send_to_client(&mut batch, socket.id(), &message);
+    }
+}
+
+
+/// Sends the contents of each container whose inventory has changed to all of
+/// its viewers. Newly created containers are sent to their viewers as opened.
+#[allow(clippy::type_complexity)]
+pub fn sync_container_contents(
+    mut batch: ResMut<MessageBatch>,
+    containers: Query<
+        (
+            &ContainerBlock,
+            &Inventory,
+            &ContainerViewers,
+            ChangeTrackers<ContainerBlock>,
+        ),
+        Changed<Inventory>,
+    >,
+    players: Query<&ClientSocket>,
+) {
+    for (block, inventory, viewers, block_tracker) in containers.iter() {
+        let message = match block_tracker.is_added() {
+            true => {
+                ContainerMessage::Open {
+                    block_pos: block.block_pos,
+                    slots:     inventory.slots().to_vec(),
+                    revision:  inventory.revision(),
+                }
+            },
+            false => {
+                ContainerMessage::Update {
+                    block_pos: block.block_pos,
+                    slots:     inventory.slots().to_vec(),
+                    revision:  inventory.revision(),
+                }
+            },
+        };
+
+        for viewer in viewers.iter() {
+            if let Ok(socket) = players.get(viewer) {
+                send_to_client(&mut batch, socket.id(), &message);
+            }
+        }
+    }
+}
+
+
+/// Tells each player that is no longer viewing a container to close it.
+///
+/// The block position of the container is included, so that a player that has
+/// already opened another container does not close it by mistake.
+pub fn sync_closed_containers(
+    mut closed_ev: EventReader<ContainerClosedEvent>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<&ClientSocket>,
+) {
+    for ev in closed_ev.iter() {
+        if let Ok(socket) = players.get(ev.viewer) {
+            send_to_client(&mut batch, socket.id(), &ContainerMessage::Close {
+                block_pos: ev.block_pos,
+            });
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! Moves the reading and writing of chunk and player data files onto a
+//! dedicated I/O thread, so that autosaves and chunk loading never block the
+//! server tick on filesystem latency.
+//!
+//! Requests are sent to the I/O thread through the [`DiskIo`] resource. Writes
+//! are sent without waiting for them to finish, and writes to the same file
+//! that are still waiting to be written are coalesced into the latest one.
+//! Reads are answered with a [`DiskReadEvent`] on a later frame. A read always
+//! sees the result of every write to the same file that was requested before
+//! it.
+//!
+//! Each file is still read and written on its own. Chunk files are not grouped
+//! into larger region files.
+
+
+use anyhow::Result;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::{fs, iter};
+
+
+/// The data file that a read was requested for, which decides how the read
+/// file is handled once it arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiskReadKind {
+    /// The saved persistent entities of a chunk.
+    ChunkEntities {
+        /// The world that the chunk is within.
+        world: Entity,
+
+        /// The coordinates of the chunk.
+        chunk_coords: IVec3,
+    },
+
+    /// The saved data of a player.
+    PlayerData {
+        /// The player entity that the data belongs to.
+        player: Entity,
+
+        /// The client id of the player.
+        client_id: u64,
+    },
+}
+
+
+/// An event that is triggered for each file that has been read by the I/O
+/// thread.
+#[derive(Debug)]
+pub struct DiskReadEvent {
+    /// The data file that the read was requested for.
+    pub kind: DiskReadKind,
+
+    /// The path of the file.
+    pub path: PathBuf,
+
+    /// The text of the file, or `None` if the file does not exist.
+    pub result: Result<Option<String>>,
+}
+
+
+/// A single request to the I/O thread.
+#[derive(Debug)]
+enum DiskRequest {
+    /// Reads the file at the given path.
+    Read {
+        /// The data file that the read was requested for.
+        kind: DiskReadKind,
+
+        /// The path of the file.
+        path: PathBuf,
+    },
+
+    /// Writes the given text to the file at the given path, or removes the
+    /// file if there is no text.
+    Write {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The text to write, or `None` to remove the file.
+        contents: Option<String>,
+    },
+}
+
+
+/// A resource that sends file reads and writes to the I/O thread.
+///
+/// When this resource is dropped, all pending writes are finished before the
+/// I/O thread is stopped.
+#[derive(Resource)]
+pub struct DiskIo {
+    /// The sending end of the request channel, which is closed to stop the I/O
+    /// thread.
+    requests: Mutex<Option<Sender<DiskRequest>>>,
+
+    /// The receiving end of the read channel.
+    reads: Mutex<Receiver<DiskReadEvent>>,
+
+    /// The handle of the I/O thread.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DiskIo {
+    /// Starts a new I/O thread.
+    pub fn start() -> Self {
+        let (request_sender, request_receiver) = channel();
+        let (read_sender, read_receiver) = channel();
+
+        let thread = std::thread::Builder::new()
+            .name("Disk I/O".to_string())
+            .spawn(move || run_disk_io(request_receiver, read_sender))
+            .unwrap();
+
+        Self {
+            requests: Mutex::new(Some(request_sender)),
+            reads:    Mutex::new(read_receiver),
+            thread:   Some(thread),
+        }
+    }
+
+
+    /// Requests the file at the given path to be read. The file is returned
+    /// with a [`DiskReadEvent`] of the given kind once it has been read.
+    pub fn read(&self, kind: DiskReadKind, path: PathBuf) {
+        self.send(DiskRequest::Read {
+            kind,
+            path,
+        });
+    }
+
+
+    /// Requests the given text to be written to the file at the given path,
+    /// creating the parent directory if needed.
+    pub fn write(&self, path: PathBuf, contents: String) {
+        self.send(DiskRequest::Write {
+            path,
+            contents: Some(contents),
+        });
+    }
+
+
+    /// Requests the file at the given path to be removed, if it exists.
+    pub fn remove(&self, path: PathBuf) {
+        self.send(DiskRequest::Write {
+            path,
+            contents: None,
+        });
+    }
+
+
+    /// Sends a request to the I/O thread.
+    fn send(&self, request: DiskRequest) {
+        let requests = self.requests.lock().unwrap();
+        let sent = requests.as_ref().is_some_and(|sender| sender.send(request).is_ok());
+
+        if !sent {
+            error!("The disk I/O thread has stopped");
+        }
+    }
+}
+
+impl Default for DiskIo {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl Drop for DiskIo {
+    fn drop(&mut self) {
+        self.requests.get_mut().unwrap().take();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("The disk I/O thread has panicked");
+            }
+        }
+    }
+}
+
+
+/// Handles all requests that are sent to the I/O thread, until the request
+/// channel is closed.
+///
+/// All requests that are waiting are handled together. Reads are answered
+/// right away, while writes are held back until all waiting requests have been
+/// handled, so that only the latest write to each file is performed.
+fn run_disk_io(requests: Receiver<DiskRequest>, reads: Sender<DiskReadEvent>) {
+    while let Ok(first) = requests.recv() {
+        let writes = handle_requests(iter::once(first).chain(requests.try_iter()), &reads);
+
+        for (path, contents) in writes {
+            if let Err(err) = write_file(&path, contents.as_deref()) {
+                error!("Failed to write '{}': {err}", path.display());
+            }
+        }
+    }
+}
+
+
+/// Answers each read within the given batch of requests, and coalesces the
+/// writes into the latest write to each file, in the order that each file was
+/// first written.
+///
+/// Reads of a file with a write earlier in the batch are answered with the
+/// contents of that write, as it has not been performed yet.
+fn handle_requests<I>(
+    requests: I,
+    reads: &Sender<DiskReadEvent>,
+) -> Vec<(PathBuf, Option<String>)>
+where
+    I: Iterator<Item = DiskRequest>,
+{
+    let mut writes: Vec<(PathBuf, Option<String>)> = vec![];
+    let mut pending: HashMap<PathBuf, usize> = HashMap::new();
+
+    for request in requests {
+        match request {
+            DiskRequest::Read {
+                kind,
+                path,
+            } => {
+                let result = match pending.get(&path) {
+                    Some(index) => Ok(writes[*index].1.clone()),
+                    None => read_file(&path),
+                };
+
+                // The reads are only discarded once the server has shut
+                // down, in which case the writes must still be finished.
+                let _ = reads.send(DiskReadEvent {
+                    kind,
+                    path,
+                    result,
+                });
+            },
+            DiskRequest::Write {
+                path,
+                contents,
+            } => {
+                match pending.get(&path) {
+                    Some(index) => writes[*index].1 = contents,
+                    None => {
+                        pending.insert(path.clone(), writes.len());
+                        writes.push((path, contents));
+                    },
+                }
+            },
+        }
+    }
+
+    writes
+}
+
+
+/// Reads the text of the file at the given path, or `None` if the file does
+/// not exist.
+fn read_file(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+
+/// Writes the given text to the file at the given path, creating the parent
+/// directory if needed, or removes the file if there is no text.
+fn write_file(path: &Path, contents: Option<&str>) -> Result<()> {
+    match contents {
+        Some(contents) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(path, contents)?;
+        },
+        None => {
+            if let Err(err) = fs::remove_file(path) {
+                if err.kind() != ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+
+/// Triggers a [`DiskReadEvent`] for each file that has been read by the I/O
+/// thread since the last frame.
+pub fn receive_disk_reads(io: Res<DiskIo>, mut read_ev: EventWriter<DiskReadEvent>) {
+    let reads = io.reads.lock().unwrap();
+    read_ev.send_batch(reads.try_iter());
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates a new, empty temporary directory with the given name.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("awgen-disk-io-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+
+    /// Creates a write request for the given path.
+    fn write(path: &Path, contents: Option<&str>) -> DiskRequest {
+        DiskRequest::Write {
+            path:     path.to_path_buf(),
+            contents: contents.map(str::to_string),
+        }
+    }
+
+
+    /// Creates a read request for the given path.
+    fn read(path: &Path) -> DiskRequest {
+        DiskRequest::Read {
+            kind: DiskReadKind::PlayerData {
+                player:    Entity::from_raw(0),
+                client_id: 1,
+            },
+            path: path.to_path_buf(),
+        }
+    }
+
+
+    /// Gets the path and text of each read that has been answered.
+    fn answered(reads: &Receiver<DiskReadEvent>) -> Vec<(PathBuf, Option<String>)> {
+        reads.try_iter().map(|ev| (ev.path, ev.result.unwrap())).collect()
+    }
+
+
+    #[test]
+    fn coalesce_writes() {
+        let dir = temp_dir("coalesce");
+        let a = dir.join("a.ron");
+        let b = dir.join("b.ron");
+        let (sender, _reads) = channel();
+
+        let writes = handle_requests(
+            vec![
+                write(&a, Some("1")),
+                write(&b, Some("x")),
+                write(&a, Some("2")),
+                write(&a, None),
+                write(&a, Some("3")),
+            ]
+            .into_iter(),
+            &sender,
+        );
+
+        assert_eq!(writes, vec![
+            (a, Some("3".to_string())),
+            (b, Some("x".to_string())),
+        ]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+
+    #[test]
+    fn read_pending_write() {
+        let dir = temp_dir("pending");
+        let a = dir.join("a.ron");
+        fs::write(&a, "old").unwrap();
+        let (sender, reads) = channel();
+
+        let writes = handle_requests(
+            vec![read(&a), write(&a, Some("new")), read(&a), write(&a, None), read(&a)].into_iter(),
+            &sender,
+        );
+
+        assert_eq!(answered(&reads), vec![
+            (a.clone(), Some("old".to_string())),
+            (a.clone(), Some("new".to_string())),
+            (a.clone(), None),
+        ]);
+        assert_eq!(writes, vec![(a.clone(), None)]);
+        assert_eq!(fs::read_to_string(&a).unwrap(), "old");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+
+    #[test]
+    fn run_requests() {
+        let dir = temp_dir("run");
+        let a = dir.join("nested").join("a.ron");
+        let b = dir.join("b.ron");
+        fs::write(&b, "removed").unwrap();
+
+        let (requests, receiver) = channel();
+        let (sender, reads) = channel();
+        for request in [read(&a), write(&a, Some("1")), write(&a, Some("2")), write(&b, None)] {
+            requests.send(request).unwrap();
+        }
+        drop(requests);
+        run_disk_io(receiver, sender);
+
+        assert_eq!(answered(&reads), vec![(a.clone(), None)]);
+        assert_eq!(fs::read_to_string(&a).unwrap(), "2");
+        assert!(!b.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+
+    #[test]
+    fn finish_writes_on_drop() {
+        let dir = temp_dir("drop");
+        let a = dir.join("a.ron");
+
+        let io = DiskIo::start();
+        io.write(a.clone(), "saved".to_string());
+        drop(io);
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "saved");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+//! Validates the requests of players to break and place blocks, applies the
+//! valid requests to the world of each player, and reports the outcome of each
+//! request back to the player that sent it.
+
+
+use crate::prelude::{
+    check_block_use, handle_block_break_requests, insert_block_edit_histories, BlockEditHistory, GameRules, PositionHistory, Rewind, BREAK_TIME_TOLERANCE
+};
+use awgen_network::prelude::{
+    send_to_client, BlockEditAck, BlockEditAction, BlockEditMessage, ClientMessage, ClientSocket, HeldItem, MessageBatch, NetworkStats
+};
+use awgen_physics::prelude::{GameMode, Position};
+use awgen_world::prelude::{
+    can_edit_block, BlockBreaking, BlockHardness, BlockItem, InWorld, VoxelWorld
+};
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+
+/// Validates each block edit request that was received from a player, and
+/// applies it to the world of the player if it is valid.
+///
+/// The world must allow building within its [`GameRules`], the targeted block
+/// must be usable by the player, as checked by [`check_block_use`] after
+/// rewinding by the round trip time of the player, and the
+/// edit must follow the rules of [`can_edit_block`]. Placed blocks must match
+/// the item that the player is holding. Every request is answered with an
+/// acknowledgement, so that the client may roll back its prediction of rejected
+/// edits.
+#[allow(clippy::type_complexity)]
+pub fn handle_block_edits<BlockData>(
+    time: Res<Time>,
+    stats: Res<NetworkStats>,
+    mut request_ev: EventReader<ClientMessage<BlockEditMessage>>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(
+        &ClientSocket,
+        &Position,
+        &InWorld,
+        &GameMode,
+        Option<&HeldItem>,
+        Option<&BlockBreaking>,
+        Option<&PositionHistory>,
+    )>,
+    mut worlds: Query<(
+        &mut VoxelWorld<BlockData>,
+        Option<&CollisionLayer>,
+        Option<&GameRules>,
+        Option<&mut BlockEditHistory>,
+    )>,
+    mut commands: Commands,
+) where
+    BlockData: BlockItem + BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let now = time.elapsed_seconds_f64();
+
+    for ev in request_ev.iter() {
+        let Ok((socket, position, in_world, game_mode, held, breaking, positions)) =
+            players.get(ev.player)
+        else {
+            continue;
+        };
+
+        if socket.id() != ev.client_id {
+            continue;
+        }
+
+        let request = &ev.message;
+        let result = match worlds.get_mut(in_world.0) {
+            Ok((_, _, Some(rules), _)) if !rules.allow_building => Err("building is not allowed"),
+            Ok((mut world, layer, _, mut edits)) => {
+                let rewind =
+                    Rewind::of_client(now, &stats, socket.id(), positions, edits.as_deref());
+                let result =
+                    check_block_use(position, game_mode, layer, request.block_pos, &rewind)
+                        .and_then(|_| {
+                            let block = match &request.action {
+                                BlockEditAction::Break => {
+                                    let finished = breaking.is_some_and(|breaking| {
+                                        breaking.block_pos == request.block_pos
+                                            && breaking.is_finished(now, BREAK_TIME_TOLERANCE)
+                                    });
+
+                                    if !finished {
+                                        return Err("block has not been broken for long enough");
+                                    }
+
+                                    BlockData::default()
+                                },
+                                BlockEditAction::Place {
+                                    item,
+                                } => {
+                                    if !held.is_some_and(|held| held.is_holding(item)) {
+                                        return Err("item is not held");
+                                    }
+
+                                    BlockData::from_item(item).ok_or("item is not a block")?
+                                },
+                            };
+
+                            if !can_edit_block(&world, request.edited_pos(), block) {
+                                return Err("block cannot be replaced");
+                            }
+
+                            world.set_block_data(request.edited_pos(), block);
+                            Ok(())
+                        });
+
+                if let (Ok(_), Some(edits)) = (&result, &mut edits) {
+                    edits.record(now, request.edited_pos());
+                }
+
+                result
+            },
+            Err(_) => Err("world is not loaded"),
+        };
+
+        if result.is_ok() && request.action == BlockEditAction::Break {
+            commands.entity(ev.player).remove::<BlockBreaking>();
+        }
+
+        if let Err(reason) = result {
+            debug!(
+                "Rejected block edit at {} from client {}: {reason}",
+                request.edited_pos(),
+                socket.id()
+            );
+        }
+
+        send_to_client(&mut batch, socket.id(), &BlockEditAck {
+            sequence: request.sequence,
+            accepted: result.is_ok(),
+        });
+    }
+}
+
+
+/// A mini extension plugin that allows players to break and place blocks of
+/// the given type.
+#[derive(Debug, Clone, Default)]
+pub struct BlockEditPlugin<BlockData>
+where BlockData: BlockItem + BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for BlockEditPlugin<BlockData>
+where BlockData: BlockItem + BlockHardness + Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.add_system(insert_block_edit_histories::<BlockData>)
+            .add_system(handle_block_break_requests::<BlockData>)
+            .add_system(
+                handle_block_edits::<BlockData>.after(handle_block_break_requests::<BlockData>),
+            );
+    }
+}
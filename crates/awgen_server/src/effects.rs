@@ -0,0 +1,195 @@
+//! Replicates effects that occur within a world, such as explosions, particles,
+//! and sounds, to the players within that world.
+
+
+use crate::prelude::{observed_entity, Spectating};
+use awgen_network::prelude::{
+    send_to_client, ClientSocket, EffectMessage, MessageBatch, ParticleEvent, ParticleKind, SoundEvent, AUDIBLE_DISTANCE_PER_VOLUME
+};
+use awgen_physics::prelude::{GameMode, Position};
+use awgen_world::prelude::{ExplosionEvent, InWorld};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The horizontal distance, in meters, that a player walks between each
+/// footstep.
+const FOOTSTEP_STRIDE: f32 = 1.6;
+
+
+/// The maximum vertical distance, in meters, that a player may move within a
+/// single frame while still being considered to walk on the ground.
+const FOOTSTEP_MAX_RISE: f32 = 0.05;
+
+
+/// The number of smoke particles emitted per unit of explosion power.
+const SMOKE_PER_POWER: f32 = 6.0;
+
+
+/// An event that is triggered on the server to emit a burst of particles for
+/// all players within a world.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldParticleEvent {
+    /// The world entity that the particles are emitted within.
+    pub world: Entity,
+
+    /// The particles to emit.
+    pub particles: ParticleEvent,
+}
+
+
+/// An event that is triggered on the server to play a sound at a position
+/// within a world, for all players that are within audible range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldSoundEvent {
+    /// The world entity that the sound is played within.
+    pub world: Entity,
+
+    /// The id of the sound to play, as a dot separated path.
+    pub sound_id: String,
+
+    /// The position that the sound is played at, in world space.
+    pub position: Vec3,
+
+    /// The volume of the sound, where 1 is the natural volume of the sound.
+    /// Louder sounds may be heard from further away.
+    pub volume: f32,
+}
+
+impl WorldSoundEvent {
+    /// Gets the distance, in meters, that this sound may be heard from.
+    pub fn audible_distance(&self) -> f32 {
+        self.volume.max(0.0) * AUDIBLE_DISTANCE_PER_VOLUME
+    }
+}
+
+
+/// Sends each explosion to all players that are within the world that the
+/// explosion occurred in, and emits a cloud of smoke where it occurred.
+pub fn replicate_explosions(
+    mut explosion_ev: EventReader<ExplosionEvent>,
+    mut particle_ev: EventWriter<WorldParticleEvent>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&ClientSocket, &InWorld)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "effect").entered();
+
+    for ev in explosion_ev.iter() {
+        let message = EffectMessage::Explosion {
+            center: ev.center,
+            power:  ev.power,
+        };
+
+        for (socket, in_world) in players.iter() {
+            if in_world.0 == ev.world {
+                send_to_client(&mut batch, socket.id(), &message);
+            }
+        }
+
+        particle_ev.send(WorldParticleEvent {
+            world:     ev.world,
+            particles: ParticleEvent {
+                kind:     ParticleKind::ExplosionSmoke,
+                position: ev.center,
+                count:    (ev.power * SMOKE_PER_POWER).clamp(1.0, u16::MAX as f32) as u16,
+            },
+        });
+    }
+}
+
+
+/// Sends each particle burst to all players that are within the world that the
+/// particles were emitted in.
+pub fn replicate_particles(
+    mut particle_ev: EventReader<WorldParticleEvent>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&ClientSocket, &InWorld)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "particles").entered();
+
+    for ev in particle_ev.iter() {
+        let message = EffectMessage::Particles(ev.particles.clone());
+        for (socket, in_world) in players.iter() {
+            if in_world.0 == ev.world {
+                send_to_client(&mut batch, socket.id(), &message);
+            }
+        }
+    }
+}
+
+
+/// Sends each sound to all players that are within audible range of it.
+///
+/// Players hear sounds from the position of the player that they observe, so
+/// spectators hear the sounds around the player that they are spectating.
+pub fn replicate_sounds(
+    mut sound_ev: EventReader<WorldSoundEvent>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(Entity, &ClientSocket, Option<&Spectating>)>,
+    observed: Query<(&InWorld, &Position)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "sound").entered();
+
+    for ev in sound_ev.iter() {
+        let range = ev.audible_distance();
+        let message = EffectMessage::Sound(SoundEvent {
+            sound_id: ev.sound_id.clone(),
+            position: ev.position,
+            volume:   ev.volume,
+        });
+
+        for (player, socket, spectating) in players.iter() {
+            let Ok((in_world, position)) = observed.get(observed_entity(player, spectating)) else {
+                continue;
+            };
+
+            let audible = in_world.0 == ev.world
+                && position.translation.distance_squared(ev.position) <= range * range;
+            if audible {
+                send_to_client(&mut batch, socket.id(), &message);
+            }
+        }
+    }
+}
+
+
+/// Emits a puff of dust beneath each walking player once for every stride that
+/// they walk. Players that are able to fly never leave footsteps.
+pub fn emit_footstep_dust(
+    mut strides: Local<HashMap<Entity, (Vec3, f32)>>,
+    mut particle_ev: EventWriter<WorldParticleEvent>,
+    players: Query<(Entity, &Position, &InWorld, &GameMode), With<ClientSocket>>,
+) {
+    strides.retain(|entity, _| players.contains(*entity));
+
+    for (entity, position, in_world, game_mode) in players.iter() {
+        let current = position.translation;
+        let (last, walked) = strides.entry(entity).or_insert((current, 0.0));
+
+        let offset = current - *last;
+        *last = current;
+
+        if game_mode.can_fly() || offset.y.abs() > FOOTSTEP_MAX_RISE {
+            *walked = 0.0;
+            continue;
+        }
+
+        *walked += Vec2::new(offset.x, offset.z).length();
+        if *walked < FOOTSTEP_STRIDE {
+            continue;
+        }
+
+        *walked -= FOOTSTEP_STRIDE;
+        particle_ev.send(WorldParticleEvent {
+            world:     in_world.0,
+            particles: ParticleEvent {
+                kind:     ParticleKind::FootstepDust,
+                position: current,
+                count:    4,
+            },
+        });
+    }
+}
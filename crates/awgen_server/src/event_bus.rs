@@ -0,0 +1,360 @@
+//! A unified event bus that mirrors selected Bevy events into serializable
+//! event records, so that they may be consumed by subsystems that live outside
+//! of the ECS, such as scripts, network replication, and replay recording.
+//!
+//! Each consumer subscribes to the bus with its own [`EventFilter`], and
+//! receives a queue of every matching record. Consumers are expected to drain
+//! their queue regularly, usually once per frame.
+//!
+//! New event types are mirrored onto the bus by adding a [`GameEvent`] variant
+//! and a system that publishes it.
+
+
+use crate::prelude::{PlayerTransferredEvent, WeatherChangedEvent, WorldConfig};
+use awgen_network::prelude::{ClientSocket, Weather};
+use awgen_world::prelude::LoadChunkEvent;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_renet::renet::ServerEvent;
+use serde::{Deserialize, Serialize};
+
+
+/// A serializable record of a game event that occurred within the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// A chunk was requested to be loaded.
+    ChunkLoaded {
+        /// The name of the world that the chunk is within.
+        world: String,
+
+        /// The coordinates of the chunk.
+        chunk_coords: IVec3,
+    },
+
+    /// A client connected to the server.
+    PlayerJoined {
+        /// The id of the client.
+        client_id: u64,
+    },
+
+    /// A client disconnected from the server.
+    PlayerLeft {
+        /// The id of the client.
+        client_id: u64,
+    },
+
+    /// A player was moved into another world.
+    PlayerTransferred {
+        /// The id of the client.
+        client_id: u64,
+
+        /// The name of the world that the player was previously in, if any.
+        from: Option<String>,
+
+        /// The name of the world that the player was moved into.
+        to: String,
+    },
+
+    /// The weather of a world changed.
+    WeatherChanged {
+        /// The name of the world.
+        world: String,
+
+        /// The new weather state.
+        weather: Weather,
+    },
+}
+
+impl GameEvent {
+    /// Gets the kind of this event.
+    pub fn kind(&self) -> GameEventKind {
+        match self {
+            GameEvent::ChunkLoaded {
+                ..
+            } => GameEventKind::ChunkLoaded,
+            GameEvent::PlayerJoined {
+                ..
+            } => GameEventKind::PlayerJoined,
+            GameEvent::PlayerLeft {
+                ..
+            } => GameEventKind::PlayerLeft,
+            GameEvent::PlayerTransferred {
+                ..
+            } => GameEventKind::PlayerTransferred,
+            GameEvent::WeatherChanged {
+                ..
+            } => GameEventKind::WeatherChanged,
+        }
+    }
+
+
+    /// Gets the name of the world that this event occurred within, if it is
+    /// tied to a single world.
+    pub fn world(&self) -> Option<&str> {
+        match self {
+            GameEvent::ChunkLoaded {
+                world,
+                ..
+            } => Some(world),
+            GameEvent::PlayerTransferred {
+                to,
+                ..
+            } => Some(to),
+            GameEvent::WeatherChanged {
+                world,
+                ..
+            } => Some(world),
+            _ => None,
+        }
+    }
+}
+
+
+/// The kinds of events that may be published to the event bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameEventKind {
+    /// See [`GameEvent::ChunkLoaded`].
+    ChunkLoaded,
+
+    /// See [`GameEvent::PlayerJoined`].
+    PlayerJoined,
+
+    /// See [`GameEvent::PlayerLeft`].
+    PlayerLeft,
+
+    /// See [`GameEvent::PlayerTransferred`].
+    PlayerTransferred,
+
+    /// See [`GameEvent::WeatherChanged`].
+    WeatherChanged,
+}
+
+
+/// A game event, alongside the time that it occurred.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// The time that the event occurred, in seconds since the server started.
+    pub time: f64,
+
+    /// The event that occurred.
+    pub event: GameEvent,
+}
+
+
+/// A filter that determines which events are received by an event bus
+/// consumer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// The kinds of events to receive. If not set, all kinds are received.
+    kinds: Option<HashSet<GameEventKind>>,
+
+    /// The name of the world to receive events from. If set, events that are
+    /// not tied to this world are not received.
+    world: Option<String>,
+}
+
+impl EventFilter {
+    /// Creates a new event filter that accepts all events.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+
+    /// Limits this filter to only accept the given kinds of events.
+    pub fn with_kinds<I>(mut self, kinds: I) -> Self
+    where I: IntoIterator<Item = GameEventKind> {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+
+    /// Limits this filter to only accept events that occurred within the world
+    /// with the given name.
+    pub fn in_world<N>(mut self, world: N) -> Self
+    where N: Into<String> {
+        self.world = Some(world.into());
+        self
+    }
+
+
+    /// Checks whether or not the given event is accepted by this filter.
+    pub fn accepts(&self, event: &GameEvent) -> bool {
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&event.kind()) {
+                return false;
+            }
+        }
+
+        match &self.world {
+            Some(world) => event.world() == Some(world.as_str()),
+            None => true,
+        }
+    }
+}
+
+
+/// A handle to a consumer that has subscribed to the event bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventConsumerId(usize);
+
+
+/// A consumer that has subscribed to the event bus.
+#[derive(Debug, Clone)]
+struct EventConsumer {
+    /// The filter that determines which events this consumer receives.
+    filter: EventFilter,
+
+    /// The events that this consumer has received, but not yet drained.
+    queue: Vec<EventRecord>,
+}
+
+
+/// The event bus, which collects mirrored game events for each subscribed
+/// consumer.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct EventBus {
+    /// The subscribed consumers, indexed by their consumer id. Consumers that
+    /// have unsubscribed are left empty, so that ids remain stable.
+    consumers: Vec<Option<EventConsumer>>,
+}
+
+impl EventBus {
+    /// Subscribes a new consumer to this event bus, which receives all events
+    /// accepted by the given filter.
+    pub fn subscribe(&mut self, filter: EventFilter) -> EventConsumerId {
+        self.consumers.push(Some(EventConsumer {
+            filter,
+            queue: vec![],
+        }));
+        EventConsumerId(self.consumers.len() - 1)
+    }
+
+
+    /// Unsubscribes the given consumer from this event bus, discarding any
+    /// events that were not yet drained.
+    pub fn unsubscribe(&mut self, consumer: EventConsumerId) {
+        if let Some(slot) = self.consumers.get_mut(consumer.0) {
+            *slot = None;
+        }
+    }
+
+
+    /// Publishes the given event record to all consumers that accept it.
+    pub fn publish(&mut self, record: EventRecord) {
+        for consumer in self.consumers.iter_mut().flatten() {
+            if consumer.filter.accepts(&record.event) {
+                consumer.queue.push(record.clone());
+            }
+        }
+    }
+
+
+    /// Removes and returns all events that have been received by the given
+    /// consumer, in the order that they were published.
+    pub fn drain(&mut self, consumer: EventConsumerId) -> Vec<EventRecord> {
+        match self.consumers.get_mut(consumer.0) {
+            Some(Some(consumer)) => std::mem::take(&mut consumer.queue),
+            _ => vec![],
+        }
+    }
+}
+
+
+/// Mirrors chunk load events onto the event bus.
+pub fn mirror_chunk_loads(
+    time: Res<Time>,
+    mut bus: ResMut<EventBus>,
+    mut load_chunk_ev: EventReader<LoadChunkEvent>,
+    worlds: Query<&WorldConfig>,
+) {
+    for ev in load_chunk_ev.iter() {
+        let Ok(config) = worlds.get(ev.world) else {
+            continue;
+        };
+
+        bus.publish(EventRecord {
+            time:  time.elapsed_seconds_f64(),
+            event: GameEvent::ChunkLoaded {
+                world:        config.name.clone(),
+                chunk_coords: ev.chunk_coords,
+            },
+        });
+    }
+}
+
+
+/// Mirrors client connection events onto the event bus.
+pub fn mirror_connections(
+    time: Res<Time>,
+    mut bus: ResMut<EventBus>,
+    mut server_events: EventReader<ServerEvent>,
+) {
+    for event in server_events.iter() {
+        let event = match event {
+            ServerEvent::ClientConnected(client_id, _) => {
+                GameEvent::PlayerJoined {
+                    client_id: *client_id,
+                }
+            },
+            ServerEvent::ClientDisconnected(client_id) => {
+                GameEvent::PlayerLeft {
+                    client_id: *client_id,
+                }
+            },
+        };
+
+        bus.publish(EventRecord {
+            time: time.elapsed_seconds_f64(),
+            event,
+        });
+    }
+}
+
+
+/// Mirrors player transfer events onto the event bus.
+pub fn mirror_player_transfers(
+    time: Res<Time>,
+    mut bus: ResMut<EventBus>,
+    mut transferred_ev: EventReader<PlayerTransferredEvent>,
+    players: Query<&ClientSocket>,
+    worlds: Query<&WorldConfig>,
+) {
+    for ev in transferred_ev.iter() {
+        let (Ok(socket), Ok(to)) = (players.get(ev.player), worlds.get(ev.to)) else {
+            continue;
+        };
+
+        let from = ev.from.and_then(|world| worlds.get(world).ok());
+        bus.publish(EventRecord {
+            time:  time.elapsed_seconds_f64(),
+            event: GameEvent::PlayerTransferred {
+                client_id: socket.id(),
+                from:      from.map(|config| config.name.clone()),
+                to:        to.name.clone(),
+            },
+        });
+    }
+}
+
+
+/// Mirrors weather change events onto the event bus.
+pub fn mirror_weather_changes(
+    time: Res<Time>,
+    mut bus: ResMut<EventBus>,
+    mut changed_ev: EventReader<WeatherChangedEvent>,
+    worlds: Query<&WorldConfig>,
+) {
+    for ev in changed_ev.iter() {
+        let Ok(config) = worlds.get(ev.world) else {
+            continue;
+        };
+
+        bus.publish(EventRecord {
+            time:  time.elapsed_seconds_f64(),
+            event: GameEvent::WeatherChanged {
+                world:   config.name.clone(),
+                weather: ev.weather,
+            },
+        });
+    }
+}
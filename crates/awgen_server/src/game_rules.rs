@@ -0,0 +1,307 @@
+//! The gameplay rules of each hosted world, which allow a single server to run
+//! several mini-game arenas that each play differently.
+//!
+//! The rules of a world are stored within its [`GameRules`] component, and
+//! are saved as part of the world metadata whenever they change. Each rule
+//! may also be read and written by its name, which is how the `gamerule`
+//! command and scripts access them.
+
+
+use crate::prelude::{CommandSender, HostedWorlds, WorldConfig, WorldDataDirectory, WorldMeta};
+use anyhow::{bail, Result};
+use awgen_world::prelude::{EntityCounts, InWorld};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The names of all game rules, in the order that they are listed.
+pub const GAME_RULES: [&str; 3] = ["allow_building", "mob_spawning", "tick_speed"];
+
+
+/// The largest tick speed multiplier that a world may run at.
+pub const MAX_TICK_SPEED: f32 = 10.0;
+
+
+/// The set of gameplay rules that are applied to a single hosted world.
+#[derive(Debug, Clone, PartialEq, Reflect, FromReflect, Component, Serialize, Deserialize)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct GameRules {
+    /// Whether or not players are allowed to place or break blocks within
+    /// this world.
+    pub allow_building: bool,
+
+    /// Whether or not mobs are allowed to spawn within this world. This is
+    /// enforced through [`EntityCounts::try_reserve`].
+    pub mob_spawning: bool,
+
+    /// The multiplier of the rate at which the time and weather of this world
+    /// advance, where 1.0 is the normal rate and 0.0 freezes the world.
+    pub tick_speed: f32,
+}
+
+impl GameRules {
+    /// Gets the value of the game rule with the given name, formatted as text.
+    ///
+    /// Returns `None` if there is no game rule with the given name.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "allow_building" => self.allow_building.to_string(),
+            "mob_spawning" => self.mob_spawning.to_string(),
+            "tick_speed" => self.tick_speed.to_string(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+
+    /// Parses the given text and assigns it to the game rule with the given
+    /// name.
+    ///
+    /// Returns an error if there is no game rule with the given name, or if
+    /// the value is not valid for that rule.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "allow_building" => self.allow_building = value.parse()?,
+            "mob_spawning" => self.mob_spawning = value.parse()?,
+            "tick_speed" => {
+                let speed: f32 = value.parse()?;
+                if !(0.0..=MAX_TICK_SPEED).contains(&speed) {
+                    bail!("The tick speed must be between 0 and {MAX_TICK_SPEED}");
+                }
+                self.tick_speed = speed;
+            },
+            _ => bail!("Unknown game rule: {name}"),
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            allow_building: true,
+            mob_spawning:   true,
+            tick_speed:     1.0,
+        }
+    }
+}
+
+
+/// Copies the game rules of each world into its metadata whenever they are
+/// modified, and saves the metadata.
+pub fn save_game_rules(
+    directory: Res<WorldDataDirectory>,
+    mut worlds: Query<(&GameRules, &mut WorldMeta), Changed<GameRules>>,
+) {
+    for (rules, mut meta) in worlds.iter_mut() {
+        if meta.rules.as_ref() == Some(rules) {
+            continue;
+        }
+
+        meta.rules = Some(rules.clone());
+        if let Err(err) = directory.save_meta(&meta.name, &meta) {
+            error!(
+                "Failed to save the game rules of world '{}': {err}",
+                meta.name
+            );
+        }
+    }
+}
+
+
+/// Applies the `mob_spawning` game rule of each world to the entity caps
+/// whenever the game rules of that world are modified.
+///
+/// This system does nothing if the entity caps are not used.
+pub fn apply_mob_spawning_rules(
+    counts: Option<ResMut<EntityCounts>>,
+    worlds: Query<(Entity, &GameRules), Changed<GameRules>>,
+    removed: RemovedComponents<GameRules>,
+) {
+    let Some(mut counts) = counts else {
+        return;
+    };
+
+    for world in removed.iter() {
+        counts.set_mob_spawning(world, true);
+    }
+
+    for (world, rules) in worlds.iter() {
+        if counts.mob_spawning(world) != rules.mob_spawning {
+            counts.set_mob_spawning(world, rules.mob_spawning);
+        }
+    }
+}
+
+
+/// Changes or reports the game rules of a world.
+///
+/// Usage: `gamerule [<rule> [value] [world]]`
+///
+/// If no world is given, the world of the sending player is used. The rule
+/// of another world may be queried with `gamerule <rule> <world>`, as a
+/// second argument that names a hosted world is read as the world rather
+/// than as a value.
+pub fn gamerule_command(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String> {
+    let hosted = world.resource::<HostedWorlds>();
+    let (rule, value, world_name) = match args {
+        [] => (None, None, None),
+        [rule] => (Some(*rule), None, None),
+        [rule, name] if hosted.get(name).is_some() => (Some(*rule), None, Some(*name)),
+        [rule, value] => (Some(*rule), Some(*value), None),
+        [rule, value, name] => (Some(*rule), Some(*value), Some(*name)),
+        _ => bail!("Usage: gamerule [<rule> [value] [world]]"),
+    };
+
+    let target = match world_name {
+        Some(name) => hosted.get(name),
+        None => {
+            match sender {
+                CommandSender::Player(player) => world.get::<InWorld>(*player).map(|w| w.0),
+                CommandSender::Console | CommandSender::Remote(_) => hosted.default_world(),
+            }
+        },
+    };
+
+    let Some(target) = target else {
+        bail!("Unknown world");
+    };
+
+    let name = world.get::<WorldConfig>(target).map(|c| c.name.clone()).unwrap_or_default();
+    let Some(mut rules) = world.get_mut::<GameRules>(target) else {
+        bail!("World '{name}' does not have game rules");
+    };
+
+    match (rule, value) {
+        (None, _) => {
+            let lines: Vec<String> = GAME_RULES
+                .iter()
+                .map(|rule| format!("{rule} = {}", rules.get(rule).unwrap_or_default()))
+                .collect();
+            Ok(format!(
+                "Game rules of world '{name}':\n{}",
+                lines.join("\n")
+            ))
+        },
+        (Some(rule), None) => {
+            let Some(value) = rules.get(rule) else {
+                bail!("Unknown game rule: {rule}");
+            };
+            Ok(format!("Game rule {rule} of world '{name}' is {value}"))
+        },
+        (Some(rule), Some(value)) => {
+            rules.set(rule, value)?;
+            Ok(format!("Set game rule {rule} of world '{name}' to {value}"))
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::update_hosted_worlds;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn set_rules() {
+        let mut rules = GameRules::default();
+        rules.set("allow_building", "false").unwrap();
+        rules.set("mob_spawning", "false").unwrap();
+        rules.set("tick_speed", "2.5").unwrap();
+
+        assert_eq!(rules, GameRules {
+            allow_building: false,
+            mob_spawning:   false,
+            tick_speed:     2.5,
+        });
+        assert_eq!(rules.get("tick_speed"), Some("2.5".to_string()));
+        assert_eq!(rules.get("allow_pvp"), None);
+    }
+
+
+    #[test]
+    fn reject_invalid_rules() {
+        let mut rules = GameRules::default();
+        assert!(rules.set("allow_pvp", "true").is_err());
+        assert!(rules.set("allow_building", "yes").is_err());
+        assert!(rules.set("tick_speed", "fast").is_err());
+        assert_eq!(rules, GameRules::default());
+    }
+
+
+    #[test]
+    fn tick_speed_bounds() {
+        let mut rules = GameRules::default();
+        assert!(rules.set("tick_speed", "0").is_ok());
+        assert!(rules.set("tick_speed", "10").is_ok());
+        assert!(rules.set("tick_speed", "-0.5").is_err());
+        assert!(rules.set("tick_speed", "10.5").is_err());
+        assert!(rules.set("tick_speed", "NaN").is_err());
+        assert_eq!(rules.tick_speed, MAX_TICK_SPEED);
+    }
+
+
+    /// Creates a new app hosting the worlds "lobby" and "arena", where the
+    /// lobby is the default world.
+    fn setup() -> App {
+        let mut app = App::new();
+        app.init_resource::<HostedWorlds>()
+            .init_resource::<EntityCounts>()
+            .add_system(update_hosted_worlds)
+            .add_system(apply_mob_spawning_rules);
+
+        for name in ["lobby", "arena"] {
+            app.world.spawn((WorldConfig::new(name, "flat"), GameRules::default()));
+            app.update();
+        }
+        app
+    }
+
+
+    /// Runs the gamerule command as the console with the given arguments.
+    fn gamerule(app: &mut App, args: &[&str]) -> Result<String> {
+        gamerule_command(&mut app.world, &CommandSender::Console, args)
+    }
+
+
+    #[test]
+    fn query_rule_of_world() {
+        let mut app = setup();
+        gamerule(&mut app, &["tick_speed", "3", "arena"]).unwrap();
+
+        assert_eq!(
+            gamerule(&mut app, &["tick_speed"]).unwrap(),
+            "Game rule tick_speed of world 'lobby' is 1"
+        );
+        assert_eq!(
+            gamerule(&mut app, &["tick_speed", "arena"]).unwrap(),
+            "Game rule tick_speed of world 'arena' is 3"
+        );
+        assert!(gamerule(&mut app, &["tick_speed", "3", "nowhere"]).is_err());
+        assert!(gamerule(&mut app, &["tick_speed", "3", "arena", "extra"]).is_err());
+    }
+
+
+    #[test]
+    fn mob_spawning_rule() {
+        let mut app = setup();
+        let arena = app.world.resource::<HostedWorlds>().get("arena").unwrap();
+
+        gamerule(&mut app, &["mob_spawning", "false", "arena"]).unwrap();
+        app.update();
+        assert!(!app.world.resource::<EntityCounts>().mob_spawning(arena));
+
+        gamerule(&mut app, &["mob_spawning", "true", "arena"]).unwrap();
+        app.update();
+        assert!(app.world.resource::<EntityCounts>().mob_spawning(arena));
+    }
+}
@@ -0,0 +1,236 @@
+//! Named text displays, or holograms, that float in the world, such as signs,
+//! leaderboards, and tutorial hints within mini-games.
+//!
+//! Holograms are persistent entities that are saved alongside the chunk that
+//! they are within. They may be spawned by scripts using the [`SpawnHologram`]
+//! command, or by the `hologram` command.
+
+
+use crate::prelude::{CommandSender, HostedWorlds};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{Replicated, TeamColor, TextDisplay};
+use awgen_physics::prelude::Position;
+use awgen_world::prelude::{InWorld, Persistent};
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+
+/// The character that separates lines of text within the `hologram` command.
+pub const HOLOGRAM_LINE_BREAK: char = '|';
+
+
+/// The unique name of a hologram, which is used to find it again later on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect, Component)]
+#[reflect(Component)]
+pub struct Hologram {
+    /// The name of the hologram.
+    pub name: String,
+}
+
+
+/// A command that spawns a hologram with the given text display into a voxel
+/// world, replacing any hologram with the same name.
+#[derive(Debug, Clone)]
+pub struct SpawnHologram {
+    /// The unique name of the hologram.
+    pub name: String,
+
+    /// The voxel world to spawn the hologram into.
+    pub world: Entity,
+
+    /// The position of the center of the text.
+    pub position: Vec3,
+
+    /// The text that is shown.
+    pub display: TextDisplay,
+}
+
+impl Command for SpawnHologram {
+    fn write(self, world: &mut World) {
+        if let Some(existing) = find_hologram(world, &self.name) {
+            world.despawn(existing);
+        }
+
+        world.spawn((
+            Hologram {
+                name: self.name,
+            },
+            self.display,
+            Position {
+                translation: self.position,
+                ..default()
+            },
+            InWorld(self.world),
+            Persistent,
+            Replicated,
+        ));
+    }
+}
+
+
+/// Finds the hologram with the given name, if it exists.
+pub fn find_hologram(world: &mut World, name: &str) -> Option<Entity> {
+    world
+        .query::<(Entity, &Hologram)>()
+        .iter(world)
+        .find(|(_, hologram)| hologram.name == name)
+        .map(|(entity, _)| entity)
+}
+
+
+/// Replicates each text display that was restored from disk, as only the
+/// persistent components of an entity are saved.
+pub fn replicate_restored_text_displays(
+    displays: Query<Entity, (With<TextDisplay>, Without<Replicated>)>,
+    mut commands: Commands,
+) {
+    for entity in displays.iter() {
+        commands.entity(entity).insert(Replicated);
+    }
+}
+
+
+/// Parses a text color, which is either the name of a team color or a
+/// hexadecimal color, such as `#ff8800`.
+fn parse_color(value: &str) -> Result<[u8; 3]> {
+    if let Some(color) = TeamColor::from_name(value) {
+        return Ok(color.rgb());
+    }
+
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.is_ascii() {
+        bail!("Unknown color: {value}");
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    match (channel(0), channel(2), channel(4)) {
+        (Ok(r), Ok(g), Ok(b)) => Ok([r, g, b]),
+        _ => bail!("Unknown color: {value}"),
+    }
+}
+
+
+/// Parses the text of a hologram from the remaining arguments of a command,
+/// where each line is separated by [`HOLOGRAM_LINE_BREAK`].
+fn parse_text(args: &[&str]) -> String {
+    args.join(" ")
+        .split(HOLOGRAM_LINE_BREAK)
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+/// Manages holograms.
+///
+/// Usage:
+/// - `hologram list`
+/// - `hologram add <name> <x> <y> <z> <text>`
+/// - `hologram remove <name>`
+/// - `hologram move <name> <x> <y> <z>`
+/// - `hologram set <name> text <text>`
+/// - `hologram set <name> color <color>`
+/// - `hologram set <name> scale <scale>`
+/// - `hologram set <name> seethrough <true|false>`
+///
+/// Lines of text are separated by `|`. New holograms are added to the world
+/// of the sending player, or to the default world.
+pub fn hologram_command(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String> {
+    match args {
+        ["list"] => {
+            let mut lines: Vec<String> = world
+                .query::<(&Hologram, &Position)>()
+                .iter(world)
+                .map(|(hologram, pos)| format!("{} at {}", hologram.name, pos.translation))
+                .collect();
+            lines.sort();
+
+            match lines.is_empty() {
+                true => Ok("There are no holograms".to_string()),
+                false => Ok(format!("Holograms:\n{}", lines.join("\n"))),
+            }
+        },
+        ["add", name, x, y, z, text @ ..] if !text.is_empty() => {
+            if find_hologram(world, name).is_some() {
+                bail!("Hologram '{name}' already exists");
+            }
+
+            let position = Vec3::new(x.parse()?, y.parse()?, z.parse()?);
+            let target = match sender {
+                CommandSender::Player(player) => world.get::<InWorld>(*player).map(|w| w.0),
+                CommandSender::Console | CommandSender::Remote(_) => {
+                    world.resource::<HostedWorlds>().default_world()
+                },
+            };
+
+            let Some(target) = target else {
+                bail!("Unknown world");
+            };
+
+            SpawnHologram {
+                name: name.to_string(),
+                world: target,
+                position,
+                display: TextDisplay {
+                    text: parse_text(text),
+                    ..default()
+                },
+            }
+            .write(world);
+            Ok(format!("Added hologram '{name}' at {position}"))
+        },
+        ["remove", name] => {
+            let Some(entity) = find_hologram(world, name) else {
+                bail!("Unknown hologram: {name}");
+            };
+
+            world.despawn(entity);
+            Ok(format!("Removed hologram '{name}'"))
+        },
+        ["move", name, x, y, z] => {
+            let position = Vec3::new(x.parse()?, y.parse()?, z.parse()?);
+            let Some(entity) = find_hologram(world, name) else {
+                bail!("Unknown hologram: {name}");
+            };
+
+            if let Some(mut pos) = world.get_mut::<Position>(entity) {
+                pos.translation = position;
+            }
+            Ok(format!("Moved hologram '{name}' to {position}"))
+        },
+        ["set", name, property @ ("text" | "color" | "scale" | "seethrough"), value @ ..]
+            if !value.is_empty() =>
+        {
+            let Some(entity) = find_hologram(world, name) else {
+                bail!("Unknown hologram: {name}");
+            };
+
+            let Some(mut display) = world.get_mut::<TextDisplay>(entity) else {
+                bail!("Unknown hologram: {name}");
+            };
+
+            match *property {
+                "text" => display.text = parse_text(value),
+                "color" => display.color = parse_color(value[0])?,
+                "scale" => {
+                    let scale: f32 = value[0].parse()?;
+                    if scale <= 0.0 {
+                        bail!("The scale of a hologram must be positive");
+                    }
+                    display.scale = scale;
+                },
+                _ => display.see_through = value[0].parse()?,
+            }
+
+            Ok(format!(
+                "Set {property} of hologram '{name}' to {}",
+                value.join(" ")
+            ))
+        },
+        _ => bail!("Usage: hologram <list|add|remove|move|set> ..."),
+    }
+}
@@ -0,0 +1,124 @@
+//! Tracks the hotbar slot that each player has selected, and updates the item
+//! that they are holding as their selection or hotbar changes.
+//!
+//! The hotbar of a player is stored as the [`Inventory`] component of their
+//! player entity.
+
+
+use crate::prelude::{command_target, CommandSender};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{
+    send_to_client, ClientMessage, ClientSocket, HeldItem, HeldItemMessage, MessageBatch, SelectHotbarSlot, HOTBAR_SLOTS
+};
+use awgen_physics::prelude::{Inventory, ItemStack};
+use awgen_world::prelude::MAX_STACK_SIZE;
+use bevy::prelude::*;
+
+
+/// The hotbar slot that a player has selected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Component)]
+pub struct SelectedSlot(pub u8);
+
+
+/// Changes the selected hotbar slot of each player that requests it. Requests
+/// for slots outside of the hotbar are ignored.
+pub fn select_hotbar_slots(
+    mut select_ev: EventReader<ClientMessage<SelectHotbarSlot>>,
+    mut players: Query<(&ClientSocket, &mut SelectedSlot)>,
+) {
+    for ev in select_ev.iter() {
+        let Ok((socket, mut selected)) = players.get_mut(ev.player) else {
+            continue;
+        };
+
+        if socket.id() != ev.client_id {
+            continue;
+        }
+
+        let slot = ev.message.slot;
+        if slot as usize >= HOTBAR_SLOTS {
+            debug!(
+                "Rejected selection of hotbar slot {slot} from client {}",
+                socket.id()
+            );
+            continue;
+        }
+
+        if selected.0 != slot {
+            selected.0 = slot;
+        }
+    }
+}
+
+
+/// Updates the held item of each player whose hotbar or selected slot has
+/// changed, and reports it to the client of the player.
+#[allow(clippy::type_complexity)]
+pub fn update_held_items(
+    mut batch: ResMut<MessageBatch>,
+    players: Query<
+        (
+            Entity,
+            &ClientSocket,
+            &Inventory,
+            &SelectedSlot,
+            Option<&HeldItem>,
+        ),
+        Or<(Changed<Inventory>, Changed<SelectedSlot>)>,
+    >,
+    mut commands: Commands,
+) {
+    for (entity, socket, hotbar, selected, held) in players.iter() {
+        let stack = hotbar.get(selected.0 as usize).cloned().unwrap_or_default();
+        if !held.is_some_and(|held| held.0 == stack) {
+            commands.entity(entity).insert(HeldItem(stack.clone()));
+        }
+
+        send_to_client(&mut batch, socket.id(), &HeldItemMessage {
+            slot: selected.0,
+            item: stack,
+        });
+    }
+}
+
+
+/// Gives a stack of items to a player, placing it within the first hotbar slot
+/// that is empty or that holds the same item with enough room.
+///
+/// Usage: `give <item> [count] [client id]`
+pub fn give_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    let Some(item) = args.first() else {
+        bail!("Usage: give <item> [count] [client id]");
+    };
+
+    let count = match args.get(1) {
+        Some(count) => count.parse()?,
+        None => 1,
+    };
+
+    if count == 0 || count > MAX_STACK_SIZE {
+        bail!("The count must be between 1 and {MAX_STACK_SIZE}");
+    }
+
+    let target = command_target(world, sender, args.get(2))?;
+    let client_id = world.get::<ClientSocket>(target).map_or(0, |socket| socket.id());
+    let Some(mut hotbar) = world.get_mut::<Inventory>(target) else {
+        bail!("Player {client_id} does not have a hotbar");
+    };
+
+    let slot = hotbar.slots().iter().position(|stack| {
+        stack.is_empty() || (stack.item == *item && stack.count + count <= MAX_STACK_SIZE)
+    });
+
+    let Some(slot) = slot else {
+        bail!("The hotbar of player {client_id} is full");
+    };
+
+    let total = match hotbar.slots()[slot].is_empty() {
+        true => count,
+        false => hotbar.slots()[slot].count + count,
+    };
+
+    hotbar.set(slot, ItemStack::new(*item, total));
+    Ok(format!("Gave {count} {item} to player {client_id}"))
+}
@@ -0,0 +1,378 @@
+//! Sends on-screen messages, such as boss bars, titles, and action bar
+//! messages, to players.
+//!
+//! Boss bars are stored within the [`BossBars`] resource, and are kept in sync
+//! with every player within their audience, including players that join the
+//! audience later on. Titles and action bar messages are sent once, by
+//! triggering a [`HudEvent`].
+//!
+//! Both may be changed by the `bossbar` and `title` commands, or directly
+//! through the resource and event.
+
+
+use crate::prelude::{score_holders, CommandSender, Teams};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{
+    send_to_client, BossBar, ClientSocket, HudMessage, MessageBatch, PlayerName, TeamColor, TitleTiming, DEFAULT_ACTION_BAR_SECONDS
+};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// The players that an on-screen message is shown to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HudAudience {
+    /// Every player on the server.
+    #[default]
+    All,
+
+    /// The players with the given names.
+    Players(Vec<String>),
+
+    /// The members of the team with the given name.
+    Team(String),
+}
+
+impl HudAudience {
+    /// Gets whether or not the player with the given name is within this
+    /// audience.
+    pub fn includes(&self, player: &str, teams: &Teams) -> bool {
+        match self {
+            HudAudience::All => true,
+            HudAudience::Players(players) => players.iter().any(|p| p == player),
+            HudAudience::Team(team) => teams.team_of(player) == Some(team.as_str()),
+        }
+    }
+}
+
+
+/// An event that may be triggered on the server to send an on-screen message,
+/// such as a title or action bar message, to an audience.
+///
+/// Boss bars that are sent this way are not shown to players that join the
+/// audience later on. Use the [`BossBars`] resource for that instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HudEvent {
+    /// The players to send the message to.
+    pub audience: HudAudience,
+
+    /// The message to send.
+    pub message: HudMessage,
+}
+
+impl HudEvent {
+    /// Creates an event that shows the given title and subtitle to the given
+    /// audience, with the default timing.
+    pub fn title(audience: HudAudience, title: &str, subtitle: &str) -> Self {
+        Self {
+            audience,
+            message: HudMessage::Title {
+                title:    title.to_string(),
+                subtitle: subtitle.to_string(),
+                timing:   TitleTiming::default(),
+            },
+        }
+    }
+
+
+    /// Creates an event that shows the given action bar message to the given
+    /// audience, for the default number of seconds.
+    pub fn action_bar(audience: HudAudience, text: &str) -> Self {
+        Self {
+            audience,
+            message: HudMessage::ActionBar {
+                text:    text.to_string(),
+                seconds: DEFAULT_ACTION_BAR_SECONDS,
+            },
+        }
+    }
+}
+
+
+/// A boss bar on the server, and the players that it is shown to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerBossBar {
+    /// The boss bar that is shown.
+    pub bar: BossBar,
+
+    /// The players that the boss bar is shown to.
+    pub audience: HudAudience,
+}
+
+
+/// A server-side resource that stores every boss bar, by id.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct BossBars {
+    /// Each boss bar, by id.
+    bars: HashMap<String, ServerBossBar>,
+}
+
+impl BossBars {
+    /// Adds a new, full, white boss bar with the given id and title, which is
+    /// shown to every player.
+    ///
+    /// Fails if the id is not a single word, or if a boss bar with the same id
+    /// already exists.
+    pub fn add(&mut self, id: &str, title: &str) -> Result<()> {
+        if id.is_empty() || id.contains(char::is_whitespace) {
+            bail!("Boss bar ids must be a single word");
+        }
+
+        if self.bars.contains_key(id) {
+            bail!("Boss bar '{id}' already exists");
+        }
+
+        self.bars.insert(id.to_string(), ServerBossBar {
+            bar:      BossBar {
+                title:    title.to_string(),
+                progress: 1.0,
+                color:    TeamColor::White,
+            },
+            audience: HudAudience::All,
+        });
+        Ok(())
+    }
+
+
+    /// Removes the boss bar with the given id.
+    pub fn remove(&mut self, id: &str) -> Result<()> {
+        if self.bars.remove(id).is_none() {
+            bail!("Unknown boss bar: {id}");
+        }
+
+        Ok(())
+    }
+
+
+    /// Gets the boss bar with the given id, if it exists.
+    pub fn get(&self, id: &str) -> Option<&ServerBossBar> {
+        self.bars.get(id)
+    }
+
+
+    /// Gets a mutable reference to the boss bar with the given id, if it
+    /// exists.
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut ServerBossBar> {
+        self.bars.get_mut(id)
+    }
+
+
+    /// Gets an iterator over each boss bar, alongside its id.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ServerBossBar)> {
+        self.bars.iter().map(|(id, bar)| (id.as_str(), bar))
+    }
+}
+
+
+/// Sends the message of each triggered HUD event to every player within its
+/// audience.
+pub fn send_hud_events(
+    teams: Res<Teams>,
+    mut hud_ev: EventReader<HudEvent>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&ClientSocket, &PlayerName)>,
+) {
+    for ev in hud_ev.iter() {
+        for (socket, name) in players.iter() {
+            if ev.audience.includes(&name.0, &teams) {
+                send_to_client(&mut batch, socket.id(), &ev.message);
+            }
+        }
+    }
+}
+
+
+/// Shows, updates, and removes the boss bars of each player whenever the boss
+/// bars, the teams, or the connected players change.
+pub fn sync_boss_bars(
+    bars: Res<BossBars>,
+    teams: Res<Teams>,
+    mut sent: Local<HashMap<u64, HashMap<String, BossBar>>>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&ClientSocket, &PlayerName)>,
+    new_players: Query<&ClientSocket, Added<ClientSocket>>,
+) {
+    if !bars.is_changed() && !teams.is_changed() && new_players.is_empty() {
+        return;
+    }
+
+    for socket in new_players.iter() {
+        sent.remove(&socket.id());
+    }
+
+    let mut now_sent = HashMap::default();
+    for (socket, name) in players.iter() {
+        let mut previous = sent.remove(&socket.id()).unwrap_or_default();
+        let mut shown = HashMap::default();
+
+        for (id, server_bar) in bars.iter() {
+            if !server_bar.audience.includes(&name.0, &teams) {
+                continue;
+            }
+
+            if previous.remove(id).as_ref() != Some(&server_bar.bar) {
+                send_to_client(&mut batch, socket.id(), &HudMessage::SetBossBar {
+                    id:  id.to_string(),
+                    bar: server_bar.bar.clone(),
+                });
+            }
+
+            shown.insert(id.to_string(), server_bar.bar.clone());
+        }
+
+        for id in previous.into_keys() {
+            send_to_client(&mut batch, socket.id(), &HudMessage::RemoveBossBar(id));
+        }
+
+        now_sent.insert(socket.id(), shown);
+    }
+
+    *sent = now_sent;
+}
+
+
+/// Gets the audience targeted by a command. `@a` targets every player, now
+/// and later, `team:<name>` targets the members of a team, and any other
+/// argument targets the players with the matching names.
+fn command_audience(
+    world: &mut World,
+    sender: &CommandSender,
+    target: &str,
+) -> Result<HudAudience> {
+    if target == "@a" {
+        return Ok(HudAudience::All);
+    }
+
+    if let Some(team) = target.strip_prefix("team:") {
+        if world.resource::<Teams>().team(team).is_none() {
+            bail!("Unknown team: {team}");
+        }
+        return Ok(HudAudience::Team(team.to_string()));
+    }
+
+    Ok(HudAudience::Players(score_holders(world, sender, target)?))
+}
+
+
+/// Shows titles and action bar messages to players.
+///
+/// Usage:
+/// - `title <audience> show <title> [| <subtitle>]`
+/// - `title <audience> actionbar <text>`
+/// - `title <audience> clear`
+pub fn title_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    let (target, ev) = match args {
+        [target, "show", text @ ..] if !text.is_empty() => {
+            let text = text.join(" ");
+            let (title, subtitle) = text.split_once('|').unwrap_or((&text, ""));
+            let audience = command_audience(world, sender, target)?;
+            (
+                target,
+                HudEvent::title(audience, title.trim(), subtitle.trim()),
+            )
+        },
+        [target, "actionbar", text @ ..] if !text.is_empty() => {
+            let audience = command_audience(world, sender, target)?;
+            (target, HudEvent::action_bar(audience, &text.join(" ")))
+        },
+        [target, "clear"] => {
+            let audience = command_audience(world, sender, target)?;
+            (target, HudEvent {
+                audience,
+                message: HudMessage::ClearTitle,
+            })
+        },
+        _ => bail!("Usage: title <audience> <show|actionbar|clear> [text]"),
+    };
+
+    world.send_event(ev);
+    Ok(format!("Sent title to {target}"))
+}
+
+
+/// Manages the boss bars that are shown to players.
+///
+/// Usage:
+/// - `bossbar list`
+/// - `bossbar add <id> [title]`
+/// - `bossbar remove <id>`
+/// - `bossbar set <id> title <title>`
+/// - `bossbar set <id> progress <0.0-1.0>`
+/// - `bossbar set <id> color <color>`
+/// - `bossbar set <id> audience <audience>`
+pub fn bossbar_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    match args {
+        ["list"] => {
+            let bars = world.resource::<BossBars>();
+            let mut lines: Vec<String> = bars
+                .iter()
+                .map(|(id, server_bar)| {
+                    format!(
+                        "{id} ({}, {:.0}%, {})",
+                        server_bar.bar.title,
+                        server_bar.bar.progress * 100.0,
+                        server_bar.bar.color.name()
+                    )
+                })
+                .collect();
+            lines.sort();
+
+            match lines.is_empty() {
+                true => Ok("There are no boss bars".to_string()),
+                false => Ok(format!("Boss bars:\n{}", lines.join("\n"))),
+            }
+        },
+        ["add", id, title @ ..] => {
+            let title = match title.is_empty() {
+                true => id.to_string(),
+                false => title.join(" "),
+            };
+
+            world.resource_mut::<BossBars>().add(id, &title)?;
+            Ok(format!("Added boss bar '{id}'"))
+        },
+        ["remove", id] => {
+            world.resource_mut::<BossBars>().remove(id)?;
+            Ok(format!("Removed boss bar '{id}'"))
+        },
+        ["set", id, "audience", target] => {
+            let audience = command_audience(world, sender, target)?;
+            let mut bars = world.resource_mut::<BossBars>();
+            let Some(server_bar) = bars.get_mut(id) else {
+                bail!("Unknown boss bar: {id}");
+            };
+
+            server_bar.audience = audience;
+            Ok(format!("Showing boss bar '{id}' to {target}"))
+        },
+        ["set", id, property @ ("title" | "progress" | "color"), value @ ..]
+            if !value.is_empty() =>
+        {
+            let value = value.join(" ");
+            let mut bars = world.resource_mut::<BossBars>();
+            let Some(server_bar) = bars.get_mut(id) else {
+                bail!("Unknown boss bar: {id}");
+            };
+
+            match *property {
+                "title" => server_bar.bar.title = value.clone(),
+                "progress" => {
+                    let progress: f32 = value.parse()?;
+                    if !(0.0..=1.0).contains(&progress) {
+                        bail!("The progress of a boss bar must be between 0 and 1");
+                    }
+                    server_bar.bar.progress = progress;
+                },
+                _ => {
+                    let Some(color) = TeamColor::from_name(&value) else {
+                        bail!("Unknown color: {value}");
+                    };
+                    server_bar.bar.color = color;
+                },
+            }
+
+            Ok(format!("Set {property} of boss bar '{id}' to {value}"))
+        },
+        _ => bail!("Usage: bossbar <list|add|remove|set> ..."),
+    }
+}
@@ -0,0 +1,102 @@
+//! Detects players that have stopped providing input, marking them as AFK and
+//! eventually disconnecting them in order to free up slots on busy servers.
+
+
+use crate::prelude::{Permissions, CONNECTION_LOG_TARGET};
+use awgen_network::prelude::{ClientSocket, InputActivity, KickClient, PendingKicks};
+use bevy::prelude::*;
+use std::time::Duration;
+
+
+/// The permission node that exempts a player from being disconnected for
+/// being idle.
+pub const IDLE_EXEMPT_PERMISSION: &str = "awgen.idle.exempt";
+
+
+/// The idle timeouts that are applied to all connected players.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct IdleTimeouts {
+    /// The amount of time without input before a player is marked as AFK. If
+    /// `None`, players are never marked as AFK.
+    pub afk_after: Option<Duration>,
+
+    /// The amount of time without input before a player is disconnected. If
+    /// `None`, idle players are never disconnected.
+    pub disconnect_after: Option<Duration>,
+}
+
+impl IdleTimeouts {
+    /// Creates a new set of idle timeouts, measured in minutes.
+    pub fn from_minutes(afk_after: Option<u64>, disconnect_after: Option<u64>) -> Self {
+        Self {
+            afk_after:        afk_after.map(|m| Duration::from_secs(m * 60)),
+            disconnect_after: disconnect_after.map(|m| Duration::from_secs(m * 60)),
+        }
+    }
+}
+
+impl Default for IdleTimeouts {
+    fn default() -> Self {
+        Self::from_minutes(Some(5), None)
+    }
+}
+
+
+/// A marker component that indicates that a player is currently AFK.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Afk;
+
+
+/// Marks players as AFK, or no longer AFK, based on their input activity and
+/// disconnects players that have been idle for too long.
+///
+/// Players with the [`IDLE_EXEMPT_PERMISSION`] are never disconnected.
+#[allow(clippy::type_complexity)]
+pub fn detect_idle_players(
+    time: Res<Time>,
+    timeouts: Res<IdleTimeouts>,
+    pending: Res<PendingKicks>,
+    mut kick_ev: EventWriter<KickClient>,
+    players: Query<(
+        Entity,
+        &ClientSocket,
+        &InputActivity,
+        Option<&Permissions>,
+        Option<&Afk>,
+    )>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for (entity, socket, activity, permissions, afk) in players.iter() {
+        let idle = Duration::from_secs_f64(activity.idle_seconds(now));
+        let client_id = socket.id();
+
+        let is_afk = matches!(timeouts.afk_after, Some(limit) if idle >= limit);
+        match (is_afk, afk.is_some()) {
+            (true, false) => {
+                info!("Player {client_id} is now AFK");
+                commands.entity(entity).insert(Afk);
+            },
+            (false, true) => {
+                info!("Player {client_id} is no longer AFK");
+                commands.entity(entity).remove::<Afk>();
+            },
+            _ => {},
+        }
+
+        let exempt = matches!(permissions, Some(p) if p.has(IDLE_EXEMPT_PERMISSION));
+        if let Some(limit) = timeouts.disconnect_after {
+            if idle >= limit && !exempt && !pending.contains(client_id) {
+                info!(
+                    target: CONNECTION_LOG_TARGET,
+                    client_id,
+                    idle_secs = idle.as_secs(),
+                    "Disconnecting idle client"
+                );
+                kick_ev.send(KickClient::new(socket, "Disconnected for being idle"));
+            }
+        }
+    }
+}
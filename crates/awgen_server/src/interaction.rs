@@ -0,0 +1,164 @@
+//! Validates the requests of players to use blocks, and replicates the outcome
+//! of each block use to all players within the same world.
+
+
+use crate::prelude::{BlockEditHistory, PositionHistory, Rewind};
+use awgen_math::prelude::GridRaycast;
+use awgen_network::prelude::{
+    send_to_client, BlockUseMessage, ClientMessage, ClientSocket, EffectMessage, MessageBatch, NetworkStats, MAX_INTERACTION_REACH, PLAYER_EYE_HEIGHT
+};
+use awgen_physics::prelude::{GameMode, Position};
+use awgen_world::prelude::{BlockUseEvent, BlockUsedEvent, InWorld, InteractionOutcome};
+use awgen_world_collision::prelude::CollisionLayer;
+use bevy::prelude::*;
+
+
+/// The distance, in meters, from the center of a block to its furthest corner.
+const BLOCK_HALF_DIAGONAL: f32 = 0.87;
+
+
+/// Checks whether the given player is able to use the block at the given
+/// position, returning the reason if it is not.
+///
+/// The player must be in a game mode that allows interactions, the block must
+/// be within reach of the eyes of the player, and, if the world has a
+/// collision layer, no other solid block may obstruct the line of sight from
+/// the eyes of the player to the block.
+///
+/// To compensate for latency, the block may instead be within reach of where
+/// the player was at the rewound time, and blocks that were edited after the
+/// rewound time never obstruct the line of sight.
+pub fn check_block_use(
+    position: &Position,
+    game_mode: &GameMode,
+    layer: Option<&CollisionLayer>,
+    block_pos: IVec3,
+    rewind: &Rewind,
+) -> Result<(), &'static str> {
+    if !game_mode.can_interact() {
+        return Err("game mode cannot interact");
+    }
+
+    let current = check_block_reach(position.translation, layer, block_pos, rewind);
+    match rewind.translation {
+        Some(translation) if current.is_err() => {
+            check_block_reach(translation, layer, block_pos, rewind).or(current)
+        },
+        _ => current,
+    }
+}
+
+
+/// Checks whether the block at the given position is within reach of a player
+/// standing at the given translation, and is not obstructed by any solid block
+/// that the player has seen.
+fn check_block_reach(
+    translation: Vec3,
+    layer: Option<&CollisionLayer>,
+    block_pos: IVec3,
+    rewind: &Rewind,
+) -> Result<(), &'static str> {
+    let eye = translation + Vec3::Y * PLAYER_EYE_HEIGHT;
+    let offset = block_pos.as_vec3() + Vec3::splat(0.5) - eye;
+    let distance = offset.length();
+    if distance > MAX_INTERACTION_REACH + BLOCK_HALF_DIAGONAL {
+        return Err("out of reach");
+    }
+
+    let Some(layer) = layer else {
+        return Ok(());
+    };
+
+    let obstructed = GridRaycast::new(eye, offset, distance)
+        .find(|step| layer.is_solid(step.cell) && !rewind.is_unseen(step.cell))
+        .is_some_and(|hit| hit.cell != block_pos && hit.distance < distance - BLOCK_HALF_DIAGONAL);
+    if obstructed {
+        return Err("line of sight obstructed");
+    }
+
+    Ok(())
+}
+
+
+/// Validates each block use request that was received from a player, and uses
+/// the block within the world of the player if the request is valid.
+///
+/// Each request is rewound by the round trip time of the player before it is
+/// validated.
+#[allow(clippy::type_complexity)]
+pub fn validate_block_use(
+    time: Res<Time>,
+    stats: Res<NetworkStats>,
+    mut request_ev: EventReader<ClientMessage<BlockUseMessage>>,
+    mut use_ev: EventWriter<BlockUseEvent>,
+    players: Query<(
+        &ClientSocket,
+        &Position,
+        &InWorld,
+        &GameMode,
+        Option<&PositionHistory>,
+    )>,
+    worlds: Query<(Option<&CollisionLayer>, Option<&BlockEditHistory>)>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for ev in request_ev.iter() {
+        let Ok((socket, position, in_world, game_mode, positions)) = players.get(ev.player) else {
+            continue;
+        };
+
+        let request = ev.message;
+        let (layer, edits) = worlds.get(in_world.0).unwrap_or((None, None));
+        let rewind = Rewind::of_client(now, &stats, socket.id(), positions, edits);
+        if let Err(reason) = check_block_use(position, game_mode, layer, request.block_pos, &rewind)
+        {
+            debug!(
+                "Rejected block use at {} from client {}: {reason}",
+                request.block_pos,
+                socket.id()
+            );
+            continue;
+        }
+
+        use_ev.send(BlockUseEvent {
+            world:     in_world.0,
+            user:      ev.player,
+            block_pos: request.block_pos,
+            face:      request.face,
+        });
+    }
+}
+
+
+/// Sends each block use that had an effect to all players that are within the
+/// world that the block was used in.
+pub fn replicate_block_use(
+    mut used_ev: EventReader<BlockUsedEvent>,
+    mut batch: ResMut<MessageBatch>,
+    players: Query<(&ClientSocket, &InWorld)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "block_used").entered();
+
+    for ev in used_ev.iter() {
+        if ev.outcome == InteractionOutcome::Pass {
+            continue;
+        }
+
+        let Ok((user, _)) = players.get(ev.user) else {
+            continue;
+        };
+
+        let message = EffectMessage::BlockUsed {
+            client_id:        user.id(),
+            block_pos:        ev.block_pos,
+            opened_container: ev.outcome == InteractionOutcome::OpenContainer,
+        };
+
+        for (socket, in_world) in players.iter() {
+            if in_world.0 == ev.world {
+                send_to_client(&mut batch, socket.id(), &message);
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Compensates for the latency of players when validating their block
+//! interactions.
+//!
+//! A player sends an interaction request based on the world as they last saw
+//! it, which is behind the server by about their round trip time. The server
+//! keeps a short history of the position of each player and of the blocks
+//! edited within each world, so that requests may be validated against the
+//! state that the player saw, rather than only the current state.
+
+
+use awgen_network::prelude::{ClientSocket, NetworkStats};
+use awgen_physics::prelude::Position;
+use awgen_world::prelude::VoxelWorld;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+
+/// The maximum number of seconds that an interaction request may be rewound
+/// by, regardless of the round trip time of the player.
+pub const MAX_REWIND: f32 = 0.5;
+
+
+/// The number of seconds of history that are kept for each player and world.
+const HISTORY_DURATION: f64 = 1.0;
+
+
+/// A server-side component for a player that stores the position of the player
+/// on each recent physics tick.
+#[derive(Debug, Clone, Default, Component)]
+pub struct PositionHistory {
+    /// The time and translation of each recorded frame, from oldest to newest.
+    frames: VecDeque<(f64, Vec3)>,
+}
+
+impl PositionHistory {
+    /// Records the translation of the player at the given time, and forgets
+    /// frames that are too old to be rewound to.
+    pub fn record(&mut self, time: f64, translation: Vec3) {
+        self.frames.push_back((time, translation));
+
+        while self.frames.front().is_some_and(|(t, _)| *t < time - HISTORY_DURATION) {
+            self.frames.pop_front();
+        }
+    }
+
+
+    /// Gets the translation of the player at the given time, interpolated
+    /// between the two surrounding frames, or `None` if no frames have been
+    /// recorded.
+    ///
+    /// Times before the oldest frame use the oldest frame.
+    pub fn rewind(&self, time: f64) -> Option<Vec3> {
+        let index = self.frames.partition_point(|(t, _)| *t <= time);
+
+        let (next_time, next) = match self.frames.get(index) {
+            Some(next) => *next,
+            None => return self.frames.back().map(|(_, last)| *last),
+        };
+
+        let Some((last_time, last)) = index.checked_sub(1).and_then(|i| self.frames.get(i)) else {
+            return Some(next);
+        };
+
+        let delta = ((time - last_time) / (next_time - last_time)) as f32;
+        Some(last.lerp(next, delta))
+    }
+}
+
+
+/// A server-side component for a world that stores the position of each block
+/// that was recently edited by a player, and the time that it was edited at.
+#[derive(Debug, Clone, Default, Component)]
+pub struct BlockEditHistory {
+    /// The time and position of each edit, from oldest to newest.
+    edits: VecDeque<(f64, IVec3)>,
+}
+
+impl BlockEditHistory {
+    /// Records an edit of the block at the given position at the given time,
+    /// and forgets edits that are too old to be rewound to.
+    pub fn record(&mut self, time: f64, block_pos: IVec3) {
+        self.edits.push_back((time, block_pos));
+
+        while self.edits.front().is_some_and(|(t, _)| *t < time - HISTORY_DURATION) {
+            self.edits.pop_front();
+        }
+    }
+
+
+    /// Gets whether or not the block at the given position has been edited
+    /// since the given time.
+    pub fn edited_since(&self, block_pos: IVec3, time: f64) -> bool {
+        self.edits
+            .iter()
+            .rev()
+            .take_while(|(t, _)| *t > time)
+            .any(|(_, pos)| *pos == block_pos)
+    }
+}
+
+
+/// The state of the server that a player saw when they sent an interaction
+/// request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rewind<'a> {
+    /// The time that the request was rewound to.
+    pub time: f64,
+
+    /// The translation of the player at the rewound time, if known.
+    pub translation: Option<Vec3>,
+
+    /// The recent block edits of the world of the player, if known.
+    pub edits: Option<&'a BlockEditHistory>,
+}
+
+impl<'a> Rewind<'a> {
+    /// Rewinds by the given round trip time, in milliseconds, from the given
+    /// time, using the given position history of the player and block edit
+    /// history of their world.
+    pub fn new(
+        now: f64,
+        rtt: f32,
+        positions: Option<&PositionHistory>,
+        edits: Option<&'a BlockEditHistory>,
+    ) -> Self {
+        let time = now - (rtt / 1000.0).clamp(0.0, MAX_REWIND) as f64;
+
+        Self {
+            time,
+            translation: positions.and_then(|positions| positions.rewind(time)),
+            edits,
+        }
+    }
+
+
+    /// Rewinds the request of the player with the given client id by their
+    /// current round trip time.
+    pub fn of_client(
+        now: f64,
+        stats: &NetworkStats,
+        client_id: u64,
+        positions: Option<&PositionHistory>,
+        edits: Option<&'a BlockEditHistory>,
+    ) -> Self {
+        let rtt = stats.client(client_id).map_or(0.0, |stats| stats.rtt);
+        Self::new(now, rtt, positions, edits)
+    }
+
+
+    /// Gets whether or not the block at the given position was edited after
+    /// the rewound time, and so was not yet seen by the player.
+    pub fn is_unseen(&self, block_pos: IVec3) -> bool {
+        self.edits.is_some_and(|edits| edits.edited_since(block_pos, self.time))
+    }
+}
+
+
+/// Adds a position history to each newly connected player.
+pub fn insert_position_histories(
+    players: Query<Entity, (With<ClientSocket>, Without<PositionHistory>)>,
+    mut commands: Commands,
+) {
+    for player in players.iter() {
+        commands.entity(player).insert(PositionHistory::default());
+    }
+}
+
+
+/// Adds a block edit history to each voxel world containing a block data layer
+/// of the given type that does not have one yet.
+pub fn insert_block_edit_histories<BlockData>(
+    worlds: Query<Entity, (With<VoxelWorld<BlockData>>, Without<BlockEditHistory>)>,
+    mut commands: Commands,
+) where
+    BlockData: Default + Copy + Send + Sync + 'static,
+{
+    for world in worlds.iter() {
+        commands.entity(world).insert(BlockEditHistory::default());
+    }
+}
+
+
+/// Records the position of each player at the end of each physics tick.
+pub fn record_position_histories(
+    time: Res<Time>,
+    mut players: Query<(&Position, &mut PositionHistory)>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (position, mut history) in players.iter_mut() {
+        history.record(now, position.translation);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates a position history with a frame every quarter of a second,
+    /// moving one meter along the x axis each frame.
+    fn history() -> PositionHistory {
+        let mut history = PositionHistory::default();
+        for frame in 0..4 {
+            history.record(10.0 + frame as f64 * 0.25, Vec3::X * frame as f32);
+        }
+        history
+    }
+
+
+    #[test]
+    fn rewind_empty_history() {
+        assert_eq!(PositionHistory::default().rewind(10.0), None);
+    }
+
+
+    #[test]
+    fn rewind_exact_frame() {
+        let history = history();
+        assert_eq!(history.rewind(10.0), Some(Vec3::ZERO));
+        assert_eq!(history.rewind(10.25), Some(Vec3::X));
+        assert_eq!(history.rewind(10.75), Some(Vec3::X * 3.0));
+    }
+
+
+    #[test]
+    fn rewind_between_frames() {
+        let history = history();
+        assert_eq!(history.rewind(10.375), Some(Vec3::X * 1.5));
+        assert_eq!(history.rewind(10.625), Some(Vec3::X * 2.5));
+    }
+
+
+    #[test]
+    fn rewind_outside_history() {
+        let history = history();
+        assert_eq!(history.rewind(5.0), Some(Vec3::ZERO));
+        assert_eq!(history.rewind(20.0), Some(Vec3::X * 3.0));
+    }
+
+
+    #[test]
+    fn rewind_is_clamped() {
+        let history = history();
+
+        let rewind = Rewind::new(10.75, 250.0, Some(&history), None);
+        assert_eq!(rewind.time, 10.5);
+        assert_eq!(rewind.translation, Some(Vec3::X * 2.0));
+
+        let rewind = Rewind::new(10.75, 5000.0, Some(&history), None);
+        assert_eq!(rewind.time, 10.75 - MAX_REWIND as f64);
+        assert_eq!(rewind.translation, Some(Vec3::X));
+
+        let rewind = Rewind::new(10.75, -100.0, None, None);
+        assert_eq!(rewind.time, 10.75);
+        assert_eq!(rewind.translation, None);
+    }
+
+
+    #[test]
+    fn prune_old_frames() {
+        let mut history = history();
+        history.record(11.5, Vec3::X * 6.0);
+
+        assert_eq!(history.frames.len(), 3);
+        assert_eq!(history.rewind(10.0), Some(Vec3::X * 2.0));
+    }
+
+
+    #[test]
+    fn edited_since() {
+        let mut edits = BlockEditHistory::default();
+        edits.record(10.0, IVec3::ZERO);
+        edits.record(10.5, IVec3::X);
+
+        assert!(edits.edited_since(IVec3::ZERO, 9.75));
+        assert!(!edits.edited_since(IVec3::ZERO, 10.0));
+        assert!(edits.edited_since(IVec3::X, 10.25));
+        assert!(!edits.edited_since(IVec3::X, 10.5));
+        assert!(!edits.edited_since(IVec3::Y, 0.0));
+        assert!(!BlockEditHistory::default().edited_since(IVec3::ZERO, 0.0));
+    }
+
+
+    #[test]
+    fn prune_old_edits() {
+        let mut edits = BlockEditHistory::default();
+        edits.record(10.0, IVec3::ZERO);
+        edits.record(11.5, IVec3::X);
+
+        assert_eq!(edits.edits.len(), 1);
+        assert!(!edits.edited_since(IVec3::ZERO, 0.0));
+
+        let rewind = Rewind::new(11.75, 500.0, None, Some(&edits));
+        assert!(rewind.is_unseen(IVec3::X));
+        assert!(!rewind.is_unseen(IVec3::ZERO));
+    }
+}
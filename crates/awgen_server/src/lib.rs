@@ -7,14 +7,91 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+pub mod bans;
+pub mod breaking;
+pub mod chat;
+pub mod commands;
+pub mod containers;
+pub mod disk_io;
+pub mod editing;
+pub mod effects;
+pub mod event_bus;
+pub mod game_rules;
+pub mod holograms;
+pub mod hotbar;
+pub mod hud;
+pub mod idle;
+pub mod interaction;
+pub mod lag_compensation;
+pub mod logging;
+pub mod map_export;
+pub mod mods;
+pub mod movement;
+pub mod permissions;
+pub mod persistence;
+pub mod players;
+pub mod pregen;
+pub mod replication;
+pub mod save_format;
+pub mod scoreboard;
+pub mod selectors;
+pub mod spectate;
+pub mod tags;
+pub mod teams;
+pub mod testing;
+pub mod weather;
+pub mod worlds;
+
+
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
+    pub use super::bans::*;
+    pub use super::breaking::*;
+    pub use super::chat::*;
+    pub use super::commands::*;
+    pub use super::containers::*;
+    pub use super::disk_io::*;
+    pub use super::editing::*;
+    pub use super::effects::*;
+    pub use super::event_bus::*;
+    pub use super::game_rules::*;
+    pub use super::holograms::*;
+    pub use super::hotbar::*;
+    pub use super::hud::*;
+    pub use super::idle::*;
+    pub use super::interaction::*;
+    pub use super::lag_compensation::*;
+    pub use super::logging::*;
+    pub use super::map_export::*;
+    pub use super::mods::*;
+    pub use super::movement::*;
+    pub use super::permissions::*;
+    pub use super::persistence::*;
+    pub use super::players::*;
+    pub use super::pregen::*;
+    pub use super::replication::*;
+    pub use super::save_format::*;
+    pub use super::scoreboard::*;
+    pub use super::selectors::*;
+    pub use super::spectate::*;
+    pub use super::tags::*;
+    pub use super::teams::*;
+    pub use super::testing::*;
+    pub use super::weather::*;
+    pub use super::worlds::*;
     pub use super::*;
 }
 
 
+use awgen_network::prelude::TextDisplay;
+use awgen_physics::prelude::apply_velocity;
+use awgen_world::prelude::{
+    BlockUseEvent, BlockUsedEvent, CloseContainerEvent, ContainerClosedEvent, ContainerMoveEvent, ContainerOpenedEvent, ExplosionEvent, PersistentComponentExt
+};
 use bevy::ecs::schedule::ReportExecutionOrderAmbiguities;
 use bevy::prelude::*;
+use prelude::*;
+use std::path::PathBuf;
 
 
 /// The Awgen server plugin implementation.
@@ -22,6 +99,22 @@ use bevy::prelude::*;
 pub struct ServerPlugin {
     /// Whether or not this plugin is loaded in debug mode.
     debug: bool,
+
+    /// The worlds to host when the server starts.
+    worlds: Vec<WorldConfig>,
+
+    /// Whether or not commands may be entered into the server console.
+    console: bool,
+
+    /// The radius, in chunks, around the spawn point of the default world to
+    /// pre-generate when the server starts.
+    pregen_radius: Option<u16>,
+
+    /// The idle timeouts that are applied to connected players.
+    idle_timeouts: IdleTimeouts,
+
+    /// The directory to load mods from, if mods are enabled.
+    mods_directory: Option<PathBuf>,
 }
 
 impl ServerPlugin {
@@ -29,10 +122,52 @@ impl ServerPlugin {
     pub fn debug() -> Self {
         Self {
             debug: true,
+            ..default()
         }
     }
 
 
+    /// Adds a world to be hosted by this server when it starts.
+    ///
+    /// The first world that is added is used as the default world that new
+    /// players are placed in when they join.
+    pub fn with_world(mut self, config: WorldConfig) -> Self {
+        self.worlds.push(config);
+        self
+    }
+
+
+    /// Enables reading commands from the standard input stream of this
+    /// process.
+    pub fn with_console(mut self) -> Self {
+        self.console = true;
+        self
+    }
+
+
+    /// Pre-generates all chunks within the given radius, in chunks, around the
+    /// spawn point of the default world when the server starts.
+    pub fn with_pregen(mut self, radius: u16) -> Self {
+        self.pregen_radius = Some(radius);
+        self
+    }
+
+
+    /// Replaces the idle timeouts that are applied to connected players.
+    pub fn with_idle_timeouts(mut self, timeouts: IdleTimeouts) -> Self {
+        self.idle_timeouts = timeouts;
+        self
+    }
+
+
+    /// Loads all mods within the given directory when the server starts.
+    pub fn with_mods<P>(mut self, directory: P) -> Self
+    where P: Into<PathBuf> {
+        self.mods_directory = Some(directory.into());
+        self
+    }
+
+
     /// Gets whether or not this server is loaded in debug mode.
     pub fn is_debug(&self) -> bool {
         self.debug
@@ -44,5 +179,274 @@ impl Plugin for ServerPlugin {
         if self.is_debug() {
             app.insert_resource(ReportExecutionOrderAmbiguities);
         }
+
+        app.register_type::<WorldConfig>()
+            .register_type::<WorldSpawn>()
+            .register_type::<WorldWeather>()
+            .register_type::<GameRules>()
+            .register_type::<RespawnPoint>()
+            .register_type::<Permissions>()
+            .register_type::<Afk>()
+            .register_type::<[u8; 3]>()
+            .register_persistent_component::<Hologram>()
+            .register_persistent_component::<TextDisplay>()
+            .insert_resource(self.idle_timeouts.clone())
+            .init_resource::<HostedWorlds>()
+            .init_resource::<CommandRegistry>()
+            .init_resource::<PlayerDataDirectory>()
+            .init_resource::<BanListFile>()
+            .init_resource::<WorldDataDirectory>()
+            .init_resource::<PregenQueue>()
+            .init_resource::<EntityPersistence>()
+            .init_resource::<DiskIo>()
+            .init_resource::<EventBus>()
+            .init_resource::<Scoreboard>()
+            .init_resource::<Teams>()
+            .init_resource::<BossBars>()
+            .add_event::<DiskReadEvent>()
+            .add_event::<PlayerTransferredEvent>()
+            .add_event::<WeatherChangedEvent>()
+            .add_event::<PrecipitationEvent>()
+            .add_event::<ServerCommandEvent>()
+            .add_event::<CommandResponseEvent>()
+            .add_event::<ExplosionEvent>()
+            .add_event::<WorldParticleEvent>()
+            .add_event::<WorldSoundEvent>()
+            .add_event::<ChatEvent>()
+            .add_event::<HudEvent>()
+            .add_event::<BlockUseEvent>()
+            .add_event::<BlockUsedEvent>()
+            .add_event::<ContainerOpenedEvent>()
+            .add_event::<ContainerClosedEvent>()
+            .add_event::<CloseContainerEvent>()
+            .add_event::<ContainerMoveEvent>()
+            .add_startup_system(load_ban_list)
+            .add_system(log_connections)
+            .add_system(save_ban_list)
+            .add_system(update_hosted_worlds)
+            .add_system(load_world_meta)
+            .add_system(save_world_spawns)
+            .add_system(save_game_rules)
+            .add_system(apply_mob_spawning_rules)
+            .add_system(autosave_world_meta)
+            .add_system_to_stage("tick", advance_world_time)
+            .add_system(load_world_weather.after(load_world_meta))
+            .add_system(update_world_weather)
+            .add_system(save_world_weather.after(update_world_weather))
+            .add_system(sync_world_weather.after(update_world_weather))
+            .add_system(mirror_weather_changes.after(update_world_weather))
+            .add_system(execute_commands)
+            .add_system(print_console_responses)
+            .add_system(read_rcon_commands)
+            .add_system(send_rcon_responses)
+            .add_system(receive_disk_reads)
+            .add_system(load_player_data.after(update_hosted_worlds))
+            .add_system(apply_player_data.after(receive_disk_reads))
+            .add_system(save_player_data)
+            .add_system(select_hotbar_slots)
+            .add_system(update_held_items.after(select_hotbar_slots))
+            .add_system(run_pregen)
+            .add_system(restore_chunk_entities)
+            .add_system(restore_read_entities.after(receive_disk_reads))
+            .add_system(unload_chunk_entities)
+            .add_system(autosave_chunk_entities)
+            .add_system(mirror_chunk_loads)
+            .add_system(mirror_connections)
+            .add_system(mirror_player_transfers)
+            .add_system(unload_previous_world_chunks)
+            .add_system(detect_idle_players)
+            .add_system(replicate_explosions)
+            .add_system(emit_footstep_dust)
+            .add_system(replicate_particles.after(replicate_explosions).after(emit_footstep_dust))
+            .add_system(replicate_sounds)
+            .add_system(handle_chat_messages)
+            .add_system(validate_block_use)
+            .add_system(replicate_block_use)
+            .add_system(replicate_block_cracks)
+            .add_system(sync_scoreboard)
+            .add_system(sync_teams)
+            .add_system(send_hud_events)
+            .add_system(sync_boss_bars)
+            .add_system(replicate_restored_text_displays)
+            .add_system(handle_container_actions)
+            .add_system(close_distant_containers)
+            .add_system(sync_opened_containers)
+            .add_system(sync_container_contents)
+            .add_system(sync_closed_containers)
+            .add_system(insert_replication_views)
+            .add_system(reset_transferred_views.before(send_replication))
+            .add_system(send_replication)
+            .add_system(insert_movement_sequences)
+            .add_system(insert_position_histories)
+            .add_system_to_stage("post_tick", record_position_histories.after(apply_velocity))
+            .add_system(apply_movement_inputs)
+            .add_system_to_stage("tick", grant_movement_inputs)
+            .add_system(handle_spectate_requests)
+            .add_system(follow_spectate_targets.after(handle_spectate_requests))
+            .add_system(end_invalid_spectating.after(handle_spectate_requests));
+
+        if self.console {
+            app.insert_resource(ConsoleInput::from_stdin()).add_system(read_console_input);
+        }
+
+        if let Some(directory) = &self.mods_directory {
+            let mods = ModList::load(directory);
+            info!("Loaded {} mods from '{}'", mods.len(), directory.display());
+            for loaded in mods.iter() {
+                info!(
+                    "Loaded mod '{}' v{}",
+                    loaded.manifest.id, loaded.manifest.version
+                );
+            }
+            app.insert_resource(mods);
+        }
+
+        let mut registry = app.world.resource_mut::<CommandRegistry>();
+        registry.register(
+            "help",
+            "help",
+            "Lists all available commands.",
+            help_command,
+        );
+        registry.register(
+            "gamemode",
+            "gamemode <mode> [client id]",
+            "Changes the game mode of a player.",
+            gamemode_command,
+        );
+        registry.register(
+            "setworldspawn",
+            "setworldspawn [<x> <y> <z> [world]]",
+            "Changes the spawn point of a world.",
+            setworldspawn_command,
+        );
+        registry.register(
+            "weather",
+            "weather [<clear|rain|storm> [seconds] [world]]",
+            "Changes or reports the weather of a world.",
+            weather_command,
+        );
+        registry.register(
+            "gamerule",
+            "gamerule [<rule> [value] [world]]",
+            "Changes or reports the game rules of a world.",
+            gamerule_command,
+        );
+        registry.register(
+            "scoreboard",
+            "scoreboard <objectives|players> ...",
+            "Manages scoreboard objectives and the scores of players.",
+            scoreboard_command,
+        );
+        registry.register(
+            "team",
+            "team <list|add|remove|members|join|leave|modify|score> ...",
+            "Manages teams, their members, and their rules.",
+            team_command,
+        );
+        registry.register(
+            "title",
+            "title <audience> <show|actionbar|clear> [text]",
+            "Shows a title or action bar message to players.",
+            title_command,
+        );
+        registry.register(
+            "bossbar",
+            "bossbar <list|add|remove|set> ...",
+            "Manages the boss bars that are shown to players.",
+            bossbar_command,
+        );
+        registry.register(
+            "hologram",
+            "hologram <list|add|remove|move|set> ...",
+            "Manages the floating text displays within the world.",
+            hologram_command,
+        );
+        registry.register(
+            "kick",
+            "kick <client id> [reason]",
+            "Disconnects a player from the server.",
+            kick_command,
+        );
+        registry.register(
+            "give",
+            "give <item> [count] [client id]",
+            "Gives a stack of items to a player.",
+            give_command,
+        );
+        registry.register(
+            "say",
+            "say <message>",
+            "Posts a message to the chat on behalf of the server.",
+            say_command,
+        );
+        registry.register(
+            "ban",
+            "ban <client id|address> [reason]",
+            "Bans a client id or IP address from the server.",
+            ban_command,
+        );
+        registry.register(
+            "unban",
+            "unban <client id|address>",
+            "Lifts the ban on a client id or IP address.",
+            unban_command,
+        );
+        registry.register(
+            "banlist",
+            "banlist",
+            "Lists all banned client ids and IP addresses.",
+            banlist_command,
+        );
+        registry.register(
+            "spawnpoint",
+            "spawnpoint [client id] [<x> <y> <z>]",
+            "Changes the respawn point of a player.",
+            spawnpoint_command,
+        );
+        registry.register(
+            "respawn",
+            "respawn [client id]",
+            "Respawns a player at their respawn point.",
+            respawn_command,
+        );
+        registry.register(
+            "permission",
+            "permission <grant|revoke> <client id> <node> | permission list <client id>",
+            "Grants, revokes, or lists the permissions of a player.",
+            permission_command,
+        );
+        registry.register(
+            "tag",
+            "tag <add|remove> <client id|selector> <tag> | tag list <tag>",
+            "Attaches, detaches, or lists entity tags.",
+            tag_command,
+        );
+        registry.register(
+            "pregen",
+            "pregen <radius> [world] | pregen cancel",
+            "Pre-generates the chunks around the spawn point of a world.",
+            pregen_command,
+        );
+        registry.register(
+            "save",
+            "save",
+            "Saves all persistent entities.",
+            save_command,
+        );
+        registry.register("mods", "mods", "Lists all loaded mods.", mods_command);
+
+        let worlds: Vec<Entity> = self
+            .worlds
+            .iter()
+            .map(|config| app.world.spawn(HostedWorldBundle::new(config.clone())).id())
+            .collect();
+
+        if let (Some(radius), Some(world)) = (self.pregen_radius, worlds.first()) {
+            app.world.resource_mut::<PregenQueue>().push(PregenRequest {
+                world: *world,
+                radius,
+            });
+        }
     }
 }
@@ -0,0 +1,331 @@
+//! Structured server logging. Log records are written to the console as well as
+//! to a set of daily rotating log files, with dedicated files for client
+//! connections and command auditing.
+
+
+use anyhow::Result;
+use bevy::log::Level;
+use bevy::prelude::*;
+use bevy_renet::renet::ServerEvent;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling;
+use tracing_subscriber::filter::{filter_fn, EnvFilter};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, Layer, Registry};
+
+
+/// The log target for client connection records. Records with this target are
+/// additionally written to the connection log file.
+pub const CONNECTION_LOG_TARGET: &str = "awgen::connections";
+
+
+/// The log target for command audit records. Records with this target are
+/// additionally written to the audit log file.
+pub const AUDIT_LOG_TARGET: &str = "awgen::audit";
+
+
+/// A boxed tracing layer that records profiling spans.
+type TraceLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+
+/// A boxed guard that flushes profiling spans when dropped.
+type TraceGuard = Box<dyn Any>;
+
+
+/// The output that profiling spans are written to.
+///
+/// Profiling spans are only recorded if Awgen was built with the `profiling`
+/// feature, and Tracy output additionally requires the `tracy` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceOutput {
+    /// Writes all spans to a Chrome trace file at the given path, which may be
+    /// viewed within `chrome://tracing` or Perfetto.
+    Chrome(PathBuf),
+
+    /// Streams all spans to a connected Tracy profiler.
+    Tracy,
+}
+
+
+/// The settings used to configure server logging.
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    /// The directory that log files are written to.
+    pub directory: PathBuf,
+
+    /// The default log level for all modules.
+    ///
+    /// This only applies to the console, the server log, the recent log
+    /// records, and profiling spans. The connection and audit logs always
+    /// record every record of their targets.
+    pub level: Level,
+
+    /// A list of module paths and the log level to use for each, overriding the
+    /// default log level.
+    pub module_levels: Vec<(String, Level)>,
+
+    /// Whether or not log records are also written to the console.
+    pub console: bool,
+
+    /// The number of recent log records to keep in memory, to be included
+    /// within crash reports.
+    pub recent_capacity: usize,
+
+    /// The output to write profiling spans to, if any.
+    pub trace: Option<TraceOutput>,
+}
+
+impl LogSettings {
+    /// Creates a new log settings instance for debug mode, where all Awgen
+    /// modules log at the debug level.
+    pub fn debug() -> Self {
+        Self::default().with_module_level("awgen", Level::DEBUG)
+    }
+
+
+    /// Overrides the log level for the given module path.
+    pub fn with_module_level<S>(mut self, module: S, level: Level) -> Self
+    where S: Into<String> {
+        self.module_levels.push((module.into(), level));
+        self
+    }
+
+
+    /// Builds the log filter for these settings.
+    fn filter(&self) -> EnvFilter {
+        let mut directives = self.level.to_string();
+
+        for (module, level) in &self.module_levels {
+            directives.push_str(&format!(",{module}={level}"));
+        }
+
+        EnvFilter::new(directives)
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            directory:       PathBuf::from("logs"),
+            level:           Level::INFO,
+            module_levels:   vec![],
+            console:         true,
+            recent_capacity: 200,
+            trace:           None,
+        }
+    }
+}
+
+
+/// An in-memory buffer of the most recent log records, which may be shared
+/// between threads.
+///
+/// When the buffer is full, the oldest log record is discarded for each new
+/// record that is written.
+#[derive(Debug, Clone, Default)]
+pub struct RecentLogs {
+    /// The buffered log records, oldest first.
+    lines: Arc<Mutex<VecDeque<String>>>,
+
+    /// The maximum number of log records to keep.
+    capacity: usize,
+}
+
+impl RecentLogs {
+    /// Creates a new, empty log buffer that keeps up to the given number of
+    /// log records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+
+    /// Gets a copy of all buffered log records, oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        match self.lines.lock() {
+            Ok(lines) => lines.iter().cloned().collect(),
+            Err(poisoned) => poisoned.into_inner().iter().cloned().collect(),
+        }
+    }
+
+
+    /// Appends a log record to the buffer, discarding the oldest records if
+    /// the buffer is full.
+    fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut lines = match self.lines.lock() {
+            Ok(lines) => lines,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        while lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+
+        lines.push_back(line);
+    }
+}
+
+impl Write for RecentLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.push(line.trim_end().to_string());
+        Ok(buf.len())
+    }
+
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for RecentLogs {
+    type Writer = RecentLogs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+
+/// A guard that flushes all buffered log records when dropped.
+///
+/// This must be kept alive for as long as logging is required, usually for the
+/// lifetime of the application.
+#[must_use]
+pub struct LogGuard {
+    /// The worker guards for each log file writer.
+    _guards: Vec<WorkerGuard>,
+
+    /// The buffer of recent log records.
+    recent_logs: RecentLogs,
+
+    /// The guard that flushes the profiling trace output, if any.
+    _trace_guard: Option<TraceGuard>,
+}
+
+impl LogGuard {
+    /// Gets the buffer of the most recent log records.
+    pub fn recent_logs(&self) -> &RecentLogs {
+        &self.recent_logs
+    }
+}
+
+
+/// Installs the global server log subscriber based on the given log settings.
+///
+/// Since this replaces the global subscriber, Bevy's `LogPlugin` must not be
+/// used within the same process afterwards.
+pub fn init_logging(settings: &LogSettings) -> Result<LogGuard> {
+    std::fs::create_dir_all(&settings.directory)?;
+
+    let (server_log, server_guard) =
+        tracing_appender::non_blocking(rolling::daily(&settings.directory, "server.log"));
+    let (connection_log, connection_guard) =
+        tracing_appender::non_blocking(rolling::daily(&settings.directory, "connections.log"));
+    let (audit_log, audit_guard) =
+        tracing_appender::non_blocking(rolling::daily(&settings.directory, "audit.log"));
+
+    let (trace_layer, trace_guard) = build_trace_layer(settings.trace.as_ref())?;
+    let recent_logs = RecentLogs::new(settings.recent_capacity);
+    let trace_layer = trace_layer.map(|layer| layer.with_filter(settings.filter()));
+    let console = settings.console.then(|| fmt::layer().with_filter(settings.filter()));
+    let subscriber = Registry::default()
+        .with(trace_layer)
+        .with(console)
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(server_log)
+                .with_filter(settings.filter()),
+        )
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(recent_logs.clone())
+                .with_filter(settings.filter()),
+        )
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(connection_log)
+                .with_filter(filter_fn(|meta| meta.target() == CONNECTION_LOG_TARGET)),
+        )
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(audit_log)
+                .with_filter(filter_fn(|meta| meta.target() == AUDIT_LOG_TARGET)),
+        );
+
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    Ok(LogGuard {
+        _guards: vec![server_guard, connection_guard, audit_guard],
+        recent_logs,
+        _trace_guard: trace_guard,
+    })
+}
+
+
+/// Builds the tracing layer that writes profiling spans to the given output,
+/// alongside a guard that must be kept alive until the output is flushed.
+#[cfg(feature = "profiling")]
+fn build_trace_layer(
+    output: Option<&TraceOutput>,
+) -> Result<(Option<TraceLayer>, Option<TraceGuard>)> {
+    match output {
+        None => Ok((None, None)),
+        Some(TraceOutput::Chrome(path)) => {
+            let (layer, guard) =
+                tracing_chrome::ChromeLayerBuilder::new().file(path).include_args(true).build();
+            Ok((Some(Box::new(layer)), Some(Box::new(guard))))
+        },
+        #[cfg(feature = "tracy")]
+        Some(TraceOutput::Tracy) => Ok((Some(Box::new(tracing_tracy::TracyLayer::new())), None)),
+        #[cfg(not(feature = "tracy"))]
+        Some(TraceOutput::Tracy) => anyhow::bail!("Tracy output requires the `tracy` feature"),
+    }
+}
+
+
+/// Builds the tracing layer that writes profiling spans to the given output.
+///
+/// Since profiling is disabled, an error is returned if an output is given.
+#[cfg(not(feature = "profiling"))]
+fn build_trace_layer(
+    output: Option<&TraceOutput>,
+) -> Result<(Option<TraceLayer>, Option<TraceGuard>)> {
+    if output.is_some() {
+        anyhow::bail!("Profiling output requires the `profiling` feature");
+    }
+
+    Ok((None, None))
+}
+
+
+/// Writes a connection log record for each client that connects to or
+/// disconnects from the server.
+pub fn log_connections(mut server_events: EventReader<ServerEvent>) {
+    for event in server_events.iter() {
+        match event {
+            ServerEvent::ClientConnected(client_id, _) => {
+                info!(target: CONNECTION_LOG_TARGET, client_id, "Client connected");
+            },
+            ServerEvent::ClientDisconnected(client_id) => {
+                info!(target: CONNECTION_LOG_TARGET, client_id, "Client disconnected");
+            },
+        }
+    }
+}
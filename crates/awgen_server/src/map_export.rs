@@ -0,0 +1,244 @@
+//! Exports top-down map images of hosted worlds, either from the chunks that
+//! are currently loaded on the server or from chunks that are regenerated from
+//! the world seed.
+
+
+use crate::prelude::{
+    CommandRegistry, CommandSender, HostedWorlds, WorldConfig, WorldDataDirectory, WorldSpawn
+};
+use anyhow::{bail, Result};
+use awgen_math::prelude::{block_to_chunk, chunk_to_block, Region2};
+use awgen_world::prelude::{
+    render_map, BlockMapColor, InWorld, VoxelChunkStates, VoxelWorld, WorldGenerator
+};
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+
+/// The largest radius, in chunks, of an exported map.
+pub const MAX_MAP_RADIUS: u16 = 64;
+
+
+/// Gets the columns of all chunks within the given radius, in chunks, around
+/// the chunk that contains the given position.
+pub fn map_columns(center: Vec3, radius: u16) -> Region2 {
+    let center = block_to_chunk(center.floor().as_ivec3());
+    let radius = radius as i32;
+    let min = chunk_to_block(IVec3::new(center.x - radius, 0, center.z - radius));
+    let max = chunk_to_block(IVec3::new(center.x + radius + 1, 0, center.z + radius + 1)) - 1;
+    Region2::from_points(IVec2::new(min.x, min.z), IVec2::new(max.x, max.z))
+}
+
+
+/// Generates all chunks within the given columns and chunk height range with
+/// the given world generator, and returns a world containing only those
+/// chunks.
+///
+/// As block changes are not saved, this is used to render maps of worlds that
+/// are not currently hosted, and shows the world as it was originally
+/// generated.
+pub fn generate_map_world<BlockData>(
+    generator: &mut WorldGenerator<BlockData>,
+    columns: Region2,
+    min_chunk_y: i32,
+    max_chunk_y: i32,
+) -> VoxelWorld<BlockData>
+where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let mut world = VoxelWorld::default();
+    let states = VoxelChunkStates::default();
+
+    let min = block_to_chunk(IVec3::new(columns.min().x, 0, columns.min().y));
+    let max = block_to_chunk(IVec3::new(columns.max().x, 0, columns.max().y));
+    for x in min.x..=max.x {
+        for z in min.z..=max.z {
+            for y in min_chunk_y..=max_chunk_y {
+                generator.generate_into(IVec3::new(x, y, z), &mut world, &states);
+            }
+        }
+    }
+
+    world
+}
+
+
+/// Renders a top-down map of the loaded chunks around the spawn point of a
+/// world, and saves it within the data directory of that world.
+///
+/// Usage: `exportmap <radius> [scale] [world]`
+///
+/// The radius is given in chunks. If no world is given, the world of the
+/// sending player is used.
+pub fn exportmap_command<BlockData>(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String>
+where
+    BlockData: BlockMapColor + Default + Copy + Send + Sync + 'static,
+{
+    let (radius, scale, world_name) = match args {
+        [radius, rest @ ..] if rest.len() <= 2 => {
+            (radius.parse::<u16>()?, rest.first(), rest.get(1))
+        },
+        _ => bail!("Usage: exportmap <radius> [scale] [world]"),
+    };
+
+    if radius > MAX_MAP_RADIUS {
+        bail!("Map radius cannot be larger than {MAX_MAP_RADIUS} chunks");
+    }
+
+    let scale = match scale {
+        Some(scale) => scale.parse::<u32>()?,
+        None => 1,
+    };
+
+    let target = match world_name {
+        Some(name) => world.resource::<HostedWorlds>().get(name),
+        None => {
+            match sender {
+                CommandSender::Player(player) => world.get::<InWorld>(*player).map(|w| w.0),
+                CommandSender::Console | CommandSender::Remote(_) => {
+                    world.resource::<HostedWorlds>().default_world()
+                },
+            }
+        },
+    };
+
+    let Some(target) = target else {
+        bail!("Unknown world");
+    };
+
+    let name = world.get::<WorldConfig>(target).map(|c| c.name.clone()).unwrap_or_default();
+    let Some(voxels) = world.get::<VoxelWorld<BlockData>>(target) else {
+        bail!("World '{name}' has no loaded chunks");
+    };
+
+    let center = world.get::<WorldSpawn>(target).map(|s| s.position).unwrap_or_default();
+    let map = render_map(voxels, map_columns(center, radius), scale)?;
+
+    let path = world.resource::<WorldDataDirectory>().map_path(&name)?;
+    map.save_png(&path)?;
+
+    Ok(format!(
+        "Saved a {}x{} map of world '{name}' to '{}'",
+        map.width(),
+        map.height(),
+        path.display()
+    ))
+}
+
+
+/// A mini extension plugin that registers the `exportmap` command for worlds
+/// containing a block data layer of the given type.
+#[derive(Debug, Clone, Default)]
+pub struct MapExportPlugin<BlockData>
+where BlockData: BlockMapColor + Default + Copy + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for MapExportPlugin<BlockData>
+where BlockData: BlockMapColor + Default + Copy + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandRegistry>();
+        app.world.resource_mut::<CommandRegistry>().register(
+            "exportmap",
+            "exportmap <radius> [scale] [world]",
+            "Saves a map image of the loaded chunks around the spawn point of a world.",
+            exportmap_command::<BlockData>,
+        );
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::update_hosted_worlds;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+
+    /// A minimal block type for testing.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    struct TestBlock(bool);
+
+    impl BlockMapColor for TestBlock {
+        fn map_color(&self) -> Option<[u8; 3]> {
+            self.0.then_some([200, 100, 50])
+        }
+    }
+
+
+    /// Creates a new app hosting a single world named "lobby", with a single
+    /// visible block at the origin, storing world data within the given
+    /// directory.
+    fn setup(data_dir: &str) -> App {
+        let mut app = App::new();
+        app.init_resource::<HostedWorlds>()
+            .insert_resource(WorldDataDirectory(std::env::temp_dir().join(data_dir)))
+            .add_system(update_hosted_worlds);
+
+        let mut voxels = VoxelWorld::<TestBlock>::default();
+        voxels.set_block_data(IVec3::ZERO, TestBlock(true));
+        app.world.spawn((WorldConfig::new("lobby", "flat"), voxels));
+        app.update();
+        app
+    }
+
+
+    /// Runs the exportmap command as the console with the given arguments.
+    fn exportmap(app: &mut App, args: &[&str]) -> Result<String> {
+        exportmap_command::<TestBlock>(&mut app.world, &CommandSender::Console, args)
+    }
+
+
+    #[test]
+    fn reject_invalid_arguments() {
+        let mut app = setup("awgen-map-test-invalid");
+        assert!(exportmap(&mut app, &[]).is_err());
+        assert!(exportmap(&mut app, &["1", "1", "lobby", "extra"]).is_err());
+        assert!(exportmap(&mut app, &["-1"]).is_err());
+        assert!(exportmap(&mut app, &["one"]).is_err());
+        assert!(exportmap(&mut app, &["65"]).is_err());
+        assert!(exportmap(&mut app, &["1", "big"]).is_err());
+        assert!(exportmap(&mut app, &["1", "1", "nowhere"]).is_err());
+    }
+
+
+    #[test]
+    fn reject_oversized_scale() {
+        let mut app = setup("awgen-map-test-oversized");
+        assert!(exportmap(&mut app, &["0", "268435456"]).is_err());
+        assert!(exportmap(&mut app, &["0", "4294967295"]).is_err());
+        assert!(exportmap(&mut app, &["64", "4"]).is_err());
+    }
+
+
+    #[test]
+    fn export_map() {
+        let data_dir = std::env::temp_dir().join("awgen-map-test-export");
+        let mut app = setup("awgen-map-test-export");
+
+        let response = exportmap(&mut app, &["1", "2", "lobby"]).unwrap();
+        let path = data_dir.join("lobby").join("map.png");
+        assert_eq!(
+            response,
+            format!("Saved a 96x96 map of world 'lobby' to '{}'", path.display())
+        );
+        assert!(path.is_file());
+
+        fs::remove_dir_all(data_dir).unwrap();
+    }
+
+
+    #[test]
+    fn columns_around_center() {
+        let columns = map_columns(Vec3::new(20.0, 5.0, -3.0), 1);
+        assert_eq!(columns.min(), IVec2::new(0, -32));
+        assert_eq!(columns.max(), IVec2::new(47, 15));
+    }
+}
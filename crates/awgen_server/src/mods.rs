@@ -0,0 +1,474 @@
+//! Discovers and loads mods from the mods directory when the server starts.
+//!
+//! Each mod is a folder within the mods directory containing a `mod.ron`
+//! manifest. Declarative data-pack mods store their content files within the
+//! `blocks`, `items`, `recipes`, and `scripts` sub-folders of the mod. WASM
+//! mods additionally name a compiled module within their manifest, which is
+//! listed as a script of the mod.
+//!
+//! The content files of each mod are only discovered and listed within its
+//! [`ModContent`]. They are not registered with the game, whose blocks, items,
+//! and recipes are still defined in code.
+//!
+//! ```ron
+//! (
+//!     id: "castles",
+//!     name: "Castles",
+//!     version: "1.2.0",
+//!     dependencies: ["stonework"],
+//!     load_after: ["banners"],
+//!     wasm: Some("castles.wasm"),
+//! )
+//! ```
+
+
+use crate::prelude::CommandSender;
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+/// The file name of the manifest file within each mod folder.
+pub const MOD_MANIFEST: &str = "mod.ron";
+
+
+/// The manifest of a mod, describing the mod and its dependencies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModManifest {
+    /// The unique id of the mod, used to reference the mod as a dependency.
+    pub id: String,
+
+    /// The display name of the mod.
+    pub name: String,
+
+    /// The version of the mod.
+    pub version: String,
+
+    /// A short description of the mod.
+    #[serde(default)]
+    pub description: String,
+
+    /// The ids of the mods that must be loaded for this mod to be loaded. This
+    /// mod is always loaded after its dependencies.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+
+    /// The ids of optional mods that this mod should be loaded after, if they
+    /// are present.
+    #[serde(default)]
+    pub load_after: Vec<String>,
+
+    /// The path of the compiled WASM module of this mod, relative to the mod
+    /// folder. Declarative data-pack mods do not have a WASM module.
+    #[serde(default)]
+    pub wasm: Option<PathBuf>,
+}
+
+
+/// The content files provided by a mod.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModContent {
+    /// The block definition files of the mod.
+    pub blocks: Vec<PathBuf>,
+
+    /// The item definition files of the mod.
+    pub items: Vec<PathBuf>,
+
+    /// The recipe definition files of the mod.
+    pub recipes: Vec<PathBuf>,
+
+    /// The script files of the mod, including the WASM module of the mod if it
+    /// has one.
+    pub scripts: Vec<PathBuf>,
+}
+
+
+/// A mod that has been discovered within the mods directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedMod {
+    /// The manifest of the mod.
+    pub manifest: ModManifest,
+
+    /// The folder that the mod was loaded from.
+    pub path: PathBuf,
+
+    /// The content files provided by the mod.
+    pub content: ModContent,
+}
+
+
+/// The list of mods loaded by the server, in load order.
+///
+/// This resource is inserted when the server plugin is built, so the content
+/// of all mods is available before any world is loaded.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ModList {
+    /// The loaded mods, in load order.
+    mods: Vec<LoadedMod>,
+}
+
+impl ModList {
+    /// Discovers all mods within the given directory and resolves their load
+    /// order.
+    ///
+    /// Mods that could not be loaded, or that are missing a dependency, are
+    /// skipped and an error is logged. If the directory does not exist, an
+    /// empty mod list is returned.
+    pub fn load(directory: &Path) -> Self {
+        let mods = match discover_mods(directory) {
+            Ok(mods) => mods,
+            Err(err) => {
+                error!(
+                    "Failed to read mods directory '{}': {err}",
+                    directory.display()
+                );
+                return Self::default();
+            },
+        };
+
+        Self {
+            mods: resolve_load_order(mods),
+        }
+    }
+
+
+    /// Gets an iterator over all loaded mods, in load order.
+    pub fn iter(&self) -> impl Iterator<Item = &LoadedMod> {
+        self.mods.iter()
+    }
+
+
+    /// Gets the loaded mod with the given id.
+    pub fn get(&self, id: &str) -> Option<&LoadedMod> {
+        self.mods.iter().find(|m| m.manifest.id == id)
+    }
+
+
+    /// Gets the number of loaded mods.
+    pub fn len(&self) -> usize {
+        self.mods.len()
+    }
+
+
+    /// Gets whether or not no mods are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.mods.is_empty()
+    }
+}
+
+
+/// Discovers all mods within the given directory, sorted by the path of their
+/// folder.
+///
+/// Mods that could not be loaded are skipped and an error is logged. If the
+/// directory does not exist, no mods are returned.
+pub fn discover_mods(directory: &Path) -> Result<Vec<LoadedMod>> {
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut mods = vec![];
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if !path.join(MOD_MANIFEST).is_file() {
+            continue;
+        }
+
+        match load_mod(&path) {
+            Ok(loaded) => mods.push(loaded),
+            Err(err) => error!("Failed to load mod '{}': {err}", path.display()),
+        }
+    }
+
+    mods.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(mods)
+}
+
+
+/// Loads the manifest and content of the mod within the given folder.
+fn load_mod(path: &Path) -> Result<LoadedMod> {
+    let text = fs::read_to_string(path.join(MOD_MANIFEST))?;
+    let manifest: ModManifest = ron::from_str(&text)?;
+
+    let mut content = ModContent {
+        blocks:  content_files(&path.join("blocks"))?,
+        items:   content_files(&path.join("items"))?,
+        recipes: content_files(&path.join("recipes"))?,
+        scripts: content_files(&path.join("scripts"))?,
+    };
+
+    if let Some(wasm) = &manifest.wasm {
+        let wasm = path.join(wasm);
+        if !wasm.is_file() {
+            bail!("Missing WASM module: {}", wasm.display());
+        }
+
+        content.scripts.insert(0, wasm);
+    }
+
+    Ok(LoadedMod {
+        manifest,
+        path: path.to_path_buf(),
+        content,
+    })
+}
+
+
+/// Lists all files within the given content folder, sorted by path. If the
+/// folder does not exist, no files are returned.
+fn content_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    if !folder.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files = vec![];
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+
+/// Sorts the given mods into load order, such that every mod is loaded after
+/// its dependencies and any present optional dependencies. Mods without an
+/// ordering constraint between them are loaded in order of their id.
+///
+/// Mods with a missing dependency, or a circular dependency, are removed, and
+/// an error is logged. Mods that depend on a removed mod are removed as well.
+/// If multiple mods share the same id, only the first of them is kept.
+pub fn resolve_load_order(mods: Vec<LoadedMod>) -> Vec<LoadedMod> {
+    let mut by_id: HashMap<String, LoadedMod> = HashMap::default();
+    for loaded in mods {
+        let id = loaded.manifest.id.clone();
+        if by_id.contains_key(&id) {
+            error!(
+                "Skipping mod at '{}': duplicate mod id '{id}'",
+                loaded.path.display()
+            );
+            continue;
+        }
+        by_id.insert(id, loaded);
+    }
+
+    // Removing a mod may leave other mods with a missing dependency, so this
+    // repeats until every remaining dependency is present.
+    loop {
+        let missing: Vec<(String, String)> = by_id
+            .values()
+            .filter_map(|m| {
+                m.manifest
+                    .dependencies
+                    .iter()
+                    .find(|dep| !by_id.contains_key(*dep))
+                    .map(|dep| (m.manifest.id.clone(), dep.clone()))
+            })
+            .collect();
+
+        if missing.is_empty() {
+            break;
+        }
+
+        for (id, dependency) in missing {
+            error!("Skipping mod '{id}': missing dependency '{dependency}'");
+            by_id.remove(&id);
+        }
+    }
+
+    let mut order = vec![];
+    let mut loaded: HashSet<String> = HashSet::default();
+    while !by_id.is_empty() {
+        let next = by_id
+            .values()
+            .filter(|m| {
+                m.manifest
+                    .dependencies
+                    .iter()
+                    .chain(m.manifest.load_after.iter())
+                    .all(|dep| loaded.contains(dep) || !by_id.contains_key(dep))
+            })
+            .map(|m| m.manifest.id.clone())
+            .min();
+
+        let Some(next) = next else {
+            let mut ids: Vec<_> = by_id.keys().cloned().collect();
+            ids.sort();
+            error!("Skipping mods with circular load order: {}", ids.join(", "));
+            break;
+        };
+
+        let loaded_mod = by_id.remove(&next).unwrap();
+        loaded.insert(next);
+        order.push(loaded_mod);
+    }
+
+    order
+}
+
+
+/// Lists all mods that are loaded by the server.
+///
+/// Usage: `mods`
+pub fn mods_command(world: &mut World, _: &CommandSender, _: &[&str]) -> Result<String> {
+    let mods = world
+        .get_resource::<ModList>()
+        .ok_or_else(|| anyhow!("Mods are not enabled on this server"))?;
+
+    if mods.is_empty() {
+        return Ok("No mods are loaded".to_string());
+    }
+
+    let mut list = format!("Loaded mods ({}):", mods.len());
+    for loaded in mods.iter() {
+        list.push_str(&format!(
+            "\n  {} ({}) v{}",
+            loaded.manifest.name, loaded.manifest.id, loaded.manifest.version
+        ));
+    }
+
+    Ok(list)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates a mod with the given id, dependencies, and optional
+    /// dependencies.
+    fn test_mod(id: &str, dependencies: &[&str], load_after: &[&str]) -> LoadedMod {
+        LoadedMod {
+            manifest: ModManifest {
+                id:           id.to_string(),
+                name:         id.to_string(),
+                version:      "1.0.0".to_string(),
+                description:  String::new(),
+                dependencies: dependencies.iter().map(|dep| dep.to_string()).collect(),
+                load_after:   load_after.iter().map(|dep| dep.to_string()).collect(),
+                wasm:         None,
+            },
+            path:     PathBuf::from(id),
+            content:  ModContent::default(),
+        }
+    }
+
+
+    /// Gets the ids of the given mods, in order.
+    fn ids(mods: &[LoadedMod]) -> Vec<&str> {
+        mods.iter().map(|m| m.manifest.id.as_str()).collect()
+    }
+
+
+    #[test]
+    fn order_by_dependencies() {
+        let mods = resolve_load_order(vec![
+            test_mod("castles", &["stonework"], &["banners"]),
+            test_mod("banners", &[], &[]),
+            test_mod("stonework", &[], &[]),
+            test_mod("alchemy", &[], &["castles", "missing"]),
+        ]);
+
+        assert_eq!(ids(&mods), vec![
+            "banners",
+            "stonework",
+            "castles",
+            "alchemy"
+        ]);
+    }
+
+
+    #[test]
+    fn remove_missing_dependencies() {
+        let mods = resolve_load_order(vec![
+            test_mod("castles", &["stonework"], &[]),
+            test_mod("towers", &["castles"], &[]),
+            test_mod("banners", &[], &["castles"]),
+        ]);
+
+        assert_eq!(ids(&mods), vec!["banners"]);
+    }
+
+
+    #[test]
+    fn remove_cycles() {
+        let mods = resolve_load_order(vec![
+            test_mod("a", &["b"], &[]),
+            test_mod("b", &[], &["a"]),
+            test_mod("c", &["a"], &[]),
+            test_mod("d", &[], &[]),
+        ]);
+
+        assert_eq!(ids(&mods), vec!["d"]);
+    }
+
+
+    #[test]
+    fn keep_first_duplicate() {
+        let mut second = test_mod("castles", &[], &[]);
+        second.path = PathBuf::from("castles-copy");
+
+        let mods = resolve_load_order(vec![test_mod("castles", &[], &[]), second]);
+        assert_eq!(ids(&mods), vec!["castles"]);
+        assert_eq!(mods[0].path, PathBuf::from("castles"));
+    }
+
+
+    #[test]
+    fn discover_and_load() {
+        let dir = std::env::temp_dir().join(format!("awgen-mods-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let castles = dir.join("castles");
+        fs::create_dir_all(castles.join("blocks")).unwrap();
+        fs::write(castles.join("blocks").join("wall.ron"), "()").unwrap();
+        fs::write(castles.join("castles.wasm"), []).unwrap();
+        fs::write(
+            castles.join(MOD_MANIFEST),
+            "(id: \"castles\", name: \"Castles\", version: \"1.2.0\", dependencies: \
+             [\"stonework\"], wasm: Some(\"castles.wasm\"))",
+        )
+        .unwrap();
+
+        let stonework = dir.join("stonework");
+        fs::create_dir_all(&stonework).unwrap();
+        fs::write(
+            stonework.join(MOD_MANIFEST),
+            "(id: \"stonework\", name: \"Stonework\", version: \"0.1.0\")",
+        )
+        .unwrap();
+
+        let broken = dir.join("broken");
+        fs::create_dir_all(&broken).unwrap();
+        fs::write(
+            broken.join(MOD_MANIFEST),
+            "(id: \"broken\", name: \"Broken\", version: \"1.0.0\", wasm: Some(\"missing.wasm\"))",
+        )
+        .unwrap();
+
+        fs::create_dir_all(dir.join("not_a_mod")).unwrap();
+
+        let mods = ModList::load(&dir);
+        assert_eq!(
+            mods.iter().map(|m| m.manifest.id.as_str()).collect::<Vec<_>>(),
+            vec!["stonework", "castles"]
+        );
+        assert_eq!(mods.get("castles").unwrap().content, ModContent {
+            blocks:  vec![castles.join("blocks").join("wall.ron")],
+            items:   vec![],
+            recipes: vec![],
+            scripts: vec![castles.join("castles.wasm")],
+        });
+
+        fs::remove_dir_all(dir).unwrap();
+        assert!(ModList::load(&std::env::temp_dir().join("awgen-mods-test-missing")).is_empty());
+    }
+}
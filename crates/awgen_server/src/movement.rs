@@ -0,0 +1,253 @@
+//! Applies the movement inputs of players on the server, which is the authority
+//! over the position of each player, and acknowledges them so that clients can
+//! reconcile their predicted positions.
+
+
+use awgen_network::prelude::{
+    ClientMessage, ClientSocket, MovementAck, MovementInput, OutgoingQueue, SendPriority, MAX_MOVEMENT_SPEED, MAX_PENDING_INPUTS
+};
+use awgen_physics::prelude::{GameMode, PhysicsTickrate, Position};
+use bevy::prelude::*;
+
+
+/// The additional fraction of the maximum movement speed that is allowed for
+/// each movement input, to account for floating point error.
+const MOVEMENT_SPEED_TOLERANCE: f32 = 0.01;
+
+
+/// The number of movement inputs that may be applied to a player ahead of the
+/// elapsed physics ticks, to absorb inputs that arrive in bursts due to
+/// network jitter.
+pub const MOVEMENT_INPUT_SLACK: u32 = 10;
+
+
+/// The sequence number of the last movement input that was applied to a
+/// player, and the number of movement inputs that may still be applied.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct MovementSequence {
+    /// The last applied sequence number, if any.
+    last: Option<u32>,
+
+    /// The number of movement inputs that may be applied before the next
+    /// physics tick. One input is granted each physics tick, up to
+    /// [`MOVEMENT_INPUT_SLACK`], so that a player can never move faster than
+    /// one input per physics tick over time.
+    budget: u32,
+}
+
+impl Default for MovementSequence {
+    fn default() -> Self {
+        Self {
+            last:   None,
+            budget: MOVEMENT_INPUT_SLACK,
+        }
+    }
+}
+
+impl MovementSequence {
+    /// Gets the sequence number of the last movement input that was applied,
+    /// if any.
+    pub fn last(&self) -> Option<u32> {
+        self.last
+    }
+
+
+    /// Gets the number of movement inputs that may be applied before the next
+    /// physics tick.
+    pub fn budget(&self) -> u32 {
+        self.budget
+    }
+}
+
+
+/// Limits the given movement input force to what the player is allowed to
+/// move within a single physics tick.
+///
+/// Vertical movement is removed if the game mode of the player does not allow
+/// for flying, and inputs that exceed the maximum movement speed are
+/// discarded entirely.
+fn validate_force(force: Vec3, max_distance: f32, can_fly: bool) -> Vec3 {
+    let horizontal = Vec3::new(force.x, 0.0, force.z);
+    let vertical = match can_fly {
+        true => force.y,
+        false => 0.0,
+    };
+
+    if !force.is_finite() || horizontal.length() > max_distance || vertical.abs() > max_distance {
+        return Vec3::ZERO;
+    }
+
+    horizontal + Vec3::Y * vertical
+}
+
+
+/// Adds a movement sequence tracker to each newly connected player.
+pub fn insert_movement_sequences(
+    players: Query<Entity, (With<ClientSocket>, Without<MovementSequence>)>,
+    mut commands: Commands,
+) {
+    for player in players.iter() {
+        commands.entity(player).insert(MovementSequence::default());
+    }
+}
+
+
+/// Grants each player one more movement input for each physics tick, up to
+/// [`MOVEMENT_INPUT_SLACK`].
+pub fn grant_movement_inputs(mut sequences: Query<&mut MovementSequence>) {
+    for mut sequence in sequences.iter_mut() {
+        sequence.budget = (sequence.budget + 1).min(MOVEMENT_INPUT_SLACK);
+    }
+}
+
+
+/// Applies each movement input that was received from a player and has not yet
+/// been applied, then acknowledges the last applied input and the resulting
+/// position of the player.
+///
+/// Each applied input uses up one input of the budget of the player. Inputs
+/// beyond the budget are left unapplied, and are applied once the client
+/// resends them within a later message. Messages with more than
+/// [`MAX_PENDING_INPUTS`] inputs are rejected entirely.
+pub fn apply_movement_inputs(
+    tickrate: Res<PhysicsTickrate>,
+    mut input_ev: EventReader<ClientMessage<MovementInput>>,
+    mut queue: ResMut<OutgoingQueue>,
+    mut players: Query<(&mut Position, &mut MovementSequence, Option<&GameMode>)>,
+) {
+    let max_distance = MAX_MOVEMENT_SPEED * tickrate.delta() * (1.0 + MOVEMENT_SPEED_TOLERANCE);
+
+    for ev in input_ev.iter() {
+        let Ok((mut position, mut sequence, game_mode)) = players.get_mut(ev.player) else {
+            continue;
+        };
+
+        let can_fly = game_mode.is_none_or(|mode| mode.can_fly());
+        let input = &ev.message;
+        if input.forces.len() > MAX_PENDING_INPUTS {
+            warn!(
+                "Client {} sent {} movement inputs at once",
+                ev.client_id,
+                input.forces.len()
+            );
+            continue;
+        }
+
+        for (offset, force) in input.forces.iter().enumerate() {
+            let input_sequence = input.first_sequence.wrapping_add(offset as u32);
+            if sequence.last.is_some_and(|last| input_sequence.wrapping_sub(last) as i32 <= 0) {
+                continue;
+            }
+
+            if sequence.budget == 0 {
+                break;
+            }
+
+            position.translation += validate_force(*force, max_distance, can_fly);
+            sequence.last = Some(input_sequence);
+            sequence.budget -= 1;
+        }
+
+        if let Some(last) = sequence.last {
+            queue.push(ev.client_id, SendPriority::High, &MovementAck {
+                sequence:    last,
+                translation: position.translation,
+            });
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// The physics tickrate of the test app.
+    const TICKRATE: f32 = 20.0;
+
+
+    /// Creates an app that applies movement inputs, with a single player.
+    fn movement_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.insert_resource(PhysicsTickrate::new(TICKRATE))
+            .init_resource::<OutgoingQueue>()
+            .add_event::<ClientMessage<MovementInput>>()
+            .add_system(apply_movement_inputs);
+
+        let player = app.world.spawn((Position::default(), MovementSequence::default())).id();
+        (app, player)
+    }
+
+
+    /// Sends a movement input message from the given player, moving one
+    /// centimeter along the X axis for each input.
+    fn send_inputs(app: &mut App, player: Entity, first_sequence: u32, count: usize) {
+        app.world.send_event(ClientMessage {
+            client_id: 1,
+            player,
+            message: MovementInput {
+                first_sequence,
+                forces: vec![Vec3::X * 0.01; count],
+            },
+        });
+        app.update();
+    }
+
+
+    /// Gets the X coordinate of the given player, in centimeters.
+    fn distance(app: &App, player: Entity) -> u32 {
+        (app.world.get::<Position>(player).unwrap().translation.x * 100.0).round() as u32
+    }
+
+
+    #[test]
+    fn inputs_are_limited_by_budget() {
+        let (mut app, player) = movement_app();
+        send_inputs(&mut app, player, 0, 40);
+        assert_eq!(distance(&app, player), MOVEMENT_INPUT_SLACK);
+
+        let sequence = app.world.get::<MovementSequence>(player).unwrap();
+        assert_eq!(sequence.last(), Some(MOVEMENT_INPUT_SLACK - 1));
+        assert_eq!(sequence.budget(), 0);
+
+        send_inputs(&mut app, player, 0, 40);
+        assert_eq!(distance(&app, player), MOVEMENT_INPUT_SLACK);
+    }
+
+
+    #[test]
+    fn budget_grows_each_tick() {
+        let (mut app, player) = movement_app();
+        app.add_system(grant_movement_inputs.before(apply_movement_inputs));
+
+        send_inputs(&mut app, player, 0, 40);
+        assert_eq!(distance(&app, player), MOVEMENT_INPUT_SLACK);
+
+        send_inputs(&mut app, player, 0, 40);
+        assert_eq!(distance(&app, player), MOVEMENT_INPUT_SLACK + 1);
+
+        for _ in 0..20 {
+            app.update();
+        }
+
+        send_inputs(&mut app, player, 0, 40);
+        assert_eq!(distance(&app, player), MOVEMENT_INPUT_SLACK * 2 + 1);
+    }
+
+
+    #[test]
+    fn oversized_messages_are_rejected() {
+        let (mut app, player) = movement_app();
+        send_inputs(&mut app, player, 0, MAX_PENDING_INPUTS + 1);
+        assert_eq!(distance(&app, player), 0);
+
+        let sequence = app.world.get::<MovementSequence>(player).unwrap();
+        assert_eq!(sequence.last(), None);
+        assert_eq!(sequence.budget(), MOVEMENT_INPUT_SLACK);
+
+        send_inputs(&mut app, player, 0, MAX_PENDING_INPUTS);
+        assert_eq!(distance(&app, player), MOVEMENT_INPUT_SLACK);
+    }
+}
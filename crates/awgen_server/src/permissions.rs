@@ -0,0 +1,98 @@
+//! Contains the permission nodes that may be granted to players in order to
+//! allow them to bypass certain server restrictions.
+
+
+use crate::prelude::{find_player, CommandSender};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// A list of permission nodes that have been granted to a player.
+#[derive(Debug, Clone, PartialEq, Eq, Reflect, Component, Default, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Permissions {
+    /// The granted permission nodes.
+    nodes: Vec<String>,
+}
+
+impl Permissions {
+    /// Gets whether or not the given permission node has been granted.
+    pub fn has(&self, node: &str) -> bool {
+        self.nodes.iter().any(|n| n == node)
+    }
+
+
+    /// Grants the given permission node.
+    ///
+    /// Returns false if the node had already been granted.
+    pub fn grant(&mut self, node: &str) -> bool {
+        if self.has(node) {
+            return false;
+        }
+
+        self.nodes.push(node.to_string());
+        true
+    }
+
+
+    /// Revokes the given permission node.
+    ///
+    /// Returns false if the node had not been granted.
+    pub fn revoke(&mut self, node: &str) -> bool {
+        let len = self.nodes.len();
+        self.nodes.retain(|n| n != node);
+        self.nodes.len() != len
+    }
+
+
+    /// Gets an iterator over all granted permission nodes.
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.nodes.iter()
+    }
+}
+
+
+/// Grants, revokes, or lists the permission nodes of a player.
+///
+/// Usage: `permission <grant|revoke> <client id> <node>` or
+/// `permission list <client id>`
+pub fn permission_command(world: &mut World, _: &CommandSender, args: &[&str]) -> Result<String> {
+    match args {
+        ["list", client_id] => {
+            let player = find_player(world, client_id.parse()?)?;
+            let nodes: Vec<_> = world
+                .get::<Permissions>(player)
+                .map(|p| p.iter().cloned().collect())
+                .unwrap_or_default();
+
+            match nodes.is_empty() {
+                true => Ok(format!("Player {client_id} has no permissions")),
+                false => Ok(format!("Player {client_id}: {}", nodes.join(", "))),
+            }
+        },
+        [action @ ("grant" | "revoke"), client_id, node] => {
+            let player = find_player(world, client_id.parse()?)?;
+            let mut player = world.entity_mut(player);
+            if !player.contains::<Permissions>() {
+                player.insert(Permissions::default());
+            }
+
+            let mut permissions = player.get_mut::<Permissions>().unwrap();
+            match *action {
+                "grant" if permissions.grant(node) => {
+                    Ok(format!("Granted '{node}' to player {client_id}"))
+                },
+                "revoke" if permissions.revoke(node) => {
+                    Ok(format!("Revoked '{node}' from player {client_id}"))
+                },
+                _ => Ok(format!("Player {client_id} was not changed")),
+            }
+        },
+        _ => {
+            bail!(
+                "Usage: permission <grant|revoke> <client id> <node> | permission list <client id>"
+            )
+        },
+    }
+}
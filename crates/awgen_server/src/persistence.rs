@@ -0,0 +1,471 @@
+//! Saves persistent non-player entities alongside the chunk that they are
+//! within, and restores them when that chunk is loaded again.
+//!
+//! Only the components that have been registered as persistent, using
+//! [`PersistentComponentExt`](awgen_world::prelude::PersistentComponentExt),
+//! are saved. Entities are saved periodically, whenever the `save` command is
+//! used, and when the chunk that they are within is unloaded. The files are
+//! read and written by the [`DiskIo`] thread.
+
+
+use crate::prelude::{
+    decode_save_seed, encode_save, CommandSender, DiskIo, DiskReadEvent, DiskReadKind, SaveKind, WorldConfig, WorldDataDirectory
+};
+use anyhow::Result;
+use awgen_math::prelude::{block_to_chunk, world_to_block};
+use awgen_physics::prelude::Position;
+use awgen_world::prelude::{
+    flush_spawn_queue, InWorld, LoadChunkEvent, Persistent, PersistentComponents, SpawnQueue, UnloadChunkEvent
+};
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectSerializer, UntypedReflectDeserializer};
+use bevy::reflect::TypeRegistry;
+use bevy::utils::{HashMap, HashSet};
+use serde::de::{DeserializeSeed, SeqAccess, Visitor};
+use serde::{Deserializer, Serialize, Serializer};
+use std::fmt;
+
+
+/// The default number of seconds between each save of all persistent
+/// entities.
+pub const ENTITY_AUTOSAVE_SECONDS: f32 = 60.0;
+
+
+/// The spawn queue priority of restored chunk entities.
+const RESTORE_SPAWN_PRIORITY: u32 = 0;
+
+
+/// The save state of all persistent entities.
+#[derive(Debug, Clone, Resource)]
+pub struct EntityPersistence {
+    /// The timer until the next save of all persistent entities.
+    autosave: Timer,
+
+    /// The world and chunk coordinates of each chunk whose saved entities are
+    /// still being read from disk.
+    loading: HashSet<(Entity, IVec3)>,
+
+    /// The world and chunk coordinates of each chunk whose saved entities have
+    /// been restored.
+    restored: HashSet<(Entity, IVec3)>,
+}
+
+impl Default for EntityPersistence {
+    fn default() -> Self {
+        Self {
+            autosave: Timer::from_seconds(ENTITY_AUTOSAVE_SECONDS, TimerMode::Repeating),
+            loading:  default(),
+            restored: default(),
+        }
+    }
+}
+
+
+/// A command that requests the saved entities within a chunk to be read from
+/// disk. Once read, the entities are restored by [`restore_read_entities`].
+///
+/// If the entities within the chunk have already been restored, or are still
+/// being read, this command does nothing.
+#[derive(Debug, Clone)]
+pub struct RestoreChunkEntities {
+    /// The world that the chunk is within.
+    pub world: Entity,
+
+    /// The coordinates of the chunk.
+    pub chunk_coords: IVec3,
+}
+
+impl Command for RestoreChunkEntities {
+    fn write(self, world: &mut World) {
+        let key = (self.world, self.chunk_coords);
+        let persistence = world.get_resource_or_insert_with(EntityPersistence::default);
+        if persistence.restored.contains(&key) || persistence.loading.contains(&key) {
+            return;
+        }
+
+        let Some(world_name) = world.get::<WorldConfig>(self.world).map(|c| c.name.clone()) else {
+            return;
+        };
+
+        let path = world
+            .resource::<WorldDataDirectory>()
+            .chunk_entities_path(&world_name, self.chunk_coords);
+
+        world.resource::<DiskIo>().read(
+            DiskReadKind::ChunkEntities {
+                world:        self.world,
+                chunk_coords: self.chunk_coords,
+            },
+            path,
+        );
+        world.resource_mut::<EntityPersistence>().loading.insert(key);
+    }
+}
+
+
+/// Restores the saved entities of each chunk that has been read from disk,
+/// queueing them to be spawned into the world by the [`SpawnQueue`].
+pub fn restore_read_entities(
+    registry: Res<AppTypeRegistry>,
+    mut read_ev: EventReader<DiskReadEvent>,
+    mut persistence: ResMut<EntityPersistence>,
+    mut queue: ResMut<SpawnQueue>,
+) {
+    let registry = registry.read();
+
+    for ev in read_ev.iter() {
+        let DiskReadKind::ChunkEntities {
+            world: world_entity,
+            chunk_coords,
+        } = ev.kind
+        else {
+            continue;
+        };
+
+        persistence.loading.remove(&(world_entity, chunk_coords));
+        persistence.restored.insert((world_entity, chunk_coords));
+
+        let text = match &ev.result {
+            Ok(Some(text)) => text,
+            Ok(None) => continue,
+            Err(err) => {
+                error!(
+                    "Failed to read saved entities '{}': {err}",
+                    ev.path.display()
+                );
+                continue;
+            },
+        };
+
+        let seed = EntityListSeed(ComponentListSeed(&registry));
+        let entities = match decode_save_seed(text, SaveKind::Chunk, seed) {
+            Ok(entities) => entities,
+            Err(err) => {
+                error!(
+                    "Failed to load saved entities '{}': {err}",
+                    ev.path.display()
+                );
+                continue;
+            },
+        };
+
+        for components in entities {
+            queue.spawn_with(RESTORE_SPAWN_PRIORITY, move |world| {
+                spawn_restored_entity(world, world_entity, components)
+            });
+        }
+    }
+}
+
+
+/// Spawns a restored persistent entity with the given reflected components
+/// into the given voxel world.
+fn spawn_restored_entity(
+    world: &mut World,
+    world_entity: Entity,
+    components: Vec<Box<dyn Reflect>>,
+) {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let entity = world.spawn((Persistent, InWorld(world_entity))).id();
+
+    for component in components {
+        let reflect_component = registry
+            .get_with_name(component.type_name())
+            .and_then(|registration| registration.data::<ReflectComponent>());
+
+        match reflect_component {
+            Some(reflect_component) => reflect_component.insert(world, entity, component.as_ref()),
+            None => {
+                warn!(
+                    "Cannot restore unknown component '{}'",
+                    component.type_name()
+                )
+            },
+        }
+    }
+}
+
+
+/// A command that saves all persistent entities within all hosted worlds.
+#[derive(Debug, Clone, Default)]
+pub struct SaveChunkEntities;
+
+impl SaveChunkEntities {
+    /// Groups all persistent entities by the world and chunk that they are
+    /// within.
+    fn group_entities(world: &mut World) -> HashMap<(Entity, IVec3), Vec<Entity>> {
+        let mut chunks: HashMap<(Entity, IVec3), Vec<Entity>> = HashMap::default();
+        let mut query = world.query_filtered::<(Entity, &InWorld, &Position), With<Persistent>>();
+
+        for (entity, in_world, pos) in query.iter(world) {
+            let chunk_coords = block_to_chunk(world_to_block(pos.translation));
+            chunks.entry((in_world.0, chunk_coords)).or_default().push(entity);
+        }
+
+        chunks
+    }
+}
+
+impl Command for SaveChunkEntities {
+    fn write(self, world: &mut World) {
+        // Entities may have moved into a chunk whose saved entities have not
+        // been restored yet. Such a chunk is only saved once its saved
+        // entities have been read and restored, so that the saved file is not
+        // overwritten, and its restore is requested here. Restored entities
+        // that are still waiting in the spawn queue are spawned before saving,
+        // as their chunk would otherwise be saved without them.
+        for (world_entity, chunk_coords) in Self::group_entities(world).into_keys() {
+            RestoreChunkEntities {
+                world: world_entity,
+                chunk_coords,
+            }
+            .write(world);
+        }
+
+        flush_spawn_queue(world);
+        let chunks = Self::group_entities(world);
+        let restored = world.resource::<EntityPersistence>().restored.clone();
+
+        for key in restored.iter() {
+            let entities = chunks.get(key).map_or(&[][..], |entities| entities.as_slice());
+            save_chunk(world, *key, entities);
+        }
+    }
+}
+
+
+/// Writes the given persistent entities to the save file of the chunk with the
+/// given world and chunk coordinates, removing the file if there are none.
+fn save_chunk(world: &World, key: (Entity, IVec3), entities: &[Entity]) {
+    let (world_entity, chunk_coords) = key;
+    let Some(config) = world.get::<WorldConfig>(world_entity) else {
+        return;
+    };
+
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let persistent = world.get_resource::<PersistentComponents>().cloned().unwrap_or_default();
+    let directory = world.resource::<WorldDataDirectory>();
+    let io = world.resource::<DiskIo>();
+
+    let path = directory.chunk_entities_path(&config.name, chunk_coords);
+    let entities: Vec<Vec<&dyn Reflect>> = entities
+        .iter()
+        .map(|entity| {
+            persistent
+                .iter()
+                .filter_map(|type_id| registry.get(type_id))
+                .filter_map(|registration| registration.data::<ReflectComponent>())
+                .filter_map(|reflect_component| reflect_component.reflect(world, *entity))
+                .collect()
+        })
+        .collect();
+
+    if entities.is_empty() {
+        io.remove(path);
+        return;
+    }
+
+    let text = encode_save(&EntityListSerializer {
+        entities: &entities,
+        registry: &registry,
+    });
+
+    match text {
+        Ok(text) => io.write(path, text),
+        Err(err) => error!("Failed to save entities '{}': {err}", path.display()),
+    }
+}
+
+
+/// A command that saves the persistent entities within a chunk that is being
+/// unloaded, and then despawns them. The entities are restored again by
+/// [`RestoreChunkEntities`] once the chunk is loaded.
+///
+/// If the saved entities of the chunk have not been restored yet, nothing is
+/// saved, as the saved file would otherwise be overwritten.
+#[derive(Debug, Clone)]
+pub struct UnloadChunkEntities {
+    /// The world that the chunk is within.
+    pub world: Entity,
+
+    /// The coordinates of the chunk.
+    pub chunk_coords: IVec3,
+}
+
+impl Command for UnloadChunkEntities {
+    fn write(self, world: &mut World) {
+        let key = (self.world, self.chunk_coords);
+        flush_spawn_queue(world);
+
+        let persistence = world.get_resource_or_insert_with(EntityPersistence::default);
+        if !persistence.restored.contains(&key) {
+            return;
+        }
+
+        let entities = SaveChunkEntities::group_entities(world).remove(&key).unwrap_or_default();
+        save_chunk(world, key, &entities);
+
+        for entity in entities {
+            world.despawn(entity);
+        }
+
+        world.resource_mut::<EntityPersistence>().restored.remove(&key);
+    }
+}
+
+
+/// Serializes a list of entities, where each entity is a list of reflected
+/// components.
+struct EntityListSerializer<'a> {
+    /// The components of each entity.
+    entities: &'a [Vec<&'a dyn Reflect>],
+
+    /// The type registry used to serialize the components.
+    registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for EntityListSerializer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.entities.iter().map(|components| {
+            ComponentListSerializer {
+                components,
+                registry: self.registry,
+            }
+        }))
+    }
+}
+
+
+/// Serializes the list of reflected components of a single entity.
+struct ComponentListSerializer<'a> {
+    /// The components of the entity.
+    components: &'a [&'a dyn Reflect],
+
+    /// The type registry used to serialize the components.
+    registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for ComponentListSerializer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.components
+                .iter()
+                .map(|component| ReflectSerializer::new(*component, self.registry)),
+        )
+    }
+}
+
+
+/// Deserializes a list of entities, where each entity is a list of reflected
+/// components.
+struct EntityListSeed<'a>(ComponentListSeed<'a>);
+
+impl<'a, 'de> DeserializeSeed<'de> for EntityListSeed<'a> {
+    type Value = Vec<Vec<Box<dyn Reflect>>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for EntityListSeed<'a> {
+    type Value = Vec<Vec<Box<dyn Reflect>>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of entities")
+    }
+
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entities = vec![];
+        while let Some(components) = seq.next_element_seed(self.0)? {
+            entities.push(components);
+        }
+        Ok(entities)
+    }
+}
+
+
+/// Deserializes the list of reflected components of a single entity.
+#[derive(Clone, Copy)]
+struct ComponentListSeed<'a>(&'a TypeRegistry);
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentListSeed<'a> {
+    type Value = Vec<Box<dyn Reflect>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentListSeed<'a> {
+    type Value = Vec<Box<dyn Reflect>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of components")
+    }
+
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut components = vec![];
+        while let Some(component) =
+            seq.next_element_seed(UntypedReflectDeserializer::new(self.0))?
+        {
+            components.push(component);
+        }
+        Ok(components)
+    }
+}
+
+
+/// Restores the saved entities within each chunk that is loaded.
+pub fn restore_chunk_entities(
+    mut load_chunk_ev: EventReader<LoadChunkEvent>,
+    mut commands: Commands,
+) {
+    for ev in load_chunk_ev.iter() {
+        commands.add(RestoreChunkEntities {
+            world:        ev.world,
+            chunk_coords: ev.chunk_coords,
+        });
+    }
+}
+
+
+/// Saves and despawns the persistent entities within each chunk that is
+/// unloaded.
+pub fn unload_chunk_entities(
+    mut unload_chunk_ev: EventReader<UnloadChunkEvent>,
+    mut commands: Commands,
+) {
+    for ev in unload_chunk_ev.iter() {
+        commands.add(UnloadChunkEntities {
+            world:        ev.world,
+            chunk_coords: ev.chunk_coords,
+        });
+    }
+}
+
+
+/// Periodically saves all persistent entities.
+pub fn autosave_chunk_entities(
+    time: Res<Time>,
+    mut persistence: ResMut<EntityPersistence>,
+    mut commands: Commands,
+) {
+    if persistence.autosave.tick(time.delta()).just_finished() {
+        commands.add(SaveChunkEntities);
+    }
+}
+
+
+/// Saves all persistent entities within all hosted worlds.
+///
+/// Usage: `save`
+pub fn save_command(world: &mut World, _: &CommandSender, _: &[&str]) -> Result<String> {
+    SaveChunkEntities.write(world);
+    Ok("Saved all persistent entities".to_string())
+}
@@ -0,0 +1,388 @@
+//! Handles player-specific server state, such as saving and loading persistent
+//! player data and the player-related commands.
+
+
+use crate::prelude::{
+    decode_save, encode_save, CommandSender, DiskIo, DiskReadEvent, DiskReadKind, EntitySelector, HostedWorlds, Permissions, SaveKind, SelectedSlot, TransferPlayer, WorldConfig, WorldSpawn
+};
+use anyhow::{anyhow, bail, Result};
+use awgen_network::prelude::{ClientSocket, KickClient, Replicated, HOTBAR_SLOTS};
+use awgen_physics::prelude::{GameMode, Inventory, ItemStack, Position};
+use awgen_world::prelude::{ChunkAnchor, InWorld, SafeSpawnSearch};
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+
+/// The radius, in chunks, that is kept loaded around each player.
+const PLAYER_CHUNK_RADIUS: u16 = 4;
+
+
+/// The radius, in chunks, around each player that chunks may remain loaded
+/// within before being unloaded.
+const PLAYER_MAX_CHUNK_RADIUS: u16 = 6;
+
+
+/// The loading priority of the chunk anchor of each player. Players are given
+/// a high priority, so that the chunks around them load before those requested
+/// by other anchors.
+const PLAYER_ANCHOR_PRIORITY: u32 = 100;
+
+
+/// The personal respawn point of a player. If a player does not have a respawn
+/// point, the spawn point of the default world is used instead.
+#[derive(Debug, Clone, PartialEq, Reflect, Component, Default, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct RespawnPoint {
+    /// The name of the world to respawn within.
+    pub world: String,
+
+    /// The position within the world to respawn at.
+    pub position: Vec3,
+}
+
+
+/// The persistent data of a single player that is saved between sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerData {
+    /// The game mode of the player.
+    pub game_mode: GameMode,
+
+    /// The personal respawn point of the player, if any.
+    pub respawn_point: Option<RespawnPoint>,
+
+    /// The permission nodes that have been granted to the player.
+    #[serde(default)]
+    pub permissions: Permissions,
+
+    /// The item stack within each hotbar slot of the player.
+    #[serde(default)]
+    pub hotbar: Vec<ItemStack>,
+}
+
+
+/// The directory that player data files are stored within.
+#[derive(Debug, Clone, Resource)]
+pub struct PlayerDataDirectory(pub PathBuf);
+
+impl PlayerDataDirectory {
+    /// Gets the path of the data file for the player with the given client id.
+    pub fn path(&self, client_id: u64) -> PathBuf {
+        self.0.join(format!("{client_id}.ron"))
+    }
+
+
+    /// Requests the data of the given player entity, with the given client id,
+    /// to be read from disk. The data is applied to the player by
+    /// [`apply_player_data`] once it has been read.
+    pub fn load(&self, io: &DiskIo, player: Entity, client_id: u64) {
+        let kind = DiskReadKind::PlayerData {
+            player,
+            client_id,
+        };
+        io.read(kind, self.path(client_id));
+    }
+
+
+    /// Requests the data of the player with the given client id to be saved.
+    pub fn save(&self, io: &DiskIo, client_id: u64, data: &PlayerData) -> Result<()> {
+        io.write(self.path(client_id), encode_save(data)?);
+        Ok(())
+    }
+}
+
+impl Default for PlayerDataDirectory {
+    fn default() -> Self {
+        Self(PathBuf::from("world/players"))
+    }
+}
+
+
+/// Requests the saved player data of each newly connected player to be read
+/// from disk.
+pub fn load_player_data(
+    io: Res<DiskIo>,
+    directory: Res<PlayerDataDirectory>,
+    new_players: Query<(Entity, &ClientSocket), Added<ClientSocket>>,
+) {
+    for (entity, socket) in new_players.iter() {
+        directory.load(&io, entity, socket.id());
+    }
+}
+
+
+/// Applies the saved player data of each player once it has been read, and
+/// spawns them into the world. Player entities are replicated to all other
+/// players within the same world.
+pub fn apply_player_data(mut read_ev: EventReader<DiskReadEvent>, mut commands: Commands) {
+    for ev in read_ev.iter() {
+        let DiskReadKind::PlayerData {
+            player,
+            client_id,
+        } = ev.kind
+        else {
+            continue;
+        };
+
+        let data = match &ev.result {
+            Ok(Some(text)) => decode_save(text, SaveKind::Player),
+            Ok(None) => Ok(PlayerData::default()),
+            Err(err) => Err(anyhow!("{err}")),
+        };
+
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                error!("Failed to load player data for client {client_id}: {err}");
+                PlayerData::default()
+            },
+        };
+
+        let mut hotbar = Inventory::new(HOTBAR_SLOTS);
+        for (slot, stack) in data.hotbar.into_iter().enumerate() {
+            hotbar.set(slot, stack);
+        }
+
+        let Some(mut entity) = commands.get_entity(player) else {
+            continue;
+        };
+
+        entity.insert((data.game_mode, data.permissions, Replicated));
+        entity.insert((hotbar, SelectedSlot::default()));
+
+        if let Some(respawn_point) = data.respawn_point {
+            entity.insert(respawn_point);
+        }
+
+        commands.add(RespawnPlayer(player));
+    }
+}
+
+
+/// Saves the player data of each player whenever it is modified.
+#[allow(clippy::type_complexity)]
+pub fn save_player_data(
+    io: Res<DiskIo>,
+    directory: Res<PlayerDataDirectory>,
+    players: Query<
+        (
+            &ClientSocket,
+            &GameMode,
+            Option<&RespawnPoint>,
+            Option<&Permissions>,
+            Option<&Inventory>,
+        ),
+        Or<(
+            Changed<GameMode>,
+            Changed<RespawnPoint>,
+            Changed<Permissions>,
+            Changed<Inventory>,
+        )>,
+    >,
+) {
+    for (socket, game_mode, respawn_point, permissions, hotbar) in players.iter() {
+        let data = PlayerData {
+            game_mode:     *game_mode,
+            respawn_point: respawn_point.cloned(),
+            permissions:   permissions.cloned().unwrap_or_default(),
+            hotbar:        hotbar.map(|h| h.slots().to_vec()).unwrap_or_default(),
+        };
+
+        if let Err(err) = directory.save(&io, socket.id(), &data) {
+            error!(
+                "Failed to save player data for client {}: {err}",
+                socket.id()
+            );
+        }
+    }
+}
+
+
+/// Finds the player entity with the given client id.
+pub fn find_player(world: &mut World, client_id: u64) -> Result<Entity> {
+    world
+        .query::<(Entity, &ClientSocket)>()
+        .iter(world)
+        .find(|(_, socket)| socket.id() == client_id)
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| anyhow!("No player with the client id {client_id} is online"))
+}
+
+
+/// Gets the player targeted by a command, either from the given client id or
+/// selector argument, or by defaulting to the command sender.
+///
+/// A selector must match exactly one player.
+pub fn command_target(
+    world: &mut World,
+    sender: &CommandSender,
+    client_id: Option<&&str>,
+) -> Result<Entity> {
+    match (client_id, sender) {
+        (Some(arg), _) if EntitySelector::is_selector(arg) => {
+            let entities = arg.parse::<EntitySelector>()?.resolve(world, sender)?;
+            match entities[..] {
+                [entity] if world.get::<ClientSocket>(entity).is_some() => Ok(entity),
+                [_] => bail!("The selector {arg} did not match a player"),
+                _ => {
+                    bail!(
+                        "The selector {arg} matched {} entities instead of one",
+                        entities.len()
+                    )
+                },
+            }
+        },
+        (Some(client_id), _) => find_player(world, client_id.parse()?),
+        (None, CommandSender::Player(player)) => Ok(*player),
+        (None, CommandSender::Console | CommandSender::Remote(_)) => {
+            bail!("A target player must be given from the console")
+        },
+    }
+}
+
+
+/// Changes the game mode of a player.
+///
+/// Usage: `gamemode <mode> [client id]`
+pub fn gamemode_command(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String> {
+    let Some(mode) = args.first() else {
+        bail!("Usage: gamemode <survival|creative|spectator> [client id]");
+    };
+
+    let mode: GameMode = mode.parse()?;
+    let target = command_target(world, sender, args.get(1))?;
+    let mut player = world.entity_mut(target);
+    player.insert(mode);
+
+    let client_id = player.get::<ClientSocket>().map_or(0, |socket| socket.id());
+    Ok(format!("Set the game mode of player {client_id} to {mode}"))
+}
+
+
+/// A command that moves a player to their respawn point, or to the spawn point
+/// of the default world if they do not have one, and then searches for the
+/// nearest safe position to stand at.
+///
+/// If the player does not yet have a chunk anchor, one is added.
+#[derive(Debug, Clone)]
+pub struct RespawnPlayer(pub Entity);
+
+impl Command for RespawnPlayer {
+    fn write(self, world: &mut World) {
+        let player = self.0;
+        let hosted = world.resource::<HostedWorlds>();
+
+        let respawn = world.get::<RespawnPoint>(player).and_then(|point| {
+            let target = hosted.get(&point.world)?;
+            Some((target, point.position))
+        });
+
+        let target = respawn.or_else(|| {
+            let target = hosted.default_world()?;
+            let spawn = world.get::<WorldSpawn>(target).map(|s| s.position);
+            Some((target, spawn.unwrap_or_default()))
+        });
+
+        let Some((target, position)) = target else {
+            warn!("No world available to spawn player {player:?} into");
+            return;
+        };
+
+        let Some(mut entity) = world.get_entity_mut(player) else {
+            return;
+        };
+
+        if !entity.contains::<ChunkAnchor>() {
+            entity.insert(
+                ChunkAnchor::new(target, PLAYER_CHUNK_RADIUS, PLAYER_MAX_CHUNK_RADIUS)
+                    .with_priority(PLAYER_ANCHOR_PRIORITY),
+            );
+        }
+
+        entity.insert(SafeSpawnSearch::default());
+
+        TransferPlayer {
+            player,
+            world: target,
+            position,
+        }
+        .write(world);
+    }
+}
+
+
+/// Changes the personal respawn point of a player.
+///
+/// Usage: `spawnpoint [client id] [<x> <y> <z>]`
+///
+/// If no position is given, the current position of the player is used.
+pub fn spawnpoint_command(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String> {
+    let (target, coords) = match args {
+        [x, y, z] => (command_target(world, sender, None)?, Some((x, y, z))),
+        [id, x, y, z] => (command_target(world, sender, Some(id))?, Some((x, y, z))),
+        [id] => (command_target(world, sender, Some(id))?, None),
+        [] => (command_target(world, sender, None)?, None),
+        _ => bail!("Usage: spawnpoint [client id] [<x> <y> <z>]"),
+    };
+
+    let position = match coords {
+        Some((x, y, z)) => Vec3::new(x.parse()?, y.parse()?, z.parse()?),
+        None => world.get::<Position>(target).map(|p| p.translation).unwrap_or_default(),
+    };
+
+    let Some(in_world) = world.get::<InWorld>(target).map(|w| w.0) else {
+        bail!("The target player is not within a world");
+    };
+
+    let world_name = world.get::<WorldConfig>(in_world).map(|c| c.name.clone()).unwrap_or_default();
+    world.entity_mut(target).insert(RespawnPoint {
+        world: world_name.clone(),
+        position,
+    });
+
+    Ok(format!(
+        "Set the respawn point of {target:?} to {position} in world '{world_name}'"
+    ))
+}
+
+
+/// Respawns a player at their respawn point.
+///
+/// Usage: `respawn [client id]`
+pub fn respawn_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    let target = command_target(world, sender, args.first())?;
+    RespawnPlayer(target).write(world);
+    Ok(format!("Respawned {target:?}"))
+}
+
+
+/// Disconnects a player from the server, reporting the given reason to them.
+///
+/// Usage: `kick <client id> [reason]`
+pub fn kick_command(world: &mut World, _: &CommandSender, args: &[&str]) -> Result<String> {
+    let Some((client_id, reason)) = args.split_first() else {
+        bail!("Usage: kick <client id> [reason]");
+    };
+
+    let client_id = client_id.parse()?;
+    find_player(world, client_id)?;
+
+    let reason = match reason.is_empty() {
+        true => "Kicked by an operator".to_string(),
+        false => reason.join(" "),
+    };
+
+    world
+        .resource_mut::<Events<KickClient>>()
+        .send(KickClient::from_id(client_id, reason.clone()));
+    Ok(format!("Kicked player {client_id}: {reason}"))
+}
@@ -0,0 +1,285 @@
+//! Handles pre-generating the chunks around the spawn point of a world,
+//! allowing for servers to warm their worlds before players begin to join.
+
+
+use crate::prelude::{CommandSender, HostedWorlds, WorldConfig, WorldSpawn};
+use anyhow::{bail, Result};
+use awgen_math::prelude::{world_to_chunk, Region};
+use awgen_world::prelude::{ChunkState, LoadChunkEvent, VoxelChunkStates};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+
+/// The maximum number of pre-generation chunks that may be waiting on the
+/// chunk generation tasks at once.
+const MAX_PREGEN_IN_FLIGHT: usize = 64;
+
+
+/// The number of seconds between each pre-generation progress report.
+const PREGEN_REPORT_INTERVAL: f32 = 5.0;
+
+
+/// A request to pre-generate all chunks within a radius around the spawn point
+/// of a world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PregenRequest {
+    /// The world to pre-generate.
+    pub world: Entity,
+
+    /// The radius, in chunks, around the world spawn to pre-generate.
+    pub radius: u16,
+}
+
+
+/// The progress of the pre-generation request that is currently running.
+#[derive(Debug, Clone)]
+pub struct PregenTask {
+    /// The request being processed.
+    request: PregenRequest,
+
+    /// The name of the world being pre-generated, for progress reports.
+    world_name: String,
+
+    /// The chunks that have not yet been queued for generation.
+    pending: Vec<IVec3>,
+
+    /// The chunks that are currently being generated.
+    in_flight: Vec<IVec3>,
+
+    /// The total number of chunks within this task.
+    total: usize,
+
+    /// The number of chunks that have finished generating.
+    completed: usize,
+}
+
+impl PregenTask {
+    /// Creates a new pre-generation task for all chunks within the request
+    /// radius around the given chunk coordinates.
+    fn new(request: PregenRequest, world_name: String, center: IVec3) -> Self {
+        let radius = request.radius as i32;
+        let region = Region::from_points(center - radius, center + radius);
+        let pending: Vec<IVec3> = region.iter().collect();
+
+        Self {
+            request,
+            world_name,
+            total: pending.len(),
+            pending,
+            in_flight: vec![],
+            completed: 0,
+        }
+    }
+
+
+    /// Gets the request that is being processed by this task.
+    pub fn request(&self) -> &PregenRequest {
+        &self.request
+    }
+
+
+    /// Gets the number of chunks that have finished generating.
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+
+    /// Gets the total number of chunks within this task.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+
+    /// Gets the fraction of this task that has been completed, within the
+    /// range 0 to 1.
+    pub fn progress(&self) -> f32 {
+        match self.total {
+            0 => 1.0,
+            total => self.completed as f32 / total as f32,
+        }
+    }
+
+
+    /// Gets whether or not all chunks within this task have been generated.
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty() && self.in_flight.is_empty()
+    }
+}
+
+
+/// A queue of world pre-generation requests. Requests are processed one at a
+/// time, in the order they were added.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PregenQueue {
+    /// The requests that are waiting to be started.
+    queued: VecDeque<PregenRequest>,
+
+    /// The task that is currently running, if any.
+    active: Option<PregenTask>,
+}
+
+impl PregenQueue {
+    /// Adds a new pre-generation request to the end of this queue.
+    pub fn push(&mut self, request: PregenRequest) {
+        self.queued.push_back(request);
+    }
+
+
+    /// Gets the task that is currently running, if any.
+    pub fn active(&self) -> Option<&PregenTask> {
+        self.active.as_ref()
+    }
+
+
+    /// Gets the number of requests that are waiting to be started.
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+
+
+    /// Cancels the active task and all queued requests.
+    ///
+    /// Chunks that are already being generated will still finish generating.
+    pub fn clear(&mut self) {
+        self.queued.clear();
+        self.active = None;
+    }
+
+
+    /// Gets whether or not there are no running or queued requests.
+    pub fn is_idle(&self) -> bool {
+        self.active.is_none() && self.queued.is_empty()
+    }
+}
+
+
+/// Processes the active pre-generation task, requesting new chunks to be
+/// generated as previous chunks finish and periodically reporting progress.
+pub fn run_pregen(
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    mut queue: ResMut<PregenQueue>,
+    mut worlds: Query<(&WorldConfig, &WorldSpawn, &mut VoxelChunkStates)>,
+    mut load_chunk_ev: EventWriter<LoadChunkEvent>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("chunk_generation", stage = "pregen").entered();
+
+    if queue.active.is_none() {
+        let Some(request) = queue.queued.front() else {
+            return;
+        };
+
+        // The world spawn is loaded a frame after the world is added, so wait.
+        let Ok((config, spawn, _)) = worlds.get(request.world) else {
+            return;
+        };
+
+        let center = world_to_chunk(spawn.position);
+        let task = PregenTask::new(request.clone(), config.name.clone(), center);
+        info!(
+            "Pre-generating {} chunks in world '{}'",
+            task.total, task.world_name
+        );
+
+        queue.queued.pop_front();
+        queue.active = Some(task);
+        *timer = 0.0;
+    }
+
+    let task = queue.active.as_mut().unwrap();
+    let world = task.request.world;
+    let Ok((_, _, mut states)) = worlds.get_mut(world) else {
+        warn!("Cancelled pre-generation of removed world {world:?}");
+        queue.active = None;
+        return;
+    };
+
+    let in_flight = task.in_flight.len();
+    task.in_flight.retain(|chunk| states.get_state(*chunk) == ChunkState::Loading);
+    task.completed += in_flight - task.in_flight.len();
+
+    let mut deferred = vec![];
+    while task.in_flight.len() < MAX_PREGEN_IN_FLIGHT {
+        let Some(chunk) = task.pending.pop() else {
+            break;
+        };
+
+        match states.get_state(chunk) {
+            ChunkState::Unloaded => {
+                states.set_state(chunk, ChunkState::Loading);
+                load_chunk_ev.send(LoadChunkEvent {
+                    chunk_coords: chunk,
+                    world,
+                });
+                task.in_flight.push(chunk);
+            },
+            ChunkState::Loading => task.in_flight.push(chunk),
+            ChunkState::Loaded => task.completed += 1,
+            ChunkState::Unloading => deferred.push(chunk),
+        }
+    }
+    task.pending.append(&mut deferred);
+
+    if task.is_finished() {
+        info!(
+            "Finished pre-generating {} chunks in world '{}'",
+            task.total, task.world_name
+        );
+        queue.active = None;
+        return;
+    }
+
+    *timer += time.delta_seconds();
+    if *timer >= PREGEN_REPORT_INTERVAL {
+        *timer = 0.0;
+        info!(
+            "Pre-generating world '{}': {}/{} chunks ({:.1}%)",
+            task.world_name,
+            task.completed,
+            task.total,
+            task.progress() * 100.0
+        );
+    }
+}
+
+
+/// Queues the chunks around the spawn point of a world to be pre-generated.
+///
+/// Usage: `pregen <radius> [world]` or `pregen cancel`
+///
+/// If no world is given, the default world is used.
+pub fn pregen_command(world: &mut World, _: &CommandSender, args: &[&str]) -> Result<String> {
+    let (radius, world_name) = match args {
+        ["cancel"] => {
+            world.resource_mut::<PregenQueue>().clear();
+            return Ok("Cancelled all world pre-generation".to_string());
+        },
+        [radius] => (radius.parse::<u16>()?, None),
+        [radius, name] => (radius.parse::<u16>()?, Some(*name)),
+        _ => bail!("Usage: pregen <radius> [world] or pregen cancel"),
+    };
+
+    let hosted = world.resource::<HostedWorlds>();
+    let target = match world_name {
+        Some(name) => hosted.get(name),
+        None => hosted.default_world(),
+    };
+
+    let Some(target) = target else {
+        bail!("Unknown world: {}", world_name.unwrap_or("default"));
+    };
+
+    let mut queue = world.resource_mut::<PregenQueue>();
+    queue.push(PregenRequest {
+        world: target,
+        radius,
+    });
+
+    let diameter = radius as usize * 2 + 1;
+    Ok(format!(
+        "Queued {} chunks for pre-generation ({} requests waiting)",
+        diameter.pow(3),
+        queue.queued()
+    ))
+}
@@ -0,0 +1,242 @@
+//! Decides which replicated entities are visible to each player, and sends the
+//! collected replication state of those entities to them each tick.
+
+
+use crate::prelude::{PlayerTransferredEvent, Spectating};
+use awgen_network::prelude::{
+    network_id, Authority, ClientSocket, ComponentData, EntityUpdateMessage, OutgoingQueue, OwnedBy, Replicated, ReplicationMessage, ReplicationOutbox, ReplicationRegistry, SendPriority
+};
+use awgen_world::prelude::InWorld;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+
+/// The replicated entities that have been spawned on the client of a player.
+#[derive(Debug, Clone, Default, Component)]
+pub struct ReplicationView {
+    /// The server entities that the client has a proxy of.
+    entities: HashSet<Entity>,
+
+    /// The server entities that the client has been told it has authority
+    /// over.
+    authority: HashSet<Entity>,
+}
+
+impl ReplicationView {
+    /// Gets whether or not the client has a proxy of the given entity.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+
+    /// Gets the number of entities that the client has a proxy of.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+
+    /// Gets whether or not the client has no entity proxies.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+
+/// Gets the entity that a player observes the world from, which is the player
+/// that they are spectating, if any, or the player themselves otherwise.
+pub fn observed_entity(player: Entity, spectating: Option<&Spectating>) -> Entity {
+    spectating.map_or(player, |s| s.target)
+}
+
+
+/// Adds a replication view to each newly connected player.
+pub fn insert_replication_views(
+    players: Query<Entity, (With<ClientSocket>, Without<ReplicationView>)>,
+    mut commands: Commands,
+) {
+    for player in players.iter() {
+        commands.entity(player).insert(ReplicationView::default());
+    }
+}
+
+
+/// Clears the replication view of each player that has been transferred into
+/// another world, along with the views of all players spectating them.
+///
+/// Every entity that the client has a proxy of is despawned, so that no proxies
+/// from the previous world are left behind, and all entities within the new
+/// world are spawned again with their full state by [`send_replication`].
+pub fn reset_transferred_views(
+    mut transferred_ev: EventReader<PlayerTransferredEvent>,
+    mut queue: ResMut<OutgoingQueue>,
+    mut players: Query<(
+        Entity,
+        &ClientSocket,
+        Option<&Spectating>,
+        &mut ReplicationView,
+    )>,
+) {
+    let transferred: HashSet<Entity> = transferred_ev.iter().map(|ev| ev.player).collect();
+    if transferred.is_empty() {
+        return;
+    }
+
+    for (player, socket, spectating, mut view) in players.iter_mut() {
+        if !transferred.contains(&observed_entity(player, spectating))
+            && !transferred.contains(&player)
+        {
+            continue;
+        }
+
+        for entity in view.entities.drain() {
+            let id = network_id(entity);
+            queue.push_keyed(
+                socket.id(),
+                SendPriority::Low,
+                id,
+                &ReplicationMessage::Despawn {
+                    entity: id,
+                },
+            );
+        }
+
+        view.authority.clear();
+    }
+}
+
+
+/// Sends the replication state that was collected on the current tick to each
+/// player.
+///
+/// Each player is able to see all replicated entities within the same world,
+/// other than their own player entity. Spectators instead see all replicated
+/// entities within the world of the player that they are spectating. Entities
+/// that become visible are spawned on the client with their full state,
+/// entities that are no longer visible are despawned, and all other visible
+/// entities are sent the components that have changed.
+///
+/// Spawns and despawns are queued with a low priority, so that a player who
+/// has just joined cannot delay the changed components sent to other players.
+/// While the spawn of an entity is still waiting to be sent, it is replaced
+/// with the latest full state of the entity instead of sending its changes.
+///
+/// Each client is told its authority over every entity that it can see, and
+/// is never sent changes to the client-authoritative components of entities
+/// that it has authority over, as it writes those itself.
+#[allow(clippy::type_complexity)]
+pub fn send_replication(
+    mut outbox: ResMut<ReplicationOutbox>,
+    mut queue: ResMut<OutgoingQueue>,
+    mut players: Query<(
+        Entity,
+        &ClientSocket,
+        &InWorld,
+        Option<&Spectating>,
+        &mut ReplicationView,
+    )>,
+    replicated: Query<(Entity, &InWorld, Option<&Authority>, Option<&OwnedBy>), With<Replicated>>,
+    worlds: Query<&InWorld>,
+    registry: Res<ReplicationRegistry>,
+) {
+    if !outbox.is_ready() {
+        return;
+    }
+
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("network", direction = "send", message = "replication").entered();
+
+    let tick = outbox.tick();
+
+    for (player, socket, player_world, spectating, mut view) in players.iter_mut() {
+        let observed = observed_entity(player, spectating);
+        let viewed_world = worlds.get(observed).unwrap_or(player_world).0;
+
+        let visible: HashSet<Entity> = replicated
+            .iter()
+            .filter(|(entity, in_world, ..)| *entity != player && in_world.0 == viewed_world)
+            .map(|(entity, ..)| entity)
+            .collect();
+
+        let client_id = socket.id();
+        let authority_of = |entity: Entity| {
+            let (_, _, authority, owner) = replicated.get(entity).unwrap();
+            Authority::of_client(authority, owner, client_id)
+        };
+
+        for entity in view.entities.difference(&visible) {
+            let id = network_id(*entity);
+            queue.push_keyed(
+                client_id,
+                SendPriority::Low,
+                id,
+                &ReplicationMessage::Despawn {
+                    entity: id,
+                },
+            );
+        }
+
+        let mut updates = Vec::new();
+        for entity in visible.iter() {
+            let id = network_id(*entity);
+            let spawned = view.entities.contains(entity)
+                && !queue.contains_key::<ReplicationMessage>(client_id, id);
+
+            let authority = authority_of(*entity);
+            let has_authority = authority == Authority::Client;
+
+            match spawned {
+                true => {
+                    if has_authority != view.authority.contains(entity) {
+                        queue.push(
+                            client_id,
+                            SendPriority::Normal,
+                            &ReplicationMessage::Authority {
+                                entity: id,
+                                authority,
+                            },
+                        );
+                    }
+
+                    let changes: Vec<ComponentData> = outbox
+                        .changes(*entity)
+                        .into_iter()
+                        .filter(|c| !has_authority || !registry.is_client_authoritative(c.id))
+                        .collect();
+
+                    if !changes.is_empty() {
+                        updates.push((id, changes));
+                    }
+                },
+                false => {
+                    queue.push_keyed(
+                        client_id,
+                        SendPriority::Low,
+                        id,
+                        &ReplicationMessage::Spawn {
+                            entity: id,
+                            tick,
+                            authority,
+                            components: outbox.full_state(*entity),
+                        },
+                    );
+                },
+            }
+        }
+
+        if !updates.is_empty() {
+            queue.push(client_id, SendPriority::Normal, &EntityUpdateMessage {
+                tick,
+                entities: updates,
+            });
+        }
+
+        view.authority = visible
+            .iter()
+            .copied()
+            .filter(|e| authority_of(*e) == Authority::Client)
+            .collect();
+        view.entities = visible;
+    }
+
+    outbox.finish_tick();
+}
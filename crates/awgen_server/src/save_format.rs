@@ -0,0 +1,371 @@
+//! Handles the versioned save format used by all world, chunk, player, and
+//! server data files. Each file stores the save format version that it was
+//! written with, and older files are upgraded through a chain of migrations
+//! when loaded.
+//!
+//! Voxel data is regenerated from the world seed and is not saved, so chunk
+//! files only contain the persistent entities within each chunk.
+//!
+//! When the layout of a saved data type changes, the save format version
+//! should be increased and a migration, such as `migrate_v1_to_v2`, should be
+//! added to the migration list of each affected [`SaveKind`]. A migration
+//! receives the full text of a file at its version, and returns the text of
+//! the same file at the next version.
+
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::Path;
+use std::{fmt, fs};
+
+
+/// The current version of the save format. Files written by this version of
+/// Awgen are tagged with this version.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+
+/// A migration that upgrades the text of a save file from one save format
+/// version to the next.
+pub type Migration = fn(&str) -> Result<String>;
+
+
+/// The kinds of save files that are written by Awgen. Each kind has its own
+/// list of migrations, as not every format change affects every file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SaveKind {
+    /// World metadata and world spawn point files.
+    World,
+
+    /// Persistent chunk entity files.
+    Chunk,
+
+    /// Persistent player data files.
+    Player,
+
+    /// Server-wide data files, such as the ban list.
+    Server,
+}
+
+impl SaveKind {
+    /// Gets the migrations for this kind of save file, where the migration at
+    /// index `n` upgrades a file from version `n` to version `n + 1`.
+    fn migrations(&self) -> &'static [Migration] {
+        match self {
+            SaveKind::World => &[migrate_v0_to_v1],
+            SaveKind::Chunk => &[migrate_v0_to_v1],
+            SaveKind::Player => &[migrate_v0_to_v1],
+            SaveKind::Server => &[migrate_v0_to_v1],
+        }
+    }
+}
+
+
+/// The layout of a save file, containing the save format version alongside
+/// the saved data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SaveFile<T> {
+    /// The save format version that this file was written with.
+    format_version: u32,
+
+    /// The saved data.
+    data: T,
+}
+
+
+/// The header of a save file, used to read the save format version without
+/// parsing the saved data.
+#[derive(Debug, Clone, Deserialize)]
+struct SaveHeader {
+    /// The save format version of the file. Files that were written before
+    /// save format versions were introduced are treated as version 0.
+    #[serde(default)]
+    format_version: u32,
+}
+
+
+/// The fields of a save file, used when deserializing a save file with a
+/// [`SaveFileSeed`].
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum SaveFileField {
+    /// The save format version field.
+    FormatVersion,
+
+    /// The saved data field.
+    Data,
+}
+
+
+/// Deserializes a save file, using the given seed to deserialize the saved
+/// data.
+struct SaveFileSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for SaveFileSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        deserializer.deserialize_struct("SaveFile", &["format_version", "data"], self)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for SaveFileSeed<S> {
+    type Value = S::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a save file")
+    }
+
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<S::Value, A::Error> {
+        let mut seed = Some(self.0);
+        let mut data = None;
+
+        while let Some(field) = map.next_key::<SaveFileField>()? {
+            match field {
+                SaveFileField::FormatVersion => {
+                    map.next_value::<u32>()?;
+                },
+                SaveFileField::Data => {
+                    let seed = seed.take().ok_or_else(|| de::Error::duplicate_field("data"))?;
+                    data = Some(map.next_value_seed(seed)?);
+                },
+            }
+        }
+
+        data.ok_or_else(|| de::Error::missing_field("data"))
+    }
+}
+
+
+/// Encodes the given data as the text of a save file, tagged with the current
+/// save format version.
+pub fn encode_save<T: Serialize>(data: &T) -> Result<String> {
+    let file = SaveFile {
+        format_version: SAVE_FORMAT_VERSION,
+        data,
+    };
+
+    Ok(ron::ser::to_string_pretty(&file, default())?)
+}
+
+
+/// Decodes the text of a save file of the given kind, migrating it to the
+/// current save format version if needed.
+///
+/// An error is returned if the file was written with a newer save format
+/// version than this version of Awgen supports.
+pub fn decode_save<T: DeserializeOwned>(text: &str, kind: SaveKind) -> Result<T> {
+    let text = migrate(text, kind)?;
+    let file: SaveFile<T> = ron::from_str(&text)?;
+    Ok(file.data)
+}
+
+
+/// Decodes the text of a save file of the given kind, migrating it to the
+/// current save format version if needed, and using the given seed to
+/// deserialize the saved data.
+///
+/// This is used for saved data that requires extra state to deserialize, such
+/// as reflected components.
+pub fn decode_save_seed<S, T>(text: &str, kind: SaveKind, seed: S) -> Result<T>
+where S: for<'de> DeserializeSeed<'de, Value = T> {
+    let text = migrate(text, kind)?;
+    let mut deserializer = ron::Deserializer::from_str(&text)?;
+    let data = SaveFileSeed(seed).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(data)
+}
+
+
+/// Reads and decodes the save file of the given kind at the given path.
+///
+/// If the file does not exist, `None` is returned.
+pub fn read_save<T: DeserializeOwned>(path: &Path, kind: SaveKind) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let text = fs::read_to_string(path)?;
+    Ok(Some(decode_save(&text, kind)?))
+}
+
+
+/// Encodes the given data and writes it to the save file at the given path,
+/// creating the parent directory if needed.
+pub fn write_save<T: Serialize>(path: &Path, data: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, encode_save(data)?)?;
+    Ok(())
+}
+
+
+/// Migrates the text of a save file of the given kind to the current save
+/// format version.
+fn migrate(text: &str, kind: SaveKind) -> Result<String> {
+    migrate_with(text, kind, kind.migrations(), SAVE_FORMAT_VERSION)
+}
+
+
+/// Migrates the text of a save file of the given kind to the given save format
+/// version, using the given list of migrations.
+///
+/// Files whose save format version cannot be read, such as files that were
+/// written before save format versions were introduced and whose data is not
+/// a struct, are treated as version 0.
+fn migrate_with(
+    text: &str,
+    kind: SaveKind,
+    migrations: &[Migration],
+    target: u32,
+) -> Result<String> {
+    let from = ron::from_str::<SaveHeader>(text).map_or(0, |header| header.format_version);
+    if from > target {
+        bail!(
+            "Save file was written with format version {from}, but only versions up to \
+             {target} are supported"
+        );
+    }
+
+    let mut text = text.to_string();
+    for version in from..target {
+        let migration = migrations
+            .get(version as usize)
+            .ok_or_else(|| anyhow!("Missing {kind:?} save migration from version {version}"))?;
+
+        text = migration(&text)?;
+    }
+
+    if from < target {
+        debug!("Migrated {kind:?} save file from format version {from} to {target}");
+    }
+
+    Ok(text)
+}
+
+
+/// Upgrades a save file that was written before save format versions were
+/// introduced, by wrapping the unchanged data within a version 1 save file.
+fn migrate_v0_to_v1(text: &str) -> Result<String> {
+    Ok(format!("(format_version: 1, data: {text})"))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// A saved data type for testing.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestData {
+        /// A saved name.
+        name: String,
+
+        /// A saved count.
+        count: u32,
+    }
+
+
+    #[test]
+    fn encode_and_decode() {
+        let data = TestData {
+            name:  "lobby".to_string(),
+            count: 3,
+        };
+
+        let text = encode_save(&data).unwrap();
+        assert_eq!(
+            decode_save::<TestData>(&text, SaveKind::World).unwrap(),
+            data
+        );
+    }
+
+
+    #[test]
+    fn wrap_v0_struct() {
+        let text = "(name: \"lobby\", count: 3)";
+        assert_eq!(
+            migrate(text, SaveKind::World).unwrap(),
+            "(format_version: 1, data: (name: \"lobby\", count: 3))"
+        );
+        assert_eq!(
+            decode_save::<TestData>(text, SaveKind::World).unwrap(),
+            TestData {
+                name:  "lobby".to_string(),
+                count: 3,
+            }
+        );
+    }
+
+
+    #[test]
+    fn wrap_v0_list() {
+        let text = "[(name: \"a\", count: 1), (name: \"b\", count: 2)]";
+        let data: Vec<TestData> = decode_save(text, SaveKind::Chunk).unwrap();
+        assert_eq!(data, vec![
+            TestData {
+                name:  "a".to_string(),
+                count: 1,
+            },
+            TestData {
+                name:  "b".to_string(),
+                count: 2,
+            },
+        ]);
+    }
+
+
+    #[test]
+    fn keep_current_version() {
+        let text = "(format_version: 1, data: (name: \"lobby\", count: 3))";
+        assert_eq!(migrate(text, SaveKind::Player).unwrap(), text);
+    }
+
+
+    #[test]
+    fn reject_newer_version() {
+        let text = "(format_version: 2, data: (name: \"lobby\", count: 3))";
+        assert!(migrate(text, SaveKind::World).is_err());
+        assert!(decode_save::<TestData>(text, SaveKind::World).is_err());
+    }
+
+
+    /// A test migration that renames the `count` field to `amount`.
+    fn migrate_v1_to_v2(text: &str) -> Result<String> {
+        Ok(text
+            .replace("format_version: 1", "format_version: 2")
+            .replace("count:", "amount:"))
+    }
+
+
+    #[test]
+    fn chain_migrations() {
+        let migrations: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
+        let text = "(name: \"lobby\", count: 3)";
+        assert_eq!(
+            migrate_with(text, SaveKind::World, migrations, 2).unwrap(),
+            "(format_version: 2, data: (name: \"lobby\", amount: 3))"
+        );
+
+        let text = "(format_version: 1, data: (name: \"lobby\", count: 3))";
+        assert_eq!(
+            migrate_with(text, SaveKind::World, migrations, 2).unwrap(),
+            "(format_version: 2, data: (name: \"lobby\", amount: 3))"
+        );
+    }
+
+
+    #[test]
+    fn missing_migration() {
+        let migrations: &[Migration] = &[migrate_v0_to_v1];
+        let text = "(format_version: 1, data: (name: \"lobby\", count: 3))";
+        assert!(migrate_with(text, SaveKind::World, migrations, 2).is_err());
+    }
+}
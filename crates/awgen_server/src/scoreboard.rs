@@ -0,0 +1,419 @@
+//! The scoreboard, which tracks the scores of players within named objectives,
+//! such as the kills of each player within a mini-game.
+//!
+//! Scores are held by name, rather than by entity, so that a player keeps their
+//! scores when they reconnect, and so that scripts may track scores for names
+//! that are not players, such as the remaining time of a round. Objectives may
+//! be shown to clients within a [`DisplaySlot`].
+//!
+//! The scoreboard may be changed by the `scoreboard` command, or directly
+//! through the [`Scoreboard`] resource.
+
+
+use crate::prelude::{command_targets, CommandSender, EntitySelector};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{
+    broadcast, send_to_client, ClientSocket, DisplaySlot, DisplayedObjective, MessageBatch, PlayerName, ScoreEntry, ScoreboardMessage, SortOrder
+};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// A single scoreboard objective, and the score of each score holder within
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Objective {
+    /// The title that this objective is shown with.
+    display_name: String,
+
+    /// The order that the scores of this objective are sorted in.
+    sort: SortOrder,
+
+    /// The score of each score holder, by name.
+    scores: HashMap<String, i32>,
+}
+
+impl Objective {
+    /// Gets the title that this objective is shown with.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+
+    /// Gets the order that the scores of this objective are sorted in.
+    pub fn sort(&self) -> SortOrder {
+        self.sort
+    }
+
+
+    /// Gets the score of the given score holder, if they have one.
+    pub fn score(&self, holder: &str) -> Option<i32> {
+        self.scores.get(holder).copied()
+    }
+
+
+    /// Gets the score of each score holder, sorted by score in the sort order
+    /// of this objective, and then by name.
+    pub fn sorted_scores(&self) -> Vec<ScoreEntry> {
+        let mut scores: Vec<ScoreEntry> = self
+            .scores
+            .iter()
+            .map(|(holder, score)| {
+                ScoreEntry {
+                    holder: holder.clone(),
+                    score:  *score,
+                }
+            })
+            .collect();
+
+        scores.sort_by(|a, b| {
+            let order = match self.sort {
+                SortOrder::Descending => b.score.cmp(&a.score),
+                SortOrder::Ascending => a.score.cmp(&b.score),
+            };
+            order.then_with(|| a.holder.cmp(&b.holder))
+        });
+
+        scores
+    }
+}
+
+
+/// A server-side resource that stores every scoreboard objective, and the
+/// objective that is shown within each display slot.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Scoreboard {
+    /// Each objective, by name.
+    objectives: HashMap<String, Objective>,
+
+    /// The name of the objective that is shown within each display slot.
+    display: HashMap<DisplaySlot, String>,
+}
+
+impl Scoreboard {
+    /// Adds a new objective with the given name, title, and sort order.
+    ///
+    /// Fails if the name is not a single word, or if an objective with the
+    /// same name already exists.
+    pub fn add_objective(&mut self, name: &str, display_name: &str, sort: SortOrder) -> Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!("Objective names must be a single word");
+        }
+
+        if self.objectives.contains_key(name) {
+            bail!("Objective '{name}' already exists");
+        }
+
+        self.objectives.insert(name.to_string(), Objective {
+            display_name: display_name.to_string(),
+            sort,
+            scores: HashMap::default(),
+        });
+        Ok(())
+    }
+
+
+    /// Removes the objective with the given name, clearing every display slot
+    /// that it was shown within.
+    pub fn remove_objective(&mut self, name: &str) -> Result<()> {
+        if self.objectives.remove(name).is_none() {
+            bail!("Unknown objective: {name}");
+        }
+
+        self.display.retain(|_, displayed| displayed != name);
+        Ok(())
+    }
+
+
+    /// Gets the objective with the given name, if it exists.
+    pub fn objective(&self, name: &str) -> Option<&Objective> {
+        self.objectives.get(name)
+    }
+
+
+    /// Gets an iterator over each objective, alongside its name.
+    pub fn objectives(&self) -> impl Iterator<Item = (&str, &Objective)> {
+        self.objectives.iter().map(|(name, objective)| (name.as_str(), objective))
+    }
+
+
+    /// Gets the score of the given score holder within the given objective, if
+    /// they have one.
+    pub fn score(&self, objective: &str, holder: &str) -> Option<i32> {
+        self.objective(objective)?.score(holder)
+    }
+
+
+    /// Sets the score of the given score holder within the given objective.
+    pub fn set_score(&mut self, objective: &str, holder: &str, score: i32) -> Result<()> {
+        let Some(objective) = self.objectives.get_mut(objective) else {
+            bail!("Unknown objective: {objective}");
+        };
+
+        objective.scores.insert(holder.to_string(), score);
+        Ok(())
+    }
+
+
+    /// Adds the given amount to the score of the given score holder within the
+    /// given objective, starting from 0 if they have no score, and returns the
+    /// new score.
+    pub fn add_score(&mut self, objective: &str, holder: &str, amount: i32) -> Result<i32> {
+        let Some(objective) = self.objectives.get_mut(objective) else {
+            bail!("Unknown objective: {objective}");
+        };
+
+        let score = objective.scores.entry(holder.to_string()).or_default();
+        *score = score.saturating_add(amount);
+        Ok(*score)
+    }
+
+
+    /// Removes the score of the given score holder within the given objective,
+    /// or within every objective if none is given. Returns the number of
+    /// scores that were removed.
+    pub fn reset_score(&mut self, objective: Option<&str>, holder: &str) -> Result<usize> {
+        match objective {
+            Some(name) => {
+                let Some(objective) = self.objectives.get_mut(name) else {
+                    bail!("Unknown objective: {name}");
+                };
+                Ok(objective.scores.remove(holder).into_iter().count())
+            },
+            None => {
+                Ok(self
+                    .objectives
+                    .values_mut()
+                    .filter_map(|objective| objective.scores.remove(holder))
+                    .count())
+            },
+        }
+    }
+
+
+    /// Shows the given objective within the given display slot, or clears the
+    /// display slot if no objective is given.
+    pub fn set_display(&mut self, slot: DisplaySlot, objective: Option<&str>) -> Result<()> {
+        match objective {
+            Some(name) => {
+                if !self.objectives.contains_key(name) {
+                    bail!("Unknown objective: {name}");
+                }
+                self.display.insert(slot, name.to_string());
+            },
+            None => {
+                self.display.remove(&slot);
+            },
+        }
+        Ok(())
+    }
+
+
+    /// Gets the name of the objective that is shown within the given display
+    /// slot, if any.
+    pub fn displayed(&self, slot: DisplaySlot) -> Option<&str> {
+        self.display.get(&slot).map(|name| name.as_str())
+    }
+
+
+    /// Gets the objective that is shown within the given display slot, as it
+    /// is sent to clients.
+    pub fn display(&self, slot: DisplaySlot) -> Option<DisplayedObjective> {
+        let name = self.displayed(slot)?;
+        let objective = self.objective(name)?;
+
+        Some(DisplayedObjective {
+            name:         name.to_string(),
+            display_name: objective.display_name.clone(),
+            scores:       objective.sorted_scores(),
+        })
+    }
+}
+
+
+/// Sends the objective of each display slot to all players whenever it
+/// changes, and to each player that joins the server.
+pub fn sync_scoreboard(
+    scoreboard: Res<Scoreboard>,
+    mut sent: Local<HashMap<DisplaySlot, DisplayedObjective>>,
+    mut batch: ResMut<MessageBatch>,
+    new_players: Query<&ClientSocket, Added<ClientSocket>>,
+) {
+    if scoreboard.is_changed() {
+        for slot in DisplaySlot::ALL {
+            let objective = scoreboard.display(slot);
+            if sent.get(&slot) == objective.as_ref() {
+                continue;
+            }
+
+            broadcast(&mut batch, &ScoreboardMessage {
+                slot,
+                objective: objective.clone(),
+            });
+
+            match objective {
+                Some(objective) => sent.insert(slot, objective),
+                None => sent.remove(&slot),
+            };
+        }
+    }
+
+    for socket in new_players.iter() {
+        for (slot, objective) in sent.iter() {
+            send_to_client(&mut batch, socket.id(), &ScoreboardMessage {
+                slot:      *slot,
+                objective: Some(objective.clone()),
+            });
+        }
+    }
+}
+
+
+/// Gets the names of the score holders targeted by a command. Selectors target
+/// the names of the matched players, while any other argument is used as the
+/// name of the score holder directly.
+///
+/// This is also used by other commands that refer to players by name, such as
+/// the `team` command.
+pub fn score_holders(
+    world: &mut World,
+    sender: &CommandSender,
+    target: &str,
+) -> Result<Vec<String>> {
+    if !EntitySelector::is_selector(target) {
+        return Ok(vec![target.to_string()]);
+    }
+
+    let holders = command_targets(world, sender, Some(target))?
+        .into_iter()
+        .filter_map(|entity| world.get::<PlayerName>(entity).map(|name| name.0.clone()))
+        .collect();
+    Ok(holders)
+}
+
+
+/// Manages the objectives and scores of the scoreboard.
+///
+/// Usage:
+/// - `scoreboard objectives list`
+/// - `scoreboard objectives add <name> [ascending|descending] [title]`
+/// - `scoreboard objectives remove <name>`
+/// - `scoreboard objectives display <sidebar|list> [name]`
+/// - `scoreboard players get <holder> <objective>`
+/// - `scoreboard players <set|add|remove> <holder|selector> <objective>
+///   <score>`
+/// - `scoreboard players reset <holder|selector> [objective]`
+pub fn scoreboard_command(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String> {
+    match args {
+        ["objectives", "list"] => {
+            let scoreboard = world.resource::<Scoreboard>();
+            let mut lines: Vec<String> = scoreboard
+                .objectives()
+                .map(|(name, objective)| {
+                    format!(
+                        "{name} ({}, {}, {} scores)",
+                        objective.display_name(),
+                        objective.sort().name(),
+                        objective.scores.len()
+                    )
+                })
+                .collect();
+            lines.sort();
+
+            match lines.is_empty() {
+                true => Ok("There are no objectives".to_string()),
+                false => Ok(format!("Objectives:\n{}", lines.join("\n"))),
+            }
+        },
+        ["objectives", "add", name, rest @ ..] => {
+            let (sort, title) = match rest.split_first() {
+                Some((order, title)) if SortOrder::from_name(order).is_some() => {
+                    (SortOrder::from_name(order).unwrap(), title)
+                },
+                _ => (SortOrder::default(), rest),
+            };
+
+            let title = match title.is_empty() {
+                true => name.to_string(),
+                false => title.join(" "),
+            };
+
+            world.resource_mut::<Scoreboard>().add_objective(name, &title, sort)?;
+            Ok(format!("Added objective '{name}'"))
+        },
+        ["objectives", "remove", name] => {
+            world.resource_mut::<Scoreboard>().remove_objective(name)?;
+            Ok(format!("Removed objective '{name}'"))
+        },
+        ["objectives", "display", slot, name @ ..] if name.len() <= 1 => {
+            let Some(slot) = DisplaySlot::from_name(slot) else {
+                bail!("Unknown display slot: {slot}");
+            };
+
+            let name = name.first().copied();
+            world.resource_mut::<Scoreboard>().set_display(slot, name)?;
+            match name {
+                Some(name) => Ok(format!("Showing objective '{name}' in the {}", slot.name())),
+                None => Ok(format!("Cleared the {}", slot.name())),
+            }
+        },
+        ["players", "get", holder, objective] => {
+            match world.resource::<Scoreboard>().score(objective, holder) {
+                Some(score) => Ok(format!("{holder} has {score} {objective}")),
+                None => bail!("{holder} has no score for {objective}"),
+            }
+        },
+        ["players", action @ ("set" | "add" | "remove"), target, objective, score] => {
+            let score: i32 = score.parse()?;
+            let holders = score_holders(world, sender, target)?;
+
+            let mut scoreboard = world.resource_mut::<Scoreboard>();
+            for holder in holders.iter() {
+                match *action {
+                    "set" => scoreboard.set_score(objective, holder, score)?,
+                    "add" => {
+                        scoreboard.add_score(objective, holder, score)?;
+                    },
+                    _ => {
+                        scoreboard.add_score(objective, holder, score.saturating_neg())?;
+                    },
+                }
+            }
+
+            match holders.as_slice() {
+                [holder] => {
+                    let score = scoreboard.score(objective, holder).unwrap_or_default();
+                    Ok(format!("Set {objective} of {holder} to {score}"))
+                },
+                _ => {
+                    Ok(format!(
+                        "Updated {objective} of {} score holders",
+                        holders.len()
+                    ))
+                },
+            }
+        },
+        ["players", "reset", target, objective @ ..] if objective.len() <= 1 => {
+            let holders = score_holders(world, sender, target)?;
+            let objective = objective.first().copied();
+
+            let mut scoreboard = world.resource_mut::<Scoreboard>();
+            let mut removed = 0;
+            for holder in holders.iter() {
+                removed += scoreboard.reset_score(objective, holder)?;
+            }
+
+            Ok(format!("Reset {removed} scores"))
+        },
+        _ => {
+            bail!(
+                "Usage: scoreboard objectives <list|add|remove|display> ... | scoreboard players \
+                 <get|set|add|remove|reset> ..."
+            )
+        },
+    }
+}
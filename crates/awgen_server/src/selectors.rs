@@ -0,0 +1,430 @@
+//! Entity selectors, which allow commands to target sets of entities by their
+//! tags and their distance from the command sender, rather than only a single
+//! player by their client id.
+//!
+//! A selector starts with a target, followed by an optional list of filters
+//! within square brackets, such as `@e[tag=red,distance<10,limit=3]`.
+//!
+//! | Target | Entities                          |
+//! |--------|-----------------------------------|
+//! | `@a`   | All players.                      |
+//! | `@p`   | The nearest player to the sender. |
+//! | `@e`   | All entities with a position.     |
+//! | `@s`   | The command sender.               |
+//!
+//! | Filter         | Matches entities that...                                |
+//! |----------------|---------------------------------------------------------|
+//! | `tag=name`     | carry the given tag.                                    |
+//! | `tag=!name`    | do not carry the given tag.                             |
+//! | `distance<N`   | are within the world of the sender, closer than N. The  |
+//! |                | `<=`, `>`, and `>=` comparisons are also supported.     |
+//! | `limit=N`      | are among the first N matches, nearest first.           |
+
+
+use crate::prelude::{find_player, CommandSender};
+use anyhow::{bail, Error, Result};
+use awgen_network::prelude::ClientSocket;
+use awgen_physics::prelude::Position;
+use awgen_world::prelude::{InWorld, TagRegistry};
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+
+/// The set of entities that a selector starts from, before any filters are
+/// applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorTarget {
+    /// All players, written as `@a`.
+    AllPlayers,
+
+    /// The nearest player to the command sender, written as `@p`.
+    NearestPlayer,
+
+    /// All entities that have a position, written as `@e`.
+    AllEntities,
+
+    /// The command sender, written as `@s`.
+    Sender,
+}
+
+
+/// A comparison between the distance of an entity and a fixed value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The distance must be less than the value.
+    Less,
+
+    /// The distance must be less than or equal to the value.
+    LessOrEqual,
+
+    /// The distance must be greater than the value.
+    Greater,
+
+    /// The distance must be greater than or equal to the value.
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Gets whether or not the given distance passes this comparison against
+    /// the given value.
+    pub fn test(self, distance: f32, value: f32) -> bool {
+        match self {
+            Comparison::Less => distance < value,
+            Comparison::LessOrEqual => distance <= value,
+            Comparison::Greater => distance > value,
+            Comparison::GreaterOrEqual => distance >= value,
+        }
+    }
+}
+
+
+/// A single condition that an entity must pass to be selected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectorFilter {
+    /// The entity must, or must not, carry the given tag.
+    Tag {
+        /// The name of the tag.
+        name: String,
+
+        /// Whether the entity must carry the tag, rather than not carry it.
+        present: bool,
+    },
+
+    /// The entity must be within the same world as the command sender, at a
+    /// distance that passes the given comparison.
+    Distance {
+        /// The comparison to apply to the distance of the entity.
+        comparison: Comparison,
+
+        /// The distance to compare against, in meters.
+        value: f32,
+    },
+}
+
+
+/// A parsed entity selector, such as `@e[tag=red,distance<10]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySelector {
+    /// The set of entities that this selector starts from.
+    pub target: SelectorTarget,
+
+    /// The filters that each selected entity must pass.
+    pub filters: Vec<SelectorFilter>,
+
+    /// The maximum number of entities to select, if any.
+    pub limit: Option<usize>,
+}
+
+impl EntitySelector {
+    /// Gets whether or not the given command argument is written as a selector,
+    /// rather than a client id.
+    pub fn is_selector(arg: &str) -> bool {
+        arg.starts_with('@')
+    }
+
+
+    /// Gets whether or not this selector needs a position to measure the
+    /// distance of entities from.
+    fn needs_origin(&self) -> bool {
+        self.target == SelectorTarget::NearestPlayer
+            || self.filters.iter().any(|f| matches!(f, SelectorFilter::Distance { .. }))
+    }
+
+
+    /// Finds all entities within the world that match this selector, on behalf
+    /// of the given command sender.
+    ///
+    /// Selected entities are ordered from nearest to furthest when the sender
+    /// is a player, or by entity otherwise. Selectors that depend on distance
+    /// may only be used by players.
+    pub fn resolve(&self, world: &mut World, sender: &CommandSender) -> Result<Vec<Entity>> {
+        let origin = match sender {
+            CommandSender::Player(player) => {
+                let position = world.get::<Position>(*player).map(|p| p.translation);
+                let in_world = world.get::<InWorld>(*player).copied();
+                position.zip(in_world)
+            },
+            CommandSender::Console | CommandSender::Remote(_) => None,
+        };
+
+        if self.needs_origin() && origin.is_none() {
+            bail!("Selectors that depend on distance may only be used by a player");
+        }
+
+        let mut candidates: Vec<(Entity, Option<f32>)> = world
+            .query::<(
+                Entity,
+                Option<&Position>,
+                Option<&InWorld>,
+                Option<&ClientSocket>,
+            )>()
+            .iter(world)
+            .filter(|(entity, position, _, socket)| {
+                match self.target {
+                    SelectorTarget::AllPlayers | SelectorTarget::NearestPlayer => socket.is_some(),
+                    SelectorTarget::AllEntities => position.is_some(),
+                    SelectorTarget::Sender => *sender == CommandSender::Player(*entity),
+                }
+            })
+            .map(|(entity, position, in_world, _)| {
+                let distance = match (origin, position, in_world) {
+                    (Some((center, origin_world)), Some(position), Some(in_world))
+                        if *in_world == origin_world =>
+                    {
+                        Some(position.translation.distance(center))
+                    },
+                    _ => None,
+                };
+                (entity, distance)
+            })
+            .collect();
+
+        if self.target == SelectorTarget::Sender && candidates.is_empty() {
+            bail!("The @s selector may only be used by a player");
+        }
+
+        let tags = world.resource::<TagRegistry>();
+        candidates.retain(|(entity, distance)| {
+            self.filters.iter().all(|filter| {
+                match filter {
+                    SelectorFilter::Tag {
+                        name,
+                        present,
+                    } => tags.has_tag(*entity, name) == *present,
+                    SelectorFilter::Distance {
+                        comparison,
+                        value,
+                    } => distance.is_some_and(|d| comparison.test(d, *value)),
+                }
+            })
+        });
+
+        if self.target == SelectorTarget::NearestPlayer {
+            candidates.retain(|(_, distance)| distance.is_some());
+        }
+
+        candidates.sort_by(|(a, a_dist), (b, b_dist)| {
+            match (a_dist, b_dist) {
+                (Some(a_dist), Some(b_dist)) => a_dist.total_cmp(b_dist),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then(a.cmp(b))
+        });
+
+        let limit = match self.target {
+            SelectorTarget::NearestPlayer => Some(self.limit.unwrap_or(1)),
+            _ => self.limit,
+        };
+
+        if let Some(limit) = limit {
+            candidates.truncate(limit);
+        }
+
+        Ok(candidates.into_iter().map(|(entity, _)| entity).collect())
+    }
+}
+
+impl FromStr for EntitySelector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (head, body) = match s.split_once('[') {
+            Some((head, rest)) => {
+                let Some(body) = rest.strip_suffix(']') else {
+                    bail!("Unclosed filter list in selector: {s}");
+                };
+                (head, Some(body))
+            },
+            None => (s, None),
+        };
+
+        let target = match head {
+            "@a" => SelectorTarget::AllPlayers,
+            "@p" => SelectorTarget::NearestPlayer,
+            "@e" => SelectorTarget::AllEntities,
+            "@s" => SelectorTarget::Sender,
+            _ => bail!("Unknown selector: {head}"),
+        };
+
+        let mut selector = EntitySelector {
+            target,
+            filters: vec![],
+            limit: None,
+        };
+
+        for filter in body.into_iter().flat_map(|b| b.split(',')).filter(|f| !f.is_empty()) {
+            let Some(split) = filter.find(['<', '>', '=']) else {
+                bail!("Selector filter is missing a comparison: {filter}");
+            };
+
+            let (key, rest) = filter.split_at(split);
+            let (op, value) = match rest.get(..2) {
+                Some(op @ ("<=" | ">=")) => (op, &rest[2..]),
+                _ => rest.split_at(1),
+            };
+
+            match (key, op) {
+                ("tag", "=") => {
+                    let (name, present) = match value.strip_prefix('!') {
+                        Some(name) => (name, false),
+                        None => (value, true),
+                    };
+
+                    if name.is_empty() {
+                        bail!("Selector tag filter is missing a tag name: {filter}");
+                    }
+
+                    selector.filters.push(SelectorFilter::Tag {
+                        name: name.to_string(),
+                        present,
+                    });
+                },
+                ("distance", _) => {
+                    let comparison = match op {
+                        "<" => Comparison::Less,
+                        "<=" => Comparison::LessOrEqual,
+                        ">" => Comparison::Greater,
+                        ">=" => Comparison::GreaterOrEqual,
+                        _ => bail!("Selector distance filter requires <, <=, >, or >=: {filter}"),
+                    };
+
+                    let value: f32 = value.parse()?;
+                    if !value.is_finite() {
+                        bail!("Selector distance must be a finite number: {filter}");
+                    }
+
+                    selector.filters.push(SelectorFilter::Distance {
+                        comparison,
+                        value,
+                    });
+                },
+                ("limit", "=") => selector.limit = Some(value.parse()?),
+                _ => bail!("Unknown selector filter: {filter}"),
+            }
+        }
+
+        Ok(selector)
+    }
+}
+
+
+/// Gets the entities targeted by a command, from the given selector or client
+/// id argument, or by defaulting to the command sender.
+///
+/// Unlike [`command_target`](crate::prelude::command_target), any number of
+/// entities may be targeted, and they need not be players.
+pub fn command_targets(
+    world: &mut World,
+    sender: &CommandSender,
+    target: Option<&str>,
+) -> Result<Vec<Entity>> {
+    match (target, sender) {
+        (Some(arg), _) if EntitySelector::is_selector(arg) => {
+            arg.parse::<EntitySelector>()?.resolve(world, sender)
+        },
+        (Some(client_id), _) => Ok(vec![find_player(world, client_id.parse()?)?]),
+        (None, CommandSender::Player(player)) => Ok(vec![*player]),
+        (None, CommandSender::Console | CommandSender::Remote(_)) => {
+            bail!("A target must be given from the console")
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn parse_targets() {
+        for (arg, target) in [
+            ("@a", SelectorTarget::AllPlayers),
+            ("@p", SelectorTarget::NearestPlayer),
+            ("@e", SelectorTarget::AllEntities),
+            ("@s", SelectorTarget::Sender),
+        ] {
+            let selector: EntitySelector = arg.parse().unwrap();
+            assert_eq!(selector, EntitySelector {
+                target,
+                filters: vec![],
+                limit: None,
+            });
+        }
+    }
+
+
+    #[test]
+    fn parse_filters() {
+        let selector: EntitySelector =
+            "@e[tag=red,tag=!blue,distance>=2.5,limit=3]".parse().unwrap();
+        assert_eq!(selector, EntitySelector {
+            target:  SelectorTarget::AllEntities,
+            filters: vec![
+                SelectorFilter::Tag {
+                    name:    "red".to_string(),
+                    present: true,
+                },
+                SelectorFilter::Tag {
+                    name:    "blue".to_string(),
+                    present: false,
+                },
+                SelectorFilter::Distance {
+                    comparison: Comparison::GreaterOrEqual,
+                    value:      2.5,
+                },
+            ],
+            limit:   Some(3),
+        });
+    }
+
+
+    #[test]
+    fn parse_distance_comparisons() {
+        for (arg, comparison) in [
+            ("@a[distance<4]", Comparison::Less),
+            ("@a[distance<=4]", Comparison::LessOrEqual),
+            ("@a[distance>4]", Comparison::Greater),
+            ("@a[distance>=4]", Comparison::GreaterOrEqual),
+        ] {
+            let selector: EntitySelector = arg.parse().unwrap();
+            assert_eq!(selector.filters, vec![SelectorFilter::Distance {
+                comparison,
+                value: 4.0,
+            }]);
+        }
+    }
+
+
+    #[test]
+    fn parse_empty_filter_list() {
+        let selector: EntitySelector = "@p[]".parse().unwrap();
+        assert_eq!(selector.target, SelectorTarget::NearestPlayer);
+        assert!(selector.filters.is_empty());
+    }
+
+
+    #[test]
+    fn reject_invalid_selectors() {
+        for arg in [
+            "@x",
+            "a",
+            "@e[tag=red",
+            "@e[tag]",
+            "@e[tag=]",
+            "@e[tag=!]",
+            "@e[color=red]",
+            "@e[distance=5]",
+            "@e[distance<far]",
+            "@e[distance<inf]",
+            "@e[limit=-1]",
+            "@e[limit<3]",
+        ] {
+            assert!(arg.parse::<EntitySelector>().is_err(), "{arg} was accepted");
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Handles the requests of permitted players to spectate other players.
+//!
+//! While spectating, a temporary chunk anchor follows the spectated player, so
+//! that the chunks around it remain loaded for the spectator even if the
+//! spectated player is within another world. The spectator is also able to see
+//! all replicated entities within the world of the spectated player.
+
+
+use crate::prelude::Permissions;
+use awgen_network::prelude::{
+    network_id, send_to_client, ClientMessage, ClientSocket, MessageBatch, SpectateRequest, SpectateStatus
+};
+use awgen_physics::prelude::Position;
+use awgen_world::prelude::{ChunkAnchor, InWorld};
+use bevy::prelude::*;
+
+
+/// The permission node that allows a player to spectate other players.
+pub const SPECTATE_PERMISSION: &str = "awgen.spectate";
+
+
+/// The radius, in chunks, that is kept loaded around each spectated player.
+const SPECTATOR_CHUNK_RADIUS: u16 = 4;
+
+
+/// The radius, in chunks, around each spectated player that chunks may remain
+/// loaded within before being unloaded.
+const SPECTATOR_MAX_CHUNK_RADIUS: u16 = 6;
+
+
+/// The loading priority of the chunk anchor of each spectator. This is below
+/// the priority of player anchors, so that playing players are served first.
+const SPECTATOR_ANCHOR_PRIORITY: u32 = 50;
+
+
+/// A component for a player that is spectating another player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Spectating {
+    /// The player entity that is being spectated.
+    pub target: Entity,
+
+    /// The temporary chunk anchor that follows the spectated player.
+    pub anchor: Entity,
+}
+
+
+/// A component for the temporary chunk anchor that follows a spectated player
+/// on behalf of a spectator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct SpectatorAnchor {
+    /// The spectating player entity.
+    pub spectator: Entity,
+}
+
+
+/// Picks the player to spectate after, or before, the currently spectated
+/// player from the given list of candidates, wrapping around at either end.
+fn cycle_target(candidates: &[Entity], current: Option<Entity>, forward: bool) -> Option<Entity> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let len = candidates.len();
+    let index = match current.and_then(|c| candidates.iter().position(|e| *e == c)) {
+        Some(index) if forward => (index + 1) % len,
+        Some(index) => (index + len - 1) % len,
+        None if forward => 0,
+        None => len - 1,
+    };
+
+    Some(candidates[index])
+}
+
+
+/// Starts, changes, or stops spectating for each spectate request that was
+/// received from a player with permission to spectate.
+pub fn handle_spectate_requests(
+    mut request_ev: EventReader<ClientMessage<SpectateRequest>>,
+    mut batch: ResMut<MessageBatch>,
+    spectators: Query<(Option<&Permissions>, Option<&Spectating>)>,
+    players: Query<(Entity, &ClientSocket, &InWorld)>,
+    mut commands: Commands,
+) {
+    for ev in request_ev.iter() {
+        let Ok((permissions, spectating)) = spectators.get(ev.player) else {
+            continue;
+        };
+
+        if !permissions.is_some_and(|p| p.has(SPECTATE_PERMISSION)) {
+            debug!(
+                "Rejected spectate request from client {}: no permission",
+                ev.client_id
+            );
+            continue;
+        }
+
+        let mut candidates: Vec<(u64, Entity)> = players
+            .iter()
+            .filter(|(entity, ..)| *entity != ev.player)
+            .map(|(entity, socket, _)| (socket.id(), entity))
+            .collect();
+        candidates.sort_unstable();
+        let candidates: Vec<Entity> = candidates.into_iter().map(|(_, e)| e).collect();
+
+        let current = spectating.map(|s| s.target);
+        let target = match ev.message {
+            SpectateRequest::Next => cycle_target(&candidates, current, true),
+            SpectateRequest::Previous => cycle_target(&candidates, current, false),
+            SpectateRequest::Stop => None,
+        };
+
+        if target == current {
+            continue;
+        }
+
+        if let Some(spectating) = spectating {
+            commands.entity(spectating.anchor).despawn();
+        }
+
+        let Some(target) = target else {
+            commands.entity(ev.player).remove::<Spectating>();
+            send_to_client(&mut batch, ev.client_id, &SpectateStatus {
+                target: None,
+            });
+            continue;
+        };
+
+        let Ok((_, _, target_world)) = players.get(target) else {
+            continue;
+        };
+
+        let anchor = commands
+            .spawn((
+                SpectatorAnchor {
+                    spectator: ev.player,
+                },
+                ChunkAnchor::new(
+                    target_world.0,
+                    SPECTATOR_CHUNK_RADIUS,
+                    SPECTATOR_MAX_CHUNK_RADIUS,
+                )
+                .with_priority(SPECTATOR_ANCHOR_PRIORITY),
+                Position::default(),
+            ))
+            .id();
+
+        commands.entity(ev.player).insert(Spectating {
+            target,
+            anchor,
+        });
+
+        send_to_client(&mut batch, ev.client_id, &SpectateStatus {
+            target: Some(network_id(target)),
+        });
+    }
+}
+
+
+/// Moves the temporary chunk anchor of each spectator to the position and
+/// world of the player that it is spectating.
+pub fn follow_spectate_targets(
+    spectators: Query<&Spectating>,
+    targets: Query<(&Position, &InWorld), Without<SpectatorAnchor>>,
+    mut anchors: Query<(&mut Position, &mut ChunkAnchor), With<SpectatorAnchor>>,
+) {
+    for spectating in spectators.iter() {
+        let Ok((target_pos, target_world)) = targets.get(spectating.target) else {
+            continue;
+        };
+
+        let Ok((mut anchor_pos, mut anchor)) = anchors.get_mut(spectating.anchor) else {
+            continue;
+        };
+
+        if anchor_pos.translation != target_pos.translation {
+            anchor_pos.translation = target_pos.translation;
+        }
+
+        if anchor.world != Some(target_world.0) {
+            anchor.world = Some(target_world.0);
+        }
+    }
+}
+
+
+/// Stops spectating for each spectator whose target has disconnected or who no
+/// longer has permission to spectate, and removes the anchors of spectators
+/// that have disconnected.
+pub fn end_invalid_spectating(
+    mut batch: ResMut<MessageBatch>,
+    spectators: Query<(Entity, &ClientSocket, &Spectating, Option<&Permissions>)>,
+    targets: Query<(), With<ClientSocket>>,
+    anchors: Query<(Entity, &SpectatorAnchor)>,
+    mut commands: Commands,
+) {
+    for (spectator, socket, spectating, permissions) in spectators.iter() {
+        let permitted = permissions.is_some_and(|p| p.has(SPECTATE_PERMISSION));
+        if permitted && targets.contains(spectating.target) {
+            continue;
+        }
+
+        commands.entity(spectating.anchor).despawn();
+        commands.entity(spectator).remove::<Spectating>();
+        send_to_client(&mut batch, socket.id(), &SpectateStatus {
+            target: None,
+        });
+    }
+
+    for (anchor, owner) in anchors.iter() {
+        if !spectators.contains(owner.spectator) {
+            commands.entity(anchor).despawn();
+        }
+    }
+}
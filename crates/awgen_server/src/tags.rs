@@ -0,0 +1,72 @@
+//! Allows tags to be attached to and detached from players through commands, so
+//! that mini-game logic may group players without any new components.
+//!
+//! Tags may also be attached to every entity matched by a selector, such as
+//! `tag add @a[distance<20] arena`.
+
+
+use crate::prelude::{command_targets, CommandSender, EntitySelector};
+use anyhow::{bail, Result};
+use awgen_network::prelude::ClientSocket;
+use awgen_world::prelude::{SetTag, TagRegistry};
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+
+/// Attaches, detaches, or lists tags.
+///
+/// Usage: `tag <add|remove> <client id|selector> <tag> | tag list <tag>`
+///
+/// Listing a tag reports the number of entities that carry it, and the client
+/// ids of the players among them.
+pub fn tag_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    match args {
+        ["list", name] => {
+            let entities: Vec<Entity> =
+                world.resource::<TagRegistry>().entities_with_tag(name).collect();
+
+            let mut players: Vec<u64> = entities
+                .iter()
+                .filter_map(|entity| world.get::<ClientSocket>(*entity))
+                .map(|socket| socket.id())
+                .collect();
+            players.sort_unstable();
+
+            let players: Vec<String> = players.iter().map(|id| id.to_string()).collect();
+            match players.is_empty() {
+                true => Ok(format!("{} entities are tagged '{name}'", entities.len())),
+                false => {
+                    Ok(format!(
+                        "{} entities are tagged '{name}', including players: {}",
+                        entities.len(),
+                        players.join(", ")
+                    ))
+                },
+            }
+        },
+        [action @ ("add" | "remove"), target, name] => {
+            let entities = command_targets(world, sender, Some(*target))?;
+            for entity in entities.iter() {
+                SetTag {
+                    entity: *entity,
+                    name:   name.to_string(),
+                    attach: *action == "add",
+                }
+                .write(world);
+            }
+
+            match (*action, EntitySelector::is_selector(target)) {
+                ("add", false) => Ok(format!("Tagged player {target} with '{name}'")),
+                ("add", true) => Ok(format!("Tagged {} entities with '{name}'", entities.len())),
+                (_, false) => Ok(format!("Removed tag '{name}' from player {target}")),
+                (_, true) => {
+                    Ok(format!(
+                        "Removed tag '{name}' from {} entities",
+                        entities.len()
+                    ))
+                },
+            }
+        },
+        _ => bail!("Usage: tag <add|remove> <client id|selector> <tag> | tag list <tag>"),
+    }
+}
@@ -0,0 +1,498 @@
+//! Teams, which group players together for mini-games.
+//!
+//! Like scores, team members are held by name, so that a player remains on
+//! their team when they reconnect. Each team has a color that the names of its
+//! members are shown with on clients.
+//!
+//! Teams may be changed by the `team` command, or directly through the
+//! [`Teams`] resource.
+
+
+use crate::prelude::{score_holders, CommandSender, Scoreboard};
+use anyhow::{bail, Result};
+use awgen_network::prelude::{
+    broadcast, send_to_client, ClientSocket, MessageBatch, TeamColor, TeamInfo, TeamsMessage
+};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::collections::BTreeSet;
+
+
+/// The names of all team options, in the order that they are listed.
+pub const TEAM_OPTIONS: [&str; 2] = ["display_name", "color"];
+
+
+/// A single team, and the names of its members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Team {
+    /// The title that this team is shown with.
+    display_name: String,
+
+    /// The color that the names of the members of this team are shown with.
+    color: TeamColor,
+
+    /// The names of the members of this team.
+    members: BTreeSet<String>,
+}
+
+impl Team {
+    /// Gets the title that this team is shown with.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+
+    /// Gets the color that the names of the members of this team are shown
+    /// with.
+    pub fn color(&self) -> TeamColor {
+        self.color
+    }
+
+
+    /// Gets an iterator over the names of the members of this team, sorted
+    /// alphabetically.
+    pub fn members(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|member| member.as_str())
+    }
+
+
+    /// Gets the value of the option with the given name, formatted as text.
+    ///
+    /// Returns `None` if there is no option with the given name.
+    pub fn option(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "display_name" => self.display_name.clone(),
+            "color" => self.color.name().to_string(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+
+    /// Parses the given text and assigns it to the option with the given name.
+    ///
+    /// Returns an error if there is no option with the given name, or if the
+    /// value is not valid for that option.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "display_name" => {
+                if value.trim().is_empty() {
+                    bail!("The display name of a team may not be empty");
+                }
+                self.display_name = value.trim().to_string();
+            },
+            "color" => {
+                let Some(color) = TeamColor::from_name(value) else {
+                    bail!("Unknown team color: {value}");
+                };
+                self.color = color;
+            },
+            _ => bail!("Unknown team option: {name}"),
+        }
+
+        Ok(())
+    }
+}
+
+
+/// A server-side resource that stores every team.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Teams {
+    /// Each team, by name.
+    teams: HashMap<String, Team>,
+}
+
+impl Teams {
+    /// Adds a new, empty team with the given name and title.
+    ///
+    /// Fails if the name is not a single word, or if a team with the same name
+    /// already exists.
+    pub fn add_team(&mut self, name: &str, display_name: &str) -> Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!("Team names must be a single word");
+        }
+
+        if self.teams.contains_key(name) {
+            bail!("Team '{name}' already exists");
+        }
+
+        self.teams.insert(name.to_string(), Team {
+            display_name: display_name.to_string(),
+            color:        TeamColor::default(),
+            members:      BTreeSet::new(),
+        });
+        Ok(())
+    }
+
+
+    /// Removes the team with the given name, and with it the membership of
+    /// each of its members.
+    pub fn remove_team(&mut self, name: &str) -> Result<()> {
+        if self.teams.remove(name).is_none() {
+            bail!("Unknown team: {name}");
+        }
+
+        Ok(())
+    }
+
+
+    /// Gets the team with the given name, if it exists.
+    pub fn team(&self, name: &str) -> Option<&Team> {
+        self.teams.get(name)
+    }
+
+
+    /// Gets a mutable reference to the team with the given name, if it exists.
+    pub fn team_mut(&mut self, name: &str) -> Option<&mut Team> {
+        self.teams.get_mut(name)
+    }
+
+
+    /// Gets an iterator over each team, alongside its name.
+    pub fn teams(&self) -> impl Iterator<Item = (&str, &Team)> {
+        self.teams.iter().map(|(name, team)| (name.as_str(), team))
+    }
+
+
+    /// Gets the name of the team that the given player is a member of, if any.
+    pub fn team_of(&self, player: &str) -> Option<&str> {
+        self.teams
+            .iter()
+            .find(|(_, team)| team.members.contains(player))
+            .map(|(name, _)| name.as_str())
+    }
+
+
+    /// Adds the given player to the given team, removing them from any team
+    /// that they were previously a member of.
+    pub fn join(&mut self, team: &str, player: &str) -> Result<()> {
+        if !self.teams.contains_key(team) {
+            bail!("Unknown team: {team}");
+        }
+
+        self.leave(player);
+        self.teams.get_mut(team).unwrap().members.insert(player.to_string());
+        Ok(())
+    }
+
+
+    /// Removes the given player from their team, returning the name of the
+    /// team that they left, if they were on one.
+    pub fn leave(&mut self, player: &str) -> Option<String> {
+        let (name, team) = self.teams.iter_mut().find(|(_, team)| team.members.contains(player))?;
+        team.members.remove(player);
+        Some(name.clone())
+    }
+
+
+    /// Gets whether or not the two given players are members of the same
+    /// team.
+    pub fn are_teammates(&self, a: &str, b: &str) -> bool {
+        match self.team_of(a) {
+            Some(team) => self.teams[team].members.contains(b),
+            None => false,
+        }
+    }
+
+
+    /// Gets the total score of the members of the given team within the given
+    /// scoreboard objective. Members without a score count as 0.
+    pub fn team_score(&self, scoreboard: &Scoreboard, team: &str, objective: &str) -> Result<i32> {
+        let Some(team) = self.teams.get(team) else {
+            bail!("Unknown team: {team}");
+        };
+
+        if scoreboard.objective(objective).is_none() {
+            bail!("Unknown objective: {objective}");
+        }
+
+        Ok(team
+            .members
+            .iter()
+            .filter_map(|member| scoreboard.score(objective, member))
+            .fold(0, i32::saturating_add))
+    }
+
+
+    /// Gets every team, as it is sent to clients, sorted by name.
+    pub fn info(&self) -> Vec<TeamInfo> {
+        let mut info: Vec<TeamInfo> = self
+            .teams
+            .iter()
+            .map(|(name, team)| {
+                TeamInfo {
+                    name:         name.clone(),
+                    display_name: team.display_name.clone(),
+                    color:        team.color,
+                    members:      team.members.iter().cloned().collect(),
+                }
+            })
+            .collect();
+
+        info.sort_by(|a, b| a.name.cmp(&b.name));
+        info
+    }
+}
+
+
+/// Sends every team to all players whenever the teams change, and to each
+/// player that joins the server.
+pub fn sync_teams(
+    teams: Res<Teams>,
+    mut sent: Local<Vec<TeamInfo>>,
+    mut batch: ResMut<MessageBatch>,
+    new_players: Query<&ClientSocket, Added<ClientSocket>>,
+) {
+    if teams.is_changed() {
+        let info = teams.info();
+        if *sent != info {
+            broadcast(&mut batch, &TeamsMessage {
+                teams: info.clone(),
+            });
+            *sent = info;
+        }
+    }
+
+    if sent.is_empty() {
+        return;
+    }
+
+    for socket in new_players.iter() {
+        send_to_client(&mut batch, socket.id(), &TeamsMessage {
+            teams: sent.clone(),
+        });
+    }
+}
+
+
+/// Manages teams and their members.
+///
+/// Usage:
+/// - `team list`
+/// - `team add <name> [title]`
+/// - `team remove <name>`
+/// - `team members <name>`
+/// - `team join <name> <player|selector>`
+/// - `team leave <player|selector>`
+/// - `team modify <name> <option> [value]`
+/// - `team score <name> <objective>`
+pub fn team_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    match args {
+        ["list"] => {
+            let teams = world.resource::<Teams>();
+            let mut lines: Vec<String> = teams
+                .teams()
+                .map(|(name, team)| {
+                    format!(
+                        "{name} ({}, {}, {} members)",
+                        team.display_name(),
+                        team.color().name(),
+                        team.members.len()
+                    )
+                })
+                .collect();
+            lines.sort();
+
+            match lines.is_empty() {
+                true => Ok("There are no teams".to_string()),
+                false => Ok(format!("Teams:\n{}", lines.join("\n"))),
+            }
+        },
+        ["add", name, title @ ..] => {
+            let title = match title.is_empty() {
+                true => name.to_string(),
+                false => title.join(" "),
+            };
+
+            world.resource_mut::<Teams>().add_team(name, &title)?;
+            Ok(format!("Added team '{name}'"))
+        },
+        ["remove", name] => {
+            world.resource_mut::<Teams>().remove_team(name)?;
+            Ok(format!("Removed team '{name}'"))
+        },
+        ["members", name] => {
+            let teams = world.resource::<Teams>();
+            let Some(team) = teams.team(name) else {
+                bail!("Unknown team: {name}");
+            };
+
+            let members: Vec<&str> = team.members().collect();
+            match members.is_empty() {
+                true => Ok(format!("Team '{name}' has no members")),
+                false => Ok(format!("Members of '{name}': {}", members.join(", "))),
+            }
+        },
+        ["join", name, target] => {
+            let players = score_holders(world, sender, target)?;
+
+            let mut teams = world.resource_mut::<Teams>();
+            for player in players.iter() {
+                teams.join(name, player)?;
+            }
+
+            match players.as_slice() {
+                [player] => Ok(format!("Added {player} to team '{name}'")),
+                _ => Ok(format!("Added {} players to team '{name}'", players.len())),
+            }
+        },
+        ["leave", target] => {
+            let players = score_holders(world, sender, target)?;
+
+            let mut teams = world.resource_mut::<Teams>();
+            let left = players.iter().filter_map(|player| teams.leave(player)).count();
+            Ok(format!("Removed {left} players from their teams"))
+        },
+        ["modify", name, option] => {
+            let teams = world.resource::<Teams>();
+            let Some(team) = teams.team(name) else {
+                bail!("Unknown team: {name}");
+            };
+
+            match team.option(option) {
+                Some(value) => Ok(format!("{option} = {value}")),
+                None => bail!("Unknown team option: {option}"),
+            }
+        },
+        ["modify", name, option, value @ ..] => {
+            let mut teams = world.resource_mut::<Teams>();
+            let Some(team) = teams.team_mut(name) else {
+                bail!("Unknown team: {name}");
+            };
+
+            team.set_option(option, &value.join(" "))?;
+            Ok(format!(
+                "Set {option} of team '{name}' to {}",
+                team.option(option).unwrap_or_default()
+            ))
+        },
+        ["score", name, objective] => {
+            let score = world.resource::<Teams>().team_score(
+                world.resource::<Scoreboard>(),
+                name,
+                objective,
+            )?;
+            Ok(format!("Team '{name}' has {score} {objective}"))
+        },
+        _ => {
+            bail!(
+                "Usage: team <list|add|remove|members|join|leave|modify|score> ... (options: {})",
+                TEAM_OPTIONS.join(", ")
+            )
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_network::prelude::SortOrder;
+    use pretty_assertions::assert_eq;
+
+
+    /// Creates the teams "red" and "blue".
+    fn teams() -> Teams {
+        let mut teams = Teams::default();
+        teams.add_team("red", "Red Team").unwrap();
+        teams.add_team("blue", "Blue Team").unwrap();
+        teams
+    }
+
+
+    #[test]
+    fn add_and_remove_teams() {
+        let mut teams = teams();
+        assert!(teams.add_team("red", "Again").is_err());
+        assert!(teams.add_team("two words", "Invalid").is_err());
+        assert!(teams.add_team("", "Invalid").is_err());
+
+        teams.join("red", "alice").unwrap();
+        teams.remove_team("red").unwrap();
+        assert!(teams.remove_team("red").is_err());
+        assert_eq!(teams.team_of("alice"), None);
+    }
+
+
+    #[test]
+    fn join_and_leave() {
+        let mut teams = teams();
+        teams.join("red", "alice").unwrap();
+        teams.join("red", "bob").unwrap();
+        assert!(teams.join("green", "alice").is_err());
+
+        assert_eq!(teams.team_of("alice"), Some("red"));
+        assert!(teams.are_teammates("alice", "bob"));
+
+        teams.join("blue", "bob").unwrap();
+        assert_eq!(teams.team_of("bob"), Some("blue"));
+        assert!(!teams.are_teammates("alice", "bob"));
+        assert_eq!(
+            teams.team("red").unwrap().members().collect::<Vec<_>>(),
+            vec!["alice"]
+        );
+
+        assert_eq!(teams.leave("bob"), Some("blue".to_string()));
+        assert_eq!(teams.leave("bob"), None);
+        assert_eq!(teams.team_of("bob"), None);
+    }
+
+
+    #[test]
+    fn set_options() {
+        let mut teams = teams();
+        let team = teams.team_mut("red").unwrap();
+
+        team.set_option("color", "red").unwrap();
+        team.set_option("display_name", "  Crimson  ").unwrap();
+        assert_eq!(team.option("color"), Some("red".to_string()));
+        assert_eq!(team.display_name(), "Crimson");
+
+        assert!(team.set_option("color", "plaid").is_err());
+        assert!(team.set_option("display_name", " ").is_err());
+        assert!(team.set_option("friendly_fire", "true").is_err());
+        assert_eq!(team.option("friendly_fire"), None);
+    }
+
+
+    #[test]
+    fn team_score() {
+        let mut teams = teams();
+        teams.join("red", "alice").unwrap();
+        teams.join("red", "bob").unwrap();
+        teams.join("red", "carol").unwrap();
+        teams.join("blue", "dave").unwrap();
+
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.add_objective("kills", "Kills", SortOrder::Descending).unwrap();
+        scoreboard.set_score("kills", "alice", 3).unwrap();
+        scoreboard.set_score("kills", "bob", i32::MAX).unwrap();
+        scoreboard.set_score("kills", "dave", 2).unwrap();
+
+        assert_eq!(
+            teams.team_score(&scoreboard, "red", "kills").unwrap(),
+            i32::MAX
+        );
+        assert_eq!(teams.team_score(&scoreboard, "blue", "kills").unwrap(), 2);
+        assert!(teams.team_score(&scoreboard, "green", "kills").is_err());
+        assert!(teams.team_score(&scoreboard, "red", "deaths").is_err());
+
+        scoreboard.set_score("kills", "bob", 1).unwrap();
+        assert_eq!(teams.team_score(&scoreboard, "red", "kills").unwrap(), 4);
+    }
+
+
+    #[test]
+    fn info_is_sorted() {
+        let mut teams = teams();
+        teams.join("red", "alice").unwrap();
+
+        let info = teams.info();
+        assert_eq!(
+            info.iter().map(|team| team.name.as_str()).collect::<Vec<_>>(),
+            vec!["blue", "red"]
+        );
+        assert_eq!(info[1].members, vec!["alice".to_string()]);
+    }
+}
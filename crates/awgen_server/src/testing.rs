@@ -0,0 +1,717 @@
+//! A headless harness for integration testing the server. The harness runs the
+//! full server plugin stack alongside any number of virtual clients, connected
+//! over the local loopback interface, and steps them all together one frame at
+//! a time.
+//!
+//! Time within the harness is simulated, rather than measured, so that each
+//! frame advances every app by exactly one physics tick. This keeps tests
+//! deterministic regardless of how long each frame takes to run.
+//!
+//! ```no_run
+//! use awgen_server::prelude::*;
+//!
+//! let mut test = TestServer::builder()
+//!     .with_world(WorldConfig::new("lobby", "default"))
+//!     .with_clients(1)
+//!     .build()
+//!     .unwrap();
+//!
+//! assert!(test.tick_until(200, |test| test.players().len() == 1));
+//! let player = test.players()[0];
+//! test.assert_in_world(player, "lobby");
+//! ```
+
+
+use crate::prelude::{
+    execute_command, BanListFile, CommandSender, HostedWorlds, PlayerDataDirectory, WorldConfig, WorldDataDirectory
+};
+use crate::ServerPlugin;
+use anyhow::{anyhow, Result};
+use awgen_network::prelude::ClientSocket;
+use awgen_network::NetworkPlugin;
+use awgen_physics::PhysicsPlugin;
+use awgen_world::prelude::InWorld;
+use awgen_world::WorldDataPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy_renet::renet::RenetClient;
+use std::fmt::Debug;
+use std::fs;
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+
+/// The default number of physics frames per second within a test server.
+const TEST_TICKRATE: f32 = 25.0;
+
+
+/// The maximum number of virtual clients that may connect to a test server.
+const TEST_MAX_CLIENTS: usize = 16;
+
+
+/// A counter used to give each test server within a process a unique data
+/// directory.
+static NEXT_TEST_ID: AtomicUsize = AtomicUsize::new(0);
+
+
+/// A builder for creating a new [`TestServer`].
+#[derive(Debug, Clone, Default)]
+pub struct TestServerBuilder {
+    /// The server plugin to run within the test server.
+    plugin: ServerPlugin,
+
+    /// The number of virtual clients to connect when the server is built.
+    clients: usize,
+}
+
+impl TestServerBuilder {
+    /// Adds a world to be hosted by the test server.
+    pub fn with_world(mut self, config: WorldConfig) -> Self {
+        self.plugin = self.plugin.with_world(config);
+        self
+    }
+
+
+    /// Replaces the server plugin that is run within the test server.
+    pub fn with_plugin(mut self, plugin: ServerPlugin) -> Self {
+        self.plugin = plugin;
+        self
+    }
+
+
+    /// Sets the number of virtual clients that are connected when the server
+    /// is built.
+    pub fn with_clients(mut self, clients: usize) -> Self {
+        self.clients = clients;
+        self
+    }
+
+
+    /// Builds the test server and all of its virtual clients.
+    ///
+    /// Each test server stores its player data, world data, and ban list within
+    /// a unique temporary directory that is removed when the server is dropped.
+    pub fn build(self) -> Result<TestServer> {
+        let port = UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let test_id = NEXT_TEST_ID.fetch_add(1, Ordering::Relaxed);
+        let data_dir =
+            std::env::temp_dir().join(format!("awgen-test-{}-{test_id}", std::process::id()));
+
+        let epoch = Instant::now();
+
+        let mut server = App::new();
+        server
+            .add_plugins(MinimalPlugins)
+            .insert_resource(Time::new(epoch))
+            .insert_resource(TimeUpdateStrategy::ManualInstant(epoch))
+            .add_plugin(PhysicsPlugin::new(TEST_TICKRATE))
+            .add_plugin(NetworkPlugin::new_server(port, TEST_MAX_CLIENTS))
+            .add_plugin(WorldDataPlugin)
+            .add_plugin(self.plugin)
+            .insert_resource(PlayerDataDirectory(data_dir.join("players")))
+            .insert_resource(WorldDataDirectory(data_dir.join("worlds")))
+            .insert_resource(BanListFile(data_dir.join("bans.ron")));
+
+        let mut test = TestServer {
+            server,
+            clients: vec![],
+            port,
+            data_dir,
+            epoch,
+            frame: 0,
+        };
+
+        for _ in 0..self.clients {
+            test.connect_client();
+        }
+
+        Ok(test)
+    }
+}
+
+
+/// A headless server instance with a set of virtual clients, used for
+/// integration testing.
+pub struct TestServer {
+    /// The server app.
+    server: App,
+
+    /// The virtual client apps.
+    clients: Vec<App>,
+
+    /// The port the server is listening on.
+    port: u16,
+
+    /// The directory that player and world data is stored within.
+    data_dir: PathBuf,
+
+    /// The simulated instant at which the test server was started.
+    epoch: Instant,
+
+    /// The number of frames that have been run so far.
+    frame: u32,
+}
+
+impl TestServer {
+    /// Creates a new test server builder.
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+
+
+    /// Creates a new virtual client and begins connecting it to the server.
+    ///
+    /// Returns the index of the new client.
+    pub fn connect_client(&mut self) -> usize {
+        let now = self.now();
+
+        let mut client = App::new();
+        client
+            .add_plugins(MinimalPlugins)
+            .insert_resource(Time::new(now))
+            .insert_resource(TimeUpdateStrategy::ManualInstant(now))
+            .add_plugin(PhysicsPlugin::new(TEST_TICKRATE))
+            .add_plugin(NetworkPlugin::new_client("127.0.0.1", self.port))
+            .add_plugin(WorldDataPlugin);
+
+        self.clients.push(client);
+        self.clients.len() - 1
+    }
+
+
+    /// Gets the simulated instant of the current frame.
+    fn now(&self) -> Instant {
+        self.epoch + Duration::from_secs_f64(1.0 / TEST_TICKRATE as f64) * self.frame
+    }
+
+
+    /// Updates the server, followed by each virtual client, for the given
+    /// number of frames.
+    ///
+    /// Each frame advances the simulated time by exactly one physics tick.
+    pub fn tick(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.frame += 1;
+            let now = self.now();
+
+            for app in std::iter::once(&mut self.server).chain(&mut self.clients) {
+                app.insert_resource(TimeUpdateStrategy::ManualInstant(now));
+                app.update();
+            }
+        }
+    }
+
+
+    /// Updates the server and all virtual clients one frame at a time until
+    /// the given condition is met or the maximum number of frames have passed.
+    ///
+    /// Returns whether or not the condition was met.
+    pub fn tick_until<F>(&mut self, max_frames: usize, mut condition: F) -> bool
+    where F: FnMut(&mut TestServer) -> bool {
+        for _ in 0..max_frames {
+            if condition(self) {
+                return true;
+            }
+
+            self.tick(1);
+        }
+
+        condition(self)
+    }
+
+
+    /// Adds an additional plugin to the server app, such as the block type
+    /// extension plugins of a game.
+    pub fn add_plugin<P>(&mut self, plugin: P) -> &mut Self
+    where P: Plugin {
+        self.server.add_plugin(plugin);
+        self
+    }
+
+
+    /// Gets the server world.
+    pub fn world(&mut self) -> &mut World {
+        &mut self.server.world
+    }
+
+
+    /// Gets the world of the virtual client with the given index.
+    ///
+    /// Panics if there is no virtual client with the given index.
+    pub fn client(&mut self, index: usize) -> &mut World {
+        &mut self.clients[index].world
+    }
+
+
+    /// Gets the number of virtual clients.
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+
+    /// Gets all player entities that are currently connected to the server.
+    pub fn players(&mut self) -> Vec<Entity> {
+        let world = self.world();
+        world.query_filtered::<Entity, With<ClientSocket>>().iter(world).collect()
+    }
+
+
+    /// Gets the server-side player entity of the virtual client with the given
+    /// index, if it has connected.
+    pub fn player(&mut self, index: usize) -> Option<Entity> {
+        let client_id = self.client(index).resource::<RenetClient>().client_id();
+        let world = self.world();
+        world
+            .query::<(Entity, &ClientSocket)>()
+            .iter(world)
+            .find(|(_, socket)| socket.id() == client_id)
+            .map(|(entity, _)| entity)
+    }
+
+
+    /// Gets the entity of the hosted world with the given name.
+    pub fn hosted_world(&self, name: &str) -> Result<Entity> {
+        self.server
+            .world
+            .resource::<HostedWorlds>()
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown world: {name}"))
+    }
+
+
+    /// Executes a command on the server as the console, returning the command
+    /// response.
+    pub fn run_command(&mut self, line: &str) -> Result<String> {
+        execute_command(self.world(), &CommandSender::Console, line)
+    }
+
+
+    /// Asserts that the given server entity has a component equal to the
+    /// expected value.
+    pub fn assert_component<T>(&self, entity: Entity, expected: &T)
+    where T: Component + PartialEq + Debug {
+        let actual = self.server.world.get::<T>(entity);
+        assert_eq!(actual, Some(expected), "Component mismatch on {entity:?}");
+    }
+
+
+    /// Asserts that the given server entity is within the hosted world with the
+    /// given name.
+    pub fn assert_in_world(&self, entity: Entity, world_name: &str) {
+        let world = self.hosted_world(world_name).unwrap();
+        let in_world = self.server.world.get::<InWorld>(entity).map(|w| w.0);
+        assert_eq!(
+            in_world,
+            Some(world),
+            "{entity:?} is not in world '{world_name}'"
+        );
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{BlockEditPlugin, ReplicationView, TransferPlayer};
+    use awgen_math::prelude::{Direction, Seed};
+    use awgen_network::prelude::{
+        send_chat_message, send_to_server, BlockEditAck, BlockEditAction, BlockEditMessage, ChatMessageReceivedEvent, MessageBatch, RemoteEntities, ServerMessage
+    };
+    use awgen_physics::prelude::{Inventory, ItemStack, PhysicsFrame, Position};
+    use awgen_world::prelude::{
+        BlockHardness, BlockItem, ChunkState, ChunkView, ContainerBlock, ContainerPlugin, GenerationPhase, GenerationStage, InteractionPlugin, Persistent, VoxelChunkStates, VoxelWorld, WorldGenerator
+    };
+    use awgen_world::WorldDataTypePlugin;
+    use bevy::ecs::event::{Event, ManualEventReader};
+    use bevy::ecs::system::Command;
+    use pretty_assertions::assert_eq;
+
+
+    /// A minimal block type for testing.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        /// An empty block.
+        #[default]
+        Air,
+
+        /// A solid block.
+        Stone,
+    }
+
+    impl BlockItem for TestBlock {
+        fn from_item(item: &str) -> Option<Self> {
+            match item {
+                "stone" => Some(TestBlock::Stone),
+                _ => None,
+            }
+        }
+    }
+
+    impl BlockHardness for TestBlock {
+        fn hardness(&self) -> Option<f32> {
+            match self {
+                TestBlock::Air => None,
+                TestBlock::Stone => Some(1.0),
+            }
+        }
+    }
+
+
+    /// Fills every block below y = 0 with stone.
+    struct Ground;
+
+    impl GenerationStage<TestBlock> for Ground {
+        fn name(&self) -> &str {
+            "ground"
+        }
+
+
+        fn phase(&self) -> GenerationPhase {
+            GenerationPhase::TerrainShape
+        }
+
+
+        fn generate(&self, view: &mut ChunkView<TestBlock>) {
+            for pos in view.area().iter().filter(|pos| pos.y < 0) {
+                view.set_block(pos, TestBlock::Stone);
+            }
+        }
+    }
+
+
+    /// Creates a test server hosting two flat worlds of test blocks, "lobby"
+    /// and "arena", with the given number of connected clients. Players join
+    /// the lobby.
+    fn flat_world(clients: usize) -> TestServer {
+        let mut test = TestServer::builder()
+            .with_world(WorldConfig::new("lobby", "flat"))
+            .with_world(WorldConfig::new("arena", "flat"))
+            .with_clients(clients)
+            .build()
+            .unwrap();
+
+        test.add_plugin(WorldDataTypePlugin::<TestBlock>::default())
+            .add_plugin(BlockEditPlugin::<TestBlock>::default());
+
+        assert!(test.tick_until(10, |test| test.hosted_world("arena").is_ok()));
+        for name in ["lobby", "arena"] {
+            let world = test.hosted_world(name).unwrap();
+            test.world().entity_mut(world).insert((
+                VoxelWorld::<TestBlock>::default(),
+                WorldGenerator::new(Seed(1)).with_stage(Ground),
+            ));
+        }
+
+        assert!(test.tick_until(200, |test| {
+            let players = test.players();
+            players.len() == clients
+                && players.iter().all(|&player| test.world().get::<InWorld>(player).is_some())
+        }));
+        test
+    }
+
+
+    /// Reads all events of the given type that have been triggered within the
+    /// given world since the last read.
+    fn read_events<E>(world: &World, reader: &mut ManualEventReader<E>) -> Vec<E>
+    where E: Event + Clone {
+        reader.iter(world.resource::<Events<E>>()).cloned().collect()
+    }
+
+
+    #[test]
+    fn time_advances_one_tick_per_frame() {
+        let mut test = TestServer::builder().with_clients(1).build().unwrap();
+        test.tick(1);
+
+        let start = test.world().resource::<PhysicsFrame>().clone();
+        let elapsed = test.world().resource::<Time>().elapsed();
+        test.tick(10);
+
+        let time = test.world().resource::<Time>();
+        assert_eq!(time.elapsed() - elapsed, Duration::from_millis(400));
+        assert_eq!(
+            test.client(0).resource::<Time>().delta(),
+            Duration::from_millis(40)
+        );
+        assert_eq!(
+            test.world().resource::<PhysicsFrame>().frame_number(),
+            start.frame_number() + 10
+        );
+    }
+
+
+    #[test]
+    fn players_join_default_world() {
+        let mut test = flat_world(2);
+
+        for index in 0..2 {
+            let player = test.player(index).unwrap();
+            test.assert_in_world(player, "lobby");
+        }
+    }
+
+
+    #[test]
+    fn chunks_stream_around_players() {
+        let mut test = flat_world(1);
+        let world = test.hosted_world("lobby").unwrap();
+
+        let loaded = test.tick_until(200, |test| {
+            let voxels = test.world().get::<VoxelWorld<TestBlock>>(world).unwrap();
+            voxels.get_block_data(IVec3::new(1, -1, 0)) == TestBlock::Stone
+        });
+        assert!(loaded);
+
+        let states = test.world().get::<VoxelChunkStates>(world).unwrap();
+        assert!(states.chunks_in_state(ChunkState::Loaded).count() > 1);
+    }
+
+
+    #[test]
+    fn place_block() {
+        let mut test = flat_world(1);
+        let world = test.hosted_world("lobby").unwrap();
+        let player = test.player(0).unwrap();
+        let client_id = test.world().get::<ClientSocket>(player).unwrap().id();
+
+        assert!(test.tick_until(200, |test| {
+            let voxels = test.world().get::<VoxelWorld<TestBlock>>(world).unwrap();
+            voxels.get_block_data(IVec3::new(1, -1, 0)) == TestBlock::Stone
+        }));
+
+        test.run_command(&format!("give stone 1 {client_id}")).unwrap();
+        test.tick(5);
+
+        send_to_server(
+            &mut test.client(0).resource_mut::<MessageBatch>(),
+            &BlockEditMessage {
+                sequence:  7,
+                block_pos: IVec3::new(1, -1, 0),
+                face:      Direction::PosY,
+                action:    BlockEditAction::Place {
+                    item: "stone".to_string(),
+                },
+            },
+        );
+
+        let mut reader = ManualEventReader::default();
+        let mut acks = vec![];
+        assert!(test.tick_until(100, |test| {
+            acks.extend(read_events::<ServerMessage<BlockEditAck>>(
+                test.client(0),
+                &mut reader,
+            ));
+            !acks.is_empty()
+        }));
+
+        assert_eq!(acks, vec![ServerMessage {
+            message: BlockEditAck {
+                sequence: 7,
+                accepted: true,
+            },
+        }]);
+
+        let voxels = test.world().get::<VoxelWorld<TestBlock>>(world).unwrap();
+        assert_eq!(voxels.get_block_data(IVec3::new(1, 0, 0)), TestBlock::Stone);
+    }
+
+
+    #[test]
+    fn chat_is_broadcast_to_all_clients() {
+        let mut test = flat_world(2);
+        let sender = test.player(0).unwrap();
+        let client_id = test.world().get::<ClientSocket>(sender).unwrap().id();
+
+        send_chat_message(&mut test.client(0).resource_mut::<MessageBatch>(), "Hello!");
+
+        let mut readers = [ManualEventReader::default(), ManualEventReader::default()];
+        let mut received = [vec![], vec![]];
+        assert!(test.tick_until(100, |test| {
+            for (index, reader) in readers.iter_mut().enumerate() {
+                let events = read_events::<ChatMessageReceivedEvent>(test.client(index), reader);
+                received[index].extend(events.into_iter().map(|ev| (ev.sender, ev.text)));
+            }
+            received.iter().all(|messages| !messages.is_empty())
+        }));
+
+        for messages in received {
+            assert_eq!(messages, vec![(Some(client_id), "Hello!".to_string())]);
+        }
+    }
+
+
+    #[test]
+    fn say_is_broadcast_to_all_clients() {
+        let mut test = flat_world(2);
+        test.run_command("say Welcome").unwrap();
+
+        let mut readers = [ManualEventReader::default(), ManualEventReader::default()];
+        let mut received = [vec![], vec![]];
+        assert!(test.tick_until(100, |test| {
+            for (index, reader) in readers.iter_mut().enumerate() {
+                let events = read_events::<ChatMessageReceivedEvent>(test.client(index), reader);
+                received[index].extend(events.into_iter().map(|ev| (ev.sender, ev.text)));
+            }
+            received.iter().all(|messages| !messages.is_empty())
+        }));
+
+        for messages in received {
+            assert_eq!(messages, vec![(None, "Welcome".to_string())]);
+        }
+    }
+
+
+    /// Gets the number of loaded chunks within the hosted world with the given
+    /// name.
+    fn loaded_chunks(test: &mut TestServer, name: &str) -> usize {
+        let world = test.hosted_world(name).unwrap();
+        let states = test.world().get::<VoxelChunkStates>(world).unwrap();
+        states.chunks_in_state(ChunkState::Loaded).count()
+    }
+
+
+    #[test]
+    fn transfer_player_resets_replication() {
+        let mut test = flat_world(2);
+        let first = test.player(0).unwrap();
+        let second = test.player(1).unwrap();
+
+        assert!(test.tick_until(100, |test| {
+            (0..2).all(|index| test.client(index).resource::<RemoteEntities>().len() == 1)
+        }));
+
+        let arena = test.hosted_world("arena").unwrap();
+        TransferPlayer {
+            player:   first,
+            world:    arena,
+            position: Vec3::ZERO,
+        }
+        .write(test.world());
+
+        assert!(test.tick_until(100, |test| {
+            (0..2).all(|index| test.client(index).resource::<RemoteEntities>().is_empty())
+        }));
+
+        test.assert_in_world(first, "arena");
+        for player in [first, second] {
+            let view = test.world().get::<ReplicationView>(player).unwrap();
+            assert!(view.is_empty());
+        }
+
+        TransferPlayer {
+            player:   first,
+            world:    test.hosted_world("lobby").unwrap(),
+            position: Vec3::ZERO,
+        }
+        .write(test.world());
+
+        assert!(test.tick_until(100, |test| {
+            (0..2).all(|index| test.client(index).resource::<RemoteEntities>().len() == 1)
+        }));
+    }
+
+
+    #[test]
+    fn transfer_player_unloads_previous_world() {
+        let mut test = flat_world(1);
+        let player = test.player(0).unwrap();
+
+        assert!(test.tick_until(200, |test| loaded_chunks(test, "lobby") > 0));
+        assert_eq!(loaded_chunks(&mut test, "arena"), 0);
+
+        let arena = test.hosted_world("arena").unwrap();
+        TransferPlayer {
+            player,
+            world: arena,
+            position: Vec3::ZERO,
+        }
+        .write(test.world());
+        test.tick(2);
+
+        assert_eq!(loaded_chunks(&mut test, "lobby"), 0);
+        assert!(test.tick_until(200, |test| loaded_chunks(test, "arena") > 0));
+    }
+
+
+    /// Gets the inventory of each container within the hosted world with the
+    /// given name.
+    fn container_inventories(test: &mut TestServer, name: &str) -> Vec<Inventory> {
+        let world_entity = test.hosted_world(name).unwrap();
+        let world = test.world();
+        world
+            .query_filtered::<(&InWorld, &Inventory), With<ContainerBlock>>()
+            .iter(world)
+            .filter(|(in_world, _)| in_world.0 == world_entity)
+            .map(|(_, inventory)| inventory.clone())
+            .collect()
+    }
+
+
+    #[test]
+    fn container_survives_chunk_unload() {
+        let mut test = flat_world(1);
+        test.add_plugin(InteractionPlugin::<TestBlock>::default())
+            .add_plugin(ContainerPlugin::<TestBlock>::default());
+
+        let lobby = test.hosted_world("lobby").unwrap();
+        let player = test.player(0).unwrap();
+        let block_pos = IVec3::new(1, -1, 0);
+
+        assert!(test.tick_until(200, |test| {
+            let voxels = test.world().get::<VoxelWorld<TestBlock>>(lobby).unwrap();
+            voxels.get_block_data(block_pos) == TestBlock::Stone
+        }));
+        test.tick(5);
+
+        let mut inventory = Inventory::new(3);
+        inventory.set(1, ItemStack::new("stone", 10));
+        test.world().spawn((
+            ContainerBlock {
+                block_pos,
+            },
+            inventory.clone(),
+            Position {
+                translation: block_pos.as_vec3() + Vec3::splat(0.5),
+                ..default()
+            },
+            InWorld(lobby),
+            Persistent,
+        ));
+        test.tick(5);
+        assert_eq!(container_inventories(&mut test, "lobby"), vec![
+            inventory.clone()
+        ]);
+
+        TransferPlayer {
+            player,
+            world: test.hosted_world("arena").unwrap(),
+            position: Vec3::ZERO,
+        }
+        .write(test.world());
+        test.tick(5);
+
+        assert_eq!(loaded_chunks(&mut test, "lobby"), 0);
+        assert_eq!(container_inventories(&mut test, "lobby"), vec![]);
+
+        TransferPlayer {
+            player,
+            world: lobby,
+            position: Vec3::ZERO,
+        }
+        .write(test.world());
+
+        assert!(test.tick_until(200, |test| !container_inventories(test, "lobby").is_empty()));
+        test.tick(5);
+        assert_eq!(container_inventories(&mut test, "lobby"), vec![inventory]);
+    }
+}
@@ -0,0 +1,339 @@
+//! Decides the weather of each hosted world, moving between clear skies, rain,
+//! and storms over time, and syncs it to the players within each world.
+//!
+//! The weather of each world follows its own deterministic sequence, derived
+//! from the world seed, and is saved alongside the other per-world data files.
+//! Gameplay systems may react to the weather through the
+//! [`WeatherChangedEvent`] and [`PrecipitationEvent`] events.
+
+
+use crate::prelude::{CommandSender, GameRules, HostedWorlds, WorldConfig, WorldDataDirectory};
+use anyhow::{bail, Result};
+use awgen_math::prelude::{Seed, SeededRng};
+use awgen_network::prelude::{send_to_client, ClientSocket, MessageBatch, Weather, WeatherMessage};
+use awgen_world::prelude::InWorld;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+/// The number of seconds that clients blend between weather states when the
+/// weather changes.
+const WEATHER_TRANSITION_SECONDS: f32 = 10.0;
+
+
+/// The number of seconds that weather which is set by a command lasts for, if
+/// no duration is given.
+const DEFAULT_COMMAND_DURATION: f32 = 600.0;
+
+
+/// The number of seconds between each precipitation event within a world that
+/// is raining.
+const PRECIPITATION_INTERVAL: f32 = 1.0;
+
+
+/// The weather of a hosted world.
+#[derive(Debug, Clone, PartialEq, Reflect, Component, Default, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct WorldWeather {
+    /// The current weather state.
+    weather: Weather,
+
+    /// The number of seconds until the weather next changes.
+    remaining: f32,
+
+    /// The number of weather changes that have occurred within this world,
+    /// which is used to derive the next weather state.
+    cycle: u64,
+
+    /// The number of seconds since the last precipitation event.
+    #[serde(skip)]
+    precipitation_timer: f32,
+}
+
+impl WorldWeather {
+    /// Creates the initial weather of a world with the given seed.
+    pub fn new(seed: Seed) -> Self {
+        let mut rng = weather_rng(seed, 0);
+        Self {
+            weather:             Weather::Clear,
+            remaining:           weather_duration(Weather::Clear, &mut rng),
+            cycle:               0,
+            precipitation_timer: 0.0,
+        }
+    }
+
+
+    /// Gets the current weather state.
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+
+    /// Gets the number of seconds until the weather next changes.
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+
+
+    /// Replaces the current weather state, which then lasts for the given
+    /// number of seconds.
+    ///
+    /// Returns the previous weather state.
+    pub fn set(&mut self, weather: Weather, duration: f32) -> Weather {
+        let previous = self.weather;
+        self.weather = weather;
+        self.remaining = duration.max(0.0);
+        previous
+    }
+
+
+    /// Moves on to the next weather state of the sequence, derived from the
+    /// given world seed.
+    ///
+    /// Returns the previous weather state.
+    fn advance(&mut self, seed: Seed) -> Weather {
+        self.cycle += 1;
+        let mut rng = weather_rng(seed, self.cycle);
+        let next = next_weather(self.weather, &mut rng);
+        let duration = weather_duration(next, &mut rng);
+        self.set(next, duration)
+    }
+}
+
+
+/// An event that is triggered when the weather of a world changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeatherChangedEvent {
+    /// The world entity.
+    pub world: Entity,
+
+    /// The previous weather state.
+    pub previous: Weather,
+
+    /// The new weather state.
+    pub weather: Weather,
+}
+
+
+/// An event that is triggered at a regular interval for each world while
+/// precipitation is falling within it.
+///
+/// Gameplay systems may listen for this event to apply the effects of rain on
+/// blocks that are exposed to the sky, such as filling cauldrons or
+/// extinguishing fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrecipitationEvent {
+    /// The world entity.
+    pub world: Entity,
+
+    /// The current weather state of the world.
+    pub weather: Weather,
+}
+
+
+/// Creates the random number generator for the given weather cycle of a world
+/// with the given seed.
+fn weather_rng(seed: Seed, cycle: u64) -> SeededRng {
+    seed.derive_str("weather").derive(cycle).rng()
+}
+
+
+/// Picks the weather state that follows the given weather state.
+fn next_weather(current: Weather, rng: &mut SeededRng) -> Weather {
+    match current {
+        Weather::Clear if rng.chance(0.15) => Weather::Storm,
+        Weather::Clear => Weather::Rain,
+        Weather::Rain if rng.chance(0.4) => Weather::Storm,
+        Weather::Rain => Weather::Clear,
+        Weather::Storm if rng.chance(0.3) => Weather::Clear,
+        Weather::Storm => Weather::Rain,
+    }
+}
+
+
+/// Picks the number of seconds that the given weather state lasts for.
+fn weather_duration(weather: Weather, rng: &mut SeededRng) -> f32 {
+    let (min, max) = match weather {
+        Weather::Clear => (600.0, 1800.0),
+        Weather::Rain => (180.0, 600.0),
+        Weather::Storm => (120.0, 360.0),
+    };
+
+    rng.range_f32(min, max)
+}
+
+
+/// Loads the saved weather of each newly hosted world.
+pub fn load_world_weather(
+    directory: Res<WorldDataDirectory>,
+    worlds: Query<(Entity, &WorldConfig), Added<WorldConfig>>,
+    mut commands: Commands,
+) {
+    for (entity, config) in worlds.iter() {
+        let weather = match directory.load_weather(&config.name) {
+            Ok(weather) => weather.unwrap_or_else(|| WorldWeather::new(config.seed)),
+            Err(err) => {
+                error!(
+                    "Failed to load the weather of world '{}': {err}",
+                    config.name
+                );
+                WorldWeather::new(config.seed)
+            },
+        };
+
+        commands.entity(entity).insert(weather);
+    }
+}
+
+
+/// Counts down the weather of each world, moving on to the next weather state
+/// once the current one has run out, and triggers precipitation events for
+/// worlds where precipitation is falling. The weather of each world advances
+/// at the tick speed of the world.
+pub fn update_world_weather(
+    time: Res<Time>,
+    mut worlds: Query<(Entity, &WorldConfig, &mut WorldWeather, Option<&GameRules>)>,
+    mut changed_ev: EventWriter<WeatherChangedEvent>,
+    mut precipitation_ev: EventWriter<PrecipitationEvent>,
+) {
+    for (world, config, mut weather, rules) in worlds.iter_mut() {
+        let delta = time.delta_seconds() * rules.map_or(1.0, |rules| rules.tick_speed);
+        weather.remaining -= delta;
+        if weather.remaining <= 0.0 {
+            let previous = weather.advance(config.seed);
+            changed_ev.send(WeatherChangedEvent {
+                world,
+                previous,
+                weather: weather.weather,
+            });
+        }
+
+        if !weather.weather.is_precipitating() {
+            weather.precipitation_timer = 0.0;
+            continue;
+        }
+
+        weather.precipitation_timer += delta;
+        if weather.precipitation_timer >= PRECIPITATION_INTERVAL {
+            weather.precipitation_timer -= PRECIPITATION_INTERVAL;
+            precipitation_ev.send(PrecipitationEvent {
+                world,
+                weather: weather.weather,
+            });
+        }
+    }
+}
+
+
+/// Saves the weather of each world whenever it changes.
+///
+/// The remaining duration of the weather is only saved when the weather
+/// changes, so the current weather state restarts its duration if the server
+/// is restarted.
+pub fn save_world_weather(
+    directory: Res<WorldDataDirectory>,
+    mut changed_ev: EventReader<WeatherChangedEvent>,
+    worlds: Query<(&WorldConfig, &WorldWeather)>,
+) {
+    for ev in changed_ev.iter() {
+        let Ok((config, weather)) = worlds.get(ev.world) else {
+            continue;
+        };
+
+        if let Err(err) = directory.save_weather(&config.name, weather) {
+            error!(
+                "Failed to save the weather of world '{}': {err}",
+                config.name
+            );
+        }
+    }
+}
+
+
+/// Sends the weather of each world to the players within it whenever it
+/// changes, and to each player that enters a world.
+pub fn sync_world_weather(
+    mut batch: ResMut<MessageBatch>,
+    mut changed_ev: EventReader<WeatherChangedEvent>,
+    players: Query<(&ClientSocket, &InWorld)>,
+    entered: Query<(&ClientSocket, &InWorld), Changed<InWorld>>,
+    worlds: Query<&WorldWeather>,
+) {
+    for ev in changed_ev.iter() {
+        for (socket, _) in players.iter().filter(|(_, in_world)| in_world.0 == ev.world) {
+            send_to_client(&mut batch, socket.id(), &WeatherMessage {
+                weather:    ev.weather,
+                transition: WEATHER_TRANSITION_SECONDS,
+            });
+        }
+    }
+
+    for (socket, in_world) in entered.iter() {
+        let Ok(weather) = worlds.get(in_world.0) else {
+            continue;
+        };
+
+        send_to_client(&mut batch, socket.id(), &WeatherMessage {
+            weather:    weather.weather,
+            transition: 0.0,
+        });
+    }
+}
+
+
+/// Changes or reports the weather of a world.
+///
+/// Usage: `weather [<clear|rain|storm> [seconds] [world]]`
+///
+/// If no world is given, the world of the sending player is used.
+pub fn weather_command(world: &mut World, sender: &CommandSender, args: &[&str]) -> Result<String> {
+    let target = match args.get(2) {
+        Some(name) => world.resource::<HostedWorlds>().get(name),
+        None => {
+            match sender {
+                CommandSender::Player(player) => world.get::<InWorld>(*player).map(|w| w.0),
+                CommandSender::Console | CommandSender::Remote(_) => {
+                    world.resource::<HostedWorlds>().default_world()
+                },
+            }
+        },
+    };
+
+    let Some(target) = target else {
+        bail!("Unknown world");
+    };
+
+    let name = world.get::<WorldConfig>(target).map(|c| c.name.clone()).unwrap_or_default();
+    let Some(mut weather) = world.get_mut::<WorldWeather>(target) else {
+        bail!("World '{name}' does not have weather");
+    };
+
+    let Some(state) = args.first() else {
+        return Ok(format!(
+            "The weather of world '{name}' is {} for {:.0} more seconds",
+            weather.weather.name(),
+            weather.remaining
+        ));
+    };
+
+    let Some(state) = Weather::from_name(state) else {
+        bail!("Usage: weather [<clear|rain|storm> [seconds] [world]]");
+    };
+
+    let duration = match args.get(1) {
+        Some(seconds) => seconds.parse()?,
+        None => DEFAULT_COMMAND_DURATION,
+    };
+
+    let previous = weather.set(state, duration);
+    world.resource_mut::<Events<WeatherChangedEvent>>().send(WeatherChangedEvent {
+        world: target,
+        previous,
+        weather: state,
+    });
+
+    Ok(format!(
+        "Set the weather of world '{name}' to {} for {duration:.0} seconds",
+        state.name()
+    ))
+}
@@ -0,0 +1,850 @@
+//! Handles hosting multiple voxel worlds on a single server instance, such as a
+//! lobby world alongside several mini-game arenas, and moving players between
+//! them.
+
+
+use crate::prelude::{
+    read_save, write_save, CommandSender, GameRules, PositionHistory, SaveKind, WorldWeather
+};
+use anyhow::{bail, Result};
+use awgen_math::prelude::{world_to_block, Seed};
+use awgen_physics::prelude::{Position, PreviousPosition};
+use awgen_world::prelude::{ChunkAnchor, ChunkState, InWorld, UnloadChunkEvent, VoxelChunkStates};
+use bevy::app::AppExit;
+use bevy::ecs::system::{Command, EntityCommands};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// The configuration settings for a single world that is hosted on the server.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct WorldConfig {
+    /// The unique name of this world.
+    pub name: String,
+
+    /// The id of the world generator that is used to populate new chunks
+    /// within this world.
+    pub generator: String,
+
+    /// The gameplay rules for this world, which are used until the world has
+    /// saved game rules of its own.
+    pub rules: GameRules,
+
+    /// The seed used by the world generator. If the world has already been
+    /// created, this is replaced by the seed the world was created with.
+    pub seed: Seed,
+}
+
+impl WorldConfig {
+    /// Creates a new world configuration with the given name and world
+    /// generator id, using the default world rules.
+    pub fn new<N, G>(name: N, generator: G) -> Self
+    where
+        N: Into<String>,
+        G: Into<String>, {
+        Self {
+            name:      name.into(),
+            generator: generator.into(),
+            rules:     default(),
+            seed:      default(),
+        }
+    }
+
+
+    /// Replaces the gameplay rules of this world configuration.
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+
+    /// Sets the seed used to generate this world, if it does not yet exist.
+    pub fn with_seed(mut self, seed: Seed) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+
+/// A bundle containing all of the components required for a world to be
+/// hosted by the server.
+#[derive(Bundle, Default)]
+pub struct HostedWorldBundle {
+    /// The name of the world entity.
+    name: Name,
+
+    /// The configuration of the hosted world.
+    config: WorldConfig,
+
+    /// The chunk loading states of the world.
+    chunk_states: VoxelChunkStates,
+}
+
+impl HostedWorldBundle {
+    /// Creates a new hosted world bundle from the given world configuration.
+    pub fn new(config: WorldConfig) -> Self {
+        Self {
+            name: Name::new(config.name.clone()),
+            config,
+            chunk_states: default(),
+        }
+    }
+}
+
+
+/// The spawn point of a hosted world. Players without a respawn point of their
+/// own are placed here when joining or respawning.
+#[derive(Debug, Clone, PartialEq, Reflect, Component, Default, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct WorldSpawn {
+    /// The spawn position within the world.
+    pub position: Vec3,
+}
+
+
+/// The default number of seconds between each save of the metadata of all
+/// hosted worlds.
+pub const WORLD_META_AUTOSAVE_SECONDS: f32 = 60.0;
+
+
+/// The border of a world, outside of which players are not meant to travel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorldBorder {
+    /// The horizontal center of the border, on the X and Z axes.
+    pub center: Vec2,
+
+    /// The horizontal distance, in meters, from the center to each edge of the
+    /// border.
+    pub radius: f32,
+}
+
+
+/// The metadata of a world, which is saved alongside the world and is the
+/// source of truth for how the world was created.
+///
+/// This is inserted into each hosted world entity once its metadata has been
+/// loaded.
+#[derive(Debug, Clone, PartialEq, Component, Serialize, Deserialize)]
+pub struct WorldMeta {
+    /// The name of the world.
+    #[serde(default)]
+    pub name: String,
+
+    /// The id of the world generator that this world was created with.
+    pub generator: String,
+
+    /// The settings of the world generator, by name.
+    #[serde(default)]
+    pub generator_settings: BTreeMap<String, String>,
+
+    /// The seed that this world was created with.
+    pub seed: Seed,
+
+    /// The spawn point of the world.
+    #[serde(default)]
+    pub spawn: Vec3,
+
+    /// The number of physics ticks that have passed within the world while it
+    /// was hosted.
+    #[serde(default)]
+    pub time: u64,
+
+    /// The border of the world, if it has one.
+    #[serde(default)]
+    pub border: Option<WorldBorder>,
+
+    /// The game rules of the world, if they have been saved.
+    #[serde(default)]
+    pub rules: Option<GameRules>,
+
+    /// The version of Awgen that this world was last hosted with.
+    pub version: String,
+
+    /// The time that this world was last hosted, in seconds since the Unix
+    /// epoch.
+    pub last_played: u64,
+
+    /// The fraction of a physics tick that the time of the world has advanced
+    /// by, when the world runs at a tick speed that is not a whole number.
+    #[serde(skip)]
+    tick_progress: f32,
+}
+
+impl WorldMeta {
+    /// Creates new world metadata for a world with the given name, world
+    /// generator, and seed, marked as last played at the current time.
+    pub fn new<N, G>(name: N, generator: G, seed: Seed) -> Self
+    where
+        N: Into<String>,
+        G: Into<String>, {
+        Self {
+            name: name.into(),
+            generator: generator.into(),
+            generator_settings: default(),
+            seed,
+            spawn: Vec3::ZERO,
+            time: 0,
+            border: None,
+            rules: None,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            last_played: 0,
+            tick_progress: 0.0,
+        }
+        .played_now()
+    }
+
+
+    /// Marks this world as last played at the current time, with the current
+    /// version of Awgen.
+    pub fn played_now(mut self) -> Self {
+        self.version = env!("CARGO_PKG_VERSION").to_string();
+        self.last_played = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        self
+    }
+}
+
+
+/// A summary of a world that has been saved to disk, as listed by
+/// [`WorldDataDirectory::list_worlds`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldSummary {
+    /// The name of the world.
+    pub name: String,
+
+    /// The metadata of the world, if it could be loaded.
+    pub meta: Option<WorldMeta>,
+
+    /// The total size of all files within the world directory, in bytes.
+    pub size: u64,
+}
+
+
+/// The directory that per-world data files, such as the world metadata, are
+/// stored within. Each world is given a sub-directory matching its name.
+#[derive(Debug, Clone, Resource)]
+pub struct WorldDataDirectory(pub PathBuf);
+
+impl WorldDataDirectory {
+    /// Gets the path of the spawn point file for the world with the given name,
+    /// which was used before the spawn point became part of the world
+    /// metadata.
+    fn legacy_spawn_path(&self, world_name: &str) -> PathBuf {
+        self.0.join(world_name).join("spawn.ron")
+    }
+
+
+    /// Gets the path of the weather file for the world with the given name.
+    fn weather_path(&self, world_name: &str) -> PathBuf {
+        self.0.join(world_name).join("weather.ron")
+    }
+
+
+    /// Gets the path that map images of the world with the given name are
+    /// exported to.
+    ///
+    /// If the name is empty or could escape the world directory, an error is
+    /// returned.
+    pub fn map_path(&self, world_name: &str) -> Result<PathBuf> {
+        Ok(self.world_path(world_name)?.join("map.png"))
+    }
+
+
+    /// Gets the path of the file that the persistent entities within the chunk
+    /// at the given chunk coordinates are saved to, for the world with the
+    /// given name.
+    pub fn chunk_entities_path(&self, world_name: &str, chunk_coords: IVec3) -> PathBuf {
+        self.0.join(world_name).join("entities").join(format!(
+            "{}.{}.{}.ron",
+            chunk_coords.x, chunk_coords.y, chunk_coords.z
+        ))
+    }
+
+
+    /// Gets the path of the metadata file for the world with the given name.
+    fn meta_path(&self, world_name: &str) -> PathBuf {
+        self.0.join(world_name).join("world.ron")
+    }
+
+
+    /// Gets the directory of the world with the given name.
+    ///
+    /// If the name is empty or could escape the world directory, an error is
+    /// returned.
+    fn world_path(&self, world_name: &str) -> Result<PathBuf> {
+        let valid = !world_name.is_empty()
+            && !world_name.starts_with('.')
+            && !world_name.contains(['/', '\\', ':']);
+
+        if !valid {
+            bail!("Invalid world name: '{world_name}'");
+        }
+
+        Ok(self.0.join(world_name))
+    }
+
+
+    /// Loads the metadata of the world with the given name.
+    ///
+    /// If the world still has a spawn point file from before the spawn point
+    /// became part of the world metadata, that spawn point is used instead. If
+    /// the world does not have saved metadata, `None` is returned.
+    pub fn load_meta(&self, world_name: &str) -> Result<Option<WorldMeta>> {
+        let Some(mut meta) = read_save::<WorldMeta>(&self.meta_path(world_name), SaveKind::World)?
+        else {
+            return Ok(None);
+        };
+
+        let legacy_spawn = self.legacy_spawn_path(world_name);
+        if let Some(spawn) = read_save::<WorldSpawn>(&legacy_spawn, SaveKind::World)? {
+            meta.spawn = spawn.position;
+        }
+
+        meta.name = world_name.to_string();
+        Ok(Some(meta))
+    }
+
+
+    /// Saves the metadata of the world with the given name, removing the spawn
+    /// point file of the world that it replaces, if any.
+    pub fn save_meta(&self, world_name: &str, meta: &WorldMeta) -> Result<()> {
+        write_save(&self.meta_path(world_name), meta)?;
+
+        let legacy_spawn = self.legacy_spawn_path(world_name);
+        if legacy_spawn.exists() {
+            fs::remove_file(legacy_spawn)?;
+        }
+
+        Ok(())
+    }
+
+
+    /// Creates a new, empty world with the given name, world generator, and
+    /// seed.
+    ///
+    /// If a world with the given name already exists, an error is returned.
+    pub fn create_world(&self, world_name: &str, generator: &str, seed: Seed) -> Result<WorldMeta> {
+        if self.world_path(world_name)?.exists() {
+            bail!("World '{world_name}' already exists");
+        }
+
+        let meta = WorldMeta::new(world_name, generator, seed);
+        self.save_meta(world_name, &meta)?;
+        Ok(meta)
+    }
+
+
+    /// Lists all worlds that have been saved within this directory, sorted by
+    /// name.
+    ///
+    /// If the directory does not exist, an empty list is returned.
+    pub fn list_worlds(&self) -> Result<Vec<WorldSummary>> {
+        if !self.0.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut worlds = vec![];
+        for entry in fs::read_dir(&self.0)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            worlds.push(WorldSummary {
+                meta: self.load_meta(&name).ok().flatten(),
+                size: directory_size(&entry.path())?,
+                name,
+            });
+        }
+
+        worlds.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(worlds)
+    }
+
+
+    /// Deletes the world with the given name, along with all of its data.
+    ///
+    /// If the world does not exist, an error is returned.
+    pub fn delete_world(&self, world_name: &str) -> Result<()> {
+        let path = self.world_path(world_name)?;
+        if !path.is_dir() {
+            bail!("Unknown world: {world_name}");
+        }
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+
+    /// Renames the world with the given name.
+    ///
+    /// If the world does not exist, or a world with the new name already
+    /// exists, an error is returned.
+    pub fn rename_world(&self, world_name: &str, new_name: &str) -> Result<()> {
+        let from = self.world_path(world_name)?;
+        let to = self.world_path(new_name)?;
+
+        if !from.is_dir() {
+            bail!("Unknown world: {world_name}");
+        }
+
+        if to.exists() {
+            bail!("World '{new_name}' already exists");
+        }
+
+        fs::rename(from, to)?;
+
+        if let Some(mut meta) = self.load_meta(new_name)? {
+            meta.name = new_name.to_string();
+            self.save_meta(new_name, &meta)?;
+        }
+
+        Ok(())
+    }
+
+
+    /// Loads the weather of the world with the given name.
+    ///
+    /// If the world does not have saved weather, `None` is returned.
+    pub fn load_weather(&self, world_name: &str) -> Result<Option<WorldWeather>> {
+        read_save(&self.weather_path(world_name), SaveKind::World)
+    }
+
+
+    /// Saves the weather of the world with the given name.
+    pub fn save_weather(&self, world_name: &str, weather: &WorldWeather) -> Result<()> {
+        write_save(&self.weather_path(world_name), weather)
+    }
+}
+
+impl Default for WorldDataDirectory {
+    fn default() -> Self {
+        Self(PathBuf::from("world/worlds"))
+    }
+}
+
+
+/// Gets the total size of all files within the given directory, in bytes.
+fn directory_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += match metadata.is_dir() {
+            true => directory_size(&entry.path())?,
+            false => metadata.len(),
+        };
+    }
+
+    Ok(size)
+}
+
+
+/// A lookup table of all worlds that are currently hosted by the server.
+///
+/// This resource is maintained automatically as entities with a
+/// [`WorldConfig`] component are spawned and despawned.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct HostedWorlds {
+    /// A map of world names to their world entities.
+    worlds: HashMap<String, Entity>,
+
+    /// The world that new players are placed in when they join the server.
+    default_world: Option<Entity>,
+}
+
+impl HostedWorlds {
+    /// Gets the world entity with the given name, if it exists.
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.worlds.get(name).copied()
+    }
+
+
+    /// Gets the world that new players are placed in when they join the
+    /// server.
+    ///
+    /// If no default world has been assigned, the first hosted world is used.
+    pub fn default_world(&self) -> Option<Entity> {
+        self.default_world
+    }
+
+
+    /// Assigns the world that new players are placed in when they join the
+    /// server.
+    ///
+    /// If there is no hosted world with the given name, an error is returned.
+    pub fn set_default_world(&mut self, name: &str) -> Result<()> {
+        match self.get(name) {
+            Some(world) => {
+                self.default_world = Some(world);
+                Ok(())
+            },
+            None => bail!("Unknown world: {name}"),
+        }
+    }
+
+
+    /// Gets an iterator over all world names and their world entities.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Entity)> {
+        self.worlds.iter()
+    }
+
+
+    /// Gets the number of worlds that are currently hosted.
+    pub fn len(&self) -> usize {
+        self.worlds.len()
+    }
+
+
+    /// Gets whether or not there are no worlds currently being hosted.
+    pub fn is_empty(&self) -> bool {
+        self.worlds.is_empty()
+    }
+}
+
+
+/// An event that is triggered after a player has been moved from one world to
+/// another.
+///
+/// Systems that hold per-player world state should listen for this event in
+/// order to reset that state for the new world, as done by
+/// [`unload_previous_world_chunks`] and
+/// [`reset_transferred_views`](crate::prelude::reset_transferred_views).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerTransferredEvent {
+    /// The player entity that was transferred.
+    pub player: Entity,
+
+    /// The world the player was previously in, if any.
+    pub from: Option<Entity>,
+
+    /// The world the player was moved into.
+    pub to: Entity,
+}
+
+
+/// A command that moves a player entity into another world at the given
+/// position.
+///
+/// The player's position, chunk anchor, and world membership are all updated
+/// and a [`PlayerTransferredEvent`] is triggered.
+#[derive(Debug, Clone)]
+pub struct TransferPlayer {
+    /// The player entity to transfer.
+    pub player: Entity,
+
+    /// The world entity to move the player into.
+    pub world: Entity,
+
+    /// The position within the new world to place the player at.
+    pub position: Vec3,
+}
+
+impl Command for TransferPlayer {
+    fn write(self, world: &mut World) {
+        if world.get::<WorldConfig>(self.world).is_none() {
+            warn!(
+                "Cannot transfer player {:?} into unknown world {:?}",
+                self.player, self.world
+            );
+            return;
+        }
+
+        let Some(mut player) = world.get_entity_mut(self.player) else {
+            warn!("Cannot transfer unknown player {:?}", self.player);
+            return;
+        };
+
+        let from = player.get::<InWorld>().map(|w| w.0);
+        player.insert(InWorld(self.world));
+
+        if let Some(mut position) = player.get_mut::<Position>() {
+            position.translation = self.position;
+        } else {
+            player.insert(Position {
+                translation: self.position,
+                ..default()
+            });
+        }
+
+        if let Some(mut previous) = player.get_mut::<PreviousPosition>() {
+            previous.translation = self.position;
+        }
+
+        if let Some(mut history) = player.get_mut::<PositionHistory>() {
+            *history = PositionHistory::default();
+        }
+
+        if let Some(mut anchor) = player.get_mut::<ChunkAnchor>() {
+            anchor.world = Some(self.world);
+        }
+
+        world
+            .resource_mut::<Events<PlayerTransferredEvent>>()
+            .send(PlayerTransferredEvent {
+                player: self.player,
+                from,
+                to: self.world,
+            });
+    }
+}
+
+
+/// An extension trait for entity commands that allows for players to be moved
+/// between hosted worlds.
+pub trait TransferPlayerExt {
+    /// Moves this player entity into the given world at the given position.
+    fn transfer_player(&mut self, world: Entity, position: Vec3) -> &mut Self;
+}
+
+impl<'w, 's, 'a> TransferPlayerExt for EntityCommands<'w, 's, 'a> {
+    fn transfer_player(&mut self, world: Entity, position: Vec3) -> &mut Self {
+        let player = self.id();
+        self.commands().add(TransferPlayer {
+            player,
+            world,
+            position,
+        });
+        self
+    }
+}
+
+
+/// Unloads the chunks of the previous world of each transferred player that are
+/// no longer within range of any other chunk anchor.
+///
+/// Chunks that leave the range of all anchors are normally kept loaded for a
+/// short delay, in case an anchor returns. A transferred player has left the
+/// world entirely, so the chunks that they were keeping loaded are unloaded
+/// immediately instead.
+pub fn unload_previous_world_chunks(
+    mut transferred_ev: EventReader<PlayerTransferredEvent>,
+    mut worlds: Query<&mut VoxelChunkStates>,
+    anchors: Query<(&ChunkAnchor, &Position)>,
+    mut unload_chunk_ev: EventWriter<UnloadChunkEvent>,
+) {
+    for ev in transferred_ev.iter() {
+        let Some(from) = ev.from.filter(|from| *from != ev.to) else {
+            continue;
+        };
+
+        let Ok(mut states) = worlds.get_mut(from) else {
+            continue;
+        };
+
+        let world_anchors: Vec<_> = anchors
+            .iter()
+            .filter(|(anchor, _)| anchor.world == Some(from))
+            .map(|(anchor, pos)| (anchor, world_to_block(pos.translation)))
+            .collect();
+
+        let released: Vec<IVec3> = states
+            .chunks_in_state(ChunkState::Loaded)
+            .filter(|chunk| {
+                !world_anchors.iter().any(|(anchor, pos)| anchor.in_range(*pos, *chunk))
+            })
+            .collect();
+
+        for chunk_coords in released {
+            states.set_state(chunk_coords, ChunkState::Unloaded);
+            unload_chunk_ev.send(UnloadChunkEvent {
+                chunk_coords,
+                world: from,
+            });
+        }
+    }
+}
+
+
+/// Keeps the hosted worlds lookup table up to date as world entities are
+/// spawned and despawned.
+pub fn update_hosted_worlds(
+    mut hosted: ResMut<HostedWorlds>,
+    added: Query<(Entity, &WorldConfig), Added<WorldConfig>>,
+    removed: RemovedComponents<WorldConfig>,
+) {
+    for entity in removed.iter() {
+        hosted.worlds.retain(|_, world| *world != entity);
+
+        if hosted.default_world == Some(entity) {
+            hosted.default_world = None;
+        }
+    }
+
+    for (entity, config) in added.iter() {
+        if hosted.worlds.insert(config.name.clone(), entity).is_some() {
+            warn!("Multiple worlds share the name '{}'", config.name);
+        }
+
+        if hosted.default_world.is_none() {
+            hosted.default_world = Some(entity);
+        }
+    }
+}
+
+
+/// Loads the metadata of each newly hosted world, marks it as played, and
+/// inserts it into the world entity alongside the spawn point and game rules
+/// of the world.
+///
+/// Worlds that already exist keep the world generator and seed they were
+/// created with, which replace those within their configuration. Worlds that
+/// have not been saved before use the world generator and seed from their
+/// configuration, which are then saved so that the world is generated the
+/// same way each time it is hosted. The same applies to game rules, so that
+/// rules changed while the world was hosted are kept.
+pub fn load_world_meta(
+    directory: Res<WorldDataDirectory>,
+    mut worlds: Query<(Entity, &mut WorldConfig), Added<WorldConfig>>,
+    mut commands: Commands,
+) {
+    for (entity, mut config) in worlds.iter_mut() {
+        let mut meta = match directory.load_meta(&config.name) {
+            Ok(Some(meta)) => meta.played_now(),
+            Ok(None) => WorldMeta::new(&config.name, &config.generator, config.seed),
+            Err(err) => {
+                error!(
+                    "Failed to load the metadata of world '{}': {err}",
+                    config.name
+                );
+                commands.entity(entity).insert((WorldSpawn::default(), config.rules.clone()));
+                continue;
+            },
+        };
+
+        let rules = meta.rules.get_or_insert_with(|| config.rules.clone()).clone();
+        config.generator = meta.generator.clone();
+        config.seed = meta.seed;
+        if let Err(err) = directory.save_meta(&config.name, &meta) {
+            error!(
+                "Failed to save the metadata of world '{}': {err}",
+                config.name
+            );
+        }
+
+        let spawn = WorldSpawn {
+            position: meta.spawn,
+        };
+        commands.entity(entity).insert((meta, spawn, rules));
+    }
+}
+
+
+/// Advances the time of each hosted world by one physics tick, scaled by the
+/// tick speed of the world.
+pub fn advance_world_time(mut worlds: Query<(&mut WorldMeta, Option<&GameRules>)>) {
+    for (mut meta, rules) in worlds.iter_mut() {
+        let tick_speed = rules.map_or(1.0, |rules| rules.tick_speed);
+        let progress = meta.tick_progress + tick_speed;
+        meta.time += progress as u64;
+        meta.tick_progress = progress.fract();
+    }
+}
+
+
+/// Copies the spawn point of each world into its metadata whenever it is
+/// modified, and saves the metadata.
+pub fn save_world_spawns(
+    directory: Res<WorldDataDirectory>,
+    mut worlds: Query<(&WorldSpawn, &mut WorldMeta), Changed<WorldSpawn>>,
+) {
+    for (spawn, mut meta) in worlds.iter_mut() {
+        if meta.spawn == spawn.position {
+            continue;
+        }
+
+        meta.spawn = spawn.position;
+        if let Err(err) = directory.save_meta(&meta.name, &meta) {
+            error!(
+                "Failed to save the spawn point of world '{}': {err}",
+                meta.name
+            );
+        }
+    }
+}
+
+
+/// Periodically saves the metadata of each hosted world, so that the time of
+/// the world is kept, and saves it once more when the server shuts down.
+pub fn autosave_world_meta(
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    mut exit_ev: EventReader<AppExit>,
+    directory: Res<WorldDataDirectory>,
+    worlds: Query<&WorldMeta>,
+) {
+    *timer += time.delta_seconds();
+    let exiting = exit_ev.iter().last().is_some();
+    if *timer < WORLD_META_AUTOSAVE_SECONDS && !exiting {
+        return;
+    }
+    *timer = 0.0;
+
+    for meta in worlds.iter() {
+        if let Err(err) = directory.save_meta(&meta.name, meta) {
+            error!(
+                "Failed to save the metadata of world '{}': {err}",
+                meta.name
+            );
+        }
+    }
+}
+
+
+/// Changes the spawn point of a world.
+///
+/// Usage: `setworldspawn [<x> <y> <z> [world]]`
+///
+/// If no position is given, the position of the sending player is used.
+pub fn setworldspawn_command(
+    world: &mut World,
+    sender: &CommandSender,
+    args: &[&str],
+) -> Result<String> {
+    let (target, position) = match (args, sender) {
+        ([x, y, z, rest @ ..], _) => {
+            let position = Vec3::new(x.parse()?, y.parse()?, z.parse()?);
+            let target = match rest.first() {
+                Some(name) => world.resource::<HostedWorlds>().get(name),
+                None => {
+                    match sender {
+                        CommandSender::Player(player) => world.get::<InWorld>(*player).map(|w| w.0),
+                        CommandSender::Console | CommandSender::Remote(_) => {
+                            world.resource::<HostedWorlds>().default_world()
+                        },
+                    }
+                },
+            };
+            (target, position)
+        },
+        ([], CommandSender::Player(player)) => {
+            let target = world.get::<InWorld>(*player).map(|w| w.0);
+            let position = world.get::<Position>(*player).map(|p| p.translation);
+            (target, position.unwrap_or_default())
+        },
+        _ => bail!("Usage: setworldspawn [<x> <y> <z> [world]]"),
+    };
+
+    let Some(target) = target else {
+        bail!("Unknown world");
+    };
+
+    let name = world.get::<WorldConfig>(target).map(|c| c.name.clone()).unwrap_or_default();
+    world.entity_mut(target).insert(WorldSpawn {
+        position,
+    });
+
+    Ok(format!(
+        "Set the spawn point of world '{name}' to {position}"
+    ))
+}
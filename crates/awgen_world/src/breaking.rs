@@ -0,0 +1,217 @@
+//! Block hardness, and the progress of entities that are breaking blocks.
+//!
+//! Breaking a block is not instant. An entity must keep breaking the same
+//! block for as long as the break time of the block, which depends on the
+//! hardness of the block and the tool that the entity is holding. While a
+//! block is being broken, its progress is shown as one of [`CRACK_STAGES`]
+//! crack stages.
+
+
+use awgen_physics::prelude::GameMode;
+use bevy::prelude::*;
+
+
+/// The number of crack stages that the progress of breaking a block is shown
+/// with.
+pub const CRACK_STAGES: u8 = 10;
+
+
+/// Describes how long a block data type takes to be broken.
+pub trait BlockHardness {
+    /// Gets the number of seconds that this block takes to be broken without
+    /// a tool, or `None` if this block cannot be broken. Empty blocks should
+    /// return 0.
+    fn hardness(&self) -> Option<f32>;
+
+
+    /// Gets the multiplier of the speed at which this block is broken while
+    /// holding the item with the given name. Items that are not tools for this
+    /// block should return 1.
+    fn tool_speed(&self, _tool: &str) -> f32 {
+        1.0
+    }
+}
+
+
+/// Gets the number of seconds that the given block takes to be broken by a
+/// player in the given game mode while holding the given tool, or `None` if
+/// the block cannot be broken.
+///
+/// Game modes that break blocks instantly break every block instantly.
+pub fn break_time<BlockData>(
+    block: &BlockData,
+    game_mode: GameMode,
+    tool: Option<&str>,
+) -> Option<f32>
+where
+    BlockData: BlockHardness,
+{
+    if game_mode.instant_break() {
+        return Some(0.0);
+    }
+
+    let hardness = block.hardness()?;
+    let speed = tool.map_or(1.0, |tool| block.tool_speed(tool));
+
+    if speed <= 0.0 {
+        return None;
+    }
+
+    Some(hardness.max(0.0) / speed)
+}
+
+
+/// Gets the crack stage of the given breaking progress, between 0 and 1.
+pub fn crack_stage(progress: f32) -> u8 {
+    let stage = (progress.clamp(0.0, 1.0) * CRACK_STAGES as f32) as u8;
+    stage.min(CRACK_STAGES - 1)
+}
+
+
+/// A component for an entity that is breaking a block.
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct BlockBreaking {
+    /// The position of the block that is being broken.
+    pub block_pos: IVec3,
+
+    /// The time that breaking started at, in seconds.
+    pub started: f64,
+
+    /// The number of seconds that the block takes to be broken.
+    pub duration: f32,
+}
+
+impl BlockBreaking {
+    /// Creates a new breaking progress state for the block at the given
+    /// position, which started at the given time and takes the given number
+    /// of seconds.
+    pub fn new(block_pos: IVec3, started: f64, duration: f32) -> Self {
+        Self {
+            block_pos,
+            started,
+            duration,
+        }
+    }
+
+
+    /// Gets the progress of breaking the block at the given time, between 0
+    /// and 1.
+    pub fn progress(&self, now: f64) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+
+        ((now - self.started) as f32 / self.duration).clamp(0.0, 1.0)
+    }
+
+
+    /// Gets the crack stage of the block at the given time.
+    pub fn crack_stage(&self, now: f64) -> u8 {
+        crack_stage(self.progress(now))
+    }
+
+
+    /// Gets whether or not the block has been broken for long enough at the
+    /// given time, allowing for the given number of seconds to have been lost
+    /// to latency.
+    pub fn is_finished(&self, now: f64, tolerance: f32) -> bool {
+        (now - self.started) as f32 + tolerance >= self.duration
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// A block type with a pickaxe as its tool.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum TestBlock {
+        Air,
+        Stone,
+        Bedrock,
+    }
+
+    impl BlockHardness for TestBlock {
+        fn hardness(&self) -> Option<f32> {
+            match self {
+                TestBlock::Air => Some(0.0),
+                TestBlock::Stone => Some(1.5),
+                TestBlock::Bedrock => None,
+            }
+        }
+
+
+        fn tool_speed(&self, tool: &str) -> f32 {
+            match (self, tool) {
+                (TestBlock::Stone, "pickaxe") => 3.0,
+                _ => 1.0,
+            }
+        }
+    }
+
+
+    #[test]
+    fn break_time_uses_hardness_and_tool() {
+        let survival = GameMode::Survival;
+
+        assert_eq!(break_time(&TestBlock::Air, survival, None), Some(0.0));
+        assert_eq!(break_time(&TestBlock::Stone, survival, None), Some(1.5));
+        assert_eq!(
+            break_time(&TestBlock::Stone, survival, Some("shovel")),
+            Some(1.5)
+        );
+        assert_eq!(
+            break_time(&TestBlock::Stone, survival, Some("pickaxe")),
+            Some(0.5)
+        );
+        assert_eq!(
+            break_time(&TestBlock::Bedrock, survival, Some("pickaxe")),
+            None
+        );
+    }
+
+
+    #[test]
+    fn instant_break_game_mode() {
+        let creative = GameMode::Creative;
+
+        assert_eq!(break_time(&TestBlock::Stone, creative, None), Some(0.0));
+        assert_eq!(break_time(&TestBlock::Bedrock, creative, None), Some(0.0));
+    }
+
+
+    #[test]
+    fn progress_and_crack_stages() {
+        let breaking = BlockBreaking::new(IVec3::ONE, 10.0, 2.0);
+
+        assert_eq!(breaking.progress(9.0), 0.0);
+        assert_eq!(breaking.progress(11.0), 0.5);
+        assert_eq!(breaking.progress(13.0), 1.0);
+        assert_eq!(breaking.crack_stage(10.0), 0);
+        assert_eq!(breaking.crack_stage(11.0), 5);
+        assert_eq!(breaking.crack_stage(12.0), CRACK_STAGES - 1);
+    }
+
+
+    #[test]
+    fn instant_breaking() {
+        let breaking = BlockBreaking::new(IVec3::ZERO, 4.0, 0.0);
+
+        assert_eq!(breaking.progress(4.0), 1.0);
+        assert!(breaking.is_finished(4.0, 0.0));
+    }
+
+
+    #[test]
+    fn finished_with_tolerance() {
+        let breaking = BlockBreaking::new(IVec3::ZERO, 0.0, 1.0);
+
+        assert!(!breaking.is_finished(0.5, 0.0));
+        assert!(!breaking.is_finished(0.5, 0.25));
+        assert!(breaking.is_finished(0.8, 0.25));
+        assert!(breaking.is_finished(1.0, 0.0));
+    }
+}
@@ -0,0 +1,151 @@
+//! A built-in world generation stage for carving caves and caverns out of the
+//! terrain.
+
+
+use crate::prelude::{ChunkView, GenerationPhase, GenerationStage};
+use awgen_math::prelude::{Fbm, NoiseFn, PerlinNoise};
+use bevy::prelude::*;
+
+
+/// The typical range of fractal noise values, used to map cave densities onto
+/// noise thresholds.
+const NOISE_RANGE: f32 = 0.5;
+
+
+/// A cave stage that carves two kinds of caves out of the terrain, using 3D
+/// noise:
+///
+/// - Cheese caves are large, open caverns, carved wherever the cavern noise is
+///   above a threshold.
+/// - Worm caves are long, winding tunnels, carved where two independent noise
+///   functions are both close to zero.
+///
+/// Caves are only carved within the given height range, and never closer to the
+/// terrain surface than the roof thickness. This keeps the surface intact, so
+/// caves remain dark rather than opening up into sunlit craters. Columns that
+/// have no known surface height are only limited by the height range.
+#[derive(Debug, Clone)]
+pub struct NoiseCaves {
+    /// The lowest y coordinate that caves may be carved at.
+    pub min_height: i32,
+
+    /// The highest y coordinate that caves may be carved at.
+    pub max_height: i32,
+
+    /// The minimum number of blocks between a cave and the terrain surface
+    /// above it.
+    pub roof_thickness: i32,
+
+    /// The density of cheese caves, from 0, for no caverns, to 1, for caverns
+    /// that fill roughly half of the underground.
+    pub cavern_density: f32,
+
+    /// The density of worm caves, from 0, for no tunnels, to 1, for wide and
+    /// frequent tunnels.
+    pub tunnel_density: f32,
+
+    /// The frequency of the cave noise. Smaller values create larger caves.
+    pub frequency: f32,
+}
+
+impl Default for NoiseCaves {
+    fn default() -> Self {
+        Self {
+            min_height:     -256,
+            max_height:     64,
+            roof_thickness: 6,
+            cavern_density: 0.3,
+            tunnel_density: 0.5,
+            frequency:      1.0 / 48.0,
+        }
+    }
+}
+
+impl<BlockData> GenerationStage<BlockData> for NoiseCaves
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    fn name(&self) -> &str {
+        "noise_caves"
+    }
+
+
+    fn phase(&self) -> GenerationPhase {
+        GenerationPhase::Caves
+    }
+
+
+    fn generate(&self, view: &mut ChunkView<BlockData>) {
+        let seed = view.seed().derive_str("caves");
+        let cavern = Fbm::new(PerlinNoise::new(seed.derive_str("caverns")))
+            .with_octaves(3)
+            .with_frequency(self.frequency);
+        let tunnel_a = Fbm::new(PerlinNoise::new(seed.derive_str("tunnels_a")))
+            .with_octaves(2)
+            .with_frequency(self.frequency * 2.0);
+        let tunnel_b = Fbm::new(PerlinNoise::new(seed.derive_str("tunnels_b")))
+            .with_octaves(2)
+            .with_frequency(self.frequency * 2.0);
+
+        let cavern_threshold = (1.0 - self.cavern_density) * NOISE_RANGE;
+        let tunnel_width = self.tunnel_density * NOISE_RANGE * 0.15;
+
+        for pos in view.area().iter() {
+            if pos.y < self.min_height || pos.y > self.max_height {
+                continue;
+            }
+
+            let surface = view.surface_height(IVec2::new(pos.x, pos.z));
+            if surface.is_some_and(|height| pos.y > height - self.roof_thickness) {
+                continue;
+            }
+
+            let sample_pos = pos.as_vec3();
+            let carve = cavern.sample(sample_pos) > cavern_threshold
+                || (tunnel_a.sample(sample_pos).abs() < tunnel_width
+                    && tunnel_b.sample(sample_pos).abs() < tunnel_width);
+
+            if carve {
+                view.set_block(pos, default());
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{NoiseTerrain, VoxelWorld, WorldGenerator};
+    use awgen_math::prelude::{chunk_blocks, Region, Seed};
+
+
+    #[test]
+    fn caves_below_surface() {
+        let generator = WorldGenerator::<u8>::new(Seed(3))
+            .with_stage(NoiseCaves {
+                cavern_density: 0.8,
+                ..default()
+            })
+            .with_stage(NoiseTerrain::new(1));
+
+        let mut world = VoxelWorld::<u8>::default();
+        for chunk_coords in Region::from_points(IVec3::new(0, -3, 0), IVec3::new(1, 1, 1)).iter() {
+            let chunk = generator.generate_chunk(chunk_coords);
+            world.set_block_region(chunk_blocks(chunk_coords), &chunk.blocks);
+        }
+
+        let mut carved = 0;
+        for column in Region::from_points(IVec3::ZERO, IVec3::new(31, 0, 31)).iter() {
+            let blocks: Vec<u8> = (-48..32)
+                .rev()
+                .map(|y| world.get_block_data(IVec3::new(column.x, y, column.z)))
+                .collect();
+
+            let surface = blocks.iter().position(|block| *block != 0).unwrap();
+            assert!(blocks[surface..surface + 6].iter().all(|block| *block == 1));
+            carved += blocks[surface..].iter().filter(|block| **block == 0).count();
+        }
+
+        assert!(carved > 0);
+    }
+}
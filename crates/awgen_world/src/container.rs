@@ -0,0 +1,521 @@
+//! Container blocks, such as chests, which store an inventory that may be
+//! viewed and changed by multiple players at once.
+//!
+//! Any block whose interaction handler returns
+//! [`InteractionOutcome::OpenContainer`] acts as a container. The inventory of
+//! a container is stored on a persistent container entity at the center of the
+//! block, which is created the first time that the container is opened, and is
+//! saved and restored alongside the chunk that it is within. Containers are
+//! removed once their block has been replaced with an empty block within a
+//! loaded chunk.
+
+
+use crate::prelude::{
+    handle_block_use, BlockUsedEvent, ChunkState, InWorld, InteractionOutcome, Persistent, PersistentComponentExt, VoxelChunkStates, VoxelWorld
+};
+use awgen_math::prelude::block_to_chunk;
+use awgen_physics::prelude::{Inventory, Position};
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+
+/// The number of inventory slots within a newly created container.
+pub const DEFAULT_CONTAINER_SLOTS: usize = 27;
+
+
+/// The maximum number of items within a single stack inside a container.
+pub const MAX_STACK_SIZE: u16 = 64;
+
+
+/// A component for the entity that stores the inventory of a container block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect, Component)]
+#[reflect(Component)]
+pub struct ContainerBlock {
+    /// The position of the container block.
+    pub block_pos: IVec3,
+}
+
+
+/// The entities that currently have a container open.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Component)]
+pub struct ContainerViewers {
+    /// The viewing entities, in the order that they opened the container.
+    viewers: Vec<Entity>,
+}
+
+impl ContainerViewers {
+    /// Gets whether or not the given entity is viewing the container.
+    pub fn contains(&self, viewer: Entity) -> bool {
+        self.viewers.contains(&viewer)
+    }
+
+
+    /// Gets an iterator over all entities that are viewing the container.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.viewers.iter().copied()
+    }
+}
+
+
+/// An event that is triggered when an entity opens a container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContainerOpenedEvent {
+    /// The container entity.
+    pub container: Entity,
+
+    /// The entity that opened the container.
+    pub viewer: Entity,
+}
+
+
+/// An event that is triggered when an entity stops viewing a container, either
+/// because it closed the container or because the container was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContainerClosedEvent {
+    /// The container entity.
+    pub container: Entity,
+
+    /// The position of the container block.
+    pub block_pos: IVec3,
+
+    /// The entity that was viewing the container.
+    pub viewer: Entity,
+}
+
+
+/// An event that is triggered to close the container that an entity is
+/// currently viewing, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloseContainerEvent {
+    /// The entity that is closing its container.
+    pub viewer: Entity,
+}
+
+
+/// An event that is triggered to move an item stack between two slots of the
+/// container that an entity is currently viewing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContainerMoveEvent {
+    /// The entity that is moving the item stack.
+    pub viewer: Entity,
+
+    /// The slot to move the item stack from.
+    pub from: usize,
+
+    /// The slot to move the item stack to.
+    pub to: usize,
+
+    /// The revision of the inventory that the viewer saw when requesting the
+    /// move. Moves against an older revision are rejected, as another viewer
+    /// has changed the container in the meantime.
+    pub revision: u32,
+}
+
+
+/// Removes the given viewer from every container other than the given one,
+/// triggering a close event for each.
+fn close_other_containers(
+    viewer: Entity,
+    keep: Option<Entity>,
+    containers: &mut Query<(
+        Entity,
+        &ContainerBlock,
+        &InWorld,
+        Option<&mut ContainerViewers>,
+    )>,
+    closed_ev: &mut EventWriter<ContainerClosedEvent>,
+) {
+    for (container, block, _, viewers) in containers.iter_mut() {
+        let Some(mut viewers) = viewers else {
+            continue;
+        };
+
+        if Some(container) == keep || !viewers.contains(viewer) {
+            continue;
+        }
+
+        viewers.viewers.retain(|v| *v != viewer);
+        closed_ev.send(ContainerClosedEvent {
+            container,
+            block_pos: block.block_pos,
+            viewer,
+        });
+    }
+}
+
+
+/// Opens the container of each block that was used with the outcome of
+/// opening a container, creating the container entity if it does not yet
+/// exist. An entity may only view a single container at a time.
+pub fn open_containers(
+    mut used_ev: EventReader<BlockUsedEvent>,
+    mut opened_ev: EventWriter<ContainerOpenedEvent>,
+    mut closed_ev: EventWriter<ContainerClosedEvent>,
+    mut containers: Query<(
+        Entity,
+        &ContainerBlock,
+        &InWorld,
+        Option<&mut ContainerViewers>,
+    )>,
+    mut commands: Commands,
+) {
+    let mut spawned: Vec<(Entity, Entity, IVec3, Vec<Entity>)> = vec![];
+    for ev in used_ev.iter() {
+        if ev.outcome != InteractionOutcome::OpenContainer {
+            continue;
+        }
+
+        let existing = containers
+            .iter()
+            .find(|(_, block, in_world, _)| {
+                in_world.0 == ev.world && block.block_pos == ev.block_pos
+            })
+            .map(|(entity, ..)| entity);
+
+        close_other_containers(ev.user, existing, &mut containers, &mut closed_ev);
+
+        let container = match existing {
+            Some(container) => {
+                match containers.get_mut(container).unwrap().3 {
+                    Some(viewers) if viewers.contains(ev.user) => {},
+                    Some(mut viewers) => viewers.viewers.push(ev.user),
+                    None => {
+                        commands.entity(container).insert(ContainerViewers {
+                            viewers: vec![ev.user],
+                        });
+                    },
+                }
+                container
+            },
+            None => {
+                let pending = spawned.iter_mut().find(|(_, world, block_pos, _)| {
+                    *world == ev.world && *block_pos == ev.block_pos
+                });
+
+                match pending {
+                    Some((container, _, _, viewers)) => {
+                        if !viewers.contains(&ev.user) {
+                            viewers.push(ev.user);
+                        }
+                        *container
+                    },
+                    None => {
+                        let container = commands.spawn_empty().id();
+                        spawned.push((container, ev.world, ev.block_pos, vec![ev.user]));
+                        container
+                    },
+                }
+            },
+        };
+
+        opened_ev.send(ContainerOpenedEvent {
+            container,
+            viewer: ev.user,
+        });
+    }
+
+    for (container, world, block_pos, viewers) in spawned {
+        commands.entity(container).insert((
+            ContainerBlock {
+                block_pos,
+            },
+            Inventory::new(DEFAULT_CONTAINER_SLOTS),
+            ContainerViewers {
+                viewers,
+            },
+            Position {
+                translation: block_pos.as_vec3() + Vec3::splat(0.5),
+                ..default()
+            },
+            InWorld(world),
+            Persistent,
+        ));
+    }
+}
+
+
+/// Closes the container that each entity is viewing when requested.
+pub fn close_containers(
+    mut close_ev: EventReader<CloseContainerEvent>,
+    mut closed_ev: EventWriter<ContainerClosedEvent>,
+    mut containers: Query<(
+        Entity,
+        &ContainerBlock,
+        &InWorld,
+        Option<&mut ContainerViewers>,
+    )>,
+) {
+    for ev in close_ev.iter() {
+        close_other_containers(ev.viewer, None, &mut containers, &mut closed_ev);
+    }
+}
+
+
+/// Moves item stacks within the container that each entity is viewing.
+///
+/// Moves that were requested against an outdated revision of the container are
+/// rejected. The inventory is still marked as changed in that case, so that
+/// the viewer receives the current contents of the container.
+pub fn move_container_items(
+    mut move_ev: EventReader<ContainerMoveEvent>,
+    mut containers: Query<(&ContainerViewers, &mut Inventory), With<ContainerBlock>>,
+) {
+    for ev in move_ev.iter() {
+        let Some((_, mut inventory)) =
+            containers.iter_mut().find(|(viewers, _)| viewers.contains(ev.viewer))
+        else {
+            continue;
+        };
+
+        if inventory.revision() != ev.revision {
+            inventory.set_changed();
+            continue;
+        }
+
+        if !inventory.move_stack(ev.from, ev.to, MAX_STACK_SIZE) {
+            inventory.set_changed();
+        }
+    }
+}
+
+
+/// Stops entities that no longer exist from viewing containers.
+pub fn prune_container_viewers(
+    mut closed_ev: EventWriter<ContainerClosedEvent>,
+    mut containers: Query<(Entity, &ContainerBlock, &mut ContainerViewers)>,
+    entities: Query<Entity>,
+) {
+    for (container, block, mut viewers) in containers.iter_mut() {
+        if viewers.iter().all(|viewer| entities.contains(viewer)) {
+            continue;
+        }
+
+        viewers.viewers.retain(|viewer| {
+            let exists = entities.contains(*viewer);
+            if !exists {
+                closed_ev.send(ContainerClosedEvent {
+                    container,
+                    block_pos: block.block_pos,
+                    viewer: *viewer,
+                });
+            }
+            exists
+        });
+    }
+}
+
+
+/// Despawns each container whose block has been replaced with an empty block,
+/// closing it for all of its viewers.
+///
+/// Only containers within loaded chunks are checked, as the blocks of chunks
+/// that are unloaded or still loading are not known.
+pub fn remove_broken_containers<BlockData>(
+    mut closed_ev: EventWriter<ContainerClosedEvent>,
+    worlds: Query<(&VoxelWorld<BlockData>, &VoxelChunkStates)>,
+    containers: Query<(Entity, &ContainerBlock, &InWorld, Option<&ContainerViewers>)>,
+    mut commands: Commands,
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    for (container, block, in_world, viewers) in containers.iter() {
+        let Ok((world, states)) = worlds.get(in_world.0) else {
+            continue;
+        };
+
+        if states.get_state(block_to_chunk(block.block_pos)) != ChunkState::Loaded {
+            continue;
+        }
+
+        if world.get_block_data(block.block_pos) != BlockData::default() {
+            continue;
+        }
+
+        for viewer in viewers.iter().flat_map(|viewers| viewers.iter()) {
+            closed_ev.send(ContainerClosedEvent {
+                container,
+                block_pos: block.block_pos,
+                viewer,
+            });
+        }
+
+        commands.entity(container).despawn();
+    }
+}
+
+
+/// A mini extension plugin that allows blocks of the given type to act as
+/// containers. This requires the
+/// [`InteractionPlugin`](crate::prelude::InteractionPlugin) for the same block
+/// data type.
+#[derive(Debug, Clone, Default)]
+pub struct ContainerPlugin<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for ContainerPlugin<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.register_persistent_component::<ContainerBlock>()
+            .register_persistent_component::<Inventory>()
+            .add_event::<ContainerOpenedEvent>()
+            .add_event::<ContainerClosedEvent>()
+            .add_event::<CloseContainerEvent>()
+            .add_event::<ContainerMoveEvent>()
+            .add_system(open_containers.after(handle_block_use::<BlockData>))
+            .add_system(close_containers.after(open_containers))
+            .add_system(move_container_items.after(close_containers))
+            .add_system(prune_container_viewers.after(move_container_items))
+            .add_system(remove_broken_containers::<BlockData>.after(prune_container_viewers));
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{BlockInteractions, BlockUseEvent, InteractionPlugin};
+    use awgen_math::prelude::Direction;
+    use awgen_physics::prelude::ItemStack;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Chest,
+    }
+
+
+    /// Creates a new app with a single chest at the origin, returning the app,
+    /// the world entity, and two player entities.
+    fn setup() -> (App, Entity, Entity, Entity) {
+        let mut app = App::new();
+        app.add_plugin(InteractionPlugin::<TestBlock>::default())
+            .add_plugin(ContainerPlugin::<TestBlock>::default());
+
+        app.world
+            .resource_mut::<BlockInteractions<TestBlock>>()
+            .register(TestBlock::Chest, |_| InteractionOutcome::OpenContainer);
+
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::ZERO, TestBlock::Chest);
+        let mut states = VoxelChunkStates::default();
+        states.set_state(IVec3::ZERO, ChunkState::Loaded);
+        let world = app.world.spawn((world, states)).id();
+        let alice = app.world.spawn_empty().id();
+        let bob = app.world.spawn_empty().id();
+
+        (app, world, alice, bob)
+    }
+
+
+    /// Uses the chest at the origin as the given player.
+    fn use_chest(app: &mut App, world: Entity, user: Entity) {
+        app.world.resource_mut::<Events<BlockUseEvent>>().send(BlockUseEvent {
+            world,
+            user,
+            block_pos: IVec3::ZERO,
+            face: Direction::PosY,
+        });
+        app.update();
+    }
+
+
+    /// Gets the single container entity within the app.
+    fn container(app: &mut App) -> Entity {
+        app.world.query_filtered::<Entity, With<ContainerBlock>>().single(&app.world)
+    }
+
+
+    #[test]
+    fn shared_container() {
+        let (mut app, world, alice, bob) = setup();
+        use_chest(&mut app, world, alice);
+        use_chest(&mut app, world, bob);
+
+        let chest = container(&mut app);
+        let viewers = app.world.get::<ContainerViewers>(chest).unwrap();
+        assert_eq!(viewers.iter().collect::<Vec<_>>(), vec![alice, bob]);
+
+        let mut inventory = app.world.get_mut::<Inventory>(chest).unwrap();
+        inventory.set(0, ItemStack::new("stone", 10));
+        let revision = inventory.revision();
+
+        let mut move_ev = app.world.resource_mut::<Events<ContainerMoveEvent>>();
+        move_ev.send(ContainerMoveEvent {
+            viewer: alice,
+            from: 0,
+            to: 1,
+            revision,
+        });
+        move_ev.send(ContainerMoveEvent {
+            viewer: bob,
+            from: 0,
+            to: 2,
+            revision,
+        });
+        app.update();
+
+        let inventory = app.world.get::<Inventory>(chest).unwrap();
+        assert_eq!(inventory.get(0), Some(&ItemStack::default()));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("stone", 10)));
+        assert_eq!(inventory.get(2), Some(&ItemStack::default()));
+
+        app.world
+            .resource_mut::<Events<CloseContainerEvent>>()
+            .send(CloseContainerEvent {
+                viewer: alice,
+            });
+        app.update();
+
+        let viewers = app.world.get::<ContainerViewers>(chest).unwrap();
+        assert_eq!(viewers.iter().collect::<Vec<_>>(), vec![bob]);
+    }
+
+
+    #[test]
+    fn broken_container_closes() {
+        let (mut app, world, alice, _) = setup();
+        use_chest(&mut app, world, alice);
+        let chest = container(&mut app);
+
+        app.world
+            .get_mut::<VoxelWorld<TestBlock>>(world)
+            .unwrap()
+            .set_block_data(IVec3::ZERO, TestBlock::Air);
+        app.update();
+
+        assert!(app.world.get_entity(chest).is_none());
+
+        let closed_ev = app.world.resource::<Events<ContainerClosedEvent>>();
+        let closed: Vec<_> = closed_ev.get_reader().iter(closed_ev).copied().collect();
+        assert_eq!(closed, vec![ContainerClosedEvent {
+            container: chest,
+            block_pos: IVec3::ZERO,
+            viewer:    alice,
+        }]);
+    }
+
+
+    #[test]
+    fn unloaded_container_is_kept() {
+        let (mut app, world, alice, _) = setup();
+        use_chest(&mut app, world, alice);
+        let chest = container(&mut app);
+
+        let mut world = app.world.entity_mut(world);
+        world.get_mut::<VoxelWorld<TestBlock>>().unwrap().remove_chunk(IVec3::ZERO);
+        world
+            .get_mut::<VoxelChunkStates>()
+            .unwrap()
+            .set_state(IVec3::ZERO, ChunkState::Unloaded);
+        app.update();
+
+        assert!(app.world.get_entity(chest).is_some());
+    }
+}
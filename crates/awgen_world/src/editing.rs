@@ -0,0 +1,288 @@
+//! Block editing, which allows entities to break blocks and to place new blocks
+//! against existing ones.
+//!
+//! Breaking a block replaces it with the default block data value, and placing
+//! a block is only allowed within an empty cell. The same rules are used by the
+//! server, which owns the authoritative world, and by clients, which predict
+//! their own edits within the [`BlockEditJournal`] until the server confirms or
+//! rejects them.
+
+
+use crate::prelude::VoxelWorld;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+
+/// The maximum number of unconfirmed block edits that are kept within a block
+/// edit journal. Older edits are assumed to be confirmed if the server stops
+/// responding.
+const MAX_PENDING_EDITS: usize = 64;
+
+
+/// A trait for block data types that may be placed from an item.
+pub trait BlockItem: Sized {
+    /// Gets the block that is placed when using the item with the given name,
+    /// or `None` if the item cannot be placed as a block.
+    fn from_item(item: &str) -> Option<Self>;
+}
+
+
+/// Checks whether or not the block at the given position may be replaced with
+/// the given block data.
+///
+/// Breaking a block, by replacing it with the default value, requires the block
+/// to not be empty. Placing any other block requires the block to be empty.
+pub fn can_edit_block<BlockData>(
+    world: &VoxelWorld<BlockData>,
+    block_pos: IVec3,
+    block: BlockData,
+) -> bool
+where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let empty = BlockData::default();
+    let current = world.get_block_data(block_pos);
+
+    match block == empty {
+        true => current != empty,
+        false => current == empty,
+    }
+}
+
+
+/// Replaces the block at the given position with the given block data, if
+/// allowed by [`can_edit_block`].
+///
+/// Returns the previous block data if the edit was applied, or `None` if the
+/// edit is not allowed.
+pub fn edit_block<BlockData>(
+    world: &mut VoxelWorld<BlockData>,
+    block_pos: IVec3,
+    block: BlockData,
+) -> Option<BlockData>
+where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    if !can_edit_block(world, block_pos, block) {
+        return None;
+    }
+
+    let previous = world.get_block_data(block_pos);
+    world.set_block_data(block_pos, block);
+    Some(previous)
+}
+
+
+/// A block edit that has been predicted, but not yet confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PendingBlockEdit<BlockData> {
+    /// The sequence number of the edit.
+    sequence: u32,
+
+    /// The position of the edited block.
+    block_pos: IVec3,
+
+    /// The block data before the edit was applied.
+    previous: BlockData,
+}
+
+
+/// A journal of the block edits that have been applied locally ahead of the
+/// server, so that they may be rolled back if the server rejects them.
+///
+/// Each predicted edit is tagged with a sequence number, which is sent to the
+/// server alongside the edit request and returned within its response.
+#[derive(Debug, Clone, Resource)]
+pub struct BlockEditJournal<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static {
+    /// The sequence number of the next predicted edit.
+    next_sequence: u32,
+
+    /// The unconfirmed edits, from oldest to newest.
+    pending: VecDeque<PendingBlockEdit<BlockData>>,
+}
+
+impl<BlockData> BlockEditJournal<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    /// Gets the number of edits that have not yet been confirmed or rejected.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+
+    /// Applies the given edit to the world immediately, and records it so that
+    /// it may be rolled back later.
+    ///
+    /// Returns the sequence number of the edit, or `None` if the edit is not
+    /// allowed, in which case the world is left unchanged.
+    pub fn predict(
+        &mut self,
+        world: &mut VoxelWorld<BlockData>,
+        block_pos: IVec3,
+        block: BlockData,
+    ) -> Option<u32> {
+        let previous = edit_block(world, block_pos, block)?;
+
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+
+        self.pending.push_back(PendingBlockEdit {
+            sequence,
+            block_pos,
+            previous,
+        });
+
+        if self.pending.len() > MAX_PENDING_EDITS {
+            self.pending.pop_front();
+        }
+
+        Some(sequence)
+    }
+
+
+    /// Marks the edit with the given sequence number as confirmed, keeping its
+    /// change within the world.
+    ///
+    /// Returns false if the edit is not pending.
+    pub fn confirm(&mut self, sequence: u32) -> bool {
+        let Some(index) = self.index_of(sequence) else {
+            return false;
+        };
+
+        self.pending.remove(index);
+        true
+    }
+
+
+    /// Rolls back the edit with the given sequence number, restoring the block
+    /// that it replaced.
+    ///
+    /// Newer pending edits of the same block were predicted on top of the
+    /// rejected edit, so they are rolled back alongside it and their own
+    /// responses are ignored.
+    ///
+    /// Returns the position of the restored block, or `None` if the edit is not
+    /// pending.
+    pub fn reject(&mut self, world: &mut VoxelWorld<BlockData>, sequence: u32) -> Option<IVec3> {
+        let index = self.index_of(sequence)?;
+        let rejected = self.pending[index];
+
+        let mut i = index;
+        while i < self.pending.len() {
+            if self.pending[i].block_pos == rejected.block_pos {
+                self.pending.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        world.set_block_data(rejected.block_pos, rejected.previous);
+        Some(rejected.block_pos)
+    }
+
+
+    /// Gets the index of the pending edit with the given sequence number.
+    fn index_of(&self, sequence: u32) -> Option<usize> {
+        self.pending.iter().position(|edit| edit.sequence == sequence)
+    }
+}
+
+impl<BlockData> Default for BlockEditJournal<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    fn default() -> Self {
+        Self {
+            next_sequence: 0,
+            pending:       VecDeque::new(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn place_and_break_rules() {
+        let mut world = VoxelWorld::<u8>::default();
+        let pos = IVec3::new(3, 4, -5);
+
+        assert_eq!(edit_block(&mut world, pos, 0), None);
+        assert_eq!(edit_block(&mut world, pos, 2), Some(0));
+        assert_eq!(edit_block(&mut world, pos, 3), None);
+        assert_eq!(world.get_block_data(pos), 2);
+        assert_eq!(edit_block(&mut world, pos, 0), Some(2));
+        assert_eq!(world.get_block_data(pos), 0);
+    }
+
+
+    #[test]
+    fn confirm_keeps_prediction() {
+        let mut world = VoxelWorld::<u8>::default();
+        let mut journal = BlockEditJournal::default();
+        let pos = IVec3::new(0, 1, 0);
+
+        let sequence = journal.predict(&mut world, pos, 4).unwrap();
+        assert_eq!(world.get_block_data(pos), 4);
+        assert_eq!(journal.pending(), 1);
+
+        assert!(journal.confirm(sequence));
+        assert!(!journal.confirm(sequence));
+        assert_eq!(world.get_block_data(pos), 4);
+        assert_eq!(journal.pending(), 0);
+    }
+
+
+    #[test]
+    fn reject_restores_block() {
+        let mut world = VoxelWorld::<u8>::default();
+        let mut journal = BlockEditJournal::default();
+        let pos = IVec3::new(-7, 2, 9);
+        world.set_block_data(pos, 5);
+
+        let sequence = journal.predict(&mut world, pos, 0).unwrap();
+        assert_eq!(world.get_block_data(pos), 0);
+
+        assert_eq!(journal.reject(&mut world, sequence), Some(pos));
+        assert_eq!(world.get_block_data(pos), 5);
+        assert_eq!(journal.reject(&mut world, sequence), None);
+    }
+
+
+    #[test]
+    fn reject_rolls_back_newer_edits_of_block() {
+        let mut world = VoxelWorld::<u8>::default();
+        let mut journal = BlockEditJournal::default();
+        let pos = IVec3::new(1, 1, 1);
+        let other = IVec3::new(2, 1, 1);
+
+        let placed = journal.predict(&mut world, pos, 3).unwrap();
+        let broken = journal.predict(&mut world, pos, 0).unwrap();
+        let replaced = journal.predict(&mut world, pos, 6).unwrap();
+        let unrelated = journal.predict(&mut world, other, 8).unwrap();
+        assert_eq!(world.get_block_data(pos), 6);
+
+        assert_eq!(journal.reject(&mut world, placed), Some(pos));
+        assert_eq!(world.get_block_data(pos), 0);
+        assert_eq!(world.get_block_data(other), 8);
+        assert_eq!(journal.pending(), 1);
+
+        assert!(!journal.confirm(broken));
+        assert!(!journal.confirm(replaced));
+        assert!(journal.confirm(unrelated));
+    }
+
+
+    #[test]
+    fn invalid_prediction_is_not_recorded() {
+        let mut world = VoxelWorld::<u8>::default();
+        let mut journal = BlockEditJournal::default();
+
+        assert_eq!(journal.predict(&mut world, IVec3::ZERO, 0), None);
+        assert_eq!(journal.pending(), 0);
+    }
+}
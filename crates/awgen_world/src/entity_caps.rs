@@ -0,0 +1,479 @@
+//! Caps on the number of mobs and item drops within each chunk, and on the
+//! number of these entities across all worlds, which protect the physics tick
+//! from entity explosions, such as within player-built farms.
+//!
+//! Spawning systems should reserve room for each capped entity with
+//! [`EntityCounts::try_reserve`] before spawning it, which also refuses mobs
+//! within worlds that have mob spawning disabled. The caps are also enforced
+//! after each physics tick by [`enforce_entity_caps`], which first merges item
+//! drops of the same item within the same chunk, and then despawns the excess
+//! item drops and mobs that remain.
+
+
+use crate::prelude::{InWorld, MAX_STACK_SIZE};
+use awgen_math::prelude::world_to_chunk;
+use awgen_physics::prelude::{apply_velocity, ItemStack, Position};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+
+/// The default maximum number of mobs within a single chunk.
+pub const DEFAULT_MOBS_PER_CHUNK: usize = 16;
+
+
+/// The default maximum number of item drops within a single chunk.
+pub const DEFAULT_ITEMS_PER_CHUNK: usize = 32;
+
+
+/// The default maximum number of mobs and item drops across all worlds.
+pub const DEFAULT_ENTITY_BUDGET: usize = 2048;
+
+
+/// A marker component for a mob, which is counted against the mob caps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Mob;
+
+
+/// A component for an item stack that has been dropped into the world, which
+/// is counted against the item caps.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect, Component)]
+#[reflect(Component)]
+pub struct ItemDrop(pub ItemStack);
+
+
+/// A kind of entity that is limited by the entity caps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CappedEntity {
+    /// A mob.
+    Mob,
+
+    /// An item drop.
+    Item,
+}
+
+
+/// The limits on the number of mobs and item drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct EntityCaps {
+    /// The maximum number of mobs within a single chunk.
+    pub mobs_per_chunk: usize,
+
+    /// The maximum number of item drops within a single chunk.
+    pub items_per_chunk: usize,
+
+    /// The maximum number of mobs and item drops across all worlds.
+    pub entity_budget: usize,
+}
+
+impl Default for EntityCaps {
+    fn default() -> Self {
+        Self {
+            mobs_per_chunk:  DEFAULT_MOBS_PER_CHUNK,
+            items_per_chunk: DEFAULT_ITEMS_PER_CHUNK,
+            entity_budget:   DEFAULT_ENTITY_BUDGET,
+        }
+    }
+}
+
+impl EntityCaps {
+    /// Gets the maximum number of entities of the given kind within a single
+    /// chunk.
+    pub fn per_chunk(&self, kind: CappedEntity) -> usize {
+        match kind {
+            CappedEntity::Mob => self.mobs_per_chunk,
+            CappedEntity::Item => self.items_per_chunk,
+        }
+    }
+}
+
+
+/// The number of mobs and item drops within a single chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkCounts {
+    /// The number of mobs within the chunk.
+    pub mobs: usize,
+
+    /// The number of item drops within the chunk.
+    pub items: usize,
+}
+
+impl ChunkCounts {
+    /// Gets the number of entities of the given kind within the chunk.
+    pub fn get(&self, kind: CappedEntity) -> usize {
+        match kind {
+            CappedEntity::Mob => self.mobs,
+            CappedEntity::Item => self.items,
+        }
+    }
+
+
+    /// Gets a mutable reference to the number of entities of the given kind
+    /// within the chunk.
+    fn get_mut(&mut self, kind: CappedEntity) -> &mut usize {
+        match kind {
+            CappedEntity::Mob => &mut self.mobs,
+            CappedEntity::Item => &mut self.items,
+        }
+    }
+}
+
+
+/// A resource that counts the mobs and item drops within each chunk of each
+/// world, as of the last time that the caps were enforced, alongside the
+/// entities that have been reserved since.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct EntityCounts {
+    /// The counts of each chunk that contains at least one capped entity, by
+    /// world and chunk coordinates.
+    chunks: HashMap<(Entity, IVec3), ChunkCounts>,
+
+    /// The number of capped entities across all worlds.
+    total: usize,
+
+    /// The worlds that mobs are not allowed to spawn within.
+    mobs_disabled: HashSet<Entity>,
+}
+
+impl EntityCounts {
+    /// Gets the counts of the chunk at the given chunk coordinates within the
+    /// given world.
+    pub fn in_chunk(&self, world: Entity, chunk_coords: IVec3) -> ChunkCounts {
+        self.chunks.get(&(world, chunk_coords)).copied().unwrap_or_default()
+    }
+
+
+    /// Gets the number of capped entities across all worlds.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+
+    /// Sets whether or not mobs are allowed to spawn within the given world.
+    pub fn set_mob_spawning(&mut self, world: Entity, allowed: bool) {
+        match allowed {
+            true => {
+                self.mobs_disabled.remove(&world);
+            },
+            false => {
+                self.mobs_disabled.insert(world);
+            },
+        }
+    }
+
+
+    /// Gets whether or not mobs are allowed to spawn within the given world.
+    pub fn mob_spawning(&self, world: Entity) -> bool {
+        !self.mobs_disabled.contains(&world)
+    }
+
+
+    /// Reserves room for a new entity of the given kind within the given
+    /// chunk, if neither the chunk cap nor the entity budget would be
+    /// exceeded, and the world allows entities of that kind to spawn.
+    ///
+    /// Returns false if the entity should not be spawned.
+    pub fn try_reserve(
+        &mut self,
+        caps: &EntityCaps,
+        world: Entity,
+        chunk_coords: IVec3,
+        kind: CappedEntity,
+    ) -> bool {
+        if kind == CappedEntity::Mob && !self.mob_spawning(world) {
+            return false;
+        }
+
+        if self.total >= caps.entity_budget {
+            return false;
+        }
+
+        let counts = self.chunks.entry((world, chunk_coords)).or_default();
+        if counts.get(kind) >= caps.per_chunk(kind) {
+            return false;
+        }
+
+        *counts.get_mut(kind) += 1;
+        self.total += 1;
+        true
+    }
+}
+
+
+/// Merges the given item stacks into as few stacks as possible, without
+/// exceeding the maximum stack size.
+///
+/// Stacks are merged into the earliest stack of the same item that still has
+/// room. Returns the index of each stack that has been emptied.
+pub fn merge_item_stacks(stacks: &mut [ItemStack]) -> Vec<usize> {
+    let mut emptied = vec![];
+
+    for source in 1..stacks.len() {
+        for target in 0..source {
+            if stacks[target].item != stacks[source].item || stacks[target].is_empty() {
+                continue;
+            }
+
+            let moved =
+                stacks[source].count.min(MAX_STACK_SIZE.saturating_sub(stacks[target].count));
+            stacks[target].count += moved;
+            stacks[source].count -= moved;
+
+            if stacks[source].count == 0 {
+                emptied.push(source);
+                break;
+            }
+        }
+    }
+
+    emptied
+}
+
+
+/// Merges the item drops within each chunk, despawns the item drops and mobs
+/// that exceed their caps, and recounts the remaining entities.
+///
+/// The smallest item drops are despawned first. Item drops are despawned
+/// before mobs when the entity budget is exceeded.
+#[allow(clippy::type_complexity)]
+pub fn enforce_entity_caps(
+    caps: Res<EntityCaps>,
+    mut counts: ResMut<EntityCounts>,
+    mut items: Query<(Entity, &Position, &InWorld, &mut ItemDrop), Without<Mob>>,
+    mobs: Query<(Entity, &Position, &InWorld), With<Mob>>,
+    mut commands: Commands,
+) {
+    let mut item_chunks: HashMap<(Entity, IVec3), Vec<Entity>> = HashMap::new();
+    for (entity, position, in_world, _) in items.iter() {
+        let key = (in_world.0, world_to_chunk(position.translation));
+        item_chunks.entry(key).or_default().push(entity);
+    }
+
+    let mut mob_chunks: HashMap<(Entity, IVec3), Vec<Entity>> = HashMap::new();
+    for (entity, position, in_world) in mobs.iter() {
+        let key = (in_world.0, world_to_chunk(position.translation));
+        mob_chunks.entry(key).or_default().push(entity);
+    }
+
+    let mut kept_items = vec![];
+    for (_, mut entities) in item_chunks.drain() {
+        entities.sort();
+
+        let mut stacks: Vec<ItemStack> =
+            entities.iter().map(|e| items.get(*e).unwrap().3 .0.clone()).collect();
+        for index in merge_item_stacks(&mut stacks) {
+            commands.entity(entities[index]).despawn_recursive();
+        }
+
+        let mut remaining: Vec<(Entity, ItemStack)> =
+            entities.into_iter().zip(stacks).filter(|(_, stack)| stack.count > 0).collect();
+
+        for (entity, stack) in remaining.iter() {
+            let mut drop = items.get_mut(*entity).unwrap().3;
+            if drop.0 != *stack {
+                drop.0 = stack.clone();
+            }
+        }
+
+        remaining
+            .sort_by(|(a, a_stack), (b, b_stack)| b_stack.count.cmp(&a_stack.count).then(a.cmp(b)));
+        for (entity, _) in remaining.drain(caps.items_per_chunk.min(remaining.len())..) {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        kept_items.extend(remaining);
+    }
+
+    let mut kept_mobs = vec![];
+    for (_, mut entities) in mob_chunks.drain() {
+        entities.sort();
+        for entity in entities.drain(caps.mobs_per_chunk.min(entities.len())..) {
+            commands.entity(entity).despawn_recursive();
+        }
+        kept_mobs.extend(entities);
+    }
+
+    let mut excess = (kept_items.len() + kept_mobs.len()).saturating_sub(caps.entity_budget);
+    if excess > 0 {
+        warn!(
+            "Entity budget of {} exceeded, despawning {excess} entities",
+            caps.entity_budget
+        );
+
+        kept_items
+            .sort_by(|(a, a_stack), (b, b_stack)| a_stack.count.cmp(&b_stack.count).then(a.cmp(b)));
+        while excess > 0 {
+            let entity = match kept_items.is_empty() {
+                false => kept_items.remove(0).0,
+                true => kept_mobs.pop().unwrap(),
+            };
+            commands.entity(entity).despawn_recursive();
+            excess -= 1;
+        }
+    }
+
+    counts.chunks.clear();
+    counts.total = kept_items.len() + kept_mobs.len();
+
+    let kept = kept_items
+        .iter()
+        .map(|(entity, _)| (*entity, CappedEntity::Item))
+        .chain(kept_mobs.iter().map(|entity| (*entity, CappedEntity::Mob)));
+
+    for (entity, kind) in kept {
+        let (position, in_world) = match kind {
+            CappedEntity::Item => {
+                let (_, position, in_world, _) = items.get(entity).unwrap();
+                (position, in_world)
+            },
+            CappedEntity::Mob => {
+                let (_, position, in_world) = mobs.get(entity).unwrap();
+                (position, in_world)
+            },
+        };
+
+        let key = (in_world.0, world_to_chunk(position.translation));
+        *counts.chunks.entry(key).or_default().get_mut(kind) += 1;
+    }
+}
+
+
+/// A mini extension plugin that enforces the entity caps on the server after
+/// each physics tick.
+#[derive(Debug, Clone, Default)]
+pub struct EntityCapsPlugin;
+
+impl Plugin for EntityCapsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Mob>()
+            .register_type::<ItemDrop>()
+            .init_resource::<EntityCaps>()
+            .init_resource::<EntityCounts>()
+            .add_system_to_stage("post_tick", enforce_entity_caps.after(apply_velocity));
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn merge_same_items() {
+        let mut stacks = vec![
+            ItemStack::new("stone", 10),
+            ItemStack::new("dirt", 5),
+            ItemStack::new("stone", 20),
+            ItemStack::new("dirt", 60),
+        ];
+
+        let emptied = merge_item_stacks(&mut stacks);
+        assert_eq!(emptied, vec![2]);
+        assert_eq!(stacks, vec![
+            ItemStack::new("stone", 30),
+            ItemStack::new("dirt", 64),
+            ItemStack::new("stone", 0),
+            ItemStack::new("dirt", 1),
+        ]);
+    }
+
+
+    #[test]
+    fn merge_respects_stack_size() {
+        let mut stacks = vec![
+            ItemStack::new("stone", 64),
+            ItemStack::new("stone", 64),
+            ItemStack::new("stone", 10),
+        ];
+
+        let emptied = merge_item_stacks(&mut stacks);
+        assert_eq!(emptied, vec![]);
+        assert_eq!(stacks[2].count, 10);
+    }
+
+
+    #[test]
+    fn reserve_within_caps() {
+        let caps = EntityCaps {
+            mobs_per_chunk:  2,
+            items_per_chunk: 1,
+            entity_budget:   4,
+        };
+
+        let world = Entity::from_raw(0);
+        let mut counts = EntityCounts::default();
+
+        assert!(counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Mob));
+        assert!(counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Mob));
+        assert!(!counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Mob));
+        assert!(counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Item));
+        assert!(!counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Item));
+        assert!(counts.try_reserve(&caps, world, IVec3::X, CappedEntity::Item));
+        assert!(!counts.try_reserve(&caps, world, IVec3::Y, CappedEntity::Mob));
+
+        assert_eq!(counts.total(), 4);
+        assert_eq!(counts.in_chunk(world, IVec3::ZERO), ChunkCounts {
+            mobs:  2,
+            items: 1,
+        });
+    }
+
+
+    #[test]
+    fn reserve_without_mob_spawning() {
+        let caps = EntityCaps::default();
+        let world = Entity::from_raw(0);
+        let other = Entity::from_raw(1);
+        let mut counts = EntityCounts::default();
+
+        counts.set_mob_spawning(world, false);
+        assert!(!counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Mob));
+        assert!(counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Item));
+        assert!(counts.try_reserve(&caps, other, IVec3::ZERO, CappedEntity::Mob));
+
+        counts.set_mob_spawning(world, true);
+        assert!(counts.try_reserve(&caps, world, IVec3::ZERO, CappedEntity::Mob));
+        assert_eq!(counts.total(), 3);
+    }
+
+
+    #[test]
+    fn enforce_caps() {
+        let mut app = App::new();
+        app.insert_resource(EntityCaps {
+            mobs_per_chunk:  1,
+            items_per_chunk: 2,
+            entity_budget:   3,
+        })
+        .init_resource::<EntityCounts>()
+        .add_system(enforce_entity_caps);
+
+        let world = app.world.spawn_empty().id();
+        let in_chunk = |x: f32| {
+            let position = Position {
+                translation: Vec3::new(x, 1.0, 1.0),
+                ..default()
+            };
+            (position, InWorld(world))
+        };
+
+        app.world.spawn((in_chunk(1.0), ItemDrop(ItemStack::new("stone", 10))));
+        app.world.spawn((in_chunk(2.0), ItemDrop(ItemStack::new("stone", 10))));
+        app.world.spawn((in_chunk(3.0), ItemDrop(ItemStack::new("dirt", 1))));
+        app.world.spawn((in_chunk(4.0), ItemDrop(ItemStack::new("sand", 5))));
+        app.world.spawn((in_chunk(5.0), Mob));
+        app.world.spawn((in_chunk(6.0), Mob));
+        app.world.spawn((in_chunk(100.0), Mob));
+        app.update();
+
+        let mut drops: Vec<ItemStack> =
+            app.world.query::<&ItemDrop>().iter(&app.world).map(|d| d.0.clone()).collect();
+        drops.sort_by(|a, b| a.item.cmp(&b.item));
+
+        assert_eq!(drops, vec![ItemStack::new("stone", 20)]);
+        assert_eq!(app.world.query::<&Mob>().iter(&app.world).count(), 2);
+        assert_eq!(app.world.resource::<EntityCounts>().total(), 3);
+    }
+}
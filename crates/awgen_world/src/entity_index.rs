@@ -0,0 +1,210 @@
+//! An index of which entities reside within each chunk of a voxel world, which
+//! allows for fast spatial queries such as finding all entities near a player.
+//!
+//! The index is updated incrementally, and only entities whose position or
+//! world has changed are moved between chunks.
+
+
+use crate::prelude::{InWorld, VoxelChunkStates};
+use awgen_math::prelude::world_to_chunk;
+use awgen_physics::prelude::Position;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// A component that stores the entities within each chunk of the voxel world
+/// that it is attached to.
+///
+/// Entities are indexed by the chunk that contains their position. Only
+/// entities with both a position and an [`InWorld`] component are indexed.
+#[derive(Debug, Clone, Default, Component)]
+pub struct ChunkEntityIndex {
+    /// The entities within each chunk that contains at least one entity.
+    chunks: HashMap<IVec3, Vec<Entity>>,
+
+    /// The chunk coordinates of each indexed entity.
+    locations: HashMap<Entity, IVec3>,
+}
+
+impl ChunkEntityIndex {
+    /// Gets all entities within the chunk at the given chunk coordinates, in
+    /// no particular order.
+    pub fn entities_in_chunk(&self, chunk_coords: IVec3) -> &[Entity] {
+        self.chunks.get(&chunk_coords).map_or(&[], |entities| entities.as_slice())
+    }
+
+
+    /// Gets the coordinates of the chunk that the given entity is indexed
+    /// within, if it is indexed within this world.
+    pub fn chunk_of(&self, entity: Entity) -> Option<IVec3> {
+        self.locations.get(&entity).copied()
+    }
+
+
+    /// Gets an iterator over the coordinates of all chunks that contain at
+    /// least one entity, alongside the number of entities within them.
+    pub fn occupied_chunks(&self) -> impl Iterator<Item = (IVec3, usize)> + '_ {
+        self.chunks.iter().map(|(coords, entities)| (*coords, entities.len()))
+    }
+
+
+    /// Gets the total number of entities within this index.
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+
+    /// Gets whether or not this index contains no entities.
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+
+    /// Indexes the given entity within the given chunk, moving it out of its
+    /// previous chunk if needed.
+    pub fn insert(&mut self, entity: Entity, chunk_coords: IVec3) {
+        if let Some(previous) = self.locations.insert(entity, chunk_coords) {
+            if previous == chunk_coords {
+                return;
+            }
+
+            self.remove_from_chunk(entity, previous);
+        }
+
+        self.chunks.entry(chunk_coords).or_default().push(entity);
+    }
+
+
+    /// Removes the given entity from this index, returning the chunk that it
+    /// was indexed within.
+    pub fn remove(&mut self, entity: Entity) -> Option<IVec3> {
+        let chunk_coords = self.locations.remove(&entity)?;
+        self.remove_from_chunk(entity, chunk_coords);
+        Some(chunk_coords)
+    }
+
+
+    /// Removes the given entity from the entity list of the given chunk.
+    fn remove_from_chunk(&mut self, entity: Entity, chunk_coords: IVec3) {
+        let Some(entities) = self.chunks.get_mut(&chunk_coords) else {
+            return;
+        };
+
+        entities.retain(|e| *e != entity);
+        if entities.is_empty() {
+            self.chunks.remove(&chunk_coords);
+        }
+    }
+}
+
+
+/// Builds and inserts a chunk entity index into each voxel world that does not
+/// have one yet, indexing all entities that are already within the world.
+pub fn insert_chunk_entity_indices(
+    worlds: Query<Entity, (With<VoxelChunkStates>, Without<ChunkEntityIndex>)>,
+    entities: Query<(Entity, &Position, &InWorld)>,
+    mut commands: Commands,
+) {
+    for world in worlds.iter() {
+        let mut index = ChunkEntityIndex::default();
+        for (entity, position, in_world) in entities.iter() {
+            if in_world.0 == world {
+                index.insert(entity, world_to_chunk(position.translation));
+            }
+        }
+
+        commands.entity(world).insert(index);
+    }
+}
+
+
+/// Moves each entity whose position or world has changed into its new chunk,
+/// and removes entities that have been despawned or have left their world.
+#[allow(clippy::type_complexity)]
+pub fn update_chunk_entity_indices(
+    mut worlds: Query<(Entity, &mut ChunkEntityIndex)>,
+    moved: Query<(Entity, &Position, &InWorld), Or<(Changed<Position>, Changed<InWorld>)>>,
+    removed_positions: RemovedComponents<Position>,
+    removed_worlds: RemovedComponents<InWorld>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("entity_index").entered();
+
+    for entity in removed_positions.iter().chain(removed_worlds.iter()) {
+        for (_, mut index) in worlds.iter_mut() {
+            index.remove(entity);
+        }
+    }
+
+    for (entity, position, in_world) in moved.iter() {
+        let chunk_coords = world_to_chunk(position.translation);
+
+        for (world, mut index) in worlds.iter_mut() {
+            match world == in_world.0 {
+                true => {
+                    if index.chunk_of(entity) != Some(chunk_coords) {
+                        index.insert(entity, chunk_coords);
+                    }
+                },
+                false => {
+                    if index.chunk_of(entity).is_some() {
+                        index.remove(entity);
+                    }
+                },
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn entities_cross_chunks() {
+        let mut app = App::new();
+        app.add_system(insert_chunk_entity_indices)
+            .add_system(update_chunk_entity_indices.after(insert_chunk_entity_indices));
+
+        let world = app.world.spawn(VoxelChunkStates::default()).id();
+        let other = app.world.spawn(VoxelChunkStates::default()).id();
+        let entity = app
+            .world
+            .spawn((
+                Position {
+                    translation: Vec3::new(1.0, 2.0, 3.0),
+                    ..default()
+                },
+                InWorld(world),
+            ))
+            .id();
+        app.update();
+
+        let index = app.world.get::<ChunkEntityIndex>(world).unwrap();
+        assert_eq!(index.entities_in_chunk(IVec3::ZERO), &[entity]);
+        assert_eq!(index.len(), 1);
+
+        app.world.get_mut::<Position>(entity).unwrap().translation = Vec3::new(-1.0, 20.0, 3.0);
+        app.update();
+
+        let index = app.world.get::<ChunkEntityIndex>(world).unwrap();
+        assert_eq!(index.entities_in_chunk(IVec3::ZERO), &[]);
+        assert_eq!(index.entities_in_chunk(IVec3::new(-1, 1, 0)), &[entity]);
+        assert_eq!(index.chunk_of(entity), Some(IVec3::new(-1, 1, 0)));
+
+        app.world.get_mut::<InWorld>(entity).unwrap().0 = other;
+        app.update();
+
+        assert!(app.world.get::<ChunkEntityIndex>(world).unwrap().is_empty());
+        let index = app.world.get::<ChunkEntityIndex>(other).unwrap();
+        assert_eq!(index.entities_in_chunk(IVec3::new(-1, 1, 0)), &[entity]);
+
+        app.world.despawn(entity);
+        app.update();
+
+        assert!(app.world.get::<ChunkEntityIndex>(other).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! Explosions, which destroy the blocks around them and push nearby entities
+//! away.
+//!
+//! An explosion is triggered by sending an [`ExplosionEvent`]. The destroyed
+//! blocks are found by casting rays outwards from the center of the explosion,
+//! where each ray loses strength as it passes through blocks, based on their
+//! [`BlockResistance`]. Blocks are destroyed by replacing them with the
+//! default block data, through the same path as any other block change.
+
+
+use crate::prelude::{InWorld, VoxelWorld};
+use awgen_math::prelude::GridRaycast;
+use awgen_physics::prelude::{Knockback, Movable, Position};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use std::marker::PhantomData;
+
+
+/// The number of rays cast along each edge of the cube of ray directions.
+const RAY_GRID_SIZE: i32 = 16;
+
+
+/// The strength that an explosion ray loses for every block that it passes
+/// through, in addition to the resistance of the block.
+const RAY_ATTENUATION: f32 = 0.3;
+
+
+/// The radius, in meters per unit of power, within which entities are knocked
+/// back by an explosion.
+const KNOCKBACK_RADIUS: f32 = 2.0;
+
+
+/// The knockback impulse, in meters per physics frame per unit of power, that
+/// is applied to an entity at the center of an explosion.
+const KNOCKBACK_STRENGTH: f32 = 0.15;
+
+
+/// Describes how well a block data type resists being destroyed by explosions.
+pub trait BlockResistance {
+    /// Gets the blast resistance of this block. Explosion rays lose this much
+    /// strength when passing through the block. Empty blocks should return 0.
+    fn blast_resistance(&self) -> f32;
+}
+
+
+/// An event that is triggered to cause an explosion within a world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExplosionEvent {
+    /// The world entity that the explosion occurs within.
+    pub world: Entity,
+
+    /// The center of the explosion, in world space.
+    pub center: Vec3,
+
+    /// The power of the explosion. This is roughly the radius, in blocks, of
+    /// the area that is destroyed when surrounded by blocks without any blast
+    /// resistance.
+    pub power: f32,
+}
+
+
+/// Gets the positions of all blocks within the given world that would be
+/// destroyed by an explosion with the given center and power, in sorted order.
+///
+/// Empty blocks, which have the default block data, are never included.
+pub fn explosion_blocks<BlockData>(
+    world: &VoxelWorld<BlockData>,
+    center: Vec3,
+    power: f32,
+) -> Vec<IVec3>
+where
+    BlockData: BlockResistance + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let air = BlockData::default();
+    let max_distance = power / RAY_ATTENUATION;
+    let mut destroyed = HashSet::new();
+
+    for direction in ray_directions() {
+        let mut strength = power;
+        for step in GridRaycast::new(center, direction, max_distance) {
+            let block = world.get_block_data(step.cell);
+            strength -= RAY_ATTENUATION + block.blast_resistance();
+            if strength <= 0.0 {
+                break;
+            }
+
+            if block != air {
+                destroyed.insert(step.cell);
+            }
+        }
+    }
+
+    let mut destroyed: Vec<_> = destroyed.into_iter().collect();
+    destroyed.sort_by_key(|pos| (pos.x, pos.y, pos.z));
+    destroyed
+}
+
+
+/// Gets the knockback impulse that an explosion with the given center and power
+/// applies to an entity at the given position, or `None` if the entity is out
+/// of range.
+pub fn explosion_knockback(center: Vec3, power: f32, position: Vec3) -> Option<Vec3> {
+    let radius = power * KNOCKBACK_RADIUS;
+    let offset = position - center;
+    let distance = offset.length();
+    if distance >= radius {
+        return None;
+    }
+
+    let direction = match distance > f32::EPSILON {
+        true => offset / distance,
+        false => Vec3::Y,
+    };
+
+    Some(direction * (1.0 - distance / radius) * power * KNOCKBACK_STRENGTH)
+}
+
+
+/// Gets the directions of all rays that are cast by an explosion, which point
+/// towards each cell on the surface of a cube around the explosion center.
+fn ray_directions() -> impl Iterator<Item = Vec3> {
+    let max = RAY_GRID_SIZE - 1;
+    (0..RAY_GRID_SIZE * RAY_GRID_SIZE * RAY_GRID_SIZE).filter_map(move |index| {
+        let cell = IVec3::new(
+            index % RAY_GRID_SIZE,
+            index / RAY_GRID_SIZE % RAY_GRID_SIZE,
+            index / (RAY_GRID_SIZE * RAY_GRID_SIZE),
+        );
+
+        let on_surface = cell.cmpeq(IVec3::ZERO).any() || cell.cmpeq(IVec3::splat(max)).any();
+        on_surface.then(|| cell.as_vec3() / max as f32 * 2.0 - 1.0)
+    })
+}
+
+
+/// Applies each explosion to the blocks of its world, and knocks back all
+/// movable entities within that world that are in range.
+pub fn handle_explosions<BlockData>(
+    mut explosion_ev: EventReader<ExplosionEvent>,
+    mut worlds: Query<&mut VoxelWorld<BlockData>>,
+    mut entities: Query<(Entity, &Position, &InWorld, Option<&mut Knockback>), With<Movable>>,
+    mut commands: Commands,
+) where
+    BlockData: BlockResistance + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("explosions").entered();
+
+    for ev in explosion_ev.iter() {
+        let Ok(mut world) = worlds.get_mut(ev.world) else {
+            continue;
+        };
+
+        for block_pos in explosion_blocks(&world, ev.center, ev.power) {
+            world.set_block_data(block_pos, default());
+        }
+
+        for (entity, position, in_world, knockback) in entities.iter_mut() {
+            if in_world.0 != ev.world {
+                continue;
+            }
+
+            let Some(impulse) = explosion_knockback(ev.center, ev.power, position.translation)
+            else {
+                continue;
+            };
+
+            match knockback {
+                Some(mut knockback) => knockback.apply_impulse(impulse),
+                None => {
+                    commands.entity(entity).insert(Knockback {
+                        velocity: impulse,
+                    });
+                },
+            }
+        }
+    }
+}
+
+
+/// A mini extension plugin that enables explosions within worlds containing a
+/// block data layer of the given type.
+#[derive(Debug, Clone, Default)]
+pub struct ExplosionPlugin<BlockData>
+where BlockData: BlockResistance + Default + Copy + PartialEq + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for ExplosionPlugin<BlockData>
+where BlockData: BlockResistance + Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExplosionEvent>().add_system(handle_explosions::<BlockData>);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_math::prelude::Region;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Stone,
+        Obsidian,
+    }
+
+    impl BlockResistance for TestBlock {
+        fn blast_resistance(&self) -> f32 {
+            match self {
+                TestBlock::Air => 0.0,
+                TestBlock::Stone => 0.5,
+                TestBlock::Obsidian => 100.0,
+            }
+        }
+    }
+
+
+    #[test]
+    fn resistant_blocks_survive() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        for pos in Region::from_points(IVec3::splat(-8), IVec3::splat(8)).iter() {
+            world.set_block_data(pos, TestBlock::Stone);
+        }
+        for pos in Region::from_points(IVec3::new(-8, -8, 2), IVec3::new(8, 8, 2)).iter() {
+            world.set_block_data(pos, TestBlock::Obsidian);
+        }
+
+        let destroyed = explosion_blocks(&world, Vec3::splat(0.5), 4.0);
+        assert!(destroyed.contains(&IVec3::ZERO));
+        assert!(destroyed.contains(&IVec3::new(0, 0, -2)));
+        assert!(!destroyed.iter().any(|pos| pos.z >= 2));
+        assert!(!destroyed.iter().any(|pos| pos.abs().max_element() > 6));
+    }
+
+
+    #[test]
+    fn explosion_event() {
+        let mut app = App::new();
+        app.add_plugin(ExplosionPlugin::<TestBlock>::default());
+
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::new(1, 0, 0), TestBlock::Stone);
+        let world = app.world.spawn(world).id();
+
+        let entity = app
+            .world
+            .spawn((
+                Position {
+                    translation: Vec3::new(2.5, 0.5, 0.5),
+                    ..default()
+                },
+                InWorld(world),
+                Movable::default(),
+            ))
+            .id();
+
+        app.world.resource_mut::<Events<ExplosionEvent>>().send(ExplosionEvent {
+            world,
+            center: Vec3::splat(0.5),
+            power: 3.0,
+        });
+        app.update();
+
+        let voxels = app.world.get::<VoxelWorld<TestBlock>>(world).unwrap();
+        assert_eq!(voxels.get_block_data(IVec3::new(1, 0, 0)), TestBlock::Air);
+
+        let knockback = app.world.get::<Knockback>(entity).unwrap();
+        assert!(knockback.velocity.x > 0.0);
+        assert_eq!(knockback.velocity.y, 0.0);
+    }
+}
@@ -0,0 +1,470 @@
+//! A data-driven world generation stage for placing small features, such as ore
+//! veins, boulders, and plants.
+//!
+//! Features are described by a [`FeatureSet`], which is loaded from a RON file
+//! for each biome. Replacing the features of a biome only requires editing its
+//! file.
+//!
+//! ```ron
+//! (
+//!     biome: "plains",
+//!     features: [
+//!         (
+//!             name: "iron_ore",
+//!             kind: Vein(block: IronOre, replace: [Stone]),
+//!             frequency: 8.0,
+//!             min_height: -128,
+//!             max_height: 16,
+//!             cluster_size: 6,
+//!         ),
+//!         (
+//!             name: "tall_grass",
+//!             kind: Plant(block: TallGrass, on: [Grass]),
+//!             frequency: 3.5,
+//!             cluster_size: 5,
+//!         ),
+//!     ],
+//! )
+//! ```
+
+
+use crate::prelude::{ChunkView, GenerationPhase, GenerationStage};
+use anyhow::Result;
+use awgen_math::prelude::{
+    chunk_blocks, chunk_to_block, floor_div, Region, Region2, Seed, SeededRng
+};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+
+/// The horizontal distance, in blocks, that plants within a single patch may be
+/// spread from the center of the patch.
+const PLANT_SPREAD: i32 = 3;
+
+
+/// The kind of a feature, which determines how its blocks are placed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FeatureKind<BlockData> {
+    /// A winding vein of blocks, such as ores, that only replaces the given
+    /// blocks. The cluster size is the number of blocks within the vein.
+    Vein {
+        /// The block that the vein is made of.
+        block: BlockData,
+
+        /// The blocks that may be replaced by the vein.
+        replace: Vec<BlockData>,
+    },
+
+    /// A rough sphere of blocks resting on the terrain surface. The cluster
+    /// size is the diameter of the boulder.
+    Boulder {
+        /// The block that the boulder is made of.
+        block: BlockData,
+    },
+
+    /// A patch of single blocks placed on top of the terrain surface. The
+    /// cluster size is the number of plants within the patch.
+    Plant {
+        /// The plant block.
+        block: BlockData,
+
+        /// The surface blocks that the plant may be placed on. If empty, the
+        /// plant may be placed on any block.
+        #[serde(default = "Vec::new")]
+        on: Vec<BlockData>,
+    },
+}
+
+
+/// The configuration of a single feature within a feature set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureConfig<BlockData> {
+    /// The unique name of this feature, which is also used to seed its
+    /// placement.
+    pub name: String,
+
+    /// The kind of this feature.
+    pub kind: FeatureKind<BlockData>,
+
+    /// The average number of times this feature is placed per chunk. Vein
+    /// features are placed per chunk, while surface features are placed per
+    /// column of chunks.
+    pub frequency: f32,
+
+    /// The lowest y coordinate that this feature may be placed at.
+    #[serde(default = "default_min_height")]
+    pub min_height: i32,
+
+    /// The highest y coordinate that this feature may be placed at.
+    #[serde(default = "default_max_height")]
+    pub max_height: i32,
+
+    /// The size of each placed cluster. The meaning of this value depends on
+    /// the kind of this feature.
+    pub cluster_size: u32,
+}
+
+impl<BlockData> FeatureConfig<BlockData> {
+    /// Gets the maximum distance, in blocks, that this feature may extend from
+    /// its origin.
+    fn reach(&self) -> i32 {
+        match self.kind {
+            FeatureKind::Vein {
+                ..
+            } => self.cluster_size as i32,
+            FeatureKind::Boulder {
+                ..
+            } => (self.cluster_size as i32 + 1) / 2,
+            FeatureKind::Plant {
+                ..
+            } => PLANT_SPREAD,
+        }
+    }
+
+
+    /// Gets the number of times this feature is placed within a chunk, using
+    /// the given random number generator.
+    fn attempts(&self, rng: &mut SeededRng) -> u32 {
+        let whole = self.frequency.max(0.0).floor();
+        whole as u32 + rng.chance(self.frequency - whole) as u32
+    }
+}
+
+
+/// The default lowest y coordinate that features may be placed at.
+fn default_min_height() -> i32 {
+    i32::MIN
+}
+
+
+/// The default highest y coordinate that features may be placed at.
+fn default_max_height() -> i32 {
+    i32::MAX
+}
+
+
+/// A set of features that are placed within a single biome.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureSet<BlockData> {
+    /// The name of the biome that this feature set belongs to.
+    pub biome: String,
+
+    /// The features within this set, in the order that they are placed.
+    pub features: Vec<FeatureConfig<BlockData>>,
+}
+
+impl<BlockData> FeatureSet<BlockData>
+where BlockData: DeserializeOwned
+{
+    /// Loads a feature set from the RON file at the given path.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+
+
+    /// Parses a feature set from the given RON text.
+    pub fn from_ron(text: &str) -> Result<Self> {
+        Ok(ron::from_str(text)?)
+    }
+}
+
+
+/// A population stage that places the features of a feature set.
+///
+/// Each feature is placed using a random number generator that is seeded by
+/// the feature name and the chunk that the feature originates from, so that
+/// features are placed identically no matter which chunk is generated first.
+/// Features may extend into neighbouring chunks.
+#[derive(Debug, Clone)]
+pub struct FeaturePlacement<BlockData> {
+    /// The name of this stage.
+    name: String,
+
+    /// The features to place.
+    features: FeatureSet<BlockData>,
+}
+
+impl<BlockData> FeaturePlacement<BlockData> {
+    /// Creates a new feature placement stage for the given feature set.
+    pub fn new(features: FeatureSet<BlockData>) -> Self {
+        Self {
+            name: format!("features:{}", features.biome),
+            features,
+        }
+    }
+}
+
+impl<BlockData> GenerationStage<BlockData> for FeaturePlacement<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+
+    fn phase(&self) -> GenerationPhase {
+        GenerationPhase::Population
+    }
+
+
+    fn margin(&self) -> i32 {
+        self.features.features.iter().map(|f| f.reach()).max().unwrap_or(0)
+    }
+
+
+    fn generate(&self, view: &mut ChunkView<BlockData>) {
+        for feature in self.features.features.iter() {
+            let seed = view.seed().derive_str(&feature.name);
+            let reach = feature.reach();
+            let area = view.area();
+            let origins = Region::from_points(
+                floor_div(area.min() - reach, 16),
+                floor_div(area.max() + reach, 16),
+            );
+            let columns = Region2::from(origins).iter().map(|c| IVec3::new(c.x, 0, c.y));
+
+            match &feature.kind {
+                FeatureKind::Vein {
+                    block,
+                    replace,
+                } => {
+                    for chunk_coords in origins.iter() {
+                        place_veins(view, feature, seed, chunk_coords, *block, replace);
+                    }
+                },
+                FeatureKind::Boulder {
+                    block,
+                } => {
+                    for chunk_coords in columns {
+                        place_boulders(view, feature, seed, chunk_coords, *block);
+                    }
+                },
+                FeatureKind::Plant {
+                    block,
+                    on,
+                } => {
+                    for chunk_coords in columns {
+                        place_plants(view, feature, seed, chunk_coords, *block, on);
+                    }
+                },
+            }
+        }
+    }
+}
+
+
+/// Places all veins of the given feature that originate within the given
+/// chunk.
+fn place_veins<BlockData>(
+    view: &mut ChunkView<BlockData>,
+    feature: &FeatureConfig<BlockData>,
+    seed: Seed,
+    chunk_coords: IVec3,
+    block: BlockData,
+    replace: &[BlockData],
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let mut rng = seed.for_chunk(chunk_coords).rng();
+    let region = chunk_blocks(chunk_coords);
+
+    for _ in 0..feature.attempts(&mut rng) {
+        let mut pos = region.min() + random_offset(&mut rng, IVec3::splat(16));
+        if pos.y < feature.min_height || pos.y > feature.max_height {
+            continue;
+        }
+
+        for _ in 0..feature.cluster_size {
+            if replace.contains(&view.get_block(pos)) {
+                view.set_block(pos, block);
+            }
+
+            pos += random_offset(&mut rng, IVec3::splat(3)) - 1;
+        }
+    }
+}
+
+
+/// Places all boulders of the given feature that originate within the given
+/// column of chunks, where the y coordinate of the chunk is always 0.
+fn place_boulders<BlockData>(
+    view: &mut ChunkView<BlockData>,
+    feature: &FeatureConfig<BlockData>,
+    seed: Seed,
+    chunk_coords: IVec3,
+    block: BlockData,
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let mut rng = seed.for_chunk(chunk_coords).rng();
+    let radius = feature.cluster_size as f32 * 0.5;
+    let reach = feature.reach();
+
+    for _ in 0..feature.attempts(&mut rng) {
+        let column = chunk_to_block(chunk_coords) + random_offset(&mut rng, IVec3::new(16, 0, 16));
+        let Some(height) = view.surface_height(IVec2::new(column.x, column.z)) else {
+            continue;
+        };
+
+        if height < feature.min_height || height > feature.max_height {
+            continue;
+        }
+
+        let center = column.as_vec3() + Vec3::new(0.5, height as f32 + 0.5, 0.5);
+        let bounds = Region::from_points(
+            IVec3::new(column.x, height, column.z) - reach,
+            IVec3::new(column.x, height, column.z) + reach,
+        );
+
+        for pos in bounds.iter() {
+            let offset = pos.as_vec3() + 0.5 - center;
+            if offset.length_squared() <= radius * radius {
+                view.set_block(pos, block);
+            }
+        }
+    }
+}
+
+
+/// Places all plant patches of the given feature that originate within the
+/// given column of chunks, where the y coordinate of the chunk is always 0.
+fn place_plants<BlockData>(
+    view: &mut ChunkView<BlockData>,
+    feature: &FeatureConfig<BlockData>,
+    seed: Seed,
+    chunk_coords: IVec3,
+    block: BlockData,
+    on: &[BlockData],
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    let mut rng = seed.for_chunk(chunk_coords).rng();
+    let air = BlockData::default();
+
+    for _ in 0..feature.attempts(&mut rng) {
+        let center = chunk_to_block(chunk_coords) + random_offset(&mut rng, IVec3::new(16, 0, 16));
+
+        for _ in 0..feature.cluster_size {
+            let spread = random_offset(&mut rng, IVec3::new(1, 0, 1) * (PLANT_SPREAD * 2 + 1));
+            let column = center + spread - IVec3::new(PLANT_SPREAD, 0, PLANT_SPREAD);
+            let Some(height) = view.surface_height(IVec2::new(column.x, column.z)) else {
+                continue;
+            };
+
+            if height < feature.min_height || height > feature.max_height {
+                continue;
+            }
+
+            let ground = IVec3::new(column.x, height, column.z);
+            let ground_block = view.get_block(ground);
+            if ground_block == air || (!on.is_empty() && !on.contains(&ground_block)) {
+                continue;
+            }
+
+            if view.get_block(ground + IVec3::Y) == air {
+                view.set_block(ground + IVec3::Y, block);
+            }
+        }
+    }
+}
+
+
+/// Generates a random offset within the range `0..size` along each axis. Axes
+/// with a size of 0 are always 0.
+fn random_offset(rng: &mut SeededRng, size: IVec3) -> IVec3 {
+    let mut axis = |size: i32| if size > 0 { rng.range_i32(0, size) } else { 0 };
+    IVec3::new(axis(size.x), axis(size.y), axis(size.z))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{NoiseTerrain, SurfaceLayers, VoxelWorld, WorldGenerator};
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Stone,
+        Grass,
+        Dirt,
+        Ore,
+        Flower,
+    }
+
+
+    const FEATURES: &str = r#"(
+        biome: "plains",
+        features: [
+            (
+                name: "ore",
+                kind: Vein(block: Ore, replace: [Stone]),
+                frequency: 6.0,
+                max_height: -8,
+                cluster_size: 8,
+            ),
+            (
+                name: "flowers",
+                kind: Plant(block: Flower, on: [Grass]),
+                frequency: 2.5,
+                cluster_size: 6,
+            ),
+        ],
+    )"#;
+
+
+    fn generate(generator: &WorldGenerator<TestBlock>) -> VoxelWorld<TestBlock> {
+        let mut world = VoxelWorld::default();
+        for chunk_coords in Region::from_points(IVec3::new(-1, -2, -1), IVec3::new(1, 1, 1)).iter()
+        {
+            let chunk = generator.generate_chunk(chunk_coords);
+            world.set_block_region(chunk_blocks(chunk_coords), &chunk.blocks);
+        }
+        world
+    }
+
+
+    #[test]
+    fn place_features_from_ron() {
+        let features = FeatureSet::<TestBlock>::from_ron(FEATURES).unwrap();
+        assert_eq!(features.features[1].min_height, i32::MIN);
+
+        let generator = WorldGenerator::new(Seed(11))
+            .with_stage(NoiseTerrain::new(TestBlock::Stone))
+            .with_stage(SurfaceLayers {
+                base:   TestBlock::Stone,
+                top:    TestBlock::Grass,
+                filler: TestBlock::Dirt,
+                depth:  3,
+            })
+            .with_stage(FeaturePlacement::new(features));
+        assert_eq!(generator.stage_names().last(), Some("features:plains"));
+
+        let world = generate(&generator);
+        let region = Region::from_points(IVec3::new(-16, -32, -16), IVec3::new(31, 31, 31));
+        let blocks = world.get_block_region(region);
+
+        let find = |block| -> Vec<IVec3> {
+            region
+                .iter()
+                .filter(|pos| blocks[region.point_to_index_unchecked(*pos)] == block)
+                .collect()
+        };
+
+        let ores = find(TestBlock::Ore);
+        assert!(!ores.is_empty());
+        assert!(ores.iter().all(|pos| pos.y <= 0));
+
+        let flowers = find(TestBlock::Flower);
+        assert!(!flowers.is_empty());
+        assert!(flowers
+            .iter()
+            .all(|pos| world.get_block_data(*pos - IVec3::Y) == TestBlock::Grass));
+    }
+}
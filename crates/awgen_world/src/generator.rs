@@ -0,0 +1,517 @@
+//! The world generation pipeline, which builds the contents of new chunks from
+//! a list of composable generation stages.
+//!
+//! Each stage belongs to a [`GenerationPhase`], and stages are applied in phase
+//! order: terrain shape, surface, caves, structures, and finally population.
+//! Mods and scripts may add their own stages to a [`WorldGenerator`] without
+//! replacing the stages that already exist.
+//!
+//! Stages operate on a [`ChunkView`], which covers the chunk being generated
+//! plus a margin of blocks around it. This allows features, such as trees, to
+//! cross chunk boundaries. As the margin of a neighbouring chunk is generated
+//! independently, stages must only depend on the world seed and block
+//! positions, and never on the chunk that is being generated.
+//!
+//! Features that are too large for a margin, such as trees, may instead place
+//! blocks beyond the chunk being generated as pending blocks. The world
+//! generator stores these until the chunk they belong to is generated, or
+//! writes them directly into that chunk if it has already been generated.
+
+
+use crate::prelude::{ChunkState, LoadChunkEvent, VoxelChunkStates, VoxelWorld};
+use awgen_math::prelude::{block_to_chunk, chunk_blocks, Region, Region2, Seed};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::fmt;
+
+
+/// The phases of world generation, in the order that they are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GenerationPhase {
+    /// Shapes the base terrain, such as hills and oceans.
+    TerrainShape,
+
+    /// Replaces the top layers of the terrain, such as with grass and dirt.
+    Surface,
+
+    /// Carves caves and ravines out of the terrain.
+    Caves,
+
+    /// Places large structures, such as villages and dungeons.
+    Structures,
+
+    /// Places small decorations, such as ores, trees, and flowers.
+    Population,
+}
+
+
+/// A single stage of the world generation pipeline.
+pub trait GenerationStage<BlockData>: Send + Sync
+where BlockData: Default + Copy + Send + Sync + 'static {
+    /// Gets the unique name of this stage, such as "terrain" or "oak_trees".
+    fn name(&self) -> &str;
+
+
+    /// Gets the phase of world generation that this stage belongs to.
+    fn phase(&self) -> GenerationPhase;
+
+
+    /// Gets the number of blocks beyond its [`ChunkView::area`] that this
+    /// stage may read from or write to. All earlier stages are applied to a
+    /// larger area, so that this margin has been generated before this stage
+    /// is applied.
+    fn margin(&self) -> i32 {
+        0
+    }
+
+
+    /// Applies this stage to the area of the given chunk view.
+    fn generate(&self, view: &mut ChunkView<BlockData>);
+}
+
+
+/// A view of the blocks within a chunk that is being generated, including a
+/// margin of blocks around the chunk.
+#[derive(Debug, Clone)]
+pub struct ChunkView<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static {
+    /// The coordinates of the chunk that is being generated.
+    chunk_coords: IVec3,
+
+    /// The seed of the world.
+    seed: Seed,
+
+    /// The region of all blocks within this view.
+    bounds: Region,
+
+    /// The region of blocks that the current stage should generate.
+    area: Region,
+
+    /// The block data of all blocks within this view, ordered by their index
+    /// within the view bounds.
+    blocks: Vec<BlockData>,
+
+    /// The height of the terrain surface within each column of this view, if
+    /// known, ordered by their index within the view bounds.
+    heightmap: Vec<Option<i32>>,
+
+    /// The feature blocks that were placed outside of the chunk that is being
+    /// generated.
+    pending: Vec<(IVec3, BlockData)>,
+}
+
+impl<BlockData> ChunkView<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    /// Creates a new, empty chunk view for the given chunk, including the
+    /// given margin of blocks around it.
+    fn new(chunk_coords: IVec3, seed: Seed, margin: i32) -> Self {
+        let bounds = chunk_blocks(chunk_coords).expand(IVec3::splat(margin));
+        Self {
+            chunk_coords,
+            seed,
+            bounds,
+            area: bounds,
+            blocks: vec![default(); bounds.count()],
+            heightmap: vec![None; Region2::from(bounds).count()],
+            pending: vec![],
+        }
+    }
+
+
+    /// Gets the coordinates of the chunk that is being generated.
+    pub fn chunk_coords(&self) -> IVec3 {
+        self.chunk_coords
+    }
+
+
+    /// Gets the seed of the world.
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+
+    /// Gets the region of all blocks within this view. Blocks outside of the
+    /// area of the current stage may be read from and written to, up to the
+    /// margin of that stage.
+    pub fn bounds(&self) -> Region {
+        self.bounds
+    }
+
+
+    /// Gets the region of blocks that the current stage should generate. This
+    /// is the chunk being generated, expanded by the margins of all later
+    /// stages.
+    pub fn area(&self) -> Region {
+        self.area
+    }
+
+
+    /// Gets the block data at the given block position. If the position is
+    /// outside of the bounds of this view, the default block data is returned.
+    pub fn get_block(&self, block_pos: IVec3) -> BlockData {
+        self.bounds
+            .get_index(block_pos)
+            .map_or_else(default, |index| self.blocks[index])
+    }
+
+
+    /// Sets the block data at the given block position. If the position is
+    /// outside of the bounds of this view, nothing happens.
+    pub fn set_block(&mut self, block_pos: IVec3, data: BlockData) {
+        if let Some(index) = self.bounds.get_index(block_pos) {
+            self.blocks[index] = data;
+        }
+    }
+
+
+    /// Places a block of a feature that may extend beyond the chunk that is
+    /// being generated, such as the leaves of a tree.
+    ///
+    /// Blocks within the chunk are set directly. Blocks outside of the chunk
+    /// are set within this view, if they are within its bounds, and are also
+    /// recorded as pending blocks. Pending blocks are written into their own
+    /// chunk when it is generated, but only replace default block data, so
+    /// they never overwrite terrain.
+    pub fn place_feature_block(&mut self, block_pos: IVec3, data: BlockData) {
+        self.set_block(block_pos, data);
+
+        if block_to_chunk(block_pos) != self.chunk_coords {
+            self.pending.push((block_pos, data));
+        }
+    }
+
+
+    /// Gets the height of the terrain surface within the given column, which is
+    /// the y coordinate of the topmost terrain block. The surface height may be
+    /// above or below the bounds of this view.
+    ///
+    /// If the column is outside of the bounds of this view, or no terrain
+    /// stage has set the surface height of the column, `None` is returned.
+    pub fn surface_height(&self, column: IVec2) -> Option<i32> {
+        Region2::from(self.bounds)
+            .get_index(column)
+            .and_then(|index| self.heightmap[index])
+    }
+
+
+    /// Sets the height of the terrain surface within the given column. This is
+    /// used by terrain shape stages so that later stages, such as caves, can
+    /// find the surface without scanning the column. If the column is outside
+    /// of the bounds of this view, nothing happens.
+    pub fn set_surface_height(&mut self, column: IVec2, height: i32) {
+        if let Some(index) = Region2::from(self.bounds).get_index(column) {
+            self.heightmap[index] = Some(height);
+        }
+    }
+
+
+    /// Consumes this view, returning the generated contents of the chunk.
+    fn finish(self) -> GeneratedChunk<BlockData> {
+        let blocks =
+            chunk_blocks(self.chunk_coords).iter().map(|pos| self.get_block(pos)).collect();
+        GeneratedChunk {
+            chunk_coords: self.chunk_coords,
+            blocks,
+            pending: self.pending,
+        }
+    }
+}
+
+
+/// The generated contents of a single chunk.
+#[derive(Debug, Clone)]
+pub struct GeneratedChunk<BlockData> {
+    /// The coordinates of the generated chunk.
+    pub chunk_coords: IVec3,
+
+    /// The block data of the chunk, ordered by their index within the chunk
+    /// region.
+    pub blocks: Vec<BlockData>,
+
+    /// The feature blocks that were placed outside of the chunk, which belong
+    /// to neighbouring chunks.
+    pub pending: Vec<(IVec3, BlockData)>,
+}
+
+
+/// The world generator of a voxel world, which is used to generate the block
+/// data of each chunk when it is loaded for the first time.
+#[derive(Component)]
+pub struct WorldGenerator<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static {
+    /// The seed of the world.
+    seed: Seed,
+
+    /// The generation stages, sorted by phase. Stages within the same phase
+    /// are applied in the order that they were added.
+    stages: Vec<Box<dyn GenerationStage<BlockData>>>,
+
+    /// The pending feature blocks of chunks that have not been generated yet,
+    /// indexed by chunk coordinates.
+    pending: HashMap<IVec3, Vec<(IVec3, BlockData)>>,
+}
+
+impl<BlockData> fmt::Debug for WorldGenerator<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorldGenerator")
+            .field("seed", &self.seed)
+            .field("stages", &self.stage_names().collect::<Vec<_>>())
+            .field("pending_chunks", &self.pending.len())
+            .finish()
+    }
+}
+
+impl<BlockData> WorldGenerator<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    /// Creates a new world generator with the given seed and no stages. Chunks
+    /// generated by this generator are left empty until stages are added.
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            seed,
+            stages: vec![],
+            pending: default(),
+        }
+    }
+
+
+    /// Adds the given stage to this world generator.
+    pub fn with_stage<S>(mut self, stage: S) -> Self
+    where S: GenerationStage<BlockData> + 'static {
+        self.add_stage(stage);
+        self
+    }
+
+
+    /// Adds the given stage to this world generator. The stage is applied after
+    /// all stages of earlier phases, and after all existing stages within the
+    /// same phase.
+    pub fn add_stage<S>(&mut self, stage: S)
+    where S: GenerationStage<BlockData> + 'static {
+        let index = self.stages.partition_point(|s| s.phase() <= stage.phase());
+        self.stages.insert(index, Box::new(stage));
+    }
+
+
+    /// Removes the stage with the given name from this world generator.
+    ///
+    /// Returns false if no stage with the given name exists.
+    pub fn remove_stage(&mut self, name: &str) -> bool {
+        let len = self.stages.len();
+        self.stages.retain(|stage| stage.name() != name);
+        self.stages.len() != len
+    }
+
+
+    /// Gets an iterator over the names of all stages within this world
+    /// generator, in the order that they are applied.
+    pub fn stage_names(&self) -> impl Iterator<Item = &str> {
+        self.stages.iter().map(|stage| stage.name())
+    }
+
+
+    /// Gets the seed of the world.
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+
+    /// Generates the contents of the chunk at the given chunk coordinates.
+    ///
+    /// This does not include any pending blocks that were placed within the
+    /// chunk by its neighbours. Use [`WorldGenerator::generate_into`] to
+    /// generate a chunk within a world.
+    pub fn generate_chunk(&self, chunk_coords: IVec3) -> GeneratedChunk<BlockData> {
+        let margin = self.stages.iter().map(|stage| stage.margin()).sum();
+        let mut view = ChunkView::new(chunk_coords, self.seed, margin);
+
+        let mut remaining = margin;
+        for stage in self.stages.iter() {
+            remaining -= stage.margin();
+            view.area = chunk_blocks(chunk_coords).expand(IVec3::splat(remaining));
+
+            #[cfg(feature = "profiling")]
+            let _span = info_span!("generation_stage", stage = stage.name()).entered();
+
+            stage.generate(&mut view);
+        }
+
+        view.finish()
+    }
+
+
+    /// Generates the chunk at the given chunk coordinates and writes it into
+    /// the given world.
+    ///
+    /// Pending blocks that neighbouring chunks placed within this chunk are
+    /// applied to it. Pending blocks that this chunk placed within neighbouring
+    /// chunks are written into the world if that chunk is already loaded, or
+    /// stored until that chunk is generated otherwise. Pending blocks only
+    /// replace default block data.
+    pub fn generate_into(
+        &mut self,
+        chunk_coords: IVec3,
+        world: &mut VoxelWorld<BlockData>,
+        states: &VoxelChunkStates,
+    ) where
+        BlockData: PartialEq,
+    {
+        let air = BlockData::default();
+        let region = chunk_blocks(chunk_coords);
+        let mut chunk = self.generate_chunk(chunk_coords);
+
+        for (block_pos, data) in self.pending.remove(&chunk_coords).unwrap_or_default() {
+            let Some(index) = region.get_index(block_pos) else {
+                continue;
+            };
+
+            if chunk.blocks[index] == air {
+                chunk.blocks[index] = data;
+            }
+        }
+
+        world.set_block_region(region, &chunk.blocks);
+
+        for (block_pos, data) in chunk.pending {
+            let target = block_to_chunk(block_pos);
+            if states.get_state(target) != ChunkState::Loaded {
+                self.pending.entry(target).or_default().push((block_pos, data));
+            } else if world.get_block_data(block_pos) == air {
+                world.set_block_data(block_pos, data);
+            }
+        }
+    }
+
+
+    /// Gets the number of chunks that have pending blocks waiting for them to
+    /// be generated.
+    pub fn pending_chunks(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+
+/// Generates the block data of each requested chunk within worlds that have a
+/// world generator, and marks the chunk as loaded.
+pub fn generate_chunks<BlockData>(
+    mut load_chunk_ev: EventReader<LoadChunkEvent>,
+    mut worlds: Query<(
+        &mut WorldGenerator<BlockData>,
+        &mut VoxelWorld<BlockData>,
+        &mut VoxelChunkStates,
+    )>,
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("chunk_generation", stage = "generate_chunks").entered();
+
+    for ev in load_chunk_ev.iter() {
+        let Ok((mut generator, mut world, mut states)) = worlds.get_mut(ev.world) else {
+            continue;
+        };
+
+        generator.generate_into(ev.chunk_coords, &mut world, &states);
+        states.set_state(ev.chunk_coords, ChunkState::Loaded);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    /// Fills every block below y = 0 with stone.
+    struct Ground;
+
+    impl GenerationStage<u8> for Ground {
+        fn name(&self) -> &str {
+            "ground"
+        }
+
+
+        fn phase(&self) -> GenerationPhase {
+            GenerationPhase::TerrainShape
+        }
+
+
+        fn generate(&self, view: &mut ChunkView<u8>) {
+            for pos in view.area().iter().filter(|pos| pos.y < 0) {
+                view.set_block(pos, 1);
+            }
+        }
+    }
+
+
+    /// Places a pillar on top of the ground at every 5th x and z coordinate,
+    /// with a 3x3 cap that may cross chunk boundaries.
+    struct Pillars;
+
+    impl GenerationStage<u8> for Pillars {
+        fn name(&self) -> &str {
+            "pillars"
+        }
+
+
+        fn phase(&self) -> GenerationPhase {
+            GenerationPhase::Population
+        }
+
+
+        fn margin(&self) -> i32 {
+            1
+        }
+
+
+        fn generate(&self, view: &mut ChunkView<u8>) {
+            let area = view.area().expand(IVec3::ONE);
+            for pos in area.iter().filter(|p| p.x % 5 == 0 && p.z % 5 == 0 && p.y == 0) {
+                if view.get_block(pos - IVec3::Y) != 1 {
+                    continue;
+                }
+
+                view.set_block(pos, 2);
+                for cap in Region::from_points(pos + IVec3::new(-1, 1, -1), pos + 1).iter() {
+                    view.set_block(cap, 3);
+                }
+            }
+        }
+    }
+
+
+    #[test]
+    fn stage_order() {
+        let mut generator = WorldGenerator::<u8>::new(Seed(1)).with_stage(Pillars);
+        generator.add_stage(Ground);
+        assert_eq!(generator.stage_names().collect::<Vec<_>>(), vec![
+            "ground", "pillars"
+        ]);
+
+        assert!(generator.remove_stage("pillars"));
+        assert!(!generator.remove_stage("pillars"));
+        assert_eq!(generator.stage_names().collect::<Vec<_>>(), vec!["ground"]);
+    }
+
+
+    #[test]
+    fn features_cross_chunks() {
+        let generator = WorldGenerator::<u8>::new(Seed(1)).with_stage(Pillars).with_stage(Ground);
+
+        let mut world = VoxelWorld::<u8>::default();
+        for chunk_coords in Region::from_points(IVec3::new(-1, -1, 0), IVec3::new(0, 0, 0)).iter() {
+            let chunk = generator.generate_chunk(chunk_coords);
+            world.set_block_region(chunk_blocks(chunk_coords), &chunk.blocks);
+        }
+
+        assert_eq!(world.get_block_data(IVec3::new(0, -1, 5)), 1);
+        assert_eq!(world.get_block_data(IVec3::new(0, 0, 5)), 2);
+        assert_eq!(world.get_block_data(IVec3::new(-1, 1, 5)), 3);
+        assert_eq!(world.get_block_data(IVec3::new(1, 1, 6)), 3);
+        assert_eq!(world.get_block_data(IVec3::new(2, 1, 5)), 0);
+    }
+}
@@ -0,0 +1,275 @@
+//! Block interactions, which allow entities to use a block, such as opening a
+//! door, toggling a lever, or opening a container.
+//!
+//! Using a block is distinct from placing a block against it. Interaction
+//! handlers are registered for specific block data values within the
+//! [`BlockInteractions`] resource, and are executed when a [`BlockUseEvent`] is
+//! received. Requests from players should be validated before the event is
+//! sent, as handlers trust the event completely.
+
+
+use crate::prelude::VoxelWorld;
+use awgen_math::prelude::Direction;
+use bevy::prelude::*;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+
+/// The result of using a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InteractionOutcome {
+    /// The block has no interaction, and the use is passed on to other
+    /// handlers, such as block placement.
+    Pass,
+
+    /// The block was used and may have been changed.
+    Used,
+
+    /// The block was used and requests that the user opens the container
+    /// interface of the block.
+    OpenContainer,
+}
+
+
+/// The state that is provided to an interaction handler when a block is used.
+pub struct BlockUseContext<'a, BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static {
+    /// The voxel world that contains the block. Handlers may modify any block
+    /// within this world.
+    pub world: &'a mut VoxelWorld<BlockData>,
+
+    /// The entity that is using the block.
+    pub user: Entity,
+
+    /// The position of the block that is being used.
+    pub block_pos: IVec3,
+
+    /// The face of the block that was targeted.
+    pub face: Direction,
+
+    /// The current data of the block that is being used.
+    pub block: BlockData,
+}
+
+
+/// A function that is called when a block is used.
+pub type InteractionHandler<BlockData> =
+    Box<dyn Fn(&mut BlockUseContext<BlockData>) -> InteractionOutcome + Send + Sync>;
+
+
+/// A registry of the interaction handlers for each block data value that may
+/// be used.
+#[derive(Resource)]
+pub struct BlockInteractions<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static {
+    /// The registered block values and their handlers.
+    handlers: Vec<(BlockData, InteractionHandler<BlockData>)>,
+}
+
+impl<BlockData> BlockInteractions<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    /// Registers an interaction handler for the given block data value. If a
+    /// handler was already registered for the block, it is replaced.
+    pub fn register<F>(&mut self, block: BlockData, handler: F)
+    where F: Fn(&mut BlockUseContext<BlockData>) -> InteractionOutcome + Send + Sync + 'static {
+        self.handlers.retain(|(registered, _)| *registered != block);
+        self.handlers.push((block, Box::new(handler)));
+    }
+
+
+    /// Gets whether or not the given block data value has an interaction
+    /// handler.
+    pub fn contains(&self, block: BlockData) -> bool {
+        self.handlers.iter().any(|(registered, _)| *registered == block)
+    }
+
+
+    /// Uses the block within the given context, returning the outcome of its
+    /// handler. Blocks without a handler always pass.
+    pub fn use_block(&self, context: &mut BlockUseContext<BlockData>) -> InteractionOutcome {
+        self.handlers
+            .iter()
+            .find(|(registered, _)| *registered == context.block)
+            .map_or(InteractionOutcome::Pass, |(_, handler)| handler(context))
+    }
+}
+
+impl<BlockData> Default for BlockInteractions<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    fn default() -> Self {
+        Self {
+            handlers: vec![],
+        }
+    }
+}
+
+impl<BlockData> Debug for BlockInteractions<BlockData>
+where BlockData: Default + Copy + Debug + Send + Sync + 'static
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockInteractions")
+            .field(
+                "blocks",
+                &self.handlers.iter().map(|(block, _)| block).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+
+/// An event that is triggered to use a block within a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockUseEvent {
+    /// The world entity that contains the block.
+    pub world: Entity,
+
+    /// The entity that is using the block.
+    pub user: Entity,
+
+    /// The position of the block to use.
+    pub block_pos: IVec3,
+
+    /// The face of the block that was targeted.
+    pub face: Direction,
+}
+
+
+/// An event that is triggered after a block has been used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockUsedEvent {
+    /// The world entity that contains the block.
+    pub world: Entity,
+
+    /// The entity that used the block.
+    pub user: Entity,
+
+    /// The position of the block that was used.
+    pub block_pos: IVec3,
+
+    /// The outcome of using the block.
+    pub outcome: InteractionOutcome,
+}
+
+
+/// Executes the interaction handler of each block that is used, and reports
+/// the outcome of each use.
+pub fn handle_block_use<BlockData>(
+    mut use_ev: EventReader<BlockUseEvent>,
+    mut used_ev: EventWriter<BlockUsedEvent>,
+    interactions: Res<BlockInteractions<BlockData>>,
+    mut worlds: Query<&mut VoxelWorld<BlockData>>,
+) where
+    BlockData: Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    for ev in use_ev.iter() {
+        let Ok(mut world) = worlds.get_mut(ev.world) else {
+            continue;
+        };
+
+        let block = world.get_block_data(ev.block_pos);
+        let mut context = BlockUseContext {
+            world: &mut world,
+            user: ev.user,
+            block_pos: ev.block_pos,
+            face: ev.face,
+            block,
+        };
+
+        used_ev.send(BlockUsedEvent {
+            world:     ev.world,
+            user:      ev.user,
+            block_pos: ev.block_pos,
+            outcome:   interactions.use_block(&mut context),
+        });
+    }
+}
+
+
+/// A mini extension plugin that allows blocks of the given type to be used
+/// through their registered interaction handlers.
+#[derive(Debug, Clone, Default)]
+pub struct InteractionPlugin<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for InteractionPlugin<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockInteractions<BlockData>>()
+            .add_event::<BlockUseEvent>()
+            .add_event::<BlockUsedEvent>()
+            .add_system(handle_block_use::<BlockData>);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Stone,
+        LeverOff,
+        LeverOn,
+        Chest,
+    }
+
+
+    #[test]
+    fn use_registered_blocks() {
+        let mut app = App::new();
+        app.add_plugin(InteractionPlugin::<TestBlock>::default());
+
+        let mut interactions = app.world.resource_mut::<BlockInteractions<TestBlock>>();
+        interactions.register(TestBlock::LeverOff, |ctx| {
+            ctx.world.set_block_data(ctx.block_pos, TestBlock::LeverOn);
+            InteractionOutcome::Used
+        });
+        interactions.register(TestBlock::Chest, |_| InteractionOutcome::OpenContainer);
+        assert!(interactions.contains(TestBlock::LeverOff));
+        assert!(!interactions.contains(TestBlock::Stone));
+
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::new(0, 0, 0), TestBlock::LeverOff);
+        world.set_block_data(IVec3::new(1, 0, 0), TestBlock::Stone);
+        world.set_block_data(IVec3::new(2, 0, 0), TestBlock::Chest);
+        let world = app.world.spawn(world).id();
+        let user = app.world.spawn_empty().id();
+
+        let mut use_ev = app.world.resource_mut::<Events<BlockUseEvent>>();
+        for x in 0..3 {
+            use_ev.send(BlockUseEvent {
+                world,
+                user,
+                block_pos: IVec3::new(x, 0, 0),
+                face: Direction::PosY,
+            });
+        }
+        app.update();
+
+        let voxels = app.world.get::<VoxelWorld<TestBlock>>(world).unwrap();
+        assert_eq!(voxels.get_block_data(IVec3::ZERO), TestBlock::LeverOn);
+
+        let used_ev = app.world.resource::<Events<BlockUsedEvent>>();
+        let outcomes: Vec<_> = used_ev
+            .get_reader()
+            .iter(used_ev)
+            .map(|ev| (ev.block_pos.x, ev.outcome))
+            .collect();
+        assert_eq!(outcomes, vec![
+            (0, InteractionOutcome::Used),
+            (1, InteractionOutcome::Pass),
+            (2, InteractionOutcome::OpenContainer),
+        ]);
+    }
+}
@@ -6,18 +6,55 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+pub mod breaking;
+pub mod caves;
+pub mod container;
+pub mod editing;
+pub mod entity_caps;
+pub mod entity_index;
+pub mod explosion;
+pub mod features;
+pub mod generator;
+pub mod interaction;
+pub mod map;
+pub mod persistence;
 pub mod populator;
+pub mod signal;
+pub mod spawn;
+pub mod spawn_queue;
+pub mod tags;
+pub mod terrain;
+pub mod trees;
 pub mod world;
 
 
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
+    pub use super::breaking::*;
+    pub use super::caves::*;
+    pub use super::container::*;
+    pub use super::editing::*;
+    pub use super::entity_caps::*;
+    pub use super::entity_index::*;
+    pub use super::explosion::*;
+    pub use super::features::*;
+    pub use super::generator::*;
+    pub use super::interaction::*;
+    pub use super::map::*;
+    pub use super::persistence::*;
     pub use super::populator::*;
+    pub use super::signal::*;
+    pub use super::spawn::*;
+    pub use super::spawn_queue::*;
+    pub use super::tags::*;
+    pub use super::terrain::*;
+    pub use super::trees::*;
     pub use super::world::*;
     pub use super::*;
 }
 
 
+use awgen_physics::prelude::{run_while_connected, AwgenSystemExt, AwgenSystemOrdering, Position};
 use bevy::prelude::*;
 use prelude::*;
 use std::marker::PhantomData;
@@ -33,9 +70,39 @@ pub struct WorldDataPlugin;
 impl Plugin for WorldDataPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<ChunkAnchor>()
+            .register_type::<AnchorShape>()
+            .register_type::<InWorld>()
             .register_type::<VoxelChunkStates>()
+            .register_type::<Persistent>()
+            .register_type::<Tag>()
+            .register_type::<Tags>()
+            .register_persistent_component::<Position>()
+            .init_resource::<ChunkLoadBudget>()
+            .init_resource::<ChunkUnloadDelay>()
+            .init_resource::<SpawnQueue>()
+            .init_resource::<TagRegistry>()
             .add_event::<LoadChunkEvent>()
-            .add_system(load_chunks);
+            .add_event::<UnloadChunkEvent>()
+            .add_system(
+                load_chunks
+                    .with_run_criteria(run_while_connected)
+                    .in_awgen_set(AwgenSystemOrdering::ChunkLoading),
+            )
+            .add_system(
+                unload_chunks
+                    .with_run_criteria(run_while_connected)
+                    .in_awgen_set(AwgenSystemOrdering::ChunkLoading)
+                    .after(load_chunks),
+            )
+            .add_system(
+                finish_world_loading
+                    .in_awgen_set(AwgenSystemOrdering::ChunkLoading)
+                    .after(unload_chunks),
+            )
+            .add_system(apply_spawn_queue)
+            .add_system(insert_chunk_entity_indices)
+            .add_system(update_chunk_entity_indices.after(insert_chunk_entity_indices))
+            .add_system(index_tags);
     }
 }
 
@@ -50,9 +117,37 @@ where BlockData: Default + Copy + Send + Sync + 'static {
 }
 
 impl<BlockData> Plugin for WorldDataTypePlugin<BlockData>
-where BlockData: Default + Copy + Send + Sync + 'static
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
 {
     fn build(&self, app: &mut App) {
-        app.register_type::<VoxelWorld<BlockData>>();
+        app.register_type::<VoxelWorld<BlockData>>()
+            .add_system(
+                prune_chunks::<BlockData>
+                    .in_awgen_set(AwgenSystemOrdering::ChunkGeneration)
+                    .before(generate_chunks::<BlockData>),
+            )
+            .add_system(
+                generate_chunks::<BlockData>.in_awgen_set(AwgenSystemOrdering::ChunkGeneration),
+            );
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_physics::prelude::{AmbiguityAudit, PhysicsPlugin};
+    use bevy::time::TimePlugin;
+
+    #[test]
+    fn world_systems_unambiguous() {
+        let mut app = App::new();
+        app.add_plugin(TimePlugin)
+            .add_plugin(PhysicsPlugin::new(20.0))
+            .add_plugin(WorldDataPlugin)
+            .add_plugin(WorldDataTypePlugin::<u16>::default());
+        app.update();
+
+        AmbiguityAudit::default().assert_none(&app);
     }
 }
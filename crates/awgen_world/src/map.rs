@@ -0,0 +1,266 @@
+//! Renders top-down map images of a voxel world, coloring each column by its
+//! topmost visible block and shading it by height, which is useful for sharing
+//! maps and debugging world generation.
+
+
+use crate::prelude::VoxelWorld;
+use anyhow::{bail, Context, Result};
+use awgen_math::prelude::{chunk_to_block, Region2, CHUNK_SIZE};
+use bevy::prelude::*;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+
+/// The largest width or height, in pixels, of a rendered map image.
+pub const MAX_MAP_SIZE: u32 = 8192;
+
+
+/// The brightness of the lowest surface within a map, relative to the highest
+/// surface.
+const MIN_HEIGHT_BRIGHTNESS: f32 = 0.55;
+
+
+/// The change in brightness of a column that is higher or lower than the
+/// column to its north, which gives the map a sense of relief.
+const RELIEF_SHADING: f32 = 0.12;
+
+
+/// Describes how a block appears on a top-down map.
+pub trait BlockMapColor {
+    /// Gets the color of this block on a map, as 8-bit RGB values. Blocks that
+    /// return `None` are see-through, and the block beneath them is shown
+    /// instead.
+    fn map_color(&self) -> Option<[u8; 3]>;
+}
+
+
+/// A rendered map image, stored as 8-bit RGBA pixels in row order. Columns
+/// without any visible blocks are fully transparent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapImage {
+    /// The width of the image, in pixels.
+    width: u32,
+
+    /// The height of the image, in pixels.
+    height: u32,
+
+    /// The RGBA values of each pixel.
+    pixels: Vec<u8>,
+}
+
+impl MapImage {
+    /// Gets the width of this image, in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+
+    /// Gets the height of this image, in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+
+    /// Gets the RGBA value of the pixel at the given coordinates.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * self.width + x) * 4) as usize;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[index..index + 4]);
+        pixel
+    }
+
+
+    /// Encodes this image as a PNG file, written to the given writer.
+    pub fn write_png<W>(&self, writer: W) -> Result<()>
+    where W: Write {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+
+
+    /// Saves this image as a PNG file at the given path, creating the parent
+    /// directory if needed.
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create map image: {}", path.display()))?;
+        self.write_png(BufWriter::new(file))
+    }
+}
+
+
+/// Gets the height and color of the topmost visible block within the given
+/// column, searching between the given heights.
+fn surface<BlockData>(
+    world: &VoxelWorld<BlockData>,
+    column: IVec2,
+    min_y: i32,
+    max_y: i32,
+) -> Option<(i32, [u8; 3])>
+where
+    BlockData: BlockMapColor + Default + Copy + Send + Sync + 'static,
+{
+    (min_y..=max_y).rev().find_map(|y| {
+        let block = world.get_block_data(IVec3::new(column.x, y, column.y));
+        block.map_color().map(|color| (y, color))
+    })
+}
+
+
+/// Renders a top-down map of the given columns of a voxel world, with each
+/// column drawn as a square of `scale` by `scale` pixels. North, towards
+/// negative Z, is at the top of the image.
+///
+/// Only chunks that exist within the world are searched for visible blocks.
+/// An error is returned if the image would be larger than [`MAX_MAP_SIZE`]
+/// along either axis.
+pub fn render_map<BlockData>(
+    world: &VoxelWorld<BlockData>,
+    columns: Region2,
+    scale: u32,
+) -> Result<MapImage>
+where
+    BlockData: BlockMapColor + Default + Copy + Send + Sync + 'static,
+{
+    let scale = scale.max(1);
+    let columns_size = columns.size().as_uvec2();
+    let size = match (
+        columns_size.x.checked_mul(scale),
+        columns_size.y.checked_mul(scale),
+    ) {
+        (Some(x), Some(y)) if x <= MAX_MAP_SIZE && y <= MAX_MAP_SIZE => UVec2::new(x, y),
+        _ => {
+            bail!(
+                "Map image of {}x{} columns at a scale of {scale} exceeds the maximum size of \
+                 {MAX_MAP_SIZE} pixels",
+                columns_size.x,
+                columns_size.y
+            )
+        },
+    };
+
+    let chunk_heights = world.chunk_versions().map(|(coords, _)| chunk_to_block(coords).y);
+    let (min_y, max_y) = chunk_heights.fold((i32::MAX, i32::MIN), |(min, max), y| {
+        (min.min(y), max.max(y + CHUNK_SIZE - 1))
+    });
+
+    let surfaces: Vec<Option<(i32, [u8; 3])>> = match min_y <= max_y {
+        true => columns.iter().map(|column| surface(world, column, min_y, max_y)).collect(),
+        false => vec![None; columns.count()],
+    };
+
+    let (low, high) =
+        surfaces.iter().flatten().fold((i32::MAX, i32::MIN), |(low, high), (y, _)| {
+            (low.min(*y), high.max(*y))
+        });
+
+    let mut pixels = vec![0; (size.x * size.y * 4) as usize];
+    for (index, column) in columns.iter().enumerate() {
+        let Some((y, color)) = surfaces[index] else {
+            continue;
+        };
+
+        let height = match high > low {
+            true => (y - low) as f32 / (high - low) as f32,
+            false => 1.0,
+        };
+        let mut brightness = MIN_HEIGHT_BRIGHTNESS + (1.0 - MIN_HEIGHT_BRIGHTNESS) * height;
+
+        let north = column - IVec2::Y;
+        if let Some(Some((north_y, _))) = columns.get_index(north).map(|i| surfaces[i]) {
+            brightness += match y.cmp(&north_y) {
+                std::cmp::Ordering::Greater => RELIEF_SHADING,
+                std::cmp::Ordering::Less => -RELIEF_SHADING,
+                std::cmp::Ordering::Equal => 0.0,
+            };
+        }
+
+        let shaded = color.map(|c| (c as f32 * brightness).round().clamp(0.0, 255.0) as u8);
+        let origin = (column - columns.min()).as_uvec2() * scale;
+        for py in origin.y..origin.y + scale {
+            for px in origin.x..origin.x + scale {
+                let i = ((py * size.x + px) * 4) as usize;
+                pixels[i..i + 3].copy_from_slice(&shaded);
+                pixels[i + 3] = 255;
+            }
+        }
+    }
+
+    Ok(MapImage {
+        width: size.x,
+        height: size.y,
+        pixels,
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    impl BlockMapColor for u8 {
+        fn map_color(&self) -> Option<[u8; 3]> {
+            match self {
+                0 => None,
+                _ => Some([200, 100, 50]),
+            }
+        }
+    }
+
+
+    #[test]
+    fn render_surface() {
+        let mut world = VoxelWorld::<u8>::default();
+        world.set_block_data(IVec3::new(0, 0, 0), 1);
+        world.set_block_data(IVec3::new(1, 0, 0), 1);
+        world.set_block_data(IVec3::new(1, 4, 0), 1);
+
+        let columns = Region2::from_points(IVec2::new(0, 0), IVec2::new(2, 0));
+        let map = render_map(&world, columns, 2).unwrap();
+
+        assert_eq!(map.width(), 6);
+        assert_eq!(map.height(), 2);
+        assert_eq!(map.pixel(0, 0), [110, 55, 28, 255]);
+        assert_eq!(map.pixel(3, 1), [200, 100, 50, 255]);
+        assert_eq!(map.pixel(4, 0), [0, 0, 0, 0]);
+    }
+
+
+    #[test]
+    fn reject_oversized_map() {
+        let world = VoxelWorld::<u8>::default();
+        let columns = Region2::from_size(IVec2::ZERO, IVec2::new(2048, 16));
+        assert!(render_map(&world, columns, 8).is_err());
+    }
+
+
+    #[test]
+    fn reject_overflowing_scale() {
+        let world = VoxelWorld::<u8>::default();
+        assert!(render_map(&world, Region2::CHUNK, 268_435_456).is_err());
+        assert!(render_map(&world, Region2::CHUNK, u32::MAX).is_err());
+    }
+
+
+    #[test]
+    fn encode_png() {
+        let mut world = VoxelWorld::<u8>::default();
+        world.set_block_data(IVec3::ZERO, 1);
+
+        let map = render_map(&world, Region2::CHUNK, 1).unwrap();
+        let mut bytes = vec![];
+        map.write_png(&mut bytes).unwrap();
+        assert_eq!(&bytes[1..4], b"PNG");
+    }
+}
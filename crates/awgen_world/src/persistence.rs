@@ -0,0 +1,102 @@
+//! Allows for components to be marked as persistent, so that non-player
+//! entities, such as dropped items and mobs, can be saved alongside the chunk
+//! they are within and restored when that chunk is loaded again.
+
+
+use bevy::prelude::*;
+use bevy::reflect::GetTypeRegistration;
+use std::any::TypeId;
+
+
+/// A marker component for a non-player entity that is saved alongside the
+/// chunk that it is within.
+///
+/// Only the components that have been registered as persistent are saved. This
+/// component relies on the Position and InWorld components in order to
+/// function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Persistent;
+
+
+/// The registry of component types that are saved for persistent entities.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PersistentComponents {
+    /// The type ids of all persistent component types, in registration order.
+    types: Vec<TypeId>,
+}
+
+impl PersistentComponents {
+    /// Marks the given component type as persistent.
+    pub fn register<C: Component>(&mut self) {
+        let type_id = TypeId::of::<C>();
+        if !self.types.contains(&type_id) {
+            self.types.push(type_id);
+        }
+    }
+
+
+    /// Gets whether or not the component type with the given type id is
+    /// persistent.
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.types.contains(&type_id)
+    }
+
+
+    /// Gets an iterator over the type ids of all persistent component types.
+    pub fn iter(&self) -> impl Iterator<Item = TypeId> + '_ {
+        self.types.iter().copied()
+    }
+}
+
+
+/// An extension trait for apps that allows for component types to be marked as
+/// persistent.
+pub trait PersistentComponentExt {
+    /// Registers the given component type for reflection, and marks it as
+    /// persistent.
+    ///
+    /// The component type must reflect the `Component` trait, using
+    /// `#[reflect(Component)]`, in order to be saved and restored.
+    fn register_persistent_component<C>(&mut self) -> &mut Self
+    where C: Component + Reflect + GetTypeRegistration;
+}
+
+impl PersistentComponentExt for App {
+    fn register_persistent_component<C>(&mut self) -> &mut Self
+    where C: Component + Reflect + GetTypeRegistration {
+        self.register_type::<C>();
+        self.world
+            .get_resource_or_insert_with(PersistentComponents::default)
+            .register::<C>();
+        self
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_physics::prelude::Position;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn register_components() {
+        let mut app = App::new();
+        app.register_persistent_component::<Position>()
+            .register_persistent_component::<Persistent>()
+            .register_persistent_component::<Position>();
+
+        let persistent = app.world.resource::<PersistentComponents>();
+        assert_eq!(
+            persistent.iter().collect::<Vec<_>>(),
+            vec![TypeId::of::<Position>(), TypeId::of::<Persistent>()]
+        );
+        assert!(!persistent.contains(TypeId::of::<Transform>()));
+
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        let registration = registry.get(TypeId::of::<Position>()).unwrap();
+        assert!(registration.data::<ReflectComponent>().is_some());
+    }
+}
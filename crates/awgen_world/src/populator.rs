@@ -2,14 +2,47 @@
 //! loading task) and chunk pruning (via chunk unloading).
 
 
-use awgen_math::region::Region;
-use awgen_physics::prelude::Position;
+use crate::prelude::VoxelWorld;
+use awgen_math::prelude::{
+    block_to_chunk, chunk_to_region, index_to_local, local_index, region_to_chunk, world_to_block, Region
+};
+use awgen_physics::prelude::{AppState, Position};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::cmp::Reverse;
+
+
+/// The default maximum number of chunks that may be requested to load within a
+/// single frame.
+pub const DEFAULT_CHUNK_LOAD_BUDGET: usize = 64;
+
+
+/// The default number of seconds that a chunk must remain out of range of all
+/// chunk anchors before it is unloaded.
+pub const DEFAULT_CHUNK_UNLOAD_DELAY: f32 = 10.0;
+
+
+/// The shape of the area of chunks that is kept loaded around a chunk anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, FromReflect, Default)]
+pub enum AnchorShape {
+    /// All chunks within a cube around the anchor are loaded.
+    Cube,
+
+    /// Only chunks within a sphere around the anchor are loaded. This loads
+    /// roughly 40% fewer chunks than a cube of the same radius.
+    #[default]
+    Sphere,
+}
 
 
 /// Defines an anchor within a world that forces a radius of chunks around
 /// itself to stay loaded.
 ///
+/// Any entity may act as a chunk anchor, such as a player, a mob spawner, or a
+/// script that needs an area to remain loaded, and a world may contain any
+/// number of anchors. When more chunks are requested than may be loaded within
+/// a single frame, anchors with a higher priority are served first.
+///
 /// This component relies on the Position component in order to function.
 #[derive(Debug, Clone, Reflect, Component, Default)]
 #[reflect(Component)]
@@ -29,6 +62,14 @@ pub struct ChunkAnchor {
     /// A value of 0 will only allow for a single chunk to be considered within
     /// range of this anchor.
     pub max_radius: u16,
+
+    /// The shape of the area of chunks that is loaded around this anchor.
+    pub shape: AnchorShape,
+
+    /// The loading priority of this anchor. Chunks around anchors with a
+    /// higher priority are loaded before chunks around anchors with a lower
+    /// priority.
+    pub priority: u32,
 }
 
 impl ChunkAnchor {
@@ -41,6 +82,100 @@ impl ChunkAnchor {
             world: Some(world),
             radius,
             max_radius,
+            shape: default(),
+            priority: 0,
+        }
+    }
+
+
+    /// Replaces the shape of the area of chunks that is loaded around this
+    /// anchor.
+    pub fn with_shape(mut self, shape: AnchorShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+
+    /// Replaces the loading priority of this anchor.
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+
+    /// Gets the coordinates of all chunks that should be loaded around this
+    /// anchor, when located at the given block position.
+    ///
+    /// Chunks are ordered from nearest to farthest from the anchor, so that the
+    /// nearest chunks are loaded first.
+    pub fn chunks(&self, block_pos: IVec3) -> Vec<IVec3> {
+        let pos = block_to_chunk(block_pos);
+        let radius = self.radius as i32;
+        let nearest = Region::from_points(pos - radius, pos + radius).iter_sorted_by_distance(pos);
+
+        match self.shape {
+            AnchorShape::Cube => nearest.collect(),
+            AnchorShape::Sphere => {
+                // Matches the radius used by `SphereIterator::chunks`.
+                let limit = (self.radius as f32 + 0.5).powi(2);
+                nearest
+                    .take_while(|chunk| (*chunk - pos).as_vec3().length_squared() <= limit)
+                    .collect()
+            },
+        }
+    }
+
+
+    /// Checks whether or not the chunk at the given chunk coordinates is within
+    /// the maximum radius of this anchor, when located at the given block
+    /// position.
+    pub fn in_range(&self, block_pos: IVec3, chunk_coords: IVec3) -> bool {
+        let offset = chunk_coords - block_to_chunk(block_pos);
+        let max_radius = self.max_radius as i32;
+
+        match self.shape {
+            AnchorShape::Cube => offset.abs().max_element() <= max_radius,
+            AnchorShape::Sphere => {
+                let limit = (self.max_radius as f32 + 0.5).powi(2);
+                offset.as_vec3().length_squared() <= limit
+            },
+        }
+    }
+}
+
+
+/// The maximum number of chunks that may be requested to load within a single
+/// frame, across all chunk anchors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct ChunkLoadBudget {
+    /// The maximum number of chunks to request per frame.
+    pub chunks_per_frame: usize,
+}
+
+impl Default for ChunkLoadBudget {
+    fn default() -> Self {
+        Self {
+            chunks_per_frame: DEFAULT_CHUNK_LOAD_BUDGET,
+        }
+    }
+}
+
+
+/// The number of seconds that a loaded chunk must remain out of range of all
+/// chunk anchors before it is unloaded.
+///
+/// This grace period prevents chunks from being unloaded and loaded again over
+/// and over while an anchor moves back and forth across a chunk boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct ChunkUnloadDelay {
+    /// The grace period, in seconds.
+    pub seconds: f32,
+}
+
+impl Default for ChunkUnloadDelay {
+    fn default() -> Self {
+        Self {
+            seconds: DEFAULT_CHUNK_UNLOAD_DELAY,
         }
     }
 }
@@ -53,13 +188,19 @@ pub struct VoxelChunkStates {
     /// A list of chunk regions within the world.
     #[reflect(ignore)]
     regions: Vec<VoxelChunkStateRegion>,
+
+    /// The loaded chunks that are out of range of all chunk anchors, alongside
+    /// the elapsed time, in seconds, at which they left the range of the last
+    /// anchor.
+    #[reflect(ignore)]
+    pending_unloads: HashMap<IVec3, f64>,
 }
 
 impl VoxelChunkStates {
     /// Gets the ChunkState for the chunk at the indicated chunk coordinates.
     pub fn get_state(&self, chunk_coords: IVec3) -> ChunkState {
-        let region_coords = chunk_coords >> 4;
-        let index = Region::CHUNK.point_to_index(chunk_coords & 15).unwrap();
+        let region_coords = chunk_to_region(chunk_coords);
+        let index = local_index(chunk_coords);
 
         self.regions
             .iter()
@@ -70,8 +211,8 @@ impl VoxelChunkStates {
 
     /// Changes the state of the chunk at the indicates chunk coordinates.
     pub fn set_state(&mut self, chunk_coords: IVec3, state: ChunkState) {
-        let region_coords = chunk_coords >> 4;
-        let index = Region::CHUNK.point_to_index(chunk_coords & 15).unwrap();
+        let region_coords = chunk_to_region(chunk_coords);
+        let index = local_index(chunk_coords);
 
         if let Some((reg_index, region)) = self
             .regions
@@ -90,6 +231,27 @@ impl VoxelChunkStates {
             self.regions.push(region);
         }
     }
+
+
+    /// Gets an iterator over the coordinates of all chunks that are currently
+    /// in the given state. This never includes unloaded chunks.
+    pub fn chunks_in_state(&self, state: ChunkState) -> impl Iterator<Item = IVec3> + '_ {
+        self.regions.iter().flat_map(move |region| {
+            region
+                .chunks
+                .iter()
+                .enumerate()
+                .filter(move |(_, chunk)| **chunk == state && state != ChunkState::Unloaded)
+                .map(|(index, _)| region_to_chunk(region.region_coords) + index_to_local(index))
+        })
+    }
+
+
+    /// Checks whether or not the chunk at the given chunk coordinates is
+    /// waiting out its grace period before being unloaded.
+    pub fn is_unload_pending(&self, chunk_coords: IVec3) -> bool {
+        self.pending_unloads.contains_key(&chunk_coords)
+    }
 }
 
 
@@ -155,57 +317,176 @@ pub struct UnloadChunkEvent {
 }
 
 
-/// Loads chunks around all current world anchors.
+/// Loads chunks around all current world anchors, up to the chunk load budget
+/// of each frame.
+///
+/// Anchors with a higher priority are served first. Anchors that share the
+/// same priority take turns requesting their nearest unloaded chunk, so that a
+/// single anchor cannot use up the entire budget while the others wait.
 pub fn load_chunks(
+    budget: Res<ChunkLoadBudget>,
     mut states: Query<&mut VoxelChunkStates>,
     anchors: Query<(&ChunkAnchor, &Position)>,
     mut load_chunk_ev: EventWriter<LoadChunkEvent>,
 ) {
-    for (anchor, pos) in anchors.iter() {
-        if let Some(world) = anchor.world {
-            let mut world_states = states.get_mut(world).unwrap();
-
-            let pos = pos.translation.as_ivec3() >> 4;
-            let min = pos - anchor.radius as i32;
-            let max = pos + anchor.radius as i32;
-            let region = Region::from_points(min, max);
-
-            for chunk in region.iter() {
-                let state = world_states.get_state(chunk);
-
-                if state == ChunkState::Unloaded {
-                    world_states.set_state(chunk, ChunkState::Loading);
-                    load_chunk_ev.send(LoadChunkEvent {
-                        chunk_coords: chunk,
-                        world,
-                    });
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("chunk_generation", stage = "load_chunks").entered();
+
+    let mut requests: Vec<_> = anchors
+        .iter()
+        .filter_map(|(anchor, pos)| {
+            let chunks = anchor.chunks(world_to_block(pos.translation));
+            Some((anchor.priority, anchor.world?, chunks.into_iter()))
+        })
+        .collect();
+    requests.sort_by_key(|(priority, ..)| Reverse(*priority));
+
+    let mut remaining = budget.chunks_per_frame;
+    while remaining > 0 && !requests.is_empty() {
+        let priority = requests[0].0;
+        let tier_len = requests.iter().take_while(|(p, ..)| *p == priority).count();
+        let mut tier: Vec<_> = requests.drain(..tier_len).collect();
+
+        while remaining > 0 && !tier.is_empty() {
+            tier.retain_mut(|(_, world, chunks)| {
+                if remaining == 0 {
+                    return true;
                 }
-            }
+
+                let Ok(mut world_states) = states.get_mut(*world) else {
+                    return false;
+                };
+
+                let Some(chunk) =
+                    chunks.find(|chunk| world_states.get_state(*chunk) == ChunkState::Unloaded)
+                else {
+                    return false;
+                };
+
+                world_states.set_state(chunk, ChunkState::Loading);
+                load_chunk_ev.send(LoadChunkEvent {
+                    chunk_coords: chunk,
+                    world:        *world,
+                });
+
+                remaining -= 1;
+                true
+            });
         }
     }
 }
 
 
-/// Unloads unused chunks based on current world anchors.
-#[allow(unused)]
+/// Moves the client from the loading state into the game once the chunk that
+/// each world anchor is standing within has finished loading.
+///
+/// This system does nothing if application states are not used, such as on a
+/// headless server.
+pub fn finish_world_loading(
+    state: Option<ResMut<State<AppState>>>,
+    worlds: Query<&VoxelChunkStates>,
+    anchors: Query<(&ChunkAnchor, &Position)>,
+) {
+    let Some(mut state) = state else {
+        return;
+    };
+
+    if *state.current() != AppState::LoadingWorld {
+        return;
+    }
+
+    let loaded = anchors.iter().all(|(anchor, pos)| {
+        let Some(world) = anchor.world.and_then(|world| worlds.get(world).ok()) else {
+            return true;
+        };
+
+        let chunk = block_to_chunk(world_to_block(pos.translation));
+        world.get_state(chunk) == ChunkState::Loaded
+    });
+
+    if loaded {
+        let _ = state.set(AppState::InGame);
+    }
+}
+
+
+/// Unloads loaded chunks that have been out of range of all chunk anchors for
+/// longer than the chunk unload delay.
+///
+/// A chunk that leaves the maximum radius of every anchor is only marked as
+/// pending. If it comes back within range of any anchor before the delay has
+/// passed, the pending unload is cancelled. Otherwise, the chunk is marked as
+/// unloaded and an [`UnloadChunkEvent`] is sent for it.
 pub fn unload_chunks(
-    mut states: Query<&mut VoxelChunkStates>,
+    time: Res<Time>,
+    delay: Res<ChunkUnloadDelay>,
+    mut states: Query<(Entity, &mut VoxelChunkStates)>,
     anchors: Query<(&ChunkAnchor, &Position)>,
     mut unload_chunk_ev: EventWriter<UnloadChunkEvent>,
 ) {
-    todo!();
+    let now = time.elapsed_seconds_f64();
+    let delay = delay.seconds as f64;
+
+    for (world, mut world_states) in states.iter_mut() {
+        let world_anchors: Vec<_> = anchors
+            .iter()
+            .filter(|(anchor, _)| anchor.world == Some(world))
+            .map(|(anchor, pos)| (anchor, world_to_block(pos.translation)))
+            .collect();
+
+        let mut pending = HashMap::default();
+        let mut expired = vec![];
+        for chunk in world_states.chunks_in_state(ChunkState::Loaded) {
+            if world_anchors.iter().any(|(anchor, pos)| anchor.in_range(*pos, chunk)) {
+                continue;
+            }
+
+            let since = world_states.pending_unloads.get(&chunk).copied().unwrap_or(now);
+            if now - since >= delay {
+                expired.push(chunk);
+            } else {
+                pending.insert(chunk, since);
+            }
+        }
+
+        world_states.pending_unloads = pending;
+        for chunk in expired {
+            world_states.set_state(chunk, ChunkState::Unloaded);
+            unload_chunk_ev.send(UnloadChunkEvent {
+                chunk_coords: chunk,
+                world,
+            });
+        }
+    }
+}
+
+
+/// Removes the block data of each unloaded chunk from its voxel world.
+pub fn prune_chunks<BlockData>(
+    mut unload_chunk_ev: EventReader<UnloadChunkEvent>,
+    mut worlds: Query<&mut VoxelWorld<BlockData>>,
+) where
+    BlockData: Default + Copy + Send + Sync + 'static,
+{
+    for ev in unload_chunk_ev.iter() {
+        if let Ok(mut world) = worlds.get_mut(ev.world) {
+            world.remove_chunk(ev.chunk_coords);
+        }
+    }
 }
 
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use awgen_math::prelude::SphereIterator;
     use pretty_assertions::assert_eq;
 
 
     #[test]
     fn load_nearby() {
         let mut app = App::new();
+        app.init_resource::<ChunkLoadBudget>();
         app.add_event::<LoadChunkEvent>();
         app.add_system(load_chunks);
 
@@ -215,7 +496,7 @@ mod test {
                 translation: Vec3::new(44.0, 2.1, -4.7), // Chunk Coords: (2, 0, -1)
                 ..default()
             },
-            ChunkAnchor::new(voxel_world, 1, 2),
+            ChunkAnchor::new(voxel_world, 1, 2).with_shape(AnchorShape::Cube),
         ));
 
         app.update();
@@ -227,7 +508,7 @@ mod test {
         let min = IVec3::new(1, -1, -2);
         let max = IVec3::new(3, 1, 0);
         let region = Region::from_points(min, max);
-        for pos in region.iter() {
+        for pos in region.iter_sorted_by_distance(IVec3::new(2, 0, -1)) {
             assert_eq!(
                 iter.next(),
                 Some(&LoadChunkEvent {
@@ -239,4 +520,165 @@ mod test {
 
         assert_eq!(iter.next(), None);
     }
+
+
+    #[test]
+    fn sphere_anchor() {
+        let anchor = ChunkAnchor::new(Entity::from_raw(0), 4, 6);
+        let cube = anchor.clone().with_shape(AnchorShape::Cube);
+
+        let chunks = anchor.chunks(IVec3::new(44, 2, -5));
+        assert!(chunks.contains(&IVec3::new(6, 0, -1)));
+        assert!(!chunks.contains(&IVec3::new(6, 4, 3)));
+        assert!(chunks.len() * 10 < cube.chunks(IVec3::new(44, 2, -5)).len() * 6);
+
+        let sphere: Vec<IVec3> = SphereIterator::chunks(IVec3::new(44, 2, -5), 4).collect();
+        assert_eq!(chunks.len(), sphere.len());
+        assert!(sphere.iter().all(|chunk| chunks.contains(chunk)));
+    }
+
+
+    #[test]
+    fn anchor_priorities() {
+        let mut app = App::new();
+        app.insert_resource(ChunkLoadBudget {
+            chunks_per_frame: 5,
+        });
+        app.add_event::<LoadChunkEvent>();
+        app.add_system(load_chunks);
+
+        let voxel_world = app.world.spawn(VoxelChunkStates::default()).id();
+        for (x, radius, priority) in [(0.0, 1, 1), (100.0, 1, 1), (200.0, 0, 5)] {
+            app.world.spawn((
+                Position {
+                    translation: Vec3::new(x, 0.0, 0.0),
+                    ..default()
+                },
+                ChunkAnchor::new(voxel_world, radius, radius).with_priority(priority),
+            ));
+        }
+
+        app.update();
+
+        let load_chunk_ev = app.world.resource::<Events<LoadChunkEvent>>();
+        let mut load_chunk_reader = load_chunk_ev.get_reader();
+        let chunks: Vec<IVec3> =
+            load_chunk_reader.iter(load_chunk_ev).map(|ev| ev.chunk_coords).collect();
+
+        let anchor = |chunk: IVec3| (chunk.x + 1) / 6;
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks[0], IVec3::new(12, 0, 0));
+        assert_eq!(
+            chunks[1..].iter().map(|c| anchor(*c)).collect::<Vec<_>>(),
+            vec![0, 1, 0, 1]
+        );
+    }
+
+
+    /// Creates an app that unloads chunks with a 10 second grace period, along
+    /// with a world containing a single loaded chunk at the origin and an
+    /// anchor standing far away from it.
+    fn unload_app() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.insert_resource(ChunkUnloadDelay {
+            seconds: 10.0,
+        });
+        app.add_event::<UnloadChunkEvent>();
+        app.add_system(unload_chunks);
+
+        let mut states = VoxelChunkStates::default();
+        states.set_state(IVec3::ZERO, ChunkState::Loaded);
+        let voxel_world = app.world.spawn(states).id();
+
+        let anchor = app
+            .world
+            .spawn((
+                Position {
+                    translation: Vec3::new(100.0, 0.0, 0.0),
+                    ..default()
+                },
+                ChunkAnchor::new(voxel_world, 1, 1),
+            ))
+            .id();
+
+        (app, voxel_world, anchor)
+    }
+
+
+    /// Advances the clock of the app to the given number of seconds since it
+    /// was created, and runs a single update.
+    fn update_at(app: &mut App, seconds: f32) {
+        let mut time = app.world.resource_mut::<Time>();
+        let instant = time.startup() + std::time::Duration::from_secs_f32(seconds);
+        time.update_with_instant(instant);
+        app.update();
+    }
+
+
+    /// Gets the coordinates of all chunks that have been unloaded so far.
+    fn unloaded(app: &App) -> Vec<IVec3> {
+        let unload_chunk_ev = app.world.resource::<Events<UnloadChunkEvent>>();
+        let mut unload_chunk_reader = unload_chunk_ev.get_reader();
+        unload_chunk_reader.iter(unload_chunk_ev).map(|ev| ev.chunk_coords).collect()
+    }
+
+
+    #[test]
+    fn unload_after_delay() {
+        let (mut app, voxel_world, _) = unload_app();
+
+        update_at(&mut app, 0.0);
+        let states = app.world.get::<VoxelChunkStates>(voxel_world).unwrap();
+        assert_eq!(states.get_state(IVec3::ZERO), ChunkState::Loaded);
+        assert!(states.is_unload_pending(IVec3::ZERO));
+        assert_eq!(unloaded(&app), vec![]);
+
+        update_at(&mut app, 9.0);
+        assert_eq!(unloaded(&app), vec![]);
+
+        update_at(&mut app, 10.5);
+        let states = app.world.get::<VoxelChunkStates>(voxel_world).unwrap();
+        assert_eq!(states.get_state(IVec3::ZERO), ChunkState::Unloaded);
+        assert!(!states.is_unload_pending(IVec3::ZERO));
+        assert_eq!(unloaded(&app), vec![IVec3::ZERO]);
+    }
+
+
+    #[test]
+    fn reentering_range_cancels_unload() {
+        let (mut app, voxel_world, anchor) = unload_app();
+        let move_anchor = |app: &mut App, x: f32| {
+            app.world.get_mut::<Position>(anchor).unwrap().translation.x = x;
+        };
+
+        update_at(&mut app, 0.0);
+
+        move_anchor(&mut app, 20.0);
+        update_at(&mut app, 6.0);
+        let states = app.world.get::<VoxelChunkStates>(voxel_world).unwrap();
+        assert!(!states.is_unload_pending(IVec3::ZERO));
+
+        move_anchor(&mut app, 100.0);
+        update_at(&mut app, 12.0);
+        assert_eq!(unloaded(&app), vec![]);
+
+        update_at(&mut app, 21.0);
+        assert_eq!(unloaded(&app), vec![]);
+
+        update_at(&mut app, 22.5);
+        assert_eq!(unloaded(&app), vec![IVec3::ZERO]);
+    }
+
+
+    #[test]
+    fn anchor_range() {
+        let anchor = ChunkAnchor::new(Entity::from_raw(0), 1, 2);
+        let cube = anchor.clone().with_shape(AnchorShape::Cube);
+
+        assert!(anchor.in_range(IVec3::new(40, 0, 0), IVec3::new(4, 0, 0)));
+        assert!(!anchor.in_range(IVec3::new(40, 0, 0), IVec3::new(4, 2, 2)));
+        assert!(cube.in_range(IVec3::new(40, 0, 0), IVec3::new(4, 2, 2)));
+        assert!(!cube.in_range(IVec3::new(40, 0, 0), IVec3::new(5, 0, 0)));
+    }
 }
@@ -0,0 +1,447 @@
+//! The signal layer of a voxel world, which carries power from emitting blocks,
+//! such as levers and buttons, through conducting blocks, such as wires, to
+//! consuming blocks, such as doors and lamps.
+//!
+//! The signal role of each block is declared through the [`BlockSignal`]
+//! trait. Power is only re-evaluated for the networks of signal blocks that
+//! have changed since the last update, so large, idle circuits cost nothing to
+//! maintain.
+
+
+use crate::prelude::VoxelWorld;
+use awgen_math::prelude::{chunk_to_block, index_to_local, local_index, Direction};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+
+/// The maximum power level of a signal.
+pub const MAX_SIGNAL_POWER: u8 = 15;
+
+
+/// The maximum number of blocks within a single signal network that are
+/// re-evaluated at once. Networks that are larger than this are only partially
+/// updated.
+const MAX_NETWORK_SIZE: usize = 32768;
+
+
+/// The role that a block plays within a signal network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SignalRole {
+    /// The block does not interact with signals.
+    #[default]
+    None,
+
+    /// The block emits a signal with the given power level to all adjacent
+    /// blocks.
+    Emitter(u8),
+
+    /// The block carries the strongest adjacent signal to its neighbors, losing
+    /// one power level for each block that it travels through.
+    Conductor,
+
+    /// The block receives the strongest adjacent signal, without passing it
+    /// on.
+    Consumer,
+}
+
+
+/// Describes how a block data type interacts with the signal layer.
+pub trait BlockSignal: Sized {
+    /// Gets the role of this block within a signal network.
+    ///
+    /// The role of a consumer must not depend on the power that it receives,
+    /// as consumers are updated to reflect their power level.
+    fn signal_role(&self) -> SignalRole;
+
+
+    /// Gets the block that this consumer block becomes when it receives the
+    /// given power level, such as an open door for any non-zero power level.
+    fn with_power(&self, power: u8) -> Self;
+}
+
+
+/// A change to the power level received by a consumer block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalChange {
+    /// The position of the consumer block.
+    pub block_pos: IVec3,
+
+    /// The power level that the consumer now receives.
+    pub power: u8,
+}
+
+
+/// An event that is triggered when the power level received by a consumer
+/// block changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalChangedEvent {
+    /// The world entity that contains the consumer block.
+    pub world: Entity,
+
+    /// The position of the consumer block.
+    pub block_pos: IVec3,
+
+    /// The power level that the consumer now receives.
+    pub power: u8,
+}
+
+
+/// A component that stores the signal network and current power levels of the
+/// voxel world that it is attached to.
+///
+/// The signal layer is maintained automatically from the block data layer of
+/// the world, and only chunks that have been modified are scanned for changes.
+#[derive(Debug, Clone, Default, Component)]
+pub struct SignalLayer {
+    /// The role of every signal block within the world.
+    roles: HashMap<IVec3, SignalRole>,
+
+    /// The positions of the signal blocks within each chunk.
+    chunks: HashMap<IVec3, Vec<IVec3>>,
+
+    /// The power level of every powered conductor and consumer.
+    power: HashMap<IVec3, u8>,
+
+    /// The last seen version of each chunk within the voxel world.
+    versions: HashMap<IVec3, u64>,
+}
+
+impl SignalLayer {
+    /// Gets the role of the block at the given block position.
+    pub fn role(&self, block_pos: IVec3) -> SignalRole {
+        self.roles.get(&block_pos).copied().unwrap_or_default()
+    }
+
+
+    /// Gets the power level of the conductor or consumer block at the given
+    /// block position. Other blocks always have a power level of 0.
+    pub fn power(&self, block_pos: IVec3) -> u8 {
+        self.power.get(&block_pos).copied().unwrap_or(0)
+    }
+
+
+    /// Scans all chunks within the given voxel world that have been modified
+    /// since this layer was last updated, and re-evaluates the power levels of
+    /// all signal networks that have changed.
+    ///
+    /// Returns the changes to the power levels received by consumer blocks.
+    pub fn update<BlockData>(&mut self, world: &VoxelWorld<BlockData>) -> Vec<SignalChange>
+    where BlockData: BlockSignal + Default + Copy + Send + Sync + 'static {
+        let mut dirty = vec![];
+
+        for (chunk_coords, version) in world.chunk_versions() {
+            if self.versions.insert(chunk_coords, version) == Some(version) {
+                continue;
+            }
+
+            let Some(blocks) = world.get_chunk_blocks(chunk_coords) else {
+                continue;
+            };
+
+            let origin = chunk_to_block(chunk_coords);
+            let mut positions = vec![];
+            for (index, block) in blocks.iter().enumerate() {
+                let role = block.signal_role();
+                if role == SignalRole::None {
+                    continue;
+                }
+
+                let block_pos = origin + index_to_local(index);
+                positions.push(block_pos);
+                if self.roles.insert(block_pos, role) != Some(role) {
+                    dirty.push(block_pos);
+                }
+            }
+
+            let previous = self.chunks.insert(chunk_coords, positions).unwrap_or_default();
+            for block_pos in previous {
+                if blocks[local_index(block_pos)].signal_role() == SignalRole::None {
+                    self.roles.remove(&block_pos);
+                    dirty.push(block_pos);
+                }
+            }
+        }
+
+        match dirty.is_empty() {
+            true => vec![],
+            false => self.evaluate(&dirty),
+        }
+    }
+
+
+    /// Re-evaluates the power levels of all signal networks that contain or
+    /// are adjacent to the given changed block positions.
+    fn evaluate(&mut self, dirty: &[IVec3]) -> Vec<SignalChange> {
+        let network = self.collect_network(dirty);
+
+        let mut previous = HashMap::new();
+        for block_pos in &network {
+            if let Some(power) = self.power.remove(block_pos) {
+                previous.insert(*block_pos, power);
+            }
+        }
+
+        let mut buckets = vec![vec![]; MAX_SIGNAL_POWER as usize + 1];
+        for block_pos in &network {
+            if let SignalRole::Emitter(power) = self.role(*block_pos) {
+                let power = power.min(MAX_SIGNAL_POWER);
+                for direction in Direction::ALL {
+                    buckets[power as usize].push(*block_pos + direction.offset());
+                }
+            }
+        }
+
+        for power in (1..=MAX_SIGNAL_POWER).rev() {
+            while let Some(block_pos) = buckets[power as usize].pop() {
+                if !network.contains(&block_pos) || self.power(block_pos) >= power {
+                    continue;
+                }
+
+                let role = self.role(block_pos);
+                if !matches!(role, SignalRole::Conductor | SignalRole::Consumer) {
+                    continue;
+                }
+
+                self.power.insert(block_pos, power);
+                if role == SignalRole::Conductor && power > 1 {
+                    for direction in Direction::ALL {
+                        buckets[power as usize - 1].push(block_pos + direction.offset());
+                    }
+                }
+            }
+        }
+
+        let mut changes: Vec<_> = network
+            .iter()
+            .filter(|block_pos| self.role(**block_pos) == SignalRole::Consumer)
+            .filter_map(|block_pos| {
+                let power = self.power(*block_pos);
+                let previous = previous.get(block_pos).copied().unwrap_or(0);
+                (power != previous).then_some(SignalChange {
+                    block_pos: *block_pos,
+                    power,
+                })
+            })
+            .collect();
+
+        changes.sort_by_key(|change| (change.block_pos.x, change.block_pos.y, change.block_pos.z));
+        changes
+    }
+
+
+    /// Collects all signal blocks that are connected to the given changed
+    /// block positions through conductors, including the changed positions
+    /// themselves and the emitters and consumers at the edges of the network.
+    fn collect_network(&self, dirty: &[IVec3]) -> HashSet<IVec3> {
+        let mut network: HashSet<IVec3> = dirty.iter().copied().collect();
+        let mut queue: VecDeque<IVec3> = dirty.iter().copied().collect();
+
+        while let Some(block_pos) = queue.pop_front() {
+            let expands =
+                dirty.contains(&block_pos) || self.role(block_pos) == SignalRole::Conductor;
+            if !expands {
+                continue;
+            }
+
+            for direction in Direction::ALL {
+                let neighbor = block_pos + direction.offset();
+                if self.role(neighbor) == SignalRole::None || network.contains(&neighbor) {
+                    continue;
+                }
+
+                if network.len() >= MAX_NETWORK_SIZE {
+                    warn!("Signal network at {block_pos} exceeds the maximum network size");
+                    return network;
+                }
+
+                network.insert(neighbor);
+                queue.push_back(neighbor);
+            }
+        }
+
+        network
+    }
+}
+
+
+/// Builds and inserts a signal layer into each voxel world containing a block
+/// data layer of the given type that does not have one yet.
+pub fn insert_signal_layers<BlockData>(
+    worlds: Query<Entity, (With<VoxelWorld<BlockData>>, Without<SignalLayer>)>,
+    mut commands: Commands,
+) where
+    BlockData: BlockSignal + Default + Copy + Send + Sync + 'static,
+{
+    for entity in worlds.iter() {
+        commands.entity(entity).insert(SignalLayer::default());
+    }
+}
+
+
+/// Called each physics frame to re-evaluate the signal layer of each voxel
+/// world, updating the blocks of consumers whose power level has changed.
+pub fn update_signal_layers<BlockData>(
+    mut signal_ev: EventWriter<SignalChangedEvent>,
+    mut worlds: Query<(Entity, &mut VoxelWorld<BlockData>, &mut SignalLayer)>,
+) where
+    BlockData: BlockSignal + Default + Copy + PartialEq + Send + Sync + 'static,
+{
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("signals").entered();
+
+    for (entity, mut world, mut layer) in worlds.iter_mut() {
+        for change in layer.update(&world) {
+            let block = world.get_block_data(change.block_pos);
+            let powered = block.with_power(change.power);
+            if powered != block {
+                world.set_block_data(change.block_pos, powered);
+            }
+
+            signal_ev.send(SignalChangedEvent {
+                world:     entity,
+                block_pos: change.block_pos,
+                power:     change.power,
+            });
+        }
+    }
+}
+
+
+/// A mini extension plugin that maintains a [`SignalLayer`] for each voxel
+/// world containing a block data layer of the given type, and evaluates it on
+/// each physics frame.
+///
+/// This plugin requires the physics plugin.
+#[derive(Debug, Clone, Default)]
+pub struct SignalPlugin<BlockData>
+where BlockData: BlockSignal + Default + Copy + PartialEq + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for SignalPlugin<BlockData>
+where BlockData: BlockSignal + Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<SignalChangedEvent>()
+            .add_system(insert_signal_layers::<BlockData>)
+            .add_system_to_stage("tick", update_signal_layers::<BlockData>);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        LeverOff,
+        LeverOn,
+        Wire,
+        DoorClosed,
+        DoorOpen,
+    }
+
+    impl BlockSignal for TestBlock {
+        fn signal_role(&self) -> SignalRole {
+            match self {
+                TestBlock::LeverOn => SignalRole::Emitter(MAX_SIGNAL_POWER),
+                TestBlock::LeverOff => SignalRole::Emitter(0),
+                TestBlock::Wire => SignalRole::Conductor,
+                TestBlock::DoorClosed | TestBlock::DoorOpen => SignalRole::Consumer,
+                TestBlock::Air => SignalRole::None,
+            }
+        }
+
+
+        fn with_power(&self, power: u8) -> Self {
+            match (self, power) {
+                (TestBlock::DoorClosed | TestBlock::DoorOpen, 0) => TestBlock::DoorClosed,
+                (TestBlock::DoorClosed | TestBlock::DoorOpen, _) => TestBlock::DoorOpen,
+                _ => *self,
+            }
+        }
+    }
+
+
+    #[test]
+    fn power_decays_along_wire() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::ZERO, TestBlock::LeverOn);
+        for x in 1..=20 {
+            world.set_block_data(IVec3::new(x, 0, 0), TestBlock::Wire);
+        }
+
+        let mut layer = SignalLayer::default();
+        assert_eq!(layer.update(&world), vec![]);
+
+        assert_eq!(layer.power(IVec3::new(1, 0, 0)), 15);
+        assert_eq!(layer.power(IVec3::new(5, 0, 0)), 11);
+        assert_eq!(layer.power(IVec3::new(15, 0, 0)), 1);
+        assert_eq!(layer.power(IVec3::new(16, 0, 0)), 0);
+        assert_eq!(layer.power(IVec3::ZERO), 0);
+    }
+
+
+    #[test]
+    fn toggle_lever_opens_door() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::ZERO, TestBlock::LeverOff);
+        for x in 1..=3 {
+            world.set_block_data(IVec3::new(x, 0, 0), TestBlock::Wire);
+        }
+        world.set_block_data(IVec3::new(4, 0, 0), TestBlock::DoorClosed);
+        world.set_block_data(IVec3::new(40, 0, 0), TestBlock::DoorClosed);
+
+        let mut layer = SignalLayer::default();
+        assert_eq!(layer.update(&world), vec![]);
+
+        world.set_block_data(IVec3::ZERO, TestBlock::LeverOn);
+        assert_eq!(layer.update(&world), vec![SignalChange {
+            block_pos: IVec3::new(4, 0, 0),
+            power:     12,
+        }]);
+
+        world.set_block_data(IVec3::new(2, 0, 0), TestBlock::Air);
+        assert_eq!(layer.update(&world), vec![SignalChange {
+            block_pos: IVec3::new(4, 0, 0),
+            power:     0,
+        }]);
+        assert_eq!(layer.power(IVec3::new(1, 0, 0)), 15);
+        assert_eq!(layer.power(IVec3::new(3, 0, 0)), 0);
+    }
+
+
+    #[test]
+    fn consumers_are_updated() {
+        let mut app = App::new();
+        app.add_stage("tick", SystemStage::parallel())
+            .add_plugin(SignalPlugin::<TestBlock>::default());
+
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::ZERO, TestBlock::LeverOn);
+        world.set_block_data(IVec3::X, TestBlock::DoorClosed);
+        let world = app.world.spawn(world).id();
+
+        app.update();
+        app.update();
+
+        let voxels = app.world.get::<VoxelWorld<TestBlock>>(world).unwrap();
+        assert_eq!(voxels.get_block_data(IVec3::X), TestBlock::DoorOpen);
+
+        let signal_ev = app.world.resource::<Events<SignalChangedEvent>>();
+        let changes: Vec<_> = signal_ev.get_reader().iter(signal_ev).copied().collect();
+        assert_eq!(changes, vec![SignalChangedEvent {
+            world,
+            block_pos: IVec3::X,
+            power: MAX_SIGNAL_POWER,
+        }]);
+    }
+}
@@ -0,0 +1,231 @@
+//! Utilities for finding safe positions within a voxel world to spawn entities
+//! at, such as players joining or respawning within a world.
+
+
+use crate::prelude::{InWorld, VoxelWorld};
+use awgen_math::prelude::{world_to_block, Region, Region2};
+use awgen_physics::prelude::Position;
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+
+/// The maximum number of frames to search for a safe spawn position before
+/// giving up and leaving the entity where it is.
+const MAX_SPAWN_SEARCH_ATTEMPTS: u32 = 100;
+
+
+/// The number of blocks above and below the origin that are searched when
+/// looking for a safe spawn position.
+const SPAWN_SEARCH_HEIGHT: i32 = 64;
+
+
+/// The horizontal radius, in blocks, around the origin that is searched when
+/// looking for a safe spawn position.
+const SPAWN_SEARCH_RADIUS: i32 = 8;
+
+
+/// Describes the physical properties of a block data type that are used to
+/// determine whether or not an entity may stand within or upon it.
+pub trait BlockSolidity {
+    /// Gets whether or not this block is solid and may be stood upon.
+    fn is_solid(&self) -> bool;
+
+
+    /// Gets whether or not this block is a fluid.
+    fn is_fluid(&self) -> bool {
+        false
+    }
+}
+
+
+/// Gets whether or not the given column of blocks is safe for a two block tall
+/// entity to stand within.
+///
+/// A column is safe if the ground block is solid and both the feet and head
+/// blocks are neither solid nor a fluid.
+fn is_safe_column<BlockData>(ground: BlockData, feet: BlockData, head: BlockData) -> bool
+where BlockData: BlockSolidity {
+    let is_open = |block: BlockData| !block.is_solid() && !block.is_fluid();
+    ground.is_solid() && !ground.is_fluid() && is_open(feet) && is_open(head)
+}
+
+
+/// Gets whether or not an entity that is two blocks tall may safely stand with
+/// its feet at the given block position.
+pub fn is_safe_spawn<BlockData>(world: &VoxelWorld<BlockData>, pos: IVec3) -> bool
+where BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static {
+    is_safe_column(
+        world.get_block_data(pos - IVec3::Y),
+        world.get_block_data(pos),
+        world.get_block_data(pos + IVec3::Y),
+    )
+}
+
+
+/// Searches for the nearest safe spawn position to the given origin.
+///
+/// Columns are searched from the origin column outwards, up to the given
+/// horizontal radius. Each column is searched from the top down, within the
+/// given height above and below the origin, in order to find the surface.
+///
+/// If no safe position could be found, `None` is returned.
+pub fn find_safe_spawn<BlockData>(
+    world: &VoxelWorld<BlockData>,
+    origin: IVec3,
+    radius: i32,
+    height: i32,
+) -> Option<IVec3>
+where
+    BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static,
+{
+    let min = origin - IVec3::new(radius, height, radius);
+    let max = origin + IVec3::new(radius, height, radius);
+    let region = Region::from_points(min - IVec3::Y, max + IVec3::Y);
+    let blocks = world.get_block_region(region);
+    let block = |pos: IVec3| blocks[region.point_to_index_unchecked(pos)];
+
+    let mut columns: Vec<IVec2> = Region2::from(region).iter().collect();
+    columns.sort_by_key(|col| {
+        let offset = *col - IVec2::new(origin.x, origin.z);
+        offset.dot(offset)
+    });
+
+    for column in columns {
+        for y in (min.y..=max.y).rev() {
+            let pos = IVec3::new(column.x, y, column.y);
+            if is_safe_column(block(pos - IVec3::Y), block(pos), block(pos + IVec3::Y)) {
+                return Some(pos);
+            }
+        }
+    }
+
+    None
+}
+
+
+/// A marker component that indicates that an entity should be moved to the
+/// nearest safe spawn position within its current world.
+///
+/// The search is retried each frame, as the surrounding chunks may not be
+/// loaded yet, until a safe position is found or too many attempts have been
+/// made.
+#[derive(Debug, Clone, Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct SafeSpawnSearch {
+    /// The number of failed search attempts so far.
+    attempts: u32,
+}
+
+
+/// Moves entities with a [`SafeSpawnSearch`] marker to the nearest safe spawn
+/// position within their world.
+pub fn apply_safe_spawns<BlockData>(
+    worlds: Query<&VoxelWorld<BlockData>>,
+    mut query: Query<(Entity, &InWorld, &mut Position, &mut SafeSpawnSearch)>,
+    mut commands: Commands,
+) where
+    BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static,
+{
+    for (entity, in_world, mut position, mut search) in query.iter_mut() {
+        let Ok(world) = worlds.get(in_world.0) else {
+            continue;
+        };
+
+        let origin = world_to_block(position.translation);
+        if let Some(pos) = find_safe_spawn(world, origin, SPAWN_SEARCH_RADIUS, SPAWN_SEARCH_HEIGHT)
+        {
+            position.translation = pos.as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+            commands.entity(entity).remove::<SafeSpawnSearch>();
+            continue;
+        }
+
+        search.attempts += 1;
+        if search.attempts >= MAX_SPAWN_SEARCH_ATTEMPTS {
+            warn!("Failed to find a safe spawn position for {entity:?} near {origin}");
+            commands.entity(entity).remove::<SafeSpawnSearch>();
+        }
+    }
+}
+
+
+/// A mini extension plugin that enables safe spawn searching within worlds
+/// containing a block data layer of the given type.
+#[derive(Debug, Clone, Default)]
+pub struct SafeSpawnPlugin<BlockData>
+where BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for SafeSpawnPlugin<BlockData>
+where BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.register_type::<SafeSpawnSearch>()
+            .add_system(apply_safe_spawns::<BlockData>);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Stone,
+        Water,
+    }
+
+    impl BlockSolidity for TestBlock {
+        fn is_solid(&self) -> bool {
+            *self == TestBlock::Stone
+        }
+
+
+        fn is_fluid(&self) -> bool {
+            *self == TestBlock::Water
+        }
+    }
+
+
+    #[test]
+    fn spawn_on_surface() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        for pos in Region::from_points(IVec3::new(-4, 0, -4), IVec3::new(4, 10, 4)).iter() {
+            world.set_block_data(pos, TestBlock::Stone);
+        }
+
+        let spawn = find_safe_spawn(&world, IVec3::new(0, 3, 0), 2, 16);
+        assert_eq!(spawn, Some(IVec3::new(0, 11, 0)));
+        assert!(is_safe_spawn(&world, IVec3::new(0, 11, 0)));
+    }
+
+
+    #[test]
+    fn avoid_fluids() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        for pos in Region::from_points(IVec3::new(-4, 0, -4), IVec3::new(4, 0, 4)).iter() {
+            world.set_block_data(pos, TestBlock::Stone);
+        }
+
+        for pos in Region::from_points(IVec3::new(-1, 1, -1), IVec3::new(1, 1, 1)).iter() {
+            world.set_block_data(pos, TestBlock::Water);
+        }
+
+        let spawn = find_safe_spawn(&world, IVec3::new(0, 1, 0), 4, 4).unwrap();
+        assert_eq!(spawn.y, 1);
+        assert_eq!((spawn - IVec3::new(0, 1, 0)).abs().max_element(), 2);
+    }
+
+
+    #[test]
+    fn no_safe_spawn() {
+        let world = VoxelWorld::<TestBlock>::default();
+        assert_eq!(find_safe_spawn(&world, IVec3::ZERO, 2, 2), None);
+    }
+}
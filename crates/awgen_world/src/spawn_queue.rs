@@ -0,0 +1,247 @@
+//! A deferred spawn queue, which spreads out large batches of entity spawns and
+//! despawns over multiple frames.
+//!
+//! Systems such as chunk loading and replication may need to spawn hundreds of
+//! entities within a single frame, which stalls command application. Instead,
+//! these operations are pushed onto the [`SpawnQueue`], which applies at most a
+//! fixed number of them each frame, starting with the highest priority.
+
+
+use bevy::prelude::*;
+use std::collections::BinaryHeap;
+use std::{cmp, fmt};
+
+
+/// The default maximum number of queued spawns and despawns that are applied
+/// within a single frame.
+pub const DEFAULT_SPAWN_BUDGET: usize = 128;
+
+
+/// A deferred spawn or despawn operation.
+type SpawnOperation = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+
+/// An operation within the spawn queue, alongside its ordering.
+struct QueuedOperation {
+    /// The priority of this operation. Higher priorities are applied first.
+    priority: u32,
+
+    /// The order that this operation was queued in, used to apply operations
+    /// of the same priority in the order that they were queued.
+    sequence: u64,
+
+    /// The operation to apply.
+    operation: SpawnOperation,
+}
+
+impl PartialEq for QueuedOperation {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for QueuedOperation {}
+
+impl PartialOrd for QueuedOperation {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedOperation {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+
+/// A queue of entity spawns and despawns that are applied over multiple frames,
+/// at most [`SpawnQueue::budget`] operations per frame.
+///
+/// Operations with a higher priority are applied first, and operations with
+/// the same priority are applied in the order that they were queued.
+#[derive(Resource)]
+pub struct SpawnQueue {
+    /// The maximum number of operations that are applied within a single frame.
+    pub budget: usize,
+
+    /// The pending operations.
+    queue: BinaryHeap<QueuedOperation>,
+
+    /// The sequence number of the next queued operation.
+    next_sequence: u64,
+}
+
+impl Default for SpawnQueue {
+    fn default() -> Self {
+        Self {
+            budget:        DEFAULT_SPAWN_BUDGET,
+            queue:         default(),
+            next_sequence: 0,
+        }
+    }
+}
+
+impl fmt::Debug for SpawnQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnQueue")
+            .field("budget", &self.budget)
+            .field("pending", &self.queue.len())
+            .finish()
+    }
+}
+
+impl SpawnQueue {
+    /// Queues a new entity to be spawned with the given bundle.
+    pub fn spawn<B: Bundle>(&mut self, priority: u32, bundle: B) {
+        self.spawn_with(priority, move |world| {
+            world.spawn(bundle);
+        });
+    }
+
+
+    /// Queues an operation that spawns one or more entities. This may be used
+    /// when the components of an entity can only be created with access to the
+    /// world, such as reflected components.
+    pub fn spawn_with<F>(&mut self, priority: u32, operation: F)
+    where F: FnOnce(&mut World) + Send + Sync + 'static {
+        self.queue.push(QueuedOperation {
+            priority,
+            sequence: self.next_sequence,
+            operation: Box::new(operation),
+        });
+        self.next_sequence += 1;
+    }
+
+
+    /// Queues the given entity, and all of its children, to be despawned.
+    pub fn despawn(&mut self, priority: u32, entity: Entity) {
+        self.spawn_with(priority, move |world| {
+            if let Some(entity) = world.get_entity_mut(entity) {
+                entity.despawn_recursive();
+            }
+        });
+    }
+
+
+    /// Gets the number of pending operations within this queue.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+
+    /// Gets whether or not this queue has no pending operations.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+
+    /// Removes up to the given number of pending operations from this queue,
+    /// in the order that they should be applied.
+    fn take(&mut self, count: usize) -> Vec<SpawnOperation> {
+        let count = count.min(self.queue.len());
+        (0..count)
+            .filter_map(|_| self.queue.pop())
+            .map(|queued| queued.operation)
+            .collect()
+    }
+}
+
+
+/// Applies all pending operations within the spawn queue of the given world,
+/// ignoring the budget.
+///
+/// This is used before actions that rely on every queued entity existing, such
+/// as saving all entities within a world.
+pub fn flush_spawn_queue(world: &mut World) {
+    while let Some(mut queue) = world.get_resource_mut::<SpawnQueue>() {
+        if queue.is_empty() {
+            return;
+        }
+
+        let len = queue.len();
+        for operation in queue.take(len) {
+            operation(world);
+        }
+    }
+}
+
+
+/// Applies up to the budget of pending operations within the spawn queue.
+///
+/// Operations are removed from the queue before they are applied, so that they
+/// may queue further operations.
+pub fn apply_spawn_queue(world: &mut World) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("spawn_queue").entered();
+
+    let Some(mut queue) = world.get_resource_mut::<SpawnQueue>() else {
+        return;
+    };
+
+    let budget = queue.budget;
+    for operation in queue.take(budget) {
+        operation(world);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+    struct Marker(u32);
+
+
+    fn markers(app: &mut App) -> Vec<u32> {
+        let mut query = app.world.query::<&Marker>();
+        let mut markers: Vec<u32> = query.iter(&app.world).map(|m| m.0).collect();
+        markers.sort();
+        markers
+    }
+
+
+    #[test]
+    fn apply_within_budget() {
+        let mut app = App::new();
+        app.init_resource::<SpawnQueue>();
+        app.add_system(apply_spawn_queue);
+
+        let mut queue = app.world.resource_mut::<SpawnQueue>();
+        queue.budget = 2;
+        queue.spawn(0, Marker(1));
+        queue.spawn(0, Marker(2));
+        queue.spawn(5, Marker(3));
+        queue.spawn(0, Marker(4));
+
+        app.update();
+        assert_eq!(markers(&mut app), vec![1, 3]);
+
+        app.update();
+        assert_eq!(markers(&mut app), vec![1, 2, 3, 4]);
+        assert!(app.world.resource::<SpawnQueue>().is_empty());
+    }
+
+
+    #[test]
+    fn flush_and_despawn() {
+        let mut app = App::new();
+        app.init_resource::<SpawnQueue>();
+
+        let entity = app.world.spawn(Marker(1)).id();
+        let mut queue = app.world.resource_mut::<SpawnQueue>();
+        queue.budget = 1;
+        queue.despawn(0, entity);
+        queue.spawn_with(0, |world| {
+            world.resource_mut::<SpawnQueue>().spawn(0, Marker(2));
+        });
+
+        flush_spawn_queue(&mut app.world);
+        assert_eq!(markers(&mut app), vec![2]);
+    }
+}
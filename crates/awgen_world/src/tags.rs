@@ -0,0 +1,345 @@
+//! Lightweight string tags that may be attached to any entity, allowing for
+//! entities to be grouped for mini-game logic, such as all players within an
+//! arena, without defining new components.
+//!
+//! Tag names are interned by the [`TagRegistry`], so each tag is stored as a
+//! small integer, and the registry keeps an index of which entities carry each
+//! tag.
+
+
+use bevy::ecs::system::{Command, EntityCommands};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+
+/// An interned tag name. Tags are only valid for the [`TagRegistry`] that
+/// created them, and are not stable between runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Reflect, FromReflect)]
+pub struct Tag(u32);
+
+
+/// The set of tags that are attached to an entity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect, Component)]
+#[reflect(Component)]
+pub struct Tags {
+    /// The attached tags, in sorted order.
+    tags: Vec<Tag>,
+}
+
+impl Tags {
+    /// Attaches the given tag. Returns false if it was already attached.
+    pub fn insert(&mut self, tag: Tag) -> bool {
+        match self.tags.binary_search(&tag) {
+            Ok(_) => false,
+            Err(index) => {
+                self.tags.insert(index, tag);
+                true
+            },
+        }
+    }
+
+
+    /// Detaches the given tag. Returns false if it was not attached.
+    pub fn remove(&mut self, tag: Tag) -> bool {
+        match self.tags.binary_search(&tag) {
+            Ok(index) => {
+                self.tags.remove(index);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+
+    /// Gets whether or not the given tag is attached.
+    pub fn contains(&self, tag: Tag) -> bool {
+        self.tags.binary_search(&tag).is_ok()
+    }
+
+
+    /// Gets an iterator over all attached tags.
+    pub fn iter(&self) -> impl Iterator<Item = Tag> + '_ {
+        self.tags.iter().copied()
+    }
+
+
+    /// Gets the number of attached tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+
+    /// Gets whether or not no tags are attached.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+
+/// Interns tag names and indexes the entities that carry each tag.
+///
+/// The index is updated by [`index_tags`], so changes to the [`Tags`] of an
+/// entity are visible from the next time that system runs.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct TagRegistry {
+    /// The name of each interned tag, indexed by tag.
+    names: Vec<String>,
+
+    /// The interned tag of each tag name.
+    ids: HashMap<String, Tag>,
+
+    /// The entities that carry each tag.
+    entities: HashMap<Tag, HashSet<Entity>>,
+
+    /// The tags of each indexed entity, as of the last time it was indexed.
+    indexed: HashMap<Entity, Vec<Tag>>,
+}
+
+impl TagRegistry {
+    /// Gets the tag with the given name, interning it if needed.
+    pub fn intern(&mut self, name: &str) -> Tag {
+        if let Some(tag) = self.ids.get(name) {
+            return *tag;
+        }
+
+        let tag = Tag(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), tag);
+        tag
+    }
+
+
+    /// Gets the tag with the given name, if it has been interned.
+    pub fn get(&self, name: &str) -> Option<Tag> {
+        self.ids.get(name).copied()
+    }
+
+
+    /// Gets the name of the given tag.
+    pub fn name(&self, tag: Tag) -> Option<&str> {
+        self.names.get(tag.0 as usize).map(|name| name.as_str())
+    }
+
+
+    /// Gets an iterator over all entities that carry the given tag.
+    pub fn entities_with(&self, tag: Tag) -> impl Iterator<Item = Entity> + '_ {
+        self.entities
+            .get(&tag)
+            .into_iter()
+            .flat_map(|entities| entities.iter().copied())
+    }
+
+
+    /// Gets an iterator over all entities that carry the tag with the given
+    /// name.
+    pub fn entities_with_tag(&self, name: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.get(name).into_iter().flat_map(|tag| self.entities_with(tag))
+    }
+
+
+    /// Gets the number of entities that carry the tag with the given name.
+    pub fn count(&self, name: &str) -> usize {
+        self.get(name).and_then(|tag| self.entities.get(&tag)).map_or(0, |e| e.len())
+    }
+
+
+    /// Gets whether or not the given entity carries the tag with the given
+    /// name.
+    pub fn has_tag(&self, entity: Entity, name: &str) -> bool {
+        self.get(name)
+            .and_then(|tag| self.entities.get(&tag))
+            .is_some_and(|entities| entities.contains(&entity))
+    }
+
+
+    /// Replaces the indexed tags of the given entity.
+    fn index(&mut self, entity: Entity, tags: Vec<Tag>) {
+        self.unindex(entity);
+
+        for tag in tags.iter() {
+            self.entities.entry(*tag).or_default().insert(entity);
+        }
+
+        if !tags.is_empty() {
+            self.indexed.insert(entity, tags);
+        }
+    }
+
+
+    /// Removes the given entity from the index.
+    fn unindex(&mut self, entity: Entity) {
+        let Some(tags) = self.indexed.remove(&entity) else {
+            return;
+        };
+
+        for tag in tags {
+            if let Some(entities) = self.entities.get_mut(&tag) {
+                entities.remove(&entity);
+                if entities.is_empty() {
+                    self.entities.remove(&tag);
+                }
+            }
+        }
+    }
+}
+
+
+/// A command that attaches or detaches a tag, by name, on an entity.
+#[derive(Debug, Clone)]
+pub struct SetTag {
+    /// The entity to modify.
+    pub entity: Entity,
+
+    /// The name of the tag.
+    pub name: String,
+
+    /// Whether to attach or detach the tag.
+    pub attach: bool,
+}
+
+impl Command for SetTag {
+    fn write(self, world: &mut World) {
+        let tag = world.get_resource_or_insert_with(TagRegistry::default).intern(&self.name);
+
+        let Some(mut entity) = world.get_entity_mut(self.entity) else {
+            return;
+        };
+
+        match entity.get_mut::<Tags>() {
+            Some(mut tags) => {
+                match self.attach {
+                    true => tags.insert(tag),
+                    false => tags.remove(tag),
+                };
+            },
+            None if self.attach => {
+                let mut tags = Tags::default();
+                tags.insert(tag);
+                entity.insert(tags);
+            },
+            None => {},
+        }
+    }
+}
+
+
+/// An extension trait for entity commands that allows for tags to be attached
+/// and detached by name.
+pub trait TagCommandsExt {
+    /// Attaches the tag with the given name to this entity.
+    fn add_tag<N>(&mut self, name: N) -> &mut Self
+    where N: Into<String>;
+
+
+    /// Detaches the tag with the given name from this entity.
+    fn remove_tag<N>(&mut self, name: N) -> &mut Self
+    where N: Into<String>;
+}
+
+impl<'w, 's, 'a> TagCommandsExt for EntityCommands<'w, 's, 'a> {
+    fn add_tag<N>(&mut self, name: N) -> &mut Self
+    where N: Into<String> {
+        let entity = self.id();
+        self.commands().add(SetTag {
+            entity,
+            name: name.into(),
+            attach: true,
+        });
+        self
+    }
+
+
+    fn remove_tag<N>(&mut self, name: N) -> &mut Self
+    where N: Into<String> {
+        let entity = self.id();
+        self.commands().add(SetTag {
+            entity,
+            name: name.into(),
+            attach: false,
+        });
+        self
+    }
+}
+
+
+/// Updates the tag index with the tags of each entity that has changed, and
+/// removes entities whose tags have been removed.
+pub fn index_tags(
+    mut registry: ResMut<TagRegistry>,
+    changed: Query<(Entity, &Tags), Changed<Tags>>,
+    removed: RemovedComponents<Tags>,
+) {
+    for entity in removed.iter() {
+        registry.unindex(entity);
+    }
+
+    for (entity, tags) in changed.iter() {
+        registry.index(entity, tags.iter().collect());
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn intern_tags() {
+        let mut registry = TagRegistry::default();
+        let arena = registry.intern("arena_1");
+        let lobby = registry.intern("lobby");
+
+        assert_eq!(registry.intern("arena_1"), arena);
+        assert_eq!(registry.get("lobby"), Some(lobby));
+        assert_eq!(registry.get("arena_2"), None);
+        assert_eq!(registry.name(arena), Some("arena_1"));
+        assert!(arena != lobby);
+    }
+
+
+    #[test]
+    fn query_by_tag() {
+        let mut app = App::new();
+        app.init_resource::<TagRegistry>().add_system(index_tags);
+
+        let a = app.world.spawn_empty().id();
+        let b = app.world.spawn(Tags::default()).id();
+
+        for (entity, name) in [(a, "arena_1"), (a, "red"), (b, "arena_1")] {
+            SetTag {
+                entity,
+                name: name.to_string(),
+                attach: true,
+            }
+            .write(&mut app.world);
+        }
+        app.update();
+
+        let registry = app.world.resource::<TagRegistry>();
+        let mut arena: Vec<_> = registry.entities_with_tag("arena_1").collect();
+        arena.sort();
+        assert_eq!(arena, vec![a, b]);
+        assert_eq!(registry.entities_with_tag("red").collect::<Vec<_>>(), vec![
+            a
+        ]);
+        assert_eq!(registry.count("blue"), 0);
+        assert!(registry.has_tag(b, "arena_1"));
+
+        SetTag {
+            entity: a,
+            name:   "arena_1".to_string(),
+            attach: false,
+        }
+        .write(&mut app.world);
+        app.world.despawn(b);
+        app.update();
+
+        let registry = app.world.resource::<TagRegistry>();
+        assert_eq!(registry.count("arena_1"), 0);
+        assert_eq!(registry.entities_with_tag("red").collect::<Vec<_>>(), vec![
+            a
+        ]);
+    }
+}
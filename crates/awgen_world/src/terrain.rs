@@ -0,0 +1,169 @@
+//! Built-in world generation stages for shaping the base terrain of a world
+//! and covering it with surface layers.
+
+
+use crate::prelude::{ChunkView, GenerationPhase, GenerationStage};
+use awgen_math::prelude::{Fbm, NoiseFn, PerlinNoise, Region2};
+use bevy::prelude::*;
+
+
+/// A terrain shape stage that fills each column of the world with a solid
+/// block, up to a height that is determined by fractal Perlin noise. The height
+/// of each column is recorded as its surface height.
+#[derive(Debug, Clone)]
+pub struct NoiseTerrain<BlockData> {
+    /// The block that the terrain is made of.
+    pub block: BlockData,
+
+    /// The average height of the terrain surface.
+    pub base_height: f32,
+
+    /// The maximum distance that the terrain surface may rise above or sink
+    /// below the base height.
+    pub amplitude: f32,
+
+    /// The frequency of the terrain noise. Smaller values create wider hills.
+    pub frequency: f32,
+}
+
+impl<BlockData> NoiseTerrain<BlockData> {
+    /// Creates a new noise terrain stage made of the given block, with gentle
+    /// rolling hills around y = 0.
+    pub fn new(block: BlockData) -> Self {
+        Self {
+            block,
+            base_height: 0.0,
+            amplitude: 16.0,
+            frequency: 1.0 / 128.0,
+        }
+    }
+
+
+    /// Gets the height of the terrain surface within the given column.
+    fn height(&self, noise: &impl NoiseFn, column: IVec2) -> i32 {
+        let sample = noise.sample_2d(column.as_vec2());
+        (self.base_height + sample * self.amplitude).floor() as i32
+    }
+}
+
+impl<BlockData> GenerationStage<BlockData> for NoiseTerrain<BlockData>
+where BlockData: Default + Copy + Send + Sync + 'static
+{
+    fn name(&self) -> &str {
+        "noise_terrain"
+    }
+
+
+    fn phase(&self) -> GenerationPhase {
+        GenerationPhase::TerrainShape
+    }
+
+
+    fn generate(&self, view: &mut ChunkView<BlockData>) {
+        let noise = Fbm::new(PerlinNoise::new(view.seed().derive_str("terrain")))
+            .with_frequency(self.frequency);
+
+        let area = view.area();
+        for column in Region2::from(area).iter() {
+            let height = self.height(&noise, column);
+            view.set_surface_height(column, height);
+
+            for y in area.min().y..=height.min(area.max().y) {
+                view.set_block(IVec3::new(column.x, y, column.y), self.block);
+            }
+        }
+    }
+}
+
+
+/// A surface stage that replaces the top blocks of the terrain, which are
+/// exposed to the default block above them, with surface blocks.
+///
+/// The topmost block is replaced with the top block, and the blocks beneath it
+/// are replaced with the filler block, down to the given depth.
+#[derive(Debug, Clone)]
+pub struct SurfaceLayers<BlockData> {
+    /// The block that the terrain is made of, which may be replaced.
+    pub base: BlockData,
+
+    /// The block placed at the very top of the terrain, such as grass.
+    pub top: BlockData,
+
+    /// The block placed beneath the top block, such as dirt.
+    pub filler: BlockData,
+
+    /// The total number of blocks that are replaced within each column,
+    /// including the top block.
+    pub depth: i32,
+}
+
+impl<BlockData> GenerationStage<BlockData> for SurfaceLayers<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn name(&self) -> &str {
+        "surface_layers"
+    }
+
+
+    fn phase(&self) -> GenerationPhase {
+        GenerationPhase::Surface
+    }
+
+
+    fn margin(&self) -> i32 {
+        self.depth
+    }
+
+
+    fn generate(&self, view: &mut ChunkView<BlockData>) {
+        let air = BlockData::default();
+        for pos in view.area().iter() {
+            if view.get_block(pos) != self.base {
+                continue;
+            }
+
+            let exposed = (1..=self.depth).find(|y| view.get_block(pos + IVec3::Y * *y) == air);
+            match exposed {
+                Some(1) => view.set_block(pos, self.top),
+                Some(_) => view.set_block(pos, self.filler),
+                None => {},
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{VoxelWorld, WorldGenerator};
+    use awgen_math::prelude::{chunk_blocks, Region, Seed};
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn terrain_with_surface() {
+        let generator = WorldGenerator::<u8>::new(Seed(7))
+            .with_stage(SurfaceLayers {
+                base:   1,
+                top:    2,
+                filler: 3,
+                depth:  3,
+            })
+            .with_stage(NoiseTerrain::new(1));
+
+        let mut world = VoxelWorld::<u8>::default();
+        for chunk_coords in Region::from_points(IVec3::new(0, -2, 0), IVec3::new(0, 1, 0)).iter() {
+            let chunk = generator.generate_chunk(chunk_coords);
+            world.set_block_region(chunk_blocks(chunk_coords), &chunk.blocks);
+        }
+
+        for x in 0..16 {
+            let column: Vec<u8> =
+                (-32..32).rev().map(|y| world.get_block_data(IVec3::new(x, y, 4))).collect();
+            let surface = column.iter().position(|block| *block != 0).unwrap();
+            assert_eq!(column[surface..surface + 4], [2, 3, 3, 1]);
+            assert!(column[surface + 4..].iter().all(|block| *block == 1));
+        }
+    }
+}
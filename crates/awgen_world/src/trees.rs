@@ -0,0 +1,231 @@
+//! A built-in world generation stage for growing trees on top of the terrain.
+//!
+//! Trees are placed as feature blocks, so their canopies may extend into
+//! neighbouring chunks, even if those chunks have not been generated yet.
+
+
+use crate::prelude::{ChunkView, GenerationPhase, GenerationStage};
+use awgen_math::prelude::{block_to_chunk, chunk_blocks, Region, Seed, SeededRng};
+use bevy::prelude::*;
+
+
+/// A population stage that grows simple trees, made of a trunk and a rounded
+/// canopy of leaves, on top of the terrain surface.
+///
+/// Each tree is only placed by the chunk that contains the bottom of its
+/// trunk. Blocks of the tree that fall within neighbouring chunks are placed as
+/// pending blocks, which are written into those chunks when they are generated.
+#[derive(Debug, Clone)]
+pub struct Trees<BlockData> {
+    /// The unique name of this stage, which is also used to seed tree
+    /// placement.
+    pub name: String,
+
+    /// The block that tree trunks are made of.
+    pub trunk: BlockData,
+
+    /// The block that tree canopies are made of.
+    pub leaves: BlockData,
+
+    /// The surface blocks that trees may grow on. If empty, trees may grow on
+    /// any block.
+    pub on: Vec<BlockData>,
+
+    /// The average number of trees per column of chunks.
+    pub frequency: f32,
+
+    /// The minimum height of tree trunks.
+    pub min_trunk_height: i32,
+
+    /// The maximum height of tree trunks.
+    pub max_trunk_height: i32,
+
+    /// The horizontal radius of tree canopies.
+    pub canopy_radius: i32,
+}
+
+impl<BlockData> Trees<BlockData> {
+    /// Creates a new tree stage with the given name, trunk, and leaves, which
+    /// grows small trees on any surface block.
+    pub fn new(name: impl Into<String>, trunk: BlockData, leaves: BlockData) -> Self {
+        Self {
+            name: name.into(),
+            trunk,
+            leaves,
+            on: vec![],
+            frequency: 2.0,
+            min_trunk_height: 4,
+            max_trunk_height: 6,
+            canopy_radius: 2,
+        }
+    }
+
+
+    /// Gets the number of trees to attempt to grow within a column of chunks,
+    /// using the given random number generator.
+    fn attempts(&self, rng: &mut SeededRng) -> u32 {
+        let whole = self.frequency.max(0.0).floor();
+        whole as u32 + rng.chance(self.frequency - whole) as u32
+    }
+
+
+    /// Grows a single tree with the given trunk height, starting at the given
+    /// root block position.
+    fn grow(&self, view: &mut ChunkView<BlockData>, root: IVec3, trunk_height: i32, seed: Seed)
+    where BlockData: Default + Copy + PartialEq + Send + Sync + 'static {
+        let mut rng = seed.rng();
+        let air = BlockData::default();
+        let top = root + IVec3::Y * (trunk_height - 1);
+        let radius = self.canopy_radius;
+
+        let canopy = Region::from_points(
+            top - IVec3::new(radius, radius, radius),
+            top + IVec3::new(radius, 1, radius),
+        );
+
+        for pos in canopy.iter() {
+            let offset = pos - top;
+            let distance = offset.x * offset.x + offset.z * offset.z + offset.y.max(0) * 4;
+            if distance > radius * radius + 1 {
+                continue;
+            }
+
+            // Randomly trim the corners of the canopy for a less uniform shape.
+            if distance == radius * radius + 1 && rng.chance(0.5) {
+                continue;
+            }
+
+            if view.get_block(pos) == air {
+                view.place_feature_block(pos, self.leaves);
+            }
+        }
+
+        for y in 0..trunk_height {
+            view.place_feature_block(root + IVec3::Y * y, self.trunk);
+        }
+    }
+}
+
+impl<BlockData> GenerationStage<BlockData> for Trees<BlockData>
+where BlockData: Default + Copy + PartialEq + Send + Sync + 'static
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+
+    fn phase(&self) -> GenerationPhase {
+        GenerationPhase::Population
+    }
+
+
+    fn margin(&self) -> i32 {
+        // The ground beneath a tree may be within the chunk below.
+        1
+    }
+
+
+    fn generate(&self, view: &mut ChunkView<BlockData>) {
+        let chunk_coords = view.chunk_coords();
+        let column = IVec3::new(chunk_coords.x, 0, chunk_coords.z);
+        let seed = view.seed().derive_str(&self.name);
+        let mut rng = seed.for_chunk(column).rng();
+        let air = BlockData::default();
+
+        for _ in 0..self.attempts(&mut rng) {
+            let offset = IVec2::new(rng.range_i32(0, 16), rng.range_i32(0, 16));
+            let trunk_height = rng.range_i32(self.min_trunk_height, self.max_trunk_height + 1);
+            let tree_seed = Seed(rng.next_u64());
+
+            let column = chunk_blocks(chunk_coords).min().truncate() + offset;
+            let Some(height) = view.surface_height(column) else {
+                continue;
+            };
+
+            let root = IVec3::new(column.x, height + 1, column.y);
+            if block_to_chunk(root) != chunk_coords {
+                continue;
+            }
+
+            let ground = view.get_block(root - IVec3::Y);
+            if ground == air || (!self.on.is_empty() && !self.on.contains(&ground)) {
+                continue;
+            }
+
+            if view.get_block(root) != air {
+                continue;
+            }
+
+            self.grow(view, root, trunk_height, tree_seed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{ChunkState, NoiseTerrain, VoxelChunkStates, VoxelWorld, WorldGenerator};
+    use pretty_assertions::assert_eq;
+
+
+    fn generator() -> WorldGenerator<u8> {
+        WorldGenerator::<u8>::new(Seed(11))
+            .with_stage(NoiseTerrain::new(1))
+            .with_stage(Trees {
+                frequency: 6.0,
+                ..Trees::new("trees", 2, 3)
+            })
+    }
+
+
+    fn generate(order: impl Iterator<Item = IVec3>) -> VoxelWorld<u8> {
+        let mut generator = generator();
+        let mut world = VoxelWorld::<u8>::default();
+        let mut states = VoxelChunkStates::default();
+
+        for chunk_coords in order {
+            generator.generate_into(chunk_coords, &mut world, &states);
+            states.set_state(chunk_coords, ChunkState::Loaded);
+        }
+
+        world
+    }
+
+
+    #[test]
+    fn trees_cross_chunks() {
+        let chunks = Region::from_points(IVec3::new(-1, -2, -1), IVec3::new(1, 1, 1));
+        let forward = generate(chunks.iter());
+        let backward = generate(chunks.iter().collect::<Vec<_>>().into_iter().rev());
+
+        let mut trunks = 0;
+        let mut leaves = 0;
+        for pos in Region::from_points(IVec3::new(-8, -32, -8), IVec3::new(23, 31, 23)).iter() {
+            let block = forward.get_block_data(pos);
+            assert_eq!(block >= 2, backward.get_block_data(pos) >= 2);
+
+            match block {
+                2 => trunks += 1,
+                3 => leaves += 1,
+                _ => {},
+            }
+        }
+
+        assert!(trunks > 0);
+        assert!(leaves > trunks);
+    }
+
+
+    #[test]
+    fn pending_blocks_wait_for_chunk() {
+        let mut generator = generator();
+        let mut world = VoxelWorld::<u8>::default();
+        let states = VoxelChunkStates::default();
+
+        for chunk_coords in Region::from_points(IVec3::new(0, -2, 0), IVec3::new(0, 1, 0)).iter() {
+            generator.generate_into(chunk_coords, &mut world, &states);
+        }
+
+        assert!(generator.pending_chunks() > 0);
+    }
+}
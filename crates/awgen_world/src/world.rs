@@ -6,7 +6,9 @@
 
 
 use anyhow::Result;
-use awgen_math::region::Region;
+use awgen_math::prelude::{
+    block_to_chunk, chunk_to_region, index_to_local, local_index, region_to_chunk, Region
+};
 use bevy::prelude::*;
 
 
@@ -17,6 +19,9 @@ struct VoxelChunk<BlockData>
 where BlockData: Default + Copy + Send + Sync + 'static {
     /// The block data array for this chunk.
     blocks: Box<[BlockData; 4096]>,
+
+    /// The version of the world that this chunk was last modified in.
+    version: u64,
 }
 
 impl<BlockData> Default for VoxelChunk<BlockData>
@@ -24,7 +29,8 @@ where BlockData: Default + Copy + Send + Sync + 'static
 {
     fn default() -> Self {
         Self {
-            blocks: Box::new([default(); 4096]),
+            blocks:  Box::new([default(); 4096]),
+            version: 0,
         }
     }
 }
@@ -54,6 +60,22 @@ where BlockData: Default + Copy + Send + Sync + 'static
     }
 }
 
+
+/// A component that indicates which voxel world an entity currently resides
+/// within.
+///
+/// The contained entity is the parent entity of the voxel world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Component)]
+#[reflect(Component)]
+pub struct InWorld(pub Entity);
+
+impl Default for InWorld {
+    fn default() -> Self {
+        Self(Entity::from_raw(u32::MAX))
+    }
+}
+
+
 /// A marker component indicating the parent entity of a voxel world.
 #[derive(Debug, Reflect, Component, Default)]
 #[reflect(Component)]
@@ -62,6 +84,11 @@ where BlockData: Default + Copy + Send + Sync + 'static {
     /// A list of all chunk regions within this world.
     #[reflect(ignore)]
     regions: Vec<VoxelRegion<BlockData>>,
+
+    /// A counter that is incremented each time a chunk within this world is
+    /// modified.
+    #[reflect(ignore)]
+    version: u64,
 }
 
 impl<BlockData> VoxelWorld<BlockData>
@@ -72,19 +99,10 @@ where BlockData: Default + Copy + Send + Sync + 'static
     /// If the block position is not within a loaded chunk, then the default
     /// value for the block data is returned.
     pub fn get_block_data(&self, block_pos: IVec3) -> BlockData {
-        let region_coords = block_pos >> 8;
-
-        let chunk_coords: IVec3 = (block_pos >> 4) & 15;
-        let chunk_index = chunk_coords.x * 16 * 16 + chunk_coords.y * 16 + chunk_coords.z;
-
-        let block_coords = block_pos & 15;
-        let block_index = block_coords.x * 16 * 16 + block_coords.y * 16 + block_coords.z;
-
-        self.regions
-            .iter()
-            .find(|r| r.region_coords.eq(&region_coords))
-            .and_then(|r| r.chunks[chunk_index as usize].as_ref())
-            .map_or_else(|| BlockData::default(), |c| c.blocks[block_index as usize])
+        self.get_chunk(block_to_chunk(block_pos)).map_or_else(
+            || BlockData::default(),
+            |c| c.blocks[local_index(block_pos)],
+        )
     }
 
 
@@ -106,25 +124,14 @@ where BlockData: Default + Copy + Send + Sync + 'static
     pub fn get_block_region(&self, region: Region) -> Vec<BlockData> {
         let mut data = vec![BlockData::default(); region.count()];
 
-        for chunk_coords in Region::from_points(region.min() >> 4, region.max() >> 4).iter() {
-            let chunk_index = Region::CHUNK.point_to_index(chunk_coords & 15).unwrap();
-            let region_coords = chunk_coords >> 4;
-            let chunk = self
-                .regions
-                .iter()
-                .find(|r| r.region_coords.eq(&region_coords))
-                .and_then(|r| r.chunks[chunk_index].as_ref());
-
-            let block_region = Region::from_size(chunk_coords << 4, IVec3::new(16, 16, 16));
-            for block in block_region.iter() {
-                if let Ok(data_index) = region.point_to_index(block) {
-                    if let Some(chunk) = chunk {
-                        let index = block_region.point_to_index(block).unwrap();
-                        data[data_index] = chunk.blocks[index];
-                    } else {
-                        data[data_index] = BlockData::default();
-                    }
-                }
+        for (chunk_coords, part) in region.split_by_chunks() {
+            let Some(chunk) = self.get_chunk(chunk_coords) else {
+                continue;
+            };
+
+            for block in part.iter() {
+                let index = region.point_to_index_unchecked(block);
+                data[index] = chunk.blocks[local_index(block)];
             }
         }
 
@@ -132,37 +139,120 @@ where BlockData: Default + Copy + Send + Sync + 'static
     }
 
 
+    /// Sets the data for a cuboid region of blocks all at once.
+    ///
+    /// The data is expected to be in the same layout as the data returned from
+    /// [`VoxelWorld::get_block_region`]. Any unloaded chunks that overlap the
+    /// region are created as needed.
+    ///
+    /// This function panics if the length of the data does not match the
+    /// number of blocks within the region.
+    pub fn set_block_region(&mut self, region: Region, data: &[BlockData]) {
+        if data.len() != region.count() {
+            panic!(
+                "Block data length does not match region size. Expected: {}, Found: {}",
+                region.count(),
+                data.len()
+            );
+        }
+
+        for (chunk_coords, part) in region.split_by_chunks() {
+            let chunk = self.get_chunk_mut(chunk_coords);
+            for block in part.iter() {
+                let index = region.point_to_index_unchecked(block);
+                chunk.blocks[local_index(block)] = data[index];
+            }
+        }
+    }
+
+
     /// Sets the block data at the given block position.
     ///
     /// If the block position is located within an unloaded chunk, a new chunk
     /// created at that location with all default values and the data value
     /// is written to it.
     pub fn set_block_data(&mut self, block_pos: IVec3, data: BlockData) {
-        let region_coords = block_pos >> 8;
-        let chunk_index = Region::CHUNK.point_to_index((block_pos >> 4) & 15).unwrap();
-        let block_index = Region::CHUNK.point_to_index(block_pos & 15).unwrap();
+        let chunk = self.get_chunk_mut(block_to_chunk(block_pos));
+        chunk.blocks[local_index(block_pos)] = data;
+    }
 
-        for region in &mut self.regions {
-            if !region.region_coords.eq(&region_coords) {
-                continue;
-            }
 
-            if let Some(chunk) = &mut region.chunks[chunk_index] {
-                chunk.blocks[block_index] = data;
-            } else {
-                let mut chunk = VoxelChunk::<BlockData>::default();
-                chunk.blocks[block_index] = data;
-                region.chunks[chunk_index] = Some(chunk);
-            }
+    /// Gets the block data array of the chunk at the given chunk coordinates,
+    /// if it exists. Blocks are ordered by their [`local_index`].
+    pub fn get_chunk_blocks(&self, chunk_coords: IVec3) -> Option<&[BlockData; 4096]> {
+        self.get_chunk(chunk_coords).map(|c| c.blocks.as_ref())
+    }
+
 
-            return;
+    /// Gets an iterator over the coordinates of all chunks within this world,
+    /// alongside the version of the world that each chunk was last modified
+    /// in.
+    ///
+    /// Versions only ever increase, so a chunk has been modified since it was
+    /// last seen if its version has changed.
+    pub fn chunk_versions(&self) -> impl Iterator<Item = (IVec3, u64)> + '_ {
+        self.regions.iter().flat_map(|region| {
+            region.chunks.iter().enumerate().filter_map(move |(index, chunk)| {
+                let chunk_coords = region_to_chunk(region.region_coords) + index_to_local(index);
+                chunk.as_ref().map(|c| (chunk_coords, c.version))
+            })
+        })
+    }
+
+
+    /// Removes the chunk at the given chunk coordinates from this world, if it
+    /// exists, returning whether or not a chunk was removed.
+    ///
+    /// Regions that no longer contain any chunks are removed as well.
+    pub fn remove_chunk(&mut self, chunk_coords: IVec3) -> bool {
+        let region_coords = chunk_to_region(chunk_coords);
+        let Some(region_index) =
+            self.regions.iter().position(|r| r.region_coords.eq(&region_coords))
+        else {
+            return false;
+        };
+
+        let region = &mut self.regions[region_index];
+        if region.chunks[local_index(chunk_coords)].take().is_none() {
+            return false;
         }
 
-        let mut region = VoxelRegion::<BlockData>::new(region_coords);
-        let mut chunk = VoxelChunk::<BlockData>::default();
-        chunk.blocks[block_index] = data;
-        region.chunks[chunk_index] = Some(chunk);
-        self.regions.push(region);
+        if region.chunks.iter().all(|c| c.is_none()) {
+            self.regions.remove(region_index);
+        }
+
+        true
+    }
+
+
+    /// Gets the chunk at the given chunk coordinates, if it exists.
+    fn get_chunk(&self, chunk_coords: IVec3) -> Option<&VoxelChunk<BlockData>> {
+        let region_coords = chunk_to_region(chunk_coords);
+        self.regions
+            .iter()
+            .find(|r| r.region_coords.eq(&region_coords))
+            .and_then(|r| r.chunks[local_index(chunk_coords)].as_ref())
+    }
+
+
+    /// Gets the chunk at the given chunk coordinates, creating a new, empty
+    /// chunk if it does not yet exist. The chunk is marked as modified.
+    fn get_chunk_mut(&mut self, chunk_coords: IVec3) -> &mut VoxelChunk<BlockData> {
+        let region_coords = chunk_to_region(chunk_coords);
+        let region_index =
+            match self.regions.iter().position(|r| r.region_coords.eq(&region_coords)) {
+                Some(index) => index,
+                None => {
+                    self.regions.push(VoxelRegion::new(region_coords));
+                    self.regions.len() - 1
+                },
+            };
+
+        self.version += 1;
+        let chunk = self.regions[region_index].chunks[local_index(chunk_coords)]
+            .get_or_insert_with(VoxelChunk::default);
+        chunk.version = self.version;
+        chunk
     }
 }
 
@@ -197,4 +287,62 @@ mod test {
         assert_eq!(data.len(), 4 * 3 * 4);
         assert_eq!(data.iter().filter(|v| **v == 3).count(), 2);
     }
+
+
+    #[test]
+    fn set_block_region() {
+        let mut world = VoxelWorld::<u8>::default();
+        let region = Region::from_points(IVec3::new(-2, 14, 30), IVec3::new(1, 17, 33));
+        let data: Vec<u8> = (0..region.count()).map(|i| i as u8).collect();
+
+        world.set_block_region(region, &data);
+
+        assert_eq!(world.get_block_region(region), data);
+        assert_eq!(world.get_block_data(IVec3::new(-2, 14, 30)), 0);
+        assert_eq!(
+            world.get_block_data(IVec3::new(1, 17, 33)),
+            (region.count() - 1) as u8
+        );
+        assert_eq!(world.get_block_data(IVec3::new(2, 17, 33)), 0);
+    }
+
+
+    #[test]
+    fn chunk_versions() {
+        let mut world = VoxelWorld::<u8>::default();
+        world.set_block_data(IVec3::new(-1, 11, 4), 3);
+        world.set_block_data(IVec3::new(40, -2, 5), 3);
+
+        let mut versions: Vec<_> = world.chunk_versions().collect();
+        versions.sort_by_key(|(_, version)| *version);
+        assert_eq!(versions, vec![
+            (IVec3::new(-1, 0, 0), 1),
+            (IVec3::new(2, -1, 0), 2)
+        ]);
+
+        world.set_block_data(IVec3::new(-2, 12, 4), 5);
+        assert!(world.chunk_versions().any(|v| v == (IVec3::new(-1, 0, 0), 3)));
+        assert_eq!(
+            world.get_chunk_blocks(IVec3::new(-1, 0, 0)).unwrap()
+                [local_index(IVec3::new(-2, 12, 4))],
+            5
+        );
+        assert!(world.get_chunk_blocks(IVec3::ZERO).is_none());
+    }
+
+
+    #[test]
+    fn remove_chunk() {
+        let mut world = VoxelWorld::<u8>::default();
+        world.set_block_data(IVec3::new(3, 4, 5), 7);
+        world.set_block_data(IVec3::new(20, 4, 5), 7);
+
+        assert!(world.remove_chunk(IVec3::ZERO));
+        assert!(!world.remove_chunk(IVec3::ZERO));
+        assert_eq!(world.get_block_data(IVec3::new(3, 4, 5)), 0);
+        assert_eq!(world.get_block_data(IVec3::new(20, 4, 5)), 7);
+
+        assert!(world.remove_chunk(IVec3::new(1, 0, 0)));
+        assert_eq!(world.chunk_versions().count(), 0);
+    }
 }
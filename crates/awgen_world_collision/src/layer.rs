@@ -0,0 +1,248 @@
+//! The collision layer of a voxel world, which stores a [`ChunkMask`] for each
+//! chunk and answers overlap queries against the solid blocks of the world.
+
+
+use crate::prelude::ChunkMask;
+use awgen_math::prelude::{block_to_chunk, Aabb, GridRaycast, RaycastStep, Region};
+use awgen_world::prelude::{BlockSolidity, VoxelWorld};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+
+/// A component that stores which blocks are solid within the voxel world that
+/// it is attached to.
+///
+/// The collision layer is maintained automatically from the block data layer
+/// of the world, and is only rebuilt for chunks that have been modified.
+#[derive(Debug, Clone, Default, Component)]
+pub struct CollisionLayer {
+    /// The solid block masks of all chunks that contain at least one solid
+    /// block.
+    masks: HashMap<IVec3, ChunkMask>,
+
+    /// The last seen version of each chunk within the voxel world.
+    versions: HashMap<IVec3, u64>,
+}
+
+impl CollisionLayer {
+    /// Rebuilds the masks of all chunks within the given voxel world that have
+    /// been modified since this layer was last updated.
+    pub fn update<BlockData>(&mut self, world: &VoxelWorld<BlockData>)
+    where BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static {
+        for (chunk_coords, version) in world.chunk_versions() {
+            if self.versions.insert(chunk_coords, version) == Some(version) {
+                continue;
+            }
+
+            let Some(blocks) = world.get_chunk_blocks(chunk_coords) else {
+                continue;
+            };
+
+            let mask = ChunkMask::from_blocks(blocks);
+            match mask.is_empty() {
+                true => self.masks.remove(&chunk_coords),
+                false => self.masks.insert(chunk_coords, mask),
+            };
+        }
+    }
+
+
+    /// Gets the solid block mask of the chunk at the given chunk coordinates.
+    ///
+    /// If the chunk contains no solid blocks, or does not exist, `None` is
+    /// returned.
+    pub fn get_mask(&self, chunk_coords: IVec3) -> Option<&ChunkMask> {
+        self.masks.get(&chunk_coords)
+    }
+
+
+    /// Gets whether or not the block at the given block position is solid.
+    pub fn is_solid(&self, block_pos: IVec3) -> bool {
+        self.masks
+            .get(&block_to_chunk(block_pos))
+            .is_some_and(|mask| mask.get(block_pos))
+    }
+
+
+    /// Checks whether or not the given bounding box overlaps any solid block.
+    ///
+    /// Blocks that only touch the edges of the bounding box are not considered
+    /// to be overlapping.
+    pub fn overlaps(&self, aabb: &Aabb) -> bool {
+        let Some(region) = block_region(aabb) else {
+            return false;
+        };
+
+        region.split_by_chunks().any(|(chunk_coords, part)| {
+            self.masks
+                .get(&chunk_coords)
+                .is_some_and(|mask| part.iter().any(|pos| mask.get(pos)))
+        })
+    }
+
+
+    /// Gets the positions of all solid blocks that overlap the given bounding
+    /// box.
+    ///
+    /// Blocks that only touch the edges of the bounding box are not considered
+    /// to be overlapping.
+    pub fn solid_blocks(&self, aabb: &Aabb) -> Vec<IVec3> {
+        let Some(region) = block_region(aabb) else {
+            return vec![];
+        };
+
+        region
+            .split_by_chunks()
+            .filter_map(|(chunk_coords, part)| Some((self.masks.get(&chunk_coords)?, part)))
+            .flat_map(|(mask, part)| part.iter().filter(|pos| mask.get(*pos)))
+            .collect()
+    }
+
+
+    /// Finds the first solid block that is hit by a ray starting at the given
+    /// origin and traveling along the given direction, up to the given
+    /// distance.
+    ///
+    /// The returned step contains the face of the block that the ray entered
+    /// through, which is `None` if the origin is within a solid block.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RaycastStep> {
+        GridRaycast::new(origin, direction, max_distance).find(|step| self.is_solid(step.cell))
+    }
+}
+
+
+/// Gets the region of all blocks that overlap the given bounding box. If the
+/// bounding box has no volume along any axis, no blocks overlap it and `None`
+/// is returned.
+fn block_region(aabb: &Aabb) -> Option<Region> {
+    let min = aabb.min().floor().as_ivec3();
+    let max = aabb.max().ceil().as_ivec3() - 1;
+
+    match max.cmpge(min).all() {
+        true => Some(Region::from_points(min, max)),
+        false => None,
+    }
+}
+
+
+/// Builds and inserts a collision layer into each voxel world containing a
+/// block data layer of the given type that does not have one yet.
+pub fn insert_collision_layers<BlockData>(
+    worlds: Query<(Entity, &VoxelWorld<BlockData>), Without<CollisionLayer>>,
+    mut commands: Commands,
+) where
+    BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static,
+{
+    for (entity, world) in worlds.iter() {
+        let mut layer = CollisionLayer::default();
+        layer.update(world);
+        commands.entity(entity).insert(layer);
+    }
+}
+
+
+/// Updates the collision layer of each voxel world whose block data layer of
+/// the given type has been modified.
+pub fn update_collision_layers<BlockData>(
+    mut worlds: Query<
+        (&VoxelWorld<BlockData>, &mut CollisionLayer),
+        Changed<VoxelWorld<BlockData>>,
+    >,
+) where
+    BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static,
+{
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("collision_layer", stage = "update_masks").entered();
+
+    for (world, mut layer) in worlds.iter_mut() {
+        layer.update(world);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_math::prelude::Direction;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Stone,
+    }
+
+    impl BlockSolidity for TestBlock {
+        fn is_solid(&self) -> bool {
+            *self == TestBlock::Stone
+        }
+    }
+
+
+    #[test]
+    fn update_modified_chunks() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::new(-3, 4, 20), TestBlock::Stone);
+
+        let mut layer = CollisionLayer::default();
+        layer.update(&world);
+        assert!(layer.is_solid(IVec3::new(-3, 4, 20)));
+        assert!(!layer.is_solid(IVec3::new(-3, 5, 20)));
+
+        world.set_block_data(IVec3::new(-3, 4, 20), TestBlock::Air);
+        layer.update(&world);
+        assert!(!layer.is_solid(IVec3::new(-3, 4, 20)));
+        assert_eq!(layer.get_mask(IVec3::new(-1, 0, 1)), None);
+    }
+
+
+    #[test]
+    fn aabb_overlap() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        for pos in Region::from_points(IVec3::new(-4, -1, -4), IVec3::new(4, -1, 4)).iter() {
+            world.set_block_data(pos, TestBlock::Stone);
+        }
+
+        let mut layer = CollisionLayer::default();
+        layer.update(&world);
+
+        let standing = Aabb::new(Vec3::new(-0.3, 0.0, -0.3), Vec3::new(0.3, 1.8, 0.3));
+        assert!(!layer.overlaps(&standing));
+
+        let sinking = Aabb::new(Vec3::new(-0.3, -0.1, -0.3), Vec3::new(0.3, 1.7, 0.3));
+        assert!(layer.overlaps(&sinking));
+        assert_eq!(layer.solid_blocks(&sinking), vec![
+            IVec3::new(-1, -1, -1),
+            IVec3::new(-1, -1, 0),
+            IVec3::new(0, -1, -1),
+            IVec3::new(0, -1, 0),
+        ]);
+
+        let flat = Aabb::new(Vec3::new(0.0, -0.5, 0.0), Vec3::new(0.0, 0.5, 1.0));
+        assert!(!layer.overlaps(&flat));
+        assert_eq!(layer.solid_blocks(&flat), vec![]);
+    }
+
+
+    #[test]
+    fn raycast_solid_block() {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        world.set_block_data(IVec3::new(0, 0, -3), TestBlock::Stone);
+
+        let mut layer = CollisionLayer::default();
+        layer.update(&world);
+
+        let hit = layer.raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::NEG_Z, 5.0).unwrap();
+        assert_eq!(hit.cell, IVec3::new(0, 0, -3));
+        assert_eq!(hit.face, Some(Direction::PosZ));
+        assert_eq!(hit.distance, 2.5);
+
+        assert_eq!(
+            layer.raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::NEG_Z, 2.0),
+            None
+        );
+        assert_eq!(layer.raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::X, 5.0), None);
+    }
+}
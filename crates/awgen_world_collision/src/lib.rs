@@ -0,0 +1,68 @@
+//! The solid block collision layer for Awgen.
+//!
+//! This crate maintains a compact bitmask of which blocks are solid within each
+//! voxel world, so that physics queries do not need to read the full block data
+//! of the world every tick. It also provides a pathfinder for walking entities,
+//! which searches the collision layer for a path around obstacles.
+
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+#![warn(rustdoc::invalid_codeblock_attributes)]
+#![warn(rustdoc::invalid_html_tags)]
+
+
+pub mod layer;
+pub mod mask;
+pub mod path_follow;
+pub mod pathfinding;
+
+
+/// A re-export of all components and systems defined within this crate.
+pub mod prelude {
+    pub use super::layer::*;
+    pub use super::mask::*;
+    pub use super::path_follow::*;
+    pub use super::pathfinding::*;
+    pub use super::*;
+}
+
+
+use awgen_world::prelude::BlockSolidity;
+use bevy::prelude::*;
+use prelude::*;
+use std::marker::PhantomData;
+
+
+/// The world collision plugin implementation, which maintains a
+/// [`CollisionLayer`] for each voxel world containing a block data layer of the
+/// given type.
+#[derive(Debug, Clone, Default)]
+pub struct WorldCollisionPlugin<BlockData>
+where BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static {
+    /// To allow for the existence of the BlockData generic.
+    _data: PhantomData<BlockData>,
+}
+
+impl<BlockData> Plugin for WorldCollisionPlugin<BlockData>
+where BlockData: BlockSolidity + Default + Copy + Send + Sync + 'static
+{
+    fn build(&self, app: &mut App) {
+        app.add_system(insert_collision_layers::<BlockData>).add_system(
+            update_collision_layers::<BlockData>.after(insert_collision_layers::<BlockData>),
+        );
+    }
+}
+
+
+/// The pathfinding plugin implementation, which moves entities with a
+/// [`PathFollow`] component along their path on each physics frame.
+///
+/// This plugin requires the physics plugin.
+#[derive(Debug, Clone, Default)]
+pub struct PathfindingPlugin;
+
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage("tick", follow_paths);
+    }
+}
@@ -0,0 +1,123 @@
+//! A packed bitmask of the solid blocks within a single chunk.
+
+
+use awgen_math::prelude::local_index;
+use awgen_world::prelude::BlockSolidity;
+use bevy::prelude::*;
+
+
+/// The number of 64-bit words within a chunk mask.
+const MASK_WORDS: usize = 4096 / 64;
+
+
+/// A bitmask that stores whether or not each block within a chunk is solid,
+/// using a single bit per block. Blocks are ordered by their local index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkMask {
+    /// The packed bits of this mask.
+    bits: Box<[u64; MASK_WORDS]>,
+}
+
+impl Default for ChunkMask {
+    fn default() -> Self {
+        Self {
+            bits: Box::new([0; MASK_WORDS]),
+        }
+    }
+}
+
+impl ChunkMask {
+    /// Creates a new chunk mask from the given block data array of a chunk,
+    /// where each bit is set if the corresponding block is solid.
+    pub fn from_blocks<BlockData>(blocks: &[BlockData; 4096]) -> Self
+    where BlockData: BlockSolidity {
+        let mut mask = Self::default();
+        for (word, chunk) in mask.bits.iter_mut().zip(blocks.chunks_exact(64)) {
+            for (bit, block) in chunk.iter().enumerate() {
+                if block.is_solid() {
+                    *word |= 1 << bit;
+                }
+            }
+        }
+        mask
+    }
+
+
+    /// Gets whether or not the block at the given position is solid. Only the
+    /// local coordinates of the position within its chunk are considered.
+    pub fn get(&self, block_pos: IVec3) -> bool {
+        let index = local_index(block_pos);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+
+    /// Sets whether or not the block at the given position is solid. Only the
+    /// local coordinates of the position within its chunk are considered.
+    pub fn set(&mut self, block_pos: IVec3, solid: bool) {
+        let index = local_index(block_pos);
+        match solid {
+            true => self.bits[index / 64] |= 1 << (index % 64),
+            false => self.bits[index / 64] &= !(1 << (index % 64)),
+        }
+    }
+
+
+    /// Gets whether or not this mask contains no solid blocks.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|word| *word == 0)
+    }
+
+
+    /// Gets the number of solid blocks within this mask.
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    struct TestBlock(bool);
+
+    impl BlockSolidity for TestBlock {
+        fn is_solid(&self) -> bool {
+            self.0
+        }
+    }
+
+
+    #[test]
+    fn read_write_mask() {
+        let mut mask = ChunkMask::default();
+        assert!(mask.is_empty());
+
+        mask.set(IVec3::new(3, 15, 0), true);
+        mask.set(IVec3::new(-1, 20, 7), true);
+        assert!(mask.get(IVec3::new(3, 15, 0)));
+        assert!(mask.get(IVec3::new(15, 4, 7)));
+        assert!(!mask.get(IVec3::new(3, 14, 0)));
+        assert_eq!(mask.count(), 2);
+
+        mask.set(IVec3::new(3, 15, 0), false);
+        assert!(!mask.get(IVec3::new(3, 15, 0)));
+        assert_eq!(mask.count(), 1);
+    }
+
+
+    #[test]
+    fn mask_from_blocks() {
+        let mut blocks = [TestBlock(false); 4096];
+        blocks[local_index(IVec3::new(1, 2, 3))] = TestBlock(true);
+        blocks[local_index(IVec3::new(15, 15, 15))] = TestBlock(true);
+
+        let mask = ChunkMask::from_blocks(&blocks);
+        assert!(mask.get(IVec3::new(1, 2, 3)));
+        assert!(mask.get(IVec3::new(15, 15, 15)));
+        assert_eq!(mask.count(), 2);
+    }
+}
@@ -0,0 +1,134 @@
+//! Components and systems for moving entities along a path that was found by
+//! the pathfinder.
+
+
+use awgen_physics::prelude::{PhysicsTickrate, Position, VelocitySource};
+use bevy::prelude::*;
+
+
+/// The distance, in meters, at which an entity is considered to have reached a
+/// waypoint of its path.
+const WAYPOINT_RADIUS: f32 = 0.05;
+
+
+/// A component that moves an entity along a path of waypoints, by setting the
+/// force of its velocity source on each physics frame.
+///
+/// The entity must also be a movable velocity source for the force to be
+/// applied. Once the last waypoint has been reached, the force is set to zero.
+#[derive(Debug, Clone, Component)]
+pub struct PathFollow {
+    /// The waypoints of the path, in the order that they are visited.
+    waypoints: Vec<Vec3>,
+
+    /// The index of the waypoint that the entity is currently moving towards.
+    next: usize,
+
+    /// The movement speed of the entity, in meters per second.
+    pub speed: f32,
+}
+
+impl PathFollow {
+    /// Creates a new path follower for the given path of block positions,
+    /// such as one returned by the pathfinder. Each waypoint is placed at the
+    /// bottom center of its block, where the feet of the entity would stand.
+    pub fn new(path: &[IVec3], speed: f32) -> Self {
+        Self {
+            waypoints: path.iter().map(|pos| pos.as_vec3() + Vec3::new(0.5, 0.0, 0.5)).collect(),
+            next: 0,
+            speed,
+        }
+    }
+
+
+    /// Gets the waypoints of this path.
+    pub fn waypoints(&self) -> &[Vec3] {
+        &self.waypoints
+    }
+
+
+    /// Gets the waypoint that the entity is currently moving towards, or
+    /// `None` if the end of the path has been reached.
+    pub fn next_waypoint(&self) -> Option<Vec3> {
+        self.waypoints.get(self.next).copied()
+    }
+
+
+    /// Gets whether or not the entity has reached the end of this path.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.waypoints.len()
+    }
+}
+
+
+/// Called each physics frame to point the velocity of each entity that is
+/// following a path towards its next waypoint.
+pub fn follow_paths(
+    tickrate: Res<PhysicsTickrate>,
+    mut followers: Query<(&Position, &mut PathFollow, &mut VelocitySource)>,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("pathfinding", stage = "follow_paths").entered();
+
+    for (position, mut path, mut velocity) in followers.iter_mut() {
+        let distance = path.speed * tickrate.delta();
+        velocity.force = Vec3::ZERO;
+
+        while let Some(waypoint) = path.next_waypoint() {
+            let offset = waypoint - position.translation;
+            if offset.length() > WAYPOINT_RADIUS {
+                velocity.force = offset.clamp_length_max(distance);
+                break;
+            }
+
+            path.next += 1;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_physics::prelude::{apply_velocity, Movable};
+    use pretty_assertions::assert_eq;
+
+
+    #[test]
+    fn follow_to_end() {
+        let mut app = App::new();
+        app.insert_resource(PhysicsTickrate::new(10.0))
+            .add_system(follow_paths)
+            .add_system(apply_velocity.after(follow_paths));
+
+        let path = [IVec3::new(0, 0, 0), IVec3::new(1, 0, 0), IVec3::new(1, 1, 0)];
+        let entity = app
+            .world
+            .spawn((
+                Position {
+                    translation: Vec3::new(0.5, 0.0, 0.5),
+                    ..default()
+                },
+                VelocitySource::default(),
+                Movable::default(),
+                PathFollow::new(&path, 5.0),
+            ))
+            .id();
+
+        app.update();
+        let position = app.world.get::<Position>(entity).unwrap();
+        assert_eq!(position.translation, Vec3::new(1.0, 0.0, 0.5));
+
+        for _ in 0..4 {
+            app.update();
+        }
+
+        let position = app.world.get::<Position>(entity).unwrap();
+        assert_eq!(position.translation, Vec3::new(1.5, 1.0, 0.5));
+        assert!(app.world.get::<PathFollow>(entity).unwrap().is_finished());
+        assert_eq!(
+            app.world.get::<VelocitySource>(entity).unwrap().force,
+            Vec3::ZERO
+        );
+    }
+}
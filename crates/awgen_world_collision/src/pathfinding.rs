@@ -0,0 +1,444 @@
+//! An A* pathfinder for walking entities, which searches the solid blocks of a
+//! [`CollisionLayer`] for a path between two block positions.
+//!
+//! Paths are made of the block positions that the feet of an entity occupy. An
+//! entity may walk onto a neighbouring block that has solid ground beneath it,
+//! jump up onto a higher block, or fall down onto a lower block, each within
+//! the limits of its [`PathfindingSettings`].
+
+
+use crate::prelude::CollisionLayer;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+
+
+/// The four horizontal directions that an entity may walk in.
+const CARDINALS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+
+/// The distance, in blocks, between samples when checking whether a straight
+/// line between two path nodes may be walked.
+const SMOOTHING_STEP: f32 = 0.25;
+
+
+/// The movement limits and costs of the entity that a path is found for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathfindingSettings {
+    /// The height of the entity, in blocks. Every block within this height
+    /// must be empty for the entity to stand there.
+    pub height: i32,
+
+    /// The maximum number of blocks that the entity may jump up in a single
+    /// step.
+    pub max_jump: i32,
+
+    /// The maximum number of blocks that the entity may fall down in a single
+    /// step.
+    pub max_fall: i32,
+
+    /// The additional cost of each block that the entity jumps up.
+    pub jump_cost: f32,
+
+    /// The additional cost of each block that the entity falls down.
+    pub fall_cost: f32,
+
+    /// The maximum number of nodes that may be expanded before the search
+    /// gives up. This limits the cost of searching for unreachable goals.
+    pub max_nodes: usize,
+}
+
+impl Default for PathfindingSettings {
+    fn default() -> Self {
+        Self {
+            height:    2,
+            max_jump:  1,
+            max_fall:  3,
+            jump_cost: 2.0,
+            fall_cost: 0.5,
+            max_nodes: 4096,
+        }
+    }
+}
+
+
+/// A node within the open set of the A* search.
+#[derive(Debug, Clone, Copy)]
+struct OpenNode {
+    /// The block position of this node.
+    pos: IVec3,
+
+    /// The cost of the cheapest known path from the start to this node.
+    cost: f32,
+
+    /// The estimated total cost of a path from the start to the goal through
+    /// this node.
+    estimate: f32,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so that the binary heap pops the lowest estimate first.
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+
+/// Finds the cheapest path for an entity to walk from the start block position
+/// to the goal block position, including both ends.
+///
+/// Returns `None` if the start or the goal cannot be stood on, or if no path
+/// was found before the node limit of the settings was reached.
+pub fn find_path(
+    layer: &CollisionLayer,
+    start: IVec3,
+    goal: IVec3,
+    settings: &PathfindingSettings,
+) -> Option<Vec<IVec3>> {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("pathfinding", stage = "find_path").entered();
+
+    if !is_walkable(layer, start, settings.height) || !is_walkable(layer, goal, settings.height) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut costs: HashMap<IVec3, f32> = default();
+    let mut parents: HashMap<IVec3, IVec3> = default();
+    let mut expanded = 0;
+
+    costs.insert(start, 0.0);
+    open.push(OpenNode {
+        pos:      start,
+        cost:     0.0,
+        estimate: heuristic(start, goal),
+    });
+
+    while let Some(node) = open.pop() {
+        if node.pos == goal {
+            let mut path = vec![goal];
+            while let Some(parent) = parents.get(path.last().unwrap()) {
+                path.push(*parent);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        if costs.get(&node.pos).is_some_and(|cost| *cost < node.cost) {
+            continue;
+        }
+
+        expanded += 1;
+        if expanded > settings.max_nodes {
+            return None;
+        }
+
+        for (next, step_cost) in neighbours(layer, node.pos, settings) {
+            let cost = node.cost + step_cost;
+            if costs.get(&next).is_some_and(|known| *known <= cost) {
+                continue;
+            }
+
+            costs.insert(next, cost);
+            parents.insert(next, node.pos);
+            open.push(OpenNode {
+                pos: next,
+                cost,
+                estimate: cost + heuristic(next, goal),
+            });
+        }
+    }
+
+    None
+}
+
+
+/// Removes unnecessary nodes from the given path, so that an entity following
+/// it walks in straight lines rather than along the block grid.
+///
+/// A node is removed if the entity can walk in a straight line, on the same
+/// level, from the previous remaining node to the node after it.
+pub fn smooth_path(
+    layer: &CollisionLayer,
+    path: &[IVec3],
+    settings: &PathfindingSettings,
+) -> Vec<IVec3> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+
+    let mut smoothed = vec![path[0]];
+    let mut anchor = 0;
+    while anchor < path.len() - 1 {
+        let mut next = anchor + 1;
+        while next + 1 < path.len()
+            && can_walk_straight(layer, path[anchor], path[next + 1], settings)
+        {
+            next += 1;
+        }
+
+        smoothed.push(path[next]);
+        anchor = next;
+    }
+
+    smoothed
+}
+
+
+/// Gets whether or not every block within the given height, starting at the
+/// given block position, is empty.
+pub fn is_clear(layer: &CollisionLayer, block_pos: IVec3, height: i32) -> bool {
+    (0..height).all(|y| !layer.is_solid(block_pos + IVec3::Y * y))
+}
+
+
+/// Gets whether or not an entity of the given height can stand with its feet
+/// at the given block position.
+pub fn is_walkable(layer: &CollisionLayer, block_pos: IVec3, height: i32) -> bool {
+    layer.is_solid(block_pos - IVec3::Y) && is_clear(layer, block_pos, height)
+}
+
+
+/// Estimates the cost of walking between two block positions. Only the
+/// horizontal distance is used, as falling may cost less than walking the same
+/// distance.
+fn heuristic(from: IVec3, to: IVec3) -> f32 {
+    Vec2::new((to.x - from.x) as f32, (to.z - from.z) as f32).length()
+}
+
+
+/// Gets all block positions that an entity may move to in a single step from
+/// the given block position, alongside the cost of each step.
+fn neighbours(
+    layer: &CollisionLayer,
+    pos: IVec3,
+    settings: &PathfindingSettings,
+) -> Vec<(IVec3, f32)> {
+    let mut steps: Vec<_> =
+        CARDINALS.iter().filter_map(|dir| step(layer, pos, *dir, settings)).collect();
+
+    for (a, b) in [(0, 2), (0, 3), (1, 2), (1, 3)] {
+        let next = pos + CARDINALS[a] + CARDINALS[b];
+        if is_walkable(layer, next, settings.height)
+            && is_clear(layer, pos + CARDINALS[a], settings.height)
+            && is_clear(layer, pos + CARDINALS[b], settings.height)
+        {
+            steps.push((next, SQRT_2));
+        }
+    }
+
+    steps
+}
+
+
+/// Gets the block position that an entity reaches by moving one block in the
+/// given horizontal direction, walking, jumping, or falling as needed,
+/// alongside the cost of that step.
+fn step(
+    layer: &CollisionLayer,
+    pos: IVec3,
+    dir: IVec3,
+    settings: &PathfindingSettings,
+) -> Option<(IVec3, f32)> {
+    let next = pos + dir;
+    if is_walkable(layer, next, settings.height) {
+        return Some((next, 1.0));
+    }
+
+    if is_clear(layer, next, settings.height) {
+        for fall in 1..=settings.max_fall {
+            let below = next - IVec3::Y * fall;
+            if layer.is_solid(below) {
+                break;
+            }
+
+            if layer.is_solid(below - IVec3::Y) {
+                return Some((below, 1.0 + settings.fall_cost * fall as f32));
+            }
+        }
+
+        return None;
+    }
+
+    for jump in 1..=settings.max_jump {
+        if layer.is_solid(pos + IVec3::Y * (settings.height - 1 + jump)) {
+            break;
+        }
+
+        let above = next + IVec3::Y * jump;
+        if is_walkable(layer, above, settings.height) {
+            return Some((above, 1.0 + settings.jump_cost * jump as f32));
+        }
+    }
+
+    None
+}
+
+
+/// Checks whether or not an entity can walk in a straight line between two
+/// block positions on the same level, without stepping into a block that it
+/// cannot stand on or cutting across the corner of a solid block.
+fn can_walk_straight(
+    layer: &CollisionLayer,
+    from: IVec3,
+    to: IVec3,
+    settings: &PathfindingSettings,
+) -> bool {
+    if from.y != to.y {
+        return false;
+    }
+
+    let start = Vec2::new(from.x as f32, from.z as f32) + 0.5;
+    let end = Vec2::new(to.x as f32, to.z as f32) + 0.5;
+    let samples = ((end - start).length() / SMOOTHING_STEP).ceil() as i32;
+
+    let mut previous = from;
+    for i in 1..=samples {
+        let point = start.lerp(end, i as f32 / samples as f32).floor().as_ivec2();
+        let block = IVec3::new(point.x, from.y, point.y);
+        if block == previous {
+            continue;
+        }
+
+        if !is_walkable(layer, block, settings.height) {
+            return false;
+        }
+
+        if block.x != previous.x && block.z != previous.z {
+            let side_a = IVec3::new(block.x, from.y, previous.z);
+            let side_b = IVec3::new(previous.x, from.y, block.z);
+            if !is_walkable(layer, side_a, settings.height)
+                || !is_walkable(layer, side_b, settings.height)
+            {
+                return false;
+            }
+        }
+
+        previous = block;
+    }
+
+    true
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use awgen_math::prelude::Region;
+    use awgen_world::prelude::{BlockSolidity, VoxelWorld};
+    use pretty_assertions::assert_eq;
+
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    enum TestBlock {
+        #[default]
+        Air,
+        Stone,
+    }
+
+    impl BlockSolidity for TestBlock {
+        fn is_solid(&self) -> bool {
+            *self == TestBlock::Stone
+        }
+    }
+
+
+    /// Creates a collision layer with a 16x16 floor at y = -1, plus the given
+    /// solid blocks.
+    fn layer(solid: impl IntoIterator<Item = IVec3>) -> CollisionLayer {
+        let mut world = VoxelWorld::<TestBlock>::default();
+        for pos in Region::from_points(IVec3::new(0, -1, 0), IVec3::new(15, -1, 15)).iter() {
+            world.set_block_data(pos, TestBlock::Stone);
+        }
+
+        for pos in solid {
+            world.set_block_data(pos, TestBlock::Stone);
+        }
+
+        let mut layer = CollisionLayer::default();
+        layer.update(&world);
+        layer
+    }
+
+
+    #[test]
+    fn straight_path() {
+        let layer = layer([]);
+        let settings = PathfindingSettings::default();
+
+        let path = find_path(&layer, IVec3::new(1, 0, 1), IVec3::new(6, 0, 1), &settings).unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(smooth_path(&layer, &path, &settings), vec![
+            IVec3::new(1, 0, 1),
+            IVec3::new(6, 0, 1)
+        ]);
+    }
+
+
+    #[test]
+    fn jump_and_fall() {
+        let step = Region::from_points(IVec3::new(4, 0, 0), IVec3::new(4, 0, 15));
+        let layer = layer(step.iter());
+        let settings = PathfindingSettings::default();
+
+        let path = find_path(&layer, IVec3::new(2, 0, 5), IVec3::new(6, 0, 5), &settings).unwrap();
+        assert_eq!(path, vec![
+            IVec3::new(2, 0, 5),
+            IVec3::new(3, 0, 5),
+            IVec3::new(4, 1, 5),
+            IVec3::new(5, 0, 5),
+            IVec3::new(6, 0, 5),
+        ]);
+    }
+
+
+    #[test]
+    fn walk_around_wall() {
+        let wall = Region::from_points(IVec3::new(4, 0, 0), IVec3::new(4, 2, 12));
+        let layer = layer(wall.iter());
+        let settings = PathfindingSettings::default();
+
+        let path = find_path(&layer, IVec3::new(2, 0, 5), IVec3::new(6, 0, 5), &settings).unwrap();
+        assert!(path.iter().any(|pos| pos.z > 12));
+        assert!(path.iter().all(|pos| pos.y == 0));
+
+        let smoothed = smooth_path(&layer, &path, &settings);
+        assert!(smoothed.len() < path.len());
+        for pair in smoothed.windows(2) {
+            assert!(can_walk_straight(&layer, pair[0], pair[1], &settings));
+        }
+    }
+
+
+    #[test]
+    fn unreachable_goal() {
+        let wall = Region::from_points(IVec3::new(4, 0, 0), IVec3::new(4, 2, 15));
+        let layer = layer(wall.iter());
+        let settings = PathfindingSettings::default();
+
+        assert_eq!(
+            find_path(&layer, IVec3::new(2, 0, 5), IVec3::new(6, 0, 5), &settings),
+            None
+        );
+        assert_eq!(
+            find_path(&layer, IVec3::new(2, 5, 5), IVec3::new(3, 0, 5), &settings),
+            None
+        );
+    }
+}
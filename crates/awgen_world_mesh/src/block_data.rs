@@ -3,6 +3,11 @@
 
 
 use crate::prelude::ChunkMesher;
+use anyhow::bail;
+use awgen_math::prelude::Direction;
+use awgen_world::prelude::{
+    BlockHardness, BlockItem, BlockMapColor, BlockResistance, BlockSolidity
+};
 use bevy::prelude::*;
 use bitflags::bitflags;
 
@@ -47,37 +52,39 @@ impl BlockOcclusion {
     ///
     /// This effect is applied for all defined directional values.
     pub fn opposite_face(&self) -> Self {
-        let mut value = BlockOcclusion::empty();
+        let mut value = *self & BlockOcclusion::INNER;
 
-        if self.contains(BlockOcclusion::INNER) {
-            value |= BlockOcclusion::INNER;
+        for dir in Direction::ALL {
+            if self.contains(dir.into()) {
+                value |= dir.opposite().into();
+            }
         }
 
-        if self.contains(BlockOcclusion::POS_X) {
-            value |= BlockOcclusion::NEG_X;
-        }
-
-        if self.contains(BlockOcclusion::NEG_X) {
-            value |= BlockOcclusion::POS_X;
-        }
-
-        if self.contains(BlockOcclusion::POS_Y) {
-            value |= BlockOcclusion::NEG_Y;
-        }
+        value
+    }
+}
 
-        if self.contains(BlockOcclusion::NEG_Y) {
-            value |= BlockOcclusion::POS_Y;
+impl From<Direction> for BlockOcclusion {
+    fn from(dir: Direction) -> Self {
+        match dir {
+            Direction::PosX => BlockOcclusion::POS_X,
+            Direction::NegX => BlockOcclusion::NEG_X,
+            Direction::PosY => BlockOcclusion::POS_Y,
+            Direction::NegY => BlockOcclusion::NEG_Y,
+            Direction::PosZ => BlockOcclusion::POS_Z,
+            Direction::NegZ => BlockOcclusion::NEG_Z,
         }
+    }
+}
 
-        if self.contains(BlockOcclusion::POS_Z) {
-            value |= BlockOcclusion::NEG_Z;
-        }
+impl TryFrom<BlockOcclusion> for Direction {
+    type Error = anyhow::Error;
 
-        if self.contains(BlockOcclusion::NEG_Z) {
-            value |= BlockOcclusion::POS_Z;
+    fn try_from(value: BlockOcclusion) -> Result<Self, Self::Error> {
+        match Direction::ALL.into_iter().find(|dir| BlockOcclusion::from(*dir) == value) {
+            Some(dir) => Ok(dir),
+            None => bail!("Block occlusion is not a single direction: {value:?}"),
         }
-
-        value
     }
 }
 
@@ -131,6 +138,85 @@ impl BlockShape {
     }
 }
 
+impl BlockSolidity for BlockShape {
+    fn is_solid(&self) -> bool {
+        match self {
+            BlockShape::Empty => false,
+            BlockShape::Cube | BlockShape::Custom => true,
+        }
+    }
+}
+
+impl BlockResistance for BlockShape {
+    fn blast_resistance(&self) -> f32 {
+        match self {
+            BlockShape::Empty => 0.0,
+            BlockShape::Cube | BlockShape::Custom => 1.0,
+        }
+    }
+}
+
+impl BlockHardness for BlockShape {
+    fn hardness(&self) -> Option<f32> {
+        match self {
+            BlockShape::Empty => Some(0.0),
+            BlockShape::Cube | BlockShape::Custom => Some(0.75),
+        }
+    }
+}
+
+impl BlockMapColor for BlockShape {
+    fn map_color(&self) -> Option<[u8; 3]> {
+        match self {
+            BlockShape::Empty => None,
+            BlockShape::Cube => Some([120, 160, 90]),
+            BlockShape::Custom => Some([150, 150, 150]),
+        }
+    }
+}
+
+impl BlockItem for BlockShape {
+    fn from_item(item: &str) -> Option<Self> {
+        match item {
+            "cube" => Some(BlockShape::Cube),
+            _ => None,
+        }
+    }
+}
+
+
+/// A per-block override for which faces of neighboring blocks a block occludes,
+/// in place of the occlusion of its block shape.
+///
+/// Overrides are stored within a separate `VoxelWorld<OcclusionOverride>` on
+/// the same entity as the block shapes. This allows for tricks such as a cube
+/// that does not hide the faces behind it, so that a hidden room or a camera
+/// block can be seen through the walls around it.
+#[derive(Debug, Clone, Copy, Reflect, Default, PartialEq, Eq)]
+pub enum OcclusionOverride {
+    /// The block occludes the faces that its block shape occludes.
+    #[default]
+    Inherit,
+
+    /// The block does not occlude any faces of its neighbors.
+    Never,
+
+    /// The block occludes all faces of its neighbors that touch it.
+    Always,
+}
+
+impl OcclusionOverride {
+    /// Applies this override to the given block occlusion flags. Only the faces
+    /// of the block are affected, and not whether the block hides itself.
+    pub fn apply(&self, occlusion: BlockOcclusion) -> BlockOcclusion {
+        match self {
+            OcclusionOverride::Inherit => occlusion,
+            OcclusionOverride::Never => occlusion & BlockOcclusion::INNER,
+            OcclusionOverride::Always => occlusion | !BlockOcclusion::INNER,
+        }
+    }
+}
+
 
 /// Writes a cube shape to the temporary mesh.
 fn write_cube(mesh: &mut ChunkMesher, occlusion: &BlockOcclusion, pos: Vec3) {
@@ -0,0 +1,218 @@
+//! Renders the chunks of voxel worlds, rebuilding the mesh of each chunk when
+//! its blocks change.
+
+
+use crate::prelude::{cached_chunk_mesher, BlockShape, MeshCache, OcclusionOverride};
+use awgen_math::prelude::{block_to_chunk, chunk_to_block, world_to_block, Direction};
+use awgen_world::prelude::VoxelWorld;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+
+/// The default distance, in chunks, that chunk meshes are shown within.
+pub const DEFAULT_RENDER_DISTANCE: u32 = 12;
+
+
+/// The default maximum number of chunk meshes that are built per frame.
+pub const DEFAULT_MESHES_PER_FRAME: usize = 32;
+
+
+/// The settings that limit how much work is spent on rendering chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct ChunkRenderSettings {
+    /// The distance, in chunks, from the camera that chunk meshes are shown
+    /// within. Chunk meshes that are farther away are hidden.
+    pub render_distance: u32,
+
+    /// The maximum number of chunk meshes that are built per frame. Chunks
+    /// that exceed this budget are built within a later frame.
+    pub meshes_per_frame: usize,
+}
+
+impl Default for ChunkRenderSettings {
+    fn default() -> Self {
+        Self {
+            render_distance:  DEFAULT_RENDER_DISTANCE,
+            meshes_per_frame: DEFAULT_MESHES_PER_FRAME,
+        }
+    }
+}
+
+
+/// A component for a voxel world that renders each of its chunks as a child
+/// mesh entity.
+///
+/// The world version that each chunk mesh was built from is stored, so that
+/// only the chunks that have been modified since are rebuilt. The world entity
+/// must have a transform, such as from a [`SpatialBundle`], for the chunk
+/// meshes to be positioned correctly.
+///
+/// If the world entity also has a `VoxelWorld<OcclusionOverride>`, its
+/// overrides are applied when building the chunk meshes.
+#[derive(Debug, Clone, Component)]
+pub struct ChunkMeshes {
+    /// The material that is used to render all chunk meshes.
+    material: Handle<StandardMaterial>,
+
+    /// The mesh entity of each chunk, alongside the versions of the block
+    /// shapes and occlusion overrides of the chunk that the mesh was built
+    /// from.
+    chunks: HashMap<IVec3, (Entity, (u64, u64))>,
+
+    /// Whether or not some modified chunks were not rebuilt yet, as they
+    /// exceeded the meshing budget.
+    pending: bool,
+}
+
+impl ChunkMeshes {
+    /// Creates a new, empty chunk mesh set that renders chunks with the given
+    /// material.
+    pub fn new(material: Handle<StandardMaterial>) -> Self {
+        Self {
+            material,
+            chunks: HashMap::default(),
+            pending: false,
+        }
+    }
+
+
+    /// Gets the material that is used to render all chunk meshes.
+    pub fn material(&self) -> &Handle<StandardMaterial> {
+        &self.material
+    }
+}
+
+
+/// Rebuilds the mesh of each chunk within a voxel world that has been modified
+/// since its mesh was last built.
+///
+/// The mesh of a chunk also depends on the blocks along the faces of its
+/// neighbors, so the existing meshes of all neighboring chunks are rebuilt as
+/// well. At most [`ChunkRenderSettings::meshes_per_frame`] meshes are built
+/// each frame, and the remaining chunks are rebuilt within later frames.
+#[allow(clippy::type_complexity)]
+pub fn update_chunk_meshes(
+    settings: Res<ChunkRenderSettings>,
+    mut worlds: Query<(
+        Entity,
+        &VoxelWorld<BlockShape>,
+        Option<&VoxelWorld<OcclusionOverride>>,
+        ChangeTrackers<VoxelWorld<BlockShape>>,
+        Option<ChangeTrackers<VoxelWorld<OcclusionOverride>>>,
+        &mut ChunkMeshes,
+    )>,
+    cache: Option<Res<MeshCache>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("meshing", stage = "update_chunk_meshes").entered();
+
+    let mut built = 0;
+    for (world_entity, world, overrides, world_changes, override_changes, mut chunk_meshes) in
+        worlds.iter_mut()
+    {
+        let changed = world_changes.is_changed()
+            || override_changes.map_or(false, |changes| changes.is_changed());
+        if !changed && !chunk_meshes.pending {
+            continue;
+        }
+
+        let mut versions: HashMap<IVec3, (u64, u64)> = HashMap::default();
+        for (chunk_coords, version) in world.chunk_versions() {
+            versions.entry(chunk_coords).or_default().0 = version;
+        }
+
+        for (chunk_coords, version) in overrides.into_iter().flat_map(VoxelWorld::chunk_versions) {
+            versions.entry(chunk_coords).or_default().1 = version;
+        }
+
+        let mut modified = HashMap::default();
+        let mut dirty = HashSet::default();
+        chunk_meshes.pending = false;
+
+        for (chunk_coords, version) in versions {
+            if chunk_meshes.chunks.get(&chunk_coords).map(|(_, v)| *v) == Some(version) {
+                continue;
+            }
+
+            let mut rebuild = vec![chunk_coords];
+            for dir in Direction::ALL {
+                let neighbor = chunk_coords + dir.offset();
+                if chunk_meshes.chunks.contains_key(&neighbor) {
+                    rebuild.push(neighbor);
+                }
+            }
+            rebuild.retain(|coords| !dirty.contains(coords));
+
+            if built > 0 && built + rebuild.len() > settings.meshes_per_frame {
+                chunk_meshes.pending = true;
+                continue;
+            }
+
+            built += rebuild.len();
+            modified.insert(chunk_coords, version);
+            dirty.extend(rebuild);
+        }
+
+        for chunk_coords in dirty {
+            let mesher = cached_chunk_mesher(chunk_coords, world, overrides, cache.as_deref());
+            let mesh = meshes.add(mesher.into());
+
+            match chunk_meshes.chunks.get_mut(&chunk_coords) {
+                Some((entity, version)) => {
+                    commands.entity(*entity).insert(mesh);
+                    if let Some(new_version) = modified.get(&chunk_coords) {
+                        *version = *new_version;
+                    }
+                },
+                None => {
+                    let entity = commands
+                        .spawn(PbrBundle {
+                            mesh,
+                            material: chunk_meshes.material.clone(),
+                            transform: Transform::from_translation(
+                                chunk_to_block(chunk_coords).as_vec3(),
+                            ),
+                            ..default()
+                        })
+                        .id();
+
+                    commands.entity(world_entity).add_child(entity);
+                    chunk_meshes.chunks.insert(chunk_coords, (entity, modified[&chunk_coords]));
+                },
+            }
+        }
+    }
+}
+
+
+/// Hides the mesh of each chunk that is farther away from the camera than the
+/// render distance, and shows it again once it is back within range.
+pub fn cull_distant_chunks(
+    settings: Res<ChunkRenderSettings>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    worlds: Query<(&ChunkMeshes, &GlobalTransform)>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+
+    let render_distance = settings.render_distance as i32;
+    for (chunk_meshes, world_transform) in worlds.iter() {
+        let local = world_transform.affine().inverse().transform_point3(camera.translation());
+        let center = block_to_chunk(world_to_block(local));
+
+        for (chunk_coords, (entity, _)) in chunk_meshes.chunks.iter() {
+            let Ok(mut visibility) = visibility.get_mut(*entity) else {
+                continue;
+            };
+
+            let visible = (*chunk_coords - center).abs().max_element() <= render_distance;
+            if visibility.is_visible != visible {
+                visibility.is_visible = visible;
+            }
+        }
+    }
+}
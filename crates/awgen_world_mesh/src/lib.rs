@@ -8,24 +8,66 @@
 
 
 pub mod block_data;
+pub mod chunk_meshes;
+pub mod mesh_cache;
 pub mod mesher;
+pub mod texture_animation;
 
 
 /// A re-export of all components and systems defined within this crate.
 pub mod prelude {
     pub use super::block_data::*;
+    pub use super::chunk_meshes::*;
+    pub use super::mesh_cache::*;
     pub use super::mesher::*;
+    pub use super::texture_animation::*;
     pub use super::*;
 }
 
 
 use bevy::prelude::*;
+use prelude::*;
+use std::path::PathBuf;
 
 
 /// The world mesh plugin implementation.
 #[derive(Debug, Clone, Default)]
-pub struct WorldMeshPlugin;
+pub struct WorldMeshPlugin {
+    /// The directory to cache generated chunk meshes within, if mesh caching
+    /// is enabled.
+    mesh_cache: Option<PathBuf>,
+
+    /// The settings that limit how much work is spent on rendering chunks.
+    render_settings: ChunkRenderSettings,
+}
+
+impl WorldMeshPlugin {
+    /// Enables caching generated chunk meshes within the given directory, so
+    /// that unchanged chunks are not remeshed when they are loaded again.
+    pub fn with_mesh_cache<P>(mut self, directory: P) -> Self
+    where P: Into<PathBuf> {
+        self.mesh_cache = Some(directory.into());
+        self
+    }
+
+
+    /// Sets the initial render distance and meshing budget. These may be
+    /// changed later on through the [`ChunkRenderSettings`] resource.
+    pub fn with_render_settings(mut self, settings: ChunkRenderSettings) -> Self {
+        self.render_settings = settings;
+        self
+    }
+}
 
 impl Plugin for WorldMeshPlugin {
-    fn build(&self, _app: &mut App) {}
+    fn build(&self, app: &mut App) {
+        if let Some(directory) = &self.mesh_cache {
+            app.insert_resource(MeshCache::new(directory));
+        }
+
+        app.insert_resource(self.render_settings)
+            .add_system(update_chunk_meshes)
+            .add_system(cull_distant_chunks.after(update_chunk_meshes))
+            .add_system(animate_chunk_textures);
+    }
 }
@@ -0,0 +1,135 @@
+//! An optional on-disk cache of generated chunk meshes, which allows unchanged
+//! chunks to skip remeshing when a world is loaded again.
+//!
+//! Cached meshes are keyed by a hash of the block shapes and occlusion
+//! overrides that the mesh was generated from, including the border of
+//! neighboring blocks that affect face occlusion. As chunk meshes are built in
+//! local coordinates, chunks with the same contents share a single cached mesh.
+
+
+use crate::prelude::{build_chunk_mesher, BlockShape, ChunkMesher, OcclusionOverride};
+use anyhow::Result;
+use awgen_math::prelude::{chunk_to_block, Region};
+use awgen_world::world::VoxelWorld;
+use bevy::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+/// The version of the chunk mesh format. This is included within each content
+/// hash, so that changes to the mesher invalidate all cached meshes.
+const MESH_FORMAT_VERSION: u64 = 1;
+
+
+/// The 64-bit FNV-1a offset basis.
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+
+/// The 64-bit FNV-1a prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+
+/// Computes the content hash of the chunk at the given chunk coordinates.
+///
+/// The hash covers every block within the chunk and the single layer of blocks
+/// surrounding it. A stable hash function is used, so that hashes remain valid
+/// across runs.
+pub fn chunk_content_hash(
+    chunk_coords: IVec3,
+    shapes: &VoxelWorld<BlockShape>,
+    overrides: Option<&VoxelWorld<OcclusionOverride>>,
+) -> u64 {
+    let region = Region::from_size(chunk_to_block(chunk_coords) - 1, IVec3::new(18, 18, 18));
+
+    let mut hash = FNV_OFFSET;
+    for byte in MESH_FORMAT_VERSION.to_le_bytes() {
+        hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+    }
+
+    for shape in shapes.get_block_region(region) {
+        hash = (hash ^ shape as u64).wrapping_mul(FNV_PRIME);
+    }
+
+    if let Some(overrides) = overrides {
+        for occlusion_override in overrides.get_block_region(region) {
+            hash = (hash ^ occlusion_override as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    hash
+}
+
+
+/// A resource that stores generated chunk meshes within a directory on disk.
+#[derive(Debug, Clone, Resource)]
+pub struct MeshCache {
+    /// The directory that cached meshes are stored within.
+    directory: PathBuf,
+}
+
+impl MeshCache {
+    /// Creates a new mesh cache that stores meshes within the given directory.
+    /// The directory is created when the first mesh is stored.
+    pub fn new<P>(directory: P) -> Self
+    where P: Into<PathBuf> {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+
+    /// Gets the directory that cached meshes are stored within.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+
+    /// Gets the path of the cached mesh with the given content hash.
+    fn mesh_path(&self, hash: u64) -> PathBuf {
+        self.directory.join(format!("{hash:016x}.mesh"))
+    }
+
+
+    /// Loads the cached mesh data with the given content hash, if it exists.
+    ///
+    /// Cache entries that cannot be read are treated as missing.
+    pub fn load(&self, hash: u64) -> Option<ChunkMesher> {
+        let bytes = fs::read(self.mesh_path(hash)).ok()?;
+        bincode::deserialize(&bytes).ok()
+    }
+
+
+    /// Stores the given mesh data under the given content hash.
+    pub fn store(&self, hash: u64, mesher: &ChunkMesher) -> Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        fs::write(self.mesh_path(hash), bincode::serialize(mesher)?)?;
+        Ok(())
+    }
+}
+
+
+/// Gets the mesh data for the chunk at the given chunk coordinates, loading it
+/// from the given mesh cache if possible. Otherwise, the mesh data is generated
+/// and stored within the cache.
+pub fn cached_chunk_mesher(
+    chunk_coords: IVec3,
+    shapes: &VoxelWorld<BlockShape>,
+    overrides: Option<&VoxelWorld<OcclusionOverride>>,
+    cache: Option<&MeshCache>,
+) -> ChunkMesher {
+    let Some(cache) = cache else {
+        return build_chunk_mesher(chunk_coords, shapes, overrides);
+    };
+
+    let hash = chunk_content_hash(chunk_coords, shapes, overrides);
+    if let Some(mesher) = cache.load(hash) {
+        return mesher;
+    }
+
+    let mesher = build_chunk_mesher(chunk_coords, shapes, overrides);
+    if let Err(err) = cache.store(hash, &mesher) {
+        warn!("Failed to cache mesh of chunk {chunk_coords}: {err}");
+    }
+
+    mesher
+}
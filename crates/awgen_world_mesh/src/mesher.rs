@@ -1,17 +1,18 @@
 //! Contains the chunk mesh generation functionality.
 
 
-use crate::prelude::{BlockOcclusion, BlockShape};
-use awgen_math::region::Region;
+use crate::prelude::{BlockOcclusion, BlockShape, OcclusionOverride};
+use awgen_math::prelude::{chunk_to_block, Direction, Region};
 use awgen_world::world::VoxelWorld;
 use bevy::prelude::*;
 use bevy::render::mesh::Indices;
 use bevy::render::render_resource::PrimitiveTopology;
+use serde::{Deserialize, Serialize};
 
 
 /// A wrapper for containing temporary mesh data to be converted into a proper
 /// mesh later.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ChunkMesher {
     /// The list of indices in this mesh.
     pub indices: Vec<u16>,
@@ -40,40 +41,63 @@ impl From<ChunkMesher> for Mesh {
 
 /// Generates a new chunk mesh from the given voxel reader for the chunk at the
 /// indicates chunk coordinates.
-pub fn generate_chunk_mesh(chunk_coords: IVec3, shapes: VoxelWorld<BlockShape>) -> Mesh {
+pub fn generate_chunk_mesh(
+    chunk_coords: IVec3,
+    shapes: VoxelWorld<BlockShape>,
+    overrides: Option<&VoxelWorld<OcclusionOverride>>,
+) -> Mesh {
+    build_chunk_mesher(chunk_coords, &shapes, overrides).into()
+}
+
+
+/// Generates the temporary mesh data for the chunk at the indicated chunk
+/// coordinates from the given voxel reader.
+///
+/// If occlusion overrides are given, they take priority over the occlusion of
+/// the block shapes when checking whether a neighboring block hides a face.
+pub fn build_chunk_mesher(
+    chunk_coords: IVec3,
+    shapes: &VoxelWorld<BlockShape>,
+    overrides: Option<&VoxelWorld<OcclusionOverride>>,
+) -> ChunkMesher {
+    #[cfg(feature = "profiling")]
+    let _span = info_span!("meshing", chunk = ?chunk_coords).entered();
+
     let mut mesher = ChunkMesher::default();
 
-    let region = Region::from_size((chunk_coords << 4) - 1, IVec3::new(18, 18, 18));
+    let region = Region::from_size(chunk_to_block(chunk_coords) - 1, IVec3::new(18, 18, 18));
     let shape_data = shapes.get_block_region(region);
+    let override_data = overrides.map(|overrides| overrides.get_block_region(region));
 
     for pos in Region::CHUNK.iter() {
-        let block_index = region.point_to_index(pos).unwrap();
+        let block_index = region.point_to_index_unchecked(pos);
 
         if shape_data[block_index].get_occlusion().contains(BlockOcclusion::INNER) {
             continue;
         }
 
-        let check_dir = |offset, flag: BlockOcclusion, occlusion: &mut BlockOcclusion| {
-            let index = region.point_to_index(pos + offset).unwrap();
-            let shape = shape_data[index];
-            if shape.get_occlusion().contains(flag.opposite_face()) {
-                occlusion.insert(flag);
-            }
-        };
-
         let mut occlusion = BlockOcclusion::empty();
-        #[rustfmt::skip]
-        {
-            check_dir(IVec3::NEG_X, BlockOcclusion::NEG_X, &mut occlusion);
-            check_dir(IVec3::    X, BlockOcclusion::POS_X, &mut occlusion);
-            check_dir(IVec3::NEG_Y, BlockOcclusion::NEG_Y, &mut occlusion);
-            check_dir(IVec3::    Y, BlockOcclusion::POS_Y, &mut occlusion);
-            check_dir(IVec3::NEG_Z, BlockOcclusion::NEG_Z, &mut occlusion);
-            check_dir(IVec3::    Z, BlockOcclusion::POS_Z, &mut occlusion);
+        for dir in Direction::ALL {
+            let index = region.point_to_index_unchecked(pos + dir.offset());
+            let occlusion_override =
+                override_data.as_ref().map_or(OcclusionOverride::Inherit, |o| o[index]);
+            if check_dir(shape_data[index], occlusion_override, dir) {
+                occlusion.insert(dir.into());
+            }
         }
 
         shape_data[block_index].push_to_mesh(&mut mesher, &occlusion, pos.as_vec3());
     }
 
-    mesher.into()
+    mesher
+}
+
+
+/// Checks whether or not the face of a block in the given direction is hidden
+/// by the neighboring block in that direction, which has the given shape and
+/// occlusion override.
+fn check_dir(neighbor: BlockShape, occlusion_override: OcclusionOverride, dir: Direction) -> bool {
+    occlusion_override
+        .apply(neighbor.get_occlusion())
+        .contains(dir.opposite().into())
 }
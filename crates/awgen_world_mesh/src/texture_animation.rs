@@ -0,0 +1,167 @@
+//! Animates the texture of chunk meshes by cycling through the frames of a
+//! frame strip, without rebuilding the chunk meshes.
+//!
+//! A frame strip is a single image with all frames of the animation stacked
+//! vertically, from the first frame at the top to the last frame at the
+//! bottom. Once the strip has loaded, it is split into one image per frame,
+//! and the material of the chunk meshes is pointed at the current frame
+//! whenever the frame changes.
+
+
+use crate::prelude::ChunkMeshes;
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension};
+
+
+/// A component for a voxel world with [`ChunkMeshes`] that animates the base
+/// color texture of its chunk material.
+#[derive(Debug, Clone, Component)]
+pub struct AnimatedTexture {
+    /// The frame strip image that contains all frames of the animation.
+    strip: Handle<Image>,
+
+    /// The number of frames within the frame strip.
+    frame_count: u32,
+
+    /// The number of seconds that each frame is shown for.
+    frame_time: f32,
+
+    /// The image of each frame, once the frame strip has been split.
+    frames: Vec<Handle<Image>>,
+
+    /// The number of seconds since the animation last started over.
+    elapsed: f32,
+
+    /// The index of the frame that is currently shown, if any.
+    current: Option<usize>,
+}
+
+impl AnimatedTexture {
+    /// Creates a new texture animation from the given frame strip, which
+    /// contains the given number of frames, each shown for the given number of
+    /// seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame count is zero or the frame time is not positive.
+    pub fn new(strip: Handle<Image>, frame_count: u32, frame_time: f32) -> Self {
+        assert!(
+            frame_count > 0,
+            "An animated texture must have at least one frame"
+        );
+        assert!(frame_time > 0.0, "The frame time must be positive");
+
+        Self {
+            strip,
+            frame_count,
+            frame_time,
+            frames: vec![],
+            elapsed: 0.0,
+            current: None,
+        }
+    }
+
+
+    /// Gets the index of the frame that is shown after the animation has run
+    /// for the given number of seconds.
+    pub fn frame_at(&self, elapsed: f32) -> usize {
+        (elapsed / self.frame_time) as usize % self.frame_count as usize
+    }
+}
+
+
+/// Splits a vertical frame strip into the image of each of its frames.
+///
+/// Returns an error if the strip cannot be evenly split into the given number
+/// of frames, or if its texture format is block compressed.
+pub fn split_frame_strip(strip: &Image, frame_count: u32) -> Result<Vec<Image>> {
+    let descriptor = &strip.texture_descriptor;
+    let size = descriptor.size;
+    let info = descriptor.format.describe();
+
+    if info.block_dimensions != (1, 1) {
+        bail!(
+            "Cannot split a frame strip with compressed format {:?}",
+            descriptor.format
+        );
+    }
+
+    if frame_count == 0 || size.height % frame_count != 0 {
+        bail!(
+            "A frame strip of height {} cannot be split into {frame_count} frames",
+            size.height
+        );
+    }
+
+    let frame_height = size.height / frame_count;
+    let frame_bytes = (size.width * frame_height) as usize * info.block_size as usize;
+
+    let frames = strip
+        .data
+        .chunks_exact(frame_bytes)
+        .take(frame_count as usize)
+        .map(|data| {
+            let mut frame = Image::new(
+                Extent3d {
+                    width:                 size.width,
+                    height:                frame_height,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data.to_vec(),
+                descriptor.format,
+            );
+            frame.sampler_descriptor = strip.sampler_descriptor.clone();
+            frame
+        })
+        .collect();
+
+    Ok(frames)
+}
+
+
+/// Advances each animated texture, and points the material of its chunk meshes
+/// at the current frame whenever the frame changes.
+///
+/// Animations whose frame strip has not loaded yet are not advanced. Frame
+/// strips that cannot be split are logged and left unanimated.
+pub fn animate_chunk_textures(
+    time: Res<Time>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut worlds: Query<(&ChunkMeshes, &mut AnimatedTexture)>,
+) {
+    for (chunk_meshes, mut animation) in worlds.iter_mut() {
+        if animation.frames.is_empty() {
+            let Some(strip) = images.get(&animation.strip) else {
+                continue;
+            };
+
+            match split_frame_strip(strip, animation.frame_count) {
+                Ok(frames) => {
+                    animation.frames = frames.into_iter().map(|frame| images.add(frame)).collect();
+                },
+                Err(err) => {
+                    error!("Failed to animate chunk texture: {err}");
+                    animation.frame_count = 1;
+                    animation.frames = vec![animation.strip.clone()];
+                },
+            }
+        }
+
+        let duration = animation.frame_time * animation.frame_count as f32;
+        animation.elapsed = (animation.elapsed + time.delta_seconds()) % duration;
+        let frame = animation.frame_at(animation.elapsed);
+        if animation.current == Some(frame) {
+            continue;
+        }
+
+        let Some(material) = materials.get_mut(chunk_meshes.material()) else {
+            continue;
+        };
+
+        material.base_color_texture = Some(animation.frames[frame].clone());
+        animation.current = Some(frame);
+    }
+}
@@ -0,0 +1,336 @@
+//! Loads the `awgen.toml` configuration file, which provides the default
+//! settings for the client and server. Any values that are missing from the
+//! file fall back to their defaults, and command line flags take priority over
+//! values from the file.
+
+
+use anyhow::{Context, Result};
+use awgen_client::prelude::{DEFAULT_MAX_PARTICLES, DEFAULT_RESTORE_FPS, DEFAULT_TARGET_FPS};
+use awgen_math::prelude::{mix_u64, Seed};
+use awgen_world::prelude::{
+    DEFAULT_ENTITY_BUDGET, DEFAULT_ITEMS_PER_CHUNK, DEFAULT_MOBS_PER_CHUNK
+};
+use awgen_world_mesh::prelude::{DEFAULT_MESHES_PER_FRAME, DEFAULT_RENDER_DISTANCE};
+use bevy::prelude::Color;
+use bevy::window::PresentMode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// The default path of the configuration file, relative to the working
+/// directory.
+pub const DEFAULT_CONFIG_PATH: &str = "awgen.toml";
+
+
+/// The root configuration structure of the `awgen.toml` file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AwgenConfig {
+    /// Whether or not to enable debug mode.
+    pub debug: bool,
+
+    /// The number of physics frames to calculate per second.
+    pub tickrate: f32,
+
+    /// The directory that world and player data is stored within.
+    pub world_directory: PathBuf,
+
+    /// The directory that server mods are loaded from.
+    pub mods_directory: PathBuf,
+
+    /// Settings that are only used when running a server.
+    pub server: ServerConfig,
+
+    /// Settings that are only used when running a client.
+    pub client: ClientConfig,
+
+    /// Settings for the client window and renderer.
+    pub render: RenderConfig,
+}
+
+impl AwgenConfig {
+    /// Loads the configuration from the file at the given path.
+    ///
+    /// If the file does not exist, the default configuration is returned. An
+    /// error is returned if the file exists but could not be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(AwgenConfig::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        toml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+}
+
+impl Default for AwgenConfig {
+    fn default() -> Self {
+        Self {
+            debug:           false,
+            tickrate:        25.0,
+            world_directory: PathBuf::from("world"),
+            mods_directory:  PathBuf::from("mods"),
+            server:          ServerConfig::default(),
+            client:          ClientConfig::default(),
+            render:          RenderConfig::default(),
+        }
+    }
+}
+
+
+/// Server specific settings within the configuration file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// The port to open the server on.
+    pub port: u16,
+
+    /// The maximum number of clients that can connect to the server at once.
+    pub max_clients: usize,
+
+    /// The name of the world that players are placed in when joining.
+    pub lobby_world: String,
+
+    /// Pre-generate all chunks within this radius, in chunks, around the world
+    /// spawn on startup.
+    pub pregen: Option<u16>,
+
+    /// The number of seconds that a chunk must remain out of range of all
+    /// players before it is unloaded.
+    pub chunk_unload_seconds: f32,
+
+    /// The number of bytes that may be sent to each client per physics tick.
+    /// Bulk world data is held back once this budget runs out, so that it
+    /// does not delay more time-sensitive updates.
+    pub send_budget: usize,
+
+    /// The maximum number of mobs within a single chunk.
+    pub mobs_per_chunk: usize,
+
+    /// The maximum number of item drops within a single chunk. Item drops of
+    /// the same item are merged before any are despawned.
+    pub items_per_chunk: usize,
+
+    /// The maximum number of mobs and item drops across all worlds.
+    pub entity_budget: usize,
+
+    /// Whether or not large messages are compressed for clients that request
+    /// it.
+    pub compression: bool,
+
+    /// The payload size, in bytes, above which messages are compressed.
+    pub compression_threshold: usize,
+
+    /// The number of minutes without input before a player is marked as AFK.
+    pub afk_minutes: Option<u64>,
+
+    /// The number of minutes without input before a player is disconnected.
+    pub idle_kick_minutes: Option<u64>,
+
+    /// The seed used to generate new worlds. This may be a number or any text.
+    /// If not set, a random seed is chosen.
+    pub seed: Option<String>,
+
+    /// The path of a file containing the hex encoded private key that connect
+    /// tokens are signed with. If set, clients must join with a connect token
+    /// signed by this key.
+    pub private_key: Option<PathBuf>,
+
+    /// Whether or not clients must encrypt their connection. This is only
+    /// needed if no private key is set, as connections that require connect
+    /// tokens are always encrypted.
+    pub encryption: bool,
+
+    /// The port to accept remote administration connections on. If not set,
+    /// remote administration is disabled.
+    pub rcon_port: Option<u16>,
+
+    /// The password that remote administration connections must authenticate
+    /// with. This must be set if an RCON port is set.
+    pub rcon_password: Option<String>,
+
+    /// The port to answer status queries on, such as from server lists. If not
+    /// set, status queries are disabled.
+    pub status_port: Option<u16>,
+
+    /// The message of the day that is reported within status queries.
+    pub motd: String,
+}
+
+impl ServerConfig {
+    /// Gets the seed used to generate new worlds. If no seed is configured, a
+    /// seed is picked based on the current time.
+    pub fn world_seed(&self) -> Seed {
+        match &self.seed {
+            Some(text) => Seed::from_text(text),
+            None => {
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|time| time.as_nanos() as u64)
+                    .unwrap_or_default();
+                Seed(mix_u64(nanos))
+            },
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port:                  30082,
+            max_clients:           128,
+            lobby_world:           "lobby".to_string(),
+            pregen:                None,
+            chunk_unload_seconds:  10.0,
+            send_budget:           8192,
+            mobs_per_chunk:        DEFAULT_MOBS_PER_CHUNK,
+            items_per_chunk:       DEFAULT_ITEMS_PER_CHUNK,
+            entity_budget:         DEFAULT_ENTITY_BUDGET,
+            compression:           true,
+            compression_threshold: 512,
+            afk_minutes:           Some(5),
+            idle_kick_minutes:     None,
+            seed:                  None,
+            private_key:           None,
+            encryption:            false,
+            rcon_port:             None,
+            rcon_password:         None,
+            status_port:           None,
+            motd:                  "An Awgen server".to_string(),
+        }
+    }
+}
+
+
+/// Client specific settings within the configuration file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// The IP of the server to join.
+    pub ip: String,
+
+    /// The port of the server to join.
+    pub port: u16,
+
+    /// The username to play as. If not set, the server names the player after
+    /// their client id.
+    pub username: Option<String>,
+
+    /// The path of a connect token file that was issued for this client, used
+    /// to join servers that require authentication.
+    pub connect_token: Option<PathBuf>,
+
+    /// The path of a file containing the hex encoded private key of the
+    /// server, used to sign a connect token locally when no connect token file
+    /// is given. This should only be set for trusted servers.
+    pub private_key: Option<PathBuf>,
+
+    /// Whether or not to request that the server compresses large messages.
+    pub compression: bool,
+
+    /// The maximum number of attempts to reconnect after the connection to
+    /// the server is lost. If zero, the client never reconnects.
+    pub reconnect_attempts: u32,
+
+    /// The number of seconds to wait before the first reconnection attempt,
+    /// which is doubled for each following attempt.
+    pub reconnect_delay: f64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            ip:                 "127.0.0.1".to_string(),
+            port:               30082,
+            username:           None,
+            connect_token:      None,
+            private_key:        None,
+            compression:        true,
+            reconnect_attempts: 5,
+            reconnect_delay:    1.0,
+        }
+    }
+}
+
+
+/// Window and render settings within the configuration file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderConfig {
+    /// The initial width of the window, in logical pixels.
+    pub width: f32,
+
+    /// The initial height of the window, in logical pixels.
+    pub height: f32,
+
+    /// Whether or not to limit the frame rate to the display refresh rate.
+    pub vsync: bool,
+
+    /// The background clear color, as RGB values between 0 and 1.
+    pub clear_color: [f32; 3],
+
+    /// The directory to cache generated chunk meshes within. Chunk meshes are
+    /// not cached if not set.
+    pub mesh_cache: Option<PathBuf>,
+
+    /// The distance, in chunks, that chunks are rendered within at full
+    /// quality.
+    pub render_distance: u32,
+
+    /// The maximum number of chunk meshes that are built per frame at full
+    /// quality.
+    pub meshes_per_frame: usize,
+
+    /// The maximum number of live particles at full quality.
+    pub max_particles: usize,
+
+    /// Whether or not to lower the render quality automatically while the
+    /// frame rate is below the target frame rate.
+    pub auto_quality: bool,
+
+    /// The frame rate below which the render quality is lowered.
+    pub target_fps: f32,
+
+    /// The frame rate above which the render quality is raised again.
+    pub restore_fps: f32,
+}
+
+impl RenderConfig {
+    /// Gets the window present mode for these render settings.
+    pub fn present_mode(&self) -> PresentMode {
+        match self.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        }
+    }
+
+
+    /// Gets the background clear color for these render settings.
+    pub fn clear_color(&self) -> Color {
+        let [r, g, b] = self.clear_color;
+        Color::rgb(r, g, b)
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            width:            1280.0,
+            height:           720.0,
+            vsync:            true,
+            clear_color:      [0.2, 0.2, 0.2],
+            mesh_cache:       None,
+            render_distance:  DEFAULT_RENDER_DISTANCE,
+            meshes_per_frame: DEFAULT_MESHES_PER_FRAME,
+            max_particles:    DEFAULT_MAX_PARTICLES,
+            auto_quality:     true,
+            target_fps:       DEFAULT_TARGET_FPS,
+            restore_fps:      DEFAULT_RESTORE_FPS,
+        }
+    }
+}
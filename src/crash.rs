@@ -0,0 +1,217 @@
+//! Writes crash reports whenever the client or server thread panics. Each
+//! report is written to a timestamped file within the crash reports directory,
+//! and contains the panic message, a backtrace, the most recent log records,
+//! and the plugins that were loaded by each thread.
+
+
+use awgen_server::prelude::RecentLogs;
+use bevy::prelude::*;
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, thread};
+
+
+/// The directory that crash reports are written to, relative to the working
+/// directory.
+pub const CRASH_REPORT_DIRECTORY: &str = "crash-reports";
+
+
+/// The buffer of recent log records to include within crash reports, if log
+/// capturing has been enabled.
+static RECENT_LOGS: OnceLock<RecentLogs> = OnceLock::new();
+
+
+/// The names of the plugins loaded by each thread, alongside the name of the
+/// thread that loaded them.
+static LOADED_PLUGINS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+
+/// Installs the crash handler for this process, replacing the default panic
+/// hook.
+///
+/// If a log buffer is given, its contents are included within each crash
+/// report.
+pub fn install_crash_handler(recent_logs: Option<RecentLogs>) {
+    if let Some(recent_logs) = recent_logs {
+        let _ = RECENT_LOGS.set(recent_logs);
+    }
+
+    panic::set_hook(Box::new(|info| {
+        let report = build_report(info);
+        match write_report(Path::new(CRASH_REPORT_DIRECTORY), &report) {
+            Ok(path) => {
+                eprintln!(
+                    "\n===== {{ CRASH }} =====\n{}\nA crash report has been saved to: {}\n",
+                    panic_summary(info),
+                    path.display()
+                );
+            },
+            Err(err) => {
+                eprintln!(
+                    "\n===== {{ CRASH }} =====\nFailed to save crash report: {err}\n\n{report}"
+                );
+            },
+        }
+    }));
+}
+
+
+/// An extension trait for apps that records each added plugin, so that it may
+/// be listed within crash reports.
+pub trait ReportedPluginExt {
+    /// Adds the given plugin to this app, recording its name for crash
+    /// reports.
+    fn add_reported_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self;
+
+
+    /// Adds the given plugin group to this app, recording it under the given
+    /// name for crash reports.
+    ///
+    /// A name must be provided, since a customized plugin group loses the type
+    /// name of the group it was built from.
+    fn add_reported_plugins<G: PluginGroup>(&mut self, name: &str, group: G) -> &mut Self;
+}
+
+impl ReportedPluginExt for App {
+    fn add_reported_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        record_plugin(plugin.name());
+        self.add_plugin(plugin)
+    }
+
+
+    fn add_reported_plugins<G: PluginGroup>(&mut self, name: &str, group: G) -> &mut Self {
+        record_plugin(name);
+        self.add_plugins(group)
+    }
+}
+
+
+/// Records the name of a plugin loaded by the current thread.
+fn record_plugin(name: &str) {
+    let thread = thread::current().name().unwrap_or("unnamed").to_string();
+    let mut plugins = LOADED_PLUGINS.lock().unwrap_or_else(|err| err.into_inner());
+    plugins.push((thread, name.to_string()));
+}
+
+
+/// Gets a short summary of the panic, including the thread it occurred on and
+/// the panic message.
+fn panic_summary(info: &PanicHookInfo) -> String {
+    let thread = thread::current().name().unwrap_or("unnamed").to_string();
+
+    let message = if let Some(msg) = info.payload().downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = info.payload().downcast_ref::<String>() {
+        msg
+    } else {
+        "Unknown error"
+    };
+
+    match info.location() {
+        Some(location) => format!("Thread '{thread}' panicked at {location}: {message}"),
+        None => format!("Thread '{thread}' panicked: {message}"),
+    }
+}
+
+
+/// Builds the full text of a crash report for the given panic.
+fn build_report(info: &PanicHookInfo) -> String {
+    let mut report = String::new();
+
+    let _ = writeln!(report, "===== Awgen Crash Report =====");
+    let _ = writeln!(
+        report,
+        "Time: {} UTC",
+        format_timestamp(unix_time(), " ", ":")
+    );
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        report,
+        "Platform: {}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(report);
+    let _ = writeln!(report, "{}", panic_summary(info));
+
+    let _ = writeln!(report, "\n===== Backtrace =====");
+    let _ = writeln!(report, "{}", Backtrace::force_capture());
+
+    let _ = writeln!(report, "\n===== Loaded Plugins =====");
+    let plugins = LOADED_PLUGINS.lock().unwrap_or_else(|err| err.into_inner());
+    for (thread, plugin) in plugins.iter() {
+        let _ = writeln!(report, "[{thread}] {plugin}");
+    }
+
+    let _ = writeln!(report, "\n===== Recent Logs =====");
+    match RECENT_LOGS.get() {
+        Some(logs) => {
+            for line in logs.lines() {
+                let _ = writeln!(report, "{line}");
+            }
+        },
+        None => {
+            let _ = writeln!(report, "No log records were captured.");
+        },
+    }
+
+    report
+}
+
+
+/// Writes the given crash report to a new timestamped file within the given
+/// directory, returning the path of the file.
+fn write_report(directory: &Path, report: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    let timestamp = format_timestamp(unix_time(), "_", "-");
+    let mut path = directory.join(format!("crash-{timestamp}.txt"));
+    let mut index = 1;
+    while path.exists() {
+        path = directory.join(format!("crash-{timestamp}-{index}.txt"));
+        index += 1;
+    }
+
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+
+/// Gets the current time, in seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+
+/// Formats the given Unix timestamp as a UTC date and time, using the given
+/// separators between the date and time, and between each time component.
+fn format_timestamp(timestamp: u64, date_separator: &str, time_separator: &str) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Converts days since the Unix epoch into a civil date, based on Howard
+    // Hinnant's `civil_from_days` algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}{date_separator}{:02}{time_separator}{:02}{time_separator}{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
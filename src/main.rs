@@ -7,182 +7,1128 @@
 #![warn(rustdoc::invalid_html_tags)]
 
 
+mod config;
+mod crash;
 mod prefabs;
 
+use anyhow::{bail, Result};
+use awgen_client::prelude::{AutoQuality, BlockEditPredictionPlugin, ParticleBudget};
 use awgen_client::ClientPlugin;
+use awgen_network::prelude::{
+    query_status, read_capture, start_capture, write_connect_token, CaptureDirection, CaptureFilter, CaptureSide, CompressionSettings, FileTokenIssuer, KeyTokenIssuer, PrivateKey, RconClient, RconSettings, ReconnectSettings, SendBudget, SessionPlayback, StatusSettings, UserData, DEFAULT_TOKEN_EXPIRE_SECONDS, TRANSPORT_PROTOCOL_ID
+};
 use awgen_network::NetworkPlugin;
 use awgen_physics::PhysicsPlugin;
-use awgen_server::ServerPlugin;
+use awgen_server::prelude::{
+    generate_map_world, init_logging, map_columns, BanListFile, BlockEditPlugin, IdleTimeouts, LogGuard, LogSettings, MapExportPlugin, PlayerDataDirectory, ServerPlugin, TraceOutput, WorldConfig, WorldDataDirectory, WorldSummary, MAX_MAP_RADIUS
+};
+use awgen_world::prelude::{
+    render_map, ChunkUnloadDelay, ContainerPlugin, EntityCaps, EntityCapsPlugin, ExplosionPlugin, InteractionPlugin, NoiseTerrain, SafeSpawnPlugin, WorldGenerator
+};
 use awgen_world::WorldDataPlugin;
+use awgen_world_collision::{PathfindingPlugin, WorldCollisionPlugin};
+use awgen_world_mesh::prelude::{BlockShape, ChunkRenderSettings};
 use awgen_world_mesh::WorldMeshPlugin;
 use bevy::log::{Level, LogPlugin};
 use bevy::prelude::*;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use config::{AwgenConfig, ClientConfig, ServerConfig, DEFAULT_CONFIG_PATH};
+use crash::{install_crash_handler, ReportedPluginExt};
+use std::any::Any;
+use std::io::BufRead;
+use std::net::SocketAddr;
 use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 
 /// The default window title for the Awgen game engine.
 const WINDOW_TITLE: &str = "Awgen";
 
 
-/// The default background clear color.
-const CLEAR_COLOR: Color = Color::rgb(0.2, 0.2, 0.2);
-
-
-/// The number of physics frames to calculate per second.
-const TICKRATE: f32 = 25.0;
-
-
-/// The maximum of clients that can connect to a server at once.
-const MAX_CLIENTS: usize = 128;
-
-
-/// The error string format for the Awgen server and client threads.
-macro_rules! print_error {
-    ( $msg:expr, $err:expr ) => {
-        println!(
-            "\n===== {{ ERROR }} =====\n{0}\nError: {1:?}\n=====================\n",
-            $msg, $err
-        );
-    };
-}
+/// The lowest and highest chunk heights that are generated when exporting a
+/// map of a world that is not hosted, which covers all terrain of the default
+/// world generator.
+const MAP_CHUNK_HEIGHTS: (i32, i32) = (-2, 1);
 
 
 /// The command line input argument structure.
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// The path of the configuration file to load.
+    #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+    config: PathBuf,
+
     /// Enable debug mode
     #[arg(long)]
     debug: bool,
 
-    /// Type of network application to launch.
+    /// Record profiling spans to the given trace output. Requires Awgen to be
+    /// built with the `profiling` feature.
+    #[arg(long, value_enum)]
+    trace: Option<TraceFormat>,
+
+    /// Write every network message sent and received by this process to the
+    /// given capture file, which may be read with `inspect-capture`.
+    #[arg(long, value_name = "PATH")]
+    capture: Option<PathBuf>,
+
+    /// Type of application to launch, or world management task to run.
     #[command(subcommand)]
-    network_command: NetworkCommand,
+    command: Command,
+}
+
+
+/// The trace formats that profiling spans may be recorded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TraceFormat {
+    /// Writes a `trace.json` file within the log directory, which may be
+    /// viewed within `chrome://tracing` or Perfetto.
+    Chrome,
+
+    /// Streams spans to a connected Tracy profiler. Requires the `tracy`
+    /// feature.
+    Tracy,
+}
+
+
+/// Command line arguments shared by all network applications that host a
+/// server.
+#[derive(Debug, Args)]
+struct HostArgs {
+    /// The number of physics frames to calculate per second.
+    #[arg(long)]
+    tickrate: Option<f32>,
+
+    /// The maximum number of clients that can connect to the server at once.
+    #[arg(long)]
+    max_clients: Option<usize>,
+
+    /// The directory that world and player data is stored within.
+    #[arg(long, value_name = "PATH")]
+    world: Option<PathBuf>,
+
+    /// The seed used to generate new worlds. This may be a number or any text.
+    #[arg(long)]
+    seed: Option<String>,
+}
+
+impl HostArgs {
+    /// Overrides the values within the given config with any arguments that
+    /// were provided.
+    fn apply(self, config: &mut AwgenConfig) {
+        config.tickrate = self.tickrate.unwrap_or(config.tickrate);
+        config.server.max_clients = self.max_clients.unwrap_or(config.server.max_clients);
+        config.server.seed = self.seed.or(config.server.seed.take());
+
+        if let Some(world) = self.world {
+            config.world_directory = world;
+        }
+    }
 }
 
 
 /// The command line input argument subcommand structure.
 #[derive(Debug, Subcommand)]
-enum NetworkCommand {
+enum Command {
     /// Launch a new Awgen client instance and joins a server.
     Client {
         /// The IP of the server to join.
-        ip: String,
+        ip: Option<String>,
 
         /// The port of the server to join.
-        port: u16,
+        port: Option<u16>,
+
+        /// The username to play as.
+        #[arg(long)]
+        username: Option<String>,
     },
 
     /// Launches a new Awgen server instance.
     Server {
         /// The port to open the server on.
-        port: u16,
+        port: Option<u16>,
+
+        /// Pre-generate all chunks within this radius, in chunks, around the
+        /// world spawn on startup.
+        #[arg(long)]
+        pregen: Option<u16>,
+
+        /// The number of minutes without input before a player is marked as
+        /// AFK.
+        #[arg(long)]
+        afk_minutes: Option<u64>,
+
+        /// The number of minutes without input before a player is
+        /// disconnected. Idle players are never disconnected if not set.
+        #[arg(long)]
+        idle_kick_minutes: Option<u64>,
+
+        /// Server hosting arguments.
+        #[command(flatten)]
+        host: HostArgs,
     },
 
     /// Launch a private server and connect to it in single player mode.
-    Localhost,
+    Localhost {
+        /// Server hosting arguments.
+        #[command(flatten)]
+        host: HostArgs,
+    },
+
+    /// Creates a new, empty world.
+    NewWorld {
+        /// The name of the world to create.
+        name: String,
+
+        /// The id of the world generator to populate the world with.
+        #[arg(long, default_value = "default")]
+        generator: String,
+
+        /// The seed used to generate the world. This may be a number or any
+        /// text. A random seed is chosen if not set.
+        #[arg(long)]
+        seed: Option<String>,
+
+        /// The directory that world and player data is stored within.
+        #[arg(long, value_name = "PATH")]
+        world: Option<PathBuf>,
+    },
+
+    /// Issues a connect token for a client, signed with the private key of
+    /// the server.
+    IssueToken {
+        /// The client id to issue the connect token for.
+        client_id: u64,
+
+        /// The file to write the connect token to.
+        #[arg(long, default_value = "connect_token.bin")]
+        output: PathBuf,
+
+        /// The address of the server that the token allows joining. Defaults
+        /// to the configured server port on the local machine.
+        #[arg(long)]
+        address: Option<SocketAddr>,
+
+        /// The number of seconds that the token remains valid for.
+        #[arg(long, default_value_t = DEFAULT_TOKEN_EXPIRE_SECONDS)]
+        expire_seconds: u64,
+
+        /// The username that the client joins with.
+        #[arg(long)]
+        username: Option<String>,
+    },
+
+    /// Exports a top-down map image of a world, generated from its seed.
+    ExportMap {
+        /// The name of the world to export.
+        name: String,
+
+        /// The radius of the map around the world spawn point, in chunks.
+        #[arg(long, default_value_t = 8)]
+        radius: u16,
+
+        /// The size of each block column on the map, in pixels.
+        #[arg(long, default_value_t = 1)]
+        scale: u32,
+
+        /// The file to write the map image to. Defaults to the map file within
+        /// the world directory.
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// The directory that world and player data is stored within.
+        #[arg(long, value_name = "PATH")]
+        world: Option<PathBuf>,
+    },
+
+    /// Prints the messages within a network capture file.
+    InspectCapture {
+        /// The capture file to read.
+        path: PathBuf,
+
+        /// Only show messages with this message ID, or whose type name
+        /// contains this text.
+        #[arg(long)]
+        message: Option<String>,
+
+        /// Only show messages sent to or received from this client.
+        #[arg(long)]
+        client: Option<u64>,
+
+        /// Only show messages captured by this side, `server` or `client`.
+        #[arg(long)]
+        side: Option<CaptureSide>,
+
+        /// Only show messages that were `sent` or `received`.
+        #[arg(long)]
+        direction: Option<CaptureDirection>,
+
+        /// Only show messages captured at or after this physics tick.
+        #[arg(long)]
+        from_tick: Option<u64>,
+
+        /// Only show messages captured at or before this physics tick.
+        #[arg(long)]
+        to_tick: Option<u64>,
+
+        /// Print the uncompressed payload of each message as hex.
+        #[arg(long)]
+        payload: bool,
+    },
+
+    /// Launches a new Awgen client instance that plays back the messages that
+    /// a client received within a network capture file, without joining a
+    /// server.
+    Playback {
+        /// The capture file to play back.
+        path: PathBuf,
+
+        /// The rate to play the session back at, where 1.0 is the speed at
+        /// which it was recorded.
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    },
+
+    /// Sends commands to a running server over its remote administration
+    /// channel.
+    Rcon {
+        /// The command to run. If not set, commands are read from the standard
+        /// input stream until it is closed.
+        command: Vec<String>,
+
+        /// The address of the server. Defaults to the configured RCON port on
+        /// this machine.
+        #[arg(long)]
+        address: Option<String>,
+
+        /// The RCON password. Defaults to the configured RCON password.
+        #[arg(long)]
+        password: Option<String>,
+    },
+
+    /// Queries the status of a server, such as its message of the day and
+    /// player count, without joining it.
+    Status {
+        /// The address of the status query of the server. Defaults to the
+        /// configured status port on this machine.
+        address: Option<String>,
+
+        /// The number of seconds to wait for the server to respond.
+        #[arg(long, default_value_t = 5.0)]
+        timeout: f32,
+    },
+
+    /// Lists, deletes, or renames existing worlds.
+    Worlds {
+        /// The world management task to run. Lists all worlds if not set.
+        #[command(subcommand)]
+        command: Option<WorldsCommand>,
+
+        /// The directory that world and player data is stored within.
+        #[arg(long, value_name = "PATH", global = true)]
+        world: Option<PathBuf>,
+    },
+}
+
+
+/// The world management subcommand structure.
+#[derive(Debug, Subcommand)]
+enum WorldsCommand {
+    /// Lists all existing worlds.
+    List,
+
+    /// Deletes a world and all of its data.
+    Delete {
+        /// The name of the world to delete.
+        name: String,
+    },
+
+    /// Renames a world.
+    Rename {
+        /// The current name of the world.
+        name: String,
+
+        /// The new name of the world.
+        new_name: String,
+    },
 }
 
 
 /// The main game app entry function.
 fn main() {
     let cli = Cli::parse();
-    let debug = cli.debug;
+    let mut config = match AwgenConfig::load(&cli.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Failed to load the Awgen config file: {err:?}");
+            return;
+        },
+    };
+    config.debug |= cli.debug;
 
-    match cli.network_command {
-        NetworkCommand::Client {
+    match cli.command {
+        Command::Client {
             ip,
             port,
-        } => launch_client(ip, port, debug),
-        NetworkCommand::Server {
+            username,
+        } => {
+            config.client.ip = ip.unwrap_or(config.client.ip);
+            config.client.port = port.unwrap_or(config.client.port);
+            config.client.username = username.or(config.client.username);
+            begin_capture(cli.capture.as_deref());
+
+            // The Bevy log plugin cannot record trace output, so the server log
+            // subscriber is used instead when tracing the client.
+            if cli.trace.is_some() {
+                let log_guard = init_server_logging(config.debug, cli.trace);
+                install_crash_handler(log_guard.as_ref().map(|g| g.recent_logs().clone()));
+                launch_client(config, false, None);
+            } else {
+                install_crash_handler(None);
+                launch_client(config, true, None);
+            }
+        },
+        Command::Server {
+            port,
+            pregen,
+            afk_minutes,
+            idle_kick_minutes,
+            host,
+        } => {
+            host.apply(&mut config);
+            config.server.port = port.unwrap_or(config.server.port);
+            config.server.pregen = pregen.or(config.server.pregen);
+            config.server.afk_minutes = afk_minutes.or(config.server.afk_minutes);
+            config.server.idle_kick_minutes = idle_kick_minutes.or(config.server.idle_kick_minutes);
+
+            let log_guard = init_server_logging(config.debug, cli.trace);
+            install_crash_handler(log_guard.as_ref().map(|g| g.recent_logs().clone()));
+            begin_capture(cli.capture.as_deref());
+            launch_server(config);
+        },
+        Command::Localhost {
+            host,
+        } => {
+            host.apply(&mut config);
+            let log_guard = init_server_logging(config.debug, cli.trace);
+            install_crash_handler(log_guard.as_ref().map(|g| g.recent_logs().clone()));
+            begin_capture(cli.capture.as_deref());
+            launch_localhost(config);
+        },
+        Command::NewWorld {
+            name,
+            generator,
+            seed,
+            world,
+        } => {
+            config.server.seed = seed.or(config.server.seed);
+            config.world_directory = world.unwrap_or(config.world_directory);
+
+            let directory = world_data_directory(&config);
+            match directory.create_world(&name, &generator, config.server.world_seed()) {
+                Ok(meta) => println!("Created world '{name}' with seed {}.", meta.seed.0 as i64),
+                Err(err) => eprintln!("Failed to create world: {err:?}"),
+            }
+        },
+        Command::IssueToken {
+            client_id,
+            output,
+            address,
+            expire_seconds,
+            username,
+        } => {
+            let address =
+                address.unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], config.server.port)));
+            let user_data = username.map(UserData::new).unwrap_or_default();
+
+            match issue_token(
+                &config.server,
+                client_id,
+                address,
+                expire_seconds,
+                &user_data,
+                &output,
+            ) {
+                Ok(()) => {
+                    println!(
+                        "Wrote connect token for client {client_id} to '{}'.",
+                        output.display()
+                    )
+                },
+                Err(err) => eprintln!("Failed to issue connect token: {err:?}"),
+            }
+        },
+        Command::ExportMap {
+            name,
+            radius,
+            scale,
+            output,
+            world,
+        } => {
+            config.world_directory = world.unwrap_or(config.world_directory);
+
+            let directory = world_data_directory(&config);
+            match export_map(&directory, &name, radius, scale, output) {
+                Ok(path) => println!("Saved map of world '{name}' to '{}'.", path.display()),
+                Err(err) => eprintln!("Failed to export map: {err:?}"),
+            }
+        },
+        Command::InspectCapture {
+            path,
+            message,
+            client,
+            side,
+            direction,
+            from_tick,
+            to_tick,
+            payload,
+        } => {
+            let filter = CaptureFilter {
+                message,
+                client_id: client,
+                side,
+                direction,
+                from_tick,
+                to_tick,
+            };
+
+            if let Err(err) = inspect_capture(&path, &filter, payload) {
+                eprintln!("Failed to inspect capture: {err:?}");
+            }
+        },
+        Command::Playback {
+            path,
+            speed,
+        } => {
+            if speed <= 0.0 {
+                eprintln!("The playback speed must be positive.");
+                return;
+            }
+
+            let session = match SessionPlayback::load(&path) {
+                Ok(session) => session.with_speed(speed),
+                Err(err) => {
+                    eprintln!("Failed to load the recorded session: {err:?}");
+                    return;
+                },
+            };
+
+            println!(
+                "Playing back {} messages from '{}'.",
+                session.remaining(),
+                path.display()
+            );
+            install_crash_handler(None);
+            launch_client(config, true, Some(session));
+        },
+        Command::Rcon {
+            command,
+            address,
+            password,
+        } => {
+            let address =
+                address.or_else(|| config.server.rcon_port.map(|port| format!("127.0.0.1:{port}")));
+            let Some(address) = address else {
+                eprintln!("No RCON address was given, and no RCON port is configured.");
+                return;
+            };
+
+            let Some(password) = password.or(config.server.rcon_password) else {
+                eprintln!("No RCON password was given, and no RCON password is configured.");
+                return;
+            };
+
+            if let Err(err) = run_rcon(&address, &password, &command.join(" ")) {
+                eprintln!("RCON failed: {err:?}");
+            }
+        },
+        Command::Status {
+            address,
+            timeout,
+        } => {
+            let address = address
+                .or_else(|| config.server.status_port.map(|port| format!("127.0.0.1:{port}")));
+            let Some(address) = address else {
+                eprintln!("No status address was given, and no status port is configured.");
+                return;
+            };
+
+            if let Err(err) = print_status(&address, timeout) {
+                eprintln!("Status query failed: {err:?}");
+            }
+        },
+        Command::Worlds {
+            command,
+            world,
+        } => {
+            config.world_directory = world.unwrap_or(config.world_directory);
+            manage_worlds(
+                &world_data_directory(&config),
+                command.unwrap_or(WorldsCommand::List),
+            );
+        },
+    }
+}
+
+
+/// Installs the server log subscriber for this process.
+///
+/// If logging could not be initialized, an error is printed and the server
+/// continues without file logging.
+fn init_server_logging(debug: bool, trace: Option<TraceFormat>) -> Option<LogGuard> {
+    let mut settings = match debug {
+        true => LogSettings::debug(),
+        false => LogSettings::default(),
+    };
+
+    settings.trace = trace.map(|format| {
+        match format {
+            TraceFormat::Chrome => TraceOutput::Chrome(settings.directory.join("trace.json")),
+            TraceFormat::Tracy => TraceOutput::Tracy,
+        }
+    });
+
+    match init_logging(&settings) {
+        Ok(guard) => Some(guard),
+        Err(err) => {
+            eprintln!("Failed to initialize server logging: {err:?}");
+            None
+        },
+    }
+}
+
+
+/// Starts capturing network messages to the given file, if set.
+///
+/// If the capture file could not be created, an error is printed and the
+/// application continues without capturing.
+fn begin_capture(path: Option<&Path>) {
+    let Some(path) = path else {
+        return;
+    };
+
+    match start_capture(path) {
+        Ok(()) => println!("Capturing network messages to '{}'.", path.display()),
+        Err(err) => eprintln!("Failed to start network capture: {err:?}"),
+    }
+}
+
+
+/// Runs the given command on the server at the given address over its remote
+/// administration channel, printing the response. If no command is given,
+/// each line of the standard input stream is run as a command instead.
+fn run_rcon(address: &str, password: &str, command: &str) -> Result<()> {
+    let mut rcon = RconClient::connect(address, password)?;
+
+    if !command.trim().is_empty() {
+        println!("{}", rcon.execute(command)?);
+        return Ok(());
+    }
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        println!("{}", rcon.execute(&line)?);
+    }
+
+    Ok(())
+}
+
+
+/// Prints all messages within the capture file at the given path that match
+/// the given filter, optionally followed by their payload.
+fn inspect_capture(path: &Path, filter: &CaptureFilter, payload: bool) -> Result<()> {
+    let (header, records) = read_capture(path)?;
+    println!(
+        "Capture written by Awgen {} with protocol {:016x}.",
+        header.version, header.protocol
+    );
+
+    let mut shown = 0;
+    for record in records.iter().filter(|record| filter.matches(record)) {
+        println!("{record}");
+        shown += 1;
+
+        if payload {
+            match record.decoded_payload() {
+                Ok(bytes) => print_hex(&bytes),
+                Err(err) => println!("    Failed to decompress payload: {err}"),
+            }
+        }
+    }
+
+    println!("Showed {shown} of {} messages.", records.len());
+    Ok(())
+}
+
+
+/// Prints the given bytes as indented rows of hex.
+fn print_hex(bytes: &[u8]) {
+    for row in bytes.chunks(32) {
+        let row: Vec<String> = row.iter().map(|byte| format!("{byte:02x}")).collect();
+        println!("    {}", row.join(" "));
+    }
+}
+
+
+/// Gets the directory that per-world data files are stored within.
+fn world_data_directory(config: &AwgenConfig) -> WorldDataDirectory {
+    WorldDataDirectory(config.world_directory.join("worlds"))
+}
+
+
+/// Issues a connect token for the given client, signed with the private key of
+/// the server, and writes it to the given file.
+fn issue_token(
+    settings: &ServerConfig,
+    client_id: u64,
+    address: SocketAddr,
+    expire_seconds: u64,
+    user_data: &UserData,
+    output: &Path,
+) -> Result<()> {
+    let Some(path) = &settings.private_key else {
+        bail!("No server private key is configured");
+    };
+
+    let issuer = KeyTokenIssuer::new(PrivateKey::load(path)?).with_expiry(expire_seconds);
+    write_connect_token(
+        &issuer,
+        client_id,
+        TRANSPORT_PROTOCOL_ID,
+        address,
+        user_data,
+        output,
+    )
+}
+
+
+/// Renders a top-down map of the world with the given name, centered on its
+/// spawn point, by regenerating its chunks from the world seed. Returns the
+/// path that the map image was saved to.
+///
+/// Block changes are not saved to disk, so the map shows the world as it was
+/// originally generated.
+/// Queries the status of the server at the given address, waiting up to the
+/// given number of seconds, and prints it.
+fn print_status(address: &str, timeout: f32) -> Result<()> {
+    let (status, latency) = query_status(address, Duration::from_secs_f32(timeout))?;
+
+    println!("{}", status.motd);
+    println!("Players: {}/{}", status.players, status.max_players);
+    println!("Version: {}", status.version);
+    println!("Ping: {} ms", latency.as_millis());
+    if !status.is_compatible() {
+        println!("This server uses a different network protocol and cannot be joined.");
+    }
+
+    Ok(())
+}
+
+
+fn export_map(
+    directory: &WorldDataDirectory,
+    name: &str,
+    radius: u16,
+    scale: u32,
+    output: Option<PathBuf>,
+) -> Result<PathBuf> {
+    if radius > MAX_MAP_RADIUS {
+        bail!("Map radius cannot be larger than {MAX_MAP_RADIUS} chunks");
+    }
+
+    let Some(meta) = directory.load_meta(name)? else {
+        bail!("World '{name}' does not exist");
+    };
+
+    let mut generator = match meta.generator.as_str() {
+        "default" => WorldGenerator::new(meta.seed).with_stage(NoiseTerrain::new(BlockShape::Cube)),
+        other => bail!("Unknown world generator: '{other}'"),
+    };
+
+    let columns = map_columns(meta.spawn, radius);
+    let (min_y, max_y) = MAP_CHUNK_HEIGHTS;
+    let voxels = generate_map_world(&mut generator, columns, min_y, max_y);
+    let map = render_map(&voxels, columns, scale)?;
+
+    let path = match output {
+        Some(path) => path,
+        None => directory.map_path(name)?,
+    };
+    map.save_png(&path)?;
+    Ok(path)
+}
+
+
+/// Creates the server instance of the network plugin, requiring connect tokens
+/// if a private key is configured, requiring encrypted connections if
+/// encryption is enabled, opening the remote administration channel if an RCON
+/// port is configured, and answering status queries if a status port is
+/// configured.
+fn server_network(settings: &ServerConfig) -> Result<NetworkPlugin> {
+    let mut network = NetworkPlugin::new_server(settings.port, settings.max_clients);
+
+    if let Some(path) = &settings.private_key {
+        network = network.with_private_key(PrivateKey::load(path)?);
+    }
+
+    if settings.encryption {
+        network = network.with_encryption();
+    }
+
+    if let Some(port) = settings.rcon_port {
+        let Some(password) = settings.rcon_password.clone() else {
+            bail!("An RCON password must be configured to enable RCON");
+        };
+
+        network = network.with_rcon(RconSettings {
+            port,
+            password,
+        });
+    }
+
+    if let Some(port) = settings.status_port {
+        network = network.with_status(StatusSettings {
             port,
-        } => launch_server(port, debug),
-        NetworkCommand::Localhost => launch_localhost(debug),
+            motd: settings.motd.clone(),
+        });
+    }
+
+    Ok(network)
+}
+
+
+/// Creates the client instance of the network plugin, joining with a connect
+/// token if a connect token file or private key is configured, and with the
+/// configured username, if any.
+fn client_network(settings: &ClientConfig) -> Result<NetworkPlugin> {
+    let mut network = NetworkPlugin::new_client(&settings.ip, settings.port);
+    if let Some(username) = &settings.username {
+        network = network.with_user_data(UserData::new(username));
+    }
+
+    match (&settings.connect_token, &settings.private_key) {
+        (Some(token), _) => Ok(network.with_token_issuer(FileTokenIssuer::new(token))),
+        (None, Some(key)) => {
+            Ok(network.with_token_issuer(KeyTokenIssuer::new(PrivateKey::load(key)?)))
+        },
+        (None, None) => Ok(network),
+    }
+}
+
+
+/// Runs the given world management task.
+fn manage_worlds(directory: &WorldDataDirectory, command: WorldsCommand) {
+    match command {
+        WorldsCommand::List => {
+            match directory.list_worlds() {
+                Ok(worlds) if worlds.is_empty() => println!("No worlds found."),
+                Ok(worlds) => worlds.iter().for_each(print_world_summary),
+                Err(err) => eprintln!("Failed to list worlds: {err:?}"),
+            }
+        },
+        WorldsCommand::Delete {
+            name,
+        } => {
+            match directory.delete_world(&name) {
+                Ok(()) => println!("Deleted world '{name}'."),
+                Err(err) => eprintln!("Failed to delete world: {err:?}"),
+            }
+        },
+        WorldsCommand::Rename {
+            name,
+            new_name,
+        } => {
+            match directory.rename_world(&name, &new_name) {
+                Ok(()) => println!("Renamed world '{name}' to '{new_name}'."),
+                Err(err) => eprintln!("Failed to rename world: {err:?}"),
+            }
+        },
+    }
+}
+
+
+/// Prints a single line describing the given world.
+fn print_world_summary(world: &WorldSummary) {
+    let size = format_size(world.size);
+    let Some(meta) = &world.meta else {
+        println!("{}  ({size}, missing world metadata)", world.name);
+        return;
+    };
+
+    let spawn = meta.spawn;
+    let border = match meta.border {
+        Some(border) => format!("{:.0}m", border.radius),
+        None => "none".to_string(),
+    };
+
+    println!(
+        "{}  ({size}, last played {}, version {}, generator '{}', seed {}, spawn {:.0} {:.0} {:.0}, \
+         time {}, border {border})",
+        world.name,
+        format_time_since(meta.last_played),
+        meta.version,
+        meta.generator,
+        meta.seed.0 as i64,
+        spawn.x,
+        spawn.y,
+        spawn.z,
+        meta.time,
+    );
+}
+
+
+/// Formats the given number of bytes as a human readable size.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+
+/// Formats the time elapsed since the given Unix timestamp, in seconds, as a
+/// human readable duration.
+fn format_time_since(timestamp: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default();
+
+    let elapsed = now.saturating_sub(timestamp);
+    match elapsed {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} minutes ago", elapsed / 60),
+        3600..=86399 => format!("{} hours ago", elapsed / 3600),
+        _ => format!("{} days ago", elapsed / 86400),
+    }
+}
+
+
+/// Gets the message string from a caught panic payload.
+fn panic_message(err: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = err.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = err.downcast_ref::<String>() {
+        msg
+    } else {
+        "Unknown error"
     }
 }
 
 
 /// Launches a new localhost Awgen server and a client instance that connects to
 /// it.
-fn launch_localhost(debug: bool) {
-    let port = 30082;
-    let ip = "127.0.0.1".to_string();
+///
+/// The client shares the log output of the server within this process.
+fn launch_localhost(mut config: AwgenConfig) {
+    config.client.ip = "127.0.0.1".to_string();
+    config.client.port = config.server.port;
+    config.client.connect_token = None;
+    config.client.private_key = config.server.private_key.clone();
+    config.server.pregen = None;
 
+    let server_config = config.clone();
     let server_thread = std::thread::Builder::new()
         .name("Server".to_string())
-        .spawn(move || launch_server(port, debug))
+        .spawn(move || launch_server(server_config))
         .unwrap();
 
-    launch_client(ip, port, debug);
+    launch_client(config, false, None);
     server_thread.join().unwrap();
 }
 
 
 /// Launches a new Awgen client instance.
-fn launch_client(ip: String, port: u16, debug: bool) {
+///
+/// If `log_plugin` is false, the client does not install its own log
+/// subscriber, relying on one already installed within this process. If a
+/// recorded session is given, the client plays it back instead of joining a
+/// server.
+fn launch_client(config: AwgenConfig, log_plugin: bool, playback: Option<SessionPlayback>) {
     let result = panic::catch_unwind(move || {
-        let window_title = match debug {
+        let window_title = match config.debug {
             true => WINDOW_TITLE.to_string(),
             false => format!("{WINDOW_TITLE} [Debug]"),
         };
 
-        let client = match debug {
+        let client = match config.debug {
             true => ClientPlugin::debug(),
             false => ClientPlugin::default(),
         };
 
+        let network = match playback {
+            Some(session) => Ok(NetworkPlugin::new_playback(session)),
+            None => client_network(&config.client),
+        };
+
+        let network = match network {
+            Ok(network) => network,
+            Err(err) => {
+                error!("Failed to configure the client network: {err:?}");
+                return;
+            },
+        };
+
+        let render_settings = ChunkRenderSettings {
+            render_distance:  config.render.render_distance,
+            meshes_per_frame: config.render.meshes_per_frame,
+        };
+
+        let mut auto_quality = AutoQuality::new(render_settings, config.render.max_particles)
+            .with_target_fps(config.render.target_fps, config.render.restore_fps);
+        auto_quality.enabled = config.render.auto_quality;
+
+        let mut world_mesh = WorldMeshPlugin::default().with_render_settings(render_settings);
+        if let Some(directory) = &config.render.mesh_cache {
+            world_mesh = world_mesh.with_mesh_cache(directory);
+        }
+
+        let mut plugins = DefaultPlugins
+            .set(WindowPlugin {
+                window: WindowDescriptor {
+                    title: window_title,
+                    width: config.render.width,
+                    height: config.render.height,
+                    present_mode: config.render.present_mode(),
+                    ..default()
+                },
+                ..default()
+            })
+            .set(LogPlugin {
+                level: Level::WARN,
+                ..default()
+            })
+            .set(ImagePlugin::default_nearest());
+
+        if !log_plugin {
+            plugins = plugins.disable::<LogPlugin>();
+        }
+
         App::new()
-            .insert_resource(ClearColor(CLEAR_COLOR))
-            .add_plugins(
-                DefaultPlugins
-                    .set(WindowPlugin {
-                        window: WindowDescriptor {
-                            title: window_title,
-                            ..default()
-                        },
-                        ..default()
-                    })
-                    .set(LogPlugin {
-                        level: Level::WARN,
-                        ..default()
-                    })
-                    .set(ImagePlugin::default_nearest()),
-            )
-            .add_plugin(PhysicsPlugin::new(TICKRATE))
-            .add_plugin(NetworkPlugin::new_client(ip, port))
-            .add_plugin(WorldDataPlugin::default())
-            .add_plugin(WorldMeshPlugin::default())
-            .add_plugin(client)
+            .insert_resource(ClearColor(config.render.clear_color()))
+            .insert_resource(CompressionSettings {
+                enabled: config.client.compression,
+                ..default()
+            })
+            .insert_resource(ReconnectSettings {
+                max_attempts: config.client.reconnect_attempts,
+                initial_delay: config.client.reconnect_delay,
+                ..default()
+            })
+            .insert_resource(ParticleBudget {
+                max_particles: config.render.max_particles,
+            })
+            .insert_resource(auto_quality)
+            .add_reported_plugins("DefaultPlugins", plugins)
+            .add_reported_plugin(PhysicsPlugin::new(config.tickrate))
+            .add_reported_plugin(network)
+            .add_reported_plugin(WorldDataPlugin::default())
+            .add_reported_plugin(WorldCollisionPlugin::<BlockShape>::default())
+            .add_reported_plugin(world_mesh)
+            .add_reported_plugin(client)
+            .add_reported_plugin(BlockEditPredictionPlugin::<BlockShape>::default())
+            .add_reported_plugin(prefabs::PrefabPlugin)
             .add_startup_system(prefabs::spawn_basic_scene)
             .add_startup_system(prefabs::spawn_player)
             .run();
     });
 
     if let Err(err) = result {
-        print_error!("An internal error has occurred in the Awgen client.", err);
+        error!(
+            error = panic_message(err.as_ref()),
+            "An internal error has occurred in the Awgen client."
+        );
     }
 }
 
 
 /// Launches a new Awgen server instance.
-fn launch_server(port: u16, debug: bool) {
+///
+/// If a pre-generation radius is given, the chunks around the spawn point of
+/// the lobby world are generated when the server starts.
+fn launch_server(config: AwgenConfig) {
     let result = panic::catch_unwind(move || {
-        let server = match debug {
+        let settings = config.server;
+        let idle = IdleTimeouts::from_minutes(settings.afk_minutes, settings.idle_kick_minutes);
+
+        let mut server = match config.debug {
             true => ServerPlugin::debug(),
             false => ServerPlugin::default(),
+        }
+        .with_world(
+            WorldConfig::new(&settings.lobby_world, "default").with_seed(settings.world_seed()),
+        )
+        .with_idle_timeouts(idle)
+        .with_mods(&config.mods_directory)
+        .with_console();
+
+        if let Some(radius) = settings.pregen {
+            server = server.with_pregen(radius);
+        }
+
+        let network = match server_network(&settings) {
+            Ok(network) => network,
+            Err(err) => {
+                error!("Failed to configure the server network: {err:?}");
+                return;
+            },
         };
 
         App::new()
-            .add_plugins(MinimalPlugins)
-            .add_plugin(PhysicsPlugin::new(TICKRATE))
-            .add_plugin(NetworkPlugin::new_server(port, MAX_CLIENTS))
-            .add_plugin(WorldDataPlugin::default())
-            .add_plugin(server)
+            .add_reported_plugins("MinimalPlugins", MinimalPlugins)
+            .insert_resource(PlayerDataDirectory(config.world_directory.join("players")))
+            .insert_resource(BanListFile(config.world_directory.join("bans.ron")))
+            .insert_resource(WorldDataDirectory(config.world_directory.join("worlds")))
+            .insert_resource(ChunkUnloadDelay {
+                seconds: settings.chunk_unload_seconds,
+            })
+            .insert_resource(EntityCaps {
+                mobs_per_chunk:  settings.mobs_per_chunk,
+                items_per_chunk: settings.items_per_chunk,
+                entity_budget:   settings.entity_budget,
+            })
+            .insert_resource(SendBudget {
+                bytes_per_tick: settings.send_budget,
+            })
+            .insert_resource(CompressionSettings {
+                enabled:   settings.compression,
+                threshold: settings.compression_threshold,
+            })
+            .add_reported_plugin(PhysicsPlugin::new(config.tickrate))
+            .add_reported_plugin(network)
+            .add_reported_plugin(WorldDataPlugin::default())
+            .add_reported_plugin(SafeSpawnPlugin::<BlockShape>::default())
+            .add_reported_plugin(ExplosionPlugin::<BlockShape>::default())
+            .add_reported_plugin(EntityCapsPlugin)
+            .add_reported_plugin(InteractionPlugin::<BlockShape>::default())
+            .add_reported_plugin(ContainerPlugin::<BlockShape>::default())
+            .add_reported_plugin(MapExportPlugin::<BlockShape>::default())
+            .add_reported_plugin(BlockEditPlugin::<BlockShape>::default())
+            .add_reported_plugin(WorldCollisionPlugin::<BlockShape>::default())
+            .add_reported_plugin(PathfindingPlugin::default())
+            .add_reported_plugin(server)
             .run();
     });
 
     if let Err(err) = result {
-        print_error!("An internal error has occurred in the Awgen server.", err);
+        error!(
+            error = panic_message(err.as_ref()),
+            "An internal error has occurred in the Awgen server."
+        );
     }
 }
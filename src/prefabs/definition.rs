@@ -0,0 +1,367 @@
+//! Loads prefab definitions from RON asset files, allowing for entity
+//! archetypes and scene objects to be described as data rather than code.
+//!
+//! Prefabs are stored within the `assets/prefabs` folder, using the
+//! `.prefab.ron` file extension, and are spawned by name. Components are
+//! inserted in the order they are listed, so a component replaces any matching
+//! component from a bundle listed before it, such as the transform of a camera.
+//!
+//! ```ron
+//! (
+//!     components: [
+//!         Name("Player"),
+//!         RigidBody,
+//!         WasdController,
+//!         MouseController(sensitivity: 0.6),
+//!         CameraController(camera: 0),
+//!     ],
+//!     children: [
+//!         (
+//!             components: [Name("Camera"), Camera3d, Transform((0.0, 1.85, 0.0))],
+//!         ),
+//!     ],
+//! )
+//! ```
+
+
+use awgen_client::prelude::{CameraController, MouseController, WasdController};
+use awgen_physics::prelude::Position;
+use awgen_physics::InterpolatedRigidBodyBundle;
+use awgen_world::prelude::ChunkAnchor;
+use bevy::asset::{AssetLoader, LoadContext, LoadState, LoadedAsset};
+use bevy::ecs::system::{Command, EntityCommands};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+
+/// The asset folder that prefab definitions are loaded from.
+pub const PREFAB_FOLDER: &str = "prefabs";
+
+
+/// The file extension of prefab definition files.
+pub const PREFAB_EXTENSION: &str = "prefab.ron";
+
+
+/// A plugin that registers the prefab asset type and spawns pending prefab
+/// instances once their definitions have been loaded.
+#[derive(Debug, Clone, Default)]
+pub struct PrefabPlugin;
+
+impl Plugin for PrefabPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PrefabDefinition>()
+            .init_asset_loader::<PrefabLoader>()
+            .add_system(apply_pending_prefabs);
+    }
+}
+
+
+/// A data-driven description of an entity, made up of a list of components and
+/// an optional list of child entities.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "8b3f62e4-5d0a-4c8e-9a51-3f7c2d1e6b90"]
+pub struct PrefabDefinition {
+    /// The components to insert into the prefab entity, in order.
+    #[serde(default)]
+    pub components: Vec<PrefabComponent>,
+
+    /// The child entities of the prefab entity.
+    #[serde(default)]
+    pub children: Vec<PrefabDefinition>,
+}
+
+
+/// A single component, or group of components, that may be described within a
+/// prefab definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PrefabComponent {
+    /// The name of the entity.
+    Name(String),
+
+    /// A render transform at the given translation, for entities that are not
+    /// moved by the physics engine.
+    Transform(Vec3),
+
+    /// A physics position at the given translation. This should be combined
+    /// with [`PrefabComponent::RigidBody`].
+    Position(Vec3),
+
+    /// A movable rigid body that is interpolated between physics frames.
+    RigidBody,
+
+    /// Keeps a radius of chunks loaded around the entity, within the world
+    /// that the entity is in.
+    ChunkAnchor {
+        /// The radius, in chunks, to keep loaded.
+        radius: u16,
+
+        /// The radius, in chunks, before loaded chunks are considered out of
+        /// range.
+        max_radius: u16,
+
+        /// The loading priority of the anchor.
+        #[serde(default)]
+        priority: u32,
+    },
+
+    /// Moves the entity based off of WASD input controls.
+    WasdController,
+
+    /// Rotates the entity based off of mouse movement inputs.
+    MouseController {
+        /// The mouse sensitivity of the controller.
+        sensitivity: f32,
+    },
+
+    /// Applies the mouse controller rotation to a camera within the children
+    /// of this entity.
+    CameraController {
+        /// The index of the child entity that contains the camera.
+        camera: usize,
+    },
+
+    /// A 3D camera.
+    Camera3d,
+
+    /// A point light source.
+    PointLight {
+        /// The luminous intensity of the light.
+        intensity: f32,
+
+        /// Whether or not the light casts shadows.
+        shadows: bool,
+    },
+
+    /// A rendered mesh, loaded from the given asset path, with a solid color
+    /// material.
+    Mesh {
+        /// The asset path of the mesh, such as
+        /// `models/tree.gltf#Mesh0/Primitive0`.
+        mesh: String,
+
+        /// The base color of the material, as RGB values between 0 and 1.
+        color: [f32; 3],
+    },
+}
+
+
+/// The asset loader for prefab definition files.
+#[derive(Debug, Clone, Default)]
+pub struct PrefabLoader;
+
+impl AssetLoader for PrefabLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let prefab: PrefabDefinition = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(prefab));
+            Ok(())
+        })
+    }
+
+
+    fn extensions(&self) -> &[&str] {
+        &[PREFAB_EXTENSION]
+    }
+}
+
+
+/// A marker for an entity that is waiting on its prefab definition to finish
+/// loading before its components are inserted.
+#[derive(Debug, Clone, Component)]
+pub struct PendingPrefab {
+    /// The name of the prefab being spawned.
+    name: String,
+
+    /// The handle of the prefab definition.
+    handle: Handle<PrefabDefinition>,
+}
+
+
+/// A command that loads the prefab with the given name and assigns it to the
+/// given entity.
+#[derive(Debug, Clone)]
+pub struct SpawnPrefab {
+    /// The entity to insert the prefab components into.
+    pub entity: Entity,
+
+    /// The name of the prefab to spawn.
+    pub name: String,
+}
+
+impl Command for SpawnPrefab {
+    fn write(self, world: &mut World) {
+        let path = format!("{PREFAB_FOLDER}/{}.{PREFAB_EXTENSION}", self.name);
+        let handle = world.resource::<AssetServer>().load(path);
+
+        let Some(mut entity) = world.get_entity_mut(self.entity) else {
+            warn!(
+                "Cannot spawn prefab '{}' on unknown entity {:?}",
+                self.name, self.entity
+            );
+            return;
+        };
+
+        entity.insert(PendingPrefab {
+            name: self.name,
+            handle,
+        });
+    }
+}
+
+
+/// An extension trait for commands that allows for entities to be spawned from
+/// prefab definitions.
+pub trait SpawnPrefabExt<'w, 's> {
+    /// Spawns a new entity from the prefab with the given name.
+    ///
+    /// The prefab components are inserted once the prefab definition has
+    /// finished loading, which may take several frames.
+    fn spawn_prefab<'a>(&'a mut self, name: &str) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> SpawnPrefabExt<'w, 's> for Commands<'w, 's> {
+    fn spawn_prefab<'a>(&'a mut self, name: &str) -> EntityCommands<'w, 's, 'a> {
+        let entity = self.spawn_empty().id();
+        self.add(SpawnPrefab {
+            entity,
+            name: name.to_string(),
+        });
+        self.entity(entity)
+    }
+}
+
+
+/// Inserts the components of each pending prefab entity whose prefab
+/// definition has finished loading.
+pub fn apply_pending_prefabs(
+    pending: Query<(Entity, &PendingPrefab)>,
+    prefabs: Res<Assets<PrefabDefinition>>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for (entity, prefab) in pending.iter() {
+        if let Some(definition) = prefabs.get(&prefab.handle) {
+            commands.entity(entity).remove::<PendingPrefab>();
+            apply_prefab(
+                entity,
+                definition,
+                &asset_server,
+                &mut materials,
+                &mut commands,
+            );
+        } else if asset_server.get_load_state(&prefab.handle) == LoadState::Failed {
+            error!("Failed to load prefab '{}'", prefab.name);
+            commands.entity(entity).remove::<PendingPrefab>();
+        }
+    }
+}
+
+
+/// Inserts the components of the given prefab definition into the given
+/// entity, spawning all of its children.
+fn apply_prefab(
+    entity: Entity,
+    definition: &PrefabDefinition,
+    asset_server: &AssetServer,
+    materials: &mut Assets<StandardMaterial>,
+    commands: &mut Commands,
+) {
+    let children: Vec<Entity> = definition
+        .children
+        .iter()
+        .map(|child| {
+            let child_entity = commands.spawn_empty().id();
+            apply_prefab(child_entity, child, asset_server, materials, commands);
+            child_entity
+        })
+        .collect();
+
+    let mut entity_commands = commands.entity(entity);
+    entity_commands.push_children(&children);
+
+    for component in &definition.components {
+        match component {
+            PrefabComponent::Name(name) => {
+                entity_commands.insert(Name::new(name.clone()));
+            },
+            PrefabComponent::Transform(translation) => {
+                entity_commands.insert(SpatialBundle::from_transform(Transform::from_translation(
+                    *translation,
+                )));
+            },
+            PrefabComponent::Position(translation) => {
+                entity_commands.insert(Position {
+                    translation: *translation,
+                    ..default()
+                });
+            },
+            PrefabComponent::RigidBody => {
+                entity_commands.insert(InterpolatedRigidBodyBundle::default());
+            },
+            PrefabComponent::ChunkAnchor {
+                radius,
+                max_radius,
+                priority,
+            } => {
+                entity_commands.insert(ChunkAnchor {
+                    radius: *radius,
+                    max_radius: *max_radius,
+                    priority: *priority,
+                    ..default()
+                });
+            },
+            PrefabComponent::WasdController => {
+                entity_commands.insert(WasdController);
+            },
+            PrefabComponent::MouseController {
+                sensitivity,
+            } => {
+                entity_commands.insert(MouseController {
+                    sensitivity: *sensitivity,
+                    ..default()
+                });
+            },
+            PrefabComponent::CameraController {
+                camera,
+            } => {
+                entity_commands.insert(CameraController {
+                    camera: children.get(*camera).copied(),
+                });
+            },
+            PrefabComponent::Camera3d => {
+                entity_commands.insert(Camera3dBundle::default());
+            },
+            PrefabComponent::PointLight {
+                intensity,
+                shadows,
+            } => {
+                entity_commands.insert(PointLightBundle {
+                    point_light: PointLight {
+                        intensity: *intensity,
+                        shadows_enabled: *shadows,
+                        ..default()
+                    },
+                    ..default()
+                });
+            },
+            PrefabComponent::Mesh {
+                mesh,
+                color,
+            } => {
+                let [r, g, b] = *color;
+                entity_commands.insert(PbrBundle {
+                    mesh: asset_server.load(mesh.as_str()),
+                    material: materials.add(Color::rgb(r, g, b).into()),
+                    ..default()
+                });
+            },
+        }
+    }
+}
@@ -3,7 +3,9 @@
 
 
 mod basic_scene;
+mod definition;
 mod player;
 
 pub use basic_scene::*;
+pub use definition::*;
 pub use player::*;